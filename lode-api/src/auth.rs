@@ -0,0 +1,258 @@
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Uri};
+use actix_web::middleware::Next;
+use actix_web::{web, HttpMessage, HttpResponse};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::handlers::AppState;
+
+/// Header carrying an API key as an alternative to `Authorization: Bearer`
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+/// Query parameter carrying an API key, for clients that cannot set headers such as `EventSource`
+pub const ACCESS_TOKEN_PARAM: &str = "access_token";
+
+/// Paths served without an API key
+const PUBLIC_PATHS: [&str; 2] = ["/health", "/ui"];
+
+/// A key allowed to call the API, with the limits of the load tests it starts
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct ApiKey {
+    /// Holder of the key, in the logs and as the owner of its runs
+    pub name: String,
+    /// The secret sent by the holder
+    pub key: String,
+    /// Load tests of the key queued or running at once
+    #[serde(default)]
+    pub max_running_tests: Option<usize>,
+    /// Requests a load test of the key may send, every attempt of a retried request included
+    #[serde(default)]
+    pub max_requests_per_test: Option<u64>,
+    /// Highest concurrency a load test of the key may reach, or be overridden to
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+    /// Highest rate a load test of the key may send at, or be overridden to; with it set, load
+    /// tests of the key must set a rate
+    #[serde(default)]
+    pub max_rate: Option<f64>,
+    /// May submit the shares of distributed runs, whose engine configuration is taken as is,
+    /// e.g. the key of `lode-cli --agents`
+    #[serde(default)]
+    pub controller: bool,
+}
+
+// The secret stays out of the logs
+impl std::fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiKey")
+            .field("name", &self.name)
+            .field("max_running_tests", &self.max_running_tests)
+            .field("max_requests_per_test", &self.max_requests_per_test)
+            .field("max_concurrency", &self.max_concurrency)
+            .field("max_rate", &self.max_rate)
+            .field("controller", &self.controller)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The keys allowed to call the API; without any the API is open
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeys {
+    keys: HashMap<String, Arc<ApiKey>>,
+}
+
+impl ApiKeys {
+    pub fn new(keys: impl IntoIterator<Item = ApiKey>) -> Self {
+        Self {
+            keys: keys
+                .into_iter()
+                .map(|key| (key.key.clone(), Arc::new(key)))
+                .collect(),
+        }
+    }
+
+    /// Whether requests need a key
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// The key sent with a request, from `Authorization: Bearer`, `X-API-Key` or the
+    /// `access_token` query parameter
+    pub fn find(&self, req: &ServiceRequest) -> Option<Arc<ApiKey>> {
+        let bearer = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let header = req
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok());
+        let extensions = req.extensions();
+        let param = extensions
+            .get::<AccessToken>()
+            .map(|token| token.0.as_str());
+        let secret = bearer.or(header).or(param)?;
+        self.keys.get(secret.trim()).cloned()
+    }
+}
+
+/// The `access_token` query parameter of a request, taken out of its URI
+#[derive(Clone)]
+struct AccessToken(String);
+
+/// The URI without its `access_token` query parameter, and the token, if it has one
+fn without_access_token(uri: &Uri) -> Option<(Uri, String)> {
+    let query = uri.query()?;
+    let mut token = None;
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|pair| {
+            let Ok(mut param) = web::Query::<HashMap<String, String>>::from_query(pair) else {
+                return true;
+            };
+            match param.0.remove(ACCESS_TOKEN_PARAM) {
+                Some(value) => {
+                    token = Some(value);
+                    false
+                }
+                None => true,
+            }
+        })
+        .collect();
+    let token = token?;
+    let path_and_query = match kept.is_empty() {
+        true => uri.path().to_string(),
+        false => format!("{}?{}", uri.path(), kept.join("&")),
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse().ok()?);
+    Some((Uri::from_parts(parts).ok()?, token))
+}
+
+/// Take the `access_token` query parameter out of the request URI, so the key stays out of the
+/// request logs
+///
+/// Wraps the request logger; [`authenticate`] still finds the key.
+pub async fn hide_access_token(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    if let Some((uri, token)) = without_access_token(req.uri()) {
+        req.match_info_mut().get_mut().update(&uri);
+        req.head_mut().uri = uri;
+        req.extensions_mut().insert(AccessToken(token));
+    }
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+/// Reject requests without a known API key when keys are configured
+///
+/// The key of an accepted request is kept in its extensions, so the handlers can apply its
+/// limits. The health check and the UI page are public; the UI passes the key on to the API.
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let keys = req
+        .app_data::<web::Data<AppState>>()
+        .map(|state| state.api_keys())
+        .filter(|keys| keys.is_enabled());
+    let Some(keys) = keys else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+    if PUBLIC_PATHS.contains(&req.path()) {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    }
+    match keys.find(&req) {
+        Some(key) => {
+            debug!("Request authenticated as {}", key.name);
+            req.extensions_mut().insert(key);
+            Ok(next.call(req).await?.map_into_boxed_body())
+        }
+        None => {
+            let response = HttpResponse::Unauthorized()
+                .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+                .json(serde_json::json!({
+                    "error": "Unauthorized",
+                    "details": "A valid API key is required, as `Authorization: Bearer <key>` or `X-API-Key`"
+                }));
+            Ok(req.into_response(response))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn keys() -> ApiKeys {
+        ApiKeys::new([ApiKey {
+            name: "ci".to_string(),
+            key: "secret".to_string(),
+            max_running_tests: Some(1),
+            max_requests_per_test: None,
+            max_concurrency: None,
+            max_rate: None,
+            controller: false,
+        }])
+    }
+
+    #[test]
+    fn test_find_key_in_request() {
+        let keys = keys();
+        let found = |req: TestRequest| keys.find(&req.to_srv_request()).map(|key| key.name.clone());
+        assert_eq!(
+            found(TestRequest::default().insert_header(("Authorization", "Bearer secret"))),
+            Some("ci".to_string())
+        );
+        assert_eq!(
+            found(TestRequest::default().insert_header(("X-API-Key", "secret"))),
+            Some("ci".to_string())
+        );
+        let req = TestRequest::default().to_srv_request();
+        req.extensions_mut()
+            .insert(AccessToken("secret".to_string()));
+        assert_eq!(
+            keys.find(&req).map(|key| key.name.clone()),
+            Some("ci".to_string())
+        );
+        assert_eq!(
+            found(TestRequest::default().insert_header(("Authorization", "Basic secret"))),
+            None
+        );
+        assert_eq!(
+            found(TestRequest::default().insert_header(("X-API-Key", "other"))),
+            None
+        );
+        assert_eq!(found(TestRequest::default()), None);
+    }
+
+    #[test]
+    fn test_access_token_out_of_uri() {
+        let hidden = |uri: &str| {
+            without_access_token(&uri.parse().unwrap()).map(|(uri, token)| (uri.to_string(), token))
+        };
+        assert_eq!(
+            hidden("/load-tests/1/stream?access_token=secret"),
+            Some(("/load-tests/1/stream".to_string(), "secret".to_string()))
+        );
+        assert_eq!(
+            hidden("/ui?tab=runs&access_token=a%2Bb&status=running"),
+            Some(("/ui?tab=runs&status=running".to_string(), "a+b".to_string()))
+        );
+        assert_eq!(hidden("/load-tests?status=running"), None);
+        assert_eq!(hidden("/load-tests"), None);
+    }
+
+    #[test]
+    fn test_keys_enabled() {
+        assert!(!ApiKeys::default().is_enabled());
+        assert!(keys().is_enabled());
+    }
+}
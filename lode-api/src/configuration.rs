@@ -1,8 +1,40 @@
+use crate::auth::ApiKey;
+use crate::targets::TargetPolicy;
+use crate::workers::DEFAULT_WORKERS;
+use lode_core::telemetry::OtlpSettings;
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
 pub struct Settings {
     pub server: ServerSettings,
+    /// Collector receiving traces and metrics, e.g. from `APP_OTLP__ENDPOINT`
+    #[serde(default)]
+    pub otlp: Option<OtlpSettings>,
+    /// Region of this agent, tagged on every run, e.g. from `APP_REGION`
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Ed25519 private key (PKCS#8 PEM) signing the reports, e.g. from `APP_SIGNING_KEY`
+    #[serde(default)]
+    pub signing_key: Option<std::path::PathBuf>,
+    /// Database keeping the run history, e.g. `sqlite://runs.db?mode=rwc` or a `postgres://` URL
+    /// from `APP_DATABASE_URL`; runs are kept in memory without one
+    #[serde(default)]
+    pub database_url: Option<String>,
+    /// Keys allowed to call the API with the limits of their load tests; the API is open
+    /// without any
+    #[serde(default)]
+    pub api_keys: Vec<ApiKey>,
+    /// Hosts the load tests may target, e.g. `APP_TARGETS__ALLOW_PRIVATE=true` to load services
+    /// of the agent's own network; private addresses are refused without it
+    #[serde(default)]
+    pub targets: TargetPolicy,
+    /// Load tests run at once, e.g. from `APP_MAX_CONCURRENT_RUNS`; the others wait in the queue
+    #[serde(default = "default_max_concurrent_runs")]
+    pub max_concurrent_runs: usize,
+}
+
+fn default_max_concurrent_runs() -> usize {
+    DEFAULT_WORKERS
 }
 
 #[derive(Debug, Deserialize)]
@@ -1,24 +1,258 @@
-use actix_web::{web, HttpResponse, Responder};
-use lode_core::{engine::LoadTestEngine, http::DefaultHttpClient, report::Report};
+use actix_web::{
+    http::{header, StatusCode},
+    web, HttpResponse, Responder,
+};
+use chrono::{DateTime, Utc};
+use futures::stream;
+use lode_core::{
+    clock::ClockReading,
+    config::{ConfigError, LoadTestConfig},
+    control::{LoadControl, OverrideLimits, Overrides},
+    engine::{LoadTestEngine, RunOptions},
+    error::Error,
+    http::{DefaultHttpClient, HttpClientOptions},
+    live::{LiveMetrics, DEFAULT_SNAPSHOT_INTERVAL},
+    metrics::{
+        exporters::{otlp, prometheus},
+        Labels,
+    },
+    progress::Progress,
+    report::{signing::ReportSigner, Report},
+};
+use serde::Serialize;
 use std::sync::Arc;
-use tokio::sync::Mutex;
 use tracing::{debug, error, instrument, warn};
-use url;
+use uuid::Uuid;
 
-use crate::models::{LoadTestRequest, LoadTestResponse};
+use crate::auth::{ApiKey, ApiKeys};
+use crate::models::{
+    LoadTestListQuery, LoadTestListResponse, LoadTestReportQuery, LoadTestRequest,
+    LoadTestResponse, ScheduleListResponse, ScheduleRequest, TargetTrendQuery, TargetTrendResponse,
+};
+use crate::runs::{ListOptions, RunPage, RunRecord, RunRegistry, RunState};
+use crate::schedules::{Schedule, ScheduleEvent, ScheduleRegistry, SCHEDULER_TICK};
+use crate::storage::{redact_secrets, MemoryStore, RunQuery, RunStore};
+use crate::targets::TargetPolicy;
+use crate::trends::{TrendOptions, TrendPoint};
+use crate::workers::WorkerPool;
 
 pub struct AppState {
-    engine: Arc<Mutex<LoadTestEngine<DefaultHttpClient>>>,
+    /// Engine of the runs with the default client settings, shared by the workers
+    engine: Arc<LoadTestEngine<DefaultHttpClient>>,
+    /// Runs the queued load tests, a bounded number at a time
+    workers: WorkerPool,
+    runs: RunRegistry,
+    /// Load tests run at an interval, started by [`spawn_scheduler`]
+    schedules: ScheduleRegistry,
+    /// Region of this agent, attached to runs that do not set one
+    region: Option<String>,
+    /// Key signing the reports of the runs, when one is configured
+    signer: Option<ReportSigner>,
+    /// Keeps the finished runs beyond the lifetime of the process
+    store: Arc<dyn RunStore>,
+    /// Keys allowed to call the API with their limits; the API is open without any
+    api_keys: ApiKeys,
+    /// Hosts the runs may target, private ones are refused by default
+    targets: Arc<TargetPolicy>,
 }
 
 impl AppState {
     pub fn new() -> anyhow::Result<Self> {
-        let client = DefaultHttpClient::new()?;
-        let engine = LoadTestEngine::new(client)?;
+        let targets = Arc::new(TargetPolicy::default());
         Ok(Self {
-            engine: Arc::new(Mutex::new(engine)),
+            engine: Arc::new(guarded_engine(&targets, HttpClientOptions::default())?),
+            workers: WorkerPool::default(),
+            runs: RunRegistry::new(),
+            schedules: ScheduleRegistry::new(),
+            region: None,
+            signer: None,
+            store: Arc::new(MemoryStore::default()),
+            api_keys: ApiKeys::default(),
+            targets,
         })
     }
+
+    /// Only run load tests against the hosts `targets` accepts, following redirects to them only
+    ///
+    /// Fails when the client following the policy cannot be built.
+    pub fn with_targets(mut self, targets: TargetPolicy) -> anyhow::Result<Self> {
+        self.targets = Arc::new(targets);
+        self.engine = Arc::new(guarded_engine(&self.targets, HttpClientOptions::default())?);
+        Ok(self)
+    }
+
+    /// Run up to `workers` load tests at once, queueing the others
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = WorkerPool::new(workers);
+        self
+    }
+
+    /// Workers running the load tests
+    pub fn workers(&self) -> &WorkerPool {
+        &self.workers
+    }
+
+    /// Tag the runs of this agent with its region
+    pub fn with_region(mut self, region: Option<String>) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// Sign the reports of the runs with `signer`
+    pub fn with_signer(mut self, signer: Option<ReportSigner>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    /// Save the finished runs to `store`
+    pub fn with_store(mut self, store: Arc<dyn RunStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Require one of `api_keys` on every request and apply its limits to the runs it starts
+    pub fn with_api_keys(mut self, api_keys: ApiKeys) -> Self {
+        self.api_keys = api_keys;
+        self
+    }
+
+    /// Keys allowed to call the API
+    pub fn api_keys(&self) -> &ApiKeys {
+        &self.api_keys
+    }
+
+    /// A run in flight, or a finished one read back from the store
+    pub async fn run(&self, id: &str) -> Option<RunRecord> {
+        if let Some(record) = self.runs.get(id).await {
+            return Some(record);
+        }
+        match self.store.get(id).await {
+            Ok(run) => run.map(RunRecord::from_stored),
+            Err(e) => {
+                error!("Failed to read run {}: {}", id, e);
+                None
+            }
+        }
+    }
+
+    /// Save a finished run and forget it, it is read back from the store from then on; a run
+    /// that cannot be saved is still served from memory
+    async fn save_run(&self, id: &str) {
+        let Some(run) = self
+            .runs
+            .get(id)
+            .await
+            .and_then(|record| record.to_stored())
+        else {
+            return;
+        };
+        match self.store.save(&run).await {
+            Ok(()) => self.runs.remove(id).await,
+            Err(e) => error!("Failed to save run {}: {}", id, e),
+        }
+    }
+
+    /// A page of the runs in flight and the saved ones
+    async fn list_runs(&self, options: &ListOptions) -> anyhow::Result<RunPage> {
+        let (saved, total) = self.store.query(&options.to_query()).await?;
+        let saved = saved.into_iter().map(RunRecord::from_stored).collect();
+        Ok(self.runs.list(options, saved, total).await)
+    }
+
+    /// Trend of a target over the runs in memory and the saved ones, with the target's URL
+    async fn trend(
+        &self,
+        target: &str,
+        options: &TrendOptions,
+    ) -> anyhow::Result<Option<(String, Vec<TrendPoint>)>> {
+        let (mut saved, _) = self.store.query(&options.to_query(target)).await?;
+        if saved.is_empty() {
+            // The URL of a target whose runs all failed or fall outside the options
+            let any = RunQuery {
+                target: Some(target.to_string()),
+                owner: options.owner.clone(),
+                limit: 1,
+                ..Default::default()
+            };
+            saved = self.store.query(&any).await?.0;
+        }
+        let saved = saved.into_iter().map(RunRecord::from_stored).collect();
+        Ok(self.runs.trend(target, options, saved).await)
+    }
+
+    /// Export the live metrics of the running runs through the global OTLP meter
+    pub fn observe_otlp(self: &Arc<Self>) {
+        let state = Arc::clone(self);
+        otlp::observe(
+            &otlp::global_meter(),
+            Arc::new(move || {
+                state
+                    .runs
+                    .try_live_snapshots()
+                    .into_iter()
+                    .map(|(id, snapshot)| (run_labels(&id), snapshot))
+                    .collect()
+            }),
+        );
+    }
+}
+
+/// Labels identifying the metrics of a run
+fn run_labels(id: &str) -> Labels {
+    Labels::from([("run_id".to_string(), id.to_string())])
+}
+
+/// Why a submission was not registered, answered with `status`
+struct Refusal {
+    status: StatusCode,
+    error: &'static str,
+    details: String,
+}
+
+impl Refusal {
+    fn response(&self) -> HttpResponse {
+        HttpResponse::build(self.status).json(serde_json::json!({
+            "error": self.error,
+            "details": self.details
+        }))
+    }
+}
+
+/// Refuse an invalid configuration with `400 Bad Request`
+fn config_refusal(e: &ConfigError) -> Refusal {
+    let error = match e {
+        ConfigError::InvalidUrl(_) => "Invalid URL",
+        ConfigError::InvalidMethod(_) => "Invalid HTTP method",
+        ConfigError::InvalidHeader(_) => "Invalid header",
+        ConfigError::InvalidFault(_) => "Invalid fault",
+        ConfigError::InvalidStage(_) => "Invalid stage",
+        ConfigError::InvalidRate(_) => "Invalid rate",
+        ConfigError::InvalidLabel(_) => "Invalid label",
+        ConfigError::InvalidScenario(_) => "Invalid scenario",
+        ConfigError::InvalidPercentile(_) => "Invalid percentile",
+        ConfigError::InvalidSlo(_) => "Invalid SLO",
+        ConfigError::InvalidThreshold(_) => "Invalid threshold",
+        ConfigError::InvalidAbortRule(_) => "Invalid abort rule",
+        ConfigError::InvalidSuccessCodes(_) => "Invalid success codes",
+        ConfigError::InvalidPacing(_) => "Invalid header pacing",
+        ConfigError::InvalidDns(_) => "Invalid DNS settings",
+        ConfigError::InvalidTrace(_) => "Invalid trace sampling",
+        ConfigError::InvalidMissingValue(_) => "Invalid missing value policy",
+        ConfigError::InvalidTuning(_) => "Invalid tuning",
+        _ => "Invalid configuration",
+    };
+    Refusal {
+        status: StatusCode::BAD_REQUEST,
+        error,
+        details: e.to_string(),
+    }
+}
+
+fn not_found(id: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({
+        "error": "Load test not found",
+        "details": format!("No load test with id '{}'", id)
+    }))
 }
 
 #[instrument(skip_all)]
@@ -30,6 +264,336 @@ pub async fn health_check() -> impl Responder {
     }))
 }
 
+/// Serve the current time for the clock handshake of distributed generators
+#[instrument(skip_all)]
+pub async fn clock_time() -> impl Responder {
+    HttpResponse::Ok().json(ClockReading::now())
+}
+
+/// Expose the live metrics of running load tests in the Prometheus format, labelled by `run_id`
+#[instrument(skip_all)]
+pub async fn prometheus_metrics(state: web::Data<AppState>) -> impl Responder {
+    let snapshots = state.runs.live_snapshots().await;
+    let labels: Vec<Labels> = snapshots.iter().map(|(id, _)| run_labels(id)).collect();
+    HttpResponse::Ok()
+        .content_type(prometheus::CONTENT_TYPE)
+        .body(prometheus::render(
+            labels
+                .iter()
+                .zip(snapshots.iter().map(|(_, snapshot)| snapshot)),
+        ))
+}
+
+/// Why a run did not produce a report
+enum RunFailure {
+    Run(String),
+    Report(String),
+    /// Cancelled before it started
+    Cancelled(String),
+}
+
+impl RunFailure {
+    fn response(&self) -> HttpResponse {
+        match self {
+            RunFailure::Run(details) => HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Failed to run load test",
+                "details": details
+            })),
+            RunFailure::Report(details) => {
+                HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "Failed to generate report",
+                    "details": details
+                }))
+            }
+            RunFailure::Cancelled(id) => HttpResponse::Conflict().json(serde_json::json!({
+                "error": "Load test cancelled",
+                "details": format!("Load test '{}' was cancelled before it started", id)
+            })),
+        }
+    }
+}
+
+/// Validate a submission and register it as a queued run
+fn prepare_run(
+    mut request: LoadTestRequest,
+    region: Option<&str>,
+) -> Result<(LoadTestConfig, RunRecord, LiveMetrics), ConfigError> {
+    if request.region.is_none() {
+        request.region = region.map(str::to_string);
+    }
+    let mut submitted = serde_json::to_value(&request).unwrap_or_default();
+    redact_secrets(&mut submitted);
+    let config = LoadTestConfig::try_from(request).inspect_err(|e| {
+        warn!("Invalid load test configuration: {}", e);
+    })?;
+    Ok(queue_run(config, submitted))
+}
+
+/// Validate the share of a distributed run sent by a controller and register it as a queued run
+///
+/// The controller sends the configuration of the engine as is. The tuning of this agent is kept
+/// and requests are not recorded, as only the report goes back to the controller.
+fn prepare_agent_run(
+    mut config: LoadTestConfig,
+    region: Option<&str>,
+) -> Result<(LoadTestConfig, RunRecord, LiveMetrics), ConfigError> {
+    if config.region.is_none() {
+        config.region = region.map(str::to_string);
+    }
+    config.advanced = Default::default();
+    config.record_requests = false;
+    let tls = &config.tls;
+    if tls.ca_cert.is_some() || tls.client_cert.is_some() || tls.client_key.is_some() {
+        warn!("Refused TLS files in the share of a distributed run");
+        return Err(ConfigError::InvalidTls(
+            "Certificates and keys must be given inline, not as files on the agent".to_string(),
+        ));
+    }
+    config.validate().inspect_err(|e| {
+        warn!("Invalid load test configuration from a controller: {}", e);
+    })?;
+    let mut submitted = serde_json::to_value(&config).unwrap_or_default();
+    redact_secrets(&mut submitted);
+    Ok(queue_run(config, submitted))
+}
+
+/// Register a validated configuration as a queued run
+fn queue_run(
+    config: LoadTestConfig,
+    submitted: serde_json::Value,
+) -> (LoadTestConfig, RunRecord, LiveMetrics) {
+    let (live, snapshots) = LiveMetrics::channel(DEFAULT_SNAPSHOT_INTERVAL);
+    let record = RunRecord::queued(Uuid::new_v4().to_string(), config.clone(), Progress::new())
+        .with_request(submitted)
+        .with_live(snapshots)
+        .with_control(LoadControl::new(OverrideLimits::for_config(&config)));
+    (config, record, live)
+}
+
+/// Engine whose clients only follow redirects to and connect to the hosts `targets` accepts,
+/// sending the requests of runs with `options`
+fn guarded_engine(
+    targets: &Arc<TargetPolicy>,
+    options: HttpClientOptions,
+) -> anyhow::Result<LoadTestEngine<DefaultHttpClient>> {
+    let client = targets.client_builder(options)?.build()?;
+    Ok(LoadTestEngine::new(client)?.with_probe_client(targets.probe_client()?))
+}
+
+/// Check that a configuration targets a host the API may load and stays within the requests,
+/// concurrency and rate the API key that sent it allows, refusing it with `403 Forbidden`
+/// otherwise
+async fn admit(
+    state: &AppState,
+    config: &LoadTestConfig,
+    key: Option<&ApiKey>,
+) -> Result<(), Refusal> {
+    if let Err(reason) = state.targets.check(config).await {
+        warn!("Refused a load test: {}", reason);
+        return Err(Refusal {
+            status: StatusCode::FORBIDDEN,
+            error: "Target not allowed",
+            details: reason,
+        });
+    }
+    let Some(key) = key else {
+        return Ok(());
+    };
+    let quota_exceeded = |details: String| {
+        warn!("Refused a load test of {}: {}", key.name, details);
+        Err(Refusal {
+            status: StatusCode::FORBIDDEN,
+            error: "Quota exceeded",
+            details,
+        })
+    };
+
+    // Every attempt of a retried request is sent to the target
+    let attempts = config
+        .retry
+        .as_ref()
+        .map_or(1, |retry| u64::from(retry.max_attempts));
+    let requests = config.total_requests().saturating_mul(attempts);
+    if let Some(max) = key.max_requests_per_test.filter(|max| requests > *max) {
+        return quota_exceeded(format!(
+            "API key '{}' may send at most {} requests per load test, retries included, this one \
+             may send {}",
+            key.name, max, requests
+        ));
+    }
+
+    // The limits bound the overrides of the run as well as its configured load
+    let limits = OverrideLimits::for_config(config);
+    let concurrency = config
+        .stages
+        .iter()
+        .map(|stage| stage.max_concurrency())
+        .fold(config.concurrency.max(limits.max_concurrency), usize::max);
+    if let Some(max) = key.max_concurrency.filter(|max| concurrency > *max) {
+        return quota_exceeded(format!(
+            "API key '{}' may run load tests at a concurrency of at most {}, this one reaches {}",
+            key.name, max, concurrency
+        ));
+    }
+    let Some(max) = key.max_rate else {
+        return Ok(());
+    };
+    let unpaced = config.rate.is_none()
+        && (config.stages.is_empty()
+            || config
+                .stages
+                .iter()
+                .any(|stage| stage.max_concurrency() > 0 && stage.rate().is_none()));
+    if unpaced {
+        return quota_exceeded(format!(
+            "API key '{}' may send at most {} requests per second, set a rate",
+            key.name, max
+        ));
+    }
+    let rate = config
+        .stages
+        .iter()
+        .filter_map(|stage| stage.rate())
+        .chain(config.rate)
+        .chain(limits.max_rate)
+        .fold(0.0, f64::max);
+    if rate > max {
+        return quota_exceeded(format!(
+            "API key '{}' may send at most {} requests per second, this one may send {}",
+            key.name, max, rate
+        ));
+    }
+    Ok(())
+}
+
+/// Check the targets of a prepared submission and the limits of the API key that sent it and
+/// register it as a queued run
+///
+/// Refuses with `400 Bad Request` an invalid configuration, with `403 Forbidden` a run that
+/// targets a host the API may not load or sends more requests than the key allows and with
+/// `429 Too Many Requests` a run of a key that already has as many runs in flight as it may.
+async fn register_run(
+    state: &AppState,
+    prepared: Result<(LoadTestConfig, RunRecord, LiveMetrics), ConfigError>,
+    key: Option<&ApiKey>,
+) -> Result<(LoadTestConfig, RunRecord, RunOptions), Refusal> {
+    let (config, record, live) = prepared.map_err(|e| config_refusal(&e))?;
+    admit(state, &config, key).await?;
+    let Some(key) = key else {
+        let options = run_options(&record, live);
+        state.runs.insert(record.clone()).await;
+        return Ok((config, record, options));
+    };
+
+    let record = record.with_owner(Some(key.name.clone()));
+    let options = run_options(&record, live);
+    if let Err(running) = state
+        .runs
+        .insert_within(record.clone(), key.max_running_tests)
+        .await
+    {
+        warn!(
+            "Refused a load test of {} with {} in flight",
+            key.name, running
+        );
+        return Err(Refusal {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            error: "Quota exceeded",
+            details: format!(
+                "API key '{}' already has {} load tests queued or running, the most it may have",
+                key.name, running
+            ),
+        });
+    }
+    Ok((config, record, options))
+}
+
+/// Options of a registered run: its progress, live metrics and controls
+fn run_options(record: &RunRecord, live: LiveMetrics) -> RunOptions {
+    RunOptions {
+        progress: Some(record.progress.clone()),
+        live: Some(live),
+        cancel: Some(record.cancel.clone()),
+        trigger: Some(record.trigger.clone()),
+        control: record.control.clone(),
+        ..Default::default()
+    }
+}
+
+/// Run a registered load test once a worker is free and record its outcome
+async fn execute_run(
+    state: &AppState,
+    id: &str,
+    config: &LoadTestConfig,
+    options: RunOptions,
+) -> Result<LoadTestResponse, RunFailure> {
+    let Some(_worker) = state.workers.acquire(options.cancel.as_ref()).await else {
+        state.save_run(id).await;
+        return Err(RunFailure::Cancelled(id.to_string()));
+    };
+    if options
+        .cancel
+        .as_ref()
+        .is_some_and(|cancel| cancel.is_cancelled())
+    {
+        state.save_run(id).await;
+        return Err(RunFailure::Cancelled(id.to_string()));
+    }
+    let Some(mut run) = state.runs.start(id).await else {
+        // Cancelled while waiting for the worker
+        state.save_run(id).await;
+        return Err(RunFailure::Cancelled(id.to_string()));
+    };
+    // The shared client has the default settings, runs that change the HTTP version, TLS or DNS
+    // get a client of their own
+    let client_options = HttpClientOptions::for_config(config);
+    let result = if client_options == HttpClientOptions::default() {
+        run.execute(&state.engine, options).await.map(|_| ())
+    } else {
+        match guarded_engine(&state.targets, client_options) {
+            Ok(engine) => run.execute(&engine, options).await.map(|_| ()),
+            Err(e) => {
+                let e = Error::Config(e.to_string());
+                let _ = run.fail(e.to_string());
+                Err(e)
+            }
+        }
+    };
+
+    let failure = match result {
+        Ok(()) => {
+            // The registry serves the report as a response, the run keeps only its state
+            let report = run.report.take().expect("finished runs keep their report");
+            let mut response: LoadTestResponse = report.into();
+            response.id = id.to_string();
+            if let Some(signer) = &state.signer {
+                match serde_json::to_value(&response)
+                    .map_err(Into::into)
+                    .and_then(|document| signer.sign(&document))
+                {
+                    Ok(signature) => response.signature = Some(signature),
+                    Err(e) => error!("Failed to sign report: {}", e),
+                }
+            }
+            state.runs.finish(run, Some(response.clone())).await;
+            state.save_run(id).await;
+            return Ok(response);
+        }
+        Err(e @ Error::Report(_)) => {
+            error!("Failed to generate report: {}", e);
+            RunFailure::Report(e.to_string())
+        }
+        Err(e) => {
+            error!("Failed to run load test: {}", e);
+            RunFailure::Run(e.to_string())
+        }
+    };
+    state.runs.finish(run, None).await;
+    state.save_run(id).await;
+    Err(failure)
+}
+
+/// Run a load test and respond with its report once it finishes
 #[instrument(skip_all, fields(
     url = %data.url,
     method = %data.method,
@@ -39,79 +603,597 @@ pub async fn health_check() -> impl Responder {
 pub async fn run_load_test(
     data: web::Json<LoadTestRequest>,
     state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
 ) -> impl Responder {
-    let request = data.into_inner();
+    let key = key.map(web::ReqData::into_inner);
+    let (config, record, options) = match register_run(
+        &state,
+        prepare_run(data.into_inner(), state.region.as_deref()),
+        key.as_deref(),
+    )
+    .await
+    {
+        Ok(run) => run,
+        Err(refusal) => return refusal.response(),
+    };
+    let id = record.id().to_string();
 
-    if let Err(e) = url::Url::parse(&request.url) {
-        warn!("Invalid URL provided: {}", e);
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "Invalid URL",
-            "details": e.to_string()
-        }));
+    match execute_run(&state, &id, &config, options).await {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(failure) => failure.response(),
     }
+}
 
-    if let Err(e) = request.method.parse::<lode_core::config::HttpMethod>() {
-        warn!("Invalid HTTP method provided: {}", e);
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "Invalid HTTP method",
-            "details": e.to_string()
+/// Queue a load test and respond with its id right away
+///
+/// The run can be followed at `GET /load-tests/{id}` and its report fetched from
+/// `GET /load-tests/{id}/report` once it completes.
+#[instrument(skip_all, fields(
+    url = %data.url,
+    method = %data.method,
+    requests = %data.requests,
+    concurrency = %data.concurrency,
+))]
+pub async fn submit_load_test(
+    data: web::Json<LoadTestRequest>,
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let key = key.map(web::ReqData::into_inner);
+    let (config, record, options) = match register_run(
+        &state,
+        prepare_run(data.into_inner(), state.region.as_deref()),
+        key.as_deref(),
+    )
+    .await
+    {
+        Ok(run) => run,
+        Err(refusal) => return refusal.response(),
+    };
+    let id = record.id().to_string();
+    let status = record.to_status();
+
+    let job_state = state.clone();
+    let job_id = id.clone();
+    actix_web::rt::spawn(async move {
+        // The outcome is recorded in the registry
+        let _ = execute_run(&job_state, &job_id, &config, options).await;
+    });
+
+    HttpResponse::Accepted()
+        .insert_header((header::LOCATION, format!("/load-tests/{}", id)))
+        .json(status)
+}
+
+/// Queue the share of a distributed run sent by a controller and respond with its status right
+/// away, as `POST /load-tests` does
+///
+/// The body is the configuration of the engine, split from the controller's test by
+/// [`lode_core::distributed::split`]. It is checked against the allowed targets and the limits of
+/// the API key like any other submission. As the configuration reaches the engine as is, with API
+/// keys only controller keys may submit one.
+#[instrument(skip_all, fields(
+    url = %data.url,
+    requests = %data.requests,
+    concurrency = %data.concurrency,
+))]
+pub async fn submit_agent_load_test(
+    data: web::Json<LoadTestConfig>,
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let key = key.map(web::ReqData::into_inner);
+    if let Some(key) = key.as_ref().filter(|key| !key.controller) {
+        warn!("Refused the share of a distributed run from {}", key.name);
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Not a controller",
+            "details": format!(
+                "API key '{}' may not submit the shares of distributed runs, a controller key is needed",
+                key.name
+            )
         }));
     }
+    let prepared = prepare_agent_run(data.into_inner(), state.region.as_deref());
+    let (config, record, options) = match register_run(&state, prepared, key.as_deref()).await {
+        Ok(run) => run,
+        Err(refusal) => return refusal.response(),
+    };
+    let id = record.id().to_string();
+    let status = record.to_status();
 
-    if let Some(headers) = &request.headers {
-        for (key, _) in headers {
-            if !key
-                .chars()
-                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
-            {
-                warn!("Invalid header name provided: {}", key);
-                return HttpResponse::BadRequest().json(serde_json::json!({
-                    "error": "Invalid header name",
-                    "details": format!("Header name '{}' contains invalid characters", key)
-                }));
-            }
+    let job_state = state.clone();
+    let job_id = id.clone();
+    actix_web::rt::spawn(async move {
+        // The outcome is recorded in the registry, where the controller polls it
+        let _ = execute_run(&job_state, &job_id, &config, options).await;
+    });
+
+    HttpResponse::Accepted()
+        .insert_header((header::LOCATION, format!("/load-tests/{}", id)))
+        .json(status)
+}
+
+#[instrument(skip_all, fields(id = %path))]
+pub async fn get_load_test(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let id = path.into_inner();
+    match find_run(&state, &id, &key).await {
+        Some(record) => HttpResponse::Ok().json(record.to_status()),
+        None => {
+            debug!("Load test not found: {}", id);
+            not_found(&id)
         }
     }
+}
 
-    let config: lode_core::config::LoadTestConfig = request.into();
-
-    let engine = state.engine.lock().await;
-    let result = engine
-        .run(
-            config.method.into(),
-            config.url,
-            config.requests as u64,
-            config.concurrency as u64,
-            config.timeout,
-            config.headers,
-            config.body,
-            None,
-        )
-        .await;
-
-    match result {
-        Ok(metrics) => {
-            let report = Report::from_metrics(metrics).await;
-            match report {
-                Ok(report) => {
-                    let response: LoadTestResponse = report.into();
-                    HttpResponse::Ok().json(response)
-                }
+/// Stream the status of a load test as server-sent events until it finishes
+///
+/// Each `status` event carries the body of `GET /load-tests/{id}`, sent right away and then at the
+/// interval of the live metrics; the stream ends with the status of the finished run.
+#[instrument(skip_all, fields(id = %path))]
+pub async fn stream_load_test_events(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let id = path.into_inner();
+    if find_run(&state, &id, &key).await.is_none() {
+        debug!("Load test not found: {}", id);
+        return not_found(&id);
+    }
+
+    let events = stream::unfold(Some((state, id, true)), |next| async move {
+        let (state, id, first) = next?;
+        if !first {
+            tokio::time::sleep(DEFAULT_SNAPSHOT_INTERVAL).await;
+        }
+        let record = state.run(&id).await?;
+        let event = sse_event("status", &record.to_status());
+        let next = (!record.status().is_finished()).then_some((state, id, false));
+        Some((Ok::<_, actix_web::Error>(event), next))
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(events)
+}
+
+/// Stream the live metrics of a load test as server-sent events while it runs
+///
+/// Each `snapshot` event carries a metrics snapshot as in the `metrics` of `GET /load-tests/{id}`,
+/// pushed as the engine publishes it: the latest one right away once the run has started, then
+/// every new one. The stream ends with the snapshot marked `finished`, or when the run stops
+/// without one. A run read back from the store streams the final snapshot of its report.
+#[instrument(skip_all, fields(id = %path))]
+pub async fn stream_load_test_metrics(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let Some(record) = find_run(&state, &id, &key).await else {
+        debug!("Load test not found: {}", id);
+        return not_found(&id);
+    };
+    let started = record.status() != RunState::Pending;
+    let Some(live) = record.live else {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "No live metrics",
+            "details": format!("Load test '{}' has no live metrics, fetch its report instead", id)
+        }));
+    };
+
+    let snapshots = stream::unfold(Some((live, started)), |next| async move {
+        let (mut live, current) = next?;
+        if !current && live.changed().await.is_err() {
+            return None;
+        }
+        let snapshot = live.borrow_and_update().clone();
+        let event = sse_event("snapshot", &snapshot);
+        let next = (!snapshot.finished).then_some((live, false));
+        Some((Ok::<_, actix_web::Error>(event), next))
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(snapshots)
+}
+
+/// A server-sent event with a JSON payload
+fn sse_event(event: &str, data: &impl Serialize) -> web::Bytes {
+    let data = serde_json::to_string(data).unwrap_or_else(|_| "null".to_string());
+    web::Bytes::from(format!("event: {}\ndata: {}\n\n", event, data))
+}
+
+/// Get the report of a finished load test, as JSON or with `?format=html` as an HTML page with
+/// charts
+#[instrument(skip_all, fields(id = %path))]
+pub async fn get_load_test_report(
+    path: web::Path<String>,
+    query: web::Query<LoadTestReportQuery>,
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let html = match query.format.as_deref() {
+        None | Some("json") => false,
+        Some("html") => true,
+        Some(format) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Invalid query",
+                "details": format!("Unknown report format '{}', expected json or html", format)
+            }))
+        }
+    };
+    let id = path.into_inner();
+    let Some(record) = find_run(&state, &id, &key).await else {
+        debug!("Load test not found: {}", id);
+        return not_found(&id);
+    };
+
+    match (record.status(), record.response) {
+        (RunState::Completed | RunState::Aborted | RunState::Cancelled, Some(response)) if html => {
+            match serde_json::to_value(&response).and_then(serde_json::from_value::<Report>) {
+                Ok(report) => HttpResponse::Ok()
+                    .content_type("text/html; charset=utf-8")
+                    .body(report.as_html(None)),
                 Err(e) => {
-                    error!("Failed to generate report: {}", e);
+                    error!("Failed to render report {}: {}", id, e);
                     HttpResponse::InternalServerError().json(serde_json::json!({
-                        "error": "Failed to generate report",
+                        "error": "Failed to render report",
                         "details": e.to_string()
                     }))
                 }
             }
         }
+        (RunState::Completed | RunState::Aborted | RunState::Cancelled, Some(response)) => {
+            HttpResponse::Ok().json(response)
+        }
+        (RunState::Failed, _) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Load test failed",
+            "details": record.run.error
+        })),
+        (RunState::Cancelled, None) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Load test cancelled",
+            "details": format!("Load test '{}' was cancelled before it started", id)
+        })),
+        (status, _) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Report not ready",
+            "details": format!("Load test '{}' is {}", id, status.as_str())
+        })),
+    }
+}
+
+/// Cancel a queued or running load test
+///
+/// A running test stops shortly after and keeps a partial report of the requests completed so far.
+#[instrument(skip_all, fields(id = %path))]
+pub async fn cancel_load_test(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let cancelled = match find_run(&state, &id, &key).await {
+        // A run read back from the store has finished
+        Some(record) => state.runs.cancel(&id).await.or(Some(record.status())),
+        None => None,
+    };
+    match cancelled {
+        None => {
+            debug!("Load test not found: {}", id);
+            not_found(&id)
+        }
+        Some(status) if status.is_finished() => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Load test already finished",
+            "details": format!("Load test '{}' is {}", id, status.as_str())
+        })),
+        Some(_) => match state.run(&id).await {
+            Some(record) => HttpResponse::Accepted().json(record.to_status()),
+            None => not_found(&id),
+        },
+    }
+}
+
+/// Move a running load test to its next stage
+///
+/// The current stage ends right away, e.g. a `signal` stage holding the load until a debugging
+/// session on the target is ready for more.
+#[instrument(skip_all, fields(id = %path))]
+pub async fn advance_load_test(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let advanced = match find_run(&state, &id, &key).await {
+        Some(record) => state.runs.advance(&id).await.or(Some(record.status())),
+        None => None,
+    };
+    match advanced {
+        None => {
+            debug!("Load test not found: {}", id);
+            not_found(&id)
+        }
+        Some(RunState::Running) => match state.run(&id).await {
+            Some(record) => HttpResponse::Accepted().json(record.to_status()),
+            None => not_found(&id),
+        },
+        Some(status) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Load test not running",
+            "details": format!("Load test '{}' is {}", id, status.as_str())
+        })),
+    }
+}
+
+/// Adjust the concurrency or rate of a running load test within the bounds of its configuration
+#[instrument(skip_all, fields(id = %path))]
+pub async fn override_load_test(
+    path: web::Path<String>,
+    data: web::Json<Overrides>,
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let id = path.into_inner();
+    let record = match find_run(&state, &id, &key).await {
+        Some(record) => record,
+        None => {
+            debug!("Load test not found: {}", id);
+            return not_found(&id);
+        }
+    };
+    let control = match &record.control {
+        Some(control) if record.status() == RunState::Running => control,
+        _ => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": "Load test not running",
+                "details": format!("Load test '{}' is {}", id, record.status().as_str())
+            }))
+        }
+    };
+    match control.apply(data.into_inner()) {
+        Ok(_) => HttpResponse::Ok().json(record.to_status()),
         Err(e) => {
-            error!("Failed to run load test: {}", e);
+            warn!("Invalid load override: {}", e);
             HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "Failed to run load test",
+                "error": "Invalid override",
                 "details": e.to_string()
             }))
         }
     }
 }
+
+#[instrument(skip_all)]
+pub async fn list_load_tests(
+    query: web::Query<LoadTestListQuery>,
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let options = match ListOptions::try_from(query.into_inner()) {
+        Ok(options) => ListOptions {
+            owner: key_name(&key).map(str::to_string),
+            ..options
+        },
+        Err(e) => {
+            warn!("Invalid listing query: {}", e);
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Invalid query",
+                "details": e
+            }));
+        }
+    };
+
+    let page = match state.list_runs(&options).await {
+        Ok(page) => page,
+        Err(e) => return history_unavailable(e),
+    };
+    HttpResponse::Ok().json(LoadTestListResponse {
+        items: page.items,
+        total: page.total,
+        limit: options.limit,
+        offset: options.offset,
+    })
+}
+
+/// p95, throughput and error rate over the completed runs against a target, for dashboards of
+/// the performance drift between releases
+///
+/// The target is the hash of its URL, the `target` of its runs in `GET /load-tests`.
+#[instrument(skip_all, fields(target = %path))]
+pub async fn get_target_trends(
+    path: web::Path<String>,
+    query: web::Query<TargetTrendQuery>,
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let target = path.into_inner().to_lowercase();
+    let options = match TrendOptions::try_from(query.into_inner()) {
+        Ok(options) => TrendOptions {
+            owner: key_name(&key).map(str::to_string),
+            ..options
+        },
+        Err(e) => {
+            warn!("Invalid trend query: {}", e);
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Invalid query",
+                "details": e
+            }));
+        }
+    };
+
+    let trend = match state.trend(&target, &options).await {
+        Ok(trend) => trend,
+        Err(e) => return history_unavailable(e),
+    };
+    match trend {
+        Some((url, points)) => HttpResponse::Ok().json(TargetTrendResponse {
+            target,
+            url,
+            points,
+        }),
+        None => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Target not found",
+            "details": format!("No load test was run against target '{}'", target)
+        })),
+    }
+}
+
+fn history_unavailable(e: anyhow::Error) -> HttpResponse {
+    error!("Failed to read the run history: {}", e);
+    HttpResponse::InternalServerError().json(serde_json::json!({
+        "error": "Run history unavailable",
+        "details": e.to_string()
+    }))
+}
+
+fn schedule_not_found(id: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({
+        "error": "Schedule not found",
+        "details": format!("No schedule with id '{}'", id)
+    }))
+}
+
+/// Run a load test at an interval, within the allowed windows and outside the blackouts of the
+/// schedule
+///
+/// The load test is checked as `POST /load-tests` checks it, and again at every run. Every time
+/// the schedule is due its history records the run started, or why it was skipped or refused.
+#[instrument(skip_all, fields(url = %data.load_test.url, every = %data.every))]
+pub async fn create_schedule(
+    data: web::Json<ScheduleRequest>,
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let key = key.map(web::ReqData::into_inner);
+    let request = data.into_inner();
+    let admitted = match prepare_run(request.load_test.clone(), state.region.as_deref()) {
+        Ok((config, _, _)) => admit(&state, &config, key.as_deref()).await,
+        Err(e) => Err(config_refusal(&e)),
+    };
+    if let Err(refusal) = admitted {
+        return refusal.response();
+    }
+    let schedule = match Schedule::new(Uuid::new_v4().to_string(), request, key) {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            warn!("Invalid schedule: {}", e);
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Invalid schedule",
+                "details": e
+            }));
+        }
+    };
+    let response = schedule.to_response();
+    state.schedules.insert(schedule).await;
+    HttpResponse::Created()
+        .insert_header((header::LOCATION, format!("/schedules/{}", response.id)))
+        .json(response)
+}
+
+/// Name of the API key of a request, which only sees the runs and schedules it created
+fn key_name(key: &Option<web::ReqData<Arc<ApiKey>>>) -> Option<&str> {
+    key.as_ref().map(|key| key.name.as_str())
+}
+
+/// A run the API key of a request may see; the runs of other keys are not found
+async fn find_run(
+    state: &AppState,
+    id: &str,
+    key: &Option<web::ReqData<Arc<ApiKey>>>,
+) -> Option<RunRecord> {
+    state
+        .run(id)
+        .await
+        .filter(|record| record.visible_to(key_name(key)))
+}
+
+#[instrument(skip_all)]
+pub async fn list_schedules(
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let items = state
+        .schedules
+        .list(key_name(&key))
+        .await
+        .iter()
+        .map(Schedule::to_response)
+        .collect();
+    HttpResponse::Ok().json(ScheduleListResponse { items })
+}
+
+#[instrument(skip_all, fields(id = %path))]
+pub async fn get_schedule(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let id = path.into_inner();
+    match state.schedules.get(&id, key_name(&key)).await {
+        Some(schedule) => HttpResponse::Ok().json(schedule.to_response()),
+        None => schedule_not_found(&id),
+    }
+}
+
+/// Delete a schedule; the runs it already started go on
+#[instrument(skip_all, fields(id = %path))]
+pub async fn delete_schedule(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    key: Option<web::ReqData<Arc<ApiKey>>>,
+) -> impl Responder {
+    let id = path.into_inner();
+    if state.schedules.remove(&id, key_name(&key)).await {
+        HttpResponse::NoContent().finish()
+    } else {
+        schedule_not_found(&id)
+    }
+}
+
+/// Queue the runs of the schedules due at `now`, or record why they were skipped or refused
+///
+/// Returns how many schedules were due.
+pub async fn run_due_schedules(state: &web::Data<AppState>, now: DateTime<Utc>) -> usize {
+    let due = state.schedules.take_due(now).await;
+    for schedule in &due {
+        let event = if let Some(reason) = schedule.blocked_at(now) {
+            debug!("Skipped schedule {}: {}", schedule.id, reason);
+            ScheduleEvent::skipped(now, reason)
+        } else {
+            let prepared = prepare_run(schedule.request.clone(), state.region.as_deref());
+            match register_run(state, prepared, schedule.owner.as_deref()).await {
+                Ok((config, record, options)) => {
+                    let job_state = state.clone();
+                    let job_id = record.id().to_string();
+                    actix_web::rt::spawn(async move {
+                        // The outcome is recorded in the registry
+                        let _ = execute_run(&job_state, &job_id, &config, options).await;
+                    });
+                    ScheduleEvent::started(now, record.id().to_string())
+                }
+                Err(refusal) => {
+                    warn!(
+                        "Refused a run of schedule {}: {}",
+                        schedule.id, refusal.details
+                    );
+                    ScheduleEvent::refused(now, refusal.details)
+                }
+            }
+        };
+        state.schedules.record(&schedule.id, event).await;
+    }
+    due.len()
+}
+
+/// Start the runs of the schedules when they are due, for the lifetime of the API
+pub fn spawn_scheduler(state: web::Data<AppState>) {
+    actix_web::rt::spawn(async move {
+        let mut ticks = tokio::time::interval(SCHEDULER_TICK);
+        loop {
+            ticks.tick().await;
+            run_due_schedules(&state, Utc::now()).await;
+        }
+    });
+}
@@ -1,5 +1,21 @@
+pub mod auth;
 pub mod handlers;
 pub mod models;
+pub mod runs;
+pub mod schedules;
+pub mod storage;
+pub mod targets;
+pub mod trends;
+#[cfg(feature = "ui")]
+pub mod ui;
+pub mod workers;
 
-pub use handlers::{health_check, run_load_test};
-pub use models::{ErrorStats, LoadTestRequest, LoadTestResponse};
+pub use handlers::{
+    advance_load_test, cancel_load_test, clock_time, get_load_test, get_load_test_report,
+    get_target_trends, health_check, list_load_tests, prometheus_metrics, run_load_test,
+    stream_load_test_events, stream_load_test_metrics, submit_load_test,
+};
+pub use models::{
+    ErrorStats, LoadTestListResponse, LoadTestRequest, LoadTestResponse, LoadTestStatusResponse,
+    TargetTrendResponse,
+};
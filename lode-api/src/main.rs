@@ -1,28 +1,101 @@
+mod auth;
 mod configuration;
 mod handlers;
 mod models;
+mod runs;
+mod schedules;
+mod storage;
+mod targets;
+mod trends;
+#[cfg(feature = "ui")]
+mod ui;
+mod workers;
 
 use actix_cors::Cors;
-use actix_web::{web, App, HttpServer};
+use actix_web::{middleware, web, App, HttpServer};
 use dotenv::dotenv;
-use lode_core::telemetry::{get_stdout_subscriber, init_subscriber};
+use lode_core::report::signing::ReportSigner;
+use lode_core::telemetry::{
+    get_otlp_subscriber, get_stdout_subscriber, init_subscriber, OtlpSettings,
+};
 use tracing::info;
 use tracing_actix_web::TracingLogger;
 
+use auth::{authenticate, hide_access_token, ApiKeys};
 use configuration::Settings;
-use handlers::{health_check, run_load_test, AppState};
+use handlers::{
+    advance_load_test, cancel_load_test, clock_time, create_schedule, delete_schedule,
+    get_load_test, get_load_test_report, get_schedule, get_target_trends, health_check,
+    list_load_tests, list_schedules, override_load_test, prometheus_metrics, run_load_test,
+    spawn_scheduler, stream_load_test_events, stream_load_test_metrics, submit_agent_load_test,
+    submit_load_test, AppState,
+};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
 
-    let subscriber = get_stdout_subscriber("lode-api".into(), "info".into());
-    init_subscriber(subscriber);
-
     let settings = Settings::get_configuration().expect("Failed to load configuration");
     let address = format!("{}:{}", settings.server.host, settings.server.port);
 
-    let app_state = web::Data::new(AppState::new().expect("Failed to create app state"));
+    // The settings take precedence over the standard OTEL_EXPORTER_OTLP_* variables
+    let otlp = match &settings.otlp {
+        Some(otlp) => Some(otlp.clone()),
+        None => OtlpSettings::from_env().expect("Failed to read the OTLP settings"),
+    };
+    let otlp_guard = match &otlp {
+        Some(otlp) => {
+            let (subscriber, guard) =
+                get_otlp_subscriber("lode-api".into(), "info".into(), std::io::stdout, otlp)
+                    .expect("Failed to set up OTLP export");
+            init_subscriber(subscriber);
+            Some(guard)
+        }
+        None => {
+            let subscriber = get_stdout_subscriber("lode-api".into(), "info".into());
+            init_subscriber(subscriber);
+            None
+        }
+    };
+
+    let signer = settings.signing_key.as_deref().map(|path| {
+        info!("Signing reports with the key in {}", path.display());
+        ReportSigner::from_file(path).expect("Failed to read the signing key")
+    });
+    // The URL may hold a password, only its scheme is logged
+    if let Some(url) = &settings.database_url {
+        let scheme = url.split(':').next().unwrap_or_default();
+        info!("Saving the run history to a {} database", scheme);
+    }
+    let store = storage::open(settings.database_url.as_deref())
+        .await
+        .expect("Failed to open the run history database");
+    let app_state = web::Data::new(
+        AppState::new()
+            .expect("Failed to create app state")
+            .with_region(settings.region.clone())
+            .with_signer(signer)
+            .with_store(store)
+            .with_api_keys(ApiKeys::new(settings.api_keys.clone()))
+            .with_targets(settings.targets.clone())
+            .expect("Failed to create the HTTP client of the runs")
+            .with_workers(settings.max_concurrent_runs),
+    );
+    if app_state.api_keys().is_enabled() {
+        info!("Requiring an API key");
+    }
+    info!(
+        "Running up to {} load tests at once",
+        app_state.workers().size()
+    );
+    if let Some(otlp) = &otlp {
+        info!(
+            "Exporting traces and metrics to {} over {}",
+            otlp.endpoint, otlp.protocol
+        );
+        app_state.observe_otlp();
+    }
+    spawn_scheduler(app_state.clone());
 
     info!("Starting Lode API server on {}", address);
 
@@ -33,14 +106,59 @@ async fn main() -> std::io::Result<()> {
             .allow_any_header()
             .max_age(3600);
 
-        App::new()
+        // Inside CORS, so preflight requests need no key
+        let app = App::new()
+            .wrap(middleware::from_fn(authenticate))
             .wrap(cors)
             .wrap(TracingLogger::default())
+            .wrap(middleware::from_fn(hide_access_token))
             .app_data(app_state.clone())
             .route("/health", web::get().to(health_check))
+            .route("/time", web::get().to(clock_time))
+            .route("/metrics", web::get().to(prometheus_metrics))
             .route("/load-test", web::post().to(run_load_test))
+            .route("/load-tests", web::get().to(list_load_tests))
+            .route("/load-tests", web::post().to(submit_load_test))
+            .route(
+                lode_core::distributed::AGENT_SUBMIT_PATH,
+                web::post().to(submit_agent_load_test),
+            )
+            .route("/load-tests/{id}", web::get().to(get_load_test))
+            .route("/load-tests/{id}", web::delete().to(cancel_load_test))
+            .route("/load-tests/{id}", web::patch().to(override_load_test))
+            .route(
+                "/load-tests/{id}/advance",
+                web::post().to(advance_load_test),
+            )
+            .route(
+                "/load-tests/{id}/report",
+                web::get().to(get_load_test_report),
+            )
+            .route(
+                "/load-tests/{id}/events",
+                web::get().to(stream_load_test_events),
+            )
+            .route(
+                "/load-tests/{id}/stream",
+                web::get().to(stream_load_test_metrics),
+            )
+            .route("/targets/{target}/trends", web::get().to(get_target_trends))
+            .route("/schedules", web::get().to(list_schedules))
+            .route("/schedules", web::post().to(create_schedule))
+            .route("/schedules/{id}", web::get().to(get_schedule))
+            .route("/schedules/{id}", web::delete().to(delete_schedule));
+        #[cfg(feature = "ui")]
+        let app = app.route("/ui", web::get().to(ui::index));
+        app
     })
     .bind(&address)?
     .run()
-    .await
+    .await?;
+
+    // Flushing waits for the collector, away from the runtime thread
+    if let Some(guard) = otlp_guard {
+        tokio::task::spawn_blocking(move || drop(guard)).await?;
+    }
+
+    Ok(())
 }
@@ -1,87 +1,608 @@
+use chrono::{DateTime, Utc};
+use lode_core::config::{
+    ConfigError, Cooldown, DnsConfig, Feeder, FormBody, GraphqlRequest, HeaderPacing, HttpMethod,
+    HttpVersion, LoadModel, LoadTestConfig, MissingValue, RandomParam, ResponseBody,
+    RetryCondition, RetryPolicy, SuccessCodes, ThinkTime, TlsConfig, TlsVersion,
+};
+use lode_core::control::Overrides;
+use lode_core::live::MetricsSnapshot;
+use lode_core::metrics::ErrorKind;
+use lode_core::progress::ProgressSnapshot;
+use lode_core::report::signing::ReportSignature;
+use lode_core::report::thresholds::ThresholdResult;
+use lode_core::report::{
+    BackoffStats, ConnectionStats, CooldownStats, CorrectedResponseTimes, GroupStats, MemoryStats,
+    MixStats, PercentileStat, PhaseStats, RegionStats, RetryStats, SkippedStats, StageStats,
+    TimeSeries, TimelineEvent, TlsResumptionStats, TransferStats,
+};
+use lode_core::scenario::mix::WorkloadMix;
+use lode_core::scenario::Scenario;
+use lode_core::slo::SloResult;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
 
-#[derive(Debug, Deserialize, Serialize)]
+use crate::schedules::{ScheduleEvent, TimeWindow};
+use crate::trends::TrendPoint;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct LoadTestRequest {
     pub url: String,
     pub method: String,
     pub requests: u64,
     pub concurrency: u64,
     pub timeout_ms: Option<u64>,
+    /// Overall deadline of the run; it ends with partial results past it
+    #[serde(default)]
+    pub deadline_ms: Option<u64>,
+    /// Time to keep probing the target after the load stops, reporting its recovery as `cooldown`
+    #[serde(default)]
+    pub cooldown_ms: Option<u64>,
+    /// URL probed during the cooldown instead of `url`, e.g. a health endpoint
+    #[serde(default)]
+    pub cooldown_probe_url: Option<String>,
+    /// Extra response time percentiles to report, e.g. `[75, 99.99]`
+    #[serde(default)]
+    pub percentiles: Option<Vec<f64>>,
     pub headers: Option<HashMap<String, String>>,
     pub body: Option<String>,
+    /// Form-encoded or multipart body sent instead of `body`, of text fields only: the API does
+    /// not read files of its host
+    #[serde(default)]
+    pub form: Option<FormBody>,
+    /// GraphQL operation POSTed instead of `body`; responses with `errors` count as failed
+    #[serde(default)]
+    pub graphql: Option<GraphqlRequest>,
+    #[serde(default)]
+    pub scenario: Option<Scenario>,
+    /// Environment of the scenario to run against, out of its `environments`
+    #[serde(default)]
+    pub env: Option<String>,
+    /// Targets sent the same request in turn instead of `url`, broken down per URL in the report
+    #[serde(default)]
+    pub urls: Option<Vec<String>>,
+    /// Pick one of `urls` at random for every request instead of in turn
+    #[serde(default)]
+    pub random_url_order: Option<bool>,
+    /// Query parameters appended with a random value, e.g. `"id=1..1000"` or `"sort=asc|desc"`
+    #[serde(default)]
+    pub random_params: Option<Vec<RandomParam>>,
+    /// Rows of test data filling the `{{column}}` placeholders, one per iteration
+    #[serde(default)]
+    pub feeder: Option<Feeder>,
+    /// What the run does with a request whose placeholder has no value: `send` (default), `skip`
+    /// or `abort`
+    #[serde(default)]
+    pub on_missing_value: Option<MissingValue>,
+    /// Service level objectives checked against the report, e.g. `"p95 < 250ms"`
+    #[serde(default)]
+    pub slos: Option<Vec<String>>,
+    /// Threshold expressions evaluated on the report, e.g. `"p95 < 300ms && error_rate < 1%"`
+    #[serde(default)]
+    pub thresholds: Option<Vec<String>>,
+    /// Conditions ending the run early, e.g. `"error_rate > 50% over 10s"` or
+    /// `"100 consecutive connection failures"`
+    #[serde(default)]
+    pub abort_on: Option<Vec<String>>,
+    /// Length of the intervals of the time series in the report
+    #[serde(default)]
+    pub time_series_interval_ms: Option<u64>,
+    /// Region of the agent, attached to every request as the `region` label
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Percentages per scenario request name replacing the scenario weights
+    #[serde(default)]
+    pub mix: Option<WorkloadMix>,
+    /// Back off virtual users for the delay of `Retry-After` headers of 429, 502 and 503 responses
+    #[serde(default)]
+    pub honor_retry_after: Option<bool>,
+    /// Pace requests by rate limit response headers, e.g.
+    /// `X-RateLimit-Remaining,X-RateLimit-Reset,90%`
+    #[serde(default)]
+    pub header_pacing: Option<HeaderPacing>,
+    /// Send failed requests again with exponential backoff before they count as failed
+    #[serde(default)]
+    pub retry: Option<RetryRequest>,
+    /// Pause of a virtual user between its requests, e.g. `"2s"`, `"1s..3s"` or `"exp:2s"`
+    #[serde(default)]
+    pub think_time: Option<ThinkTime>,
+    /// Status codes counted as successful, e.g. `"201"` or `"2xx,404"`; any 2xx when unset
+    #[serde(default)]
+    pub success_codes: Option<SuccessCodes>,
+    /// What to do with response bodies: `drain` (default), `read` or `discard`
+    #[serde(default)]
+    pub response_body: Option<ResponseBody>,
+    /// HTTP version to speak with the target: `auto`, `http1` or `http2`
+    #[serde(default)]
+    pub http_version: Option<HttpVersion>,
+    /// TLS settings, with certificates and key given inline as PEM
+    #[serde(default)]
+    pub tls: Option<TlsRequest>,
+    /// Host name resolution by other nameservers than the agent's, e.g.
+    /// `{"nameservers": ["10.0.0.2:53"], "no_cache": true}`
+    #[serde(default)]
+    pub dns: Option<DnsConfig>,
+    #[serde(default)]
+    pub labels: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub rate: Option<f64>,
+    /// Requests the closed model may start at once at `rate`; 1 (default) paces them strictly
+    #[serde(default)]
+    pub rate_burst: Option<u32>,
+    /// Highest concurrency `PATCH /load-tests/{id}` may set, the highest configured by default
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+    /// Highest rate `PATCH /load-tests/{id}` may set, the highest configured by default
+    #[serde(default)]
+    pub max_rate: Option<f64>,
+    /// When requests start: `closed` (default), or on the schedule of `rate` with `open` or `poisson`
+    #[serde(default)]
+    pub load_model: Option<LoadModel>,
+    /// Also report response times corrected for coordinated omission, needs `rate`
+    #[serde(default)]
+    pub correct_coordinated_omission: bool,
+    #[serde(default)]
+    pub faults: Option<Vec<String>>,
+    #[serde(default)]
+    pub stages: Option<Vec<String>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Retry policy of a run, see `lode_core::retry::RetryPolicy`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryRequest {
+    /// Attempts per request at most, the first one included
+    pub max_attempts: u32,
+    /// Failures retried: `"connect"` or status codes such as `503`, failures to connect by default
+    #[serde(default)]
+    pub retry_on: Option<Vec<RetryCondition>>,
+    /// Upper bound of the delay before the first retry, doubled for every further one
+    #[serde(default)]
+    pub base_delay_ms: Option<u64>,
+    /// Upper bound of the delay before any retry
+    #[serde(default)]
+    pub max_delay_ms: Option<u64>,
+}
+
+/// TLS settings of a run, see `lode_core::config::TlsConfig`
+///
+/// Certificates and key are given inline: paths would be read on the host of the API, and
+/// turning off certificate verification is left to the CLI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsRequest {
+    /// PEM of CA certificates trusted on top of the system's
+    #[serde(default)]
+    pub ca_cert: Option<String>,
+    /// PEM of the client certificate presented for mutual TLS, together with `client_key`
+    #[serde(default)]
+    pub client_cert: Option<String>,
+    /// PEM of the PKCS #8 private key of `client_cert`
+    #[serde(default)]
+    pub client_key: Option<String>,
+    /// Lowest TLS version accepted: `1.0`, `1.1`, `1.2` or `1.3`, the latter with
+    /// `session_resumption` set only
+    #[serde(default)]
+    pub min_version: Option<TlsVersion>,
+    /// Resume earlier TLS sessions on new connections, or always do a full handshake
+    #[serde(default)]
+    pub session_resumption: Option<bool>,
+}
+
+impl From<TlsRequest> for TlsConfig {
+    fn from(req: TlsRequest) -> Self {
+        TlsConfig {
+            ca_cert_pem: req.ca_cert,
+            client_cert_pem: req.client_cert,
+            client_key_pem: req.client_key,
+            min_version: req.min_version,
+            session_resumption: req.session_resumption,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<RetryRequest> for RetryPolicy {
+    fn from(req: RetryRequest) -> Self {
+        let mut policy = RetryPolicy::new(req.max_attempts);
+        if let Some(retry_on) = req.retry_on {
+            policy.retry_on = retry_on;
+        }
+        if let Some(base_delay_ms) = req.base_delay_ms {
+            policy.base_delay = Duration::from_millis(base_delay_ms);
+        }
+        if let Some(max_delay_ms) = req.max_delay_ms {
+            policy.max_delay = Duration::from_millis(max_delay_ms);
+        }
+        policy
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LoadTestResponse {
+    /// Version of the report format, see `lode_core::report::schema`
+    #[serde(default)]
+    pub schema_version: u32,
     pub id: String,
     pub status: String,
+    /// Abort rule that ended an `aborted` run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abort_reason: Option<String>,
     pub total_requests: u64,
     pub successful_requests: u64,
     pub failed_requests: u64,
     pub requests_per_second: f64,
+    #[serde(default)]
+    pub iterations: u64,
+    #[serde(default)]
+    pub iterations_per_second: f64,
+    #[serde(default)]
+    pub virtual_users: u64,
     pub min_response_time_ms: f64,
     pub max_response_time_ms: f64,
     pub mean_response_time_ms: f64,
     pub median_response_time_ms: f64,
     pub p95_response_time_ms: f64,
     pub p99_response_time_ms: f64,
+    #[serde(default)]
+    pub p999_response_time_ms: f64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub percentiles: Vec<PercentileStat>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tail_latencies_ms: Vec<f64>,
     pub total_duration_seconds: f64,
+    /// Response times as a base64 compressed HdrHistogram, see `lode_core::report::hdr`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_time_histogram: Option<String>,
+    /// Response times corrected for coordinated omission, when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corrected_response_times: Option<CorrectedResponseTimes>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_series: Option<TimeSeries>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub timeline: Vec<TimelineEvent>,
     pub error_stats: Option<ErrorStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connections: Option<ConnectionStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_resumption: Option<TlsResumptionStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<SkippedStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transfer: Option<TransferStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backoff: Option<BackoffStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<RetryStats>,
+    /// Responses whose body was cut at the max body size
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated_bodies: Option<u64>,
+    /// Resident memory of the generator under its memory ceiling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryStats>,
+    /// Responses per HTTP status code
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub status_codes: BTreeMap<u16, u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phases: Vec<PhaseStats>,
+    /// Breakdown per stage of a staged run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stages: Vec<StageStats>,
+    /// Recovery of the target after the load stopped, when a cooldown was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown: Option<CooldownStats>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protocols: Vec<GroupStats>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub endpoints: Vec<GroupStats>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mix: Vec<MixStats>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<GroupStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<RegionStats>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub slos: Vec<SloResult>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thresholds: Vec<ThresholdResult>,
+    /// Signature of this response by the agent's signing key, see `lode_core::report::signing`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ReportSignature>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorStats {
-    pub error_counts: HashMap<String, u64>,
+    pub error_counts: HashMap<ErrorKind, u64>,
     pub error_messages: Vec<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoadTestStatusResponse {
+    pub id: String,
+    pub status: String,
+    pub url: String,
+    pub method: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub progress: ProgressSnapshot,
+    /// Live metrics of a started run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<MetricsSnapshot>,
+    /// Concurrency and rate set with `PATCH /load-tests/{id}`, once any are
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overrides: Option<Overrides>,
+    /// Why the run failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct LoadTestReportQuery {
+    /// `json` (default) or `html`
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct LoadTestListQuery {
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    pub sort: Option<String>,
+    pub order: Option<String>,
+    pub fields: Option<String>,
+    pub url: Option<String>,
+    pub status: Option<String>,
+    /// RFC 3339 time, e.g. `2025-03-30T00:00:00Z`
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TargetTrendQuery {
+    /// Hash of a scenario, `none` for runs without one or `any` (default)
+    pub scenario: Option<String>,
+    /// RFC 3339 time, e.g. `2025-03-30T00:00:00Z`
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// p95, throughput and error rate of the completed runs against a target, the oldest first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TargetTrendResponse {
+    /// Hash of the target URL
+    pub target: String,
+    pub url: String,
+    pub points: Vec<TrendPoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoadTestListResponse {
+    pub items: Vec<serde_json::Map<String, serde_json::Value>>,
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+/// A load test to run at a fixed interval, within allowed windows and outside blackouts
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScheduleRequest {
+    /// The load test, as the body of `POST /load-tests`
+    pub load_test: LoadTestRequest,
+    /// Interval between runs, e.g. `30m` or `6h`
+    pub every: String,
+    /// Times runs are allowed at, e.g. nights and weekends; any time when empty
+    #[serde(default)]
+    pub windows: Vec<TimeWindow>,
+    /// Times no run may start at, e.g. business hours of the team using the target
+    #[serde(default)]
+    pub blackouts: Vec<TimeWindow>,
+    /// Offset of the time zone of the windows and blackouts, e.g. `+02:00`; UTC when unset
+    #[serde(default)]
+    pub utc_offset: Option<String>,
+    /// First run, right away when unset
+    #[serde(default)]
+    pub start_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScheduleResponse {
+    pub id: String,
+    pub url: String,
+    pub method: String,
+    pub every_seconds: u64,
+    pub windows: Vec<TimeWindow>,
+    pub blackouts: Vec<TimeWindow>,
+    pub utc_offset: String,
+    pub created_at: DateTime<Utc>,
+    pub next_run_at: DateTime<Utc>,
+    /// Runs started, skipped and refused, the oldest first
+    pub history: Vec<ScheduleEvent>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScheduleListResponse {
+    pub items: Vec<ScheduleResponse>,
+}
+
 impl From<lode_core::report::Report> for LoadTestResponse {
     fn from(report: lode_core::report::Report) -> Self {
         LoadTestResponse {
+            schema_version: report.schema_version,
             id: report.id,
             status: report.status,
+            abort_reason: report.abort_reason,
             total_requests: report.total_requests,
             successful_requests: report.successful_requests,
             failed_requests: report.failed_requests,
             requests_per_second: report.requests_per_second,
+            iterations: report.iterations,
+            iterations_per_second: report.iterations_per_second,
+            virtual_users: report.virtual_users,
             min_response_time_ms: report.min_response_time_ms,
             max_response_time_ms: report.max_response_time_ms,
             mean_response_time_ms: report.mean_response_time_ms,
             median_response_time_ms: report.median_response_time_ms,
             p95_response_time_ms: report.p95_response_time_ms,
             p99_response_time_ms: report.p99_response_time_ms,
+            p999_response_time_ms: report.p999_response_time_ms,
+            percentiles: report.percentiles,
+            tail_latencies_ms: report.tail_latencies_ms,
             total_duration_seconds: report.total_duration_seconds,
+            response_time_histogram: report.response_time_histogram,
+            corrected_response_times: report.corrected_response_times,
+            time_series: report.time_series,
+            timeline: report.timeline,
             error_stats: report.error_stats.map(|stats| ErrorStats {
                 error_counts: stats.error_counts,
                 error_messages: stats.error_messages,
             }),
+            connections: report.connections,
+            tls_resumption: report.tls_resumption,
+            skipped: report.skipped,
+            transfer: report.transfer,
+            backoff: report.backoff,
+            retries: report.retries,
+            truncated_bodies: report.truncated_bodies,
+            memory: report.memory,
+            status_codes: report.status_codes,
+            phases: report.phases,
+            stages: report.stages,
+            cooldown: report.cooldown,
+            protocols: report.protocols,
+            endpoints: report.endpoints,
+            mix: report.mix,
+            groups: report.groups,
+            region: report.region,
+            regions: report.regions,
+            slos: report.slos,
+            thresholds: report.thresholds,
+            // A signature of the report does not hold for the response, which is signed on its own
+            signature: None,
         }
     }
 }
 
-impl From<LoadTestRequest> for lode_core::config::LoadTestConfig {
-    fn from(req: LoadTestRequest) -> Self {
-        let mut config = Self::new(
-            req.url,
-            req.method
-                .parse()
-                .unwrap_or(lode_core::config::HttpMethod::GET),
-            req.requests as usize,
-            req.concurrency as usize,
-            std::time::Duration::from_millis(req.timeout_ms.unwrap_or(5000)),
-        )
-        .expect("Failed to create load test config");
+impl TryFrom<LoadTestRequest> for LoadTestConfig {
+    type Error = ConfigError;
 
-        if let Some(headers) = req.headers {
-            config.headers = headers.into_iter().collect();
+    fn try_from(req: LoadTestRequest) -> Result<Self, Self::Error> {
+        let method: HttpMethod = req.method.parse()?;
+        let mut scenario = req.scenario;
+        if let Some(urls) = req.urls {
+            if scenario.is_some() {
+                return Err(ConfigError::InvalidScenario(
+                    "Give either a scenario or a list of URLs".to_string(),
+                ));
+            }
+            let from_urls = Scenario::from_urls(urls, method.clone());
+            scenario = Some(match req.random_url_order.unwrap_or_default() {
+                true => from_urls.with_random_order(),
+                false => from_urls,
+            });
         }
-
-        if let Some(body) = req.body {
-            config.body = Some(body);
+        if let Some(env) = &req.env {
+            scenario
+                .as_mut()
+                .ok_or_else(|| {
+                    ConfigError::InvalidScenario("An environment needs a scenario".to_string())
+                })?
+                .apply_environment(env)?;
         }
-
-        config
+        if let Some(mix) = &req.mix {
+            scenario
+                .as_mut()
+                .ok_or_else(|| {
+                    ConfigError::InvalidScenario("A mix needs a weighted scenario".to_string())
+                })?
+                .apply_mix(mix)?;
+        }
+        if let Some(field) = req
+            .form
+            .iter()
+            .flat_map(|form| &form.fields)
+            .find(|field| field.file.is_some())
+        {
+            return Err(ConfigError::InvalidForm(format!(
+                "Field '{}' uploads a file, which the API does not accept: it would read the file \
+                 from the host of the API",
+                field.name
+            )));
+        }
+        let config = LoadTestConfig {
+            url: req.url,
+            method,
+            requests: req.requests as usize,
+            concurrency: req.concurrency as usize,
+            timeout: Duration::from_millis(req.timeout_ms.unwrap_or(5000)),
+            headers: req.headers.unwrap_or_default().into_iter().collect(),
+            body: req.body,
+            form: req.form,
+            graphql: req.graphql,
+            scenario,
+            feeder: req.feeder,
+            on_missing_value: req.on_missing_value.unwrap_or_default(),
+            random_params: req.random_params.unwrap_or_default(),
+            labels: req.labels.unwrap_or_default().into_iter().collect(),
+            rate: req.rate,
+            rate_burst: req.rate_burst,
+            max_concurrency: req.max_concurrency,
+            max_rate: req.max_rate,
+            load_model: req.load_model.unwrap_or_default(),
+            correct_coordinated_omission: req.correct_coordinated_omission,
+            faults: req
+                .faults
+                .unwrap_or_default()
+                .iter()
+                .map(|f| f.parse())
+                .collect::<Result<_, _>>()?,
+            stages: req
+                .stages
+                .unwrap_or_default()
+                .iter()
+                .map(|s| s.parse())
+                .collect::<Result<_, _>>()?,
+            record_requests: false,
+            trace: None,
+            deadline: req.deadline_ms.map(Duration::from_millis),
+            cooldown: req.cooldown_ms.map(|ms| Cooldown {
+                duration: Duration::from_millis(ms),
+                probe_url: req.cooldown_probe_url,
+            }),
+            percentiles: req.percentiles.unwrap_or_default(),
+            region: req.region,
+            time_series_interval: req.time_series_interval_ms.map(Duration::from_millis),
+            honor_retry_after: req.honor_retry_after.unwrap_or_default(),
+            header_pacing: req.header_pacing,
+            retry: req.retry.map(RetryPolicy::from),
+            think_time: req.think_time,
+            success_codes: req.success_codes,
+            response_body: req.response_body.unwrap_or_default(),
+            http_version: req.http_version.unwrap_or_default(),
+            tls: req.tls.map(TlsConfig::from).unwrap_or_default(),
+            dns: req.dns,
+            slos: req
+                .slos
+                .unwrap_or_default()
+                .iter()
+                .map(|s| s.parse())
+                .collect::<Result<_, _>>()?,
+            thresholds: req
+                .thresholds
+                .unwrap_or_default()
+                .iter()
+                .map(|s| s.parse())
+                .collect::<Result<_, _>>()?,
+            abort_on: req
+                .abort_on
+                .unwrap_or_default()
+                .iter()
+                .map(|s| s.parse())
+                .collect::<Result<_, _>>()?,
+            advanced: Default::default(),
+        };
+        config.validate()?;
+        Ok(config)
     }
 }
 
@@ -113,6 +634,97 @@ mod tests {
         assert_eq!(request.body, Some("test".to_string()));
     }
 
+    #[test]
+    fn test_load_test_request_into_config() {
+        let request = LoadTestRequest {
+            url: "https://example.com".to_string(),
+            method: "post".to_string(),
+            requests: 100,
+            concurrency: 10,
+            timeout_ms: Some(250),
+            faults: Some(vec!["pause@1s+1s".to_string()]),
+            ..Default::default()
+        };
+        let config = LoadTestConfig::try_from(request).unwrap();
+        assert_eq!(config.method, lode_core::config::HttpMethod::POST);
+        assert_eq!(config.timeout, Duration::from_millis(250));
+        assert_eq!(config.deadline, None);
+        assert_eq!(config.faults.len(), 1);
+
+        let request = LoadTestRequest {
+            url: "https://example.com".to_string(),
+            method: "GET".to_string(),
+            requests: 5,
+            concurrency: 10,
+            ..Default::default()
+        };
+        assert!(matches!(
+            LoadTestConfig::try_from(request).unwrap_err(),
+            ConfigError::InvalidConcurrency(_)
+        ));
+    }
+
+    #[test]
+    fn test_form_file_uploads_refused() {
+        let request = |field: serde_json::Value| -> LoadTestRequest {
+            serde_json::from_value(serde_json::json!({
+                "url": "https://example.com",
+                "method": "POST",
+                "requests": 10,
+                "concurrency": 1,
+                "form": {"encoding": "multipart", "fields": [field]}
+            }))
+            .unwrap()
+        };
+        let text = request(serde_json::json!({"name": "title", "value": "avatar"}));
+        assert!(LoadTestConfig::try_from(text).unwrap().form.is_some());
+        let upload = request(serde_json::json!({"name": "file", "file": "/etc/passwd"}));
+        assert!(matches!(
+            LoadTestConfig::try_from(upload).unwrap_err(),
+            ConfigError::InvalidForm(_)
+        ));
+    }
+
+    #[test]
+    fn test_load_test_request_with_mix() {
+        let request: LoadTestRequest = serde_json::from_str(
+            r#"{
+                "url": "https://example.com",
+                "method": "GET",
+                "requests": 100,
+                "concurrency": 10,
+                "scenario": {"requests": [
+                    {"name": "browse", "url": "https://example.com/products"},
+                    {"name": "checkout", "url": "https://example.com/orders"}
+                ]},
+                "mix": {"browse": 90, "checkout": 10}
+            }"#,
+        )
+        .unwrap();
+        let config = LoadTestConfig::try_from(request).unwrap();
+        let weights: Vec<u32> = config
+            .scenario
+            .unwrap()
+            .requests
+            .iter()
+            .map(|r| r.weight)
+            .collect();
+        assert_eq!(weights, vec![9, 1]);
+
+        let request = LoadTestRequest {
+            url: "https://example.com".to_string(),
+            method: "GET".to_string(),
+            requests: 100,
+            concurrency: 10,
+            mix: Some(WorkloadMix::parse("browse,100").unwrap()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            LoadTestConfig::try_from(request).unwrap_err(),
+            ConfigError::InvalidScenario(_)
+        ));
+    }
+
     #[test]
     fn test_load_test_response_serialization() {
         let response = LoadTestResponse {
@@ -130,6 +742,7 @@ mod tests {
             p99_response_time_ms: 450.0,
             total_duration_seconds: 10.0,
             error_stats: None,
+            ..Default::default()
         };
 
         let json = serde_json::to_string(&response).unwrap();
@@ -0,0 +1,1112 @@
+use chrono::{DateTime, Utc};
+use lode_core::config::LoadTestConfig;
+use lode_core::control::{LoadControl, Overrides};
+use lode_core::live::MetricsSnapshot;
+use lode_core::progress::Progress;
+pub use lode_core::run::RunState;
+use lode_core::run::TestRun;
+use lode_core::trigger::StageTrigger;
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::{watch, RwLock};
+use tokio_util::sync::CancellationToken;
+
+use crate::models::{LoadTestListQuery, LoadTestResponse, LoadTestStatusResponse};
+use crate::storage::{RunQuery, StoredRun};
+use crate::trends::{self, scenario_hash, target_hash, TrendOptions, TrendPoint};
+
+/// Default number of runs returned by a listing
+pub const DEFAULT_PAGE_LIMIT: usize = 20;
+
+/// Maximum number of runs returned by a listing
+pub const MAX_PAGE_LIMIT: usize = 100;
+
+/// A load test run tracked by the API
+///
+/// The [`TestRun`] holds the run's lifecycle: its state, times and error. The record adds what
+/// the API follows the run with while it is in flight, and the report as the API serves it.
+#[derive(Debug, Clone)]
+pub struct RunRecord {
+    pub run: TestRun,
+    pub progress: Progress,
+    /// Stops the run when cancelled
+    pub cancel: CancellationToken,
+    /// Moves the run to its next stage
+    pub trigger: StageTrigger,
+    /// Overrides the load of the run while it is in flight
+    pub control: Option<LoadControl>,
+    /// Latest metrics snapshot published by the engine
+    pub live: Option<watch::Receiver<MetricsSnapshot>>,
+    /// The report of the run with its id, and signature when the API signs reports
+    pub response: Option<LoadTestResponse>,
+    /// The submitted request, saved with the run's report
+    pub request: Option<Value>,
+    /// Name of the API key that submitted the run, when the API requires keys
+    pub owner: Option<String>,
+}
+
+impl RunRecord {
+    /// Create a record for a run waiting for a worker
+    pub fn queued(id: String, config: LoadTestConfig, progress: Progress) -> Self {
+        Self::of(TestRun::with_id(id, config), progress)
+    }
+
+    fn of(run: TestRun, progress: Progress) -> Self {
+        Self {
+            run,
+            progress,
+            cancel: CancellationToken::new(),
+            trigger: StageTrigger::new(),
+            control: None,
+            live: None,
+            response: None,
+            request: None,
+            owner: None,
+        }
+    }
+
+    /// Follow the live metrics of the run
+    pub fn with_live(mut self, live: watch::Receiver<MetricsSnapshot>) -> Self {
+        self.live = Some(live);
+        self
+    }
+
+    /// Keep the submitted request to save with the report
+    pub fn with_request(mut self, request: Value) -> Self {
+        self.request = Some(request);
+        self
+    }
+
+    /// A finished run read back from the store
+    ///
+    /// Only the target and method of its configuration are saved, besides the submitted request.
+    /// Its progress and final metrics snapshot are worked out from its report.
+    pub fn from_stored(run: StoredRun) -> Self {
+        let config = LoadTestConfig {
+            url: run.url,
+            method: run.method.parse().unwrap_or_default(),
+            ..Default::default()
+        };
+        // Runs are only saved once finished
+        let state = match run.status.parse::<RunState>() {
+            Ok(state) if state.is_finished() => state,
+            _ => RunState::Completed,
+        };
+        let restored = TestRun::restored(
+            run.id,
+            config,
+            state,
+            run.started_at,
+            run.finished_at,
+            run.error,
+        )
+        .expect("restored runs are finished");
+        let progress = match &run.report {
+            Some(report) => Progress::finished(
+                report.total_requests,
+                run.config["requests"]
+                    .as_u64()
+                    .unwrap_or_default()
+                    .max(report.total_requests),
+                Duration::try_from_secs_f64(report.total_duration_seconds).unwrap_or_default(),
+            ),
+            None => Progress::new(),
+        };
+        Self {
+            live: run
+                .report
+                .as_ref()
+                .map(|report| watch::channel(final_snapshot(report)).1),
+            response: run.report,
+            request: Some(run.config),
+            owner: run.owner,
+            ..Self::of(restored, progress)
+        }
+    }
+
+    /// The run as saved in the store, once it has finished
+    pub fn to_stored(&self) -> Option<StoredRun> {
+        if !self.status().is_finished() {
+            return None;
+        }
+        Some(StoredRun {
+            id: self.run.id.clone(),
+            status: self.status().as_str().to_string(),
+            url: self.url().to_string(),
+            method: self.method(),
+            started_at: self.started_at(),
+            finished_at: self.run.finished_at,
+            config: self.request.clone().unwrap_or(Value::Null),
+            report: self.response.clone(),
+            error: self.run.error.clone(),
+            owner: self.owner.clone(),
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.run.id
+    }
+
+    pub fn status(&self) -> RunState {
+        self.run.state()
+    }
+
+    pub fn url(&self) -> &str {
+        &self.run.config.url
+    }
+
+    pub fn method(&self) -> String {
+        reqwest::Method::from(self.run.config.method.clone()).to_string()
+    }
+
+    /// When the run started, or was queued while it waits for a worker
+    pub fn started_at(&self) -> DateTime<Utc> {
+        self.run.started_at.unwrap_or(self.run.created_at)
+    }
+
+    /// Whether the API key named `owner` may see the run; every run is visible without a key
+    pub fn visible_to(&self, owner: Option<&str>) -> bool {
+        owner.is_none_or(|owner| self.owner.as_deref() == Some(owner))
+    }
+
+    /// Count the run towards the limits of the API key named `owner`
+    pub fn with_owner(mut self, owner: Option<String>) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    /// Let the load of the run be overridden through `control`
+    pub fn with_control(mut self, control: LoadControl) -> Self {
+        self.control = Some(control);
+        self
+    }
+
+    /// Render the run as a flat JSON object: report fields (once available) and run metadata
+    pub fn to_json(&self) -> Map<String, Value> {
+        let mut object = match self.response.as_ref().map(serde_json::to_value) {
+            Some(Ok(Value::Object(object))) => object,
+            _ => Map::new(),
+        };
+        object.insert("id".to_string(), Value::from(self.id()));
+        object.insert("status".to_string(), Value::from(self.status().as_str()));
+        object.insert("url".to_string(), Value::from(self.url()));
+        object.insert("method".to_string(), Value::from(self.method()));
+        object.insert("target".to_string(), Value::from(target_hash(self.url())));
+        if let Some(scenario) = scenario_hash(self.request.as_ref()) {
+            object.insert("scenario".to_string(), Value::from(scenario));
+        }
+        object.insert(
+            "started_at".to_string(),
+            Value::from(self.started_at().to_rfc3339()),
+        );
+        object.insert(
+            "finished_at".to_string(),
+            self.run
+                .finished_at
+                .map(|t| Value::from(t.to_rfc3339()))
+                .unwrap_or(Value::Null),
+        );
+        object
+    }
+
+    /// Build the status payload of the run, including its live progress
+    pub fn to_status(&self) -> LoadTestStatusResponse {
+        LoadTestStatusResponse {
+            id: self.id().to_string(),
+            status: self.status().as_str().to_string(),
+            url: self.url().to_string(),
+            method: self.method(),
+            started_at: self.started_at(),
+            finished_at: self.run.finished_at,
+            progress: self.progress.snapshot(),
+            metrics: match self.status() {
+                RunState::Pending => None,
+                _ => self.live.as_ref().map(|live| live.borrow().clone()),
+            },
+            overrides: self
+                .control
+                .as_ref()
+                .map(LoadControl::current)
+                .filter(|overrides| *overrides != Overrides::default()),
+            error: self.run.error.clone(),
+        }
+    }
+}
+
+/// The last metrics snapshot of a finished run, from its report
+fn final_snapshot(report: &LoadTestResponse) -> MetricsSnapshot {
+    MetricsSnapshot {
+        elapsed_seconds: report.total_duration_seconds,
+        total_requests: report.total_requests,
+        successful_requests: report.successful_requests,
+        failed_requests: report.failed_requests,
+        current_rps: report.requests_per_second,
+        p95_response_time_ms: report.p95_response_time_ms,
+        error_rate: match report.total_requests {
+            0 => 0.0,
+            total => report.failed_requests as f64 / total as f64 * 100.0,
+        },
+        finished: true,
+        ..Default::default()
+    }
+}
+
+/// Keys a run listing can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    StartedAt,
+    P95,
+    Rps,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "started_at" => Ok(SortKey::StartedAt),
+            "p95" => Ok(SortKey::P95),
+            "rps" => Ok(SortKey::Rps),
+            other => Err(format!(
+                "'{}' is not a supported sort key. Use `started_at`, `p95` or `rps`.",
+                other
+            )),
+        }
+    }
+}
+
+/// Sort direction of a run listing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "asc" => Ok(SortOrder::Asc),
+            "desc" => Ok(SortOrder::Desc),
+            other => Err(format!(
+                "'{}' is not a supported sort order. Use `asc` or `desc`.",
+                other
+            )),
+        }
+    }
+}
+
+/// Filtering, pagination, sorting and field selection for a run listing
+#[derive(Debug, Clone)]
+pub struct ListOptions {
+    pub limit: usize,
+    pub offset: usize,
+    pub sort: SortKey,
+    pub order: SortOrder,
+    pub fields: Option<Vec<String>>,
+    /// Only runs against this URL
+    pub url: Option<String>,
+    pub status: Option<RunState>,
+    /// Only runs started at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only runs started before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Only runs the API key with this name may see
+    pub owner: Option<String>,
+}
+
+impl ListOptions {
+    /// The saved runs that may show up on the page: those up to its end, the runs in memory
+    /// are sorted in with them
+    pub fn to_query(&self) -> RunQuery {
+        RunQuery {
+            url: self.url.clone(),
+            status: self.status,
+            since: self.since,
+            until: self.until,
+            owner: self.owner.clone(),
+            sort: self.sort,
+            order: self.order,
+            limit: self.offset.saturating_add(self.limit),
+            offset: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Whether a run passes the filters
+    fn matches(&self, record: &RunRecord) -> bool {
+        record.visible_to(self.owner.as_deref())
+            && self.url.as_ref().is_none_or(|url| record.url() == url)
+            && self.status.is_none_or(|status| record.status() == status)
+            && self.since.is_none_or(|since| record.started_at() >= since)
+            && self.until.is_none_or(|until| record.started_at() < until)
+    }
+}
+
+impl Default for ListOptions {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_PAGE_LIMIT,
+            offset: 0,
+            sort: SortKey::StartedAt,
+            order: SortOrder::Desc,
+            fields: None,
+            url: None,
+            status: None,
+            since: None,
+            until: None,
+            owner: None,
+        }
+    }
+}
+
+impl TryFrom<LoadTestListQuery> for ListOptions {
+    type Error = String;
+
+    fn try_from(query: LoadTestListQuery) -> Result<Self, Self::Error> {
+        let defaults = Self::default();
+
+        let limit = query.limit.unwrap_or(defaults.limit);
+        if limit == 0 || limit > MAX_PAGE_LIMIT {
+            return Err(format!(
+                "limit must be between 1 and {}, got {}",
+                MAX_PAGE_LIMIT, limit
+            ));
+        }
+
+        let sort = match query.sort {
+            Some(sort) => sort.parse()?,
+            None => defaults.sort,
+        };
+        let order = match query.order {
+            Some(order) => order.parse()?,
+            None => defaults.order,
+        };
+        let fields = query.fields.map(|fields| {
+            fields
+                .split(',')
+                .map(|f| f.trim().to_string())
+                .filter(|f| !f.is_empty())
+                .collect()
+        });
+
+        let time = |name: &str, value: Option<String>| {
+            value
+                .map(|value| {
+                    DateTime::parse_from_rfc3339(&value)
+                        .map(|time| time.with_timezone(&Utc))
+                        .map_err(|e| {
+                            format!("{} must be an RFC 3339 time, got '{}': {}", name, value, e)
+                        })
+                })
+                .transpose()
+        };
+
+        Ok(Self {
+            limit,
+            offset: query.offset.unwrap_or(defaults.offset),
+            sort,
+            order,
+            fields,
+            url: query.url,
+            status: query.status.map(|status| status.parse()).transpose()?,
+            since: time("since", query.since)?,
+            until: time("until", query.until)?,
+            owner: None,
+        })
+    }
+}
+
+/// A single page of a run listing
+#[derive(Debug)]
+pub struct RunPage {
+    pub items: Vec<Map<String, Value>>,
+    pub total: usize,
+}
+
+/// In-memory registry of load test runs
+#[derive(Debug, Default)]
+pub struct RunRegistry {
+    runs: RwLock<HashMap<String, RunRecord>>,
+}
+
+impl RunRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a run, replacing any previous run with the same id
+    pub async fn insert(&self, record: RunRecord) {
+        self.runs
+            .write()
+            .await
+            .insert(record.id().to_string(), record);
+    }
+
+    /// Forget a finished run once it is saved in the store, which serves it from then on
+    pub async fn remove(&self, id: &str) {
+        self.runs.write().await.remove(id);
+    }
+
+    /// Store a new run unless its owner already has `max_running` runs queued or running
+    ///
+    /// Returns the number of runs of the owner in flight when the run is refused.
+    pub async fn insert_within(
+        &self,
+        record: RunRecord,
+        max_running: Option<usize>,
+    ) -> Result<(), usize> {
+        let mut runs = self.runs.write().await;
+        if let (Some(owner), Some(max)) = (&record.owner, max_running) {
+            let running = runs
+                .values()
+                .filter(|run| run.owner.as_ref() == Some(owner) && !run.status().is_finished())
+                .count();
+            if running >= max {
+                return Err(running);
+            }
+        }
+        runs.insert(record.id().to_string(), record);
+        Ok(())
+    }
+
+    /// Get a run by id
+    pub async fn get(&self, id: &str) -> Option<RunRecord> {
+        self.runs.read().await.get(id).cloned()
+    }
+
+    /// Mark a queued run as running from now on, returning the run to execute
+    ///
+    /// `None` when the run is unknown or no longer queued, e.g. cancelled while it waited.
+    pub async fn start(&self, id: &str) -> Option<TestRun> {
+        let mut runs = self.runs.write().await;
+        let record = runs.get_mut(id)?;
+        record.run.start().ok()?;
+        Some(record.run.clone())
+    }
+
+    /// Cancel a queued or running run, returning its status beforehand
+    ///
+    /// A queued run is cancelled right away; a running one once the engine has stopped it.
+    pub async fn cancel(&self, id: &str) -> Option<RunState> {
+        let mut runs = self.runs.write().await;
+        let record = runs.get_mut(id)?;
+        let status = record.status();
+        if !status.is_finished() {
+            record.cancel.cancel();
+        }
+        if status == RunState::Pending {
+            // Only a run that never started is cancelled here
+            let _ = record.run.cancel();
+        }
+        Some(status)
+    }
+
+    /// Move a running run to its next stage, returning its status
+    ///
+    /// Runs that are not running are left as they are.
+    pub async fn advance(&self, id: &str) -> Option<RunState> {
+        let runs = self.runs.read().await;
+        let record = runs.get(id)?;
+        if record.status() == RunState::Running {
+            record.trigger.advance();
+        }
+        Some(record.status())
+    }
+
+    /// Replace a running run with its execution once it has finished, along with its report as
+    /// the API serves it
+    pub async fn finish(&self, run: TestRun, response: Option<LoadTestResponse>) {
+        if let Some(record) = self
+            .runs
+            .write()
+            .await
+            .get_mut(&run.id)
+            .filter(|record| record.status() == RunState::Running && run.state().is_finished())
+        {
+            record.run = run;
+            record.response = response;
+        }
+    }
+
+    /// Latest metrics snapshot of every running run, by id
+    pub async fn live_snapshots(&self) -> Vec<(String, MetricsSnapshot)> {
+        running_snapshots(&*self.runs.read().await)
+    }
+
+    /// Like [`RunRegistry::live_snapshots`] without waiting; empty while a run is being updated
+    ///
+    /// For callers outside of async code, such as metric collection callbacks.
+    pub fn try_live_snapshots(&self) -> Vec<(String, MetricsSnapshot)> {
+        self.runs
+            .try_read()
+            .map(|runs| running_snapshots(&runs))
+            .unwrap_or_default()
+    }
+
+    /// List the runs in memory according to the given options, together with saved ones as
+    /// read from the store with [`ListOptions::to_query`] out of `saved_total` matching runs
+    pub async fn list(
+        &self,
+        options: &ListOptions,
+        saved: Vec<RunRecord>,
+        saved_total: usize,
+    ) -> RunPage {
+        let runs = self.runs.read().await;
+        let mut records: Vec<&RunRecord> = runs
+            .values()
+            .filter(|record| options.matches(record))
+            .collect();
+        // A run may be saved while the listing reads the store, before it is forgotten here
+        let saved_count = saved.len();
+        let saved: Vec<&RunRecord> = saved
+            .iter()
+            .filter(|record| runs.get(record.id()).is_none())
+            .collect();
+        let total = (records.len() + saved_total).saturating_sub(saved_count - saved.len());
+        records.extend(saved);
+
+        records.sort_by(|a, b| {
+            let ordering = compare(a, b, options.sort);
+            match options.order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        });
+
+        let items = records
+            .into_iter()
+            .skip(options.offset)
+            .take(options.limit)
+            .map(|record| select_fields(record.to_json(), options.fields.as_deref()))
+            .collect();
+
+        RunPage { items, total }
+    }
+
+    /// Trend of the completed runs against the target with this hash, with the target's URL
+    ///
+    /// The runs in memory are merged with saved ones, as read from the store with
+    /// [`TrendOptions::to_query`]. `None` when no run was sent to the target.
+    pub async fn trend(
+        &self,
+        target: &str,
+        options: &TrendOptions,
+        saved: Vec<RunRecord>,
+    ) -> Option<(String, Vec<TrendPoint>)> {
+        let runs = self.runs.read().await;
+        let mut records: HashMap<&str, &RunRecord> =
+            saved.iter().map(|record| (record.id(), record)).collect();
+        records.extend(runs.values().map(|record| (record.id(), record)));
+        let url = records
+            .values()
+            .find(|record| {
+                target_hash(record.url()) == target && record.visible_to(options.owner.as_deref())
+            })?
+            .url()
+            .to_string();
+        Some((url, trends::trend(records.into_values(), target, options)))
+    }
+}
+
+fn running_snapshots(runs: &HashMap<String, RunRecord>) -> Vec<(String, MetricsSnapshot)> {
+    let mut snapshots: Vec<_> = runs
+        .values()
+        .filter(|record| record.status() == RunState::Running)
+        .filter_map(|record| {
+            let live = record.live.as_ref()?;
+            Some((record.id().to_string(), live.borrow().clone()))
+        })
+        .collect();
+    snapshots.sort_by(|a, b| a.0.cmp(&b.0));
+    snapshots
+}
+
+fn compare(a: &RunRecord, b: &RunRecord, key: SortKey) -> Ordering {
+    let ordering = match key {
+        SortKey::StartedAt => a.started_at().cmp(&b.started_at()),
+        SortKey::P95 => compare_metric(a, b, |r| r.p95_response_time_ms),
+        SortKey::Rps => compare_metric(a, b, |r| r.requests_per_second),
+    };
+    ordering.then_with(|| a.id().cmp(b.id()))
+}
+
+/// Compare a report metric; runs without a report yet sort before any finished run
+fn compare_metric(a: &RunRecord, b: &RunRecord, metric: fn(&LoadTestResponse) -> f64) -> Ordering {
+    match (a.response.as_ref(), b.response.as_ref()) {
+        (Some(a), Some(b)) => metric(a).total_cmp(&metric(b)),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Keep only the requested fields; the run id is always included
+fn select_fields(object: Map<String, Value>, fields: Option<&[String]>) -> Map<String, Value> {
+    match fields {
+        Some(fields) => object
+            .into_iter()
+            .filter(|(key, _)| key == "id" || fields.iter().any(|f| f == key))
+            .collect(),
+        None => object,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn record(id: &str, minutes_ago: i64, p95: f64, rps: f64) -> RunRecord {
+        let started_at = Utc::now() - Duration::minutes(minutes_ago);
+        let run = TestRun::restored(
+            id.to_string(),
+            config(),
+            RunState::Completed,
+            started_at,
+            Some(started_at + Duration::seconds(10)),
+            None,
+        )
+        .unwrap();
+        RunRecord {
+            response: Some(LoadTestResponse {
+                id: id.to_string(),
+                status: "completed".to_string(),
+                total_requests: 100,
+                successful_requests: 100,
+                failed_requests: 0,
+                requests_per_second: rps,
+                min_response_time_ms: 1.0,
+                max_response_time_ms: 500.0,
+                mean_response_time_ms: 200.0,
+                median_response_time_ms: 180.0,
+                p95_response_time_ms: p95,
+                p99_response_time_ms: 450.0,
+                total_duration_seconds: 10.0,
+                error_stats: None,
+                ..Default::default()
+            }),
+            ..RunRecord::of(run, Progress::new())
+        }
+    }
+
+    fn queued(id: &str) -> RunRecord {
+        RunRecord::queued(id.to_string(), config(), Progress::new())
+    }
+
+    fn running(id: &str, progress: Progress) -> RunRecord {
+        let mut record = RunRecord::queued(id.to_string(), config(), progress);
+        record.run.start().unwrap();
+        record
+    }
+
+    fn config() -> LoadTestConfig {
+        LoadTestConfig::new(
+            "http://example.com".to_string(),
+            Default::default(),
+            100,
+            10,
+            std::time::Duration::from_secs(5),
+        )
+        .unwrap()
+    }
+
+    async fn registry() -> RunRegistry {
+        let registry = RunRegistry::new();
+        registry.insert(record("a", 30, 300.0, 50.0)).await;
+        registry.insert(record("b", 20, 100.0, 150.0)).await;
+        registry.insert(record("c", 10, 200.0, 100.0)).await;
+        registry
+    }
+
+    fn ids(page: &RunPage) -> Vec<&str> {
+        page.items
+            .iter()
+            .map(|item| item["id"].as_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_try_live_snapshots_of_running_runs() {
+        let registry = registry().await;
+        let (_sender, receiver) = watch::channel(MetricsSnapshot {
+            total_requests: 7,
+            ..Default::default()
+        });
+        registry
+            .insert(running("d", Progress::new()).with_live(receiver))
+            .await;
+
+        let snapshots = registry.try_live_snapshots();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].0, "d");
+        assert_eq!(snapshots[0].1.total_requests, 7);
+
+        let _writer = registry.runs.write().await;
+        assert!(registry.try_live_snapshots().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_insert_within_limit_of_owner() {
+        let registry = RunRegistry::new();
+        let queued = |id: &str, owner: &str| queued(id).with_owner(Some(owner.to_string()));
+        assert_eq!(
+            registry.insert_within(queued("a", "ci"), Some(1)).await,
+            Ok(())
+        );
+        assert_eq!(
+            registry.insert_within(queued("b", "ci"), Some(1)).await,
+            Err(1)
+        );
+        assert_eq!(
+            registry.insert_within(queued("c", "qa"), Some(1)).await,
+            Ok(())
+        );
+        assert_eq!(
+            registry.insert_within(queued("d", "ci"), None).await,
+            Ok(())
+        );
+
+        // Finished runs no longer count
+        for id in ["a", "d"] {
+            let mut run = registry.start(id).await.unwrap();
+            run.fail("connection refused").unwrap();
+            registry.finish(run, None).await;
+        }
+        assert_eq!(
+            registry.insert_within(queued("b", "ci"), Some(1)).await,
+            Ok(())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_defaults_to_newest_first() {
+        let registry = registry().await;
+        let page = registry.list(&ListOptions::default(), Vec::new(), 0).await;
+        assert_eq!(page.total, 3);
+        assert_eq!(ids(&page), vec!["c", "b", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_sorting() {
+        let registry = registry().await;
+
+        let options = ListOptions {
+            sort: SortKey::P95,
+            order: SortOrder::Asc,
+            ..Default::default()
+        };
+        assert_eq!(
+            ids(&registry.list(&options, Vec::new(), 0).await),
+            vec!["b", "c", "a"]
+        );
+
+        let options = ListOptions {
+            sort: SortKey::Rps,
+            order: SortOrder::Desc,
+            ..Default::default()
+        };
+        assert_eq!(
+            ids(&registry.list(&options, Vec::new(), 0).await),
+            vec!["b", "c", "a"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_pagination() {
+        let registry = registry().await;
+        let options = ListOptions {
+            limit: 2,
+            offset: 1,
+            ..Default::default()
+        };
+        let page = registry.list(&options, Vec::new(), 0).await;
+        assert_eq!(page.total, 3);
+        assert_eq!(ids(&page), vec!["b", "a"]);
+
+        let options = ListOptions {
+            offset: 5,
+            ..Default::default()
+        };
+        assert!(registry
+            .list(&options, Vec::new(), 0)
+            .await
+            .items
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_field_selection() {
+        let registry = registry().await;
+        let options = ListOptions {
+            fields: Some(vec!["p95_response_time_ms".to_string()]),
+            ..Default::default()
+        };
+        let page = registry.list(&options, Vec::new(), 0).await;
+        let keys: Vec<&String> = page.items[0].keys().collect();
+        assert_eq!(keys.len(), 2);
+        assert!(page.items[0].contains_key("id"));
+        assert!(page.items[0].contains_key("p95_response_time_ms"));
+    }
+
+    #[tokio::test]
+    async fn test_list_filters() {
+        let registry = registry().await;
+        let mut other = record("d", 5, 100.0, 10.0);
+        other.run = TestRun::restored(
+            "d".to_string(),
+            LoadTestConfig {
+                url: "http://example.com/other".to_string(),
+                ..config()
+            },
+            RunState::Cancelled,
+            other.started_at(),
+            None,
+            None,
+        )
+        .unwrap();
+        registry.insert(other).await;
+
+        let options = ListOptions {
+            url: Some("http://example.com".to_string()),
+            ..Default::default()
+        };
+        let page = registry.list(&options, Vec::new(), 0).await;
+        assert_eq!(page.total, 3);
+        assert_eq!(ids(&page), vec!["c", "b", "a"]);
+
+        let options = ListOptions {
+            status: Some(RunState::Cancelled),
+            ..Default::default()
+        };
+        assert_eq!(
+            ids(&registry.list(&options, Vec::new(), 0).await),
+            vec!["d"]
+        );
+
+        let options = ListOptions {
+            since: Some(Utc::now() - Duration::minutes(25)),
+            until: Some(Utc::now() - Duration::minutes(7)),
+            ..Default::default()
+        };
+        let page = registry.list(&options, Vec::new(), 0).await;
+        assert_eq!(page.total, 2);
+        assert_eq!(ids(&page), vec!["c", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_stored_run_round_trip() {
+        let run = record("a", 30, 300.0, 50.0)
+            .with_request(serde_json::json!({"requests": 100}))
+            .to_stored()
+            .unwrap();
+        assert_eq!(run.status, "completed");
+        let completed = RunRecord::from_stored(run);
+        assert_eq!(completed.status(), RunState::Completed);
+        assert_eq!(completed.response.unwrap().p95_response_time_ms, 300.0);
+        assert_eq!(completed.request.unwrap()["requests"], 100);
+
+        // Failed runs are saved with their error, unfinished ones are not saved
+        let mut failed = running("f", Progress::new());
+        failed.run.fail("connection refused").unwrap();
+        let failed = RunRecord::from_stored(failed.to_stored().unwrap());
+        assert_eq!(failed.status(), RunState::Failed);
+        assert_eq!(failed.url(), "http://example.com");
+        assert_eq!(failed.method(), "GET");
+        assert_eq!(failed.run.error.as_deref(), Some("connection refused"));
+        assert!(queued("q").to_stored().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_list_merges_saved_runs() {
+        let registry = RunRegistry::new();
+        registry.insert(record("a", 10, 100.0, 50.0)).await;
+        registry.insert(record("b", 30, 200.0, 40.0)).await;
+        registry.insert(record("d", 40, 400.0, 20.0)).await;
+        registry.remove("d").await;
+        let options = ListOptions {
+            limit: 2,
+            offset: 1,
+            ..Default::default()
+        };
+        assert_eq!(options.to_query().limit, 3);
+
+        // "b" is saved and not forgotten yet, it is listed and counted once
+        let saved = vec![record("b", 30, 200.0, 40.0), record("c", 20, 300.0, 30.0)];
+        let page = registry.list(&options, saved, 5).await;
+        assert_eq!(page.total, 6);
+        assert_eq!(ids(&page), vec!["c", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_running_runs_sort_before_finished_ones() {
+        let registry = registry().await;
+        let progress = Progress::new();
+        progress.start(10);
+        registry.insert(running("d", progress)).await;
+
+        let options = ListOptions {
+            sort: SortKey::P95,
+            order: SortOrder::Asc,
+            ..Default::default()
+        };
+        let page = registry.list(&options, Vec::new(), 0).await;
+        assert_eq!(ids(&page), vec!["d", "b", "c", "a"]);
+        assert_eq!(page.items[0]["status"], "running");
+        assert!(!page.items[0].contains_key("p95_response_time_ms"));
+    }
+
+    #[tokio::test]
+    async fn test_finish_run() {
+        let registry = RunRegistry::new();
+        let progress = Progress::new();
+        progress.start(1);
+        registry
+            .insert(RunRecord::queued(
+                "run".to_string(),
+                config(),
+                progress.clone(),
+            ))
+            .await;
+        let mut run = registry.start("run").await.unwrap();
+
+        let status = registry.get("run").await.unwrap().to_status();
+        assert_eq!(status.status, "running");
+        assert!(status.finished_at.is_none());
+        assert_eq!(status.progress.total_requests, 1);
+
+        progress.record();
+        progress.finish();
+        run.fail("Connection refused").unwrap();
+        registry.finish(run, None).await;
+
+        let status = registry.get("run").await.unwrap().to_status();
+        assert_eq!(status.status, "failed");
+        assert!(status.finished_at.is_some());
+        assert_eq!(status.error.as_deref(), Some("Connection refused"));
+        assert_eq!(status.progress.completed_requests, 1);
+        assert!(registry.get("missing").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_queued_run_starts() {
+        let registry = RunRegistry::new();
+        let (_live, snapshots) =
+            lode_core::live::LiveMetrics::channel(lode_core::live::DEFAULT_SNAPSHOT_INTERVAL);
+        registry.insert(queued("job").with_live(snapshots)).await;
+
+        let status = registry.get("job").await.unwrap().to_status();
+        assert_eq!(status.status, "queued");
+        assert!(status.metrics.is_none());
+
+        // A run that never started cannot finish
+        let mut cancelled = queued("job").run;
+        cancelled.cancel().unwrap();
+        registry.finish(cancelled, None).await;
+        assert_eq!(
+            registry.get("job").await.unwrap().status(),
+            RunState::Pending
+        );
+
+        registry.start("job").await.unwrap();
+        assert!(registry.start("job").await.is_none());
+        let status = registry.get("job").await.unwrap().to_status();
+        assert_eq!(status.status, "running");
+        assert_eq!(status.metrics, Some(MetricsSnapshot::default()));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_run() {
+        let registry = RunRegistry::new();
+        registry.insert(queued("queued")).await;
+        registry.insert(queued("running")).await;
+        registry.start("running").await;
+        registry.insert(record("done", 1, 100.0, 10.0)).await;
+
+        assert_eq!(registry.cancel("queued").await, Some(RunState::Pending));
+        let record = registry.get("queued").await.unwrap();
+        assert_eq!(record.status(), RunState::Cancelled);
+        assert!(record.cancel.is_cancelled());
+        assert!(record.run.finished_at.is_some());
+
+        // A running run stays running until the engine stops
+        assert_eq!(registry.cancel("running").await, Some(RunState::Running));
+        let record = registry.get("running").await.unwrap();
+        assert_eq!(record.status(), RunState::Running);
+        assert!(record.cancel.is_cancelled());
+
+        assert_eq!(registry.cancel("done").await, Some(RunState::Completed));
+        assert!(!registry.get("done").await.unwrap().cancel.is_cancelled());
+        assert_eq!(registry.cancel("unknown").await, None);
+    }
+
+    #[test]
+    fn test_list_options_from_query() {
+        let options = ListOptions::try_from(LoadTestListQuery {
+            limit: Some(5),
+            offset: Some(10),
+            sort: Some("rps".to_string()),
+            order: Some("asc".to_string()),
+            fields: Some("status, p95_response_time_ms,".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(options.limit, 5);
+        assert_eq!(options.offset, 10);
+        assert_eq!(options.sort, SortKey::Rps);
+        assert_eq!(options.order, SortOrder::Asc);
+        assert_eq!(
+            options.fields.unwrap(),
+            vec!["status".to_string(), "p95_response_time_ms".to_string()]
+        );
+
+        let too_large = LoadTestListQuery {
+            limit: Some(MAX_PAGE_LIMIT + 1),
+            ..Default::default()
+        };
+        assert!(ListOptions::try_from(too_large).is_err());
+
+        let bad_sort = LoadTestListQuery {
+            sort: Some("latency".to_string()),
+            ..Default::default()
+        };
+        assert!(ListOptions::try_from(bad_sort).is_err());
+
+        let filtered = ListOptions::try_from(LoadTestListQuery {
+            status: Some("completed".to_string()),
+            since: Some("2025-03-30T00:00:00Z".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(filtered.status, Some(RunState::Completed));
+        assert_eq!(
+            filtered.since.unwrap().to_rfc3339(),
+            "2025-03-30T00:00:00+00:00"
+        );
+        for bad_filter in [
+            LoadTestListQuery {
+                status: Some("done".to_string()),
+                ..Default::default()
+            },
+            LoadTestListQuery {
+                until: Some("yesterday".to_string()),
+                ..Default::default()
+            },
+        ] {
+            assert!(ListOptions::try_from(bad_filter).is_err());
+        }
+    }
+
+    #[test]
+    fn test_sort_parsing() {
+        assert_eq!("p95".parse::<SortKey>().unwrap(), SortKey::P95);
+        assert_eq!("RPS".parse::<SortKey>().unwrap(), SortKey::Rps);
+        assert!("latency".parse::<SortKey>().is_err());
+        assert_eq!("asc".parse::<SortOrder>().unwrap(), SortOrder::Asc);
+        assert!("up".parse::<SortOrder>().is_err());
+    }
+}
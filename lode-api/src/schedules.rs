@@ -0,0 +1,464 @@
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime, NaiveTime, Utc, Weekday};
+use lode_core::config::parse_duration;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use crate::auth::ApiKey;
+use crate::models::{LoadTestRequest, ScheduleRequest, ScheduleResponse};
+
+/// Shortest interval between two runs of a schedule
+pub const MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Longest interval between two runs of a schedule, and how long ago its first run may be
+pub const MAX_INTERVAL: Duration = Duration::from_secs(365 * 24 * 3600);
+
+/// Events kept in the history of a schedule; the oldest are dropped first
+pub const HISTORY_LIMIT: usize = 100;
+
+/// How often the API looks for schedules that are due
+pub const SCHEDULER_TICK: Duration = Duration::from_secs(1);
+
+/// A time of day recurring on some days of the week, in the time zone of its schedule
+///
+/// A window ending before it starts runs past midnight, e.g. 22:00 to 06:00; one that ends when it
+/// starts covers the whole day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    /// Days the window opens on, every day when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Weekday>,
+    /// Start of the window, e.g. `09:00`
+    pub start: NaiveTime,
+    /// End of the window, excluded, e.g. `17:00`
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    /// Whether the local time `at` falls in the window
+    pub fn contains(&self, at: NaiveDateTime) -> bool {
+        let time = at.time();
+        if self.start == self.end {
+            self.opens_on(at.weekday())
+        } else if self.start < self.end {
+            self.opens_on(at.weekday()) && self.start <= time && time < self.end
+        } else if self.start <= time {
+            self.opens_on(at.weekday())
+        } else {
+            // After midnight, in the window opened the day before
+            time < self.end && self.opens_on(at.weekday().pred())
+        }
+    }
+
+    fn opens_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )?;
+        if !self.days.is_empty() {
+            let days: Vec<String> = self.days.iter().map(Weekday::to_string).collect();
+            write!(f, " on {}", days.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// What happened when a schedule was due
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleEventKind {
+    /// A run was queued
+    Started,
+    /// The time was outside the allowed windows or in a blackout, no run was queued
+    Skipped,
+    /// The run was refused, e.g. its target is no longer allowed or its key is over its quota
+    Refused,
+}
+
+/// An entry of the history of a schedule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleEvent {
+    pub at: DateTime<Utc>,
+    pub kind: ScheduleEventKind,
+    /// Run queued by a `started` event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    /// Why the run was skipped or refused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl ScheduleEvent {
+    pub fn started(at: DateTime<Utc>, run_id: String) -> Self {
+        Self {
+            at,
+            kind: ScheduleEventKind::Started,
+            run_id: Some(run_id),
+            reason: None,
+        }
+    }
+
+    pub fn skipped(at: DateTime<Utc>, reason: String) -> Self {
+        Self {
+            at,
+            kind: ScheduleEventKind::Skipped,
+            run_id: None,
+            reason: Some(reason),
+        }
+    }
+
+    pub fn refused(at: DateTime<Utc>, reason: String) -> Self {
+        Self {
+            at,
+            kind: ScheduleEventKind::Refused,
+            run_id: None,
+            reason: Some(reason),
+        }
+    }
+}
+
+/// A load test the API runs at a fixed interval, within its allowed windows and outside its
+/// blackouts
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub id: String,
+    /// The load test queued at every run
+    pub request: LoadTestRequest,
+    pub every: Duration,
+    /// Times runs are allowed at, any time when empty
+    pub windows: Vec<TimeWindow>,
+    /// Times no run may start at, even within a window
+    pub blackouts: Vec<TimeWindow>,
+    /// Time zone of the windows and blackouts
+    pub utc_offset: FixedOffset,
+    pub created_at: DateTime<Utc>,
+    pub next_run_at: DateTime<Utc>,
+    /// Key that created the schedule, whose limits apply to its runs
+    pub owner: Option<Arc<ApiKey>>,
+    /// Latest events, the oldest first
+    pub history: VecDeque<ScheduleEvent>,
+}
+
+impl Schedule {
+    /// A schedule of `request`, with its first run at its `start_at` or right away
+    pub fn new(
+        id: String,
+        request: ScheduleRequest,
+        owner: Option<Arc<ApiKey>>,
+    ) -> Result<Self, String> {
+        let every = parse_duration(&request.every).map_err(|e| e.to_string())?;
+        if every < MIN_INTERVAL {
+            return Err(format!(
+                "A schedule runs at most every {}s, '{}' is too often",
+                MIN_INTERVAL.as_secs(),
+                request.every
+            ));
+        }
+        if every > MAX_INTERVAL {
+            return Err(format!(
+                "A schedule runs at least every {} days, '{}' is too seldom",
+                MAX_INTERVAL.as_secs() / (24 * 3600),
+                request.every
+            ));
+        }
+        let utc_offset = match &request.utc_offset {
+            Some(offset) => offset
+                .parse()
+                .map_err(|_| format!("Invalid UTC offset '{}', expected e.g. +02:00", offset))?,
+            None => FixedOffset::east_opt(0).expect("UTC is a valid offset"),
+        };
+        let now = Utc::now();
+        let next_run_at = request.start_at.unwrap_or(now);
+        if (now - next_run_at)
+            .to_std()
+            .is_ok_and(|ago| ago > MAX_INTERVAL)
+        {
+            return Err(format!(
+                "The first run is at most {} days ago, {} is too far in the past",
+                MAX_INTERVAL.as_secs() / (24 * 3600),
+                next_run_at
+            ));
+        }
+        Ok(Self {
+            id,
+            request: request.load_test,
+            every,
+            windows: request.windows,
+            blackouts: request.blackouts,
+            utc_offset,
+            created_at: now,
+            next_run_at,
+            owner,
+            history: VecDeque::new(),
+        })
+    }
+
+    /// Why no run may start at `at`, if it may not
+    pub fn blocked_at(&self, at: DateTime<Utc>) -> Option<String> {
+        let local = at.with_timezone(&self.utc_offset).naive_local();
+        if let Some(blackout) = self.blackouts.iter().find(|b| b.contains(local)) {
+            return Some(format!("In the blackout {}", blackout));
+        }
+        if !self.windows.is_empty() && !self.windows.iter().any(|w| w.contains(local)) {
+            return Some("Outside the allowed windows".to_string());
+        }
+        None
+    }
+
+    /// Move the next run past `now`, skipping the runs missed meanwhile
+    pub fn advance(&mut self, now: DateTime<Utc>) {
+        let Ok(behind) = (now - self.next_run_at).to_std() else {
+            return;
+        };
+        // Every period started since the run was due is skipped at once
+        let every = self.every.as_millis().max(1);
+        let periods = behind.as_millis() / every + 1;
+        self.next_run_at = i64::try_from(periods * every)
+            .ok()
+            .and_then(chrono::Duration::try_milliseconds)
+            .and_then(|ahead| self.next_run_at.checked_add_signed(ahead))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+    }
+
+    /// Add an event to the history, dropping the oldest beyond [`HISTORY_LIMIT`]
+    pub fn record(&mut self, event: ScheduleEvent) {
+        if self.history.len() == HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+        self.history.push_back(event);
+    }
+
+    pub fn to_response(&self) -> ScheduleResponse {
+        ScheduleResponse {
+            id: self.id.clone(),
+            url: self.request.url.clone(),
+            method: self.request.method.clone(),
+            every_seconds: self.every.as_secs(),
+            windows: self.windows.clone(),
+            blackouts: self.blackouts.clone(),
+            utc_offset: self.utc_offset.to_string(),
+            created_at: self.created_at,
+            next_run_at: self.next_run_at,
+            history: self.history.iter().cloned().collect(),
+        }
+    }
+
+    fn owned_by(&self, owner: Option<&str>) -> bool {
+        owner.is_none_or(|owner| self.owner.as_ref().is_some_and(|key| key.name == owner))
+    }
+}
+
+/// In-memory registry of the schedules; they are lost when the API restarts
+#[derive(Debug, Default)]
+pub struct ScheduleRegistry {
+    schedules: RwLock<HashMap<String, Schedule>>,
+}
+
+impl ScheduleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn insert(&self, schedule: Schedule) {
+        self.schedules
+            .write()
+            .await
+            .insert(schedule.id.clone(), schedule);
+    }
+
+    /// Get a schedule by id, if `owner` may see it; every schedule is visible without an owner
+    pub async fn get(&self, id: &str, owner: Option<&str>) -> Option<Schedule> {
+        self.schedules
+            .read()
+            .await
+            .get(id)
+            .filter(|schedule| schedule.owned_by(owner))
+            .cloned()
+    }
+
+    /// Schedules `owner` may see, the oldest first
+    pub async fn list(&self, owner: Option<&str>) -> Vec<Schedule> {
+        let mut schedules: Vec<Schedule> = self
+            .schedules
+            .read()
+            .await
+            .values()
+            .filter(|schedule| schedule.owned_by(owner))
+            .cloned()
+            .collect();
+        schedules.sort_by_key(|schedule| schedule.created_at);
+        schedules
+    }
+
+    /// Delete a schedule `owner` may see; returns whether there was one
+    pub async fn remove(&self, id: &str, owner: Option<&str>) -> bool {
+        let mut schedules = self.schedules.write().await;
+        if schedules.get(id).is_some_and(|s| s.owned_by(owner)) {
+            schedules.remove(id).is_some()
+        } else {
+            false
+        }
+    }
+
+    /// The schedules due at `now`, their next run already moved past it
+    pub async fn take_due(&self, now: DateTime<Utc>) -> Vec<Schedule> {
+        let mut schedules = self.schedules.write().await;
+        schedules
+            .values_mut()
+            .filter(|schedule| schedule.next_run_at <= now)
+            .map(|schedule| {
+                let due = schedule.clone();
+                schedule.advance(now);
+                due
+            })
+            .collect()
+    }
+
+    /// Add an event to the history of a schedule, unless it was deleted meanwhile
+    pub async fn record(&self, id: &str, event: ScheduleEvent) {
+        if let Some(schedule) = self.schedules.write().await.get_mut(id) {
+            schedule.record(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        // 2026-01-05 is a Monday
+        Utc.with_ymd_and_hms(2026, 1, day, hour, minute, 0).unwrap()
+    }
+
+    fn window(days: &[Weekday], start: &str, end: &str) -> TimeWindow {
+        TimeWindow {
+            days: days.to_vec(),
+            start: start.parse().unwrap(),
+            end: end.parse().unwrap(),
+        }
+    }
+
+    fn schedule(windows: Vec<TimeWindow>, blackouts: Vec<TimeWindow>) -> Schedule {
+        let request = ScheduleRequest {
+            load_test: LoadTestRequest::default(),
+            every: "1h".to_string(),
+            windows,
+            blackouts,
+            utc_offset: None,
+            start_at: None,
+        };
+        let mut schedule = Schedule::new("nightly".to_string(), request, None).unwrap();
+        schedule.next_run_at = at(5, 0, 0);
+        schedule
+    }
+
+    #[test]
+    fn test_time_windows() {
+        let business_hours = window(&[Weekday::Mon, Weekday::Fri], "09:00", "17:00");
+        assert!(business_hours.contains(at(5, 9, 0).naive_utc()));
+        assert!(!business_hours.contains(at(5, 17, 0).naive_utc()));
+        assert!(!business_hours.contains(at(6, 12, 0).naive_utc()));
+        assert_eq!(business_hours.to_string(), "09:00-17:00 on Mon, Fri");
+
+        // Friday night runs into Saturday morning, not Monday's into Tuesday's
+        let nights = window(&[Weekday::Fri], "22:00", "06:00");
+        assert!(nights.contains(at(9, 23, 0).naive_utc()));
+        assert!(nights.contains(at(10, 5, 59).naive_utc()));
+        assert!(!nights.contains(at(10, 6, 0).naive_utc()));
+        assert!(!nights.contains(at(6, 1, 0).naive_utc()));
+
+        let weekend = window(&[Weekday::Sat, Weekday::Sun], "00:00", "00:00");
+        assert!(weekend.contains(at(11, 13, 0).naive_utc()));
+        assert!(!weekend.contains(at(12, 0, 0).naive_utc()));
+    }
+
+    #[test]
+    fn test_blackouts_win_over_windows() {
+        let schedule = schedule(
+            vec![window(&[], "08:00", "20:00")],
+            vec![window(&[Weekday::Mon], "12:00", "13:00")],
+        );
+        assert_eq!(schedule.blocked_at(at(5, 9, 0)), None);
+        assert_eq!(
+            schedule.blocked_at(at(5, 12, 30)).as_deref(),
+            Some("In the blackout 12:00-13:00 on Mon")
+        );
+        assert_eq!(schedule.blocked_at(at(6, 12, 30)), None);
+        assert_eq!(
+            schedule.blocked_at(at(5, 21, 0)).as_deref(),
+            Some("Outside the allowed windows")
+        );
+
+        // The windows are in the time zone of the schedule
+        let mut schedule = schedule;
+        schedule.utc_offset = "+02:00".parse().unwrap();
+        assert!(schedule.blocked_at(at(5, 10, 30)).is_some());
+        assert_eq!(schedule.blocked_at(at(5, 12, 30)), None);
+    }
+
+    #[test]
+    fn test_missed_runs_are_not_made_up_for() {
+        let mut schedule = schedule(Vec::new(), Vec::new());
+        schedule.advance(at(5, 5, 30));
+        assert_eq!(schedule.next_run_at, at(5, 6, 0));
+        schedule.advance(at(5, 7, 0));
+        assert_eq!(schedule.next_run_at, at(5, 8, 0));
+        schedule.advance(at(5, 7, 30));
+        assert_eq!(schedule.next_run_at, at(5, 8, 0));
+
+        // Centuries of missed runs are skipped in one go, and the end of time is never passed
+        schedule.every = MIN_INTERVAL;
+        schedule.next_run_at = Utc.with_ymd_and_hms(1, 1, 1, 0, 0, 0).unwrap();
+        schedule.advance(at(5, 7, 30));
+        assert_eq!(schedule.next_run_at, at(5, 7, 31));
+        schedule.every = Duration::MAX;
+        schedule.advance(at(5, 7, 31));
+        assert_eq!(schedule.next_run_at, DateTime::<Utc>::MAX_UTC);
+
+        for minute in 0..=HISTORY_LIMIT as u32 {
+            schedule.record(ScheduleEvent::skipped(at(5, 6, 0), minute.to_string()));
+        }
+        assert_eq!(schedule.history.len(), HISTORY_LIMIT);
+        assert_eq!(schedule.history[0].reason.as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn test_schedule_interval() {
+        let mut request = ScheduleRequest {
+            load_test: LoadTestRequest::default(),
+            every: "10s".to_string(),
+            windows: Vec::new(),
+            blackouts: Vec::new(),
+            utc_offset: None,
+            start_at: None,
+        };
+        assert!(Schedule::new("a".to_string(), request.clone(), None).is_err());
+        request.every = "9000000000h".to_string();
+        assert!(Schedule::new("a".to_string(), request.clone(), None).is_err());
+        request.every = "15m".to_string();
+        request.start_at = Some(Utc.with_ymd_and_hms(1, 1, 1, 0, 0, 0).unwrap());
+        assert!(Schedule::new("a".to_string(), request.clone(), None).is_err());
+        request.start_at = Some(Utc::now() - chrono::Duration::days(1));
+        assert!(Schedule::new("a".to_string(), request.clone(), None).is_ok());
+        request.start_at = None;
+        request.utc_offset = Some("Europe/Paris".to_string());
+        assert!(Schedule::new("a".to_string(), request.clone(), None).is_err());
+        request.utc_offset = Some("-05:00".to_string());
+        let schedule = Schedule::new("a".to_string(), request, None).unwrap();
+        assert_eq!(schedule.every, Duration::from_secs(900));
+        assert_eq!(schedule.to_response().utc_offset, "-05:00");
+    }
+}
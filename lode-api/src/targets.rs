@@ -0,0 +1,365 @@
+use lode_core::config::{LoadTestConfig, Stage};
+use lode_core::dns::{DnsConfig, HickoryResolver};
+use lode_core::http::{
+    DefaultHttpClient, DefaultHttpClientBuilder, HttpClientOptions, SystemResolver,
+};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use url::{Host, Url};
+
+/// Redirects followed at most, as by reqwest's default policy
+const MAX_REDIRECTS: usize = 10;
+
+/// Hosts the load tests of the API may target, so it cannot be used to flood internal services
+/// or arbitrary third parties
+///
+/// A host matches a pattern when it equals it or, for a `*.example.com` pattern, is a subdomain
+/// of `example.com`. Denied hosts are refused first; with an allowlist only the hosts on it are
+/// accepted. Localhost and loopback, private and link-local addresses are refused unless
+/// `allow_private` is set, both in the URLs and among the addresses their hosts resolve to, when
+/// the run is submitted and again whenever a client of the policy connects.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct TargetPolicy {
+    /// Hosts load tests may target; any host without any
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Hosts load tests may never target
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Allow localhost and private addresses, e.g. for an agent testing services of its own network
+    #[serde(default)]
+    pub allow_private: bool,
+}
+
+impl TargetPolicy {
+    /// Check the hosts of the URLs of a run without resolving them
+    pub fn check_config(&self, config: &LoadTestConfig) -> Result<(), String> {
+        for url in target_urls(config) {
+            self.check_url(url)?;
+        }
+        if !self.allow_private
+            && config
+                .dns
+                .as_ref()
+                .is_some_and(|dns| !dns.nameservers.is_empty())
+        {
+            return Err(
+                "Custom nameservers are not allowed, their answers could point at private addresses"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Check the hosts of the URLs of a run, then the addresses they resolve to
+    ///
+    /// Hosts that do not resolve are refused. A host may resolve to other addresses later on, so
+    /// the clients of [`TargetPolicy::client_builder`] and [`TargetPolicy::probe_client`] check the
+    /// addresses again whenever they connect.
+    pub async fn check(&self, config: &LoadTestConfig) -> Result<(), String> {
+        self.check_config(config)?;
+        if self.allow_private {
+            return Ok(());
+        }
+        for url in target_urls(config) {
+            let Ok(url) = Url::parse(url) else {
+                continue;
+            };
+            let (Some(Host::Domain(domain)), Some(port)) =
+                (url.host(), url.port_or_known_default())
+            else {
+                continue;
+            };
+            let addresses = tokio::net::lookup_host((domain, port))
+                .await
+                .map_err(|e| format!("{} does not resolve: {}", domain, e))?;
+            for address in addresses {
+                if is_private(address.ip()) {
+                    return Err(format!(
+                        "{} resolves to the private address {}",
+                        domain,
+                        address.ip()
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the host of a URL against the patterns and, unless allowed, the private addresses
+    pub fn check_url(&self, url: &str) -> Result<(), String> {
+        let parsed =
+            Url::parse(url).map_err(|_| format!("{} does not name a host to check", url))?;
+        let Some(host) = parsed.host() else {
+            return Err(format!("{} does not name a host to check", url));
+        };
+        // A host filled in by a placeholder is only known when the request is sent
+        let name = match &host {
+            Host::Domain(domain) if domain.contains("%7B%7B") || domain.contains("{{") => {
+                return Err(format!("The host of {} cannot be a placeholder", url));
+            }
+            Host::Domain(domain) => domain.trim_end_matches('.').to_lowercase(),
+            Host::Ipv4(ip) => ip.to_string(),
+            Host::Ipv6(ip) => ip.to_string(),
+        };
+        if self.deny.iter().any(|pattern| matches(pattern, &name)) {
+            return Err(format!("{} is denied", name));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|pattern| matches(pattern, &name)) {
+            return Err(format!("{} is not on the allowlist", name));
+        }
+        if self.allow_private {
+            return Ok(());
+        }
+        let private = match host {
+            Host::Domain(_) => name == "localhost" || name.ends_with(".localhost"),
+            Host::Ipv4(ip) => is_private(IpAddr::V4(ip)),
+            Host::Ipv6(ip) => is_private(IpAddr::V6(ip)),
+        };
+        match private {
+            true => Err(format!("{} is a private address", name)),
+            false => Ok(()),
+        }
+    }
+
+    /// Redirect policy following up to 10 redirects, to the hosts the policy accepts only
+    pub fn redirect_policy(self: &Arc<Self>) -> reqwest::redirect::Policy {
+        let policy = Arc::clone(self);
+        reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if let Err(reason) = policy.check_url(attempt.url().as_str()) {
+                attempt.error(format!("redirect refused: {}", reason))
+            } else {
+                attempt.follow()
+            }
+        })
+    }
+
+    /// Resolver looking host names up through `dns`, or else the system's resolver, failing the
+    /// lookups that return a private address unless the policy allows them
+    pub fn resolver(
+        &self,
+        dns: Option<&DnsConfig>,
+    ) -> Result<Arc<GuardedResolver>, lode_core::Error> {
+        let inner: Arc<dyn Resolve> = match dns {
+            Some(dns) => Arc::new(HickoryResolver::new(dns)?),
+            None => Arc::new(SystemResolver),
+        };
+        Ok(Arc::new(GuardedResolver {
+            inner,
+            allow_private: self.allow_private,
+        }))
+    }
+
+    /// Builder of a client sending the requests of runs with `options`, whose redirects and
+    /// connections only go to the hosts and addresses the policy accepts
+    pub fn client_builder(
+        self: &Arc<Self>,
+        options: HttpClientOptions,
+    ) -> Result<DefaultHttpClientBuilder, lode_core::Error> {
+        let resolver = self.resolver(options.dns.as_ref())?;
+        Ok(DefaultHttpClient::builder()
+            .options(options)
+            .dns_resolver(resolver)
+            .redirect(self.redirect_policy()))
+    }
+
+    /// Client polling the URLs of `wait` stages, under the policy like the requests of the runs
+    pub fn probe_client(self: &Arc<Self>) -> anyhow::Result<reqwest::Client> {
+        Ok(reqwest::Client::builder()
+            .dns_resolver(self.resolver(None)?)
+            .redirect(self.redirect_policy())
+            .build()?)
+    }
+}
+
+/// Resolves host names through another resolver, failing the lookups that return a private
+/// address unless they are allowed
+///
+/// Connections are checked as they are opened, so a host cannot pass the checks of a run and then
+/// resolve to a private address, as with DNS rebinding or a redirect.
+pub struct GuardedResolver {
+    inner: Arc<dyn Resolve>,
+    allow_private: bool,
+}
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let lookup = self.inner.resolve(name);
+        let allow_private = self.allow_private;
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = lookup.await?.collect();
+            if let Some(address) = addrs.iter().find(|address| is_private(address.ip())) {
+                if !allow_private {
+                    return Err(format!(
+                        "{} resolves to the private address {}",
+                        host,
+                        address.ip()
+                    )
+                    .into());
+                }
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// URLs a run sends requests to
+fn target_urls(config: &LoadTestConfig) -> impl Iterator<Item = &str> {
+    std::iter::once(config.url.as_str())
+        .chain(
+            config
+                .scenario
+                .iter()
+                .flat_map(|scenario| scenario.requests.iter().map(|request| request.url.as_str())),
+        )
+        .chain(
+            config
+                .cooldown
+                .as_ref()
+                .and_then(|cooldown| cooldown.probe_url.as_deref()),
+        )
+        .chain(config.stages.iter().filter_map(|stage| match stage {
+            Stage::Wait(condition) => Some(condition.url.as_str()),
+            _ => None,
+        }))
+}
+
+fn matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().trim_end_matches('.').to_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|sub| sub.ends_with('.')),
+        None => host == pattern,
+    }
+}
+
+/// Whether an address is of the machine itself or of a private network
+fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_private_v4(ip),
+            None => {
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    // Unique local fc00::/7 and link-local fe80::/10
+                    || (ip.segments()[0] & 0xfe00) == 0xfc00
+                    || (ip.segments()[0] & 0xffc0) == 0xfe80
+            }
+        },
+    }
+}
+
+fn is_private_v4(ip: Ipv4Addr) -> bool {
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        // This network 0.0.0.0/8 and carrier-grade NAT 100.64.0.0/10
+        || ip.octets()[0] == 0
+        || (ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lode_core::config::{HttpMethod, WaitCondition};
+    use std::time::Duration;
+
+    #[test]
+    fn test_private_targets_are_refused_by_default() {
+        let policy = TargetPolicy::default();
+        assert!(policy.check_url("https://example.com/users").is_ok());
+        assert!(policy.check_url("https://93.184.215.14/").is_ok());
+        for url in [
+            "http://localhost:8080/",
+            "http://api.localhost/",
+            "http://127.0.0.1/",
+            "http://10.1.2.3/",
+            "http://192.168.0.1/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://100.64.0.1/",
+            "http://0.0.0.0/",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://[::ffff:127.0.0.1]/",
+        ] {
+            assert!(policy.check_url(url).is_err(), "{}", url);
+        }
+        let private = TargetPolicy {
+            allow_private: true,
+            ..Default::default()
+        };
+        assert!(private.check_url("http://127.0.0.1:8080/").is_ok());
+    }
+
+    #[test]
+    fn test_allow_and_deny_patterns() {
+        let policy = TargetPolicy {
+            allow: vec!["*.example.com".to_string(), "api.test.dev".to_string()],
+            deny: vec!["admin.example.com".to_string()],
+            allow_private: false,
+        };
+        assert!(policy.check_url("https://shop.example.com/").is_ok());
+        assert!(policy.check_url("https://API.test.dev./v1").is_ok());
+        assert_eq!(
+            policy.check_url("https://admin.example.com/"),
+            Err("admin.example.com is denied".to_string())
+        );
+        assert_eq!(
+            policy.check_url("https://example.com/"),
+            Err("example.com is not on the allowlist".to_string())
+        );
+        assert!(policy.check_url("https://notexample.com/").is_err());
+        assert!(policy.check_url("https://{{host}}/").is_err());
+        assert!(policy.check_url("not a url").is_err());
+    }
+
+    #[test]
+    fn test_wait_stage_urls_are_checked() {
+        let mut config = LoadTestConfig::new(
+            "https://example.com/".to_string(),
+            HttpMethod::GET,
+            10,
+            1,
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        config.stages = vec![Stage::Wait(WaitCondition {
+            url: "http://169.254.169.254/latest/meta-data".to_string(),
+            value_path: None,
+            stable_polls: 1,
+            poll_interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(5),
+        })];
+        assert_eq!(
+            TargetPolicy::default().check_config(&config),
+            Err("169.254.169.254 is a private address".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolver_refuses_private_addresses() {
+        let resolve = |policy: TargetPolicy| async move {
+            let name: Name = "localhost".parse().unwrap();
+            let resolver = policy.resolver(None).unwrap();
+            resolver.resolve(name).await.map(|addrs| addrs.count())
+        };
+        let refused = resolve(TargetPolicy::default()).await.err().unwrap();
+        assert!(refused
+            .to_string()
+            .starts_with("localhost resolves to the private address"));
+        let private = TargetPolicy {
+            allow_private: true,
+            ..Default::default()
+        };
+        assert!(resolve(private).await.unwrap() > 0);
+    }
+}
@@ -0,0 +1,330 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::models::TargetTrendQuery;
+use crate::runs::{RunRecord, RunState, SortKey, SortOrder};
+use crate::storage::RunQuery;
+
+/// Default number of runs in a trend, the latest ones
+pub const DEFAULT_TREND_LIMIT: usize = 100;
+
+/// Maximum number of runs in a trend
+pub const MAX_TREND_LIMIT: usize = 1000;
+
+/// Hex digits of the SHA-256 kept in target and scenario hashes
+const HASH_LENGTH: usize = 16;
+
+/// Hash identifying the target of runs by their URL, as in `/targets/{hash}/trends`
+pub fn target_hash(url: &str) -> String {
+    short_hash(url.as_bytes())
+}
+
+/// Hash identifying the scenario a run was submitted with, unset for runs without one
+///
+/// Runs of an edited scenario get another hash, so a trend only compares runs of the same
+/// workload.
+pub fn scenario_hash(request: Option<&Value>) -> Option<String> {
+    let scenario = request?.get("scenario").filter(|s| !s.is_null())?;
+    Some(short_hash(scenario.to_string().as_bytes()))
+}
+
+fn short_hash(bytes: &[u8]) -> String {
+    let mut hash = format!("{:x}", Sha256::digest(bytes));
+    hash.truncate(HASH_LENGTH);
+    hash
+}
+
+/// Which runs of a target a trend covers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ScenarioFilter {
+    /// Runs of any scenario and without one
+    #[default]
+    Any,
+    /// Runs submitted without a scenario
+    None,
+    /// Runs of the scenario with this hash
+    Hash(String),
+}
+
+/// Scenario, time range and length of a trend
+#[derive(Debug, Clone)]
+pub struct TrendOptions {
+    pub scenario: ScenarioFilter,
+    /// Only runs started at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only runs started before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Latest runs kept
+    pub limit: usize,
+    /// Only runs the API key with this name may see
+    pub owner: Option<String>,
+}
+
+impl Default for TrendOptions {
+    fn default() -> Self {
+        Self {
+            scenario: ScenarioFilter::Any,
+            since: None,
+            until: None,
+            limit: DEFAULT_TREND_LIMIT,
+            owner: None,
+        }
+    }
+}
+
+impl TrendOptions {
+    /// The saved runs of the trend of a target: its latest completed runs passing the options
+    pub fn to_query(&self, target: &str) -> RunQuery {
+        RunQuery {
+            target: Some(target.to_string()),
+            scenario: self.scenario.clone(),
+            status: Some(RunState::Completed),
+            since: self.since,
+            until: self.until,
+            owner: self.owner.clone(),
+            sort: SortKey::StartedAt,
+            order: SortOrder::Desc,
+            limit: self.limit,
+            ..Default::default()
+        }
+    }
+
+    fn matches(&self, record: &RunRecord) -> bool {
+        let scenario = scenario_hash(record.request.as_ref());
+        let scenario_matches = match &self.scenario {
+            ScenarioFilter::Any => true,
+            ScenarioFilter::None => scenario.is_none(),
+            ScenarioFilter::Hash(hash) => scenario.as_ref() == Some(hash),
+        };
+        scenario_matches
+            && record.visible_to(self.owner.as_deref())
+            && self.since.is_none_or(|since| record.started_at() >= since)
+            && self.until.is_none_or(|until| record.started_at() < until)
+    }
+}
+
+impl TryFrom<TargetTrendQuery> for TrendOptions {
+    type Error = String;
+
+    fn try_from(query: TargetTrendQuery) -> Result<Self, Self::Error> {
+        let limit = query.limit.unwrap_or(DEFAULT_TREND_LIMIT);
+        if limit == 0 || limit > MAX_TREND_LIMIT {
+            return Err(format!(
+                "limit must be between 1 and {}, got {}",
+                MAX_TREND_LIMIT, limit
+            ));
+        }
+        let scenario = match query.scenario.as_deref() {
+            None | Some("any") => ScenarioFilter::Any,
+            Some("none") => ScenarioFilter::None,
+            Some(hash) => ScenarioFilter::Hash(hash.to_lowercase()),
+        };
+        let time = |name: &str, value: Option<String>| {
+            value
+                .map(|value| {
+                    DateTime::parse_from_rfc3339(&value)
+                        .map(|time| time.with_timezone(&Utc))
+                        .map_err(|e| {
+                            format!("{} must be an RFC 3339 time, got '{}': {}", name, value, e)
+                        })
+                })
+                .transpose()
+        };
+        Ok(Self {
+            scenario,
+            since: time("since", query.since)?,
+            until: time("until", query.until)?,
+            limit,
+            owner: None,
+        })
+    }
+}
+
+/// Figures of one completed run in a trend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrendPoint {
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    pub method: String,
+    /// Hash of the scenario of the run, missing for runs without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<String>,
+    pub p95_response_time_ms: f64,
+    pub requests_per_second: f64,
+    pub error_rate_percent: f64,
+}
+
+/// The completed runs against a target that pass the options, the oldest first
+///
+/// Failed and cancelled runs are left out, their figures would read as drift.
+pub fn trend<'a>(
+    runs: impl Iterator<Item = &'a RunRecord>,
+    target: &str,
+    options: &TrendOptions,
+) -> Vec<TrendPoint> {
+    let mut points: Vec<TrendPoint> = runs
+        .filter(|record| record.status() == RunState::Completed)
+        .filter(|record| target_hash(record.url()) == target && options.matches(record))
+        .filter_map(|record| {
+            let report = record.response.as_ref()?;
+            Some(TrendPoint {
+                id: record.id().to_string(),
+                started_at: record.started_at(),
+                method: record.method().to_string(),
+                scenario: scenario_hash(record.request.as_ref()),
+                p95_response_time_ms: report.p95_response_time_ms,
+                requests_per_second: report.requests_per_second,
+                error_rate_percent: match report.total_requests {
+                    0 => 0.0,
+                    total => report.failed_requests as f64 / total as f64 * 100.0,
+                },
+            })
+        })
+        .collect();
+    points.sort_by(|a, b| {
+        a.started_at
+            .cmp(&b.started_at)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    let skipped = points.len().saturating_sub(options.limit);
+    points.drain(..skipped);
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LoadTestResponse;
+    use chrono::Duration;
+    use lode_core::config::LoadTestConfig;
+    use lode_core::progress::Progress;
+    use lode_core::run::TestRun;
+
+    fn finished(id: &str, url: &str, state: RunState, minutes_ago: i64) -> TestRun {
+        let config = LoadTestConfig {
+            url: url.to_string(),
+            ..Default::default()
+        };
+        let started_at = Utc::now() - Duration::minutes(minutes_ago);
+        TestRun::restored(id.to_string(), config, state, started_at, None, None).unwrap()
+    }
+
+    fn record(id: &str, minutes_ago: i64, request: Value, failed: u64) -> RunRecord {
+        let mut record = RunRecord::queued(id.to_string(), Default::default(), Progress::new())
+            .with_request(request);
+        record.run = finished(id, "http://example.com", RunState::Completed, minutes_ago);
+        record.response = Some(LoadTestResponse {
+            total_requests: 200,
+            failed_requests: failed,
+            p95_response_time_ms: 40.0 + minutes_ago as f64,
+            ..Default::default()
+        });
+        record
+    }
+
+    #[test]
+    fn test_hashes() {
+        assert_eq!(target_hash("http://example.com").len(), HASH_LENGTH);
+        assert_eq!(
+            target_hash("http://example.com"),
+            target_hash("http://example.com")
+        );
+        assert_ne!(
+            target_hash("http://example.com"),
+            target_hash("http://example.com/")
+        );
+        let scenario = serde_json::json!({"scenario": {"requests": []}});
+        assert!(scenario_hash(Some(&scenario)).is_some());
+        assert_eq!(
+            scenario_hash(Some(&serde_json::json!({"scenario": null}))),
+            None
+        );
+        assert_eq!(scenario_hash(None), None);
+    }
+
+    #[test]
+    fn test_trend_of_target_and_scenario() {
+        let plain = serde_json::json!({"url": "http://example.com"});
+        let scenario = serde_json::json!({"scenario": {"requests": [{"name": "home"}]}});
+        let mut failed = record("failed", 5, plain.clone(), 0);
+        failed.run = finished("failed", "http://example.com", RunState::Failed, 5);
+        let mut elsewhere = record("elsewhere", 5, plain.clone(), 0);
+        elsewhere.run = finished("elsewhere", "http://example.org", RunState::Completed, 5);
+        let runs = [
+            record("c", 10, plain.clone(), 0),
+            record("a", 30, plain.clone(), 10),
+            record("b", 20, scenario.clone(), 0),
+            failed,
+            elsewhere,
+        ];
+        let target = target_hash("http://example.com");
+
+        let points = trend(runs.iter(), &target, &TrendOptions::default());
+        let ids: Vec<&str> = points.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c"]);
+        assert_eq!(points[0].error_rate_percent, 5.0);
+        assert_eq!(points[0].p95_response_time_ms, 70.0);
+        assert_eq!(points[1].scenario, scenario_hash(Some(&scenario)));
+
+        let only = |filter| TrendOptions {
+            scenario: filter,
+            ..Default::default()
+        };
+        let ids = |options: &TrendOptions| -> Vec<String> {
+            trend(runs.iter(), &target, options)
+                .into_iter()
+                .map(|p| p.id)
+                .collect()
+        };
+        assert_eq!(ids(&only(ScenarioFilter::None)), ["a", "c"]);
+        let hash = scenario_hash(Some(&scenario)).unwrap();
+        assert_eq!(ids(&only(ScenarioFilter::Hash(hash))), ["b"]);
+        let latest = TrendOptions {
+            limit: 2,
+            ..Default::default()
+        };
+        assert_eq!(ids(&latest), ["b", "c"]);
+        let window = TrendOptions {
+            since: Some(Utc::now() - Duration::minutes(25)),
+            until: Some(Utc::now() - Duration::minutes(15)),
+            ..Default::default()
+        };
+        assert_eq!(ids(&window), ["b"]);
+    }
+
+    #[test]
+    fn test_trend_options_from_query() {
+        let options = TrendOptions::try_from(TargetTrendQuery {
+            scenario: Some("ABC123".to_string()),
+            since: Some("2025-03-30T00:00:00Z".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(options.scenario, ScenarioFilter::Hash("abc123".to_string()));
+        assert!(options.since.is_some());
+        assert_eq!(options.limit, DEFAULT_TREND_LIMIT);
+
+        let none = TargetTrendQuery {
+            scenario: Some("none".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            TrendOptions::try_from(none).unwrap().scenario,
+            ScenarioFilter::None
+        );
+        for invalid in [
+            TargetTrendQuery {
+                limit: Some(MAX_TREND_LIMIT + 1),
+                ..Default::default()
+            },
+            TargetTrendQuery {
+                until: Some("tomorrow".to_string()),
+                ..Default::default()
+            },
+        ] {
+            assert!(TrendOptions::try_from(invalid).is_err());
+        }
+    }
+}
@@ -0,0 +1,13 @@
+use actix_web::{HttpResponse, Responder};
+
+/// The single page of the UI: it lists the runs, follows a running one through
+/// `GET /load-tests/{id}/events` with a live chart from `GET /load-tests/{id}/stream` and shows a
+/// finished one with its HTML report
+const INDEX: &str = include_str!("../ui/index.html");
+
+/// Serve the web UI, built with the `ui` feature
+pub async fn index() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(INDEX)
+}
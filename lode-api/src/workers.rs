@@ -0,0 +1,83 @@
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+/// Load tests run at once when no limit is configured, so runs do not skew each other's results
+pub const DEFAULT_WORKERS: usize = 1;
+
+/// Bounded pool of workers running the submitted load tests
+///
+/// Runs wait in a queue for a free worker in the order they were submitted, and keep their worker
+/// until they finish.
+#[derive(Debug, Clone)]
+pub struct WorkerPool {
+    slots: Arc<Semaphore>,
+    size: usize,
+}
+
+impl WorkerPool {
+    /// A pool running up to `size` load tests at once, at least one
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1);
+        Self {
+            slots: Arc::new(Semaphore::new(size)),
+            size,
+        }
+    }
+
+    /// Load tests the pool runs at once
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Wait in the queue for a free worker, held until the returned permit is dropped
+    ///
+    /// Returns `None` when the run is cancelled while it waits.
+    pub async fn acquire(
+        &self,
+        cancel: Option<&CancellationToken>,
+    ) -> Option<OwnedSemaphorePermit> {
+        let slot = Arc::clone(&self.slots).acquire_owned();
+        match cancel {
+            Some(cancel) => tokio::select! {
+                biased;
+                _ = cancel.cancelled() => None,
+                permit = slot => permit.ok(),
+            },
+            None => slot.await.ok(),
+        }
+    }
+}
+
+impl Default for WorkerPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_WORKERS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_runs_wait_for_a_free_worker() {
+        let pool = WorkerPool::new(2);
+        let first = pool.acquire(None).await.unwrap();
+        let _second = pool.acquire(None).await.unwrap();
+
+        // A queued run gives up its place when cancelled
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        assert!(pool.acquire(Some(&cancel)).await.is_none());
+
+        let waiting = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.acquire(None).await.is_some() }
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+        drop(first);
+        assert!(waiting.await.unwrap());
+        assert_eq!(WorkerPool::new(0).size(), 1);
+    }
+}
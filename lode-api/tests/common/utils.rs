@@ -1,20 +1,122 @@
 use actix_web::{test, web, App};
 use lode_api::handlers::AppState;
+use lode_api::targets::TargetPolicy;
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 pub async fn setup_test_app() -> impl actix_web::dev::Service<
     actix_http::Request,
     Response = actix_web::dev::ServiceResponse,
     Error = actix_web::Error,
+> {
+    setup_test_app_with_state(test_state()).await
+}
+
+/// State of an API loading the mock servers, which listen on localhost
+pub fn test_state() -> AppState {
+    AppState::new()
+        .unwrap()
+        .with_targets(TargetPolicy {
+            allow_private: true,
+            ..Default::default()
+        })
+        .unwrap()
+}
+
+pub async fn setup_test_app_with_state(
+    state: AppState,
+) -> impl actix_web::dev::Service<
+    actix_http::Request,
+    Response = actix_web::dev::ServiceResponse,
+    Error = actix_web::Error,
+> {
+    setup_test_app_with_data(web::Data::new(state)).await
+}
+
+/// App sharing its state with the test, e.g. to run the schedules that are due
+pub async fn setup_test_app_with_data(
+    state: web::Data<AppState>,
+) -> impl actix_web::dev::Service<
+    actix_http::Request,
+    Response = actix_web::dev::ServiceResponse,
+    Error = actix_web::Error,
 > {
     test::init_service(
         App::new()
-            .app_data(web::Data::new(AppState::new().unwrap()))
+            .wrap(actix_web::middleware::from_fn(lode_api::auth::authenticate))
+            .wrap(actix_web::middleware::from_fn(
+                lode_api::auth::hide_access_token,
+            ))
+            .app_data(state)
             .route("/health", web::get().to(lode_api::handlers::health_check))
+            .route(
+                "/metrics",
+                web::get().to(lode_api::handlers::prometheus_metrics),
+            )
             .route(
                 "/load-test",
                 web::post().to(lode_api::handlers::run_load_test),
-            ),
+            )
+            .route(
+                "/load-tests",
+                web::get().to(lode_api::handlers::list_load_tests),
+            )
+            .route(
+                "/load-tests",
+                web::post().to(lode_api::handlers::submit_load_test),
+            )
+            .route(
+                "/agent/load-tests",
+                web::post().to(lode_api::handlers::submit_agent_load_test),
+            )
+            .route(
+                "/load-tests/{id}",
+                web::get().to(lode_api::handlers::get_load_test),
+            )
+            .route(
+                "/load-tests/{id}",
+                web::delete().to(lode_api::handlers::cancel_load_test),
+            )
+            .route(
+                "/load-tests/{id}",
+                web::patch().to(lode_api::handlers::override_load_test),
+            )
+            .route(
+                "/load-tests/{id}/advance",
+                web::post().to(lode_api::handlers::advance_load_test),
+            )
+            .route(
+                "/load-tests/{id}/report",
+                web::get().to(lode_api::handlers::get_load_test_report),
+            )
+            .route(
+                "/load-tests/{id}/events",
+                web::get().to(lode_api::handlers::stream_load_test_events),
+            )
+            .route(
+                "/load-tests/{id}/stream",
+                web::get().to(lode_api::handlers::stream_load_test_metrics),
+            )
+            .route(
+                "/targets/{target}/trends",
+                web::get().to(lode_api::handlers::get_target_trends),
+            )
+            .route(
+                "/schedules",
+                web::get().to(lode_api::handlers::list_schedules),
+            )
+            .route(
+                "/schedules",
+                web::post().to(lode_api::handlers::create_schedule),
+            )
+            .route(
+                "/schedules/{id}",
+                web::get().to(lode_api::handlers::get_schedule),
+            )
+            .route(
+                "/schedules/{id}",
+                web::delete().to(lode_api::handlers::delete_schedule),
+            )
+            .route("/ui", web::get().to(lode_api::ui::index)),
     )
     .await
 }
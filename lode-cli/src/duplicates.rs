@@ -0,0 +1,217 @@
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long to wait for another lode process to release the log
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Age of a lock file that is taken as left behind by a process that died holding it
+const STALE_LOCK: Duration = Duration::from_secs(30);
+
+/// Runs started from this directory recently, one line each, to catch a test started twice, e.g.
+/// by a CI retry
+///
+/// A line holds the start as Unix seconds, the fingerprint of the configuration and the target:
+/// `1718000000 3f2a… https://example.com`. Lines older than the window are dropped as new runs
+/// are recorded.
+#[derive(Debug)]
+pub struct RunLog {
+    path: PathBuf,
+    window: Duration,
+}
+
+/// An earlier run of an identical test
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateRun {
+    /// How long ago it started
+    pub age: Duration,
+    pub url: String,
+}
+
+impl RunLog {
+    /// Look for duplicates in the file at `path` among the runs started less than `window` ago
+    pub fn new(path: PathBuf, window: Duration) -> Self {
+        Self { path, window }
+    }
+
+    /// Latest run with this fingerprint within the window; a missing or unreadable log has none
+    pub fn find(&self, fingerprint: &str) -> Option<DuplicateRun> {
+        let now = unix_now();
+        self.entries()
+            .into_iter()
+            .filter(|(started, logged, _)| {
+                logged == fingerprint && now.saturating_sub(*started) < self.window
+            })
+            .map(|(started, _, url)| DuplicateRun {
+                age: now.saturating_sub(started),
+                url,
+            })
+            .min_by_key(|run| run.age)
+    }
+
+    /// Look for a duplicate of a run starting now and note the run, as one step no other lode
+    /// process can come between
+    ///
+    /// With `refuse`, a run that has a duplicate is not noted, since it will not start.
+    pub fn check_in(
+        &self,
+        fingerprint: &str,
+        url: &str,
+        refuse: bool,
+    ) -> std::io::Result<Option<DuplicateRun>> {
+        let _lock = LogLock::acquire(&self.path)?;
+        let duplicate = self.find(fingerprint);
+        if duplicate.is_none() || !refuse {
+            self.record(fingerprint, url)?;
+        }
+        Ok(duplicate)
+    }
+
+    /// Note a run starting now, dropping the runs that fell out of the window
+    pub fn record(&self, fingerprint: &str, url: &str) -> std::io::Result<()> {
+        let now = unix_now();
+        let mut lines: Vec<String> = self
+            .entries()
+            .into_iter()
+            .filter(|(started, _, _)| now.saturating_sub(*started) < self.window)
+            .map(|(started, fingerprint, url)| line(started, &fingerprint, &url))
+            .collect();
+        lines.push(line(now, fingerprint, url));
+        let mut file = std::fs::File::create(&self.path)?;
+        for line in lines {
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    /// Runs in the log as their start since the epoch, fingerprint and target; bad lines are skipped
+    fn entries(&self) -> Vec<(Duration, String, String)> {
+        let Ok(contents) = std::fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        contents
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(3, ' ');
+                let started = parts.next()?.parse().ok().map(Duration::from_secs)?;
+                let fingerprint = parts.next()?.to_string();
+                let url = parts.next().unwrap_or_default().to_string();
+                Some((started, fingerprint, url))
+            })
+            .collect()
+    }
+}
+
+/// Exclusive hold on a run log, as a `.lock` file next to it created by one process at a time
+struct LogLock {
+    path: PathBuf,
+}
+
+impl LogLock {
+    fn acquire(log: &Path) -> std::io::Result<Self> {
+        let mut path = log.as_os_str().to_owned();
+        path.push(".lock");
+        let path = PathBuf::from(path);
+        let deadline = std::time::Instant::now() + LOCK_TIMEOUT;
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(Self { path }),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let stale = std::fs::metadata(&path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok())
+                        .is_some_and(|age| age > STALE_LOCK);
+                    if stale {
+                        let _ = std::fs::remove_file(&path);
+                    } else if std::time::Instant::now() >= deadline {
+                        return Err(std::io::Error::new(
+                            ErrorKind::TimedOut,
+                            format!("{} is held by another run", path.display()),
+                        ));
+                    } else {
+                        std::thread::sleep(Duration::from_millis(10));
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for LogLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn line(started: Duration, fingerprint: &str, url: &str) -> String {
+    format!("{} {} {}", started.as_secs(), fingerprint, url)
+}
+
+fn unix_now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_log_finds_recent_duplicates() {
+        let path = std::env::temp_dir().join(format!("lode-runs-{}", std::process::id()));
+        let stale = unix_now() - Duration::from_secs(3600);
+        std::fs::write(
+            &path,
+            format!("{} old https://example.com\nnot a run\n", stale.as_secs()),
+        )
+        .unwrap();
+
+        let log = RunLog::new(path.clone(), Duration::from_secs(600));
+        assert_eq!(log.find("old"), None);
+        assert_eq!(log.find("abc"), None);
+
+        log.record("abc", "https://example.com/a").unwrap();
+        let duplicate = log.find("abc").unwrap();
+        assert_eq!(duplicate.url, "https://example.com/a");
+        assert!(duplicate.age < Duration::from_secs(5));
+        assert_eq!(log.find("abd"), None);
+
+        // Runs out of the window are dropped
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_simultaneous_runs_check_in_one_at_a_time() {
+        let path = std::env::temp_dir().join(format!("lode-runs-race-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let log = RunLog::new(path.clone(), Duration::from_secs(600));
+        let start = std::sync::Barrier::new(8);
+
+        let duplicates = std::thread::scope(|scope| {
+            let runs: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        start.wait();
+                        log.check_in("abc", "https://example.com", true).unwrap()
+                    })
+                })
+                .collect();
+            runs.into_iter()
+                .filter_map(|run| run.join().unwrap())
+                .count()
+        });
+        // Only the first run started, every other one saw it
+        assert_eq!(duplicates, 7);
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
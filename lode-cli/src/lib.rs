@@ -1,12 +1,36 @@
 pub use clap::Parser;
-use num_cpus;
+use clap::{Args, Subcommand};
+use lode_core::report::format::{LatencyUnit, Locale};
+
+pub mod duplicates; // Detection of a test started twice, e.g. by a CI retry
+pub mod progress; // Plain-text progress for logs without a terminal
+pub mod watch; // Re-running the test when its input files change
+
+/// Exit code of a run that missed one of its `--slo` objectives, so CI can tell it from a failed run
+pub const SLO_VIOLATION_EXIT_CODE: u8 = 3;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 pub struct Cli {
-    /// Target URL to load test
-    #[arg(short, long)]
-    pub url: String,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Target URL to load test; repeat it to send the request to each URL in turn
+    #[arg(short, long, required_unless_present_any = ["merge", "url_file"])]
+    pub url: Vec<String>,
+
+    /// File of target URLs, one per line, added to --url ("#" starts a comment line)
+    #[arg(long, value_name = "FILE")]
+    pub url_file: Option<std::path::PathBuf>,
+
+    /// Pick one of several target URLs at random for every request instead of in turn
+    #[arg(long)]
+    pub random_url_order: bool,
+
+    /// Query parameter appended with a random value, e.g. "id=1..1000" or "sort=asc|desc"
+    /// (repeatable)
+    #[arg(long = "random-param", value_name = "NAME=VALUES")]
+    pub random_params: Vec<String>,
 
     /// Number of requests to send
     #[arg(short, long, default_value = "100")]
@@ -24,19 +48,649 @@ pub struct Cli {
     #[arg(short, long, default_value = "30")]
     pub timeout: u64,
 
+    /// Overall deadline of the test in seconds; the run ends with partial results past it
+    #[arg(long, value_name = "SECONDS")]
+    pub deadline: Option<u64>,
+
+    /// Keep probing the target once per second for DURATION after the load stops (e.g. "30s") and
+    /// report how long its latency takes to get back to the baseline
+    #[arg(long, value_name = "DURATION")]
+    pub cooldown: Option<String>,
+
+    /// URL probed during the cooldown instead of --url, e.g. a health endpoint
+    #[arg(long, value_name = "URL", requires = "cooldown")]
+    pub cooldown_probe: Option<String>,
+
     /// JSON body for POST/PUT requests
     #[arg(short, long)]
     pub body: Option<String>,
 
-    /// Custom headers (format: "key:value")
-    #[arg(short = 'H', long, num_args = 0.., value_delimiter = ',')]
+    /// URL-encoded form field sent as the body, e.g. "user=alice" (repeatable)
+    #[arg(long = "form", value_name = "NAME=VALUE", conflicts_with_all = ["body", "multipart"])]
+    pub form: Vec<String>,
+
+    /// Multipart form field sent as the body, "name=value" or a file upload "name=@path[;type=mime]"
+    /// (repeatable)
+    #[arg(long, value_name = "NAME=VALUE", conflicts_with = "body")]
+    pub multipart: Vec<String>,
+
+    /// GraphQL query POSTed as the body, or "@path" to read it from a file; responses with
+    /// `errors` count as failed even with a 200
+    #[arg(long, value_name = "QUERY", conflicts_with_all = ["body", "form", "multipart", "scenario"])]
+    pub graphql: Option<String>,
+
+    /// Variables of the GraphQL query, a JSON object
+    #[arg(long, value_name = "JSON", requires = "graphql")]
+    pub graphql_variables: Option<String>,
+
+    /// Operation to run when the GraphQL document defines several
+    #[arg(long, value_name = "NAME", requires = "graphql")]
+    pub graphql_operation: Option<String>,
+
+    /// Custom headers (format: "key:value"; a comma followed by another "key:" starts a new one)
+    #[arg(short = 'H', long, num_args = 0..)]
     pub headers: Option<Vec<String>>,
 
-    /// Output format (text or json)
+    /// JSON file describing a weighted mix or sequential flow of requests sent instead of --url
+    #[arg(long, value_name = "FILE")]
+    pub scenario: Option<std::path::PathBuf>,
+
+    /// Weights file (JSON or "name,percentage" lines) replacing the weights of the --scenario requests
+    #[arg(long, value_name = "FILE", requires = "scenario")]
+    pub mix: Option<std::path::PathBuf>,
+
+    /// Environment of the --scenario file to run against, e.g. "staging": its base URL, headers,
+    /// auth and variables apply to the scenario requests
+    #[arg(long, value_name = "NAME", requires = "scenario")]
+    pub env: Option<String>,
+
+    /// CSV (with a header line) or JSONL file of test data rows filling "{{column}}" placeholders of
+    /// the URL, headers and body, one row per iteration
+    #[arg(long, value_name = "FILE")]
+    pub feeder: Option<std::path::PathBuf>,
+
+    /// Order of the --feeder rows: circular, random or unique (ends the run when they run out)
+    #[arg(
+        long,
+        value_name = "STRATEGY",
+        default_value = "circular",
+        requires = "feeder"
+    )]
+    pub feeder_strategy: String,
+
+    /// What a unique --feeder does once its rows run out: stop (ends the run), fail (aborts the run)
+    /// or recycle (starts over from the first row)
+    #[arg(
+        long,
+        value_name = "POLICY",
+        default_value = "stop",
+        requires = "feeder"
+    )]
+    pub feeder_on_exhausted: String,
+
+    /// What an iteration does when a "{{placeholder}}" has no value: abort (the run), skip (its
+    /// requests, counted in the report) or send (the placeholder as it is)
+    #[arg(long, value_name = "POLICY", default_value = "send")]
+    pub on_missing_value: String,
+
+    /// Label attached to every request for metric breakdowns (format: "key=value", repeatable)
+    #[arg(long = "label", value_name = "KEY=VALUE")]
+    pub labels: Vec<String>,
+
+    /// Cap throughput at this many requests per second
+    #[arg(long)]
+    pub rate: Option<f64>,
+
+    /// Requests that may start at once at --rate, the capacity of its token bucket: 1 paces them
+    /// strictly, more lets idle moments build up bursts at the same average rate
+    #[arg(long, value_name = "REQUESTS", requires = "rate")]
+    pub rate_burst: Option<u32>,
+
+    /// When requests start: closed (when a virtual user is free), or open (constant) and poisson
+    /// (random gaps) arrivals at --rate whatever is in flight, with --concurrency capping
+    /// outstanding requests and response times counted from the scheduled start
+    #[arg(long, value_name = "MODEL", default_value = "closed")]
+    pub load_model: String,
+
+    /// Also report response times corrected for coordinated omission: counted from when --rate
+    /// had requests start, with the requests a stalled virtual user held back filled in
+    #[arg(long, requires = "rate")]
+    pub correct_coordinated_omission: bool,
+
+    /// Generator fault to inject, e.g. "pause@60s+30s" or "throttle:10@2m+1m" (repeatable)
+    #[arg(long = "fault", value_name = "FAULT")]
+    pub faults: Vec<String>,
+
+    /// Load stage, e.g. "ramp:1m:50", "hold:5m:50:200", "burst:30s:1m:5:50", "wait(stable=3):<url>"
+    /// or "signal" (repeatable, run in order)
+    #[arg(long = "stage", value_name = "STAGE")]
+    pub stages: Vec<String>,
+
+    /// Move to the next stage whenever this file is created or touched
+    #[arg(long, value_name = "PATH")]
+    pub advance_file: Option<std::path::PathBuf>,
+
+    /// Move to the next stage on every POST to http://127.0.0.1:<PORT>/advance
+    #[arg(long, value_name = "PORT")]
+    pub control_port: Option<u16>,
+
+    /// Back off for the delay of `Retry-After` headers of 429, 502 and 503 responses, like a
+    /// cooperative client
+    #[arg(long)]
+    pub honor_retry_after: bool,
+
+    /// Pace requests by the rate limit published in response headers, spreading the requests left
+    /// over the time to the reset: "REMAINING,RESET[,SHARE%]", X-RateLimit-Remaining and
+    /// X-RateLimit-Reset at 90% when no value is given
+    #[arg(
+        long,
+        value_name = "HEADERS",
+        num_args = 0..=1,
+        default_missing_value = "X-RateLimit-Remaining,X-RateLimit-Reset"
+    )]
+    pub pace_from_headers: Option<String>,
+
+    /// Pause of a virtual user after each response before its next request: fixed ("2s"), a
+    /// uniform range ("1s..3s") or exponential around a mean ("exp:2s")
+    #[arg(long, value_name = "PAUSE")]
+    pub think_time: Option<String>,
+
+    /// Status codes counted as successful instead of any 2xx, e.g. "201", "2xx,404" or "200-399"
+    #[arg(long, value_name = "CODES")]
+    pub success_codes: Option<String>,
+
+    /// Region of this agent, attached to every request as the `region` label
+    #[arg(long)]
+    pub region: Option<String>,
+
+    /// Merge the JSON reports of several agents, broken down by region, instead of running a test
+    #[arg(long, value_name = "FILE", num_args = 1.., conflicts_with_all = ["url", "url_file"])]
+    pub merge: Vec<std::path::PathBuf>,
+
+    /// Split the test across lode-api agents at these base URLs, e.g.
+    /// "http://10.0.0.5:8081,http://10.0.0.6:8081", and report on their merged results instead
+    /// of sending the requests from here
+    #[arg(
+        long,
+        value_name = "URL",
+        value_delimiter = ',',
+        conflicts_with_all = ["merge", "watch", "output_requests", "har", "clock_sync"]
+    )]
+    pub agents: Vec<String>,
+
+    /// API key sent to the --agents
+    #[arg(long, value_name = "KEY", requires = "agents")]
+    pub agent_api_key: Option<String>,
+
+    /// Coordinator `/time` endpoint to measure this generator's clock offset against before the run
+    #[arg(long, value_name = "URL")]
+    pub clock_sync: Option<String>,
+
+    /// Service level objective checked against the report, e.g. "p95 < 250ms", "error_rate < 1%"
+    /// or "rps >= 100", or a threshold expression such as "p95 < 300ms && error_rate < 1%" or
+    /// "http_req_duration{step:checkout} p99 < 800ms" (repeatable); the exit code is 3 if one is
+    /// missed
+    #[arg(long = "slo", visible_alias = "threshold", value_name = "OBJECTIVE")]
+    pub slos: Vec<String>,
+
+    /// End the run early with status "aborted" once a rule is met, e.g. "error_rate > 50% over
+    /// 10s" or "100 consecutive connection failures" (repeatable)
+    #[arg(long = "abort-on", value_name = "RULE")]
+    pub abort_on: Vec<String>,
+
+    /// Output format (text, json, html, sla, junit or ndjson-stream)
     #[arg(short, long, default_value = "text")]
     pub format: String,
 
+    /// Serve live metrics in the Prometheus format at http://0.0.0.0:<PORT>/metrics during the run
+    #[arg(long, value_name = "PORT")]
+    pub prometheus_port: Option<u16>,
+
+    /// Number conventions of the text format, e.g. "en", "de" or "fr"
+    #[arg(long, default_value = "en")]
+    pub locale: Locale,
+
+    /// Unit of response times in the text format: auto, us, ms or s
+    #[arg(long, default_value = "auto")]
+    pub latency_unit: LatencyUnit,
+
+    /// Decimals of response times in the text format (default: three significant digits)
+    #[arg(long)]
+    pub precision: Option<usize>,
+
+    /// Add a time series of throughput and response times to the report, one bucket per INTERVAL (e.g. "1s")
+    #[arg(long, value_name = "INTERVAL")]
+    pub time_series: Option<String>,
+
+    /// Extra response time percentiles to report, e.g. "75,90,99.99"
+    #[arg(long, value_name = "PERCENTILES", value_delimiter = ',')]
+    pub percentiles: Vec<f64>,
+
+    /// JSON report of an earlier run to compare against in the html format
+    #[arg(long, value_name = "FILE")]
+    pub baseline: Option<std::path::PathBuf>,
+
+    /// Record every request and write them (timestamp, duration, status, error) to FILE, as Parquet
+    /// when it ends with .parquet and as CSV otherwise
+    #[arg(long, value_name = "FILE")]
+    pub output_requests: Option<std::path::PathBuf>,
+
+    /// Write a sample of the requests actually sent, with their headers, bodies and responses, to
+    /// FILE as HAR, to open in browser devtools or share with the owner of the API
+    #[arg(long, value_name = "FILE")]
+    pub har: Option<std::path::PathBuf>,
+
+    /// Share of the requests written to the --har file, in percent
+    #[arg(long, value_name = "PERCENT", default_value = "1", requires = "har")]
+    pub har_sample: f64,
+
+    /// Requests written to the --har file at most, the first ones sampled
+    #[arg(long, value_name = "COUNT", default_value_t = lode_core::trace::DEFAULT_TRACE_ENTRIES, requires = "har")]
+    pub har_max_entries: usize,
+
+    /// Bytes of each request and response body kept in the --har file, the rest is cut
+    #[arg(long, value_name = "BYTES", default_value_t = lode_core::trace::DEFAULT_TRACE_BODY_SIZE, requires = "har")]
+    pub har_max_body: usize,
+
+    /// Keep the values of credential headers such as Authorization, Cookie and X-Api-Key in the
+    /// --har file instead of redacting them
+    #[arg(long, requires = "har")]
+    pub har_keep_secrets: bool,
+
+    /// Sign the JSON report with the ed25519 private key in FILE (PKCS#8 PEM), so `lode verify`
+    /// can show later that it is unchanged
+    #[arg(long, value_name = "FILE")]
+    pub sign_key: Option<std::path::PathBuf>,
+
+    /// Render latency-over-time and distribution charts of the run to DIR as SVG and PNG images,
+    /// with a one-second time series unless --time-series sets one
+    #[arg(long, value_name = "DIR")]
+    pub charts: Option<std::path::PathBuf>,
+
+    /// JSON report of the requests completed so far, written if lode panics during the run
+    #[arg(long, value_name = "FILE", default_value = "lode-recovery.json")]
+    pub recovery_file: std::path::PathBuf,
+
+    /// Warn when an identical test against the same target started less than this many minutes
+    /// ago from the same directory, e.g. a double submission by a CI retry
+    #[arg(long, value_name = "MINUTES")]
+    pub duplicate_window: Option<u64>,
+
+    /// Refuse to run a test that is a duplicate within --duplicate-window (default: 10) instead of
+    /// warning
+    #[arg(long)]
+    pub no_duplicate: bool,
+
+    /// Log of the recent runs checked for duplicates with --duplicate-window or --no-duplicate
+    #[arg(long, value_name = "FILE", default_value = ".lode-runs")]
+    pub run_log: std::path::PathBuf,
+
+    /// Seconds between plain-text progress lines, printed instead of the progress bar when stderr
+    /// is not a terminal or `CI` is set
+    #[arg(long, value_name = "SECONDS", default_value = "10")]
+    pub progress_interval: u64,
+
+    /// Seconds between summary lines of the current rate, p95 and error rate printed above the
+    /// progress bar; 0 turns them off
+    #[arg(long, value_name = "SECONDS", default_value = "10")]
+    pub summary_interval: u64,
+
+    /// Open a new connection for every request instead of reusing pooled ones
+    #[arg(long, help_heading = "Connections")]
+    pub disable_keepalive: bool,
+
+    /// Idle connections kept open per host (default: unlimited)
+    #[arg(long, value_name = "COUNT", help_heading = "Connections")]
+    pub max_idle_per_host: Option<usize>,
+
+    /// How long an idle connection is kept open, e.g. "30s" (default: 90s)
+    #[arg(long, value_name = "DURATION", help_heading = "Connections")]
+    pub pool_idle_timeout: Option<String>,
+
+    /// Open at most this many new connections per second, independent of the request rate, to
+    /// avoid a burst of connections at the start that firewalls may take for a SYN flood
+    #[arg(long, value_name = "PER_SECOND", help_heading = "Connections")]
+    pub connect_rate: Option<f64>,
+
+    /// HTTP version to speak: "1.1", "2" (h2c on plain HTTP) or "auto" to negotiate it over TLS
+    #[arg(
+        long,
+        value_name = "VERSION",
+        default_value = "auto",
+        help_heading = "Connections"
+    )]
+    pub http_version: String,
+
+    /// What to do with response bodies: "drain" them to reuse the connection, "read" them fully or
+    /// "discard" them after the headers, which closes the connection
+    #[arg(
+        long,
+        value_name = "POLICY",
+        default_value = "drain",
+        help_heading = "Connections"
+    )]
+    pub response_body: String,
+
+    /// PEM file of CA certificates to trust on top of the system's, e.g. for a private CA
+    #[arg(long, value_name = "FILE", help_heading = "TLS")]
+    pub ca_cert: Option<std::path::PathBuf>,
+
+    /// PEM file of a client certificate to present for mutual TLS, requires --client-key
+    #[arg(
+        long,
+        value_name = "FILE",
+        help_heading = "TLS",
+        requires = "client_key"
+    )]
+    pub client_cert: Option<std::path::PathBuf>,
+
+    /// PEM file of the PKCS #8 private key of --client-cert
+    #[arg(
+        long,
+        value_name = "FILE",
+        help_heading = "TLS",
+        requires = "client_cert"
+    )]
+    pub client_key: Option<std::path::PathBuf>,
+
+    /// Load --client-cert and --client-key again when they change on disk during the run, e.g.
+    /// short-lived certificates renewed during a soak test
+    #[arg(long, help_heading = "TLS", requires = "client_cert")]
+    pub reload_client_cert: bool,
+
+    /// Lowest TLS version to accept: 1.0, 1.1, 1.2 or 1.3 (with --tls-session-resumption only)
+    #[arg(long, value_name = "VERSION", help_heading = "TLS")]
+    pub tls_min_version: Option<String>,
+
+    /// Skip verification of server certificates and host names, e.g. for self-signed certificates
+    #[arg(short = 'k', long, help_heading = "TLS")]
+    pub insecure: bool,
+
+    /// Resume earlier TLS sessions on new connections ("on") or always do a full handshake
+    /// ("off"), and report the share of handshakes resumed; switches to the rustls TLS backend
+    #[arg(
+        long,
+        value_name = "on|off",
+        help_heading = "TLS",
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub tls_session_resumption: Option<bool>,
+
+    /// Resolve host names with this nameserver instead of the system resolver, e.g. `10.0.0.2` or
+    /// `10.0.0.2:5353` (repeatable)
+    #[arg(long = "dns-server", value_name = "ADDRESS", help_heading = "DNS")]
+    pub dns_servers: Vec<String>,
+
+    /// Give up on a DNS query after this long, e.g. "500ms" (default: 5s)
+    #[arg(long, value_name = "DURATION", help_heading = "DNS")]
+    pub dns_timeout: Option<String>,
+
+    /// Look host names up again for every new connection instead of caching the answers
+    #[arg(long, help_heading = "DNS")]
+    pub no_dns_cache: bool,
+
+    /// Send a failed request up to this many times in total before it counts as failed
+    #[arg(long, value_name = "ATTEMPTS", help_heading = "Retries", value_parser = clap::value_parser!(u32).range(1..))]
+    pub retry_attempts: Option<u32>,
+
+    /// Failures retried: "connect" for failures to connect and/or status codes, e.g. "connect,503"
+    #[arg(
+        long,
+        value_name = "CONDITIONS",
+        value_delimiter = ',',
+        default_value = "connect",
+        help_heading = "Retries",
+        requires = "retry_attempts"
+    )]
+    pub retry_on: Vec<String>,
+
+    /// Upper bound of the random delay before the first retry, doubled for every further one
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "100ms",
+        help_heading = "Retries"
+    )]
+    pub retry_backoff: String,
+
+    /// Upper bound of the random delay before any retry
+    #[arg(
+        long,
+        value_name = "DURATION",
+        default_value = "5s",
+        help_heading = "Retries"
+    )]
+    pub retry_max_backoff: String,
+
+    /// Worker threads of the runtime sending requests (default: one per CPU core)
+    #[arg(long, help_heading = "Advanced", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub worker_threads: Option<usize>,
+
+    /// Requests polled at once (default: the highest concurrency of the run)
+    #[arg(long, help_heading = "Advanced", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub stream_buffer: Option<usize>,
+
+    /// Bytes of a response body kept for extractors and GraphQL checks; longer bodies are cut
+    /// (default: 10 MiB)
+    #[arg(long, value_name = "BYTES", help_heading = "Advanced", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_body_size: Option<usize>,
+
+    /// Bytes of resident memory to stay under; nearing it, requests recorded for
+    /// --output-requests are flushed to disk and half of the --har traces are dropped (Linux only)
+    #[arg(long, value_name = "BYTES", help_heading = "Advanced", value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(1..))]
+    pub memory_ceiling: Option<u64>,
+
+    /// Directory recorded requests are flushed to under memory pressure (default: the system temp
+    /// directory)
+    #[arg(
+        long,
+        value_name = "DIR",
+        help_heading = "Advanced",
+        requires = "memory_ceiling"
+    )]
+    pub spill_dir: Option<std::path::PathBuf>,
+
+    /// Run the test again whenever one of its input files changes (--scenario, --mix, --feeder,
+    /// --url-file, a @file GraphQL query or a multipart upload), printing one line per run with
+    /// the change since the previous one; runs skip progress, exporters and output files
+    #[arg(long, conflicts_with = "merge")]
+    pub watch: bool,
+
     /// Show debug logs
     #[arg(long, default_value_t = false)]
     pub no_capture: bool,
 }
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Send a few requests and check that they all succeed quickly, a sanity check before a long run
+    ///
+    /// Passes when every response is 2xx and the p95 response time is below --max-p95; exits with
+    /// code 3 otherwise.
+    Smoke(SmokeArgs),
+
+    /// Check a test definition without running it: the run options after `--` are parsed and
+    /// validated, then linted for likely misconfigurations
+    ///
+    /// Warnings are printed to stderr; with --strict they fail the check with exit code 4.
+    Validate(ValidateArgs),
+
+    /// Check the signature of a JSON report signed with --sign-key or by the API
+    ///
+    /// Exits with code 5 when the report is unsigned, was signed by another key than --public-key
+    /// or changed after it was signed.
+    Verify(VerifyArgs),
+
+    /// Aggregate the JSON reports of repeated runs of the same test into means with 95% confidence
+    /// intervals and the run-to-run variation of each figure
+    Aggregate(AggregateArgs),
+
+    /// Compare a run with a baseline run and flag the figures that got worse beyond a tolerance,
+    /// a performance regression gate for CI
+    ///
+    /// Exits with code 6 when the requests per second dropped, or the error rate or a response
+    /// time percentile rose, by more than its tolerance.
+    Compare(CompareArgs),
+}
+
+/// Exit code of `lode compare` for a run that regressed from its baseline
+pub const REGRESSION_EXIT_CODE: u8 = 6;
+
+/// Reports and tolerances of `lode compare`
+#[derive(Args, Debug)]
+pub struct CompareArgs {
+    /// JSON reports (`--format json`) of the baseline and then the compared run, or only the
+    /// compared run with --baseline
+    #[arg(value_name = "REPORT", num_args = 1..=2, required = true)]
+    pub reports: Vec<std::path::PathBuf>,
+
+    /// JSON report of the baseline run
+    #[arg(long, value_name = "FILE")]
+    pub baseline: Option<std::path::PathBuf>,
+
+    /// Drop of the requests per second that counts as a regression, in percent (default: 5)
+    #[arg(long, value_name = "PERCENT")]
+    pub rps_tolerance: Option<f64>,
+
+    /// Rise of the median, p95, p99 or p99.9 response time that counts as a regression, in percent
+    /// (default: 10)
+    #[arg(long, value_name = "PERCENT")]
+    pub latency_tolerance: Option<f64>,
+
+    /// Rise of the error rate that counts as a regression, in percentage points (default: 1)
+    #[arg(long, value_name = "POINTS")]
+    pub error_rate_tolerance: Option<f64>,
+
+    /// Output format (text or json)
+    #[arg(short, long, default_value = "text")]
+    pub format: String,
+}
+
+impl CompareArgs {
+    /// Paths of the baseline and the compared report
+    pub fn report_paths(&self) -> Result<(&std::path::Path, &std::path::Path), String> {
+        match (&self.baseline, self.reports.as_slice()) {
+            (Some(baseline), [current]) => Ok((baseline, current)),
+            (None, [baseline, current]) => Ok((baseline, current)),
+            (Some(_), _) => Err(
+                "Give the baseline either with --baseline or as the first report, not both"
+                    .to_string(),
+            ),
+            (None, _) => Err(
+                "Give the baseline and the compared report, or the baseline with --baseline"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+/// Reports combined by `lode aggregate`
+#[derive(Args, Debug)]
+pub struct AggregateArgs {
+    /// JSON reports of the runs (`--format json`), at least two
+    #[arg(value_name = "REPORT", num_args = 2.., required = true)]
+    pub reports: Vec<std::path::PathBuf>,
+
+    /// Output format (text or json)
+    #[arg(short, long, default_value = "text")]
+    pub format: String,
+}
+
+/// Exit code of `lode validate --strict` for a definition with warnings
+pub const LINT_WARNING_EXIT_CODE: u8 = 4;
+
+/// Exit code of `lode verify` for a report whose signature does not hold
+pub const SIGNATURE_INVALID_EXIT_CODE: u8 = 5;
+
+/// Report checked by `lode verify`
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// JSON report to check
+    #[arg(value_name = "REPORT")]
+    pub report: std::path::PathBuf,
+
+    /// Public key the report must be signed with (SPKI PEM); without it the key in the report is
+    /// used, which shows the report is intact but not who signed it
+    #[arg(long, value_name = "FILE")]
+    pub public_key: Option<std::path::PathBuf>,
+}
+
+/// Run options checked by `lode validate`
+#[derive(Args, Debug)]
+pub struct ValidateArgs {
+    /// Fail on warnings, not only on errors
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Options of the run to check, as given to a run, e.g. `-- --url https://example.com -c 50`
+    #[arg(last = true, value_name = "RUN_OPTIONS")]
+    pub run: Vec<String>,
+}
+
+impl ValidateArgs {
+    /// Arguments of the load test run to check
+    pub fn run_args(&self) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("lode").chain(self.run.iter().map(String::as_str)))
+    }
+}
+
+/// Target and limits of `lode smoke`
+#[derive(Args, Debug)]
+pub struct SmokeArgs {
+    /// Target URL to check
+    #[arg(short, long)]
+    pub url: String,
+
+    /// HTTP method to use (GET, POST, etc.)
+    #[arg(short, long, default_value = "GET")]
+    pub method: String,
+
+    /// JSON body for POST/PUT requests
+    #[arg(short, long)]
+    pub body: Option<String>,
+
+    /// Custom headers (format: "key:value"; a comma followed by another "key:" starts a new one)
+    #[arg(short = 'H', long, num_args = 0..)]
+    pub headers: Option<Vec<String>>,
+
+    /// Number of requests to send
+    #[arg(short, long, default_value = "20")]
+    pub requests: u32,
+
+    /// Number of concurrent requests
+    #[arg(short, long, default_value = "2")]
+    pub concurrency: usize,
+
+    /// Highest acceptable p95 response time, e.g. "500ms"
+    #[arg(long, value_name = "DURATION", default_value = "1s")]
+    pub max_p95: String,
+
+    /// Skip verification of server certificates and host names
+    #[arg(short = 'k', long)]
+    pub insecure: bool,
+}
+
+impl SmokeArgs {
+    /// Arguments of the load test run for the check
+    pub fn run_args(&self) -> Result<Cli, clap::Error> {
+        let mut args = vec![
+            "lode".to_string(),
+            format!("--url={}", self.url),
+            format!("--method={}", self.method),
+            format!("--requests={}", self.requests),
+            format!("--concurrency={}", self.concurrency),
+            "--slo=error_rate <= 0%".to_string(),
+            format!("--slo=p95 < {}", self.max_p95),
+        ];
+        if let Some(body) = &self.body {
+            args.push(format!("--body={}", body));
+        }
+        for header in self.headers.iter().flatten() {
+            args.push(format!("--headers={}", header));
+        }
+        if self.insecure {
+            args.push("--insecure".to_string());
+        }
+        Cli::try_parse_from(args)
+    }
+}
@@ -1,85 +1,876 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use clap::Parser;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use lode_core::{
-    config::LoadTestConfig,
-    engine::LoadTestEngine,
-    http::DefaultHttpClient,
-    report::Report,
-    telemetry::{get_stdout_subscriber, init_subscriber},
+    clock::{ClockOffset, DEFAULT_CLOCK_SAMPLES},
+    config::{
+        parse_duration, AdvancedConfig, Cooldown, DnsConfig, Feeder, FormBody, GraphqlRequest,
+        LoadTestConfig, RetryPolicy, TlsConfig, TraceSampling, MAX_RATE, MIN_RATE,
+    },
+    distributed::Controller,
+    dns::parse_nameserver,
+    engine::{LoadTestEngine, RunOptions},
+    headers::{parse_header, split_headers},
+    http::{DefaultHttpClient, HttpClientOptions},
+    lint::lint,
+    live::{LiveMetrics, DEFAULT_SNAPSHOT_INTERVAL},
+    metrics::exporters::{otlp, prometheus},
+    metrics::Labels,
+    recovery::Recovery,
+    report::{
+        aggregate::Aggregate,
+        compare::Tolerances,
+        format::TextOptions,
+        signing::{self, ReportSigner},
+        stream::StreamRecord,
+        thresholds::Threshold,
+        Report,
+    },
+    run::{RunState, TestRun},
+    scenario::{mix::WorkloadMix, Scenario},
+    slo::SloTarget,
+    telemetry::{get_otlp_subscriber, get_stdout_subscriber, init_subscriber, OtlpSettings},
+    trigger::StageTrigger,
 };
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 
-use lode_cli::Cli;
+use lode_cli::duplicates::RunLog;
+use lode_cli::progress::{plain_line, plain_progress, summary_line, PlainProgress};
+use lode_cli::watch::{input_files, iteration_line, Watcher};
+use lode_cli::{
+    AggregateArgs, Cli, Command, CompareArgs, ValidateArgs, VerifyArgs, LINT_WARNING_EXIT_CODE,
+    REGRESSION_EXIT_CODE, SIGNATURE_INVALID_EXIT_CODE, SLO_VIOLATION_EXIT_CODE,
+};
+
+/// Window of --no-duplicate when --duplicate-window is not given
+const DEFAULT_DUPLICATE_WINDOW_MINUTES: u64 = 10;
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     let cli = Cli::parse();
+    let smoke = match &cli.command {
+        Some(Command::Smoke(smoke)) => Some(smoke.run_args()?),
+        Some(Command::Validate(args)) => return validate(args),
+        Some(Command::Verify(args)) => return verify(args),
+        Some(Command::Aggregate(args)) => return aggregate(args),
+        Some(Command::Compare(args)) => return compare(args),
+        None => None,
+    };
+    if let Some(cli) = smoke {
+        let code = run_on_runtime(cli)?;
+        if code == ExitCode::SUCCESS {
+            eprintln!("Smoke test passed");
+        } else {
+            eprintln!("Smoke test failed");
+        }
+        return Ok(code);
+    }
+    run_on_runtime(cli)
+}
+
+/// Run the command on a runtime sized for it
+fn run_on_runtime(cli: Cli) -> Result<ExitCode> {
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    runtime.enable_all();
+    if let Some(worker_threads) = cli.worker_threads {
+        runtime.worker_threads(worker_threads);
+    }
+    runtime.build()?.block_on(run(cli))
+}
+
+async fn run(cli: Cli) -> Result<ExitCode> {
+    let signer = report_signer(&cli)?;
+    if !cli.merge.is_empty() {
+        let reports = cli
+            .merge
+            .iter()
+            .map(|path| read_report(path, "agent"))
+            .collect::<Result<Vec<_>>>()?;
+        let mut report = Report::merge(&reports)?;
+        report.check_slos(&parse_slos(&cli)?);
+        report.check_thresholds(&parse_thresholds(&cli)?);
+        if let Some(signer) = &signer {
+            report.sign(signer)?;
+        }
+        print_report(&cli, &cli.format.to_lowercase(), &report)?;
+        return Ok(slo_exit_code(&cli, &report));
+    }
+
+    // Traces and metrics go to a collector when OTEL_EXPORTER_OTLP_ENDPOINT is set
+    let otlp = OtlpSettings::from_env()?;
+    let otlp_guard = match &otlp {
+        Some(otlp) if cli.no_capture => {
+            let (subscriber, guard) =
+                get_otlp_subscriber("lode-cli".into(), "info".into(), std::io::stdout, otlp)?;
+            init_subscriber(subscriber);
+            Some(guard)
+        }
+        Some(otlp) => {
+            let (subscriber, guard) =
+                get_otlp_subscriber("lode-cli".into(), "info".into(), std::io::sink, otlp)?;
+            init_subscriber(subscriber);
+            Some(guard)
+        }
+        None => {
+            if cli.no_capture {
+                let subscriber = get_stdout_subscriber("lode-cli".into(), "info".into());
+                init_subscriber(subscriber);
+            }
+            None
+        }
+    };
+
+    if cli.watch {
+        return watch(&cli).await;
+    }
+    if !cli.agents.is_empty() {
+        return distribute(&cli, signer.as_ref()).await;
+    }
+
+    let pb = progress_bar(cli.requests as u64)?;
+    // Without a terminal the bar garbles logs, print a progress line now and then instead
+    let mut plain = plain_progress()
+        .then(|| PlainProgress::new(Duration::from_secs(cli.progress_interval.max(1))));
+    if plain.is_some() {
+        pb.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    // Plain progress lines already carry the summary
+    let mut summary = (plain.is_none() && cli.summary_interval > 0)
+        .then(|| PlainProgress::new(Duration::from_secs(cli.summary_interval)));
+
+    let config = load_config(&cli)?;
+    check_duplicate(&cli, &config)?;
+
+    let clock_offset = match &cli.clock_sync {
+        Some(url) => Some(
+            ClockOffset::measure(url, DEFAULT_CLOCK_SAMPLES)
+                .await
+                .with_context(|| format!("Failed to measure the clock offset against {}", url))?,
+        ),
+        None => None,
+    };
+
+    let format = cli.format.to_lowercase();
+    let stream = format == "ndjson-stream";
+
+    let (live, mut snapshots) = LiveMetrics::channel(DEFAULT_SNAPSHOT_INTERVAL);
+    let live_pb = pb.clone();
+    let watcher = tokio::spawn(async move {
+        while snapshots.changed().await.is_ok() {
+            let snapshot = snapshots.borrow_and_update().clone();
+            live_pb.set_message(format!(
+                "{:.0} req/s, p95 {:.1} ms, {:.1}% errors",
+                snapshot.current_rps, snapshot.p95_response_time_ms, snapshot.error_rate
+            ));
+            if stream {
+                println!("{}", StreamRecord::Snapshot(&snapshot).to_line()?);
+            }
+            if let Some(summary) = &mut summary {
+                // The report follows the last snapshot
+                if !snapshot.finished && summary.due(&snapshot) {
+                    live_pb.println(summary_line(&snapshot));
+                }
+            }
+            if let Some(plain) = &mut plain {
+                if plain.due(&snapshot) {
+                    let total = live_pb.length().unwrap_or(snapshot.total_requests);
+                    eprintln!("{}", plain_line(&snapshot, total));
+                }
+            }
+            if snapshot.finished {
+                break;
+            }
+        }
+        anyhow::Ok(())
+    });
+
+    if let Some(port) = cli.prometheus_port {
+        let listener = tokio::net::TcpListener::bind(("0.0.0.0", port))
+            .await
+            .with_context(|| format!("Failed to listen for Prometheus scrapes on port {}", port))?;
+        tokio::spawn(prometheus::serve(listener, live.subscribe()));
+    }
+
+    if otlp.is_some() {
+        let snapshots = live.subscribe();
+        otlp::observe(
+            &otlp::global_meter(),
+            Arc::new(move || vec![(Labels::new(), snapshots.borrow().clone())]),
+        );
+    }
+
+    // Stop on Ctrl-C and still report what was measured so far
+    let cancel = CancellationToken::new();
+    let ctrl_c = cancel.clone();
+    let cancel_pb = pb.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel_pb.abandon_with_message("cancelled");
+            ctrl_c.cancel();
+        }
+    });
+
+    // Stages can be ended from outside, e.g. once a debugging session on the target is ready
+    let trigger =
+        (cli.advance_file.is_some() || cli.control_port.is_some()).then(StageTrigger::new);
+    if let (Some(trigger), Some(path)) = (&trigger, &cli.advance_file) {
+        tokio::spawn(trigger.clone().watch_file(path.clone()));
+    }
+    if let (Some(trigger), Some(port)) = (&trigger, cli.control_port) {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+            .await
+            .with_context(|| format!("Failed to listen for control requests on port {}", port))?;
+        tokio::spawn(trigger.clone().serve(listener));
+    }
+
+    // A bug surfacing mid-run keeps what was measured until then
+    let recovery = Recovery::new(&cli.recovery_file);
+    recovery.install();
 
-    if cli.no_capture {
-        let subscriber = get_stdout_subscriber("lode-cli".into(), "info".into());
-        init_subscriber(subscriber);
+    let client = DefaultHttpClient::with_options(client_options(&cli, &config)?)?;
+    let engine = LoadTestEngine::new(client)?;
+    let mut run = TestRun::new(config);
+    let report = run
+        .execute(
+            &engine,
+            RunOptions {
+                progress_bar: Some(pb),
+                clock_offset,
+                live: Some(live),
+                cancel: Some(cancel),
+                recovery: Some(recovery),
+                trigger,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    if let Some(path) = &cli.output_requests {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("parquet") => std::fs::File::create(path)
+                .map_err(Into::into)
+                .and_then(|file| report.write_parquet(file)),
+            _ => std::fs::File::create(path)
+                .map_err(Into::into)
+                .and_then(|file| report.write_csv(std::io::BufWriter::new(file))),
+        }
+        .with_context(|| format!("Failed to write requests to {}", path.display()))?;
+    }
+    if let Some(path) = &cli.har {
+        report
+            .as_har()
+            .map_err(anyhow::Error::from)
+            .and_then(|har| std::fs::write(path, har).map_err(Into::into))
+            .with_context(|| format!("Failed to write the HAR trace to {}", path.display()))?;
+    }
+    if let Some(dir) = &cli.charts {
+        report
+            .render_charts(dir)
+            .with_context(|| format!("Failed to render charts to {}", dir.display()))?;
+    }
+    // The last snapshot goes out before the summary
+    watcher.await??;
+
+    if let Some(signer) = &signer {
+        report.sign(signer)?;
     }
+    print_report(&cli, &format, report)?;
 
-    let pb = ProgressBar::new(cli.requests as u64);
+    // Flushing waits for the collector, away from the runtime threads
+    if let Some(guard) = otlp_guard {
+        tokio::task::spawn_blocking(move || drop(guard)).await?;
+    }
+
+    Ok(slo_exit_code(&cli, report))
+}
+
+/// Progress bar of the requests of a run
+fn progress_bar(requests: u64) -> Result<ProgressBar> {
+    let pb = ProgressBar::new(requests);
     pb.set_style(
         ProgressStyle::default_bar()
             .template(
-                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})",
+                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}",
             )?
             .progress_chars("#>-"),
     );
+    Ok(pb)
+}
+
+/// Split the test across the lode-api agents of --agents and report on their merged results
+async fn distribute(cli: &Cli, signer: Option<&ReportSigner>) -> Result<ExitCode> {
+    let config = load_config(cli)?;
+    let controller = Controller::new(&cli.agents)?.with_api_key(cli.agent_api_key.clone());
+
+    let pb = progress_bar(config.requests as u64)?;
+    if plain_progress() {
+        pb.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    // Stop on Ctrl-C, cancelling the agents' runs, and still report what they measured so far
+    let cancel = CancellationToken::new();
+    let ctrl_c = cancel.clone();
+    let cancel_pb = pb.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel_pb.abandon_with_message("cancelled");
+            ctrl_c.cancel();
+        }
+    });
 
+    let mut run = TestRun::new(config);
+    run.start()?;
+    let mut report = match controller.run(&run.config, Some(&pb), Some(&cancel)).await {
+        Ok(report) => report,
+        Err(e) => {
+            run.fail(e.to_string())?;
+            return Err(e).context("Failed to run the load test on the agents");
+        }
+    };
+    pb.finish_and_clear();
+    report.check_slos(&run.config.slos);
+    report.check_thresholds(&run.config.thresholds);
+    run.finish(report)?;
+    let report = run
+        .report
+        .as_mut()
+        .expect("finished runs keep their report");
+    if let Some(dir) = &cli.charts {
+        report
+            .render_charts(dir)
+            .with_context(|| format!("Failed to render charts to {}", dir.display()))?;
+    }
+    if let Some(signer) = signer {
+        report.sign(signer)?;
+    }
+    print_report(cli, &cli.format.to_lowercase(), report)?;
+    Ok(slo_exit_code(cli, report))
+}
+
+/// Run the test again every time one of its input files changes, printing how each run compares
+/// with the one before, until Ctrl-C
+async fn watch(cli: &Cli) -> Result<ExitCode> {
+    let files = input_files(cli);
+    if files.is_empty() {
+        bail!(
+            "--watch needs an input file to watch: --scenario, --mix, --feeder, --url-file, a \
+             @file GraphQL query or a multipart upload"
+        );
+    }
+    let mut watcher = Watcher::new(files);
+    let cancel = CancellationToken::new();
+    let ctrl_c = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            ctrl_c.cancel();
+        }
+    });
+    let watched = watcher
+        .files()
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    eprintln!("Watching {} for changes, Ctrl-C to stop", watched);
+
+    let mut previous: Option<Report> = None;
+    let mut iteration = 1;
+    loop {
+        match load_config(cli) {
+            Ok(config) => {
+                let client = DefaultHttpClient::with_options(client_options(cli, &config)?)?;
+                let mut run = TestRun::new(config);
+                run.execute(
+                    &LoadTestEngine::new(client)?,
+                    RunOptions {
+                        cancel: Some(cancel.clone()),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+                // A run cut short by Ctrl-C is not worth comparing
+                if run.state() == RunState::Cancelled {
+                    break;
+                }
+                let report = run.into_report().expect("finished runs keep their report");
+                println!("{}", iteration_line(iteration, &report, previous.as_ref()));
+                previous = Some(report);
+                iteration += 1;
+            }
+            Err(e) => eprintln!("Invalid test definition, waiting for a change: {:#}", e),
+        }
+        tokio::select! {
+            _ = watcher.changed() => {}
+            _ = cancel.cancelled() => break,
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Build and validate the configuration of the run from the command line
+fn load_config(cli: &Cli) -> Result<LoadTestConfig> {
+    let mut urls = cli.url.clone();
+    if let Some(path) = &cli.url_file {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read URL file {}", path.display()))?;
+        urls.extend(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string),
+        );
+    }
     let mut config = LoadTestConfig::new(
-        cli.url,
+        urls.first()
+            .cloned()
+            .context("--url or --url-file is required")?,
         cli.method.parse()?,
         cli.requests as usize,
         cli.concurrency,
         Duration::from_secs(cli.timeout),
     )?;
 
-    if let Some(body) = cli.body {
-        config.body = Some(body);
+    if let Some(body) = &cli.body {
+        config.body = Some(body.clone());
+    }
+
+    if !cli.form.is_empty() {
+        let fields = cli.form.iter().map(|field| field.parse());
+        config.form = Some(FormBody::urlencoded(fields.collect::<Result<_, _>>()?));
+    }
+    if !cli.multipart.is_empty() {
+        let fields = cli.multipart.iter().map(|field| field.parse());
+        config.form = Some(FormBody::multipart(fields.collect::<Result<_, _>>()?));
+    }
+
+    if let Some(query) = &cli.graphql {
+        let query = match query.strip_prefix('@') {
+            Some(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read GraphQL query file {}", path))?,
+            None => query.clone(),
+        };
+        let mut graphql = GraphqlRequest::new(query);
+        if let Some(variables) = &cli.graphql_variables {
+            graphql = graphql.with_variables(
+                serde_json::from_str(variables).context("Invalid GraphQL variables")?,
+            );
+        }
+        if let Some(operation) = &cli.graphql_operation {
+            graphql = graphql.with_operation_name(operation);
+        }
+        config = config.with_graphql(graphql);
     }
 
-    if let Some(headers) = cli.headers {
+    if let Some(headers) = &cli.headers {
         config.headers = headers
             .iter()
-            .map(|h| {
-                let parts: Vec<&str> = h.split(':').collect();
-                if parts.len() != 2 {
-                    anyhow::bail!("Invalid header format: {}", h);
-                }
-                Ok((parts[0].to_string(), parts[1].to_string()))
-            })
-            .collect::<Result<Vec<_>>>()?;
+            .flat_map(|h| split_headers(h))
+            .map(parse_header)
+            .collect::<Result<Vec<_>, _>>()?;
     }
 
-    let client = DefaultHttpClient::new()?;
-    let engine = LoadTestEngine::new(client)?;
-    let result = engine
-        .run(
-            config.method.into(),
-            config.url,
-            config.requests as u64,
-            config.concurrency as u64,
-            config.timeout,
-            config.headers,
-            config.body,
-            Some(pb),
-        )
-        .await?;
+    if let Some(path) = &cli.scenario {
+        let scenario = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read scenario file {}", path.display()))?;
+        let mut scenario: Scenario = serde_json::from_str(&scenario)
+            .with_context(|| format!("Invalid scenario file {}", path.display()))?;
+        if let Some(path) = &cli.mix {
+            let mix = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read weights file {}", path.display()))?;
+            scenario.apply_mix(&WorkloadMix::parse(&mix)?)?;
+        }
+        if let Some(env) = &cli.env {
+            scenario.apply_environment(env)?;
+        }
+        config.scenario = Some(scenario);
+    }
+
+    // Several URLs become a scenario of the same request per URL, broken down per URL
+    if urls.len() > 1 {
+        if config.scenario.is_some() {
+            anyhow::bail!("--scenario cannot be combined with several URLs");
+        }
+        let scenario = Scenario::from_urls(urls, config.method.clone());
+        config.scenario = Some(match cli.random_url_order {
+            true => scenario.with_random_order(),
+            false => scenario,
+        });
+    }
+    for param in &cli.random_params {
+        config.random_params.push(param.parse()?);
+    }
 
-    let report = Report::from_metrics(result).await?;
+    if let Some(path) = &cli.feeder {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read feeder file {}", path.display()))?;
+        let feeder = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("jsonl" | "ndjson") => Feeder::from_jsonl(&content),
+            _ => Feeder::from_csv(&content),
+        }
+        .with_context(|| format!("Invalid feeder file {}", path.display()))?;
+        config.feeder = Some(
+            feeder
+                .with_strategy(cli.feeder_strategy.parse()?)
+                .with_on_exhausted(cli.feeder_on_exhausted.parse()?),
+        );
+    }
+    config.on_missing_value = cli.on_missing_value.parse()?;
 
-    match cli.format.to_lowercase().as_str() {
-        "json" => println!("{}", report.as_json()?),
-        _ => println!("{}", report.as_string()),
+    for label in &cli.labels {
+        let (key, value) = label
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Invalid label format: {}", label))?;
+        config
+            .labels
+            .insert(key.trim().to_string(), value.trim().to_string());
+    }
+
+    config.rate = cli.rate;
+    config.rate_burst = cli.rate_burst;
+    config.load_model = cli.load_model.parse()?;
+    config.correct_coordinated_omission = cli.correct_coordinated_omission;
+    config.deadline = cli.deadline.map(Duration::from_secs);
+    config.cooldown = match &cli.cooldown {
+        Some(duration) => Some(Cooldown {
+            duration: parse_duration(duration)?,
+            probe_url: cli.cooldown_probe.clone(),
+        }),
+        None => None,
+    };
+    config.percentiles = cli.percentiles.clone();
+    config.region = cli.region.clone();
+    config.time_series_interval = match cli.time_series.as_deref() {
+        Some(interval) => Some(parse_duration(interval)?),
+        None => cli.charts.as_ref().map(|_| Duration::from_secs(1)),
+    };
+    config.slos = parse_slos(cli)?;
+    config.thresholds = parse_thresholds(cli)?;
+    config.abort_on = cli
+        .abort_on
+        .iter()
+        .map(|rule| rule.parse())
+        .collect::<Result<_, _>>()?;
+    config.honor_retry_after = cli.honor_retry_after;
+    config.think_time = cli.think_time.as_deref().map(str::parse).transpose()?;
+    config.header_pacing = cli
+        .pace_from_headers
+        .as_deref()
+        .map(str::parse)
+        .transpose()?;
+    config.success_codes = cli.success_codes.as_deref().map(str::parse).transpose()?;
+    config.retry = match cli.retry_attempts {
+        Some(attempts) => Some(
+            RetryPolicy::new(attempts)
+                .with_retry_on(
+                    cli.retry_on
+                        .iter()
+                        .map(|condition| condition.parse())
+                        .collect::<Result<_, _>>()?,
+                )
+                .with_backoff(
+                    parse_duration(&cli.retry_backoff)?,
+                    parse_duration(&cli.retry_max_backoff)?,
+                ),
+        ),
+        None => None,
+    };
+    config.response_body = cli.response_body.parse()?;
+    config.http_version = cli.http_version.parse()?;
+    config.tls = TlsConfig {
+        ca_cert: cli.ca_cert.clone(),
+        client_cert: cli.client_cert.clone(),
+        client_key: cli.client_key.clone(),
+        min_version: cli.tls_min_version.as_deref().map(str::parse).transpose()?,
+        insecure: cli.insecure,
+        reload_client_cert: cli.reload_client_cert,
+        session_resumption: cli.tls_session_resumption,
+        ..Default::default()
+    };
+    if !cli.dns_servers.is_empty() || cli.dns_timeout.is_some() || cli.no_dns_cache {
+        config.dns = Some(DnsConfig {
+            nameservers: cli
+                .dns_servers
+                .iter()
+                .map(|server| parse_nameserver(server))
+                .collect::<Result<_, _>>()?,
+            timeout: cli.dns_timeout.as_deref().map(parse_duration).transpose()?,
+            no_cache: cli.no_dns_cache,
+        });
+    }
+    config.advanced = AdvancedConfig {
+        worker_threads: cli.worker_threads,
+        stream_buffer: cli.stream_buffer,
+        max_body_size: cli.max_body_size,
+        memory_ceiling: cli.memory_ceiling,
+        spill_dir: cli.spill_dir.clone(),
+    };
+
+    for fault in &cli.faults {
+        config.faults.push(fault.parse()?);
+    }
+
+    for stage in &cli.stages {
+        config.stages.push(stage.parse()?);
+    }
+    config.record_requests = cli.output_requests.is_some();
+    config.trace = cli.har.as_ref().map(|_| TraceSampling {
+        percent: cli.har_sample,
+        max_entries: cli.har_max_entries,
+        max_body_size: cli.har_max_body,
+        keep_secrets: cli.har_keep_secrets,
+    });
+    config.validate()?;
+    Ok(config)
+}
+
+/// Connection settings of the HTTP client of the run
+fn client_options(cli: &Cli, config: &LoadTestConfig) -> Result<HttpClientOptions> {
+    if cli
+        .connect_rate
+        .is_some_and(|rate| !(MIN_RATE..=MAX_RATE).contains(&rate))
+    {
+        anyhow::bail!(
+            "--connect-rate must be between {} and {} connections per second",
+            MIN_RATE,
+            MAX_RATE
+        );
+    }
+    Ok(HttpClientOptions {
+        keep_alive: !cli.disable_keepalive,
+        pool_max_idle_per_host: cli.max_idle_per_host,
+        pool_idle_timeout: cli
+            .pool_idle_timeout
+            .as_deref()
+            .map(parse_duration)
+            .transpose()?,
+        max_connect_rate: cli.connect_rate,
+        ..HttpClientOptions::for_config(config)
+    })
+}
+
+/// Warn about, or refuse with --no-duplicate, an identical test started within --duplicate-window,
+/// and note this run for the next ones; runs are not checked unless one of them is set
+fn check_duplicate(cli: &Cli, config: &LoadTestConfig) -> Result<()> {
+    let minutes = match cli.duplicate_window {
+        Some(minutes) => minutes,
+        None if cli.no_duplicate => DEFAULT_DUPLICATE_WINDOW_MINUTES,
+        None => return Ok(()),
+    };
+    if minutes == 0 {
+        return Ok(());
+    }
+    let runs = RunLog::new(cli.run_log.clone(), Duration::from_secs(minutes * 60));
+    let duplicate = match runs.check_in(&config.fingerprint(), &config.url, cli.no_duplicate) {
+        Ok(duplicate) => duplicate,
+        Err(e) => {
+            eprintln!(
+                "warning: Failed to check the run against {}: {}",
+                cli.run_log.display(),
+                e
+            );
+            return Ok(());
+        }
+    };
+    if let Some(duplicate) = duplicate {
+        let message = format!(
+            "An identical test against {} started {}s ago",
+            duplicate.url,
+            duplicate.age.as_secs()
+        );
+        if cli.no_duplicate {
+            bail!("{}, not running it again (--no-duplicate)", message);
+        }
+        eprintln!("warning: {}", message);
+    }
+    Ok(())
+}
+
+/// Check the run of `lode validate` without sending a request, printing its warnings
+fn validate(args: &ValidateArgs) -> Result<ExitCode> {
+    let cli = args.run_args()?;
+    let config = load_config(&cli)?;
+    let warnings = lint(&config, &client_options(&cli, &config)?);
+    for warning in &warnings {
+        eprintln!("warning: {}", warning);
+    }
+    if args.strict && !warnings.is_empty() {
+        eprintln!(
+            "Test definition has {} {}",
+            warnings.len(),
+            if warnings.len() == 1 {
+                "warning"
+            } else {
+                "warnings"
+            }
+        );
+        return Ok(ExitCode::from(LINT_WARNING_EXIT_CODE));
+    }
+    eprintln!("Test definition is valid");
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print the means and confidence intervals of repeated runs
+fn aggregate(args: &AggregateArgs) -> Result<ExitCode> {
+    let reports = args
+        .reports
+        .iter()
+        .map(|path| read_report(path, "run"))
+        .collect::<Result<Vec<_>>>()?;
+    let aggregate = Aggregate::from_reports(&reports)?;
+    match args.format.to_lowercase().as_str() {
+        "json" => println!("{}", aggregate.as_json()?),
+        _ => println!("{}", aggregate.as_string_with(&TextOptions::default())),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print how a run changed from its baseline and fail on a regression
+fn compare(args: &CompareArgs) -> Result<ExitCode> {
+    let (baseline, current) = args.report_paths().map_err(anyhow::Error::msg)?;
+    let defaults = Tolerances::default();
+    let tolerances = Tolerances {
+        throughput_percent: args.rps_tolerance.unwrap_or(defaults.throughput_percent),
+        latency_percent: args.latency_tolerance.unwrap_or(defaults.latency_percent),
+        error_rate_points: args
+            .error_rate_tolerance
+            .unwrap_or(defaults.error_rate_points),
+    };
+    tolerances.validate()?;
+    let comparison =
+        read_report(current, "compared")?.compare(&read_report(baseline, "baseline")?, &tolerances);
+    match args.format.to_lowercase().as_str() {
+        "json" => println!("{}", comparison.as_json()?),
+        _ => println!("{}", comparison.as_string_with(&TextOptions::default())),
+    }
+    Ok(if comparison.regressed() {
+        ExitCode::from(REGRESSION_EXIT_CODE)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Check the signature of a signed JSON report
+fn verify(args: &VerifyArgs) -> Result<ExitCode> {
+    let report = std::fs::read_to_string(&args.report)
+        .with_context(|| format!("Failed to read report {}", args.report.display()))?;
+    let report: serde_json::Value = serde_json::from_str(&report)
+        .with_context(|| format!("Invalid report {}", args.report.display()))?;
+    let trusted = match &args.public_key {
+        Some(path) => Some(
+            std::fs::read_to_string(path)
+                .map_err(Into::into)
+                .and_then(|pem| signing::public_key_from_pem(&pem))
+                .with_context(|| format!("Failed to read public key {}", path.display()))?,
+        ),
+        None => None,
+    };
+
+    match signing::verify(&report, trusted.as_ref()) {
+        Ok(key) => {
+            if trusted.is_none() {
+                eprintln!(
+                    "warning: Checked against the key in the report, pass --public-key to check who signed it"
+                );
+            }
+            eprintln!(
+                "Signature is valid, signed by {}",
+                signing::encode_public_key(&key)
+            );
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(e) => {
+            eprintln!("Signature is invalid: {}", e);
+            Ok(ExitCode::from(SIGNATURE_INVALID_EXIT_CODE))
+        }
     }
+}
+
+/// Signer of `--sign-key`, read before the run so a bad key does not waste one
+fn report_signer(cli: &Cli) -> Result<Option<ReportSigner>> {
+    let Some(path) = &cli.sign_key else {
+        return Ok(None);
+    };
+    if cli.format.to_lowercase() != "json" {
+        bail!("--sign-key signs JSON reports, use it with --format json");
+    }
+    ReportSigner::from_file(path)
+        .map(Some)
+        .with_context(|| format!("Failed to read signing key {}", path.display()))
+}
+
+/// Print the outcome of the objectives and thresholds to stderr, away from the report, and fail
+/// if one is missed
+fn slo_exit_code(cli: &Cli, report: &Report) -> ExitCode {
+    // The sla format already lists them
+    if cli.format.to_lowercase() != "sla" {
+        for result in &report.slos {
+            eprintln!("{}", result.as_string_with(&text_options(cli)));
+        }
+        for result in &report.thresholds {
+            eprintln!("{}", result.as_string_with(&text_options(cli)));
+        }
+    }
+    if report.slos_passed() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(SLO_VIOLATION_EXIT_CODE)
+    }
+}
 
+/// Print the report in the selected format
+fn print_report(cli: &Cli, format: &str, report: &Report) -> Result<()> {
+    match format {
+        "json" => println!("{}", report.as_json()?),
+        "ndjson-stream" => println!("{}", StreamRecord::Summary(report).to_line()?),
+        "html" => {
+            let baseline = match &cli.baseline {
+                Some(path) => Some(read_report(path, "baseline")?),
+                None => None,
+            };
+            print!("{}", report.as_html(baseline.as_ref()));
+        }
+        "sla" => println!("{}", report.as_sla_summary(&text_options(cli))),
+        "junit" => print!("{}", report.as_junit_xml()),
+        _ => println!("{}", report.as_string_with(&text_options(cli))),
+    }
     Ok(())
 }
 
+/// Number conventions and response time units of the text formats
+fn text_options(cli: &Cli) -> TextOptions {
+    TextOptions {
+        locale: cli.locale,
+        latency_unit: cli.latency_unit,
+        precision: cli.precision,
+    }
+}
+
+/// Service level objectives of `--slo`
+fn parse_slos(cli: &Cli) -> Result<Vec<SloTarget>> {
+    Ok(cli.slos.iter().filter_map(|slo| slo.parse().ok()).collect())
+}
+
+/// Threshold expressions of `--slo`, the values that are not a plain objective
+fn parse_thresholds(cli: &Cli) -> Result<Vec<Threshold>> {
+    Ok(cli
+        .slos
+        .iter()
+        .filter(|slo| slo.parse::<SloTarget>().is_err())
+        .map(|threshold| threshold.parse())
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Read a JSON report written with `--format json`
+fn read_report(path: &Path, kind: &str) -> Result<Report> {
+    let report = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {} report {}", kind, path.display()))?;
+    serde_json::from_str(&report)
+        .with_context(|| format!("Invalid {} report {}", kind, path.display()))
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
@@ -102,7 +893,7 @@ mod tests {
         ];
 
         let cli = crate::Cli::try_parse_from(args)?;
-        assert_eq!(cli.url, "https://example.com");
+        assert_eq!(cli.url, vec!["https://example.com"]);
         assert_eq!(cli.requests, 100);
         assert_eq!(cli.concurrency, 10);
         assert_eq!(cli.method, "GET");
@@ -122,10 +913,27 @@ mod tests {
         ];
 
         let cli = crate::Cli::try_parse_from(args)?;
-        let headers = cli.headers.unwrap();
+        let headers = super::load_config(&cli)?.headers;
         assert_eq!(headers.len(), 2);
-        assert!(headers.contains(&"Authorization:Bearer token".to_string()));
-        assert!(headers.contains(&"Content-Type:application/json".to_string()));
+        assert!(headers.contains(&("Authorization".to_string(), "Bearer token".to_string())));
+        assert!(headers.contains(&("Content-Type".to_string(), "application/json".to_string())));
+
+        // A comma within a value does not split the header
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "-H",
+            "Accept: text/html, application/json",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(
+            super::load_config(&cli)?.headers,
+            [(
+                "Accept".to_string(),
+                "text/html, application/json".to_string()
+            )]
+        );
         Ok(())
     }
 
@@ -145,11 +953,872 @@ mod tests {
     }
 
     #[test]
-    fn test_cli_output_formats() -> Result<()> {
-        let args = vec!["lode", "--url", "https://example.com", "--format", "json"];
+    fn test_cli_with_scenario() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--scenario",
+            "checkout.json",
+        ];
 
         let cli = crate::Cli::try_parse_from(args)?;
-        assert_eq!(cli.format, "json");
+        assert_eq!(cli.scenario.unwrap().to_str(), Some("checkout.json"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_env() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("lode-env-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{
+                "environments": {
+                    "dev": {"url": "http://localhost:8080"},
+                    "staging": {"url": "https://staging.example.com", "auth": {"bearer": "abc"}}
+                },
+                "requests": [{"name": "users", "url": "/users"}]
+            }"#,
+        )?;
+        let scenario = path.to_str().unwrap();
+
+        let cli = crate::Cli::try_parse_from([
+            "lode",
+            "--url",
+            "https://example.com",
+            "--scenario",
+            scenario,
+            "--env",
+            "staging",
+        ])?;
+        let request = &super::load_config(&cli)?.scenario.unwrap().requests[0];
+        assert_eq!(request.url, "https://staging.example.com/users");
+        assert_eq!(request.headers["Authorization"], "Bearer abc");
+
+        // Relative URLs need an environment
+        let cli = crate::Cli::try_parse_from([
+            "lode",
+            "--url",
+            "https://example.com",
+            "--scenario",
+            scenario,
+        ])?;
+        assert!(super::load_config(&cli).is_err());
+        std::fs::remove_file(&path)?;
+
+        let args = ["lode", "--url", "https://example.com", "--env", "staging"];
+        assert!(crate::Cli::try_parse_from(args).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_url_list() -> Result<()> {
+        use lode_core::scenario::ScenarioMode;
+
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com/a",
+            "--url",
+            "https://example.com/b",
+            "--random-url-order",
+            "--random-param",
+            "id=1..1000",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        let config = super::load_config(&cli)?;
+        let scenario = config.scenario.unwrap();
+        assert_eq!(scenario.mode, ScenarioMode::Random);
+        assert_eq!(scenario.requests[1].name, "https://example.com/b");
+        assert_eq!(config.random_params[0].to_string(), "id=1..1000");
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_mix() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--scenario",
+            "checkout.json",
+            "--mix",
+            "production.csv",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.mix.unwrap().to_str(), Some("production.csv"));
+
+        // A mix only reweights scenario requests
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--mix",
+            "production.csv",
+        ];
+        assert!(crate::Cli::try_parse_from(args).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_compare_report_paths() -> Result<()> {
+        let compare = |args: &[&str]| -> Result<crate::CompareArgs> {
+            let cli = crate::Cli::try_parse_from(["lode", "compare"].iter().chain(args))?;
+            let Some(crate::Command::Compare(compare)) = cli.command else {
+                anyhow::bail!("not a compare command");
+            };
+            Ok(compare)
+        };
+        let args = compare(&["base.json", "new.json", "--rps-tolerance", "2.5"])?;
+        assert_eq!(args.rps_tolerance, Some(2.5));
+        let (baseline, current) = args.report_paths().map_err(anyhow::Error::msg)?;
+        assert_eq!(
+            (baseline.to_str(), current.to_str()),
+            (Some("base.json"), Some("new.json"))
+        );
+
+        let args = compare(&["--baseline", "base.json", "new.json"])?;
+        let (baseline, current) = args.report_paths().map_err(anyhow::Error::msg)?;
+        assert_eq!(
+            (baseline.to_str(), current.to_str()),
+            (Some("base.json"), Some("new.json"))
+        );
+
+        assert!(compare(&["new.json"])?.report_paths().is_err());
+        assert!(compare(&["--baseline", "base.json", "a.json", "b.json"])?
+            .report_paths()
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_dns_settings() -> Result<()> {
+        let args = ["lode", "--url", "https://example.com"];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(super::load_config(&cli)?.dns, None);
+
+        let args = [
+            "lode",
+            "--url",
+            "https://example.com",
+            "--dns-server",
+            "10.0.0.2",
+            "--dns-server",
+            "10.0.0.3:5353",
+            "--dns-timeout",
+            "500ms",
+            "--no-dns-cache",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        let dns = super::load_config(&cli)?.dns.unwrap();
+        assert_eq!(
+            dns.nameservers,
+            ["10.0.0.2:53".parse()?, "10.0.0.3:5353".parse()?]
+        );
+        assert_eq!(dns.timeout, Some(std::time::Duration::from_millis(500)));
+        assert!(dns.no_cache);
+
+        let args = [
+            "lode",
+            "--url",
+            "https://example.com",
+            "--dns-server",
+            "dns.example.com",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert!(super::load_config(&cli).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_header_pacing() -> Result<()> {
+        use lode_core::config::HeaderPacing;
+
+        let args = [
+            "lode",
+            "--url",
+            "https://example.com",
+            "--pace-from-headers",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(
+            super::load_config(&cli)?.header_pacing,
+            Some(HeaderPacing::default())
+        );
+
+        let args = [
+            "lode",
+            "--url",
+            "https://example.com",
+            "--pace-from-headers",
+            "RateLimit-Remaining,RateLimit-Reset,50%",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        let pacing = super::load_config(&cli)?.header_pacing.unwrap();
+        assert_eq!(pacing.remaining_header, "RateLimit-Remaining");
+        assert_eq!(pacing.share, 0.5);
+
+        let args = [
+            "lode",
+            "--url",
+            "https://example.com",
+            "--pace-from-headers",
+            "RateLimit-Remaining",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert!(super::load_config(&cli).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_think_time() -> Result<()> {
+        use lode_core::config::ThinkTime;
+        use std::time::Duration;
+
+        let args = [
+            "lode",
+            "--url",
+            "https://example.com",
+            "--think-time",
+            "1s..3s",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(
+            super::load_config(&cli)?.think_time,
+            Some(ThinkTime::Uniform {
+                min: Duration::from_secs(1),
+                max: Duration::from_secs(3)
+            })
+        );
+
+        let args = [
+            "lode",
+            "--url",
+            "https://example.com",
+            "--think-time",
+            "3s..1s",
+        ];
+        assert!(super::load_config(&crate::Cli::try_parse_from(args)?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_labels() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--label",
+            "step=login",
+            "--label",
+            "region=eu-west-1",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.labels, vec!["step=login", "region=eu-west-1"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_rate() -> Result<()> {
+        let args = vec!["lode", "--url", "https://example.com", "--rate", "50"];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.rate, Some(50.0));
+        assert_eq!(cli.load_model, "closed");
+
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--rate",
+            "50",
+            "--load-model",
+            "poisson",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(
+            super::load_config(&cli)?.load_model,
+            lode_core::config::LoadModel::Poisson
+        );
+
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--rate",
+            "50",
+            "--rate-burst",
+            "10",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(super::load_config(&cli)?.rate_burst, Some(10));
+
+        let args = vec!["lode", "--url", "https://example.com", "--rate-burst", "10"];
+        assert!(crate::Cli::try_parse_from(args).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_deadline() -> Result<()> {
+        let args = vec!["lode", "--url", "https://example.com", "--deadline", "600"];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.deadline, Some(600));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_cooldown() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--cooldown",
+            "30s",
+            "--cooldown-probe",
+            "https://example.com/health",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.cooldown.as_deref(), Some("30s"));
+        assert_eq!(
+            cli.cooldown_probe.as_deref(),
+            Some("https://example.com/health")
+        );
+
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--cooldown-probe",
+            "x",
+        ];
+        assert!(crate::Cli::try_parse_from(args).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_faults() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--fault",
+            "pause@60s+30s",
+            "--fault",
+            "throttle:10@2m+1m",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.faults, vec!["pause@60s+30s", "throttle:10@2m+1m"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_stages() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--stage",
+            "hold:5m:50",
+            "--stage",
+            "wait(stable=3):http://prometheus:9090/api/v1/query?query=replicas",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(
+            cli.stages,
+            vec![
+                "hold:5m:50",
+                "wait(stable=3):http://prometheus:9090/api/v1/query?query=replicas"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_clock_sync() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--clock-sync",
+            "http://coordinator:8080/time",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(
+            cli.clock_sync.as_deref(),
+            Some("http://coordinator:8080/time")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_agents() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--agents",
+            "http://10.0.0.5:8081,http://10.0.0.6:8081",
+            "--agent-api-key",
+            "secret",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.agents, ["http://10.0.0.5:8081", "http://10.0.0.6:8081"]);
+        assert_eq!(cli.agent_api_key.as_deref(), Some("secret"));
+
+        // Agents send back their reports only
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--agents",
+            "http://10.0.0.5:8081",
+            "--har",
+            "trace.har",
+        ];
+        assert!(crate::Cli::try_parse_from(args).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_latency_unit() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--latency-unit",
+            "us",
+            "--precision",
+            "1",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(
+            cli.latency_unit,
+            lode_core::report::format::LatencyUnit::Micros
+        );
+        assert_eq!(cli.precision, Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_region_and_merge() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--region",
+            "eu-west-1",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.region.as_deref(), Some("eu-west-1"));
+
+        let args = vec!["lode", "--merge", "eu.json", "us.json"];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert!(cli.url.is_empty());
+        assert_eq!(cli.merge.len(), 2);
+
+        assert!(crate::Cli::try_parse_from(vec!["lode"]).is_err());
+        let args = vec!["lode", "--url", "https://example.com", "--merge", "eu.json"];
+        assert!(crate::Cli::try_parse_from(args).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_time_series() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--time-series",
+            "500ms",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.time_series.as_deref(), Some("500ms"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_connection_options() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--disable-keepalive",
+            "--max-idle-per-host",
+            "8",
+            "--pool-idle-timeout",
+            "30s",
+            "--connect-rate",
+            "100",
+            "--http-version",
+            "2",
+            "--ca-cert",
+            "ca.pem",
+            "-k",
+            "--tls-session-resumption",
+            "off",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.connect_rate, Some(100.0));
+        assert_eq!(cli.http_version, "2");
+        assert_eq!(cli.ca_cert, Some(std::path::PathBuf::from("ca.pem")));
+        assert!(cli.insecure);
+        assert!(cli.disable_keepalive);
+        assert_eq!(cli.max_idle_per_host, Some(8));
+        assert_eq!(cli.pool_idle_timeout.as_deref(), Some("30s"));
+        assert_eq!(
+            super::load_config(&cli)?.tls.session_resumption,
+            Some(false)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_tuning() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--worker-threads",
+            "2",
+            "--stream-buffer",
+            "512",
+            "--max-body-size",
+            "1024",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.worker_threads, Some(2));
+        assert_eq!(cli.stream_buffer, Some(512));
+        assert_eq!(cli.max_body_size, Some(1024));
+        assert_eq!(cli.memory_ceiling, None);
+
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--memory-ceiling",
+            "2000000000",
+            "--spill-dir",
+            "/var/tmp/lode",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        let config = super::load_config(&cli)?;
+        assert_eq!(config.advanced.memory_ceiling, Some(2_000_000_000));
+        assert_eq!(
+            config.advanced.spill_dir,
+            Some(std::path::PathBuf::from("/var/tmp/lode"))
+        );
+
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--worker-threads",
+            "0",
+        ];
+        assert!(crate::Cli::try_parse_from(args).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_slos() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--slo",
+            "p95 < 250ms",
+            "--threshold",
+            "error_rate < 1%",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.slos, vec!["p95 < 250ms", "error_rate < 1%"]);
+        assert_eq!(super::parse_slos(&cli)?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_threshold_expressions() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--slo",
+            "p95 < 250ms",
+            "--threshold",
+            "http_req_duration{step:checkout} p99 < 800ms || rps > 10",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(super::parse_slos(&cli)?.len(), 1);
+        assert_eq!(super::parse_thresholds(&cli)?.len(), 1);
+
+        let args = vec!["lode", "--url", "https://example.com", "--slo", "p95 <"];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert!(super::parse_thresholds(&cli).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_abort_rules() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--abort-on",
+            "error_rate > 50% over 10s",
+            "--abort-on",
+            "100 consecutive connection failures",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(super::load_config(&cli)?.abort_on.len(), 2);
+
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--abort-on",
+            "error_rate > 50%",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert!(super::load_config(&cli).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_percentiles() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--percentiles",
+            "50,75,99.99",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.percentiles, vec![50.0, 75.0, 99.99]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_progress_interval() -> Result<()> {
+        let cli = crate::Cli::try_parse_from(vec!["lode", "--url", "https://example.com"])?;
+        assert_eq!(cli.progress_interval, 10);
+
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--progress-interval",
+            "30",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.progress_interval, 30);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_summary_interval() -> Result<()> {
+        let cli = crate::Cli::try_parse_from(vec!["lode", "--url", "https://example.com"])?;
+        assert_eq!(cli.summary_interval, 10);
+
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--summary-interval",
+            "0",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.summary_interval, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_output_formats() -> Result<()> {
+        let args = vec!["lode", "--url", "https://example.com", "--format", "json"];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.format, "json");
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_missing_data_policies() -> Result<()> {
+        use lode_core::config::{FeederExhaustion, MissingValue};
+
+        let args = ["lode", "--url", "https://example.com"];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(
+            super::load_config(&cli)?.on_missing_value,
+            MissingValue::Send
+        );
+
+        let path = std::env::temp_dir().join(format!("lode-ids-{}.csv", std::process::id()));
+        std::fs::write(&path, "id\n1\n2\n")?;
+        let args = [
+            "lode",
+            "--url",
+            "https://example.com/{{id}}",
+            "--feeder",
+            path.to_str().unwrap(),
+            "--feeder-strategy",
+            "unique",
+            "--feeder-on-exhausted",
+            "recycle",
+            "--on-missing-value",
+            "skip",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        let config = super::load_config(&cli)?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(config.on_missing_value, MissingValue::Skip);
+        assert_eq!(
+            config.feeder.unwrap().on_exhausted,
+            FeederExhaustion::Recycle
+        );
+
+        let args = [
+            "lode",
+            "--url",
+            "https://example.com",
+            "--feeder-on-exhausted",
+            "fail",
+        ];
+        assert!(crate::Cli::try_parse_from(args).is_err());
+        let args = [
+            "lode",
+            "--url",
+            "https://example.com",
+            "--on-missing-value",
+            "ignore",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert!(super::load_config(&cli).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_har() -> Result<()> {
+        use lode_core::config::TraceSampling;
+
+        let args = ["lode", "--url", "https://example.com", "--har", "run.har"];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(
+            super::load_config(&cli)?.trace,
+            Some(TraceSampling::new(1.0))
+        );
+
+        let args = [
+            "lode",
+            "--url",
+            "https://example.com",
+            "--har",
+            "run.har",
+            "--har-sample",
+            "100",
+            "--har-max-entries",
+            "20",
+            "--har-max-body",
+            "512",
+            "--har-keep-secrets",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        let trace = super::load_config(&cli)?.trace.unwrap();
+        assert_eq!(trace.percent, 100.0);
+        assert_eq!(trace.max_entries, 20);
+        assert_eq!(trace.max_body_size, 512);
+        assert!(trace.keep_secrets);
+
+        let args = ["lode", "--url", "https://example.com", "--har-sample", "5"];
+        assert!(crate::Cli::try_parse_from(args).is_err());
+        let args = [
+            "lode",
+            "--url",
+            "https://example.com",
+            "--har",
+            "run.har",
+            "--har-sample",
+            "0",
+        ];
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert!(super::load_config(&cli).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_with_output_requests() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--output-requests",
+            "results.csv",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.output_requests.unwrap().to_str(), Some("results.csv"));
+        assert_eq!(cli.recovery_file.to_str(), Some("lode-recovery.json"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_html_with_baseline() -> Result<()> {
+        let args = vec![
+            "lode",
+            "--url",
+            "https://example.com",
+            "--format",
+            "html",
+            "--baseline",
+            "baseline.json",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        assert_eq!(cli.format, "html");
+        assert_eq!(cli.baseline.unwrap().to_str(), Some("baseline.json"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_smoke_command() -> Result<()> {
+        let args = vec![
+            "lode",
+            "smoke",
+            "--url",
+            "https://example.com/health",
+            "--max-p95",
+            "500ms",
+            "-H",
+            "Authorization:Bearer token",
+        ];
+
+        let cli = crate::Cli::try_parse_from(args)?;
+        let Some(crate::Command::Smoke(smoke)) = &cli.command else {
+            panic!("smoke command not parsed");
+        };
+        let run = smoke.run_args()?;
+        assert_eq!(run.url, vec!["https://example.com/health"]);
+        assert_eq!(run.requests, 20);
+        assert_eq!(run.concurrency, 2);
+        assert_eq!(run.slos, ["error_rate <= 0%", "p95 < 500ms"]);
+        assert_eq!(
+            run.headers,
+            Some(vec!["Authorization:Bearer token".to_string()])
+        );
         Ok(())
     }
 }
@@ -0,0 +1,119 @@
+use lode_core::live::MetricsSnapshot;
+use std::io::IsTerminal;
+use std::time::Duration;
+
+/// Whether progress should be printed as plain lines instead of a progress bar
+///
+/// The bar needs a terminal on stderr; CI runners often fake one, so `CI` also selects plain lines.
+pub fn plain_progress() -> bool {
+    !std::io::stderr().is_terminal() || std::env::var_os("CI").is_some()
+}
+
+/// Progress of a run as one line of plain text, for logs that cannot redraw a bar
+pub fn plain_line(snapshot: &MetricsSnapshot, total_requests: u64) -> String {
+    let percent = if total_requests == 0 {
+        0.0
+    } else {
+        snapshot.total_requests as f64 / total_requests as f64 * 100.0
+    };
+    format!(
+        "[{:>4}s] {}/{} requests ({:.0}%), {:.0} req/s, p95 {:.1} ms, {:.1}% errors",
+        snapshot.elapsed_seconds.round() as u64,
+        snapshot.total_requests,
+        total_requests,
+        percent,
+        snapshot.current_rps,
+        snapshot.p95_response_time_ms,
+        snapshot.error_rate
+    )
+}
+
+/// Rolling summary of a run printed above the progress bar, so long runs can be followed from the
+/// scrollback without waiting for the report
+pub fn summary_line(snapshot: &MetricsSnapshot) -> String {
+    format!(
+        "[{:>4}s] {:.0} req/s, p95 {:.1} ms, {:.1}% errors",
+        snapshot.elapsed_seconds.round() as u64,
+        snapshot.current_rps,
+        snapshot.p95_response_time_ms,
+        snapshot.error_rate
+    )
+}
+
+/// Decides when the next plain progress or summary line is due
+#[derive(Debug)]
+pub struct PlainProgress {
+    interval: Duration,
+    next: Duration,
+}
+
+impl PlainProgress {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: interval,
+        }
+    }
+
+    /// Whether a line should be printed for this snapshot; the last snapshot always gets one
+    pub fn due(&mut self, snapshot: &MetricsSnapshot) -> bool {
+        if snapshot.finished {
+            return true;
+        }
+        let elapsed = Duration::from_secs_f64(snapshot.elapsed_seconds.max(0.0));
+        if elapsed < self.next {
+            return false;
+        }
+        while self.next <= elapsed {
+            self.next += self.interval;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(elapsed_seconds: f64, finished: bool) -> MetricsSnapshot {
+        MetricsSnapshot {
+            elapsed_seconds,
+            total_requests: 250,
+            current_rps: 24.6,
+            p95_response_time_ms: 120.04,
+            error_rate: 2.0,
+            finished,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_plain_line() {
+        assert_eq!(
+            plain_line(&snapshot(10.2, false), 1000),
+            "[  10s] 250/1000 requests (25%), 25 req/s, p95 120.0 ms, 2.0% errors"
+        );
+    }
+
+    #[test]
+    fn test_summary_line() {
+        assert_eq!(
+            summary_line(&snapshot(10.2, false)),
+            "[  10s] 25 req/s, p95 120.0 ms, 2.0% errors"
+        );
+    }
+
+    #[test]
+    fn test_plain_progress_interval() {
+        let mut progress = PlainProgress::new(Duration::from_secs(10));
+        assert!(!progress.due(&snapshot(1.0, false)));
+        assert!(!progress.due(&snapshot(9.9, false)));
+        assert!(progress.due(&snapshot(10.0, false)));
+        assert!(!progress.due(&snapshot(11.0, false)));
+        // A stall skips the missed lines instead of printing them all at once
+        assert!(progress.due(&snapshot(35.0, false)));
+        assert!(!progress.due(&snapshot(39.0, false)));
+        assert!(progress.due(&snapshot(40.0, false)));
+        assert!(progress.due(&snapshot(41.0, true)));
+    }
+}
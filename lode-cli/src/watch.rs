@@ -0,0 +1,154 @@
+use lode_core::report::Report;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::Cli;
+
+/// How often the watched files are checked for changes
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Files a run reads its definition from, which `--watch` re-runs the test on
+pub fn input_files(cli: &Cli) -> Vec<PathBuf> {
+    let uploads = cli.multipart.iter().filter_map(|field| {
+        let (_, value) = field.split_once('=')?;
+        let path = value.strip_prefix('@')?;
+        Some(PathBuf::from(
+            path.split_once(";type=").map_or(path, |(path, _)| path),
+        ))
+    });
+    let graphql = cli
+        .graphql
+        .as_deref()
+        .and_then(|query| query.strip_prefix('@'))
+        .map(PathBuf::from);
+    [&cli.scenario, &cli.mix, &cli.feeder, &cli.url_file]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .chain(graphql)
+        .chain(uploads)
+        .collect()
+}
+
+/// Watches files for changes by their modification time
+#[derive(Debug)]
+pub struct Watcher {
+    files: Vec<PathBuf>,
+    modified: Vec<Option<SystemTime>>,
+}
+
+impl Watcher {
+    pub fn new(files: Vec<PathBuf>) -> Self {
+        let modified = modified(&files);
+        Self { files, modified }
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Wait until one of the files is changed, created or removed since the last change
+    pub async fn changed(&mut self) {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let current = modified(&self.files);
+            if current != self.modified {
+                self.modified = current;
+                return;
+            }
+        }
+    }
+}
+
+fn modified(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|path| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
+
+/// One line summing up a run of the watch loop, with the change of every figure since the
+/// previous run
+pub fn iteration_line(iteration: usize, report: &Report, previous: Option<&Report>) -> String {
+    let error_rate = |report: &Report| match report.total_requests {
+        0 => 0.0,
+        total => report.failed_requests as f64 / total as f64 * 100.0,
+    };
+    let relative = |figure: fn(&Report) -> f64| match previous.map(figure) {
+        Some(before) if before > 0.0 => {
+            format!(" ({:+.1}%)", (figure(report) - before) / before * 100.0)
+        }
+        _ => String::new(),
+    };
+    let errors = match previous {
+        Some(previous) => format!(" ({:+.1} pp)", error_rate(report) - error_rate(previous)),
+        None => String::new(),
+    };
+    format!(
+        "#{}: {:.0} req/s{}, p50 {:.1} ms{}, p95 {:.1} ms{}, p99 {:.1} ms{}, {:.1}% errors{}",
+        iteration,
+        report.requests_per_second,
+        relative(|r| r.requests_per_second),
+        report.median_response_time_ms,
+        relative(|r| r.median_response_time_ms),
+        report.p95_response_time_ms,
+        relative(|r| r.p95_response_time_ms),
+        report.p99_response_time_ms,
+        relative(|r| r.p99_response_time_ms),
+        error_rate(report),
+        errors
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_input_files() {
+        let cli = Cli::try_parse_from([
+            "lode",
+            "--url",
+            "https://example.com",
+            "--feeder",
+            "users.csv",
+            "--multipart",
+            "name=alice",
+            "--multipart",
+            "avatar=@me.png;type=image/png",
+        ])
+        .unwrap();
+        assert_eq!(
+            input_files(&cli),
+            [PathBuf::from("users.csv"), PathBuf::from("me.png")]
+        );
+    }
+
+    #[test]
+    fn test_iteration_line() {
+        let report = |rps: f64, p95: f64, failed: u64| Report {
+            total_requests: 100,
+            failed_requests: failed,
+            requests_per_second: rps,
+            median_response_time_ms: 10.0,
+            p95_response_time_ms: p95,
+            p99_response_time_ms: 0.0,
+            ..Default::default()
+        };
+        let first = report(200.0, 40.0, 0);
+        assert_eq!(
+            iteration_line(1, &first, None),
+            "#1: 200 req/s, p50 10.0 ms, p95 40.0 ms, p99 0.0 ms, 0.0% errors"
+        );
+        assert_eq!(
+            iteration_line(2, &report(250.0, 30.0, 2), Some(&first)),
+            "#2: 250 req/s (+25.0%), p50 10.0 ms (+0.0%), p95 30.0 ms (-25.0%), p99 0.0 ms, \
+             2.0% errors (+2.0 pp)"
+        );
+    }
+}
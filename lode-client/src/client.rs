@@ -0,0 +1,312 @@
+use lode_core::live::MetricsSnapshot;
+use lode_core::report::Report;
+use reqwest::header::ACCEPT;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use std::time::{Duration, Instant};
+use tracing::{debug, instrument};
+use url::Url;
+
+use crate::error::ClientError;
+use crate::models::{ApiErrorBody, ListQuery, LoadTestList, LoadTestRequest, LoadTestStatus};
+use crate::Result;
+
+/// Client for a remote `lode-api` instance
+#[derive(Debug, Clone)]
+pub struct LodeClient {
+    base_url: Url,
+    client: Client,
+}
+
+impl LodeClient {
+    /// Create a client for the API at the given base URL
+    pub fn new(base_url: &str) -> Result<Self> {
+        Self::with_client(base_url, Client::new())
+    }
+
+    /// Create a client using a pre-configured `reqwest::Client`
+    pub fn with_client(base_url: &str, client: Client) -> Result<Self> {
+        let mut base_url =
+            Url::parse(base_url).map_err(|e| ClientError::InvalidUrl(e.to_string()))?;
+        if base_url.cannot_be_a_base() {
+            return Err(ClientError::InvalidUrl(format!(
+                "{} cannot be used as a base URL",
+                base_url
+            )));
+        }
+        if !base_url.path().ends_with('/') {
+            let path = format!("{}/", base_url.path());
+            base_url.set_path(&path);
+        }
+        Ok(Self { base_url, client })
+    }
+
+    /// Base URL of the API
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// Run a load test and wait for its report
+    #[instrument(skip_all, fields(url = %request.url))]
+    pub async fn run_load_test(&self, request: &LoadTestRequest) -> Result<Report> {
+        let url = self.endpoint("load-test")?;
+        self.send(self.client.post(url).json(request)).await
+    }
+
+    /// Queue a load test without waiting for it to finish
+    ///
+    /// Follow it with [`LodeClient::wait_for_completion`] and fetch its results with
+    /// [`LodeClient::report`].
+    #[instrument(skip_all, fields(url = %request.url))]
+    pub async fn submit_load_test(&self, request: &LoadTestRequest) -> Result<LoadTestStatus> {
+        let url = self.endpoint("load-tests")?;
+        self.send(self.client.post(url).json(request)).await
+    }
+
+    /// Get the report of a completed load test
+    #[instrument(skip(self))]
+    pub async fn report(&self, id: &str) -> Result<Report> {
+        let url = self.run_endpoint(id, Some("report"))?;
+        self.send(self.client.get(url)).await
+    }
+
+    /// Get the status and progress of a load test
+    #[instrument(skip(self))]
+    pub async fn status(&self, id: &str) -> Result<LoadTestStatus> {
+        let url = self.run_endpoint(id, None)?;
+        self.send(self.client.get(url)).await
+    }
+
+    /// Cancel a queued or running load test
+    ///
+    /// A running test keeps a partial report, available from [`LodeClient::report`] once it has
+    /// stopped.
+    #[instrument(skip(self))]
+    pub async fn cancel(&self, id: &str) -> Result<LoadTestStatus> {
+        let url = self.run_endpoint(id, None)?;
+        self.send(self.client.delete(url)).await
+    }
+
+    /// Follow the live metrics of a running load test
+    ///
+    /// The snapshots are read from the server-sent events of `GET /load-tests/{id}/stream` as the
+    /// run publishes them, the last one marked `finished`.
+    #[instrument(skip(self))]
+    pub async fn stream_metrics(&self, id: &str) -> Result<MetricsStream> {
+        let url = self.run_endpoint(id, Some("stream"))?;
+        let request = self.client.get(url).header(ACCEPT, "text/event-stream");
+        Ok(MetricsStream {
+            response: checked(request.send().await?).await?,
+            buffer: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// List load tests
+    #[instrument(skip_all)]
+    pub async fn list(&self, query: &ListQuery) -> Result<LoadTestList> {
+        let url = self.endpoint("load-tests")?;
+        self.send(self.client.get(url).query(query)).await
+    }
+
+    /// Poll a load test until it finishes or the timeout elapses
+    #[instrument(skip(self))]
+    pub async fn wait_for_completion(
+        &self,
+        id: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<LoadTestStatus> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let status = self.status(id).await?;
+            if status.is_finished() {
+                return Ok(status);
+            }
+
+            debug!(
+                "Load test {} is {}: {:.1}% complete",
+                id, status.status, status.progress.percent_complete
+            );
+
+            if Instant::now() + poll_interval > deadline {
+                return Err(ClientError::Timeout(id.to_string()));
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    fn endpoint(&self, path: &str) -> Result<Url> {
+        self.base_url
+            .join(path)
+            .map_err(|e| ClientError::InvalidUrl(e.to_string()))
+    }
+
+    /// Endpoint of a load test, with its id escaped as a single path segment
+    ///
+    /// URLs have no way to carry a `.` or `..` segment, which are refused as ids.
+    fn run_endpoint(&self, id: &str, tail: Option<&str>) -> Result<Url> {
+        if matches!(id, "" | "." | "..") {
+            return Err(ClientError::InvalidUrl(format!(
+                "'{}' is not a load test id",
+                id
+            )));
+        }
+        let mut url = self.endpoint("load-tests")?;
+        url.path_segments_mut()
+            .map_err(|_| {
+                ClientError::InvalidUrl(format!("{} cannot be used as a base URL", self.base_url))
+            })?
+            .push(id)
+            .extend(tail);
+        Ok(url)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        let response = checked(request.send().await?).await?;
+        Ok(response.json().await?)
+    }
+}
+
+/// The response if it succeeded, else the error the API answered with
+async fn checked(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    Err(match serde_json::from_str::<ApiErrorBody>(&body) {
+        Ok(error) => ClientError::Api {
+            status: status.as_u16(),
+            error: error.error,
+            details: error.details,
+        },
+        Err(_) => ClientError::Api {
+            status: status.as_u16(),
+            error: status
+                .canonical_reason()
+                .unwrap_or("Unknown error")
+                .to_string(),
+            details: (!body.is_empty()).then_some(body),
+        },
+    })
+}
+
+/// Live metrics snapshots of a load test, from [`LodeClient::stream_metrics`]
+#[derive(Debug)]
+pub struct MetricsStream {
+    response: Response,
+    /// Bytes received past the last complete event
+    buffer: Vec<u8>,
+    finished: bool,
+}
+
+impl MetricsStream {
+    /// The next snapshot, `None` once the run has finished or the API closed the stream
+    pub async fn next(&mut self) -> Option<Result<MetricsSnapshot>> {
+        while !self.finished {
+            if let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+                let event: Vec<u8> = self.buffer.drain(..end + 2).collect();
+                if let Some(snapshot) = snapshot_event(&event) {
+                    self.finished = snapshot.as_ref().is_ok_and(|s| s.finished);
+                    return Some(snapshot);
+                }
+                continue;
+            }
+            match self.response.chunk().await {
+                Ok(Some(chunk)) => self.buffer.extend_from_slice(&chunk),
+                Ok(None) => self.finished = true,
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// The snapshot carried by a server-sent event, if it is a `snapshot` event
+fn snapshot_event(event: &[u8]) -> Option<Result<MetricsSnapshot>> {
+    let event = String::from_utf8_lossy(event);
+    let mut name = None;
+    let mut data = Vec::new();
+    for line in event.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            name = Some(value.trim());
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    (name == Some("snapshot")).then(|| {
+        serde_json::from_str(&data.join("\n")).map_err(|e| ClientError::InvalidEvent(e.to_string()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_url_normalization() {
+        let client = LodeClient::new("http://localhost:8081/lode").unwrap();
+        assert_eq!(client.base_url().as_str(), "http://localhost:8081/lode/");
+        assert_eq!(
+            client.endpoint("load-tests").unwrap().as_str(),
+            "http://localhost:8081/lode/load-tests"
+        );
+    }
+
+    #[test]
+    fn test_run_id_is_escaped() {
+        let client = LodeClient::new("http://localhost:8081/lode").unwrap();
+        assert_eq!(
+            client.run_endpoint("abc", Some("report")).unwrap().as_str(),
+            "http://localhost:8081/lode/load-tests/abc/report"
+        );
+        assert_eq!(
+            client
+                .run_endpoint("../health?x=1#top", None)
+                .unwrap()
+                .as_str(),
+            "http://localhost:8081/lode/load-tests/..%2Fhealth%3Fx=1%23top"
+        );
+        assert!(matches!(
+            client.run_endpoint("..", None).unwrap_err(),
+            ClientError::InvalidUrl(_)
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_events() {
+        let snapshot = MetricsSnapshot {
+            total_requests: 3,
+            finished: true,
+            ..Default::default()
+        };
+        let event = format!(
+            "event: snapshot\ndata: {}\n\n",
+            serde_json::to_string(&snapshot).unwrap()
+        );
+        assert_eq!(snapshot_event(event.as_bytes()).unwrap().unwrap(), snapshot);
+        assert!(snapshot_event(b": keep-alive\n\n").is_none());
+        assert!(snapshot_event(b"event: status\ndata: {}\n\n").is_none());
+        assert!(matches!(
+            snapshot_event(b"event: snapshot\ndata: {\n\n"),
+            Some(Err(ClientError::InvalidEvent(_)))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_base_url() {
+        assert!(matches!(
+            LodeClient::new("not a url").unwrap_err(),
+            ClientError::InvalidUrl(_)
+        ));
+        assert!(matches!(
+            LodeClient::new("mailto:lode@example.com").unwrap_err(),
+            ClientError::InvalidUrl(_)
+        ));
+    }
+}
@@ -0,0 +1,47 @@
+/// Errors returned by the Lode API client
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("API error ({status}): {error}{}", details.as_ref().map(|d| format!(" - {}", d)).unwrap_or_default())]
+    Api {
+        status: u16,
+        error: String,
+        details: Option<String>,
+    },
+
+    #[error("Timed out waiting for load test {0}")]
+    Timeout(String),
+
+    #[error("Invalid event: {0}")]
+    InvalidEvent(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_error_display() {
+        let error = ClientError::Api {
+            status: 400,
+            error: "Invalid URL".to_string(),
+            details: Some("relative URL without a base".to_string()),
+        };
+        assert_eq!(
+            error.to_string(),
+            "API error (400): Invalid URL - relative URL without a base"
+        );
+
+        let error = ClientError::Api {
+            status: 404,
+            error: "Load test not found".to_string(),
+            details: None,
+        };
+        assert_eq!(error.to_string(), "API error (404): Load test not found");
+    }
+}
@@ -0,0 +1,14 @@
+//! Lode Client - Typed client for the Lode REST API
+//!
+//! This crate lets other Rust services trigger and observe load tests on a remote `lode-api`
+//! instance without hand-rolling HTTP calls against its JSON.
+
+pub mod client; // REST API client
+pub mod error; // Client error types
+pub mod models; // Request and response payloads
+
+pub use client::{LodeClient, MetricsStream};
+pub use error::ClientError;
+pub use models::{ListQuery, LoadTestList, LoadTestRequest, LoadTestStatus};
+
+pub type Result<T> = std::result::Result<T, ClientError>;
@@ -0,0 +1,235 @@
+use chrono::{DateTime, Utc};
+use lode_core::live::MetricsSnapshot;
+use lode_core::progress::ProgressSnapshot;
+use lode_core::scenario::Scenario;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Load test submission, mirroring the body accepted by `POST /load-test`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadTestRequest {
+    pub url: String,
+    pub method: String,
+    pub requests: u64,
+    pub concurrency: u64,
+    pub timeout_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline_ms: Option<u64>,
+    pub headers: Option<HashMap<String, String>>,
+    pub body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<Scenario>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_series_interval_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub faults: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stages: Option<Vec<String>>,
+}
+
+impl LoadTestRequest {
+    /// Create a GET load test against the given URL
+    pub fn new(url: impl Into<String>, requests: u64, concurrency: u64) -> Self {
+        Self {
+            url: url.into(),
+            method: "GET".to_string(),
+            requests,
+            concurrency,
+            timeout_ms: None,
+            deadline_ms: None,
+            headers: None,
+            body: None,
+            scenario: None,
+            labels: None,
+            region: None,
+            time_series_interval_ms: None,
+            rate: None,
+            faults: None,
+            stages: None,
+        }
+    }
+
+    /// Set the HTTP method
+    pub fn with_method(mut self, method: impl Into<String>) -> Self {
+        self.method = method.into();
+        self
+    }
+
+    /// Set the per-request timeout in milliseconds
+    pub fn with_timeout_ms(mut self, timeout_ms: u64) -> Self {
+        self.timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    /// Set the overall deadline of the run in milliseconds
+    pub fn with_deadline_ms(mut self, deadline_ms: u64) -> Self {
+        self.deadline_ms = Some(deadline_ms);
+        self
+    }
+
+    /// Add a request header
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers
+            .get_or_insert_with(HashMap::new)
+            .insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the request body
+    pub fn with_body(mut self, body: impl Into<String>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Send a weighted mix of requests instead of the single request
+    pub fn with_scenario(mut self, scenario: Scenario) -> Self {
+        self.scenario = Some(scenario);
+        self
+    }
+
+    /// Attach a label to every request, e.g. `step` or `region`
+    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels
+            .get_or_insert_with(HashMap::new)
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Tag every request with the region of the agent, overriding the region configured on the server
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    /// Report requests and response times per interval of the given milliseconds
+    pub fn with_time_series_interval_ms(mut self, interval_ms: u64) -> Self {
+        self.time_series_interval_ms = Some(interval_ms);
+        self
+    }
+
+    /// Cap throughput at the given number of requests per second
+    pub fn with_rate(mut self, rate: f64) -> Self {
+        self.rate = Some(rate);
+        self
+    }
+
+    /// Add a generator fault, e.g. `pause@60s+30s`
+    pub fn with_fault(mut self, fault: impl Into<String>) -> Self {
+        self.faults.get_or_insert_with(Vec::new).push(fault.into());
+        self
+    }
+
+    /// Add a load stage, e.g. `hold:5m:50` or `wait:<url>`
+    pub fn with_stage(mut self, stage: impl Into<String>) -> Self {
+        self.stages.get_or_insert_with(Vec::new).push(stage.into());
+        self
+    }
+}
+
+/// Status of a load test as returned by `GET /load-tests/{id}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadTestStatus {
+    pub id: String,
+    pub status: String,
+    pub url: String,
+    pub method: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub progress: ProgressSnapshot,
+    /// Live metrics of a started run
+    #[serde(default)]
+    pub metrics: Option<MetricsSnapshot>,
+    /// Why the run failed
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl LoadTestStatus {
+    /// Whether the load test has reached a final state
+    pub fn is_finished(&self) -> bool {
+        self.finished_at.is_some() || !matches!(self.status.as_str(), "queued" | "running")
+    }
+}
+
+/// Pagination, sorting and field selection for `GET /load-tests`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<String>,
+}
+
+/// A page of load tests as returned by `GET /load-tests`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadTestList {
+    pub items: Vec<serde_json::Map<String, serde_json::Value>>,
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+/// Error body returned by the API
+#[derive(Debug, Deserialize)]
+pub(crate) struct ApiErrorBody {
+    pub error: String,
+    pub details: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_test_request_builder() {
+        let request = LoadTestRequest::new("https://example.com", 100, 10)
+            .with_method("POST")
+            .with_timeout_ms(5000)
+            .with_header("Authorization", "Bearer token")
+            .with_body(r#"{"key": "value"}"#)
+            .with_label("region", "eu")
+            .with_region("eu-west-1")
+            .with_time_series_interval_ms(1000)
+            .with_rate(25.0)
+            .with_fault("pause@1s+1s")
+            .with_stage("hold:1s:5");
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["url"], "https://example.com");
+        assert_eq!(json["method"], "POST");
+        assert_eq!(json["requests"], 100);
+        assert_eq!(json["concurrency"], 10);
+        assert_eq!(json["timeout_ms"], 5000);
+        assert_eq!(json["headers"]["Authorization"], "Bearer token");
+        assert_eq!(json["body"], r#"{"key": "value"}"#);
+        assert_eq!(json["labels"]["region"], "eu");
+        assert_eq!(json["region"], "eu-west-1");
+        assert_eq!(json["time_series_interval_ms"], 1000);
+        assert_eq!(json["rate"], 25.0);
+        assert_eq!(json["faults"][0], "pause@1s+1s");
+        assert_eq!(json["stages"][0], "hold:1s:5");
+    }
+
+    #[test]
+    fn test_list_query_omits_unset_parameters() {
+        let query = ListQuery {
+            limit: Some(10),
+            sort: Some("p95".to_string()),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&query).unwrap();
+        assert_eq!(json, serde_json::json!({ "limit": 10, "sort": "p95" }));
+    }
+}
@@ -0,0 +1,314 @@
+use lode_client::{ClientError, ListQuery, LoadTestRequest, LodeClient};
+use serde_json::json;
+use std::time::Duration;
+use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn report_json(id: &str) -> serde_json::Value {
+    json!({
+        "id": id,
+        "status": "completed",
+        "total_requests": 10,
+        "successful_requests": 10,
+        "failed_requests": 0,
+        "requests_per_second": 100.0,
+        "min_response_time_ms": 1.0,
+        "max_response_time_ms": 5.0,
+        "mean_response_time_ms": 2.0,
+        "median_response_time_ms": 2.0,
+        "p95_response_time_ms": 4.0,
+        "p99_response_time_ms": 5.0,
+        "total_duration_seconds": 0.1,
+        "error_stats": null
+    })
+}
+
+fn status_json(id: &str, status: &str, completed: u64) -> serde_json::Value {
+    json!({
+        "id": id,
+        "status": status,
+        "url": "http://example.com",
+        "method": "GET",
+        "started_at": "2025-03-30T16:35:24Z",
+        "finished_at": if matches!(status, "queued" | "running") { serde_json::Value::Null } else { json!("2025-03-30T16:35:25Z") },
+        "progress": {
+            "completed_requests": completed,
+            "total_requests": 10,
+            "percent_complete": completed as f64 * 10.0,
+            "elapsed_seconds": 1.0,
+            "current_rps": 10.0,
+            "eta_seconds": null
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_run_load_test() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/load-test"))
+        .and(body_partial_json(json!({
+            "url": "http://example.com",
+            "method": "GET",
+            "requests": 10,
+            "concurrency": 2
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(report_json("run-1")))
+        .mount(&mock_server)
+        .await;
+
+    let client = LodeClient::new(&mock_server.uri()).unwrap();
+    let report = client
+        .run_load_test(&LoadTestRequest::new("http://example.com", 10, 2))
+        .await
+        .unwrap();
+
+    assert_eq!(report.id, "run-1");
+    assert_eq!(report.total_requests(), 10);
+    assert_eq!(report.successful_requests(), 10);
+}
+
+#[tokio::test]
+async fn test_submit_load_test_and_fetch_report() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/load-tests"))
+        .and(body_partial_json(json!({ "url": "http://example.com" })))
+        .respond_with(ResponseTemplate::new(202).set_body_json(status_json("run-1", "queued", 0)))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/load-tests/run-1/report"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(report_json("run-1")))
+        .mount(&mock_server)
+        .await;
+
+    let client = LodeClient::new(&mock_server.uri()).unwrap();
+    let status = client
+        .submit_load_test(&LoadTestRequest::new("http://example.com", 10, 2))
+        .await
+        .unwrap();
+    assert_eq!(status.id, "run-1");
+    assert_eq!(status.status, "queued");
+    assert!(!status.is_finished());
+
+    let report = client.report(&status.id).await.unwrap();
+    assert_eq!(report.id, "run-1");
+    assert_eq!(report.total_requests(), 10);
+}
+
+#[tokio::test]
+async fn test_cancel() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/load-tests/run-1"))
+        .respond_with(ResponseTemplate::new(202).set_body_json(status_json("run-1", "running", 5)))
+        .mount(&mock_server)
+        .await;
+
+    let client = LodeClient::new(&mock_server.uri()).unwrap();
+    let status = client.cancel("run-1").await.unwrap();
+    assert_eq!(status.id, "run-1");
+    assert_eq!(status.progress.completed_requests, 5);
+}
+
+#[tokio::test]
+async fn test_report_not_ready() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/load-tests/run-1/report"))
+        .respond_with(ResponseTemplate::new(409).set_body_json(json!({
+            "error": "Report not ready",
+            "details": "Load test 'run-1' is running"
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = LodeClient::new(&mock_server.uri()).unwrap();
+    match client.report("run-1").await.unwrap_err() {
+        ClientError::Api { status, error, .. } => {
+            assert_eq!(status, 409);
+            assert_eq!(error, "Report not ready");
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+}
+
+#[tokio::test]
+async fn test_api_errors_are_mapped() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/load-test"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": "Invalid URL",
+            "details": "relative URL without a base"
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = LodeClient::new(&mock_server.uri()).unwrap();
+    let error = client
+        .run_load_test(&LoadTestRequest::new("invalid-url", 10, 2))
+        .await
+        .unwrap_err();
+
+    match error {
+        ClientError::Api {
+            status,
+            error,
+            details,
+        } => {
+            assert_eq!(status, 400);
+            assert_eq!(error, "Invalid URL");
+            assert_eq!(details.as_deref(), Some("relative URL without a base"));
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_status_and_list() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/load-tests/run-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(status_json(
+            "run-1",
+            "completed",
+            10,
+        )))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/load-tests"))
+        .and(query_param("limit", "1"))
+        .and(query_param("sort", "p95"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "items": [{ "id": "run-1", "p95_response_time_ms": 4.0 }],
+            "total": 3,
+            "limit": 1,
+            "offset": 0
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = LodeClient::new(&mock_server.uri()).unwrap();
+
+    let status = client.status("run-1").await.unwrap();
+    assert_eq!(status.status, "completed");
+    assert_eq!(status.progress.completed_requests, 10);
+    assert!(status.is_finished());
+
+    let page = client
+        .list(&ListQuery {
+            limit: Some(1),
+            sort: Some("p95".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(page.total, 3);
+    assert_eq!(page.items[0]["id"], "run-1");
+}
+
+#[tokio::test]
+async fn test_wait_for_completion() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/load-tests/run-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(status_json("run-1", "running", 5)))
+        .up_to_n_times(2)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/load-tests/run-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(status_json(
+            "run-1",
+            "completed",
+            10,
+        )))
+        .mount(&mock_server)
+        .await;
+
+    let client = LodeClient::new(&mock_server.uri()).unwrap();
+    let status = client
+        .wait_for_completion("run-1", Duration::from_millis(10), Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(status.status, "completed");
+    assert_eq!(status.progress.percent_complete, 100.0);
+}
+
+#[tokio::test]
+async fn test_wait_for_completion_timeout() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/load-tests/run-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(status_json("run-1", "running", 5)))
+        .mount(&mock_server)
+        .await;
+
+    let client = LodeClient::new(&mock_server.uri()).unwrap();
+    let error = client
+        .wait_for_completion(
+            "run-1",
+            Duration::from_millis(20),
+            Duration::from_millis(50),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ClientError::Timeout(id) if id == "run-1"));
+}
+
+#[tokio::test]
+async fn test_stream_metrics() {
+    let snapshot = |total: u64, finished: bool| {
+        json!({
+            "elapsed_seconds": total as f64 / 10.0,
+            "total_requests": total,
+            "successful_requests": total,
+            "failed_requests": 0,
+            "current_rps": 10.0,
+            "p95_response_time_ms": 4.0,
+            "error_rate": 0.0,
+            "finished": finished
+        })
+    };
+    let body = format!(
+        "event: snapshot\ndata: {}\n\nevent: snapshot\ndata: {}\n\n",
+        snapshot(5, false),
+        snapshot(10, true)
+    );
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/load-tests/run-1/stream"))
+        .and(header("accept", "text/event-stream"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/load-tests/run-2/stream"))
+        .respond_with(ResponseTemplate::new(409).set_body_json(json!({
+            "error": "No live metrics",
+            "details": "Load test 'run-2' has no live metrics, fetch its report instead"
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = LodeClient::new(&mock_server.uri()).unwrap();
+    let mut stream = client.stream_metrics("run-1").await.unwrap();
+    let first = stream.next().await.unwrap().unwrap();
+    assert_eq!(first.total_requests, 5);
+    assert!(!first.finished);
+    let last = stream.next().await.unwrap().unwrap();
+    assert_eq!(last.total_requests, 10);
+    assert!(last.finished);
+    assert!(stream.next().await.is_none());
+
+    match client.stream_metrics("run-2").await.unwrap_err() {
+        ClientError::Api { status, error, .. } => {
+            assert_eq!(status, 409);
+            assert_eq!(error, "No live metrics");
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+}
@@ -0,0 +1,330 @@
+//! Recipes of load tests against lode-playground: staged profiles, chained flows, thresholds and
+//! data feeds
+//!
+//! Start the playground, then run every recipe or the one named:
+//!
+//! ```sh
+//! cargo run -p lode-playground
+//! cargo run -p lode-core --example cookbook -- [staged|flow|thresholds|feed] [BASE_URL]
+//! ```
+//!
+//! The scenario and feeder files of the recipes are in `examples/cookbook`, ready for `lode-cli`
+//! as well. `cargo test -p lode-core --example cookbook` runs the recipes against a mock of the
+//! playground.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use lode_core::config::{Feeder, FeederStrategy, HttpMethod, LoadTestConfig, MissingValue, Stage};
+use lode_core::engine::{LoadTestEngine, RunOptions};
+use lode_core::http::DefaultHttpClient;
+use lode_core::report::thresholds::Threshold;
+use lode_core::report::Report;
+use lode_core::scenario::Scenario;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Where `cargo run -p lode-playground` listens
+const PLAYGROUND_URL: &str = "http://127.0.0.1:8080";
+
+/// Environment of the cookbook scenarios pointing at the playground
+const PLAYGROUND_ENV: &str = "playground";
+
+/// A load test of the cookbook, built for a base URL and a length of its stages
+struct Recipe {
+    name: &'static str,
+    about: &'static str,
+    config: fn(&str, Duration) -> Result<LoadTestConfig>,
+}
+
+const RECIPES: [Recipe; 4] = [
+    Recipe {
+        name: "staged",
+        about: "Ramp up to 10 concurrent requests, hold and ramp back down",
+        config: staged,
+    },
+    Recipe {
+        name: "flow",
+        about: "Sequential flow passing values extracted from each response to the next request",
+        config: flow,
+    },
+    Recipe {
+        name: "thresholds",
+        about: "Weighted mix of endpoints gated on latency and error rate thresholds",
+        config: thresholds,
+    },
+    Recipe {
+        name: "feed",
+        about: "One request per row of a CSV file of users, each sent once",
+        config: feed,
+    },
+];
+
+fn staged(base: &str, stage: Duration) -> Result<LoadTestConfig> {
+    let ramp = |target| Stage::Ramp {
+        duration: stage,
+        target,
+        rate: None,
+    };
+    // The stages end the run, long before this many requests
+    Ok(LoadTestConfig::new(
+        format!("{}/api/v1/data", base),
+        HttpMethod::GET,
+        1_000_000,
+        1,
+        Duration::from_secs(5),
+    )?
+    .with_stage(ramp(10))
+    .with_stage(Stage::Hold {
+        duration: stage,
+        concurrency: 10,
+        rate: None,
+    })
+    .with_stage(ramp(0)))
+}
+
+fn flow(base: &str, _: Duration) -> Result<LoadTestConfig> {
+    Ok(LoadTestConfig::new(
+        base.to_string(),
+        HttpMethod::GET,
+        24,
+        4,
+        Duration::from_secs(5),
+    )?
+    .with_scenario(scenario("checkout-flow.json", base)?)
+    .with_feeder(users()?))
+}
+
+fn thresholds(base: &str, _: Duration) -> Result<LoadTestConfig> {
+    Ok(LoadTestConfig::new(
+        base.to_string(),
+        HttpMethod::GET,
+        200,
+        8,
+        Duration::from_secs(5),
+    )?
+    .with_scenario(scenario("api-mix.json", base)?)
+    .with_threshold("p95 < 1s".parse::<Threshold>()?)
+    .with_threshold("error_rate < 1%".parse::<Threshold>()?))
+}
+
+fn feed(base: &str, _: Duration) -> Result<LoadTestConfig> {
+    // Rows without a value are skipped rather than ending the run
+    Ok(LoadTestConfig::new(
+        format!("{}/api/v1/data", base),
+        HttpMethod::POST,
+        100,
+        2,
+        Duration::from_secs(5),
+    )?
+    .with_header("Content-Type".to_string(), "application/json".to_string())
+    .with_body(r#"{"name": "{{name}}", "email": "{{email}}", "data": "{{data}}"}"#.to_string())
+    .with_feeder(users()?.with_strategy(FeederStrategy::Unique))
+    .with_on_missing_value(MissingValue::Skip))
+}
+
+fn cookbook() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/cookbook")
+}
+
+/// A scenario of the cookbook with its playground environment pointed at `base`
+fn scenario(file: &str, base: &str) -> Result<Scenario> {
+    let mut scenario: Scenario =
+        serde_json::from_str(&std::fs::read_to_string(cookbook().join(file))?)?;
+    if let Some(environment) = scenario.environments.get_mut(PLAYGROUND_ENV) {
+        environment.url = Some(base.to_string());
+    }
+    scenario.apply_environment(PLAYGROUND_ENV)?;
+    Ok(scenario)
+}
+
+fn users() -> Result<Feeder> {
+    Ok(Feeder::from_csv(&std::fs::read_to_string(
+        cookbook().join("users.csv"),
+    )?)?)
+}
+
+/// Run a recipe and check its thresholds
+async fn run(config: &LoadTestConfig) -> Result<Report> {
+    let engine = LoadTestEngine::new(DefaultHttpClient::new()?)?;
+    let metrics = engine.execute(config, RunOptions::default()).await?;
+    let mut report = Report::from_metrics(metrics).await?;
+    report.check_thresholds(&config.thresholds);
+    Ok(report)
+}
+
+/// Whether a recipe ran as intended: completed, without failed requests or thresholds
+fn passed(report: &Report) -> bool {
+    report.status == "completed"
+        && report.failed_requests == 0
+        && report.thresholds.iter().all(|threshold| threshold.passed)
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let name = args.next().unwrap_or_else(|| "all".to_string());
+    let base = args.next().unwrap_or_else(|| PLAYGROUND_URL.to_string());
+    let recipes: Vec<&Recipe> = RECIPES
+        .iter()
+        .filter(|recipe| name == "all" || recipe.name == name)
+        .collect();
+    if recipes.is_empty() {
+        eprintln!("Unknown recipe {}, expected one of:", name);
+        for recipe in &RECIPES {
+            eprintln!("  {:<12}{}", recipe.name, recipe.about);
+        }
+        return ExitCode::from(2);
+    }
+
+    let mut failed = false;
+    for recipe in recipes {
+        println!("== {}: {}", recipe.name, recipe.about);
+        let report = match (recipe.config)(&base, Duration::from_secs(5)) {
+            Ok(config) => run(&config).await,
+            Err(e) => Err(e),
+        };
+        match report {
+            Ok(report) => {
+                println!("{}", report.as_string());
+                failed |= !passed(&report);
+            }
+            Err(e) => {
+                eprintln!("Recipe {} failed: {}", recipe.name, e);
+                failed = true;
+            }
+        }
+    }
+    match failed {
+        true => ExitCode::FAILURE,
+        false => ExitCode::SUCCESS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lode_core::scenario::ENDPOINT_LABEL;
+    use std::collections::BTreeSet;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Stand-in for lode-playground answering its routes like it does
+    async fn playground() -> MockServer {
+        let server = MockServer::start().await;
+        for verb in ["GET", "POST"] {
+            Mock::given(method(verb))
+                .and(path("/api/v1/data"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "message": "Request processed",
+                    "request_info": { "method": verb }
+                })))
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .and(path("/api/v1/process"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "message": "Processed data for user: alice"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v1/auth"))
+            .and(header("Authorization", "Bearer test-token"))
+            .respond_with(ResponseTemplate::new(200))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v1/auth"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/v1/error"))
+            .and(header("X-Error-Type", "404"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn cook(name: &str, server: &MockServer) -> Report {
+        let recipe = RECIPES.iter().find(|recipe| recipe.name == name).unwrap();
+        let config = (recipe.config)(&server.uri(), Duration::from_millis(200)).unwrap();
+        let report = run(&config).await.unwrap();
+        assert!(passed(&report), "{}", report.as_string());
+        report
+    }
+
+    #[tokio::test]
+    async fn test_staged() {
+        let server = playground().await;
+        let report = cook("staged", &server).await;
+        assert_eq!(report.stages.len(), 3);
+        assert!(report.total_requests > 0);
+    }
+
+    #[tokio::test]
+    async fn test_flow() {
+        let server = playground().await;
+        let report = cook("flow", &server).await;
+        assert_eq!(report.iterations, 24);
+        let endpoints: BTreeSet<&str> = report
+            .endpoints
+            .iter()
+            .map(|endpoint| endpoint.labels[ENDPOINT_LABEL].as_str())
+            .collect();
+        assert_eq!(endpoints, BTreeSet::from(["auth", "echo", "process"]));
+
+        // Every request carries what the one before extracted
+        let requests = server.received_requests().await.unwrap();
+        let echoes: Vec<_> = requests
+            .iter()
+            .filter(|request| request.url.path() == "/api/v1/data")
+            .collect();
+        assert_eq!(echoes.len(), 24);
+        assert!(echoes
+            .iter()
+            .all(|request| request.headers["x-greeting"] == "Processed data for user: alice"));
+        assert!(requests
+            .iter()
+            .filter(|request| request.url.path() == "/api/v1/auth")
+            .all(|request| request.headers["x-echoed-method"] == "GET"));
+    }
+
+    #[tokio::test]
+    async fn test_thresholds() {
+        let server = playground().await;
+        let report = cook("thresholds", &server).await;
+        assert_eq!(report.thresholds.len(), 2);
+        assert_eq!(report.total_requests, 200);
+        assert_eq!(report.status_codes[&404], 20);
+    }
+
+    #[tokio::test]
+    async fn test_feed() {
+        let server = playground().await;
+        let report = cook("feed", &server).await;
+        let rows = users().unwrap().rows.len() as u64;
+        assert_eq!(report.total_requests, rows);
+        let bodies: BTreeSet<String> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| String::from_utf8_lossy(&request.body).to_string())
+            .collect();
+        assert_eq!(bodies.len() as u64, rows);
+        assert!(bodies.iter().all(|body| !body.contains("{{")));
+    }
+
+    #[test]
+    fn test_cookbook_files_are_valid() {
+        let users = users().unwrap();
+        let columns = users.columns();
+        for file in ["checkout-flow.json", "api-mix.json"] {
+            let scenario = scenario(file, PLAYGROUND_URL).unwrap();
+            scenario.validate_with(&columns).unwrap();
+        }
+    }
+}
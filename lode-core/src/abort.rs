@@ -0,0 +1,443 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::config::{parse_duration, ConfigError};
+use crate::metrics::{ErrorKind, RequestMetrics};
+
+/// Condition ending a run early when the target is clearly broken, so it does not keep going for
+/// the rest of a long run
+///
+/// Written as `error_rate > 50% over 10s`, the share of failed requests among those completed in
+/// the last 10 seconds, or `100 consecutive failures`. Consecutive failures may be narrowed to a
+/// kind of failure, e.g. `100 consecutive timeout failures` or `100 consecutive http_503
+/// failures`; `connection` covers the DNS, TCP and TLS failures of new connections.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum AbortRule {
+    /// More than `percent` of the requests completed within `window` failed
+    ErrorRate { percent: f64, window: Duration },
+    /// `count` requests in a row failed, of the given kind of failure when set
+    ConsecutiveFailures {
+        count: u64,
+        kind: Option<FailureFilter>,
+    },
+}
+
+/// Kinds of failure counted by [`AbortRule::ConsecutiveFailures`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureFilter {
+    /// Any failure to open a new connection: DNS, TCP connect or TLS
+    Connection,
+    Kind(ErrorKind),
+}
+
+impl FailureFilter {
+    fn matches(&self, kind: ErrorKind) -> bool {
+        match self {
+            FailureFilter::Connection => {
+                matches!(kind, ErrorKind::Dns | ErrorKind::Connect | ErrorKind::Tls)
+            }
+            FailureFilter::Kind(filter) => *filter == kind,
+        }
+    }
+}
+
+impl fmt::Display for FailureFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureFilter::Connection => f.write_str("connection"),
+            FailureFilter::Kind(kind) => write!(f, "{}", kind),
+        }
+    }
+}
+
+impl AbortRule {
+    /// Check that the rule can ever be met
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        match *self {
+            AbortRule::ErrorRate { percent, window } => {
+                if !(0.0..100.0).contains(&percent) {
+                    return Err(ConfigError::InvalidAbortRule(format!(
+                        "{}: the error rate must be at least 0% and below 100%",
+                        self
+                    )));
+                }
+                if window.is_zero() {
+                    return Err(ConfigError::InvalidAbortRule(format!(
+                        "{}: the window must be longer than zero",
+                        self
+                    )));
+                }
+            }
+            AbortRule::ConsecutiveFailures { count: 0, .. } => {
+                return Err(ConfigError::InvalidAbortRule(format!(
+                    "{}: the count must be greater than 0",
+                    self
+                )))
+            }
+            AbortRule::ConsecutiveFailures { .. } => {}
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for AbortRule {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || {
+            ConfigError::InvalidAbortRule(format!(
+                "{} (expected e.g. \"error_rate > 50% over 10s\" or \"100 consecutive failures\")",
+                s
+            ))
+        };
+        let rule = if let Some(rest) = s.strip_prefix("error_rate") {
+            let (percent, window) = rest
+                .trim_start()
+                .strip_prefix('>')
+                .and_then(|rest| rest.split_once(" over "))
+                .ok_or_else(invalid)?;
+            AbortRule::ErrorRate {
+                percent: percent
+                    .trim()
+                    .strip_suffix('%')
+                    .and_then(|percent| percent.trim().parse().ok())
+                    .ok_or_else(invalid)?,
+                window: parse_duration(window.trim()).map_err(|_| invalid())?,
+            }
+        } else {
+            let words: Vec<&str> = s.split_whitespace().collect();
+            let (count, kind) = match words[..] {
+                [count, "consecutive", "failures"] => (count, None),
+                [count, "consecutive", kind, "failures"] => (count, Some(kind)),
+                _ => return Err(invalid()),
+            };
+            AbortRule::ConsecutiveFailures {
+                count: count.parse().map_err(|_| invalid())?,
+                kind: match kind {
+                    None => None,
+                    Some("connection") => Some(FailureFilter::Connection),
+                    Some(kind) => Some(FailureFilter::Kind(kind.parse().map_err(|_| invalid())?)),
+                },
+            }
+        };
+        rule.validate()?;
+        Ok(rule)
+    }
+}
+
+impl TryFrom<String> for AbortRule {
+    type Error = ConfigError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<AbortRule> for String {
+    fn from(rule: AbortRule) -> Self {
+        rule.to_string()
+    }
+}
+
+impl fmt::Display for AbortRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbortRule::ErrorRate { percent, window } => write!(
+                f,
+                "error_rate > {}% over {}ms",
+                percent,
+                window.as_secs_f64() * 1000.0
+            ),
+            AbortRule::ConsecutiveFailures { count, kind: None } => {
+                write!(f, "{} consecutive failures", count)
+            }
+            AbortRule::ConsecutiveFailures {
+                count,
+                kind: Some(kind),
+            } => write!(f, "{} consecutive {} failures", count, kind),
+        }
+    }
+}
+
+/// Watches the completed requests of a run for the first of its abort rules to be met
+///
+/// An error rate rule only applies once the run has lasted its window, so the first failures of
+/// a run do not end it on their own.
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    rules: Vec<AbortRule>,
+    started: Instant,
+    /// The longest window of the error rate rules
+    longest: Option<Duration>,
+    /// Completion time and outcome of the requests within the longest window
+    recent: VecDeque<(Instant, bool)>,
+    /// Requests that have left the longest window, dropped from the front of `recent`
+    dropped: usize,
+    /// Requests within the window of each error rate rule
+    windows: Vec<WindowCounts>,
+    /// Failures in a row so far, per rule
+    consecutive: Vec<u64>,
+    /// The first rule met
+    tripped: Option<AbortRule>,
+}
+
+/// Requests within the window of an error rate rule, counted as they enter and leave it
+#[derive(Debug, Clone, Copy, Default)]
+struct WindowCounts {
+    /// Position of the oldest request within the window, counting every request recorded
+    start: usize,
+    total: u64,
+    failed: u64,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(rules: &[AbortRule], started: Instant) -> Self {
+        Self {
+            rules: rules.to_vec(),
+            started,
+            longest: rules
+                .iter()
+                .filter_map(|rule| match rule {
+                    AbortRule::ErrorRate { window, .. } => Some(*window),
+                    AbortRule::ConsecutiveFailures { .. } => None,
+                })
+                .max(),
+            recent: VecDeque::new(),
+            dropped: 0,
+            windows: vec![WindowCounts::default(); rules.len()],
+            consecutive: vec![0; rules.len()],
+            tripped: None,
+        }
+    }
+
+    /// Count a completed request, returning the rule it met if it is the first one met
+    pub(crate) fn record(&mut self, metrics: &RequestMetrics) -> Option<AbortRule> {
+        if self.tripped.is_some() {
+            return None;
+        }
+        self.tripped = self.record_at(Instant::now(), metrics.is_success(), metrics.error_kind());
+        self.tripped
+    }
+
+    /// The rule that ended the run, if one was met
+    pub(crate) fn tripped(&self) -> Option<AbortRule> {
+        self.tripped
+    }
+
+    fn record_at(
+        &mut self,
+        now: Instant,
+        success: bool,
+        kind: Option<ErrorKind>,
+    ) -> Option<AbortRule> {
+        if self.longest.is_some() {
+            self.recent.push_back((now, success));
+        }
+
+        let mut broken = None;
+        let rules = self.rules.iter().zip(&mut self.windows);
+        for ((rule, counts), consecutive) in rules.zip(&mut self.consecutive) {
+            let met = match *rule {
+                AbortRule::ErrorRate { percent, window } => {
+                    counts.total += 1;
+                    counts.failed += u64::from(!success);
+                    while let Some(&(at, success)) = self.recent.get(counts.start - self.dropped) {
+                        if now.duration_since(at) <= window {
+                            break;
+                        }
+                        counts.start += 1;
+                        counts.total -= 1;
+                        counts.failed -= u64::from(!success);
+                    }
+                    if now.duration_since(self.started) < window {
+                        continue;
+                    }
+                    counts.failed as f64 / counts.total as f64 * 100.0 > percent
+                }
+                AbortRule::ConsecutiveFailures {
+                    count,
+                    kind: filter,
+                } => {
+                    let counted = !success
+                        && filter
+                            .is_none_or(|filter| kind.is_some_and(|kind| filter.matches(kind)));
+                    *consecutive = if counted { *consecutive + 1 } else { 0 };
+                    *consecutive >= count
+                }
+            };
+            if met && broken.is_none() {
+                broken = Some(*rule);
+            }
+        }
+
+        if let Some(longest) = self.longest {
+            while self
+                .recent
+                .front()
+                .is_some_and(|(at, _)| now.duration_since(*at) > longest)
+            {
+                self.recent.pop_front();
+                self.dropped += 1;
+            }
+        }
+        broken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abort_rule_from_str() {
+        assert_eq!(
+            "error_rate > 50% over 10s".parse::<AbortRule>().unwrap(),
+            AbortRule::ErrorRate {
+                percent: 50.0,
+                window: Duration::from_secs(10)
+            }
+        );
+        assert_eq!(
+            " 100 consecutive failures ".parse::<AbortRule>().unwrap(),
+            AbortRule::ConsecutiveFailures {
+                count: 100,
+                kind: None
+            }
+        );
+        assert_eq!(
+            "5 consecutive connection failures"
+                .parse::<AbortRule>()
+                .unwrap(),
+            AbortRule::ConsecutiveFailures {
+                count: 5,
+                kind: Some(FailureFilter::Connection)
+            }
+        );
+        assert_eq!(
+            "5 consecutive http_503 failures"
+                .parse::<AbortRule>()
+                .unwrap(),
+            AbortRule::ConsecutiveFailures {
+                count: 5,
+                kind: Some(FailureFilter::Kind(ErrorKind::Http(503)))
+            }
+        );
+        for invalid in [
+            "error_rate > 100% over 10s",
+            "error_rate > 50% over 0s",
+            "error_rate < 50% over 10s",
+            "error_rate > 50%",
+            "0 consecutive failures",
+            "5 consecutive flaky failures",
+            "stop when broken",
+        ] {
+            assert!(
+                matches!(
+                    invalid.parse::<AbortRule>(),
+                    Err(ConfigError::InvalidAbortRule(_))
+                ),
+                "{} should be rejected",
+                invalid
+            );
+        }
+
+        let rule: AbortRule = serde_json::from_str("\"error_rate > 50% over 10s\"").unwrap();
+        assert_eq!(
+            serde_json::to_string(&rule).unwrap(),
+            "\"error_rate > 50% over 10000ms\""
+        );
+    }
+
+    #[test]
+    fn test_circuit_breaker_error_rate() {
+        let started = Instant::now();
+        let rule: AbortRule = "error_rate > 50% over 10s".parse().unwrap();
+        let mut breaker = CircuitBreaker::new(&[rule], started);
+        let at = |seconds: u64| started + Duration::from_secs(seconds);
+
+        // Not before the run has lasted the window
+        assert_eq!(
+            breaker.record_at(at(1), false, Some(ErrorKind::Timeout)),
+            None
+        );
+        assert_eq!(breaker.record_at(at(9), true, None), None);
+        // 2 of 3 failed within the window
+        assert_eq!(
+            breaker.record_at(at(10), false, Some(ErrorKind::Timeout)),
+            Some(rule)
+        );
+        // The first failure has left the window: 1 of 3
+        assert_eq!(breaker.record_at(at(12), true, None), None);
+    }
+
+    #[test]
+    fn test_circuit_breaker_error_rate_windows() {
+        let started = Instant::now();
+        let long: AbortRule = "error_rate > 75% over 10s".parse().unwrap();
+        let short: AbortRule = "error_rate > 50% over 2s".parse().unwrap();
+        let mut breaker = CircuitBreaker::new(&[long, short], started);
+        let at = |seconds: u64| started + Duration::from_secs(seconds);
+        let timeout = Some(ErrorKind::Timeout);
+
+        for second in 0..10 {
+            assert_eq!(breaker.record_at(at(second), true, None), None);
+        }
+        // 1, then 2 of 4 failed over 2s
+        assert_eq!(breaker.record_at(at(10), false, timeout), None);
+        assert_eq!(breaker.record_at(at(10), false, timeout), None);
+        // 3 of 5 over 2s, 3 of 12 over 10s
+        assert_eq!(breaker.record_at(at(10), false, timeout), Some(short));
+
+        // Only failures are left within 2s, and the successes of the first 3s have left the 10s
+        // window: 21 of 28 over 10s, then 22 of 29
+        for _ in 0..18 {
+            assert_eq!(breaker.record_at(at(13), false, timeout), Some(short));
+        }
+        assert_eq!(breaker.record_at(at(13), false, timeout), Some(long));
+        assert_eq!(breaker.recent.len(), 29);
+    }
+
+    #[test]
+    fn test_circuit_breaker_consecutive_failures() {
+        let started = Instant::now();
+        let connection: AbortRule = "3 consecutive connection failures".parse().unwrap();
+        let any: AbortRule = "4 consecutive failures".parse().unwrap();
+        let mut breaker = CircuitBreaker::new(&[connection, any], started);
+
+        assert_eq!(
+            breaker.record_at(started, false, Some(ErrorKind::Dns)),
+            None
+        );
+        assert_eq!(
+            breaker.record_at(started, false, Some(ErrorKind::Connect)),
+            None
+        );
+        // A timeout breaks the connection failure streak, not the other one
+        assert_eq!(
+            breaker.record_at(started, false, Some(ErrorKind::Timeout)),
+            None
+        );
+        assert_eq!(
+            breaker.record_at(started, false, Some(ErrorKind::Tls)),
+            Some(any)
+        );
+
+        let mut breaker = CircuitBreaker::new(&[connection], started);
+        for _ in 0..2 {
+            assert_eq!(
+                breaker.record_at(started, false, Some(ErrorKind::Connect)),
+                None
+            );
+        }
+        assert_eq!(breaker.record_at(started, true, None), None);
+        assert_eq!(
+            breaker.record_at(started, false, Some(ErrorKind::Connect)),
+            None
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use reqwest::{Client, RequestBuilder};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{info, instrument};
+
+use crate::error::{Error, Result};
+
+/// Default number of exchanges used to estimate a clock offset
+pub const DEFAULT_CLOCK_SAMPLES: u32 = 8;
+
+/// Time served by a coordinator for the clock handshake
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ClockReading {
+    /// Milliseconds since the Unix epoch on the coordinator's clock
+    pub unix_time_ms: i64,
+}
+
+impl ClockReading {
+    /// Read the local clock
+    pub fn now() -> Self {
+        Self {
+            unix_time_ms: Utc::now().timestamp_millis(),
+        }
+    }
+}
+
+/// Offset of the local clock from a reference clock, such as the coordinator of a distributed run
+///
+/// Timestamps taken by different generators are comparable once each is shifted by its own
+/// offset, so their per-second buckets line up when the results are merged.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ClockOffset {
+    /// Milliseconds to add to a local timestamp to get the reference time
+    pub offset_ms: f64,
+
+    /// Round trip of the exchange the offset was taken from, the bound on its error
+    pub round_trip_ms: f64,
+}
+
+impl ClockOffset {
+    /// Offset from one exchange, assuming the reference read its clock halfway through
+    ///
+    /// This is the NTP estimate: the error is at most half the round trip.
+    pub fn from_exchange(
+        sent: DateTime<Utc>,
+        reference: DateTime<Utc>,
+        received: DateTime<Utc>,
+    ) -> Self {
+        let round_trip = received - sent;
+        let midpoint = sent + round_trip / 2;
+        Self {
+            offset_ms: millis(reference - midpoint),
+            round_trip_ms: millis(round_trip),
+        }
+    }
+
+    /// The offset with the shortest round trip, which has the tightest error bound
+    pub fn best(samples: impl IntoIterator<Item = ClockOffset>) -> Option<Self> {
+        samples
+            .into_iter()
+            .min_by(|a, b| a.round_trip_ms.total_cmp(&b.round_trip_ms))
+    }
+
+    /// Offset of the reference clock from the local one, with the same error bound
+    pub fn reversed(&self) -> Self {
+        Self {
+            offset_ms: -self.offset_ms,
+            round_trip_ms: self.round_trip_ms,
+        }
+    }
+
+    /// Convert a local timestamp to the reference clock
+    pub fn apply(&self, local: DateTime<Utc>) -> DateTime<Utc> {
+        local + TimeDelta::microseconds((self.offset_ms * 1000.0).round() as i64)
+    }
+
+    /// Estimate the offset from the coordinator serving [`ClockReading`]s at `url`
+    #[instrument]
+    pub async fn measure(url: &str, samples: u32) -> Result<Self> {
+        let client = Client::new();
+        let offset = Self::measure_with(|| client.get(url), samples).await?;
+        info!(
+            "Clock offset from {}: {:.1}ms (round trip {:.1}ms)",
+            url, offset.offset_ms, offset.round_trip_ms
+        );
+        Ok(offset)
+    }
+
+    /// Estimate the offset from the [`ClockReading`]s answering `request`, e.g. one carrying the
+    /// coordinator's API key
+    pub async fn measure_with(request: impl Fn() -> RequestBuilder, samples: u32) -> Result<Self> {
+        let mut offsets = Vec::with_capacity(samples as usize);
+        for _ in 0..samples.max(1) {
+            let sent = Utc::now();
+            let reading: ClockReading = request()
+                .timeout(Duration::from_secs(5))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            let received = Utc::now();
+
+            let reference =
+                DateTime::from_timestamp_millis(reading.unix_time_ms).ok_or_else(|| {
+                    Error::Http(format!("Invalid coordinator time {}", reading.unix_time_ms))
+                })?;
+            offsets.push(Self::from_exchange(sent, reference, received));
+        }
+
+        Ok(Self::best(offsets).expect("at least one clock sample is taken"))
+    }
+}
+
+fn millis(delta: TimeDelta) -> f64 {
+    delta.num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_from_exchange() {
+        let sent = DateTime::from_timestamp_millis(1_000_000).unwrap();
+        let received = sent + TimeDelta::milliseconds(20);
+
+        // The reference clock is 500ms ahead and read halfway through the exchange
+        let reference = sent + TimeDelta::milliseconds(510);
+        let offset = ClockOffset::from_exchange(sent, reference, received);
+
+        assert_eq!(offset.offset_ms, 500.0);
+        assert_eq!(offset.round_trip_ms, 20.0);
+        assert_eq!(offset.apply(sent), sent + TimeDelta::milliseconds(500));
+        assert_eq!(
+            offset.reversed().apply(sent),
+            sent - TimeDelta::milliseconds(500)
+        );
+    }
+
+    #[test]
+    fn test_best_offset_has_shortest_round_trip() {
+        let offset = |offset_ms, round_trip_ms| ClockOffset {
+            offset_ms,
+            round_trip_ms,
+        };
+
+        assert_eq!(
+            ClockOffset::best([offset(30.0, 80.0), offset(-2.0, 4.0), offset(10.0, 12.0)]),
+            Some(offset(-2.0, 4.0))
+        );
+        assert_eq!(ClockOffset::best([]), None);
+    }
+}
@@ -1,8 +1,32 @@
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
 use serde::{Deserialize, Serialize};
-use std::{str::FromStr, time::Duration};
+use sha2::{Digest, Sha256};
+use std::{path::PathBuf, str::FromStr, time::Duration};
 use thiserror::Error;
 use url::Url;
 
+use crate::headers::validate_header;
+use crate::metrics::{Labels, REGION_LABEL};
+use crate::report::thresholds::Threshold;
+use crate::scenario::Scenario;
+use crate::slo::SloTarget;
+
+pub use crate::abort::AbortRule;
+pub use crate::cooldown::Cooldown;
+pub use crate::dns::DnsConfig;
+pub use crate::faults::{FaultAction, GeneratorFault};
+pub use crate::feeder::{Feeder, FeederExhaustion, FeederStrategy};
+pub use crate::form::{FormBody, FormEncoding, FormField};
+pub use crate::graphql::GraphqlRequest;
+pub use crate::metrics::SuccessCodes;
+pub use crate::pacing::HeaderPacing;
+pub use crate::retry::{RetryCondition, RetryPolicy};
+pub use crate::scenario::query::RandomParam;
+pub use crate::stages::{Stage, WaitCondition};
+pub use crate::think::ThinkTime;
+pub use crate::trace::TraceSampling;
+
 #[derive(Debug, Error)]
 pub enum ConfigError {
     #[error("Invalid URL: {0}")]
@@ -15,11 +39,68 @@ pub enum ConfigError {
     InvalidTimeout(String),
     #[error("Invalid method: {0}")]
     InvalidMethod(String),
+    #[error("Invalid header: {0}")]
+    InvalidHeader(String),
+    #[error("Invalid duration: {0}")]
+    InvalidDuration(String),
+    #[error("Invalid fault: {0}")]
+    InvalidFault(String),
+    #[error("Invalid stage: {0}")]
+    InvalidStage(String),
+    #[error("Invalid rate: {0}")]
+    InvalidRate(String),
+    #[error("Invalid label: {0}")]
+    InvalidLabel(String),
+    #[error("Invalid scenario: {0}")]
+    InvalidScenario(String),
+    #[error("Invalid percentile: {0}")]
+    InvalidPercentile(String),
+    #[error("Invalid SLO: {0}")]
+    InvalidSlo(String),
+    #[error("Invalid tuning: {0}")]
+    InvalidTuning(String),
+    #[error("Invalid HTTP version: {0}")]
+    InvalidHttpVersion(String),
+    #[error("Invalid TLS settings: {0}")]
+    InvalidTls(String),
+    #[error("Invalid retry policy: {0}")]
+    InvalidRetry(String),
+    #[error("Invalid response body policy: {0}")]
+    InvalidResponseBody(String),
+    #[error("Invalid feeder: {0}")]
+    InvalidFeeder(String),
+    #[error("Invalid missing value policy: {0}")]
+    InvalidMissingValue(String),
+    #[error("Invalid query parameter: {0}")]
+    InvalidQueryParam(String),
+    #[error("Invalid form: {0}")]
+    InvalidForm(String),
+    #[error("Invalid cooldown: {0}")]
+    InvalidCooldown(String),
+    #[error("Invalid GraphQL request: {0}")]
+    InvalidGraphql(String),
+    #[error("Invalid load model: {0}")]
+    InvalidLoadModel(String),
+    #[error("Invalid think time: {0}")]
+    InvalidThinkTime(String),
+    #[error("Invalid threshold: {0}")]
+    InvalidThreshold(String),
+    #[error("Invalid abort rule: {0}")]
+    InvalidAbortRule(String),
+    #[error("Invalid success codes: {0}")]
+    InvalidSuccessCodes(String),
+    #[error("Invalid header pacing: {0}")]
+    InvalidPacing(String),
+    #[error("Invalid DNS settings: {0}")]
+    InvalidDns(String),
+    #[error("Invalid trace sampling: {0}")]
+    InvalidTrace(String),
 }
 
 /// HTTP methods supported by the load tester
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub enum HttpMethod {
+    #[default]
     GET,
     POST,
     PUT,
@@ -54,8 +135,454 @@ impl FromStr for HttpMethod {
     }
 }
 
+/// HTTP version spoken with the target
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpVersion {
+    /// HTTP/1.1, or HTTP/2 when a TLS server offers it through ALPN
+    #[default]
+    Auto,
+    /// HTTP/1.1 only
+    Http1,
+    /// HTTP/2 only, with prior knowledge on plain HTTP (h2c) and through ALPN over TLS
+    Http2,
+}
+
+impl FromStr for HttpVersion {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Ok(HttpVersion::Auto),
+            "1.1" | "http1" | "http/1.1" => Ok(HttpVersion::Http1),
+            "2" | "http2" | "http/2" | "h2" | "h2c" => Ok(HttpVersion::Http2),
+            _ => Err(ConfigError::InvalidHttpVersion(format!(
+                "{} (expected auto, 1.1 or 2)",
+                s
+            ))),
+        }
+    }
+}
+
+/// How the engine decides when requests start
+///
+/// In the closed model a slow target slows the load down: requests wait for a free virtual user
+/// before their rate limiter slot, so the requests it would have received are never sent and
+/// never measured (coordinated omission). The open models schedule requests at `rate` whatever is
+/// in flight and count response times from the scheduled start, so the time a request waits for
+/// `concurrency`, then a cap on outstanding requests, shows in its response time.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadModel {
+    /// Virtual users send their next request once the previous one completes, at most `rate` per
+    /// second
+    #[default]
+    Closed,
+    /// Requests arrive at a constant `rate`
+    Open,
+    /// Requests arrive at `rate` on average, with random gaps as independent users would (a
+    /// Poisson process)
+    Poisson,
+}
+
+impl FromStr for LoadModel {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "closed" => Ok(LoadModel::Closed),
+            "open" => Ok(LoadModel::Open),
+            "poisson" => Ok(LoadModel::Poisson),
+            _ => Err(ConfigError::InvalidLoadModel(format!(
+                "{} (expected closed, open or poisson)",
+                s
+            ))),
+        }
+    }
+}
+
+/// What the engine does with the body of a response
+///
+/// Bodies are consumed as they arrive on the wire: no `Accept-Encoding` is sent and nothing is
+/// decompressed or decoded, whatever the policy.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseBody {
+    /// Read the body chunk by chunk and drop it, so the connection goes back to the pool and the
+    /// response time covers the download
+    #[default]
+    Drain,
+    /// Read the whole body into memory, up to the max body size of the advanced settings, as
+    /// extractors of a scenario need; requests with extractors read their bodies whatever the
+    /// policy
+    Read,
+    /// Drop the response after its headers; the connection is then closed unless the body had
+    /// already arrived, and the response time ends with the first byte
+    Discard,
+}
+
+impl FromStr for ResponseBody {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "drain" => Ok(ResponseBody::Drain),
+            "read" => Ok(ResponseBody::Read),
+            "discard" => Ok(ResponseBody::Discard),
+            _ => Err(ConfigError::InvalidResponseBody(format!(
+                "{} (expected drain, read or discard)",
+                s
+            ))),
+        }
+    }
+}
+
+/// What the engine does with a request whose `{{name}}` placeholder has no value, e.g. a column
+/// missing from a row of the feeder
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingValue {
+    /// End the run as aborted, naming the placeholder
+    Abort,
+    /// Skip the rest of the iteration without sending it; the skipped requests are counted in the
+    /// report
+    Skip,
+    /// Send the request with the placeholder as it is, as runs did before the policy existed
+    #[default]
+    Send,
+}
+
+impl FromStr for MissingValue {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "abort" => Ok(MissingValue::Abort),
+            "skip" => Ok(MissingValue::Skip),
+            "send" => Ok(MissingValue::Send),
+            _ => Err(ConfigError::InvalidMissingValue(format!(
+                "{} (expected abort, skip or send)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Lowest TLS version accepted from the target
+///
+/// The native TLS backend cannot require TLS 1.3, while rustls, used with
+/// [`TlsConfig::session_resumption`], speaks TLS 1.2 and 1.3 only: 1.3 needs session resumption
+/// set and 1.0 and 1.1 cannot go with it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TlsVersion {
+    #[serde(rename = "1.0")]
+    Tls1_0,
+    #[serde(rename = "1.1")]
+    Tls1_1,
+    #[serde(rename = "1.2")]
+    Tls1_2,
+    #[serde(rename = "1.3")]
+    Tls1_3,
+}
+
+impl From<TlsVersion> for reqwest::tls::Version {
+    fn from(version: TlsVersion) -> Self {
+        match version {
+            TlsVersion::Tls1_0 => reqwest::tls::Version::TLS_1_0,
+            TlsVersion::Tls1_1 => reqwest::tls::Version::TLS_1_1,
+            TlsVersion::Tls1_2 => reqwest::tls::Version::TLS_1_2,
+            TlsVersion::Tls1_3 => reqwest::tls::Version::TLS_1_3,
+        }
+    }
+}
+
+impl FromStr for TlsVersion {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let version = s.trim().to_lowercase();
+        match version.strip_prefix("tls").unwrap_or(&version).trim() {
+            "1.0" | "1" => Ok(TlsVersion::Tls1_0),
+            "1.1" => Ok(TlsVersion::Tls1_1),
+            "1.2" => Ok(TlsVersion::Tls1_2),
+            "1.3" => Ok(TlsVersion::Tls1_3),
+            _ => Err(ConfigError::InvalidTls(format!(
+                "Unsupported minimum TLS version {} (expected 1.0, 1.1, 1.2 or 1.3)",
+                s
+            ))),
+        }
+    }
+}
+
+/// TLS settings of the connections to the target, applied by whoever builds the HTTP client
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TlsConfig {
+    /// PEM file of CA certificates trusted on top of the system's
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+    /// PEM file of the client certificate presented for mutual TLS, together with `client_key`
+    #[serde(default)]
+    pub client_cert: Option<PathBuf>,
+    /// PEM file of the PKCS #8 private key of `client_cert`
+    #[serde(default)]
+    pub client_key: Option<PathBuf>,
+    /// PEM of CA certificates trusted on top of the system's, given inline instead of `ca_cert`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert_pem: Option<String>,
+    /// PEM of the client certificate, given inline instead of `client_cert`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert_pem: Option<String>,
+    /// PEM of the PKCS #8 private key of the client certificate, given inline instead of
+    /// `client_key`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key_pem: Option<String>,
+    /// Lowest TLS version accepted, the backend's default when unset
+    #[serde(default)]
+    pub min_version: Option<TlsVersion>,
+    /// Accept any server certificate and host name, e.g. self-signed certificates of a test stand
+    #[serde(default)]
+    pub insecure: bool,
+    /// Load the client certificate and key again when their files change during the run, for
+    /// short-lived certificates renewed while a soak test runs
+    #[serde(default)]
+    pub reload_client_cert: bool,
+    /// Resume earlier TLS sessions when opening new connections, or always do a full handshake
+    ///
+    /// Unset keeps the native TLS backend, which never resumes. Either value switches the run to
+    /// rustls, speaking TLS 1.2 and 1.3 only, and reports the share of handshakes resumed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_resumption: Option<bool>,
+}
+
+impl TlsConfig {
+    /// Whether a client certificate is given, from a file or inline
+    pub fn has_client_cert(&self) -> bool {
+        self.client_cert.is_some() || self.client_cert_pem.is_some()
+    }
+
+    /// Whether the key of the client certificate is given, from a file or inline
+    pub fn has_client_key(&self) -> bool {
+        self.client_key.is_some() || self.client_key_pem.is_some()
+    }
+
+    /// Check the settings, parsing the PEM given inline so that a bad one is refused up front
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for (name, file, inline) in [
+            (
+                "CA certificate",
+                self.ca_cert.is_some(),
+                self.ca_cert_pem.is_some(),
+            ),
+            (
+                "client certificate",
+                self.client_cert.is_some(),
+                self.client_cert_pem.is_some(),
+            ),
+            (
+                "client key",
+                self.client_key.is_some(),
+                self.client_key_pem.is_some(),
+            ),
+        ] {
+            if file && inline {
+                return Err(ConfigError::InvalidTls(format!(
+                    "The {} is given both as a file and inline",
+                    name
+                )));
+            }
+        }
+        if self.has_client_cert() != self.has_client_key() {
+            return Err(ConfigError::InvalidTls(
+                "A client certificate and its key must be given together".to_string(),
+            ));
+        }
+        match (self.min_version, self.session_resumption) {
+            (Some(TlsVersion::Tls1_3), None) => {
+                return Err(ConfigError::InvalidTls(
+                    "A minimum TLS version of 1.3 requires session_resumption to be set, the \
+                     native TLS backend cannot require it"
+                        .to_string(),
+                ))
+            }
+            (Some(TlsVersion::Tls1_0 | TlsVersion::Tls1_1), Some(_)) => {
+                return Err(ConfigError::InvalidTls(
+                    "session_resumption speaks TLS 1.2 and 1.3 only, so the minimum TLS version \
+                     must be 1.2 or 1.3"
+                        .to_string(),
+                ))
+            }
+            _ => {}
+        }
+        if self.reload_client_cert && self.client_cert.is_none() {
+            return Err(ConfigError::InvalidTls(
+                "Reloading the client certificate requires a client certificate file".to_string(),
+            ));
+        }
+        if let Some(pem) = &self.ca_cert_pem {
+            certificates_pem(pem, "ca_cert_pem")?;
+        }
+        if let Some(pem) = &self.client_cert_pem {
+            certificates_pem(pem, "client_cert_pem")?;
+        }
+        if let Some(pem) = &self.client_key_pem {
+            match PrivateKeyDer::from_pem_slice(pem.as_bytes()) {
+                Ok(PrivateKeyDer::Pkcs8(_)) => {}
+                Ok(_) => {
+                    return Err(ConfigError::InvalidTls(
+                        "client_key_pem is not a PKCS #8 private key".to_string(),
+                    ))
+                }
+                Err(e) => {
+                    return Err(ConfigError::InvalidTls(format!(
+                        "Invalid PEM in client_key_pem: {}",
+                        e
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The settings with the certificate and key files read into inline PEM, for agents that
+    /// cannot read the files of the host that starts the run
+    pub fn inlined(&self) -> Result<TlsConfig, ConfigError> {
+        if self.reload_client_cert {
+            return Err(ConfigError::InvalidTls(
+                "Reloading the client certificate needs its files on the host running the test"
+                    .to_string(),
+            ));
+        }
+        let read = |path: &Option<PathBuf>, inline: &Option<String>| match path {
+            Some(path) => std::fs::read_to_string(path).map(Some).map_err(|e| {
+                ConfigError::InvalidTls(format!("Failed to read {}: {}", path.display(), e))
+            }),
+            None => Ok(inline.clone()),
+        };
+        Ok(TlsConfig {
+            ca_cert: None,
+            client_cert: None,
+            client_key: None,
+            ca_cert_pem: read(&self.ca_cert, &self.ca_cert_pem)?,
+            client_cert_pem: read(&self.client_cert, &self.client_cert_pem)?,
+            client_key_pem: read(&self.client_key, &self.client_key_pem)?,
+            ..self.clone()
+        })
+    }
+}
+
+/// Check that inline PEM holds at least one certificate and nothing unreadable
+fn certificates_pem(pem: &str, name: &str) -> Result<(), ConfigError> {
+    let certs = CertificateDer::pem_slice_iter(pem.as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| ConfigError::InvalidTls(format!("Invalid PEM in {}: {}", name, e)))?;
+    if certs.is_empty() {
+        return Err(ConfigError::InvalidTls(format!(
+            "{} holds no PEM certificate",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Parse a human-friendly duration such as `250ms`, `30s`, `2m` or `1h`
+///
+/// A bare number is interpreted as seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, ConfigError> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+
+    let value: f64 = value
+        .parse()
+        .map_err(|_| ConfigError::InvalidDuration(s.to_string()))?;
+    let seconds = match unit.trim() {
+        "ms" => value / 1000.0,
+        "" | "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        _ => return Err(ConfigError::InvalidDuration(s.to_string())),
+    };
+
+    Duration::try_from_secs_f64(seconds).map_err(|_| ConfigError::InvalidDuration(s.to_string()))
+}
+
+/// Bytes of a response body kept in memory when no limit is set
+pub const DEFAULT_MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+/// Lowest rate of a run or stage, one request every 1000 seconds
+pub const MIN_RATE: f64 = 0.001;
+
+/// Highest rate of a run or stage in requests per second
+pub const MAX_RATE: f64 = 1_000_000.0;
+
+/// Longest wait between paced requests or connections, a day, whatever rate a server publishes
+const MAX_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Whether `rate` is a number of requests per second a run may be paced at
+pub(crate) fn rate_in_bounds(rate: f64) -> bool {
+    (MIN_RATE..=MAX_RATE).contains(&rate)
+}
+
+/// Time `count` slots at `rate` per second take, at most [`MAX_INTERVAL`] so rates too low to
+/// represent cannot overflow a [`Duration`]
+pub(crate) fn interval_at(rate: f64, count: f64) -> Duration {
+    Duration::try_from_secs_f64(count / rate)
+        .map_or(MAX_INTERVAL, |interval| interval.min(MAX_INTERVAL))
+}
+
+/// Resource tuning of the generator for its hardware; the defaults suit most machines
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AdvancedConfig {
+    /// Worker threads of the Tokio runtime, one per CPU core when unset
+    ///
+    /// The engine runs on its caller's runtime, so this is applied by binaries that build the
+    /// runtime for the run, such as the CLI.
+    #[serde(default)]
+    pub worker_threads: Option<usize>,
+
+    /// Requests the engine polls at once, the highest concurrency of the run when unset
+    ///
+    /// A buffer below the concurrency caps the requests in flight; one above it keeps requests
+    /// ready while the concurrency is ramped up.
+    #[serde(default)]
+    pub stream_buffer: Option<usize>,
+
+    /// Bytes of a response body kept in memory for extractors and GraphQL checks,
+    /// [`DEFAULT_MAX_BODY_SIZE`] when unset
+    ///
+    /// Reading stops at the limit and the connection is dropped, so a run pointed at a large
+    /// download cannot exhaust the generator's memory. Truncated bodies are counted in the report.
+    #[serde(default)]
+    pub max_body_size: Option<usize>,
+
+    /// Bytes of resident memory the generator should stay under, unlimited when unset
+    ///
+    /// Nearing the ceiling, the requests recorded for the CSV or Parquet output are flushed to disk
+    /// and half of the sampled traces are dropped, instead of the process being killed near the end
+    /// of a giant run. Only measured on Linux.
+    #[serde(default)]
+    pub memory_ceiling: Option<u64>,
+
+    /// Directory recorded requests are flushed to under memory pressure, the system temp directory
+    /// when unset
+    #[serde(default)]
+    pub spill_dir: Option<std::path::PathBuf>,
+}
+
+impl AdvancedConfig {
+    /// Bytes of a response body kept in memory
+    pub fn max_body_size(&self) -> usize {
+        self.max_body_size.unwrap_or(DEFAULT_MAX_BODY_SIZE)
+    }
+}
+
 /// Configuration for a load test
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// The default has no URL and sends no requests, so it does not pass
+/// [`LoadTestConfig::validate`]; start from [`LoadTestConfig::new`] to run one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct LoadTestConfig {
     /// Target URL to test
@@ -78,6 +605,165 @@ pub struct LoadTestConfig {
 
     /// Optional request body
     pub body: Option<String>,
+
+    /// Form-encoded or multipart body sent instead of a raw `body`
+    #[serde(default)]
+    pub form: Option<FormBody>,
+
+    /// GraphQL operation POSTed as the body instead of a raw `body`, with `errors` in the response
+    /// failing the request
+    #[serde(default)]
+    pub graphql: Option<GraphqlRequest>,
+
+    /// Generator faults injected during the run
+    #[serde(default)]
+    pub faults: Vec<GeneratorFault>,
+
+    /// Weighted mix or sequential flow of requests sent instead of the single request above
+    ///
+    /// Run-level headers, body and labels still apply to every scenario request. With a sequential
+    /// scenario `requests` counts iterations of the flow and `concurrency` limits concurrent flows.
+    #[serde(default)]
+    pub scenario: Option<Scenario>,
+
+    /// Rows of test data filling the `{{column}}` placeholders of the requests, one per iteration
+    #[serde(default)]
+    pub feeder: Option<Feeder>,
+
+    /// What the run does with a request whose placeholder has no value
+    #[serde(default)]
+    pub on_missing_value: MissingValue,
+
+    /// Query parameters appended to every request with a random value
+    #[serde(default)]
+    pub random_params: Vec<RandomParam>,
+
+    /// Labels attached to every request, used to break metrics down (e.g. step, variant, region)
+    #[serde(default)]
+    pub labels: Labels,
+
+    /// Optional cap on throughput in requests per second, the arrival rate of the open models
+    #[serde(default)]
+    pub rate: Option<f64>,
+
+    /// Requests the closed model may start at once at `rate`, the capacity of its token bucket;
+    /// 1 (the default) paces requests strictly one interval apart, more lets the unused rate of
+    /// idle moments build up into bursts of up to this many requests at the same average rate
+    #[serde(default)]
+    pub rate_burst: Option<u32>,
+
+    /// Highest concurrency a live override may set, the highest configured one by default
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+
+    /// Highest rate a live override may set, the highest configured one by default
+    #[serde(default)]
+    pub max_rate: Option<f64>,
+
+    /// Whether requests start when virtual users are free or on the schedule of `rate`
+    #[serde(default)]
+    pub load_model: LoadModel,
+
+    /// Also report response times corrected for coordinated omission, counted from when `rate`
+    /// had the requests start and filling in the requests a stalled virtual user held back
+    #[serde(default)]
+    pub correct_coordinated_omission: bool,
+
+    /// Load stages run in order; when empty the run uses `concurrency` throughout
+    ///
+    /// A staged run starts at `concurrency` and ends when the last stage completes or after `requests` requests,
+    /// whichever comes first.
+    #[serde(default)]
+    pub stages: Vec<Stage>,
+
+    /// Keep the timestamp, duration, status and error of every request for a per-request export
+    #[serde(default)]
+    pub record_requests: bool,
+
+    /// Trace a sample of the requests with their headers and bodies, e.g. for a HAR file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<TraceSampling>,
+
+    /// Longest the whole run may take, unlike `timeout` which bounds each request
+    ///
+    /// Past the deadline the requests in flight are dropped and the run ends with the metrics
+    /// recorded so far, even if the target stops responding.
+    #[serde(default)]
+    pub deadline: Option<Duration>,
+
+    /// Probing of the target after the load stops, reporting how long its latency takes to recover
+    #[serde(default)]
+    pub cooldown: Option<Cooldown>,
+
+    /// Extra response time percentiles to report, e.g. 75, 90 or 99.99, next to median, p95 and p99
+    #[serde(default)]
+    pub percentiles: Vec<f64>,
+
+    /// Region of the agent running the test, attached to every request as the `region` label
+    #[serde(default)]
+    pub region: Option<String>,
+
+    /// Length of the intervals of the time series kept in the report, none when unset
+    #[serde(default)]
+    pub time_series_interval: Option<Duration>,
+
+    /// Service level objectives checked against the report, e.g. `p95 < 250ms`
+    #[serde(default)]
+    pub slos: Vec<SloTarget>,
+
+    /// Threshold expressions evaluated on the report, e.g. `p95 < 300ms && error_rate < 1%`
+    #[serde(default)]
+    pub thresholds: Vec<Threshold>,
+
+    /// Conditions ending the run early, e.g. `error_rate > 50% over 10s`; the report of a run
+    /// ended by one is marked `aborted`
+    #[serde(default)]
+    pub abort_on: Vec<AbortRule>,
+
+    /// Back off a virtual user for the delay of a `Retry-After` header of a 429, 502 or 503
+    /// response, like a cooperative client would
+    #[serde(default)]
+    pub honor_retry_after: bool,
+
+    /// Pace requests by the rate limit servers publish in response headers, e.g.
+    /// `X-RateLimit-Remaining` and `X-RateLimit-Reset`, under `rate` when one is set
+    #[serde(default)]
+    pub header_pacing: Option<HeaderPacing>,
+
+    /// Send failed requests again by this policy before they count as failed, none when unset
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+
+    /// Pause of a virtual user after each of its responses before its next request, none when
+    /// unset; the virtual user keeps its slot of `concurrency` meanwhile
+    #[serde(default)]
+    pub think_time: Option<ThinkTime>,
+
+    /// Status codes of the responses counted as successful, e.g. `201` or `2xx,404`; any 2xx
+    /// when unset. Scenario requests may set their own.
+    #[serde(default)]
+    pub success_codes: Option<SuccessCodes>,
+
+    /// What the engine does with response bodies
+    #[serde(default)]
+    pub response_body: ResponseBody,
+
+    /// HTTP version to speak with the target, applied by whoever builds the HTTP client
+    #[serde(default)]
+    pub http_version: HttpVersion,
+
+    /// TLS settings of the connections to the target
+    #[serde(default)]
+    pub tls: TlsConfig,
+
+    /// Host name resolution by the built-in resolver, applied by whoever builds the HTTP client;
+    /// the system resolver when unset
+    #[serde(default)]
+    pub dns: Option<DnsConfig>,
+
+    /// Resource tuning of the generator
+    #[serde(default)]
+    pub advanced: AdvancedConfig,
 }
 
 impl LoadTestConfig {
@@ -89,42 +775,345 @@ impl LoadTestConfig {
         concurrency: usize,
         timeout: Duration,
     ) -> Result<Self, ConfigError> {
-        if let Err(e) = Url::parse(&url) {
-            return Err(ConfigError::InvalidUrl(e.to_string()));
+        let config = Self {
+            url,
+            method,
+            requests,
+            concurrency,
+            timeout,
+            headers: Vec::new(),
+            body: None,
+            form: None,
+            graphql: None,
+            scenario: None,
+            feeder: None,
+            on_missing_value: MissingValue::Send,
+            random_params: Vec::new(),
+            labels: Labels::new(),
+            faults: Vec::new(),
+            rate: None,
+            rate_burst: None,
+            max_concurrency: None,
+            max_rate: None,
+            load_model: LoadModel::Closed,
+            correct_coordinated_omission: false,
+            stages: Vec::new(),
+            record_requests: false,
+            trace: None,
+            deadline: None,
+            cooldown: None,
+            percentiles: Vec::new(),
+            region: None,
+            time_series_interval: None,
+            slos: Vec::new(),
+            thresholds: Vec::new(),
+            abort_on: Vec::new(),
+            honor_retry_after: false,
+            header_pacing: None,
+            retry: None,
+            think_time: None,
+            success_codes: None,
+            response_body: ResponseBody::Drain,
+            http_version: HttpVersion::Auto,
+            tls: TlsConfig::default(),
+            dns: None,
+            advanced: AdvancedConfig::default(),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check the configuration invariants
+    ///
+    /// Configurations built field by field (e.g. deserialized or assembled by an embedder) should be
+    /// validated before they are handed to the engine; [`LoadTestConfig::new`] validates for you.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let url = Url::parse(&self.url).map_err(|e| ConfigError::InvalidUrl(e.to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(ConfigError::InvalidUrl(format!(
+                "Unsupported scheme '{}', expected http or https",
+                url.scheme()
+            )));
         }
 
-        if requests == 0 {
+        if self.requests == 0 {
             return Err(ConfigError::InvalidRequests(
                 "Number of requests must be greater than 0".to_string(),
             ));
         }
 
-        if concurrency == 0 {
+        if self.concurrency == 0 {
             return Err(ConfigError::InvalidConcurrency(
                 "Concurrency must be greater than 0".to_string(),
             ));
         }
-        if concurrency > requests {
+        if self.concurrency > self.requests {
             return Err(ConfigError::InvalidConcurrency(
                 "Concurrency cannot be greater than the number of requests".to_string(),
             ));
         }
 
-        if timeout.as_secs() == 0 {
+        if self.timeout.is_zero() {
+            return Err(ConfigError::InvalidTimeout(
+                "Timeout must be greater than 0".to_string(),
+            ));
+        }
+        if self.deadline.is_some_and(|deadline| deadline.is_zero()) {
             return Err(ConfigError::InvalidTimeout(
-                "Timeout must be greater than 0 seconds".to_string(),
+                "Deadline must be greater than 0".to_string(),
             ));
         }
 
-        Ok(Self {
-            url,
-            method,
-            requests,
-            concurrency,
-            timeout,
-            headers: Vec::new(),
-            body: None,
-        })
+        if self
+            .time_series_interval
+            .is_some_and(|interval| interval < Duration::from_millis(1))
+        {
+            return Err(ConfigError::InvalidDuration(
+                "Time series interval must be at least 1ms".to_string(),
+            ));
+        }
+
+        if self.advanced.worker_threads == Some(0) {
+            return Err(ConfigError::InvalidTuning(
+                "Worker threads must be greater than 0".to_string(),
+            ));
+        }
+        if self.advanced.stream_buffer == Some(0) {
+            return Err(ConfigError::InvalidTuning(
+                "Stream buffer must be greater than 0".to_string(),
+            ));
+        }
+        if self.advanced.max_body_size == Some(0) {
+            return Err(ConfigError::InvalidTuning(
+                "Max body size must be greater than 0".to_string(),
+            ));
+        }
+        if self.advanced.memory_ceiling == Some(0) {
+            return Err(ConfigError::InvalidTuning(
+                "Memory ceiling must be greater than 0".to_string(),
+            ));
+        }
+
+        for percentile in &self.percentiles {
+            if !(percentile.is_finite() && *percentile > 0.0 && *percentile <= 100.0) {
+                return Err(ConfigError::InvalidPercentile(format!(
+                    "{} is not a percentile, expected a number above 0 and at most 100",
+                    percentile
+                )));
+            }
+        }
+
+        self.tls.validate()?;
+
+        if let Some(dns) = &self.dns {
+            dns.validate()?;
+        }
+
+        if let Some(retry) = &self.retry {
+            retry.validate()?;
+        }
+
+        for rule in &self.abort_on {
+            rule.validate()?;
+        }
+        if let Some(think_time) = &self.think_time {
+            think_time.validate()?;
+        }
+        if let Some(pacing) = &self.header_pacing {
+            pacing.validate()?;
+        }
+        if let Some(trace) = &self.trace {
+            trace.validate()?;
+        }
+
+        if let Some(cooldown) = &self.cooldown {
+            cooldown.validate()?;
+        }
+
+        if let Some(rate) = self.rate {
+            if !rate_in_bounds(rate) {
+                return Err(ConfigError::InvalidRate(format!(
+                    "Rate must be between {} and {} requests per second",
+                    MIN_RATE, MAX_RATE
+                )));
+            }
+        }
+        if self.load_model != LoadModel::Closed && self.rate.is_none() {
+            return Err(ConfigError::InvalidRate(
+                "The open load models schedule requests at a rate, which must be set".to_string(),
+            ));
+        }
+        match self.rate_burst {
+            Some(0) => {
+                return Err(ConfigError::InvalidRate(
+                    "Burst must be at least 1 request".to_string(),
+                ))
+            }
+            Some(burst) if burst > 1 && self.load_model != LoadModel::Closed => {
+                return Err(ConfigError::InvalidRate(
+                    "The open load models keep to their schedule, a burst applies to the closed \
+                     model"
+                        .to_string(),
+                ))
+            }
+            _ => {}
+        }
+        match self.max_concurrency {
+            Some(0) => {
+                return Err(ConfigError::InvalidConcurrency(
+                    "Max concurrency must be at least 1".to_string(),
+                ))
+            }
+            Some(max) if max as u64 > self.total_requests() => {
+                return Err(ConfigError::InvalidConcurrency(
+                    "Max concurrency cannot be greater than the number of requests".to_string(),
+                ))
+            }
+            _ => {}
+        }
+        if let Some(max_rate) = self.max_rate {
+            if !rate_in_bounds(max_rate) {
+                return Err(ConfigError::InvalidRate(format!(
+                    "Max rate must be between {} and {} requests per second",
+                    MIN_RATE, MAX_RATE
+                )));
+            }
+        }
+        if self.correct_coordinated_omission && self.rate.is_none() {
+            return Err(ConfigError::InvalidRate(
+                "Coordinated-omission correction needs the rate requests were due at".to_string(),
+            ));
+        }
+
+        for (name, value) in &self.headers {
+            validate_header(name, value)?;
+        }
+
+        if let Some(form) = &self.form {
+            if self.body.is_some() {
+                return Err(ConfigError::InvalidForm(
+                    "A form cannot be sent together with a raw body".to_string(),
+                ));
+            }
+            form.validate()?;
+        }
+
+        if let Some(graphql) = &self.graphql {
+            if self.body.is_some() || self.form.is_some() {
+                return Err(ConfigError::InvalidGraphql(
+                    "A GraphQL operation cannot be sent together with a body or form".to_string(),
+                ));
+            }
+            if self.scenario.is_some() {
+                return Err(ConfigError::InvalidGraphql(
+                    "A GraphQL operation cannot be sent with a scenario".to_string(),
+                ));
+            }
+            if self.method != HttpMethod::POST {
+                return Err(ConfigError::InvalidGraphql(
+                    "GraphQL operations are sent with POST".to_string(),
+                ));
+            }
+            graphql.validate()?;
+        }
+
+        if let Some(feeder) = &self.feeder {
+            feeder.validate()?;
+        }
+
+        if let Some(scenario) = &self.scenario {
+            let columns = self
+                .feeder
+                .as_ref()
+                .map(Feeder::columns)
+                .unwrap_or_default();
+            scenario.validate_with(&columns)?;
+        }
+
+        for key in self.labels.keys() {
+            if key.trim().is_empty() || key.contains(['=', ',']) {
+                return Err(ConfigError::InvalidLabel(format!(
+                    "Label key '{}' must be non-empty and cannot contain '=' or ','",
+                    key
+                )));
+            }
+        }
+
+        if let Some(region) = &self.region {
+            if region.trim().is_empty() || region.contains(['=', ',']) {
+                return Err(ConfigError::InvalidLabel(format!(
+                    "Region '{}' must be non-empty and cannot contain '=' or ','",
+                    region
+                )));
+            }
+        }
+
+        for fault in &self.faults {
+            if fault.duration.is_zero() {
+                return Err(ConfigError::InvalidFault(format!(
+                    "{}: duration must be greater than 0",
+                    fault
+                )));
+            }
+            if let FaultAction::Throttle { load_percent } = fault.action {
+                if load_percent == 0 || load_percent > 100 {
+                    return Err(ConfigError::InvalidFault(format!(
+                        "{}: load percentage must be between 1 and 100",
+                        fault
+                    )));
+                }
+            }
+        }
+
+        for stage in &self.stages {
+            match stage {
+                Stage::Hold { concurrency, .. } | Stage::Burst { concurrency, .. }
+                    if *concurrency == 0 =>
+                {
+                    return Err(ConfigError::InvalidStage(format!(
+                        "{}: concurrency must be greater than 0",
+                        stage
+                    )));
+                }
+                Stage::Ramp {
+                    rate: Some(rate), ..
+                }
+                | Stage::Hold {
+                    rate: Some(rate), ..
+                }
+                | Stage::Burst {
+                    rate: Some(rate), ..
+                } if !rate_in_bounds(*rate) => {
+                    return Err(ConfigError::InvalidStage(format!(
+                        "{}: rate must be between {} and {} requests per second",
+                        stage, MIN_RATE, MAX_RATE
+                    )));
+                }
+                Stage::Wait(condition) => {
+                    if let Err(e) = Url::parse(&condition.url) {
+                        return Err(ConfigError::InvalidStage(format!(
+                            "{}: invalid URL: {}",
+                            stage, e
+                        )));
+                    }
+                    if condition.stable_polls == 0 {
+                        return Err(ConfigError::InvalidStage(format!(
+                            "{}: stable polls must be greater than 0",
+                            stage
+                        )));
+                    }
+                }
+                Stage::Burst { cycles: 0, .. } => {
+                    return Err(ConfigError::InvalidStage(format!(
+                        "{}: cycles must be greater than 0",
+                        stage
+                    )));
+                }
+                Stage::Ramp { .. } | Stage::Hold { .. } | Stage::Burst { .. } | Stage::Signal => {}
+            }
+        }
+
+        Ok(())
     }
 
     /// Add a header to the configuration
@@ -138,6 +1127,246 @@ impl LoadTestConfig {
         self.body = Some(body);
         self
     }
+
+    /// Send a form-encoded or multipart body instead of a raw one
+    pub fn with_form(mut self, form: FormBody) -> Self {
+        self.form = Some(form);
+        self
+    }
+
+    /// POST a GraphQL operation instead of a raw body, counting responses with `errors` as failed
+    pub fn with_graphql(mut self, graphql: GraphqlRequest) -> Self {
+        self.method = HttpMethod::POST;
+        self.graphql = Some(graphql);
+        self
+    }
+
+    /// Add a generator fault to the configuration
+    pub fn with_fault(mut self, fault: GeneratorFault) -> Self {
+        self.faults.push(fault);
+        self
+    }
+
+    /// Send a weighted mix of requests instead of the single configured request
+    pub fn with_scenario(mut self, scenario: Scenario) -> Self {
+        self.scenario = Some(scenario);
+        self
+    }
+
+    /// Fill the placeholders of the requests with rows of test data
+    pub fn with_feeder(mut self, feeder: Feeder) -> Self {
+        self.feeder = Some(feeder);
+        self
+    }
+
+    /// What an iteration does when a placeholder of its requests has no value
+    pub fn with_on_missing_value(mut self, on_missing_value: MissingValue) -> Self {
+        self.on_missing_value = on_missing_value;
+        self
+    }
+
+    /// Append a query parameter with a random value to every request
+    pub fn with_random_param(mut self, param: RandomParam) -> Self {
+        self.random_params.push(param);
+        self
+    }
+
+    /// Attach a label to every request
+    pub fn with_label(mut self, key: String, value: String) -> Self {
+        self.labels.insert(key, value);
+        self
+    }
+
+    /// Report the requests and response times of every `interval` of the run
+    pub fn with_time_series(mut self, interval: Duration) -> Self {
+        self.time_series_interval = Some(interval);
+        self
+    }
+
+    /// Check a service level objective against the report
+    pub fn with_slo(mut self, slo: SloTarget) -> Self {
+        self.slos.push(slo);
+        self
+    }
+
+    /// Evaluate a threshold expression on the report
+    pub fn with_threshold(mut self, threshold: Threshold) -> Self {
+        self.thresholds.push(threshold);
+        self
+    }
+
+    /// End the run early once the rule is met
+    pub fn with_abort_rule(mut self, rule: AbortRule) -> Self {
+        self.abort_on.push(rule);
+        self
+    }
+
+    /// Back off virtual users as servers ask in `Retry-After` headers
+    pub fn with_retry_after(mut self) -> Self {
+        self.honor_retry_after = true;
+        self
+    }
+
+    /// Pace requests by the rate limit headers of the responses
+    pub fn with_header_pacing(mut self, pacing: HeaderPacing) -> Self {
+        self.header_pacing = Some(pacing);
+        self
+    }
+
+    /// Trace a sample of the requests with their headers and bodies
+    pub fn with_trace(mut self, trace: TraceSampling) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Send failed requests again by the given policy
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Pause virtual users between their requests
+    pub fn with_think_time(mut self, think_time: ThinkTime) -> Self {
+        self.think_time = Some(think_time);
+        self
+    }
+
+    /// Count responses with these status codes as successful instead of any 2xx
+    pub fn with_success_codes(mut self, success_codes: SuccessCodes) -> Self {
+        self.success_codes = Some(success_codes);
+        self
+    }
+
+    /// Consume response bodies by this policy
+    pub fn with_response_body(mut self, response_body: ResponseBody) -> Self {
+        self.response_body = response_body;
+        self
+    }
+
+    /// Speak this HTTP version with the target
+    pub fn with_http_version(mut self, http_version: HttpVersion) -> Self {
+        self.http_version = http_version;
+        self
+    }
+
+    /// Connect to the target with these TLS settings
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    /// Resolve host names with the built-in resolver by these settings
+    pub fn with_dns(mut self, dns: DnsConfig) -> Self {
+        self.dns = Some(dns);
+        self
+    }
+
+    /// Tune the resources of the generator
+    pub fn with_advanced(mut self, advanced: AdvancedConfig) -> Self {
+        self.advanced = advanced;
+        self
+    }
+
+    /// Tag every request with the region of the agent running the test
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    /// Content hash of the configuration, the same for two runs of an identical test
+    ///
+    /// The SHA-256 of the configuration as JSON with its keys sorted, in hex. Files the
+    /// configuration points to, e.g. a feeder, count by their path rather than their content.
+    pub fn fingerprint(&self) -> String {
+        // Going through a value sorts the keys of the maps, e.g. the labels
+        let json = serde_json::to_value(self)
+            .map(|value| value.to_string())
+            .unwrap_or_else(|_| format!("{:?}", self));
+        Sha256::digest(json.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Requests the run sends, not counting retries: every iteration of a sequential scenario
+    /// sends all its requests, other runs one request per iteration
+    pub fn total_requests(&self) -> u64 {
+        match &self.scenario {
+            Some(scenario) if scenario.is_sequential() => {
+                self.requests as u64 * scenario.requests.len() as u64
+            }
+            _ => self.requests as u64,
+        }
+    }
+
+    /// Labels attached to every request, including the region
+    pub fn request_labels(&self) -> Labels {
+        let mut labels = self.labels.clone();
+        if let Some(region) = &self.region {
+            labels.insert(REGION_LABEL.to_string(), region.clone());
+        }
+        labels
+    }
+
+    /// Cap throughput at the given number of requests per second
+    pub fn with_rate(mut self, rate: f64) -> Self {
+        self.rate = Some(rate);
+        self
+    }
+
+    /// Let up to `burst` requests start at once at the rate of the closed model
+    pub fn with_rate_burst(mut self, burst: u32) -> Self {
+        self.rate_burst = Some(burst);
+        self
+    }
+
+    /// Let live overrides raise the concurrency up to `max` requests in flight
+    pub fn with_max_concurrency(mut self, max: usize) -> Self {
+        self.max_concurrency = Some(max);
+        self
+    }
+
+    /// Let live overrides raise the rate up to `max` requests per second
+    pub fn with_max_rate(mut self, max: f64) -> Self {
+        self.max_rate = Some(max);
+        self
+    }
+
+    /// Start requests by this load model
+    pub fn with_load_model(mut self, load_model: LoadModel) -> Self {
+        self.load_model = load_model;
+        self
+    }
+
+    /// Report response times corrected for coordinated omission next to the measured ones
+    pub fn with_coordinated_omission_correction(mut self, correct: bool) -> Self {
+        self.correct_coordinated_omission = correct;
+        self
+    }
+
+    /// Add a load stage to the configuration
+    pub fn with_stage(mut self, stage: Stage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    /// Report the response time at the given percentile
+    pub fn with_percentile(mut self, percentile: f64) -> Self {
+        self.percentiles.push(percentile);
+        self
+    }
+
+    /// End the run after the given time, whether or not all requests were sent
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Keep probing the target after the load stops to see how long it takes to recover
+    pub fn with_cooldown(mut self, cooldown: Cooldown) -> Self {
+        self.cooldown = Some(cooldown);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -158,6 +1387,162 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_http_version_from_str() {
+        assert_eq!(HttpVersion::from_str("auto").unwrap(), HttpVersion::Auto);
+        assert_eq!(HttpVersion::from_str("1.1").unwrap(), HttpVersion::Http1);
+        assert_eq!(
+            HttpVersion::from_str("HTTP/1.1").unwrap(),
+            HttpVersion::Http1
+        );
+        assert_eq!(HttpVersion::from_str("2").unwrap(), HttpVersion::Http2);
+        assert_eq!(HttpVersion::from_str("h2c").unwrap(), HttpVersion::Http2);
+
+        assert!(matches!(
+            HttpVersion::from_str("3").unwrap_err(),
+            ConfigError::InvalidHttpVersion(_)
+        ));
+    }
+
+    #[test]
+    fn test_load_model_from_str() {
+        assert_eq!(LoadModel::from_str("Open").unwrap(), LoadModel::Open);
+        assert_eq!(LoadModel::from_str("poisson").unwrap(), LoadModel::Poisson);
+        assert!(matches!(
+            LoadModel::from_str("arrival").unwrap_err(),
+            ConfigError::InvalidLoadModel(_)
+        ));
+    }
+
+    #[test]
+    fn test_response_body_from_str() {
+        assert_eq!(
+            ResponseBody::from_str("Drain").unwrap(),
+            ResponseBody::Drain
+        );
+        assert_eq!(
+            ResponseBody::from_str("discard").unwrap(),
+            ResponseBody::Discard
+        );
+        assert!(matches!(
+            ResponseBody::from_str("decode").unwrap_err(),
+            ConfigError::InvalidResponseBody(_)
+        ));
+    }
+
+    #[test]
+    fn test_tls_version_from_str() {
+        assert_eq!(TlsVersion::from_str("1.2").unwrap(), TlsVersion::Tls1_2);
+        assert_eq!(TlsVersion::from_str("TLS1.1").unwrap(), TlsVersion::Tls1_1);
+        assert_eq!(TlsVersion::from_str("tls 1.3").unwrap(), TlsVersion::Tls1_3);
+        assert!(matches!(
+            TlsVersion::from_str("1.4").unwrap_err(),
+            ConfigError::InvalidTls(_)
+        ));
+    }
+
+    #[test]
+    fn test_interval_at_rate() {
+        assert_eq!(interval_at(4.0, 1.0), Duration::from_millis(250));
+        assert_eq!(interval_at(10.0, 5.0), Duration::from_millis(500));
+        for rate in [1e-300, 0.0, f64::NAN] {
+            assert_eq!(interval_at(rate, 1.0), MAX_INTERVAL);
+        }
+    }
+
+    #[test]
+    fn test_tls_min_version_matches_the_backend() {
+        let tls = |min_version, session_resumption| TlsConfig {
+            min_version: Some(min_version),
+            session_resumption,
+            ..Default::default()
+        };
+        tls(TlsVersion::Tls1_0, None).validate().unwrap();
+        tls(TlsVersion::Tls1_2, Some(true)).validate().unwrap();
+        tls(TlsVersion::Tls1_3, Some(false)).validate().unwrap();
+        for bad in [
+            tls(TlsVersion::Tls1_3, None),
+            tls(TlsVersion::Tls1_1, Some(true)),
+        ] {
+            assert!(matches!(bad.validate(), Err(ConfigError::InvalidTls(_))));
+        }
+    }
+
+    #[test]
+    fn test_client_cert_needs_key() {
+        let config = LoadTestConfig::new(
+            "https://example.com".to_string(),
+            HttpMethod::GET,
+            1,
+            1,
+            Duration::from_secs(1),
+        )
+        .unwrap()
+        .with_tls(TlsConfig {
+            client_cert: Some(PathBuf::from("client.pem")),
+            ..Default::default()
+        });
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidTls(_))));
+
+        let config = config.with_tls(TlsConfig {
+            reload_client_cert: true,
+            ..Default::default()
+        });
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidTls(_))));
+    }
+
+    #[test]
+    fn test_inline_pem_is_parsed() {
+        let fixture = |name: &str| {
+            std::fs::read_to_string(
+                PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                    .join("tests/fixtures/tls")
+                    .join(name),
+            )
+            .unwrap()
+        };
+        let tls = TlsConfig {
+            ca_cert_pem: Some(fixture("ca.pem")),
+            client_cert_pem: Some(fixture("client.pem")),
+            client_key_pem: Some(fixture("client.key")),
+            ..Default::default()
+        };
+        tls.validate().unwrap();
+
+        for bad in [
+            TlsConfig {
+                ca_cert_pem: Some("/etc/ssl/certs/ca.pem".to_string()),
+                ..Default::default()
+            },
+            TlsConfig {
+                client_key_pem: Some(fixture("client.pem")),
+                ..tls.clone()
+            },
+            TlsConfig {
+                ca_cert: Some(PathBuf::from("ca.pem")),
+                ..tls.clone()
+            },
+            TlsConfig {
+                client_key_pem: None,
+                ..tls.clone()
+            },
+        ] {
+            assert!(matches!(bad.validate(), Err(ConfigError::InvalidTls(_))));
+        }
+
+        let files = TlsConfig {
+            ca_cert: Some(
+                PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tls/ca.pem"),
+            ),
+            min_version: Some(TlsVersion::Tls1_2),
+            ..Default::default()
+        };
+        let inlined = files.inlined().unwrap();
+        assert_eq!(inlined.ca_cert, None);
+        assert_eq!(inlined.ca_cert_pem, Some(fixture("ca.pem")));
+        assert_eq!(inlined.min_version, Some(TlsVersion::Tls1_2));
+    }
+
     #[test]
     fn test_http_method_into_reqwest() {
         assert_eq!(reqwest::Method::from(HttpMethod::GET), reqwest::Method::GET);
@@ -244,6 +1629,153 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_validate_fields_set_after_construction() {
+        let config = LoadTestConfig::new(
+            "http://example.com".to_string(),
+            HttpMethod::GET,
+            100,
+            10,
+            Duration::from_millis(500),
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let mut invalid = config.clone();
+        invalid.url = "ftp://example.com".to_string();
+        assert!(matches!(
+            invalid.validate().unwrap_err(),
+            ConfigError::InvalidUrl(_)
+        ));
+
+        for percentile in [0.0, 100.5, f64::NAN] {
+            let invalid = config.clone().with_percentile(percentile);
+            assert!(matches!(
+                invalid.validate().unwrap_err(),
+                ConfigError::InvalidPercentile(_)
+            ));
+        }
+        assert!(config.clone().with_percentile(99.99).validate().is_ok());
+
+        let invalid = config.clone().with_deadline(Duration::ZERO);
+        assert!(matches!(
+            invalid.validate().unwrap_err(),
+            ConfigError::InvalidTimeout(_)
+        ));
+
+        let invalid = config
+            .clone()
+            .with_header("Invalid@Header".to_string(), "value".to_string());
+        assert!(matches!(
+            invalid.validate().unwrap_err(),
+            ConfigError::InvalidHeader(_)
+        ));
+
+        let invalid = config
+            .clone()
+            .with_header("X-Test".to_string(), "line\nbreak".to_string());
+        assert!(matches!(
+            invalid.validate().unwrap_err(),
+            ConfigError::InvalidHeader(_)
+        ));
+
+        let invalid = config.clone().with_fault(GeneratorFault::throttle(
+            Duration::ZERO,
+            Duration::from_secs(1),
+            0,
+        ));
+        assert!(matches!(
+            invalid.validate().unwrap_err(),
+            ConfigError::InvalidFault(_)
+        ));
+
+        let invalid = config
+            .clone()
+            .with_label("step=1".to_string(), "login".to_string());
+        assert!(matches!(
+            invalid.validate().unwrap_err(),
+            ConfigError::InvalidLabel(_)
+        ));
+
+        let invalid = config.clone().with_time_series(Duration::ZERO);
+        assert!(matches!(
+            invalid.validate().unwrap_err(),
+            ConfigError::InvalidDuration(_)
+        ));
+
+        let invalid = config.clone().with_region(" ");
+        assert!(matches!(
+            invalid.validate().unwrap_err(),
+            ConfigError::InvalidLabel(_)
+        ));
+
+        for rate in [0.0, 1e-300, f64::INFINITY] {
+            let invalid = config.clone().with_rate(rate);
+            assert!(matches!(
+                invalid.validate().unwrap_err(),
+                ConfigError::InvalidRate(_)
+            ));
+            let invalid = config.clone().with_max_rate(rate);
+            assert!(matches!(
+                invalid.validate().unwrap_err(),
+                ConfigError::InvalidRate(_)
+            ));
+        }
+        let invalid = config.clone().with_max_concurrency(usize::MAX);
+        assert!(matches!(
+            invalid.validate().unwrap_err(),
+            ConfigError::InvalidConcurrency(_)
+        ));
+
+        let invalid = config.clone().with_load_model(LoadModel::Poisson);
+        assert!(matches!(
+            invalid.validate().unwrap_err(),
+            ConfigError::InvalidRate(_)
+        ));
+        assert!(config
+            .clone()
+            .with_load_model(LoadModel::Open)
+            .with_rate(100.0)
+            .validate()
+            .is_ok());
+
+        let invalid = config.clone().with_coordinated_omission_correction(true);
+        assert!(matches!(
+            invalid.validate().unwrap_err(),
+            ConfigError::InvalidRate(_)
+        ));
+
+        let invalid = config.with_stage(Stage::Hold {
+            duration: Duration::from_secs(1),
+            concurrency: 0,
+            rate: None,
+        });
+        assert!(matches!(
+            invalid.validate().unwrap_err(),
+            ConfigError::InvalidStage(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+        assert_eq!(parse_duration("1h").unwrap(), Duration::from_secs(3600));
+        assert_eq!(parse_duration("45").unwrap(), Duration::from_secs(45));
+
+        assert!(matches!(
+            parse_duration("ten seconds").unwrap_err(),
+            ConfigError::InvalidDuration(_)
+        ));
+        assert!(matches!(
+            parse_duration("10d").unwrap_err(),
+            ConfigError::InvalidDuration(_)
+        ));
+        assert!(parse_duration("").is_err());
+    }
+
     #[test]
     fn test_load_test_config_with_header_and_body() {
         let config = LoadTestConfig::new(
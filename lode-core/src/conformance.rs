@@ -0,0 +1,171 @@
+//! Conformance checks of [`HttpClient`] implementations
+//!
+//! The engine expects every client to behave like the [`DefaultHttpClient`]: it sends the given
+//! method, headers and body, binary bodies byte for byte, returns unsuccessful statuses as responses rather than errors, and
+//! fails with reqwest errors that [`ErrorKind::from_error`] classifies, so that reports key the
+//! failures of any client alike. Run [`check_http_client`] from a test of an alternative client:
+//!
+//! ```no_run
+//! # async fn example() {
+//! use lode_core::conformance::check_http_client;
+//! use lode_core::http::DefaultHttpClient;
+//!
+//! check_http_client(&DefaultHttpClient::new().unwrap()).await;
+//! # }
+//! ```
+//!
+//! Available with the `conformance` feature.
+//!
+//! [`DefaultHttpClient`]: crate::http::DefaultHttpClient
+
+use crate::http::HttpClient;
+use crate::metrics::ErrorKind;
+use reqwest::Method;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use wiremock::matchers::{body_bytes, body_string, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Timeout of the requests expected to succeed
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Timeout of the request to the slow endpoint, which answers after [`TIMEOUT`]
+const SHORT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Check that a client behaves as the engine expects, panicking on the first deviation
+pub async fn check_http_client<C: HttpClient>(client: &C) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/echo"))
+        .and(header("x-lode-check", "conformance"))
+        .and(body_string("payload"))
+        .respond_with(ResponseTemplate::new(201).set_body_string("created"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/upload"))
+        .and(body_bytes(BINARY_BODY))
+        .respond_with(ResponseTemplate::new(201))
+        .mount(&server)
+        .await;
+    Mock::given(path("/unavailable"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+    Mock::given(path("/slow"))
+        .respond_with(ResponseTemplate::new(200).set_delay(TIMEOUT))
+        .mount(&server)
+        .await;
+
+    check_request(client, &server.uri()).await;
+    check_binary_body(client, &server.uri()).await;
+    check_error_status(client, &server.uri()).await;
+    check_timeout(client, &server.uri()).await;
+    check_connect_error(client).await;
+}
+
+/// Method, headers and body reach the server, status and body come back
+async fn check_request<C: HttpClient>(client: &C, base: &str) {
+    let response = client
+        .send_request(
+            Method::POST,
+            format!("{}/echo", base),
+            TIMEOUT,
+            vec![("X-Lode-Check".to_string(), "conformance".to_string())],
+            Some("payload".to_string()),
+        )
+        .await
+        .expect("request failed");
+    assert_eq!(
+        response.status().as_u16(),
+        201,
+        "method, headers or body did not reach the server"
+    );
+    let body = response.text().await.expect("reading the body failed");
+    assert_eq!(body, "created", "unexpected response body");
+}
+
+/// Body of the upload check, not valid UTF-8
+const BINARY_BODY: [u8; 4] = [0, 159, 146, 150];
+
+/// A binary body reaches the server unchanged
+async fn check_binary_body<C: HttpClient>(client: &C, base: &str) {
+    let response = client
+        .send_bytes(
+            Method::POST,
+            format!("{}/upload", base),
+            TIMEOUT,
+            vec![],
+            BINARY_BODY.to_vec(),
+        )
+        .await
+        .expect("request failed");
+    assert_eq!(
+        response.status().as_u16(),
+        201,
+        "the binary body did not reach the server unchanged"
+    );
+}
+
+/// Unsuccessful statuses are responses, for the engine to count by status
+async fn check_error_status<C: HttpClient>(client: &C, base: &str) {
+    let response = client
+        .send_request(
+            Method::GET,
+            format!("{}/unavailable", base),
+            TIMEOUT,
+            vec![],
+            None,
+        )
+        .await
+        .expect("an unsuccessful status must be returned as a response, not an error");
+    assert_eq!(response.status().as_u16(), 503);
+}
+
+/// The client applies the timeout itself and fails with a timeout error
+async fn check_timeout<C: HttpClient>(client: &C, base: &str) {
+    let request = client.send_request(
+        Method::GET,
+        format!("{}/slow", base),
+        SHORT_TIMEOUT,
+        vec![],
+        None,
+    );
+    let result = tokio::time::timeout(TIMEOUT, request)
+        .await
+        .expect("the client did not apply the timeout");
+    let error = result.expect_err("a response past the timeout must be an error");
+    assert_eq!(
+        ErrorKind::from_error(&error),
+        ErrorKind::Timeout,
+        "a timeout must be classified as one: {}",
+        error
+    );
+}
+
+/// A refused connection fails with a connect error
+async fn check_connect_error<C: HttpClient>(client: &C) {
+    // A port just released by the listener refuses connections
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("binding a port failed");
+    let addr = listener.local_addr().expect("no local address");
+    drop(listener);
+
+    let error = client
+        .send_request(
+            Method::GET,
+            format!("http://{}/", addr),
+            TIMEOUT,
+            vec![],
+            None,
+        )
+        .await
+        .expect_err("a refused connection must be an error");
+    assert_eq!(
+        ErrorKind::from_error(&error),
+        ErrorKind::Connect,
+        "a refused connection must be classified as a connect error: {}",
+        error
+    );
+}
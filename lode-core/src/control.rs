@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use thiserror::Error;
+use tracing::info;
+
+use crate::config::{rate_in_bounds, LoadTestConfig, MIN_RATE};
+
+/// Concurrency and rate set by an operator while a run is in flight
+///
+/// A field left unset keeps the load the run's configuration and stages give it; once set it holds
+/// for the rest of the run, ignoring the stages that follow.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Overrides {
+    /// Requests in flight at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<usize>,
+    /// Requests per second
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate: Option<f64>,
+}
+
+impl fmt::Display for Overrides {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(concurrency) = self.concurrency {
+            parts.push(format!("concurrency {}", concurrency));
+        }
+        if let Some(rate) = self.rate {
+            parts.push(format!("rate {}/s", rate));
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// Highest load an override may set, from the configuration of the run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverrideLimits {
+    pub max_concurrency: usize,
+    /// `None` when the run sets no rate, so none can be overridden
+    pub max_rate: Option<f64>,
+}
+
+impl OverrideLimits {
+    /// Limits of a run: its `max_concurrency` and `max_rate`, or else the highest concurrency and
+    /// rate it configures, so overrides can only turn the load down
+    pub fn for_config(config: &LoadTestConfig) -> Self {
+        let stage_rates = config.stages.iter().filter_map(|stage| stage.rate());
+        Self {
+            max_concurrency: config.max_concurrency.unwrap_or_else(|| {
+                config
+                    .stages
+                    .iter()
+                    .map(|stage| stage.max_concurrency())
+                    .fold(config.concurrency, usize::max)
+            }),
+            max_rate: config
+                .max_rate
+                .or_else(|| config.rate.into_iter().chain(stage_rates).reduce(f64::max)),
+        }
+    }
+}
+
+/// Why an override was refused
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum OverrideError {
+    #[error("Concurrency must be between 1 and {max}, got {requested}")]
+    Concurrency { requested: usize, max: usize },
+    #[error("Rate must be between {min} and {max} requests per second, got {requested}")]
+    Rate { requested: f64, min: f64, max: f64 },
+    #[error("The run sets no rate and no max rate, so its rate cannot be overridden")]
+    NoRate,
+    #[error("Nothing to override, set a concurrency or a rate")]
+    Empty,
+}
+
+/// Adjusts the load of a run while it is in flight, e.g. from an API call during an exploratory
+/// session
+///
+/// Clones share the same overrides. Every accepted change is kept with the time it was made, for
+/// the timeline of the run.
+#[derive(Debug, Clone)]
+pub struct LoadControl {
+    limits: OverrideLimits,
+    state: Arc<Mutex<ControlState>>,
+}
+
+#[derive(Debug, Default)]
+struct ControlState {
+    current: Overrides,
+    changes: Vec<(Instant, Overrides)>,
+}
+
+impl LoadControl {
+    /// Create a control accepting overrides up to `limits`
+    pub fn new(limits: OverrideLimits) -> Self {
+        Self {
+            limits,
+            state: Arc::default(),
+        }
+    }
+
+    /// Highest load an override may set
+    pub fn limits(&self) -> OverrideLimits {
+        self.limits
+    }
+
+    /// Set the fields of `overrides` that are set, keeping the others, and return the overrides
+    /// now in place
+    ///
+    /// Nothing changes when one of the fields is out of bounds.
+    pub fn apply(&self, overrides: Overrides) -> Result<Overrides, OverrideError> {
+        if overrides == Overrides::default() {
+            return Err(OverrideError::Empty);
+        }
+        if let Some(requested) = overrides.concurrency {
+            let max = self.limits.max_concurrency;
+            if requested == 0 || requested > max {
+                return Err(OverrideError::Concurrency { requested, max });
+            }
+        }
+        if let Some(requested) = overrides.rate {
+            let max = self.limits.max_rate.ok_or(OverrideError::NoRate)?;
+            if !rate_in_bounds(requested) || requested > max {
+                return Err(OverrideError::Rate {
+                    requested,
+                    min: MIN_RATE,
+                    max,
+                });
+            }
+        }
+
+        info!("Load overridden: {}", overrides);
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.current = Overrides {
+            concurrency: overrides.concurrency.or(state.current.concurrency),
+            rate: overrides.rate.or(state.current.rate),
+        };
+        state.changes.push((Instant::now(), overrides));
+        Ok(state.current)
+    }
+
+    /// Overrides in place
+    pub fn current(&self) -> Overrides {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).current
+    }
+
+    /// Changes accepted so far with the time each was made
+    pub fn changes(&self) -> Vec<(Instant, Overrides)> {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .changes
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_within_limits() {
+        let control = LoadControl::new(OverrideLimits {
+            max_concurrency: 20,
+            max_rate: Some(100.0),
+        });
+        let current = control
+            .apply(Overrides {
+                concurrency: Some(15),
+                rate: None,
+            })
+            .unwrap();
+        assert_eq!(current.concurrency, Some(15));
+        let current = control
+            .apply(Overrides {
+                concurrency: None,
+                rate: Some(50.0),
+            })
+            .unwrap();
+        assert_eq!(current.concurrency, Some(15));
+        assert_eq!(current.rate, Some(50.0));
+
+        for refused in [
+            Overrides {
+                concurrency: Some(21),
+                rate: Some(10.0),
+            },
+            Overrides {
+                concurrency: Some(0),
+                rate: None,
+            },
+            Overrides {
+                concurrency: None,
+                rate: Some(150.0),
+            },
+            Overrides {
+                concurrency: None,
+                rate: Some(1e-300),
+            },
+            Overrides {
+                concurrency: None,
+                rate: Some(f64::NAN),
+            },
+            Overrides::default(),
+        ] {
+            assert!(control.apply(refused).is_err(), "{:?}", refused);
+        }
+        assert_eq!(control.current(), current);
+        assert_eq!(control.changes().len(), 2);
+
+        let unpaced = LoadControl::new(OverrideLimits {
+            max_concurrency: 20,
+            max_rate: None,
+        });
+        assert_eq!(
+            unpaced.apply(Overrides {
+                concurrency: None,
+                rate: Some(1.0),
+            }),
+            Err(OverrideError::NoRate)
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::time::MissedTickBehavior;
+use url::Url;
+
+use crate::config::ConfigError;
+use crate::http::HttpClient;
+
+/// Probes sent back to back before the load starts to measure the baseline latency
+pub const BASELINE_PROBES: usize = 3;
+
+/// Time between two probes of the cooldown
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A probe is back at the baseline when it succeeds within this factor of the baseline latency
+pub const RECOVERY_FACTOR: f64 = 1.5;
+
+/// Observation of the target after the load stops, to see how long it takes to recover
+///
+/// The probe request is sent a few times before the load starts to measure the baseline latency,
+/// then once per second for the whole cooldown. The target has recovered once every later probe
+/// succeeds within [`RECOVERY_FACTOR`] times the baseline.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Cooldown {
+    /// How long to keep probing after the load stops
+    pub duration: Duration,
+
+    /// URL probed with a GET and the run's headers, the run's `url` when unset, e.g. a health
+    /// endpoint
+    #[serde(default)]
+    pub probe_url: Option<String>,
+}
+
+impl Cooldown {
+    /// Probe the run's URL for `duration` after the load stops
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            probe_url: None,
+        }
+    }
+
+    /// Probe this URL instead of the run's
+    pub fn with_probe_url(mut self, url: impl Into<String>) -> Self {
+        self.probe_url = Some(url.into());
+        self
+    }
+
+    /// Check that the cooldown lasts and probes an HTTP URL
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.duration.is_zero() {
+            return Err(ConfigError::InvalidCooldown(
+                "Duration must be greater than 0".to_string(),
+            ));
+        }
+        if let Some(url) = &self.probe_url {
+            let parsed = Url::parse(url)
+                .map_err(|e| ConfigError::InvalidCooldown(format!("Probe URL {}: {}", url, e)))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                return Err(ConfigError::InvalidCooldown(format!(
+                    "Probe URL {} is not an http or https URL",
+                    url
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One probe sent during the cooldown
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProbeSample {
+    /// Time since the load stopped
+    pub offset: Duration,
+    pub response_time: Duration,
+    /// Whether the probe got a successful response
+    pub success: bool,
+}
+
+/// Baseline latency and probes of a cooldown
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CooldownMetrics {
+    /// Median response time of the probes sent before the load, none when they all failed
+    pub baseline: Option<Duration>,
+    pub probes: Vec<ProbeSample>,
+}
+
+impl CooldownMetrics {
+    /// Time after the load stopped from which every probe was back at the baseline, none when the
+    /// last probe was not or there is no baseline
+    pub fn recovery_time(&self) -> Option<Duration> {
+        let baseline = self.baseline?;
+        let threshold = baseline.mul_f64(RECOVERY_FACTOR);
+        let recovered = |probe: &ProbeSample| probe.success && probe.response_time <= threshold;
+        let first_recovered = self
+            .probes
+            .iter()
+            .rposition(|probe| !recovered(probe))
+            .map_or(0, |index| index + 1);
+        self.probes.get(first_recovered).map(|probe| probe.offset)
+    }
+}
+
+/// Sends the probe request of a cooldown
+pub(crate) struct Prober<'a, C: HttpClient> {
+    pub(crate) client: &'a C,
+    pub(crate) url: String,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) timeout: Duration,
+}
+
+impl<C: HttpClient> Prober<'_, C> {
+    /// Send the probe once, returning its response time and whether it succeeded
+    async fn probe(&self) -> (Duration, bool) {
+        let started = Instant::now();
+        let response = self
+            .client
+            .send_request(
+                Method::GET,
+                self.url.clone(),
+                self.timeout,
+                self.headers.clone(),
+                None,
+            )
+            .await;
+        let success = match response {
+            Ok(response) => {
+                let status = response.status();
+                response.bytes().await.is_ok() && status.is_success()
+            }
+            Err(_) => false,
+        };
+        (started.elapsed(), success)
+    }
+
+    /// Median response time of [`BASELINE_PROBES`] probes, none when they all failed
+    pub(crate) async fn baseline(&self) -> Option<Duration> {
+        let mut times = Vec::with_capacity(BASELINE_PROBES);
+        for _ in 0..BASELINE_PROBES {
+            if let (time, true) = self.probe().await {
+                times.push(time);
+            }
+        }
+        times.sort_unstable();
+        times.get(times.len() / 2).copied()
+    }
+
+    /// Probe every [`PROBE_INTERVAL`] for `duration`, adding the samples as they come so they are
+    /// kept when the observation is cut short
+    pub(crate) async fn observe(&self, duration: Duration, probes: &mut Vec<ProbeSample>) {
+        let started = Instant::now();
+        let mut interval = tokio::time::interval(PROBE_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let offset = started.elapsed();
+            if offset >= duration {
+                break;
+            }
+            let (response_time, success) = self.probe().await;
+            probes.push(ProbeSample {
+                offset,
+                response_time,
+                success,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(offset_secs: u64, response_ms: u64, success: bool) -> ProbeSample {
+        ProbeSample {
+            offset: Duration::from_secs(offset_secs),
+            response_time: Duration::from_millis(response_ms),
+            success,
+        }
+    }
+
+    #[test]
+    fn test_recovery_time() {
+        let mut metrics = CooldownMetrics {
+            baseline: Some(Duration::from_millis(10)),
+            probes: vec![
+                sample(0, 200, true),
+                sample(1, 12, true),
+                sample(2, 50, false),
+                sample(3, 14, true),
+                sample(4, 9, true),
+            ],
+        };
+        // The second probe was fast but a later one was not
+        assert_eq!(metrics.recovery_time(), Some(Duration::from_secs(3)));
+
+        metrics.probes.push(sample(5, 16, true));
+        assert_eq!(metrics.recovery_time(), None);
+
+        metrics.probes.truncate(2);
+        metrics.probes[0] = sample(0, 11, true);
+        assert_eq!(metrics.recovery_time(), Some(Duration::ZERO));
+
+        metrics.baseline = None;
+        assert_eq!(metrics.recovery_time(), None);
+    }
+
+    #[test]
+    fn test_validate_cooldown() {
+        assert!(Cooldown::new(Duration::from_secs(30)).validate().is_ok());
+        assert!(Cooldown::new(Duration::ZERO).validate().is_err());
+        assert!(Cooldown::new(Duration::from_secs(30))
+            .with_probe_url("ftp://example.com/health")
+            .validate()
+            .is_err());
+    }
+}
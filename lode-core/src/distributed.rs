@@ -0,0 +1,559 @@
+use indicatif::ProgressBar;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::clock::{ClockOffset, DEFAULT_CLOCK_SAMPLES};
+use crate::config::{ConfigError, FeederStrategy, LoadTestConfig, MIN_RATE};
+use crate::error::{Error, Result};
+use crate::progress::ProgressSnapshot;
+use crate::report::Report;
+use crate::run::RunState;
+use crate::stages::Stage;
+
+/// Path of the agents' API receiving the share of a distributed run
+pub const AGENT_SUBMIT_PATH: &str = "/agent/load-tests";
+
+/// Path of the agents' API serving their clock for the clock handshake
+pub const AGENT_CLOCK_PATH: &str = "/time";
+
+/// Time between two polls of the agents' runs
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time an agent has to answer a request of the controller
+pub const AGENT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Split a load test into `agents` shares that together send the load of the whole test
+///
+/// Requests, concurrencies and rates are divided as evenly as possible. The test is split across
+/// at most as many agents as it has requests, virtual users in any of its stages and, for a feeder
+/// taken once each, rows, so every share keeps at least one of each and the shares never add up
+/// to more load than the test. Likewise no share of a rate drops below [`MIN_RATE`]. The rows of such a feeder are dealt out in turn so no row is sent
+/// twice. Requests are not recorded and no traces are taken, as neither travels back with the
+/// reports; tuning is left to each agent.
+pub fn split(
+    config: &LoadTestConfig,
+    agents: usize,
+) -> std::result::Result<Vec<LoadTestConfig>, ConfigError> {
+    if config
+        .stages
+        .iter()
+        .any(|stage| matches!(stage, Stage::Signal))
+    {
+        return Err(ConfigError::InvalidStage(
+            "Signal stages cannot be moved on across agents, use a wait stage".to_string(),
+        ));
+    }
+    let stage_concurrencies = config.stages.iter().filter_map(|stage| match stage {
+        Stage::Hold { concurrency, .. } | Stage::Burst { concurrency, .. } => Some(*concurrency),
+        Stage::Ramp { .. } | Stage::Wait(_) | Stage::Signal => None,
+    });
+    let unique_rows = config
+        .feeder
+        .as_ref()
+        .filter(|feeder| feeder.strategy == FeederStrategy::Unique)
+        .map(|feeder| feeder.rows.len());
+    let rate_shares = [config.rate, config.max_rate]
+        .into_iter()
+        .chain(config.stages.iter().map(Stage::rate))
+        .flatten()
+        .map(|rate| (rate / MIN_RATE) as usize);
+    let most = [config.requests, config.concurrency]
+        .into_iter()
+        .chain(config.max_concurrency)
+        .chain(stage_concurrencies)
+        .chain(unique_rows)
+        .chain(rate_shares)
+        .min()
+        .unwrap_or_default();
+    let agents = agents.clamp(1, most.max(1));
+    Ok((0..agents)
+        .map(|agent| {
+            let mut share = config.clone();
+            share.requests = portion(config.requests, agent, agents);
+            share.concurrency = portion(config.concurrency, agent, agents);
+            share.rate = config.rate.map(|rate| rate / agents as f64);
+            share.max_concurrency = config
+                .max_concurrency
+                .map(|max| portion(max, agent, agents));
+            share.max_rate = config.max_rate.map(|rate| rate / agents as f64);
+            share.stages = config
+                .stages
+                .iter()
+                .map(|stage| split_stage(stage, agent, agents))
+                .collect();
+            if let Some(feeder) = share
+                .feeder
+                .as_mut()
+                .filter(|feeder| feeder.strategy == FeederStrategy::Unique)
+            {
+                feeder.rows = feeder
+                    .rows
+                    .iter()
+                    .skip(agent)
+                    .step_by(agents)
+                    .cloned()
+                    .collect();
+            }
+            share.record_requests = false;
+            share.trace = None;
+            share.advanced = Default::default();
+            share
+        })
+        .collect())
+}
+
+/// The agent's part of `total`, the first agents taking one more of the remainder
+fn portion(total: usize, agent: usize, agents: usize) -> usize {
+    total / agents + usize::from(agent < total % agents)
+}
+
+fn split_stage(stage: &Stage, agent: usize, agents: usize) -> Stage {
+    let rate = |rate: &Option<f64>| rate.map(|rate| rate / agents as f64);
+    match stage {
+        Stage::Ramp {
+            duration,
+            target,
+            rate: ramp_rate,
+        } => Stage::Ramp {
+            duration: *duration,
+            target: portion(*target, agent, agents),
+            rate: rate(ramp_rate),
+        },
+        Stage::Hold {
+            duration,
+            concurrency,
+            rate: hold_rate,
+        } => Stage::Hold {
+            duration: *duration,
+            concurrency: portion(*concurrency, agent, agents),
+            rate: rate(hold_rate),
+        },
+        Stage::Burst {
+            on,
+            off,
+            cycles,
+            concurrency,
+            rate: burst_rate,
+        } => Stage::Burst {
+            on: *on,
+            off: *off,
+            cycles: *cycles,
+            concurrency: portion(*concurrency, agent, agents),
+            rate: rate(burst_rate),
+        },
+        Stage::Wait(_) | Stage::Signal => stage.clone(),
+    }
+}
+
+/// Status of a run as an agent reports it at `GET /load-tests/{id}`
+#[derive(Debug, Deserialize)]
+struct AgentRun {
+    id: String,
+    status: String,
+    progress: ProgressSnapshot,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl AgentRun {
+    /// State of the run, an agent reporting an unknown one has failed it
+    fn state(&self) -> RunState {
+        self.status.parse().unwrap_or(RunState::Failed)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.state().is_finished()
+    }
+}
+
+/// Runs a load test on several lode-api agents and merges their reports into one
+///
+/// The test is [`split`] into a share per agent, submitted to every agent before any is waited
+/// on, then followed until all shares finish. The reports of the agents are merged with
+/// [`Report::merge`], from their response time histograms and counters, so the percentiles are
+/// as precise as those of a single run and broken down by the agents' regions. Each report is
+/// first moved onto the controller's clock with a clock handshake against its agent, so the time
+/// series of the agents line up.
+#[derive(Debug, Clone)]
+pub struct Controller {
+    agents: Vec<Url>,
+    client: Client,
+    api_key: Option<String>,
+    poll_interval: Duration,
+}
+
+impl Controller {
+    /// Control the agents at the base URLs of their API, e.g. `http://10.0.0.5:8081`
+    pub fn new(agents: &[String]) -> Result<Self> {
+        if agents.is_empty() {
+            return Err(Error::Config("At least one agent is needed".to_string()));
+        }
+        let agents = agents
+            .iter()
+            .map(|agent| {
+                Url::parse(agent)
+                    .map_err(|e| Error::Config(format!("Invalid agent URL {}: {}", agent, e)))
+            })
+            .collect::<Result<_>>()?;
+        let client = Client::builder().timeout(AGENT_REQUEST_TIMEOUT).build()?;
+        Ok(Self {
+            agents,
+            client,
+            api_key: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        })
+    }
+
+    /// Send `api_key` to agents that require one
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    /// Poll the agents' runs every `interval`
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Run the test across the agents and merge their reports
+    ///
+    /// The progress bar follows the requests completed by all agents. Cancelling cancels the
+    /// shares still running, whose partial reports are merged as usual. When an agent refuses its
+    /// share, or the controller loses track of a share, the shares submitted and not known to be
+    /// finished are cancelled.
+    pub async fn run(
+        &self,
+        config: &LoadTestConfig,
+        progress_bar: Option<&ProgressBar>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<Report> {
+        // The agents cannot read the certificate files of this host, they get their contents
+        let config = &LoadTestConfig {
+            tls: config
+                .tls
+                .inlined()
+                .map_err(|e| Error::Config(e.to_string()))?,
+            ..config.clone()
+        };
+        let shares = split(config, self.agents.len())
+            .map_err(|e: ConfigError| Error::Config(e.to_string()))?;
+        let mut runs = Vec::with_capacity(shares.len());
+        for (agent, share) in self.agents.iter().zip(&shares) {
+            match self.submit(agent, share).await {
+                Ok(run) => {
+                    info!(
+                        "Agent {} runs {} of the {} requests as {}",
+                        agent, share.requests, config.requests, run.id
+                    );
+                    runs.push((agent, run));
+                }
+                Err(e) => {
+                    for (agent, run) in &runs {
+                        self.cancel(agent, &run.id).await;
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        if let Some(pb) = progress_bar {
+            pb.set_length(
+                runs.iter()
+                    .map(|(_, run)| run.progress.total_requests)
+                    .sum(),
+            );
+        }
+
+        let result = self.follow(&mut runs, progress_bar, cancel).await;
+        if result.is_err() {
+            for (agent, run) in &runs {
+                if !run.is_finished() {
+                    self.cancel(agent, &run.id).await;
+                }
+            }
+        }
+        result
+    }
+
+    /// Poll the runs of the agents until they all finish, then merge their reports
+    async fn follow(
+        &self,
+        runs: &mut [(&Url, AgentRun)],
+        progress_bar: Option<&ProgressBar>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<Report> {
+        let mut cancelled = false;
+        loop {
+            for (agent, run) in runs.iter_mut() {
+                if !run.is_finished() {
+                    *run = self.status(agent, &run.id).await?;
+                }
+            }
+            if let Some(pb) = progress_bar {
+                pb.set_position(
+                    runs.iter()
+                        .map(|(_, run)| run.progress.completed_requests)
+                        .sum(),
+                );
+            }
+            if runs.iter().all(|(_, run)| run.is_finished()) {
+                break;
+            }
+            let stop = async {
+                match cancel {
+                    Some(cancel) if !cancelled => cancel.cancelled().await,
+                    _ => futures::future::pending().await,
+                }
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(self.poll_interval) => {}
+                _ = stop => {
+                    cancelled = true;
+                    for (agent, run) in runs.iter() {
+                        if !run.is_finished() {
+                            self.cancel(agent, &run.id).await;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut reports = Vec::with_capacity(runs.len());
+        for (agent, run) in runs.iter() {
+            match run.state() {
+                RunState::Failed => {
+                    return Err(Error::Report(format!(
+                        "The run of agent {} failed: {}",
+                        agent,
+                        run.error.as_deref().unwrap_or("no details")
+                    )))
+                }
+                // Cancelled before it started, the agent measured nothing
+                RunState::Cancelled if run.progress.completed_requests == 0 => {
+                    warn!("Agent {} was cancelled before its run started", agent);
+                }
+                _ => {
+                    let mut report = self.report(agent, &run.id).await?;
+                    self.align_clock(agent, &mut report).await;
+                    reports.push(report);
+                }
+            }
+        }
+        Report::merge(&reports)
+    }
+
+    fn request(&self, builder: RequestBuilder) -> RequestBuilder {
+        match &self.api_key {
+            Some(key) => builder.bearer_auth(key),
+            None => builder,
+        }
+    }
+
+    async fn submit(&self, agent: &Url, share: &LoadTestConfig) -> Result<AgentRun> {
+        let response = self
+            .request(self.client.post(endpoint(agent, AGENT_SUBMIT_PATH)?))
+            .json(share)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::ACCEPTED => Ok(response.json().await?),
+            status => Err(Error::Http(format!(
+                "Agent {} refused its share with {}: {}",
+                agent,
+                status,
+                response.text().await.unwrap_or_default()
+            ))),
+        }
+    }
+
+    async fn status(&self, agent: &Url, id: &str) -> Result<AgentRun> {
+        let path = format!("/load-tests/{}", id);
+        Ok(self
+            .request(self.client.get(endpoint(agent, &path)?))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    async fn report(&self, agent: &Url, id: &str) -> Result<Report> {
+        let path = format!("/load-tests/{}/report", id);
+        Ok(self
+            .request(self.client.get(endpoint(agent, &path)?))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    /// Shift the report of an agent onto the controller's clock, from the offset measured against
+    /// the agent's clock; the report of an agent that cannot tell its time is left on its clock
+    async fn align_clock(&self, agent: &Url, report: &mut Report) {
+        let measured = match endpoint(agent, AGENT_CLOCK_PATH) {
+            Ok(url) => {
+                ClockOffset::measure_with(
+                    || self.request(self.client.get(url.clone())),
+                    DEFAULT_CLOCK_SAMPLES,
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        match measured {
+            Ok(offset) => {
+                // The handshake measures the agent's clock from the controller's
+                let offset = offset.reversed();
+                info!(
+                    "Clock offset of agent {}: {:.1}ms (round trip {:.1}ms)",
+                    agent, offset.offset_ms, offset.round_trip_ms
+                );
+                report.started_at = report.started_at.map(|at| offset.apply(at));
+                report.clock_offset = Some(offset);
+            }
+            Err(e) => warn!(
+                "Failed to measure the clock offset of agent {}, its report stays on its clock: {}",
+                agent, e
+            ),
+        }
+    }
+
+    /// Cancel a run, which may have finished in the meantime
+    async fn cancel(&self, agent: &Url, id: &str) {
+        let path = format!("/load-tests/{}", id);
+        let Ok(url) = endpoint(agent, &path) else {
+            return;
+        };
+        if let Err(e) = self.request(self.client.delete(url)).send().await {
+            warn!("Failed to cancel run {} of agent {}: {}", id, agent, e);
+        }
+    }
+}
+
+/// URL of a path of the agent's API, under the path of its base URL
+fn endpoint(agent: &Url, path: &str) -> Result<Url> {
+    let base = agent.as_str().trim_end_matches('/');
+    Url::parse(&format!("{}{}", base, path))
+        .map_err(|e| Error::Config(format!("Invalid agent URL {}: {}", agent, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Feeder, HttpMethod};
+    use crate::scenario::extract::Variables;
+
+    fn base_config(requests: usize, concurrency: usize) -> LoadTestConfig {
+        LoadTestConfig::new(
+            "https://example.com".to_string(),
+            HttpMethod::GET,
+            requests,
+            concurrency,
+            Duration::from_secs(5),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_split_divides_the_load() {
+        let mut config = base_config(10, 5).with_rate(90.0).with_stage(Stage::Ramp {
+            duration: Duration::from_secs(10),
+            target: 7,
+            rate: Some(30.0),
+        });
+        config.record_requests = true;
+        let shares = split(&config, 3).unwrap();
+        let requests: Vec<usize> = shares.iter().map(|share| share.requests).collect();
+        assert_eq!(requests, [4, 3, 3]);
+        let concurrency: Vec<usize> = shares.iter().map(|share| share.concurrency).collect();
+        assert_eq!(concurrency, [2, 2, 1]);
+        assert!(shares.iter().all(|share| share.rate == Some(30.0)));
+        assert!(shares.iter().all(|share| !share.record_requests));
+        assert_eq!(
+            shares[2].stages[0],
+            Stage::Ramp {
+                duration: Duration::from_secs(10),
+                target: 2,
+                rate: Some(10.0),
+            }
+        );
+
+        // No agent is left without requests or virtual users, which would add to the load
+        let shares = split(&base_config(10, 2), 5).unwrap();
+        assert_eq!(shares.len(), 2);
+        assert!(shares.iter().all(|share| share.concurrency == 1));
+        let config = base_config(10, 4).with_stage(Stage::Hold {
+            duration: Duration::from_secs(10),
+            concurrency: 3,
+            rate: None,
+        });
+        let shares = split(&config, 4).unwrap();
+        assert_eq!(shares.len(), 3);
+        let held: usize = shares
+            .iter()
+            .map(|share| share.stages[0].max_concurrency())
+            .sum();
+        assert_eq!(held, 3);
+
+        // Nor with a rate too low to be paced
+        let config = base_config(10, 4).with_rate(0.002).with_stage(Stage::Hold {
+            duration: Duration::from_secs(10),
+            concurrency: 4,
+            rate: Some(0.003),
+        });
+        let shares = split(&config, 3).unwrap();
+        assert_eq!(shares.len(), 2);
+        assert!(shares
+            .iter()
+            .all(|share| share.rate.unwrap() >= MIN_RATE
+                && share.stages[0].rate().unwrap() >= MIN_RATE));
+        assert!(split(&base_config(10, 1).with_stage(Stage::Signal), 2).is_err());
+    }
+
+    #[test]
+    fn test_split_deals_out_unique_rows() {
+        let rows: Vec<Variables> = (0..5)
+            .map(|i| Variables::from([("id".to_string(), i.to_string())]))
+            .collect();
+        let config = base_config(5, 2)
+            .with_feeder(Feeder::new(rows.clone()).with_strategy(FeederStrategy::Unique));
+        let shares = split(&config, 2).unwrap();
+        let ids = |share: &LoadTestConfig| -> Vec<String> {
+            let feeder = share.feeder.as_ref().unwrap();
+            feeder.rows.iter().map(|row| row["id"].clone()).collect()
+        };
+        assert_eq!(ids(&shares[0]), ["0", "2", "4"]);
+        assert_eq!(ids(&shares[1]), ["1", "3"]);
+
+        // Every agent gets at least one row
+        let few = Feeder::new(rows[..2].to_vec()).with_strategy(FeederStrategy::Unique);
+        let shares = split(&base_config(10, 4).with_feeder(few), 4).unwrap();
+        assert_eq!(shares.len(), 2);
+
+        // Circular rows are shared by every agent
+        let config = config.with_feeder(Feeder::new(rows));
+        assert!(split(&config, 2).unwrap().iter().all(|share| share
+            .feeder
+            .as_ref()
+            .unwrap()
+            .rows
+            .len()
+            == 5));
+    }
+
+    #[test]
+    fn test_agent_endpoints() {
+        let agent = Url::parse("http://10.0.0.5:8081/lode/").unwrap();
+        assert_eq!(
+            endpoint(&agent, AGENT_SUBMIT_PATH).unwrap().as_str(),
+            "http://10.0.0.5:8081/lode/agent/load-tests"
+        );
+        assert!(Controller::new(&[]).is_err());
+        assert!(Controller::new(&["not a url".to_string()]).is_err());
+    }
+}
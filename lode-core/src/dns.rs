@@ -0,0 +1,155 @@
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::TokioAsyncResolver;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use crate::config::ConfigError;
+use crate::error::Error;
+
+/// Host name resolution of a run by the built-in resolver instead of the system's, e.g. to ask
+/// other nameservers or to look every host name up again for each connection
+///
+/// Lookups are timed either way, as the `dns` phase of the requests that open connections.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DnsConfig {
+    /// Nameservers asked over UDP, falling back to TCP, e.g. `10.0.0.2:53`; those of the system's
+    /// configuration when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nameservers: Vec<SocketAddr>,
+    /// Give up on a query to a nameserver after this long, 5s by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<Duration>,
+    /// Ask the nameservers for every lookup instead of keeping answers for their TTL
+    #[serde(default)]
+    pub no_cache: bool,
+}
+
+impl DnsConfig {
+    /// Check that the timeout leaves time for a query
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.timeout.is_some_and(|timeout| timeout.is_zero()) {
+            return Err(ConfigError::InvalidDns(
+                "Timeout must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Port of nameservers given without one
+pub const DNS_PORT: u16 = 53;
+
+/// Parse a nameserver address, e.g. `10.0.0.2`, `10.0.0.2:5353` or `[2001:db8::53]:53`
+pub fn parse_nameserver(s: &str) -> Result<SocketAddr, ConfigError> {
+    let s = s.trim();
+    s.parse::<SocketAddr>()
+        .or_else(|_| s.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, DNS_PORT)))
+        .map_err(|_| {
+            ConfigError::InvalidDns(format!(
+                "'{}' is not a nameserver address, expected an IP address and an optional port",
+                s
+            ))
+        })
+}
+
+/// Resolves host names with hickory-dns by a [`DnsConfig`]
+#[derive(Debug, Clone)]
+pub struct HickoryResolver {
+    resolver: TokioAsyncResolver,
+}
+
+impl HickoryResolver {
+    /// Create a resolver; without nameservers the system's configuration is read, failing when it
+    /// cannot be
+    pub fn new(config: &DnsConfig) -> Result<Self, Error> {
+        let (resolver_config, mut options) = if config.nameservers.is_empty() {
+            hickory_resolver::system_conf::read_system_conf().map_err(|e| {
+                Error::Config(format!("Failed to read the DNS configuration: {}", e))
+            })?
+        } else {
+            let mut nameservers = NameServerConfigGroup::new();
+            for address in &config.nameservers {
+                nameservers.merge(NameServerConfigGroup::from_ips_clear(
+                    &[address.ip()],
+                    address.port(),
+                    true,
+                ));
+            }
+            (
+                ResolverConfig::from_parts(None, Vec::new(), nameservers),
+                ResolverOpts::default(),
+            )
+        };
+        if let Some(timeout) = config.timeout {
+            options.timeout = timeout;
+        }
+        if config.no_cache {
+            options.cache_size = 0;
+        }
+        Ok(Self {
+            resolver: TokioAsyncResolver::tokio(resolver_config, options),
+        })
+    }
+}
+
+impl Resolve for HickoryResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.resolver.clone();
+        Box::pin(async move {
+            let lookup = resolver.lookup_ip(name.as_str()).await?;
+            let addrs: Vec<SocketAddr> = lookup.iter().map(|ip| SocketAddr::new(ip, 0)).collect();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dns_config_validation() {
+        assert!(DnsConfig::default().validate().is_ok());
+        let config = DnsConfig {
+            timeout: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::InvalidDns(_))));
+    }
+
+    #[test]
+    fn test_parse_nameserver() {
+        assert_eq!(
+            parse_nameserver("10.0.0.2").unwrap(),
+            "10.0.0.2:53".parse().unwrap()
+        );
+        assert_eq!(
+            parse_nameserver("10.0.0.2:5353").unwrap(),
+            "10.0.0.2:5353".parse().unwrap()
+        );
+        assert_eq!(
+            parse_nameserver("[2001:db8::53]:53").unwrap(),
+            "[2001:db8::53]:53".parse().unwrap()
+        );
+        assert_eq!(
+            parse_nameserver("2001:db8::53").unwrap(),
+            "[2001:db8::53]:53".parse().unwrap()
+        );
+        assert!(parse_nameserver("dns.example.com").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_nameserver_fails_lookup() {
+        // Nothing answers DNS queries on the discard port
+        let resolver = HickoryResolver::new(&DnsConfig {
+            nameservers: vec!["127.0.0.1:9".parse().unwrap()],
+            timeout: Some(Duration::from_millis(100)),
+            no_cache: true,
+        })
+        .unwrap();
+        let name: Name = "lode.invalid".parse().unwrap();
+        assert!(resolver.resolve(name).await.is_err());
+    }
+}
@@ -1,18 +1,913 @@
-use crate::error::Result;
-use crate::http::HttpClient;
-use crate::metrics::{RequestMetrics, TestMetrics};
+use crate::abort::CircuitBreaker;
+use crate::clock::ClockOffset;
+use crate::config::{
+    interval_at, ConfigError, FeederExhaustion, LoadModel, LoadTestConfig, MissingValue,
+    RandomParam, ResponseBody,
+};
+use crate::control::LoadControl;
+use crate::cooldown::{CooldownMetrics, Prober};
+use crate::error::{Error, Result};
+use crate::faults::{active_fault, GeneratorFault};
+use crate::form::FormTemplate;
+use crate::graphql;
+use crate::http::{connection_addr, connection_setup, retry_after, HttpClient};
+use crate::live::LiveMetrics;
+use crate::memory::MemoryGuard;
+use crate::metrics::Labels;
+use crate::metrics::{ErrorKind, Phase, RequestMetrics, RunEvent, SuccessCodes, TestMetrics};
+use crate::pacing::Pacer;
+use crate::progress::Progress;
+use crate::recovery::Recovery;
+use crate::retry::random_fraction;
+use crate::scenario::extract::{placeholders, render, CompiledExtractor};
+use crate::scenario::fair::{FairQueue, FairSlot};
+use crate::scenario::query::append_random_params;
+use crate::scenario::{ScenarioMode, ENDPOINT_LABEL};
+use crate::stages::{Stage, WaitOutcome};
+use crate::telemetry::{ErrorLog, ERROR_LOG_WINDOW};
+use crate::trace::{http_version_name, TraceEntry, TraceSampler, TracedBody, TracedResponse};
+use crate::trigger::StageTrigger;
+use crate::vu::{VirtualUser, VirtualUsers};
 
+use futures::future;
 use futures::stream::{self, StreamExt};
 use indicatif::ProgressBar;
-use reqwest::Method;
+use reqwest::{Method, Response};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Notify};
+use tokio_util::sync::CancellationToken;
 use tracing::{info, instrument, warn};
 
+/// How often a request blocked by the concurrency gate re-checks the allowed concurrency
+const GATE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often a ramp stage updates the allowed concurrency and rate
+const STAGE_TICK: Duration = Duration::from_millis(100);
+
+/// Observers attached to a single load test run
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Progress bar advanced after every completed request
+    pub progress_bar: Option<ProgressBar>,
+
+    /// Progress counters that can be snapshotted while the run is in flight
+    pub progress: Option<Progress>,
+
+    /// Offset of the local clock from the coordinator of a distributed run
+    pub clock_offset: Option<ClockOffset>,
+
+    /// Publisher of periodic metrics snapshots while the run is in flight
+    pub live: Option<LiveMetrics>,
+
+    /// Stops the run early once cancelled; the metrics then cover the requests completed so far
+    pub cancel: Option<CancellationToken>,
+
+    /// Writes the metrics collected so far to a file if the process panics during the run
+    pub recovery: Option<Recovery>,
+
+    /// Moves a staged run to its next stage when fired
+    pub trigger: Option<StageTrigger>,
+
+    /// Overrides the concurrency and rate of the run while it is in flight
+    pub control: Option<LoadControl>,
+}
+
+/// Why a run stopped before sending all its requests
+enum Stop {
+    Cancelled,
+    Deadline,
+}
+
+/// Caps the number of requests in flight; the cap may change while the run is in progress
+struct ConcurrencyGate {
+    in_flight: std::sync::Mutex<usize>,
+    released: Notify,
+}
+
+/// A slot in the concurrency gate, released on drop
+struct GatePermit<'a> {
+    gate: &'a ConcurrencyGate,
+}
+
+impl ConcurrencyGate {
+    fn new() -> Self {
+        Self {
+            in_flight: std::sync::Mutex::new(0),
+            released: Notify::new(),
+        }
+    }
+
+    /// Wait until fewer than `limit()` requests are in flight and take a slot
+    ///
+    /// Returns `None` once `limit()` returns `None`, i.e. when the run no longer accepts requests.
+    async fn acquire(&self, limit: impl Fn() -> Option<usize>) -> Option<GatePermit<'_>> {
+        loop {
+            let released = self.released.notified();
+            {
+                let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+                if *in_flight < limit()? {
+                    *in_flight += 1;
+                    return Some(GatePermit { gate: self });
+                }
+            }
+            let _ = tokio::time::timeout(GATE_POLL_INTERVAL, released).await;
+        }
+    }
+}
+
+impl Drop for GatePermit<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self
+            .gate
+            .in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        *in_flight -= 1;
+        self.gate.released.notify_one();
+    }
+}
+
+/// Paces requests so they start at most `rate` times per second
+///
+/// Each request reserves the next free start slot, one interval after the previous one. In the
+/// closed model slots are never reserved further in the past than the burst allows, a token
+/// bucket of that capacity: with the default of 1 idle periods (e.g. a pause fault) do not turn
+/// into bursts, with more up to `burst` requests may start at once after one. The open models keep
+/// the schedule: a request reserving its slot late gets the one it was due in, so the time it
+/// waited counts against it.
+struct RateLimiter {
+    state: std::sync::Mutex<LimiterState>,
+    model: LoadModel,
+    burst: u32,
+}
+
+struct LimiterState {
+    /// Requests per second, `None` when unlimited
+    rate: Option<f64>,
+    next_slot: Option<Instant>,
+}
+
+impl RateLimiter {
+    fn new(rate: Option<f64>, model: LoadModel, burst: u32) -> Self {
+        Self {
+            state: std::sync::Mutex::new(LimiterState {
+                rate,
+                next_slot: None,
+            }),
+            model,
+            burst: burst.max(1),
+        }
+    }
+
+    fn rate(&self) -> Option<f64> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).rate
+    }
+
+    fn set_rate(&self, rate: Option<f64>) {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).rate = rate;
+    }
+
+    /// Wait for the next start slot and return it, `None` when the rate is unlimited
+    ///
+    /// An `overridden` rate replaces the one set by the stages, and the rate never exceeds `cap`.
+    async fn wait(&self, overridden: Option<f64>, cap: Option<f64>) -> Option<Instant> {
+        let slot = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let rate = match (overridden.or(state.rate), cap) {
+                (Some(rate), Some(cap)) => rate.min(cap),
+                (rate, cap) => rate.or(cap)?,
+            };
+            let now = Instant::now();
+            let slot = match self.model {
+                // The slots of the requests the bucket holds may be taken at once
+                LoadModel::Closed => state.next_slot.map_or(now, |next| {
+                    let held = interval_at(rate, f64::from(self.burst - 1));
+                    next.checked_sub(held).unwrap_or(now).max(now)
+                }),
+                LoadModel::Open | LoadModel::Poisson => state.next_slot.unwrap_or(now),
+            };
+            // Exponential gaps averaging 1 / rate make Poisson arrivals
+            let gap = match self.model {
+                LoadModel::Poisson => interval_at(rate, -(1.0 - random_fraction()).ln()),
+                LoadModel::Closed | LoadModel::Open => interval_at(rate, 1.0),
+            };
+            let reserved = state.next_slot.map_or(slot, |next| next.max(slot));
+            state.next_slot = Some(reserved + gap);
+            slot
+        };
+        tokio::time::sleep_until(slot.into()).await;
+        Some(slot)
+    }
+}
+
+/// Concurrency and rate allowed at each point of a run
+struct LoadShape<'a> {
+    /// Concurrency of the current stage
+    concurrency: AtomicUsize,
+    /// Run-level rate, used by stages without a rate of their own
+    base_rate: Option<f64>,
+    limiter: RateLimiter,
+    /// Set once the last stage has completed
+    stopped: AtomicBool,
+    faults: &'a [GeneratorFault],
+    announced: Vec<AtomicBool>,
+    /// Live overrides taking precedence over the stages
+    control: Option<LoadControl>,
+    /// Cap on the rate from the rate limit headers of the responses
+    pacer: Option<Arc<Pacer>>,
+}
+
+impl<'a> LoadShape<'a> {
+    fn new(
+        config: &'a LoadTestConfig,
+        control: Option<LoadControl>,
+        pacer: Option<Arc<Pacer>>,
+    ) -> Self {
+        Self {
+            concurrency: AtomicUsize::new(config.concurrency),
+            base_rate: config.rate,
+            limiter: RateLimiter::new(
+                config.rate,
+                config.load_model,
+                config.rate_burst.unwrap_or(1),
+            ),
+            stopped: AtomicBool::new(false),
+            faults: &config.faults,
+            announced: config
+                .faults
+                .iter()
+                .map(|_| AtomicBool::new(false))
+                .collect(),
+            control,
+            pacer,
+        }
+    }
+
+    /// Concurrency allowed at the given offset into the run, `None` once the run is over
+    fn concurrency_at(&self, elapsed: Duration) -> Option<usize> {
+        if self.is_stopped() {
+            return None;
+        }
+        let concurrency = self
+            .control
+            .as_ref()
+            .and_then(|control| control.current().concurrency)
+            .unwrap_or_else(|| self.concurrency.load(Ordering::Relaxed));
+        Some(match active_fault(self.faults, elapsed) {
+            Some(fault) => {
+                self.announce(fault);
+                fault.concurrency_limit(concurrency)
+            }
+            None => concurrency,
+        })
+    }
+
+    /// Wait until the rate allows the next request to start, and return when it was due to
+    async fn pace(&self) -> Instant {
+        let ready = Instant::now();
+        let overridden = self
+            .control
+            .as_ref()
+            .and_then(|control| control.current().rate);
+        let cap = self.pacer.as_ref().and_then(|pacer| pacer.rate());
+        self.limiter.wait(overridden, cap).await.unwrap_or(ready)
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    /// End the run before its last stage, e.g. once there is no more data to send
+    fn stop(&self, reason: &str) {
+        if !self.stopped.swap(true, Ordering::Relaxed) {
+            warn!("{}, stopping the run", reason);
+        }
+    }
+
+    /// Walk through the stages, adjusting the allowed concurrency and rate as they progress
+    ///
+    /// The requests completed during each stage are counted in a stage of `metrics`. A `trigger`
+    /// ends the current stage early.
+    async fn run_stages(
+        &self,
+        stages: &[Stage],
+        metrics: &Mutex<TestMetrics>,
+        trigger: Option<&StageTrigger>,
+        probe: &reqwest::Client,
+    ) {
+        for (index, stage) in stages.iter().enumerate() {
+            info!("Starting stage {}/{}: {}", index + 1, stages.len(), stage);
+            metrics.lock().await.start_stage(stage.to_string());
+            let run = self.run_stage(stage, probe, trigger.is_some());
+            match trigger {
+                Some(trigger) => tokio::select! {
+                    _ = run => {}
+                    _ = trigger.triggered() => {
+                        info!("Stage {}/{} ended by a trigger", index + 1, stages.len())
+                    }
+                },
+                None => run.await,
+            }
+        }
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    /// Apply one stage for as long as it lasts
+    async fn run_stage(&self, stage: &Stage, probe: &reqwest::Client, triggered: bool) {
+        match stage {
+            Stage::Ramp {
+                duration,
+                target,
+                rate,
+            } => self.ramp(*duration, *target, *rate).await,
+            Stage::Hold {
+                duration,
+                concurrency,
+                rate,
+            } => {
+                self.concurrency.store(*concurrency, Ordering::Relaxed);
+                self.limiter.set_rate(rate.or(self.base_rate));
+                tokio::time::sleep(*duration).await;
+            }
+            Stage::Burst {
+                on,
+                off,
+                cycles,
+                concurrency,
+                rate,
+            } => {
+                for cycle in 1..=*cycles {
+                    info!("Burst {}/{}", cycle, cycles);
+                    self.concurrency.store(*concurrency, Ordering::Relaxed);
+                    self.limiter.set_rate(rate.or(self.base_rate));
+                    tokio::time::sleep(*on).await;
+                    self.concurrency.store(0, Ordering::Relaxed);
+                    tokio::time::sleep(*off).await;
+                }
+            }
+            Stage::Wait(condition) => match condition.wait(probe).await {
+                WaitOutcome::Met { value, waited } => {
+                    info!("Wait condition met after {:?} at {}", waited, value)
+                }
+                WaitOutcome::TimedOut { waited } => {
+                    warn!("Wait condition timed out after {:?}", waited)
+                }
+            },
+            Stage::Signal if triggered => {
+                info!("Holding the load until a trigger moves the run on");
+                future::pending::<()>().await
+            }
+            Stage::Signal => warn!("No trigger can end the signal stage, moving on"),
+        }
+    }
+
+    /// Linearly move from the current concurrency and rate to the targets over `duration`
+    ///
+    /// Without a current rate to start from, the target rate applies from the start of the ramp.
+    async fn ramp(&self, duration: Duration, target: usize, target_rate: Option<f64>) {
+        let from = self.concurrency.load(Ordering::Relaxed) as f64;
+        let from_rate = self.limiter.rate();
+        let started = Instant::now();
+
+        loop {
+            let elapsed = started.elapsed();
+            let fraction = if duration.is_zero() {
+                1.0
+            } else {
+                (elapsed.as_secs_f64() / duration.as_secs_f64()).min(1.0)
+            };
+
+            let concurrency = from + (target as f64 - from) * fraction;
+            self.concurrency
+                .store(concurrency.ceil() as usize, Ordering::Relaxed);
+            self.limiter.set_rate(match (from_rate, target_rate) {
+                (Some(from), Some(to)) => Some(from + (to - from) * fraction),
+                (None, Some(to)) => Some(to),
+                (_, None) => self.base_rate,
+            });
+
+            if fraction >= 1.0 {
+                return;
+            }
+            tokio::time::sleep(STAGE_TICK.min(duration - elapsed)).await;
+        }
+    }
+
+    fn announce(&self, fault: &GeneratorFault) {
+        let index = self
+            .faults
+            .iter()
+            .position(|f| std::ptr::eq(f, fault))
+            .unwrap_or_default();
+        if !self.announced[index].swap(true, Ordering::Relaxed) {
+            warn!("Generator fault active: {}", fault);
+        }
+    }
+}
+
+/// A fully resolved request the engine can send
+struct RequestTarget {
+    method: Method,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+    /// Form sent when there is no raw body
+    form: Option<Arc<FormTemplate>>,
+    labels: Labels,
+    extract: Vec<CompiledExtractor>,
+    response_body: ResponseBody,
+    /// Bytes of a read body kept in memory
+    max_body_size: usize,
+    random_params: Vec<RandomParam>,
+    /// Whether the body is a GraphQL operation, whose response fails with `errors`
+    graphql: bool,
+    /// Status codes counted as success instead of any 2xx
+    success_codes: Option<Arc<SuccessCodes>>,
+    /// Follows the rate limit headers of the responses, shared by all requests of the run
+    pacer: Option<Arc<Pacer>>,
+    /// Picks the requests traced with their headers and bodies, shared by all requests of the run
+    trace: Option<Arc<TraceSampler>>,
+    /// Names of the `{{name}}` placeholders of the URL, header values, body and form
+    placeholders: Vec<String>,
+}
+
+impl RequestTarget {
+    /// Find the placeholders of the request, once its parts are set
+    fn with_placeholders(mut self) -> Self {
+        let mut names: Vec<String> = std::iter::once(self.url.as_str())
+            .chain(self.headers.iter().map(|(_, value)| value.as_str()))
+            .chain(self.body.as_deref())
+            .chain(self.form.iter().flat_map(|form| form.texts()))
+            .flat_map(placeholders)
+            .map(str::to_string)
+            .collect();
+        names.sort();
+        names.dedup();
+        self.placeholders = names;
+        self
+    }
+
+    /// The first placeholder of the request without a value for the virtual user
+    fn missing_value(&self, vu: &VirtualUser) -> Option<&str> {
+        let variables = vu.variables();
+        self.placeholders
+            .iter()
+            .find(|name| !variables.contains_key(name.as_str()))
+            .map(String::as_str)
+    }
+
+    /// Send the request of a virtual user with its values and cookies filled in and measure it
+    ///
+    /// The response body is consumed by the run's policy; unless it is discarded the response time
+    /// covers its download. A body read into memory is cut at the max body size. The cookies of the
+    /// response and the values extracted from it are kept by the virtual user; a response missing
+    /// one of the values fails the request, as does a GraphQL response with errors. A request
+    /// picked by the trace sampling carries its trace.
+    async fn send<C: HttpClient>(
+        &self,
+        client: &C,
+        timeout: Duration,
+        vu: &mut VirtualUser,
+    ) -> RequestMetrics {
+        let mut trace = None;
+        let request_metrics = self.send_traced(client, timeout, vu, &mut trace).await;
+        let Some(mut entry) = trace else {
+            return request_metrics;
+        };
+        entry.time = request_metrics
+            .duration()
+            .unwrap_or_else(|| request_metrics.start_time().elapsed());
+        entry.error = match request_metrics.failure() {
+            Some((_, message)) => Some(message.to_string()),
+            None => request_metrics.error().map(ToString::to_string),
+        };
+        if !self
+            .trace
+            .as_ref()
+            .is_some_and(|sampler| sampler.keep_secrets())
+        {
+            entry.redact();
+        }
+        request_metrics.with_trace(entry)
+    }
+
+    /// Send and measure the request, filling in `trace` when the request is sampled
+    async fn send_traced<C: HttpClient>(
+        &self,
+        client: &C,
+        timeout: Duration,
+        vu: &mut VirtualUser,
+        trace: &mut Option<TraceEntry>,
+    ) -> RequestMetrics {
+        let mut request_metrics = RequestMetrics::new().with_labels(self.labels.clone());
+        if let Some(success_codes) = &self.success_codes {
+            request_metrics = request_metrics.with_success_codes(Arc::clone(success_codes));
+        }
+        let variables = vu.variables();
+        let mut headers: Vec<_> = self
+            .headers
+            .iter()
+            .map(|(name, value)| (name.clone(), render(value, variables)))
+            .collect();
+        let url = append_random_params(&render(&self.url, variables), &self.random_params);
+        if let Some(cookies) = vu.cookies().header(&url) {
+            if !headers
+                .iter()
+                .any(|(name, _)| name.eq_ignore_ascii_case("cookie"))
+            {
+                headers.push(("Cookie".to_string(), cookies));
+            }
+        }
+        let cookie_url = url.clone();
+        if self.trace.as_ref().is_some_and(|sampler| sampler.sample()) {
+            *trace = Some(TraceEntry::new(self.method.as_str(), &url));
+        }
+        let trace_body_size = self
+            .trace
+            .as_ref()
+            .map_or(0, |sampler| sampler.max_body_size());
+        let request = match (&self.body, &self.form) {
+            (None, Some(form)) => {
+                if !headers
+                    .iter()
+                    .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                {
+                    headers.push(("Content-Type".to_string(), form.content_type()));
+                }
+                let body = form.encode(variables);
+                if let Some(entry) = trace.as_mut() {
+                    entry.headers = headers.clone();
+                    entry.body = Some(TracedBody::new(&body, body.len() as u64, trace_body_size));
+                }
+                let sent = request_size(&self.method, &url, &headers, body.len());
+                request_metrics = request_metrics.with_bytes(sent, 0);
+                client.send_bytes(self.method.clone(), url, timeout, headers, body)
+            }
+            (body, _) => {
+                let body = body.as_deref().map(|body| render(body, variables));
+                let body_len = body.as_ref().map_or(0, String::len);
+                if let Some(entry) = trace.as_mut() {
+                    entry.headers = headers.clone();
+                    entry.body = body.as_ref().map(|body| {
+                        TracedBody::new(body.as_bytes(), body_len as u64, trace_body_size)
+                    });
+                }
+                let sent = request_size(&self.method, &url, &headers, body_len);
+                request_metrics = request_metrics.with_bytes(sent, 0);
+                client.send_request(self.method.clone(), url, timeout, headers, body)
+            }
+        };
+        // Enforced here too, for clients that do not apply the timeout themselves
+        let result = match tokio::time::timeout(timeout, request).await {
+            Ok(result) => result,
+            Err(_) => return request_metrics.time_out(timeout),
+        };
+
+        let response = match result {
+            Ok(response) => response,
+            Err(error) => return request_metrics.record_error(error),
+        };
+        let sent = request_metrics.bytes_sent();
+        let head = response_head_size(&response);
+        let setup = connection_setup(&response).unwrap_or_default();
+        let ttfb = request_metrics
+            .start_time()
+            .elapsed()
+            .saturating_sub(setup.total());
+        let status = response.status();
+        let headers = response.headers().clone();
+        if let Some(entry) = trace.as_mut() {
+            entry.dns = setup.dns;
+            entry.connect = setup.connect.or(setup.tls);
+            entry.tls = setup.tls;
+            entry.wait = Some(ttfb);
+            entry.response = Some(TracedResponse {
+                status: status.as_u16(),
+                http_version: http_version_name(response.version()).to_string(),
+                headers: headers
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = String::from_utf8_lossy(value.as_bytes());
+                        (name.to_string(), value.into_owned())
+                    })
+                    .collect(),
+                body: None,
+                body_error: None,
+            });
+        }
+        vu.cookies_mut().store(&cookie_url, &headers);
+        if let Some(pacer) = &self.pacer {
+            pacer.observe(&headers);
+        }
+        let request_metrics = request_metrics
+            .with_connection(connection_addr(&response))
+            .with_tls_resumption(setup.tls_resumed)
+            .with_retry_after(retry_after(&response))
+            .with_version(response.version())
+            .with_phase(Phase::Dns, setup.dns)
+            .with_phase(Phase::Connect, setup.connect)
+            .with_phase(Phase::Tls, setup.tls)
+            .with_phase(Phase::Ttfb, Some(ttfb));
+
+        let policy = match self.extract.is_empty() && !self.graphql {
+            true => self.response_body,
+            false => ResponseBody::Read,
+        };
+        let download_started = Instant::now();
+        let mut drained = 0;
+        let mut drained_start = Vec::new();
+        let keep = match trace {
+            Some(_) => trace_body_size,
+            None => 0,
+        };
+        let body = match policy {
+            ResponseBody::Read => read_capped(response, self.max_body_size).await.map(Some),
+            ResponseBody::Drain => drain(response, keep).await.map(|(read, start)| {
+                drained = read;
+                drained_start = start;
+                None
+            }),
+            ResponseBody::Discard => {
+                return request_metrics.with_bytes(sent, head).complete(status)
+            }
+        };
+        let truncated = matches!(body, Ok(Some((_, true))));
+        let body_read = match &body {
+            Ok(Some((body, _))) => body.len() as u64,
+            Ok(None) => drained,
+            Err(_) => 0,
+        };
+        if let Some(response) = trace.as_mut().and_then(|entry| entry.response.as_mut()) {
+            match &body {
+                Ok(Some((body, _))) => {
+                    response.body = Some(TracedBody::new(body, body_read, trace_body_size))
+                }
+                Ok(None) => {
+                    response.body = Some(TracedBody::new(&drained_start, drained, trace_body_size))
+                }
+                Err(error) => response.body_error = Some(error.to_string()),
+            }
+        }
+        let download = download_started.elapsed();
+        if let Some(entry) = trace.as_mut() {
+            entry.receive = Some(download);
+        }
+        let request_metrics = request_metrics
+            .with_bytes(sent, head + body_read)
+            .with_phase(Phase::Download, Some(download))
+            .with_truncated_body(truncated)
+            .complete(status);
+        let body = match body {
+            Ok(Some((body, _))) if request_metrics.is_success() => body,
+            Ok(_) => return request_metrics,
+            Err(error) => return request_metrics.fail(ErrorKind::Body, error.to_string()),
+        };
+
+        let body = String::from_utf8_lossy(&body);
+        if self.graphql {
+            if let Some(error) = graphql::response_error(&body) {
+                return request_metrics.fail(ErrorKind::Graphql, error);
+            }
+        }
+        for extractor in &self.extract {
+            match extractor.extract(&headers, &body) {
+                Some(value) => {
+                    vu.variables_mut().insert(extractor.name.clone(), value);
+                }
+                None => {
+                    let mut message =
+                        format!("No value for {} in the response", extractor.describe());
+                    if truncated {
+                        message.push_str(&format!(
+                            " (body truncated at {} bytes)",
+                            self.max_body_size
+                        ));
+                    }
+                    return request_metrics.fail(ErrorKind::Assertion, message);
+                }
+            }
+        }
+        request_metrics
+    }
+}
+
+/// Read a response body into memory up to `limit` bytes, telling whether it was cut
+///
+/// The rest of a cut body is left unread, so its connection is closed instead of going back to
+/// the pool.
+async fn read_capped(mut response: Response, limit: usize) -> reqwest::Result<(Vec<u8>, bool)> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        let room = limit - body.len();
+        if chunk.len() > room {
+            body.extend_from_slice(&chunk[..room]);
+            return Ok((body, true));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((body, false))
+}
+
+/// Read a response body to its end, so its connection can be reused, keeping only its first
+/// `keep` bytes
+///
+/// Returns the number of bytes read with those kept.
+async fn drain(mut response: Response, keep: usize) -> reqwest::Result<(u64, Vec<u8>)> {
+    let mut read = 0;
+    let mut start = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        let room = keep - start.len();
+        start.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        read += chunk.len() as u64;
+    }
+    Ok((read, start))
+}
+
+/// Bytes of a request as written in HTTP/1.1: request line, headers, blank line and body
+///
+/// Headers the client adds on its own, such as `Host`, are not counted.
+fn request_size(method: &Method, url: &str, headers: &[(String, String)], body: usize) -> u64 {
+    let target = reqwest::Url::parse(url)
+        .map(|url| url[url::Position::BeforePath..url::Position::AfterQuery].len())
+        .unwrap_or(url.len());
+    let line = method.as_str().len() + 1 + target + " HTTP/1.1\r\n".len();
+    let headers: usize = headers
+        .iter()
+        .map(|(name, value)| name.len() + 2 + value.len() + 2)
+        .sum();
+    (line + headers + 2 + body) as u64
+}
+
+/// Bytes of a response head as written in HTTP/1.1: status line, headers and blank line
+fn response_head_size(response: &Response) -> u64 {
+    let status = response.status();
+    let line = "HTTP/1.1 ".len()
+        + status.as_str().len()
+        + 1
+        + status.canonical_reason().unwrap_or_default().len()
+        + 2;
+    let headers: usize = response
+        .headers()
+        .iter()
+        .map(|(name, value)| name.as_str().len() + 2 + value.len() + 2)
+        .sum();
+    (line + headers + 2) as u64
+}
+
+/// How an iteration picks the requests it sends
+enum Steps {
+    /// Every request in order: the configured request, or the flow of a sequential scenario
+    All,
+    /// The request of a weighted mix furthest behind its share of the slot time when a slot frees
+    /// up
+    Fair(FairQueue),
+    /// A request at random in proportion to the weights, from one cycle of the schedule
+    Random(Vec<usize>),
+}
+
+impl Steps {
+    /// Requests of the next iteration, with the slot of a fair mix charged to the request once
+    /// the iteration drops it
+    fn pick<'a>(
+        &'a self,
+        targets: &'a [RequestTarget],
+    ) -> (&'a [RequestTarget], Option<FairSlot<'a>>) {
+        match self {
+            Steps::All => (targets, None),
+            Steps::Fair(queue) => {
+                let slot = queue.acquire();
+                (std::slice::from_ref(&targets[slot.index()]), Some(slot))
+            }
+            Steps::Random(schedule) => {
+                let index = (random_fraction() * schedule.len() as f64) as usize;
+                let target = &targets[schedule[index.min(schedule.len() - 1)]];
+                (std::slice::from_ref(target), None)
+            }
+        }
+    }
+}
+
+/// Resolve the requests of a run and how iterations pick them
+///
+/// Without a scenario the run sends the configured request every time. A sequential scenario sends
+/// all its requests in every iteration. Fails when a form file cannot be read or an extractor of
+/// the scenario is invalid.
+fn request_targets(
+    config: &LoadTestConfig,
+    pacer: Option<&Arc<Pacer>>,
+    trace: Option<&Arc<TraceSampler>>,
+) -> Result<(Vec<RequestTarget>, Steps)> {
+    let form = config
+        .form
+        .as_ref()
+        .map(FormTemplate::load)
+        .transpose()?
+        .map(Arc::new);
+    let Some(scenario) = &config.scenario else {
+        let mut headers = config.headers.clone();
+        let body = match &config.graphql {
+            Some(graphql) => {
+                if !headers
+                    .iter()
+                    .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                {
+                    headers.push(("Content-Type".to_string(), "application/json".to_string()));
+                }
+                Some(graphql.body())
+            }
+            None => config.body.clone(),
+        };
+        let target = RequestTarget {
+            method: config.method.clone().into(),
+            url: config.url.clone(),
+            headers,
+            body,
+            form,
+            labels: config.request_labels(),
+            extract: Vec::new(),
+            response_body: config.response_body,
+            max_body_size: config.advanced.max_body_size(),
+            random_params: config.random_params.clone(),
+            graphql: config.graphql.is_some(),
+            success_codes: config.success_codes.clone().map(Arc::new),
+            pacer: pacer.cloned(),
+            trace: trace.cloned(),
+            placeholders: Vec::new(),
+        }
+        .with_placeholders();
+        return Ok((vec![target], Steps::All));
+    };
+
+    let targets = scenario
+        .requests
+        .iter()
+        .map(|spec| {
+            let mut labels = config.request_labels();
+            labels.extend(spec.labels.clone());
+            labels.insert(ENDPOINT_LABEL.to_string(), spec.name.clone());
+            let extract = spec
+                .extract
+                .iter()
+                .map(|extractor| {
+                    extractor
+                        .compile()
+                        .map_err(|e| Error::Config(e.to_string()))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(RequestTarget {
+                method: spec.method.clone().into(),
+                url: spec.url.clone(),
+                headers: config
+                    .headers
+                    .iter()
+                    .cloned()
+                    .chain(spec.headers.clone())
+                    .collect(),
+                body: spec.body.clone().or_else(|| config.body.clone()),
+                form: form.clone(),
+                labels,
+                extract,
+                response_body: config.response_body,
+                max_body_size: config.advanced.max_body_size(),
+                random_params: config.random_params.clone(),
+                graphql: false,
+                success_codes: spec
+                    .success_codes
+                    .clone()
+                    .or_else(|| config.success_codes.clone())
+                    .map(Arc::new),
+                pacer: pacer.cloned(),
+                trace: trace.cloned(),
+                placeholders: Vec::new(),
+            }
+            .with_placeholders())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let steps = match scenario.mode {
+        ScenarioMode::Weighted => Steps::Fair(scenario.fair_queue()),
+        ScenarioMode::Random => Steps::Random(scenario.schedule()),
+        ScenarioMode::Sequential => Steps::All,
+    };
+    Ok((targets, steps))
+}
+
+/// Take requests that will not be sent out of the expected total
+fn skip_progress(progress: Option<&Progress>, progress_bar: Option<&ProgressBar>, requests: u64) {
+    if let Some(progress) = progress {
+        progress.skip(requests);
+    }
+    if let Some(pb) = progress_bar {
+        pb.set_length(pb.length().unwrap_or_default().saturating_sub(requests));
+    }
+}
+
 /// Load test engine that executes HTTP requests concurrently
 pub struct LoadTestEngine<C: HttpClient> {
     client: Arc<C>,
+    /// Polls the URLs of `wait` stages
+    probe: reqwest::Client,
 }
 
 impl<C: HttpClient> LoadTestEngine<C> {
@@ -21,17 +916,19 @@ impl<C: HttpClient> LoadTestEngine<C> {
     pub fn new(client: C) -> Result<Self> {
         Ok(Self {
             client: Arc::new(client),
+            probe: reqwest::Client::new(),
         })
     }
 
+    /// Poll the URLs of `wait` stages with `probe` instead of a client with reqwest's defaults,
+    /// e.g. one with a timeout that only connects to the hosts a service may load
+    pub fn with_probe_client(mut self, probe: reqwest::Client) -> Self {
+        self.probe = probe;
+        self
+    }
+
     /// Run the load test with the given parameters
-    #[instrument(skip(self, headers, body, progress_bar), fields(
-        method = %method,
-        url = %url,
-        num_requests = %num_requests,
-        concurrency = %concurrency,
-        timeout_ms = %timeout.as_millis(),
-    ))]
+    #[allow(clippy::too_many_arguments)]
     pub async fn run(
         &self,
         method: Method,
@@ -43,65 +940,489 @@ impl<C: HttpClient> LoadTestEngine<C> {
         body: Option<String>,
         progress_bar: Option<ProgressBar>,
     ) -> Result<Arc<Mutex<TestMetrics>>> {
+        let config = LoadTestConfig {
+            url,
+            method: method
+                .as_str()
+                .parse()
+                .map_err(|e: ConfigError| Error::Config(e.to_string()))?,
+            requests: num_requests as usize,
+            concurrency: concurrency as usize,
+            timeout,
+            headers,
+            body,
+            form: None,
+            graphql: None,
+            labels: Default::default(),
+            scenario: None,
+            feeder: None,
+            on_missing_value: MissingValue::Send,
+            random_params: Vec::new(),
+            faults: Vec::new(),
+            rate: None,
+            rate_burst: None,
+            max_concurrency: None,
+            max_rate: None,
+            load_model: LoadModel::Closed,
+            correct_coordinated_omission: false,
+            stages: Vec::new(),
+            record_requests: false,
+            trace: None,
+            deadline: None,
+            cooldown: None,
+            percentiles: Vec::new(),
+            region: None,
+            time_series_interval: None,
+            slos: Vec::new(),
+            thresholds: Vec::new(),
+            abort_on: Vec::new(),
+            honor_retry_after: false,
+            header_pacing: None,
+            retry: None,
+            think_time: None,
+            success_codes: None,
+            response_body: Default::default(),
+            http_version: Default::default(),
+            tls: Default::default(),
+            dns: None,
+            advanced: Default::default(),
+        };
+
+        self.execute(
+            &config,
+            RunOptions {
+                progress_bar,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Run the load test described by the given configuration
+    #[instrument(skip_all, fields(
+        method = ?config.method,
+        url = %config.url,
+        num_requests = %config.requests,
+        concurrency = %config.concurrency,
+        timeout_ms = %config.timeout.as_millis(),
+    ))]
+    pub async fn execute(
+        &self,
+        config: &LoadTestConfig,
+        options: RunOptions,
+    ) -> Result<Arc<Mutex<TestMetrics>>> {
+        let method: Method = config.method.clone().into();
+        let url = config.url.clone();
+        let num_requests = config.requests as u64;
+        let timeout = config.timeout;
+
         info!(
             "Starting load test:\n\
              Target: {} {}\n\
              Requests: {}\n\
              Concurrency: {}\n\
+             Rate: {}\n\
              Timeout: {:?}",
-            method, url, num_requests, concurrency, timeout
+            method,
+            url,
+            num_requests,
+            config.concurrency,
+            config
+                .rate
+                .map_or("unlimited".to_string(), |rate| format!("{} req/s", rate)),
+            timeout
         );
 
-        let start_time = std::time::Instant::now();
-        let metrics = Arc::new(Mutex::new(TestMetrics::new()?));
+        let pacer = config.header_pacing.clone().map(Pacer::new).map(Arc::new);
+        let trace = config.trace.clone().map(TraceSampler::new).map(Arc::new);
+        let (targets, steps) = request_targets(config, pacer.as_ref(), trace.as_ref())?;
+        let (targets, steps) = (&targets, &steps);
+        let requests_per_iteration = match steps {
+            Steps::All => targets.len() as u64,
+            Steps::Fair(_) | Steps::Random(_) => 1,
+        };
+
+        if let Some(progress) = &options.progress {
+            progress.start(num_requests * requests_per_iteration);
+        }
+        if let Some(pb) = &options.progress_bar {
+            pb.set_length(num_requests * requests_per_iteration);
+        }
+
+        // The cooldown compares the probes after the load with their latency before it
+        let prober = config.cooldown.as_ref().map(|cooldown| Prober {
+            client: &*self.client,
+            url: cooldown
+                .probe_url
+                .clone()
+                .unwrap_or_else(|| config.url.clone()),
+            headers: config.headers.clone(),
+            timeout: config.timeout,
+        });
+        let baseline = match &prober {
+            Some(prober) => prober.baseline().await,
+            None => None,
+        };
+
+        let start_time = Instant::now();
+        let shape = LoadShape::new(config, options.control.clone(), pacer);
+        let shape = &shape;
+        let gate = ConcurrencyGate::new();
+        let gate = &gate;
+        // Failures the client did not log itself, e.g. timeouts; repeats are logged when it drops
+        let errors = ErrorLog::new(ERROR_LOG_WINDOW);
+        let errors = &errors;
+        let mut metrics = TestMetrics::new()?;
+        metrics.mark_started(options.clock_offset);
+        if config.record_requests {
+            metrics.record_individual_requests();
+        }
+        let memory = config
+            .advanced
+            .memory_ceiling
+            .map(|ceiling| MemoryGuard::new(ceiling, config.advanced.spill_dir.clone()));
+        if let Some(memory) = &memory {
+            metrics.watch_memory(memory.ceiling());
+        }
+        metrics.set_percentiles(config.percentiles.clone());
+        metrics.set_region(config.region.clone());
+        if let Some(success_codes) = &config.success_codes {
+            metrics.set_success_codes(success_codes.clone());
+        }
+        if let Some(scenario) = &config.scenario {
+            metrics.set_mix(scenario.configured_mix());
+        }
+        if config.correct_coordinated_omission {
+            // Each virtual user of a closed model is due to send once every `concurrency` slots;
+            // requests of the open models arrive on schedule, so only their waits are missing
+            let expected_interval = match config.load_model {
+                LoadModel::Closed => config
+                    .rate
+                    .map(|rate| interval_at(rate, config.concurrency as f64)),
+                LoadModel::Open | LoadModel::Poisson => None,
+            };
+            metrics.correct_coordinated_omission(expected_interval);
+        }
+        if let Some(interval) = config.time_series_interval {
+            metrics.record_time_series(interval);
+        }
+        let metrics = Arc::new(Mutex::new(metrics));
+        if let Some(recovery) = &options.recovery {
+            recovery.watch(&metrics, start_time);
+        }
         let metrics_for_stream = Arc::clone(&metrics);
+        let progress_bar = options.progress_bar.clone();
+        let progress = options.progress.clone();
 
-        stream::iter((0..num_requests).map(move |i| {
-            let client = Arc::clone(&self.client);
-            let metrics = Arc::clone(&metrics_for_stream);
-            let url = url.clone();
-            let method = method.clone();
-            let headers = headers.clone();
-            let body = body.clone();
-            let progress_bar = progress_bar.clone();
-
-            let span = tracing::info_span!(
-                "request",
-                request_id = %i,
-                method = %method,
-                url = %url
-            );
+        // Room for the highest concurrency the stages or a live override can set
+        let max_concurrency = config
+            .stages
+            .iter()
+            .map(Stage::max_concurrency)
+            .chain(options.control.iter().map(|c| c.limits().max_concurrency))
+            .fold(config.concurrency, usize::max);
+        let stream_buffer = config.advanced.stream_buffer.unwrap_or(max_concurrency);
+        let honor_retry_after = config.honor_retry_after;
+        let retry = config.retry.as_ref();
+        let think_time = config.think_time;
+        let open = config.load_model != LoadModel::Closed;
+        let vus = VirtualUsers::default();
+        let vus = &vus;
+        let breaker = (!config.abort_on.is_empty())
+            .then(|| std::sync::Mutex::new(CircuitBreaker::new(&config.abort_on, start_time)));
+        let breaker = &breaker;
+        // Why the run was ended for missing data, the first reason found
+        let missing = std::sync::OnceLock::new();
+        let missing = &missing;
 
-            async move {
-                let _enter = span.enter();
-                let request_metrics = RequestMetrics::new();
-                let result = client
-                    .send_request(method, url, timeout, headers, body)
-                    .await;
-
-                let mut metrics = metrics.lock().await;
-                match result {
-                    Ok(response) => {
-                        metrics.record_request(request_metrics.complete(response.status()));
-                    }
-                    Err(error) => {
-                        metrics.record_request(request_metrics.record_error(error));
+        let requests = stream::iter(0..num_requests)
+            .take_while(|_| future::ready(!shape.is_stopped()))
+            .map(move |i| {
+                let client = Arc::clone(&self.client);
+                let metrics = Arc::clone(&metrics_for_stream);
+                let progress_bar = progress_bar.clone();
+                let progress = progress.clone();
+
+                let span = tracing::info_span!(
+                    "request",
+                    request_id = %i,
+                    vu = tracing::field::Empty,
+                    method = tracing::field::Empty,
+                    url = tracing::field::Empty
+                );
+
+                async move {
+                    let _enter = span.enter();
+                    let row = match &config.feeder {
+                        Some(feeder) => match feeder.row(i) {
+                            Some(row) => Some(row),
+                            None => {
+                                let reason = "Feeder ran out of unique rows";
+                                if feeder.on_exhausted == FeederExhaustion::Fail {
+                                    let _ = missing.set(reason.to_string());
+                                }
+                                shape.stop(reason);
+                                return;
+                            }
+                        },
+                        None => None,
+                    };
+                    // In an open model requests arrive on schedule whatever is in flight
+                    let mut arrival = match open {
+                        true => Some(shape.pace().await),
+                        false => None,
+                    };
+                    let Some(_permit) = gate
+                        .acquire(|| shape.concurrency_at(start_time.elapsed()))
+                        .await
+                    else {
+                        return;
+                    };
+                    // A virtual user runs the iteration with what it kept from its previous ones
+                    let mut vu = vus.take();
+                    span.record("vu", vu.id());
+                    vu.begin_iteration(row);
+                    // An iteration sends one request of a mix, or every request of a flow; the
+                    // request of a mix is only picked now that the iteration has its slot, which
+                    // is charged to it until the iteration ends
+                    let (steps, _slot) = steps.pick(targets);
+                    span.record("method", tracing::field::display(&steps[0].method));
+                    span.record("url", tracing::field::display(&steps[0].url));
+                    for (step, target) in steps.iter().enumerate() {
+                        if let Some(name) = target.missing_value(&vu) {
+                            let reason = format!("No value for {{{{{}}}}}", name);
+                            match config.on_missing_value {
+                                MissingValue::Abort => {
+                                    shape.stop(&reason);
+                                    let _ = missing.set(reason);
+                                    return;
+                                }
+                                MissingValue::Skip => {
+                                    // This request and the rest of its flow are not sent
+                                    let skipped = (steps.len() - step) as u64;
+                                    metrics.lock().await.record_skipped(reason, skipped);
+                                    skip_progress(
+                                        progress.as_ref(),
+                                        progress_bar.as_ref(),
+                                        skipped,
+                                    );
+                                    break;
+                                }
+                                MissingValue::Send => {}
+                            }
+                        }
+                        let mut attempt = 1;
+                        // Only the last attempt is recorded, with the number of attempts it took
+                        let request_metrics = loop {
+                            let due = match arrival.take() {
+                                Some(due) => due,
+                                None => shape.pace().await,
+                            };
+                            if shape.is_stopped() {
+                                return;
+                            }
+                            let request_metrics = target.send(&*client, timeout, &mut vu).await;
+                            // Late starts are the generator's doing, kept out of the response time
+                            // unless requests arrive on their own schedule
+                            let queued =
+                                request_metrics.start_time().saturating_duration_since(due);
+                            let request_metrics = request_metrics
+                                .with_phase(Phase::Queue, Some(queued))
+                                .with_attempts(attempt);
+                            let request_metrics = match open {
+                                true => request_metrics.with_intended_start(due),
+                                false => request_metrics.with_due(due),
+                            };
+                            let Some(delay) = retry
+                                .and_then(|retry| retry.retry_delay(attempt, &request_metrics))
+                            else {
+                                break request_metrics;
+                            };
+                            // A server asking for a longer pause gets it, as for the final attempt
+                            let delay = match request_metrics.retry_after() {
+                                Some(retry_after) if honor_retry_after => delay.max(retry_after),
+                                _ => delay,
+                            };
+                            tokio::time::sleep(delay).await;
+                            attempt += 1;
+                        };
+                        if let Some((kind, message)) = request_metrics.failure() {
+                            errors.warn(format!("{}: {}", kind, message));
+                        }
+                        let success = request_metrics.is_success();
+                        let backoff = request_metrics.retry_after().filter(|_| honor_retry_after);
+                        if let Some(rule) = breaker.as_ref().and_then(|breaker| {
+                            breaker
+                                .lock()
+                                .unwrap_or_else(|e| e.into_inner())
+                                .record(&request_metrics)
+                        }) {
+                            shape.stop(&format!("Abort rule met: {}", rule));
+                        }
+                        metrics.lock().await.record_request(request_metrics);
+
+                        if let Some(progress) = &progress {
+                            progress.record();
+                        }
+
+                        if let Some(pb) = &progress_bar {
+                            pb.inc(1);
+                        }
+
+                        // The virtual user keeps its slot while it backs off, as the server asked
+                        if let Some(backoff) = backoff {
+                            metrics.lock().await.record_backoff(backoff);
+                            tokio::time::sleep(backoff).await;
+                        }
+
+                        // And while it thinks before its next request
+                        if let Some(think_time) = &think_time {
+                            tokio::time::sleep(think_time.sample()).await;
+                        }
+
+                        // The rest of a flow depends on this request, skip it
+                        let remaining = (steps.len() - step - 1) as u64;
+                        if !success && remaining > 0 {
+                            skip_progress(progress.as_ref(), progress_bar.as_ref(), remaining);
+                            break;
+                        }
                     }
+                    metrics.lock().await.record_iteration();
+                    vus.give_back(vu);
                 }
+            })
+            .buffer_unordered(stream_buffer)
+            .collect::<Vec<_>>();
 
-                if let Some(pb) = progress_bar {
-                    pb.inc(1);
+        let run = async {
+            if config.stages.is_empty() {
+                requests.await;
+            } else {
+                tokio::pin!(requests);
+                // Poll the stages first so the first stage applies before any request starts
+                tokio::select! {
+                    biased;
+                    _ = shape.run_stages(
+                        &config.stages,
+                        &metrics,
+                        options.trigger.as_ref(),
+                        &self.probe,
+                    ) => {
+                        requests.await;
+                    }
+                    _ = &mut requests => {}
                 }
             }
-        }))
-        .buffer_unordered(concurrency as usize)
-        .collect::<Vec<_>>()
-        .await;
+        };
+
+        let publish = async {
+            match &options.live {
+                Some(live) => live.publish(&metrics, start_time).await,
+                None => future::pending().await,
+            }
+        };
+        let cancelled = async {
+            match &options.cancel {
+                Some(cancel) => cancel.cancelled().await,
+                None => future::pending().await,
+            }
+        };
+        let guard_memory = async {
+            match &memory {
+                Some(memory) => memory.watch(&metrics, start_time).await,
+                None => future::pending().await,
+            }
+        };
+        let deadline = async {
+            match config.deadline {
+                Some(deadline) => tokio::time::sleep(deadline).await,
+                None => future::pending().await,
+            }
+        };
+
+        // Requests still in flight when the run is stopped are dropped and not recorded
+        let stopped = tokio::select! {
+            _ = run => None,
+            _ = publish => None,
+            _ = guard_memory => None,
+            _ = cancelled => {
+                warn!("Load test cancelled after {:?}", start_time.elapsed());
+                Some(Stop::Cancelled)
+            }
+            _ = deadline => {
+                warn!("Load test deadline of {:?} exceeded", config.deadline.unwrap_or_default());
+                Some(Stop::Deadline)
+            }
+        };
 
         let duration = start_time.elapsed();
+        if let (Some(cooldown), Some(prober), None) = (&config.cooldown, &prober, &stopped) {
+            info!(
+                "Load stopped, probing the target for {:?} to observe its recovery",
+                cooldown.duration
+            );
+            let mut probes = Vec::new();
+            let cancelled = async {
+                match &options.cancel {
+                    Some(cancel) => cancel.cancelled().await,
+                    None => future::pending().await,
+                }
+            };
+            tokio::select! {
+                _ = prober.observe(cooldown.duration, &mut probes) => {}
+                _ = cancelled => warn!("Cooldown cancelled after {} probes", probes.len()),
+            }
+            metrics
+                .lock()
+                .await
+                .set_cooldown(CooldownMetrics { baseline, probes });
+        }
         {
             let mut metrics = metrics.lock().await;
+            match stopped {
+                Some(Stop::Cancelled) => metrics.mark_cancelled(),
+                Some(Stop::Deadline) => metrics.mark_deadline_exceeded(),
+                None => {}
+            }
+            if let Some(rule) = breaker
+                .as_ref()
+                .and_then(|breaker| breaker.lock().unwrap_or_else(|e| e.into_inner()).tripped())
+            {
+                metrics.mark_aborted(rule.to_string());
+            } else if let Some(reason) = missing.get() {
+                metrics.mark_aborted(reason.clone());
+            }
+            if let Some(control) = &options.control {
+                for (at, overrides) in control.changes() {
+                    metrics.record_event(RunEvent {
+                        offset: at.saturating_duration_since(start_time),
+                        kind: "load_overridden".to_string(),
+                        detail: overrides.to_string(),
+                    });
+                }
+            }
+            // Rotations of an earlier run of the same client are not this run's
+            for rotation in self.client.cert_rotations() {
+                if let Some(offset) = rotation.at.checked_duration_since(start_time) {
+                    metrics.record_event(RunEvent {
+                        offset,
+                        kind: "client_cert_rotated".to_string(),
+                        detail: rotation.cert.display().to_string(),
+                    });
+                }
+            }
+            metrics.set_virtual_users(vus.created() as u64);
             metrics.finalize(duration).await?;
+            if let Some(recovery) = &options.recovery {
+                recovery.finish();
+            }
+            if let Some(live) = &options.live {
+                live.finish(&metrics);
+            }
+        }
+
+        if let Some(progress) = &options.progress {
+            progress.finish();
         }
 
         Ok(metrics)
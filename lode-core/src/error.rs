@@ -13,6 +13,9 @@ pub enum Error {
     #[error("Report error: {0}")]
     Report(String),
 
+    #[error("Run error: {0}")]
+    Run(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
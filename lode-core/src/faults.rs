@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::config::{parse_duration, ConfigError};
+
+/// What the generator does while a fault is active
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum FaultAction {
+    /// Stop sending new requests
+    Pause,
+    /// Reduce concurrency to the given percentage of the configured value
+    Throttle { load_percent: u8 },
+}
+
+/// A window of the run during which the generator deliberately changes its load
+///
+/// Faults are written as `<action>@<start>+<duration>`, e.g. `pause@60s+30s` pauses all traffic
+/// for 30 seconds one minute into the run and `throttle:10@2m+1m` drops to 10% of the configured
+/// concurrency for a minute.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct GeneratorFault {
+    /// Offset from the start of the run at which the fault begins
+    pub start: Duration,
+
+    /// How long the fault lasts
+    pub duration: Duration,
+
+    /// Action taken while the fault is active
+    #[serde(flatten)]
+    pub action: FaultAction,
+}
+
+impl GeneratorFault {
+    /// Pause all traffic for `duration` starting `start` into the run
+    pub fn pause(start: Duration, duration: Duration) -> Self {
+        Self {
+            start,
+            duration,
+            action: FaultAction::Pause,
+        }
+    }
+
+    /// Drop to `load_percent`% of the configured concurrency for `duration` starting `start` into the run
+    pub fn throttle(start: Duration, duration: Duration, load_percent: u8) -> Self {
+        Self {
+            start,
+            duration,
+            action: FaultAction::Throttle { load_percent },
+        }
+    }
+
+    /// Offset from the start of the run at which the fault ends
+    pub fn end(&self) -> Duration {
+        self.start + self.duration
+    }
+
+    /// Whether the fault is active at the given offset into the run
+    pub fn is_active_at(&self, elapsed: Duration) -> bool {
+        elapsed >= self.start && elapsed < self.end()
+    }
+
+    /// Concurrency allowed while the fault is active, given the configured concurrency
+    pub fn concurrency_limit(&self, concurrency: usize) -> usize {
+        match self.action {
+            FaultAction::Pause => 0,
+            FaultAction::Throttle { load_percent } => {
+                (concurrency * load_percent as usize).div_ceil(100).max(1)
+            }
+        }
+    }
+}
+
+/// Find the fault active at the given offset into the run, if any
+pub fn active_fault(faults: &[GeneratorFault], elapsed: Duration) -> Option<&GeneratorFault> {
+    faults.iter().find(|fault| fault.is_active_at(elapsed))
+}
+
+impl FromStr for GeneratorFault {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| ConfigError::InvalidFault(format!("{}: {}", s, reason));
+
+        let (action, window) = s
+            .split_once('@')
+            .ok_or_else(|| invalid("expected <action>@<start>+<duration>"))?;
+        let (start, duration) = window
+            .split_once('+')
+            .ok_or_else(|| invalid("expected <start>+<duration> after '@'"))?;
+
+        let action = match action.trim().split_once(':') {
+            None if action.trim().eq_ignore_ascii_case("pause") => FaultAction::Pause,
+            Some((name, percent)) if name.trim().eq_ignore_ascii_case("throttle") => {
+                let load_percent: u8 = percent
+                    .trim()
+                    .trim_end_matches('%')
+                    .parse()
+                    .map_err(|_| invalid("load percentage must be a number"))?;
+                if load_percent == 0 || load_percent > 100 {
+                    return Err(invalid("load percentage must be between 1 and 100"));
+                }
+                FaultAction::Throttle { load_percent }
+            }
+            _ => return Err(invalid("action must be `pause` or `throttle:<percent>`")),
+        };
+
+        let duration = parse_duration(duration)?;
+        if duration.is_zero() {
+            return Err(invalid("duration must be greater than 0"));
+        }
+
+        Ok(Self {
+            start: parse_duration(start)?,
+            duration,
+            action,
+        })
+    }
+}
+
+impl fmt::Display for GeneratorFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.action {
+            FaultAction::Pause => write!(f, "pause")?,
+            FaultAction::Throttle { load_percent } => write!(f, "throttle:{}", load_percent)?,
+        }
+        write!(
+            f,
+            "@{}s+{}s",
+            self.start.as_secs_f64(),
+            self.duration.as_secs_f64()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_faults() {
+        assert_eq!(
+            "pause@60s+30s".parse::<GeneratorFault>().unwrap(),
+            GeneratorFault::pause(Duration::from_secs(60), Duration::from_secs(30))
+        );
+        assert_eq!(
+            "throttle:10%@2m+1m".parse::<GeneratorFault>().unwrap(),
+            GeneratorFault::throttle(Duration::from_secs(120), Duration::from_secs(60), 10)
+        );
+
+        for invalid in [
+            "pause",
+            "pause@60s",
+            "stop@1s+1s",
+            "throttle@1s+1s",
+            "throttle:0@1s+1s",
+            "throttle:150@1s+1s",
+            "pause@1s+0s",
+            "pause@soon+1s",
+        ] {
+            assert!(
+                matches!(
+                    invalid.parse::<GeneratorFault>(),
+                    Err(ConfigError::InvalidFault(_)) | Err(ConfigError::InvalidDuration(_))
+                ),
+                "{} should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_fault_display_round_trip() {
+        let fault =
+            GeneratorFault::throttle(Duration::from_secs(5), Duration::from_millis(1500), 25);
+        assert_eq!(fault.to_string(), "throttle:25@5s+1.5s");
+        assert_eq!(fault.to_string().parse::<GeneratorFault>().unwrap(), fault);
+    }
+
+    #[test]
+    fn test_active_fault() {
+        let faults = vec![
+            GeneratorFault::pause(Duration::from_secs(10), Duration::from_secs(5)),
+            GeneratorFault::throttle(Duration::from_secs(20), Duration::from_secs(5), 10),
+        ];
+
+        assert!(active_fault(&faults, Duration::from_secs(9)).is_none());
+        assert_eq!(
+            active_fault(&faults, Duration::from_secs(10))
+                .unwrap()
+                .action,
+            FaultAction::Pause
+        );
+        assert!(active_fault(&faults, Duration::from_secs(15)).is_none());
+        assert_eq!(
+            active_fault(&faults, Duration::from_secs(24))
+                .unwrap()
+                .action,
+            FaultAction::Throttle { load_percent: 10 }
+        );
+    }
+
+    #[test]
+    fn test_concurrency_limit() {
+        let throttle = GeneratorFault::throttle(Duration::ZERO, Duration::from_secs(1), 10);
+        assert_eq!(throttle.concurrency_limit(100), 10);
+        assert_eq!(throttle.concurrency_limit(15), 2);
+        assert_eq!(throttle.concurrency_limit(1), 1);
+
+        let pause = GeneratorFault::pause(Duration::ZERO, Duration::from_secs(1));
+        assert_eq!(pause.concurrency_limit(100), 0);
+    }
+
+    #[test]
+    fn test_fault_serialization() {
+        let fault = GeneratorFault::throttle(Duration::from_secs(1), Duration::from_secs(2), 10);
+        let json = serde_json::to_value(fault).unwrap();
+        assert_eq!(json["action"], "throttle");
+        assert_eq!(json["load_percent"], 10);
+        let deserialized: GeneratorFault = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, fault);
+    }
+}
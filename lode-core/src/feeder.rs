@@ -0,0 +1,342 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::ConfigError;
+use crate::retry::random_fraction;
+use crate::scenario::extract::Variables;
+
+/// Order in which iterations of a run take the rows of a feeder
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FeederStrategy {
+    /// Rows in file order, starting over after the last one
+    #[default]
+    Circular,
+    /// A random row for every iteration
+    Random,
+    /// Every row once in file order; the run ends when the rows run out
+    Unique,
+}
+
+impl FromStr for FeederStrategy {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "circular" => Ok(FeederStrategy::Circular),
+            "random" => Ok(FeederStrategy::Random),
+            "unique" => Ok(FeederStrategy::Unique),
+            _ => Err(ConfigError::InvalidFeeder(format!(
+                "Unknown strategy {} (expected circular, random or unique)",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for FeederStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeederStrategy::Circular => write!(f, "circular"),
+            FeederStrategy::Random => write!(f, "random"),
+            FeederStrategy::Unique => write!(f, "unique"),
+        }
+    }
+}
+
+/// What a run does once a feeder taking every row once has none left
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FeederExhaustion {
+    /// End the run with the requests sent so far, as completed
+    #[default]
+    Stop,
+    /// End the run as aborted, the data was expected to last
+    Fail,
+    /// Start over from the first row
+    Recycle,
+}
+
+impl FromStr for FeederExhaustion {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "stop" => Ok(FeederExhaustion::Stop),
+            "fail" => Ok(FeederExhaustion::Fail),
+            "recycle" => Ok(FeederExhaustion::Recycle),
+            _ => Err(ConfigError::InvalidFeeder(format!(
+                "Unknown policy {} when rows run out (expected stop, fail or recycle)",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for FeederExhaustion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeederExhaustion::Stop => write!(f, "stop"),
+            FeederExhaustion::Fail => write!(f, "fail"),
+            FeederExhaustion::Recycle => write!(f, "recycle"),
+        }
+    }
+}
+
+/// Rows of test data, one per iteration, filling the `{{column}}` placeholders of the URL, header
+/// values and body
+///
+/// Rows are typically loaded from a CSV file with a header line or from a JSONL file of objects,
+/// e.g. one user to log in per iteration. Values extracted from responses take precedence over
+/// columns of the same name.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Feeder {
+    #[serde(default)]
+    pub strategy: FeederStrategy,
+
+    /// What the run does once the unique rows have run out
+    #[serde(default)]
+    pub on_exhausted: FeederExhaustion,
+
+    pub rows: Vec<Variables>,
+}
+
+impl Feeder {
+    /// Create a feeder taking the rows in file order, starting over after the last one
+    pub fn new(rows: Vec<Variables>) -> Self {
+        Self {
+            strategy: FeederStrategy::Circular,
+            on_exhausted: FeederExhaustion::Stop,
+            rows,
+        }
+    }
+
+    /// Take the rows by this strategy
+    pub fn with_strategy(mut self, strategy: FeederStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Do this once the unique rows have run out
+    pub fn with_on_exhausted(mut self, on_exhausted: FeederExhaustion) -> Self {
+        self.on_exhausted = on_exhausted;
+        self
+    }
+
+    /// Parse CSV with a header line naming the columns
+    ///
+    /// Fields may be quoted with `"`, doubling quotes inside them, to hold commas or line breaks.
+    /// Empty lines are skipped.
+    pub fn from_csv(content: &str) -> Result<Self, ConfigError> {
+        let mut records = parse_csv(content)?.into_iter();
+        let header = records
+            .next()
+            .ok_or_else(|| ConfigError::InvalidFeeder("The CSV has no header line".to_string()))?;
+        let rows = records
+            .enumerate()
+            .map(|(index, record)| {
+                if record.len() != header.len() {
+                    return Err(ConfigError::InvalidFeeder(format!(
+                        "Row {} of the CSV has {} fields, the header {}",
+                        index + 1,
+                        record.len(),
+                        header.len()
+                    )));
+                }
+                Ok(header.iter().cloned().zip(record).collect())
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(rows))
+    }
+
+    /// Parse JSON Lines, one object per line
+    ///
+    /// String values are used as they are, other values as JSON. Empty lines are skipped.
+    pub fn from_jsonl(content: &str) -> Result<Self, ConfigError> {
+        let rows = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| match serde_json::from_str(line) {
+                Ok(Value::Object(object)) => Ok(object
+                    .into_iter()
+                    .map(|(key, value)| match value {
+                        Value::String(value) => (key, value),
+                        value => (key, value.to_string()),
+                    })
+                    .collect()),
+                Ok(_) => Err(ConfigError::InvalidFeeder(format!(
+                    "Line {} of the JSONL is not an object",
+                    index + 1
+                ))),
+                Err(e) => Err(ConfigError::InvalidFeeder(format!(
+                    "Line {} of the JSONL: {}",
+                    index + 1,
+                    e
+                ))),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(rows))
+    }
+
+    /// Check that the feeder has rows to feed
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.rows.is_empty() {
+            return Err(ConfigError::InvalidFeeder(
+                "A feeder needs at least one row".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Columns every row has values for
+    pub fn columns(&self) -> BTreeSet<&str> {
+        let mut rows = self.rows.iter();
+        let Some(first) = rows.next() else {
+            return BTreeSet::new();
+        };
+        let mut columns: BTreeSet<&str> = first.keys().map(String::as_str).collect();
+        for row in rows {
+            columns.retain(|column| row.contains_key(*column));
+        }
+        columns
+    }
+
+    /// Row of the `iteration`-th iteration of the run, `None` once unique rows have run out
+    ///
+    /// Unique rows recycled once they run out start over from the first row.
+    pub fn row(&self, iteration: u64) -> Option<&Variables> {
+        if self.rows.is_empty() {
+            return None;
+        }
+        let len = self.rows.len() as u64;
+        let index = match self.strategy {
+            FeederStrategy::Circular => iteration % len,
+            FeederStrategy::Random => ((random_fraction() * len as f64) as u64).min(len - 1),
+            FeederStrategy::Unique if iteration < len => iteration,
+            FeederStrategy::Unique if self.on_exhausted == FeederExhaustion::Recycle => {
+                iteration % len
+            }
+            FeederStrategy::Unique => return None,
+        };
+        self.rows.get(index as usize)
+    }
+}
+
+/// Split CSV into records of fields
+fn parse_csv(content: &str) -> Result<Vec<Vec<String>>, ConfigError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    // Whether the current record has anything, to skip empty lines
+    let mut started = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => {
+                quoted = true;
+                started = true;
+            }
+            _ if quoted => field.push(c),
+            ',' => {
+                record.push(std::mem::take(&mut field));
+                started = true;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                if started || !field.is_empty() {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                started = false;
+            }
+            _ => {
+                field.push(c);
+                started = true;
+            }
+        }
+    }
+    if quoted {
+        return Err(ConfigError::InvalidFeeder(
+            "The CSV ends inside a quoted field".to_string(),
+        ));
+    }
+    if started || !field.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feeder_from_csv() {
+        let feeder =
+            Feeder::from_csv("username,password\r\nalice,\"se,cret\"\n\nbob,\"say \"\"hi\"\"\"\n")
+                .unwrap();
+        assert_eq!(feeder.rows.len(), 2);
+        assert_eq!(feeder.rows[0]["password"], "se,cret");
+        assert_eq!(feeder.rows[1]["username"], "bob");
+        assert_eq!(feeder.rows[1]["password"], "say \"hi\"");
+        assert_eq!(feeder.columns(), BTreeSet::from(["password", "username"]));
+
+        assert!(Feeder::from_csv("a,b\n1\n").is_err());
+        assert!(Feeder::from_csv("a\n\"1\n").is_err());
+    }
+
+    #[test]
+    fn test_feeder_from_jsonl() {
+        let feeder =
+            Feeder::from_jsonl("{\"id\": 7, \"name\": \"alice\"}\n\n{\"id\": 8}\n").unwrap();
+        assert_eq!(feeder.rows[0]["id"], "7");
+        assert_eq!(feeder.rows[0]["name"], "alice");
+        assert_eq!(feeder.columns(), BTreeSet::from(["id"]));
+
+        assert!(Feeder::from_jsonl("[1, 2]").is_err());
+    }
+
+    #[test]
+    fn test_feeder_strategies() {
+        let rows: Vec<Variables> = (0..3)
+            .map(|i| Variables::from([("id".to_string(), i.to_string())]))
+            .collect();
+        let id = |feeder: &Feeder, iteration| feeder.row(iteration).map(|row| row["id"].clone());
+
+        let circular = Feeder::new(rows.clone());
+        assert_eq!(id(&circular, 1).as_deref(), Some("1"));
+        assert_eq!(id(&circular, 4).as_deref(), Some("1"));
+
+        let unique = Feeder::new(rows.clone()).with_strategy(FeederStrategy::Unique);
+        assert_eq!(id(&unique, 2).as_deref(), Some("2"));
+        assert_eq!(id(&unique, 3), None);
+        let failing = unique.clone().with_on_exhausted(FeederExhaustion::Fail);
+        assert_eq!(id(&failing, 3), None);
+        let recycled = unique.with_on_exhausted(FeederExhaustion::Recycle);
+        assert_eq!(id(&recycled, 3).as_deref(), Some("0"));
+        assert_eq!(id(&recycled, 5).as_deref(), Some("2"));
+        assert_eq!(
+            "Recycle".parse::<FeederExhaustion>().unwrap(),
+            FeederExhaustion::Recycle
+        );
+        assert!("skip".parse::<FeederExhaustion>().is_err());
+
+        let random = Feeder::new(rows).with_strategy(FeederStrategy::Random);
+        assert!((0..100).all(|iteration| random.row(iteration).is_some()));
+    }
+}
@@ -0,0 +1,360 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use url::form_urlencoded;
+
+use crate::config::ConfigError;
+use crate::error::Error;
+use crate::scenario::extract::{render, Variables};
+
+/// How the fields of a form body are encoded
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FormEncoding {
+    /// `application/x-www-form-urlencoded`, text fields only
+    #[default]
+    Urlencoded,
+    /// `multipart/form-data`, text fields and file uploads
+    Multipart,
+}
+
+/// A field of a form body, either a text value or the content of a file
+///
+/// Written as `name=value` or, for a file upload, `name=@path` with an optional `;type=<mime>`, as
+/// with curl's `-F`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FormField {
+    pub name: String,
+
+    /// Text value; may contain `{{name}}` placeholders
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+
+    /// File uploaded as the field, read on the agent running the test
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+
+    /// Content type of the uploaded file, `application/octet-stream` when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+
+impl FormField {
+    /// Field with a text value
+    pub fn text(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: Some(value.into()),
+            file: None,
+            content_type: None,
+        }
+    }
+
+    /// Field uploading the content of a file
+    pub fn file(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            value: None,
+            file: Some(path.into()),
+            content_type: None,
+        }
+    }
+
+    /// Send the uploaded file with this content type
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+}
+
+impl FromStr for FormField {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s.split_once('=').ok_or_else(|| {
+            ConfigError::InvalidForm(format!("{}: expected name=value or name=@path", s))
+        })?;
+        let Some(path) = value.strip_prefix('@') else {
+            return Ok(Self::text(name.trim(), value));
+        };
+        Ok(match path.split_once(";type=") {
+            Some((path, content_type)) => {
+                Self::file(name.trim(), path).with_content_type(content_type.trim())
+            }
+            None => Self::file(name.trim(), path),
+        })
+    }
+}
+
+/// Request body encoded from form fields instead of given raw
+///
+/// The engine sets the matching `Content-Type` header unless the request has one. Files are read
+/// once when the run starts; text values are rendered for every request.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FormBody {
+    #[serde(default)]
+    pub encoding: FormEncoding,
+
+    pub fields: Vec<FormField>,
+}
+
+impl FormBody {
+    /// URL-encoded form of text fields
+    pub fn urlencoded(fields: Vec<FormField>) -> Self {
+        Self {
+            encoding: FormEncoding::Urlencoded,
+            fields,
+        }
+    }
+
+    /// Multipart form of text fields and file uploads
+    pub fn multipart(fields: Vec<FormField>) -> Self {
+        Self {
+            encoding: FormEncoding::Multipart,
+            fields,
+        }
+    }
+
+    /// Check that every field has a name and either a value or a file it can carry
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |reason: String| Err(ConfigError::InvalidForm(reason));
+        if self.fields.is_empty() {
+            return invalid("A form needs at least one field".to_string());
+        }
+        for field in &self.fields {
+            if field.name.trim().is_empty() {
+                return invalid("Field names cannot be empty".to_string());
+            }
+            match (&field.value, &field.file) {
+                (Some(_), None) => {}
+                (None, Some(_)) if self.encoding == FormEncoding::Multipart => {}
+                (None, Some(_)) => {
+                    return invalid(format!(
+                        "Field '{}' uploads a file, which requires a multipart form",
+                        field.name
+                    ))
+                }
+                _ => {
+                    return invalid(format!(
+                        "Field '{}' needs either a value or a file",
+                        field.name
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A form body with its files read, encoded for every request
+#[derive(Debug)]
+pub(crate) struct FormTemplate {
+    encoding: FormEncoding,
+    parts: Vec<(String, Part)>,
+    boundary: String,
+}
+
+#[derive(Debug)]
+enum Part {
+    Text(String),
+    File {
+        filename: String,
+        content_type: String,
+        data: Vec<u8>,
+    },
+}
+
+impl FormTemplate {
+    /// Read the files of a validated form
+    pub(crate) fn load(form: &FormBody) -> Result<Self, Error> {
+        let parts = form
+            .fields
+            .iter()
+            .map(|field| {
+                let part = match (&field.value, &field.file) {
+                    (Some(value), _) => Part::Text(value.clone()),
+                    (None, Some(path)) => Part::File {
+                        filename: filename(path),
+                        content_type: field
+                            .content_type
+                            .clone()
+                            .unwrap_or_else(|| "application/octet-stream".to_string()),
+                        data: std::fs::read(path).map_err(|e| {
+                            Error::Config(format!(
+                                "Failed to read form file {}: {}",
+                                path.display(),
+                                e
+                            ))
+                        })?,
+                    },
+                    (None, None) => Part::Text(String::new()),
+                };
+                Ok((field.name.clone(), part))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self {
+            encoding: form.encoding,
+            parts,
+            boundary: format!("lode-{:016x}", RandomState::new().hash_one(0u8)),
+        })
+    }
+
+    /// Text values of the fields, in which placeholders are filled in
+    pub(crate) fn texts(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|(_, part)| match part {
+            Part::Text(value) => Some(value.as_str()),
+            Part::File { .. } => None,
+        })
+    }
+
+    /// Value of the `Content-Type` header of the body
+    pub(crate) fn content_type(&self) -> String {
+        match self.encoding {
+            FormEncoding::Urlencoded => "application/x-www-form-urlencoded".to_string(),
+            FormEncoding::Multipart => format!("multipart/form-data; boundary={}", self.boundary),
+        }
+    }
+
+    /// Encode the body with the placeholders of text values filled in
+    pub(crate) fn encode(&self, variables: &Variables) -> Vec<u8> {
+        match self.encoding {
+            FormEncoding::Urlencoded => {
+                let mut form = form_urlencoded::Serializer::new(String::new());
+                for (name, part) in &self.parts {
+                    if let Part::Text(value) = part {
+                        form.append_pair(name, &render(value, variables));
+                    }
+                }
+                form.finish().into_bytes()
+            }
+            FormEncoding::Multipart => {
+                let mut body = Vec::new();
+                for (name, part) in &self.parts {
+                    body.extend_from_slice(format!("--{}\r\n", self.boundary).as_bytes());
+                    match part {
+                        Part::Text(value) => {
+                            body.extend_from_slice(
+                                format!(
+                                    "Content-Disposition: form-data; name=\"{}\"\r\n\r\n",
+                                    quote(name)
+                                )
+                                .as_bytes(),
+                            );
+                            body.extend_from_slice(render(value, variables).as_bytes());
+                        }
+                        Part::File {
+                            filename,
+                            content_type,
+                            data,
+                        } => {
+                            body.extend_from_slice(
+                                format!(
+                                    "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
+                                     Content-Type: {}\r\n\r\n",
+                                    quote(name),
+                                    quote(filename),
+                                    content_type
+                                )
+                                .as_bytes(),
+                            );
+                            body.extend_from_slice(data);
+                        }
+                    }
+                    body.extend_from_slice(b"\r\n");
+                }
+                body.extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+                body
+            }
+        }
+    }
+}
+
+fn filename(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "file".to_string())
+}
+
+/// Escape a name for a quoted string of a `Content-Disposition` header
+fn quote(name: &str) -> String {
+    name.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_form_field_from_str() {
+        assert_eq!(
+            "user=a=b".parse::<FormField>().unwrap(),
+            FormField::text("user", "a=b")
+        );
+        assert_eq!(
+            "avatar=@me.png;type=image/png"
+                .parse::<FormField>()
+                .unwrap(),
+            FormField::file("avatar", "me.png").with_content_type("image/png")
+        );
+        assert!("user".parse::<FormField>().is_err());
+    }
+
+    #[test]
+    fn test_form_validate() {
+        assert!(FormBody::urlencoded(vec![FormField::text("a", "1")])
+            .validate()
+            .is_ok());
+        assert!(FormBody::urlencoded(vec![]).validate().is_err());
+        assert!(FormBody::urlencoded(vec![FormField::file("a", "a.txt")])
+            .validate()
+            .is_err());
+        assert!(FormBody::multipart(vec![FormField::file("a", "a.txt")])
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_encode_forms() {
+        let variables = Variables::from([("user".to_string(), "alice".to_string())]);
+        let form = FormBody::urlencoded(vec![
+            FormField::text("user", "{{user}}"),
+            FormField::text("note", "a&b c"),
+        ]);
+        let template = FormTemplate::load(&form).unwrap();
+        assert_eq!(
+            template.encode(&variables),
+            b"user=alice&note=a%26b+c".to_vec()
+        );
+
+        let path = std::env::temp_dir().join(format!("lode-form-{}.bin", uuid::Uuid::new_v4()));
+        std::fs::write(&path, [0u8, 159, 146, 150]).unwrap();
+        let form = FormBody::multipart(vec![
+            FormField::text("user", "{{user}}"),
+            FormField::file("upload", &path),
+        ]);
+        let template = FormTemplate::load(&form).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let boundary = &template.boundary;
+        assert_eq!(
+            template.content_type(),
+            format!("multipart/form-data; boundary={}", boundary)
+        );
+        let mut expected = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"user\"\r\n\r\nalice\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"upload\"; filename=\"{f}\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n",
+            b = boundary,
+            f = filename(&path)
+        )
+        .into_bytes();
+        expected.extend_from_slice(&[0, 159, 146, 150]);
+        expected.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+        assert_eq!(template.encode(&variables), expected);
+
+        assert!(FormTemplate::load(&form).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::ConfigError;
+
+/// A GraphQL operation sent as the body of every request
+///
+/// The engine POSTs it as `{"query", "variables", "operationName"}` JSON with a JSON
+/// `Content-Type` unless the request has one. GraphQL servers answer failed operations with a
+/// 200 and an `errors` array, so a response with errors counts as a `graphql` failure.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GraphqlRequest {
+    /// Query or mutation document; may contain `{{name}}` placeholders
+    pub query: String,
+
+    /// Variables of the operation, a JSON object
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variables: Option<Value>,
+
+    /// Operation to run when the document defines several
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation_name: Option<String>,
+}
+
+impl GraphqlRequest {
+    /// Operation sending `query` without variables
+    pub fn new(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            variables: None,
+            operation_name: None,
+        }
+    }
+
+    /// Send the operation with these variables
+    pub fn with_variables(mut self, variables: Value) -> Self {
+        self.variables = Some(variables);
+        self
+    }
+
+    /// Run this operation of the document
+    pub fn with_operation_name(mut self, operation_name: impl Into<String>) -> Self {
+        self.operation_name = Some(operation_name.into());
+        self
+    }
+
+    /// Check that there is a query and the variables are an object
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.query.trim().is_empty() {
+            return Err(ConfigError::InvalidGraphql(
+                "The query cannot be empty".to_string(),
+            ));
+        }
+        if self
+            .variables
+            .as_ref()
+            .is_some_and(|variables| !variables.is_object())
+        {
+            return Err(ConfigError::InvalidGraphql(
+                "Variables must be a JSON object".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// JSON body of the request
+    pub fn body(&self) -> String {
+        let mut body = json!({ "query": self.query });
+        if let Some(variables) = &self.variables {
+            body["variables"] = variables.clone();
+        }
+        if let Some(operation_name) = &self.operation_name {
+            body["operationName"] = json!(operation_name);
+        }
+        body.to_string()
+    }
+}
+
+/// Message of the first error of a GraphQL response, `None` when it has no `errors`
+///
+/// Bodies that are not JSON are left to the HTTP status to judge.
+pub fn response_error(body: &str) -> Option<String> {
+    let response: Value = serde_json::from_str(body).ok()?;
+    let errors = response.get("errors")?.as_array()?;
+    let first = errors.first()?;
+    let message = first
+        .get("message")
+        .and_then(Value::as_str)
+        .map_or_else(|| first.to_string(), str::to_string);
+    Some(match errors.len() {
+        1 => format!("GraphQL error: {}", message),
+        count => format!("GraphQL error: {} (and {} more)", message, count - 1),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graphql_body() {
+        let request = GraphqlRequest::new("query User($id: ID!) { user(id: $id) { name } }")
+            .with_variables(json!({"id": "42"}))
+            .with_operation_name("User");
+        let body: Value = serde_json::from_str(&request.body()).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "query": "query User($id: ID!) { user(id: $id) { name } }",
+                "variables": {"id": "42"},
+                "operationName": "User",
+            })
+        );
+
+        assert!(request.validate().is_ok());
+        assert!(GraphqlRequest::new(" ").validate().is_err());
+        assert!(GraphqlRequest::new("{ a }")
+            .with_variables(json!([1]))
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_response_error() {
+        assert_eq!(response_error(r#"{"data": {"user": null}}"#), None);
+        assert_eq!(response_error(r#"{"data": {}, "errors": []}"#), None);
+        assert_eq!(response_error("not json"), None);
+        assert_eq!(
+            response_error(r#"{"errors": [{"message": "Not found"}, {"message": "Denied"}]}"#)
+                .as_deref(),
+            Some("GraphQL error: Not found (and 1 more)")
+        );
+        assert_eq!(
+            response_error(r#"{"errors": ["boom"]}"#).as_deref(),
+            Some("GraphQL error: \"boom\"")
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use reqwest::header::{HeaderName, HeaderValue};
+
+use crate::config::ConfigError;
+
+/// Check that a header name is an RFC 9110 token and its value contains only visible ASCII,
+/// spaces, tabs and obs-text
+pub fn validate_header(name: &str, value: &str) -> Result<(), ConfigError> {
+    if name.is_empty() {
+        return Err(ConfigError::InvalidHeader(
+            "Header name cannot be empty".to_string(),
+        ));
+    }
+    if HeaderName::from_bytes(name.as_bytes()).is_err() {
+        return Err(ConfigError::InvalidHeader(format!(
+            "Header name '{}' contains invalid characters",
+            name
+        )));
+    }
+    if HeaderValue::from_str(value).is_err() {
+        return Err(ConfigError::InvalidHeader(format!(
+            "Value of header '{}' contains invalid characters",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Parse and validate a header written as `Name: value`
+///
+/// The header is split at the first colon, so values may contain colons (e.g. URLs), and
+/// surrounding whitespace is trimmed from both parts.
+pub fn parse_header(header: &str) -> Result<(String, String), ConfigError> {
+    let (name, value) = header.split_once(':').ok_or_else(|| {
+        ConfigError::InvalidHeader(format!("'{}' is not in the form 'Name: value'", header))
+    })?;
+    let (name, value) = (name.trim(), value.trim());
+    validate_header(name, value)?;
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Split a comma-separated list of headers, e.g. `Accept: text/html,X-Trace: 1`
+///
+/// A comma only starts a new header when a header name and a colon follow it, so values holding
+/// commas, such as `Accept: text/html, application/json` or an HTTP date, stay whole.
+pub fn split_headers(list: &str) -> Vec<&str> {
+    let mut headers = Vec::new();
+    let mut start = 0;
+    for (comma, _) in list.match_indices(',') {
+        let next = &list[comma + 1..];
+        let starts_header = next.split_once(':').is_some_and(|(name, _)| {
+            let name = name.trim();
+            !name.is_empty() && HeaderName::from_bytes(name.as_bytes()).is_ok()
+        });
+        if starts_header {
+            headers.push(&list[start..comma]);
+            start = comma + 1;
+        }
+    }
+    headers.push(&list[start..]);
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(
+            parse_header("Content-Type: application/json").unwrap(),
+            ("Content-Type".to_string(), "application/json".to_string())
+        );
+        assert_eq!(
+            parse_header("Referer:https://example.com:8443/path").unwrap(),
+            (
+                "Referer".to_string(),
+                "https://example.com:8443/path".to_string()
+            )
+        );
+        assert_eq!(
+            parse_header("X-Empty:").unwrap(),
+            ("X-Empty".to_string(), String::new())
+        );
+
+        for invalid in ["no-colon", ": value", "Bad Name: value", "X-Test: a\nb"] {
+            assert!(
+                matches!(parse_header(invalid), Err(ConfigError::InvalidHeader(_))),
+                "{:?} should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_split_headers() {
+        assert_eq!(
+            split_headers("Authorization:Bearer token,Content-Type:application/json"),
+            [
+                "Authorization:Bearer token",
+                "Content-Type:application/json"
+            ]
+        );
+        assert_eq!(
+            split_headers("Accept: text/html, application/json;q=0.9"),
+            ["Accept: text/html, application/json;q=0.9"]
+        );
+        assert_eq!(
+            split_headers("If-Modified-Since: Wed, 21 Oct 2015 07:28:00 GMT,X-Id: 1"),
+            [
+                "If-Modified-Since: Wed, 21 Oct 2015 07:28:00 GMT",
+                "X-Id: 1"
+            ]
+        );
+        assert_eq!(split_headers("X-Empty:"), ["X-Empty:"]);
+    }
+
+    #[test]
+    fn test_validate_header() {
+        assert!(validate_header("X-Request-Id", "abc-123").is_ok());
+        assert!(validate_header("x_custom.header~1", "value with spaces").is_ok());
+        assert!(validate_header("Invalid@Header", "value").is_err());
+        assert!(validate_header("X-Test", "bad\r\nvalue").is_err());
+        assert!(validate_header("", "value").is_err());
+    }
+}
@@ -1,7 +1,36 @@
 use async_trait::async_trait;
-use reqwest::{Client, Error as ReqwestError, Method, Response};
-use std::time::Duration;
-use tracing::{instrument, warn};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use hyper_util::client::legacy::connect::{Connection, HttpInfo};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{
+    header, Body, Certificate, Client, ClientBuilder, Error as ReqwestError, Identity, Method,
+    Response, StatusCode,
+};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::{
+    ClientSessionMemoryCache, ResolvesClientCert, Resumption, WebPkiServerVerifier,
+};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::sign::CertifiedKey;
+use rustls::{DigitallySignedStruct, SignatureScheme, SupportedProtocolVersion};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+use tower_layer::Layer;
+use tower_service::Service;
+use tracing::{info, instrument};
+
+use crate::config::{interval_at, HttpVersion, LoadTestConfig, TlsConfig, TlsVersion};
+use crate::dns::{DnsConfig, HickoryResolver};
+use crate::error::Error;
+use crate::telemetry::{ErrorLog, ERROR_LOG_WINDOW};
 
 /// HTTP client trait for making requests
 #[async_trait]
@@ -15,23 +44,937 @@ pub trait HttpClient: Send + Sync {
         headers: Vec<(String, String)>,
         body: Option<String>,
     ) -> Result<Response, ReqwestError>;
+
+    /// Send an HTTP request with a binary body, e.g. a multipart file upload, byte for byte
+    async fn send_bytes(
+        &self,
+        method: Method,
+        url: String,
+        timeout: Duration,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    ) -> Result<Response, ReqwestError>;
+
+    /// Client certificates loaded again since the client was built, oldest first
+    ///
+    /// Only clients reloading their certificate when it changes on disk have any.
+    fn cert_rotations(&self) -> Vec<CertRotation> {
+        Vec::new()
+    }
+}
+
+/// A client certificate loaded again after its files changed on disk
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertRotation {
+    /// When the new certificate was loaded
+    pub at: Instant,
+    /// PEM file of the certificate
+    pub cert: PathBuf,
+}
+
+/// How often the files of a reloaded client certificate are checked for changes at most
+pub const CERT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// TLS sessions kept to resume, as many as rustls keeps by default
+const TLS_SESSION_CACHE_SIZE: usize = 256;
+
+/// Connection pool, protocol and TLS settings of the [`DefaultHttpClient`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpClientOptions {
+    /// Reuse connections between requests; when disabled every request opens a new connection
+    pub keep_alive: bool,
+    /// Idle connections kept open per host, unlimited when unset
+    pub pool_max_idle_per_host: Option<usize>,
+    /// How long an idle connection is kept open, reqwest's default (90s) when unset
+    pub pool_idle_timeout: Option<Duration>,
+    /// New connections opened per second at most, unlimited when unset or not positive
+    ///
+    /// Requests waiting for a connection wait for their turn, which softens the burst of
+    /// connections at the start of a run that firewalls may take for a SYN flood.
+    pub max_connect_rate: Option<f64>,
+    /// HTTP version spoken with the target
+    pub http_version: HttpVersion,
+    /// Trusted CA certificates, client certificate and server verification
+    pub tls: TlsConfig,
+    /// Host name resolution by the built-in resolver, the system's when unset
+    pub dns: Option<DnsConfig>,
 }
 
+impl HttpClientOptions {
+    /// Default pool settings with the HTTP version, TLS and DNS settings of a run
+    pub fn for_config(config: &LoadTestConfig) -> Self {
+        Self {
+            http_version: config.http_version,
+            tls: config.tls.clone(),
+            dns: config.dns.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for HttpClientOptions {
+    fn default() -> Self {
+        Self {
+            keep_alive: true,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout: None,
+            max_connect_rate: None,
+            http_version: HttpVersion::Auto,
+            tls: TlsConfig::default(),
+            dns: None,
+        }
+    }
+}
+
+/// Time a request spent setting up the connection it was the first to use
+///
+/// Attached to the responses of the [`DefaultHttpClient`], read it with [`connection_setup`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ConnectionSetup {
+    /// Host name resolution, unset when the URL has an IP address
+    pub dns: Option<Duration>,
+    /// TCP connect of a plain HTTP connection
+    pub connect: Option<Duration>,
+    /// TCP connect and TLS handshake of an HTTPS connection
+    ///
+    /// reqwest connects and handshakes in one step, so the two are not told apart.
+    pub tls: Option<Duration>,
+    /// Whether the TLS handshake resumed an earlier session instead of doing a full one
+    ///
+    /// Only known when the TLS settings set [`TlsConfig::session_resumption`].
+    pub tls_resumed: Option<bool>,
+}
+
+impl ConnectionSetup {
+    /// Time spent in all the setup phases
+    pub fn total(&self) -> Duration {
+        [self.dns, self.connect, self.tls]
+            .into_iter()
+            .flatten()
+            .sum()
+    }
+}
+
+/// Setup of the connections opened so far that no response came in on yet, by local address
+type PendingSetups = Arc<Mutex<HashMap<SocketAddr, ConnectionTiming>>>;
+
 /// Default HTTP client implementation using reqwest
 pub struct DefaultHttpClient {
-    client: Client,
+    client: RwLock<Client>,
+    setups: PendingSetups,
+    errors: ErrorLog,
+    reload: Option<CertReload>,
 }
 
 impl DefaultHttpClient {
     /// Create a new default HTTP client
     #[instrument(skip_all)]
     pub fn new() -> Result<Self, ReqwestError> {
+        let setups = PendingSetups::default();
+        Ok(Self {
+            client: RwLock::new(
+                timed(Client::builder(), &setups, Arc::new(SystemResolver), false).build()?,
+            ),
+            setups,
+            errors: ErrorLog::new(ERROR_LOG_WINDOW),
+            reload: None,
+        })
+    }
+
+    /// Create an HTTP client with the given settings
+    ///
+    /// Fails when a certificate or key of the TLS settings cannot be read or parsed.
+    #[instrument(skip_all)]
+    pub fn with_options(options: HttpClientOptions) -> Result<Self, Error> {
+        Self::builder().options(options).build()
+    }
+
+    /// Start building a client with more of reqwest's settings, e.g. a proxy or a user agent
+    pub fn builder() -> DefaultHttpClientBuilder {
+        DefaultHttpClientBuilder {
+            settings: Vec::new(),
+            options: HttpClientOptions::default(),
+            resolver: None,
+        }
+    }
+
+    /// Send requests with a client built elsewhere
+    ///
+    /// The connections of such a client are not timed, so responses carry no [`ConnectionSetup`]
+    /// and reports no DNS, connect or TLS phases.
+    pub fn from_client(client: Client) -> Self {
+        Self {
+            client: RwLock::new(client),
+            setups: PendingSetups::default(),
+            errors: ErrorLog::new(ERROR_LOG_WINDOW),
+            reload: None,
+        }
+    }
+}
+
+/// A reqwest setting of a [`DefaultHttpClientBuilder`], applied again when the client is rebuilt
+type Setting = Arc<dyn Fn(ClientBuilder) -> ClientBuilder + Send + Sync>;
+
+/// Builder of a [`DefaultHttpClient`], from [`DefaultHttpClient::builder`]
+///
+/// The client keeps timing its connections: its DNS resolver is wrapped in one that times the
+/// lookups, through the system resolver unless the options or [`Self::dns_resolver`] set another.
+pub struct DefaultHttpClientBuilder {
+    settings: Vec<Setting>,
+    options: HttpClientOptions,
+    resolver: Option<Arc<dyn Resolve>>,
+}
+
+impl DefaultHttpClientBuilder {
+    /// Use these connection pool, protocol and TLS settings
+    pub fn options(mut self, options: HttpClientOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Resolve host names with `resolver`, taking precedence over the DNS settings of the options
+    ///
+    /// Its lookups are timed like those of the built-in resolvers.
+    pub fn dns_resolver(mut self, resolver: Arc<dyn Resolve>) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
+    /// Give up on connecting after `timeout`
+    pub fn connect_timeout(self, timeout: Duration) -> Self {
+        self.configure(move |client| client.connect_timeout(timeout))
+    }
+
+    /// Give up on a read of the response that stalls for `timeout`
+    pub fn read_timeout(self, timeout: Duration) -> Self {
+        self.configure(move |client| client.read_timeout(timeout))
+    }
+
+    /// Send requests through a proxy; by default the system's proxy settings apply
+    pub fn proxy(self, proxy: reqwest::Proxy) -> Self {
+        self.configure(move |client| client.proxy(proxy.clone()))
+    }
+
+    /// Connect directly, ignoring the system's proxy settings
+    pub fn no_proxy(self) -> Self {
+        self.configure(ClientBuilder::no_proxy)
+    }
+
+    /// Follow redirects by this policy instead of up to 10 of them
+    pub fn redirect(self, policy: reqwest::redirect::Policy) -> Self {
+        // Shared by the clients rebuilt with a rotated certificate
+        let policy = Arc::new(policy);
+        self.configure(move |client| {
+            let policy = Arc::clone(&policy);
+            client.redirect(reqwest::redirect::Policy::custom(move |attempt| {
+                policy.redirect(attempt)
+            }))
+        })
+    }
+
+    /// Send this `User-Agent` header with every request
+    pub fn user_agent(self, user_agent: impl Into<String>) -> Self {
+        let user_agent = user_agent.into();
+        self.configure(move |client| client.user_agent(user_agent.clone()))
+    }
+
+    /// Send these headers with every request, unless a request sets them
+    pub fn default_headers(self, headers: header::HeaderMap) -> Self {
+        self.configure(move |client| client.default_headers(headers.clone()))
+    }
+
+    /// Apply any other reqwest setting
+    ///
+    /// The setting is applied again whenever the client is rebuilt to load a rotated client
+    /// certificate.
+    pub fn configure(
+        mut self,
+        configure: impl Fn(ClientBuilder) -> ClientBuilder + Send + Sync + 'static,
+    ) -> Self {
+        self.settings.push(Arc::new(configure));
+        self
+    }
+
+    /// Build the client
+    ///
+    /// Fails when a certificate or key of the TLS settings cannot be read or parsed, or when
+    /// reqwest rejects its settings.
+    #[instrument(skip_all)]
+    pub fn build(self) -> Result<DefaultHttpClient, Error> {
+        let setups = PendingSetups::default();
+        // Shared by the clients rebuilt with a rotated certificate, keeping its cache
+        let resolver: Arc<dyn Resolve> = match (self.resolver, &self.options.dns) {
+            (Some(resolver), _) => resolver,
+            (None, Some(dns)) => Arc::new(HickoryResolver::new(dns)?),
+            (None, None) => Arc::new(SystemResolver),
+        };
+        // Shared as well, so that connections after a rotation still resume sessions
+        let rustls = match self.options.tls.session_resumption {
+            Some(_) => Some(RustlsShared::new(&self.options.tls)?),
+            None => None,
+        };
+        let client = build_client(
+            &self.settings,
+            &self.options,
+            &setups,
+            &resolver,
+            rustls.as_ref(),
+        )?;
+        let tls = &self.options.tls;
+        let reload = match (&tls.client_cert, &tls.client_key) {
+            (Some(cert), Some(key)) if tls.reload_client_cert => Some((cert.clone(), key.clone())),
+            _ => None,
+        }
+        .map(|(cert, key)| {
+            let setups = setups.clone();
+            CertReload::new(
+                cert,
+                key,
+                Box::new(move || {
+                    build_client(
+                        &self.settings,
+                        &self.options,
+                        &setups,
+                        &resolver,
+                        rustls.as_ref(),
+                    )
+                }),
+            )
+        });
+        Ok(DefaultHttpClient {
+            client: RwLock::new(client),
+            setups,
+            errors: ErrorLog::new(ERROR_LOG_WINDOW),
+            reload,
+        })
+    }
+}
+
+/// Build a reqwest client with the settings of a builder, reading the TLS files as they are now
+fn build_client(
+    settings: &[Setting],
+    options: &HttpClientOptions,
+    setups: &PendingSetups,
+    resolver: &Arc<dyn Resolve>,
+    rustls: Option<&RustlsShared>,
+) -> Result<Client, Error> {
+    let builder = settings
+        .iter()
+        .fold(Client::builder(), |builder, setting| setting(builder));
+    let resumption = options.tls.session_resumption;
+    let mut builder = timed(builder, setups, Arc::clone(resolver), resumption.is_some());
+    if !options.keep_alive {
+        // Without idle connections in the pool every request connects anew
+        builder = builder.pool_max_idle_per_host(0);
+    } else if let Some(max_idle) = options.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(timeout) = options.pool_idle_timeout {
+        builder = builder.pool_idle_timeout(timeout);
+    }
+    match options.http_version {
+        HttpVersion::Auto => {}
+        HttpVersion::Http1 => builder = builder.http1_only(),
+        HttpVersion::Http2 => builder = builder.http2_prior_knowledge(),
+    }
+    if let Some(rate) = options
+        .max_connect_rate
+        .filter(|rate| rate.is_finite() && *rate > 0.0)
+    {
+        builder = builder.connector_layer(ConnectRateLayer::new(rate));
+    }
+    builder = match (resumption, rustls) {
+        (Some(resume), Some(shared)) => builder.use_preconfigured_tls(rustls_config(
+            &options.tls,
+            resume,
+            shared,
+            options.http_version,
+        )?),
+        _ => with_tls(builder, &options.tls)?,
+    };
+    Ok(builder.build()?)
+}
+
+/// Apply TLS settings to a client, reading the certificates and key they point to
+fn with_tls(mut builder: ClientBuilder, tls: &TlsConfig) -> Result<ClientBuilder, Error> {
+    if let Some(ca) = Pem::of(&tls.ca_cert, &tls.ca_cert_pem, "ca_cert_pem")? {
+        for cert in Certificate::from_pem_bundle(&ca.bytes).map_err(|e| ca.error(e))? {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    if let Some((cert, key)) = client_identity(tls)? {
+        let identity =
+            Identity::from_pkcs8_pem(&cert.bytes, &key.bytes).map_err(|e| cert.error(e))?;
+        builder = builder.identity(identity);
+    }
+    if let Some(version) = tls.min_version {
+        builder = builder.min_tls_version(version.into());
+    }
+    if tls.insecure {
+        builder = builder
+            .danger_accept_invalid_certs(true)
+            .danger_accept_invalid_hostnames(true);
+    }
+    Ok(builder)
+}
+
+/// PEM of the client certificate and its key, when the TLS settings give them
+fn client_identity(tls: &TlsConfig) -> Result<Option<(Pem, Pem)>, Error> {
+    match (
+        Pem::of(&tls.client_cert, &tls.client_cert_pem, "client_cert_pem")?,
+        Pem::of(&tls.client_key, &tls.client_key_pem, "client_key_pem")?,
+    ) {
+        (Some(cert), Some(key)) => Ok(Some((cert, key))),
+        (None, None) => Ok(None),
+        _ => Err(Error::Config(
+            "A client certificate and its key must be given together".to_string(),
+        )),
+    }
+}
+
+/// What the rustls configurations of a client share across the rebuilds for a rotated
+/// certificate
+///
+/// rustls resumes a session only with the certificate verifier and client certificate resolver
+/// it was established with, so rebuilt configurations keep those along with the session cache,
+/// and the resolver presents the certificate loaded last.
+struct RustlsShared {
+    provider: Arc<CryptoProvider>,
+    verifier: Arc<dyn ServerCertVerifier>,
+    client_cert: Arc<LatestClientCert>,
+    sessions: Arc<ClientSessionMemoryCache>,
+}
+
+impl RustlsShared {
+    /// Set up the verification of the server certificate of the TLS settings
+    fn new(tls: &TlsConfig) -> Result<Self, Error> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let verifier: Arc<dyn ServerCertVerifier> = if tls.insecure {
+            Arc::new(AcceptAnyCert(Arc::clone(&provider)))
+        } else {
+            let mut roots = rustls::RootCertStore::empty();
+            roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+            if let Some(ca) = Pem::of(&tls.ca_cert, &tls.ca_cert_pem, "ca_cert_pem")? {
+                for cert in CertificateDer::pem_slice_iter(&ca.bytes) {
+                    roots
+                        .add(cert.map_err(|e| ca.error(e))?)
+                        .map_err(|e| ca.error(e))?;
+                }
+            }
+            WebPkiServerVerifier::builder_with_provider(Arc::new(roots), Arc::clone(&provider))
+                .build()
+                .map_err(|e| Error::Config(format!("Failed to set up TLS verification: {}", e)))?
+        };
         Ok(Self {
-            client: Client::new(),
+            provider,
+            verifier: Arc::new(NoteFullHandshake(verifier)),
+            client_cert: Arc::default(),
+            sessions: Arc::new(ClientSessionMemoryCache::new(TLS_SESSION_CACHE_SIZE)),
+        })
+    }
+}
+
+/// Presents the client certificate loaded last, if any
+#[derive(Debug, Default)]
+struct LatestClientCert(RwLock<Option<Arc<CertifiedKey>>>);
+
+impl ResolvesClientCert for LatestClientCert {
+    fn resolve(
+        &self,
+        _root_hint_subjects: &[&[u8]],
+        _sigschemes: &[SignatureScheme],
+    ) -> Option<Arc<CertifiedKey>> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn has_certs(&self) -> bool {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).is_some()
+    }
+}
+
+/// Configure rustls with TLS settings, resuming sessions on new connections or never
+///
+/// Loads the client certificate into the resolver of `shared`. rustls speaks TLS 1.2 and 1.3
+/// only: a minimum of 1.3 leaves out 1.2, while lower minimums are refused by
+/// [`TlsConfig::validate`]. ALPN offers the protocols of the HTTP version, as reqwest does for
+/// the TLS it configures itself.
+fn rustls_config(
+    tls: &TlsConfig,
+    resume: bool,
+    shared: &RustlsShared,
+    http_version: HttpVersion,
+) -> Result<rustls::ClientConfig, Error> {
+    let versions: &[&'static SupportedProtocolVersion] = match tls.min_version {
+        Some(TlsVersion::Tls1_3) => &[&rustls::version::TLS13],
+        _ => rustls::DEFAULT_VERSIONS,
+    };
+    let builder = rustls::ClientConfig::builder_with_provider(Arc::clone(&shared.provider))
+        .with_protocol_versions(versions)
+        .map_err(|e| Error::Config(format!("Failed to set up TLS: {}", e)))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::clone(&shared.verifier));
+    if let Some((cert, key)) = client_identity(tls)? {
+        let certs = CertificateDer::pem_slice_iter(&cert.bytes)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| cert.error(e))?;
+        let key_der = PrivateKeyDer::from_pem_slice(&key.bytes).map_err(|e| key.error(e))?;
+        let certified =
+            CertifiedKey::from_der(certs, key_der, &shared.provider).map_err(|e| cert.error(e))?;
+        *shared
+            .client_cert
+            .0
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(certified));
+    }
+    let mut config = builder.with_client_cert_resolver(Arc::clone(&shared.client_cert) as _);
+    config.resumption = match resume {
+        true => Resumption::store(Arc::clone(&shared.sessions) as _),
+        false => Resumption::disabled(),
+    };
+    config.alpn_protocols = match http_version {
+        HttpVersion::Auto => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+        HttpVersion::Http1 => vec![b"http/1.1".to_vec()],
+        HttpVersion::Http2 => vec![b"h2".to_vec()],
+    };
+    Ok(config)
+}
+
+/// Verifies server certificates with another verifier, noting that the connection being opened
+/// did a full TLS handshake: a resumed session skips the certificate
+#[derive(Debug)]
+struct NoteFullHandshake(Arc<dyn ServerCertVerifier>);
+
+impl ServerCertVerifier for NoteFullHandshake {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        // Handshakes outside of a timed connect, if any, go unnoted
+        let _ = FULL_HANDSHAKE.try_with(|full| full.set(true));
+        self.0
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.0.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.0.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_verify_schemes()
+    }
+}
+
+/// Accepts any server certificate and host name, still checking the handshake signatures
+#[derive(Debug)]
+struct AcceptAnyCert(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// PEM of the TLS settings, read from its file or given inline, with where it came from
+struct Pem {
+    bytes: Vec<u8>,
+    source: String,
+}
+
+impl Pem {
+    /// The PEM of a setting, read from the file when given and taken inline otherwise
+    fn of(
+        path: &Option<PathBuf>,
+        inline: &Option<String>,
+        name: &str,
+    ) -> Result<Option<Pem>, Error> {
+        match (path, inline) {
+            (Some(path), _) => Ok(Some(Pem {
+                bytes: std::fs::read(path).map_err(|e| {
+                    Error::Config(format!("Failed to read {}: {}", path.display(), e))
+                })?,
+                source: path.display().to_string(),
+            })),
+            (None, Some(pem)) => Ok(Some(Pem {
+                bytes: pem.clone().into_bytes(),
+                source: name.to_string(),
+            })),
+            (None, None) => Ok(None),
+        }
+    }
+
+    fn error(&self, error: impl std::fmt::Display) -> Error {
+        Error::Config(format!("Invalid PEM in {}: {}", self.source, error))
+    }
+}
+
+/// Rebuilds a client with its client certificate when the certificate or key file changes
+///
+/// Requests sent after the rebuild connect with the new certificate; requests in flight finish
+/// on the connections of the previous client.
+struct CertReload {
+    cert: PathBuf,
+    key: PathBuf,
+    rebuild: Box<dyn Fn() -> Result<Client, Error> + Send + Sync>,
+    state: Mutex<ReloadState>,
+}
+
+struct ReloadState {
+    checked: Instant,
+    /// Modification times of the certificate and key files the client was built with
+    modified: [Option<SystemTime>; 2],
+    rotations: Vec<CertRotation>,
+}
+
+impl CertReload {
+    fn new(
+        cert: PathBuf,
+        key: PathBuf,
+        rebuild: Box<dyn Fn() -> Result<Client, Error> + Send + Sync>,
+    ) -> Self {
+        let modified = [modified(&cert), modified(&key)];
+        Self {
+            cert,
+            key,
+            rebuild,
+            state: Mutex::new(ReloadState {
+                checked: Instant::now(),
+                modified,
+                rotations: Vec::new(),
+            }),
+        }
+    }
+
+    /// A client built with the changed certificate when its files changed since the last check
+    ///
+    /// Checks at most once per [`CERT_CHECK_INTERVAL`], and not while another request checks.
+    /// Files that fail to load, e.g. a certificate written before its key, are tried again at
+    /// the next check.
+    fn reload(&self, errors: &ErrorLog) -> Option<Client> {
+        let mut state = self.state.try_lock().ok()?;
+        if state.checked.elapsed() < CERT_CHECK_INTERVAL {
+            return None;
+        }
+        state.checked = Instant::now();
+        let modified = [modified(&self.cert), modified(&self.key)];
+        if modified == state.modified {
+            return None;
+        }
+        match (self.rebuild)() {
+            Ok(client) => {
+                info!("Reloaded client certificate {}", self.cert.display());
+                state.modified = modified;
+                state.rotations.push(CertRotation {
+                    at: Instant::now(),
+                    cert: self.cert.clone(),
+                });
+                Some(client)
+            }
+            Err(e) => {
+                errors.warn(format!("Failed to reload client certificate: {}", e));
+                None
+            }
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// Time the setup of the connections a client opens into `setups`
+///
+/// Layers added later wrap this one, so waiting for them, e.g. for the connection rate, is not
+/// part of the setup. With `notes_full_handshakes` the TLS of the client is configured by
+/// [`rustls_config`], whose certificate verification tells full handshakes from resumed ones.
+fn timed(
+    builder: ClientBuilder,
+    setups: &PendingSetups,
+    resolver: Arc<dyn Resolve>,
+    notes_full_handshakes: bool,
+) -> ClientBuilder {
+    builder
+        .dns_resolver(Arc::new(TimedResolver { inner: resolver }))
+        .connector_layer(ConnectTimingLayer {
+            setups: setups.clone(),
+            notes_full_handshakes,
+        })
+}
+
+tokio::task_local! {
+    /// Host name resolution time of the connection being opened by the current task
+    static DNS_TIME: Cell<Option<Duration>>;
+    /// Whether the connection being opened by the current task verified a server certificate
+    static FULL_HANDSHAKE: Cell<bool>;
+}
+
+/// Resolves host names with the system resolver like reqwest does
+#[derive(Debug)]
+pub struct SystemResolver;
+
+impl Resolve for SystemResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Times the lookups of another resolver
+struct TimedResolver {
+    inner: Arc<dyn Resolve>,
+}
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let lookup = self.inner.resolve(name);
+        Box::pin(async move {
+            let started = Instant::now();
+            let addrs = lookup.await?;
+            // Resolutions outside of a timed connect, if any, go untimed
+            let _ = DNS_TIME.try_with(|time| time.set(Some(started.elapsed())));
+            Ok(addrs)
+        })
+    }
+}
+
+/// Time of a connection setup until a response tells whether it used TLS
+#[derive(Debug, Clone, Copy)]
+struct ConnectionTiming {
+    dns: Option<Duration>,
+    /// Everything after the host name resolution
+    handshake: Duration,
+    /// Whether a TLS handshake skipped the server certificate, when full handshakes are noted
+    resumed: Option<bool>,
+}
+
+/// Times the connections a client opens, by the local address that identifies them
+#[derive(Debug, Clone)]
+struct ConnectTimingLayer {
+    setups: PendingSetups,
+    notes_full_handshakes: bool,
+}
+
+impl<S> Layer<S> for ConnectTimingLayer {
+    type Service = ConnectTiming<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectTiming {
+            inner,
+            setups: self.setups.clone(),
+            notes_full_handshakes: self.notes_full_handshakes,
+        }
+    }
+}
+
+/// Connector service wrapped by a [`ConnectTimingLayer`]
+#[derive(Debug, Clone)]
+struct ConnectTiming<S> {
+    inner: S,
+    setups: PendingSetups,
+    notes_full_handshakes: bool,
+}
+
+impl<S, R> Service<R> for ConnectTiming<S>
+where
+    S: Service<R> + Clone + Send + 'static,
+    S::Response: Connection,
+    S::Future: Send,
+    R: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let setups = self.setups.clone();
+        let notes_full_handshakes = self.notes_full_handshakes;
+        // The service polled ready is the one to call, leave a fresh clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let connect = async move {
+            let started = Instant::now();
+            let connection = inner.call(request).await?;
+            let elapsed = started.elapsed();
+
+            let mut extensions = Default::default();
+            connection.connected().get_extras(&mut extensions);
+            if let Some(info) = extensions.get::<HttpInfo>() {
+                let dns = DNS_TIME.with(Cell::get);
+                let timing = ConnectionTiming {
+                    dns,
+                    handshake: elapsed.saturating_sub(dns.unwrap_or_default()),
+                    resumed: notes_full_handshakes.then(|| !FULL_HANDSHAKE.with(Cell::get)),
+                };
+                setups
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(info.local_addr(), timing);
+            }
+            Ok(connection)
+        };
+        Box::pin(DNS_TIME.scope(
+            Cell::new(None),
+            FULL_HANDSHAKE.scope(Cell::new(false), connect),
+        ))
+    }
+}
+
+/// Spaces out the connections a client opens so they start at most `rate` times per second
+///
+/// Like the request rate limiter of the engine, each connection reserves the next free slot, one
+/// interval after the previous one, and slots are never reserved in the past.
+#[derive(Debug, Clone)]
+struct ConnectRateLayer {
+    interval: Duration,
+    next_slot: Arc<Mutex<Option<Instant>>>,
+}
+
+impl ConnectRateLayer {
+    fn new(rate: f64) -> Self {
+        Self {
+            interval: interval_at(rate, 1.0),
+            next_slot: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl<S> Layer<S> for ConnectRateLayer {
+    type Service = ConnectRate<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectRate {
+            inner,
+            limit: self.clone(),
+        }
+    }
+}
+
+/// Connector service wrapped by a [`ConnectRateLayer`]
+#[derive(Debug, Clone)]
+struct ConnectRate<S> {
+    inner: S,
+    limit: ConnectRateLayer,
+}
+
+impl<S, R> Service<R> for ConnectRate<S>
+where
+    S: Service<R> + Clone + Send + 'static,
+    S::Future: Send,
+    R: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let slot = {
+            let mut next_slot = self
+                .limit
+                .next_slot
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let slot = next_slot.map_or(now, |next| next.max(now));
+            *next_slot = Some(slot + self.limit.interval);
+            slot
+        };
+        // The service polled ready is the one to call, leave a fresh clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            tokio::time::sleep_until(slot.into()).await;
+            inner.call(request).await
         })
     }
 }
 
+/// Time the request of a response spent setting up its connection
+///
+/// Only known for responses of a [`DefaultHttpClient`], and only for the first response on a
+/// connection.
+pub fn connection_setup(response: &Response) -> Option<ConnectionSetup> {
+    response.extensions().get::<ConnectionSetup>().copied()
+}
+
+/// Local address of the connection a response came in on, which identifies the connection
+///
+/// Only known for responses of a reqwest client that connected itself.
+pub fn connection_addr(response: &Response) -> Option<SocketAddr> {
+    response
+        .extensions()
+        .get::<HttpInfo>()
+        .map(|info| info.local_addr())
+}
+
 #[async_trait]
 impl HttpClient for DefaultHttpClient {
     #[instrument(skip(self, headers, body), fields(
@@ -49,7 +992,62 @@ impl HttpClient for DefaultHttpClient {
         headers: Vec<(String, String)>,
         body: Option<String>,
     ) -> Result<Response, ReqwestError> {
-        let mut request = self.client.request(method, url).timeout(timeout);
+        self.send(method, url, timeout, headers, body.map(Body::from))
+            .await
+    }
+
+    #[instrument(skip(self, headers, body), fields(
+        method = %method,
+        url = %url,
+        timeout_ms = %timeout.as_millis(),
+        num_headers = %headers.len(),
+        body_bytes = %body.len(),
+    ))]
+    async fn send_bytes(
+        &self,
+        method: Method,
+        url: String,
+        timeout: Duration,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    ) -> Result<Response, ReqwestError> {
+        self.send(method, url, timeout, headers, Some(Body::from(body)))
+            .await
+    }
+
+    fn cert_rotations(&self) -> Vec<CertRotation> {
+        self.reload
+            .as_ref()
+            .map(|reload| {
+                let state = reload.state.lock().unwrap_or_else(|e| e.into_inner());
+                state.rotations.clone()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl DefaultHttpClient {
+    async fn send(
+        &self,
+        method: Method,
+        url: String,
+        timeout: Duration,
+        headers: Vec<(String, String)>,
+        body: Option<Body>,
+    ) -> Result<Response, ReqwestError> {
+        if let Some(client) = self
+            .reload
+            .as_ref()
+            .and_then(|reload| reload.reload(&self.errors))
+        {
+            *self.client.write().unwrap_or_else(|e| e.into_inner()) = client;
+        }
+        let client = self
+            .client
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let mut request = client.request(method, url).timeout(timeout);
 
         for (name, value) in headers {
             request = request.header(name, value);
@@ -60,10 +1058,34 @@ impl HttpClient for DefaultHttpClient {
         }
 
         match request.send().await {
-            Ok(response) => Ok(response),
+            Ok(mut response) => {
+                let timing = connection_addr(&response).and_then(|addr| {
+                    self.setups
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .remove(&addr)
+                });
+                if let Some(timing) = timing {
+                    let handshake = Some(timing.handshake);
+                    let tls = response.url().scheme() == "https";
+                    response.extensions_mut().insert(ConnectionSetup {
+                        dns: timing.dns,
+                        connect: handshake.filter(|_| !tls),
+                        tls: handshake.filter(|_| tls),
+                        tls_resumed: timing.resumed.filter(|_| tls),
+                    });
+                }
+                Ok(response)
+            }
             Err(e) => {
-                warn!("Request failed: {}", e);
-                Err(e)
+                // Logged without its URL, so failures of every URL repeat the same line
+                let url = e.url().cloned();
+                let e = e.without_url();
+                self.errors.warn(format!("Request failed: {}", e));
+                Err(match url {
+                    Some(url) => e.with_url(url),
+                    None => e,
+                })
             }
         }
     }
@@ -71,8 +1093,60 @@ impl HttpClient for DefaultHttpClient {
 
 impl Default for DefaultHttpClient {
     fn default() -> Self {
-        Self {
-            client: Client::new(),
-        }
+        Self::new().expect("default HTTP client can be built")
+    }
+}
+
+/// Delay a 429, 502 or 503 response asks for in its `Retry-After` header
+pub fn retry_after(response: &Response) -> Option<Duration> {
+    if !matches!(
+        response.status(),
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE
+    ) {
+        return None;
+    }
+    let value = response.headers().get(header::RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value, Utc::now())
+}
+
+/// Parse a `Retry-After` value, either seconds or an HTTP date, into the delay from `now`
+///
+/// A date in the past is no delay.
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc3339("2026-10-16T08:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Fri, 16 Oct 2026 08:00:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Fri, 16 Oct 2026 07:59:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("-1", now), None);
+        assert_eq!(parse_retry_after("soon", now), None);
     }
 }
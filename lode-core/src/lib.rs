@@ -3,20 +3,52 @@
 //! This library provides the core functionality for executing load tests against HTTP APIs.
 //! It is designed to be efficient, reliable, and easy to integrate into both CLI and REST API applications.
 
+pub mod abort; // Abort rules ending runs against a broken target early
+pub mod clock; // Clock offsets for distributed runs
 pub mod config; // Load test configuration
+#[cfg(feature = "conformance")]
+pub mod conformance; // Conformance checks of HttpClient implementations
+pub mod control; // Live overrides of the load of running tests
+pub mod cooldown; // Probing of the target's recovery after the load stops
+pub mod distributed; // Load tests split across lode-api agents
+pub mod dns; // Host name resolution by other nameservers than the system's
 pub mod engine; // Test execution engine
 pub mod error; // Error types and handling
+pub mod faults; // Generator fault injection
+pub mod feeder; // Test data rows filling request templates
+pub mod form; // Form-encoded and multipart request bodies
+pub mod graphql; // GraphQL operations and their errors
+pub mod headers; // Header parsing and validation
 pub mod http; // HTTP client and request handling
+pub mod lint; // Warnings about likely misconfigured test definitions
+pub mod live; // Live metrics snapshots of running tests
+pub mod memory; // Soft memory ceiling of the generator during a run
 pub mod metrics; // Performance metrics collection and analysis
+pub mod pacing; // Pacing by the rate limits servers publish in response headers
+pub mod progress; // Live progress tracking of running tests
+pub mod recovery; // Recovery of collected metrics when the process panics
 pub mod report; // Test results and reporting
+pub mod retry; // Retries of failed requests with backoff
+pub mod run; // Load test runs and their lifecycle
+pub mod scenario; // Weighted and sequential multi-endpoint scenarios
+pub mod slo; // Service level objectives checked against reports
+pub mod stages; // Staged load profiles
 pub mod telemetry; // Structured logging and telemetry
+pub mod think; // Think time of virtual users between requests
+pub mod trace; // Sampled traces of requests with their headers and bodies
+pub mod trigger; // External triggers moving staged runs to their next stage
+pub mod vu; // Virtual users and their state between iterations
 
 pub use config::LoadTestConfig;
-pub use engine::LoadTestEngine;
+pub use engine::{LoadTestEngine, RunOptions};
 pub use error::Error;
+pub use live::{LiveMetrics, MetricsSnapshot};
 pub use metrics::TestMetrics;
+pub use progress::{Progress, ProgressSnapshot};
+#[cfg(feature = "otlp")]
+pub use telemetry::get_otlp_subscriber;
 pub use telemetry::{
-    get_stderr_subscriber, get_stdout_subscriber, get_subscriber, init_subscriber,
+    get_stderr_subscriber, get_stdout_subscriber, get_subscriber, init_subscriber, OtlpSettings,
 };
 
 pub type Result<T> = std::result::Result<T, Error>;
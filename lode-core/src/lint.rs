@@ -0,0 +1,268 @@
+use std::fmt;
+
+use crate::config::{
+    FeederExhaustion, FeederStrategy, HttpMethod, LoadTestConfig, MissingValue, Stage,
+};
+use crate::http::HttpClientOptions;
+use crate::scenario::extract::placeholders;
+
+/// A valid but likely unintended setting of a test definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// Stable name of the check, e.g. `body-with-get`
+    pub check: &'static str,
+    /// What is off and what it leads to
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.message, self.check)
+    }
+}
+
+/// Look for settings that are valid but likely misconfigured, since they only surface as confusing
+/// results, e.g. a body sent with GET or more concurrency than pooled connections
+///
+/// The configuration is expected to be valid; see [`LoadTestConfig::validate`].
+pub fn lint(config: &LoadTestConfig, client: &HttpClientOptions) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let mut warn = |check, message: String| warnings.push(Warning { check, message });
+
+    let concurrency = config
+        .stages
+        .iter()
+        .map(Stage::max_concurrency)
+        .fold(config.concurrency, usize::max);
+    if let Some(max_idle) = client.pool_max_idle_per_host.filter(|_| client.keep_alive) {
+        if concurrency > max_idle {
+            warn(
+                "pool-size",
+                format!(
+                    "Concurrency of {} is above the {} idle connections kept per host, so requests \
+                     beyond them open a new connection every time",
+                    concurrency, max_idle
+                ),
+            );
+        }
+    }
+    if let Some(buffer) = config.advanced.stream_buffer {
+        if buffer < concurrency {
+            warn(
+                "stream-buffer",
+                format!(
+                    "Stream buffer of {} caps the concurrency of {} at {}",
+                    buffer, concurrency, buffer
+                ),
+            );
+        }
+    }
+
+    let requests: Vec<_> = match &config.scenario {
+        Some(scenario) => scenario
+            .requests
+            .iter()
+            .map(|spec| {
+                (
+                    format!("Request '{}'", spec.name),
+                    &spec.method,
+                    spec.body.is_some() || config.body.is_some() || config.form.is_some(),
+                )
+            })
+            .collect(),
+        None => vec![(
+            "The request".to_string(),
+            &config.method,
+            config.body.is_some() || config.form.is_some(),
+        )],
+    };
+    for (request, method, has_body) in requests {
+        if has_body && *method == HttpMethod::GET {
+            warn(
+                "body-with-get",
+                format!(
+                    "{} sends a body with GET, which servers and proxies may ignore or reject",
+                    request
+                ),
+            );
+        }
+    }
+
+    let content_type = config
+        .headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+    if config.scenario.is_none() && config.body.is_some() && !content_type {
+        warn(
+            "body-without-content-type",
+            "The request sends a body without a Content-Type header, which servers may reject \
+             with 415"
+                .to_string(),
+        );
+    }
+
+    // Scenarios are validated to fill their placeholders, single requests are not
+    if config.scenario.is_none() {
+        let columns = config
+            .feeder
+            .as_ref()
+            .map(|feeder| feeder.columns())
+            .unwrap_or_default();
+        let templates = std::iter::once(config.url.as_str())
+            .chain(config.headers.iter().map(|(_, value)| value.as_str()))
+            .chain(config.body.as_deref())
+            .chain(
+                config
+                    .graphql
+                    .as_ref()
+                    .map(|graphql| graphql.query.as_str()),
+            );
+        let consequence = match config.on_missing_value {
+            MissingValue::Abort => "aborts the run",
+            MissingValue::Skip => "skips every request",
+            MissingValue::Send => "is sent as it is",
+        };
+        for name in templates.flat_map(placeholders) {
+            if !columns.contains(name) {
+                warn(
+                    "unfilled-placeholder",
+                    format!(
+                        "'{{{{{}}}}}' {}, since no feeder column fills it",
+                        name, consequence
+                    ),
+                );
+            }
+        }
+    }
+
+    if let Some(feeder) = &config.feeder {
+        if feeder.strategy == FeederStrategy::Unique
+            && feeder.on_exhausted != FeederExhaustion::Recycle
+            && feeder.rows.len() < config.requests
+        {
+            warn(
+                "feeder-too-short",
+                format!(
+                    "The feeder has {} unique rows for {} iterations, so the run {} after {}",
+                    feeder.rows.len(),
+                    config.requests,
+                    match feeder.on_exhausted {
+                        FeederExhaustion::Fail => "is aborted",
+                        _ => "ends",
+                    },
+                    feeder.rows.len()
+                ),
+            );
+        }
+    }
+
+    if let Some(deadline) = config.deadline {
+        if config.timeout >= deadline {
+            warn(
+                "timeout-past-deadline",
+                format!(
+                    "Request timeout of {:?} is not shorter than the deadline of {:?}, so slow \
+                     requests are dropped at the deadline instead of timing out",
+                    config.timeout, deadline
+                ),
+            );
+        }
+    }
+    if let Some(retry) = &config.retry {
+        if retry.max_attempts == 1 {
+            warn(
+                "retry-single-attempt",
+                "A retry policy of 1 attempt never retries".to_string(),
+            );
+        }
+        if retry.max_delay >= config.timeout {
+            warn(
+                "retry-delay-past-timeout",
+                format!(
+                    "Retries may wait up to {:?}, longer than the request timeout of {:?}, which \
+                     stretches the iterations of retried requests",
+                    retry.max_delay, config.timeout
+                ),
+            );
+        }
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Feeder, RetryPolicy};
+    use crate::scenario::extract::Variables;
+    use std::time::Duration;
+
+    fn checks(config: &LoadTestConfig, client: &HttpClientOptions) -> Vec<&'static str> {
+        lint(config, client)
+            .into_iter()
+            .map(|warning| warning.check)
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_clean_config() {
+        let config = LoadTestConfig::new(
+            "http://example.com/api".to_string(),
+            HttpMethod::POST,
+            100,
+            10,
+            Duration::from_secs(5),
+        )
+        .unwrap()
+        .with_header("Content-Type".to_string(), "application/json".to_string())
+        .with_body("{}".to_string());
+        assert!(lint(&config, &HttpClientOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_lint_warnings() {
+        let rows = vec![Variables::from([("id".to_string(), "1".to_string())])];
+        let config = LoadTestConfig::new(
+            "http://example.com/users/{{id}}/{{name}}".to_string(),
+            HttpMethod::GET,
+            100,
+            10,
+            Duration::from_secs(5),
+        )
+        .unwrap()
+        .with_body("{}".to_string())
+        .with_feeder(Feeder::new(rows).with_strategy(FeederStrategy::Unique))
+        .with_deadline(Duration::from_secs(5))
+        .with_retry(RetryPolicy::new(3).with_backoff(Duration::ZERO, Duration::from_secs(10)));
+        let client = HttpClientOptions {
+            pool_max_idle_per_host: Some(4),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            checks(&config, &client),
+            vec![
+                "pool-size",
+                "body-with-get",
+                "body-without-content-type",
+                "unfilled-placeholder",
+                "feeder-too-short",
+                "timeout-past-deadline",
+                "retry-delay-past-timeout",
+            ]
+        );
+        let warning = &lint(&config, &client)[3];
+        assert_eq!(
+            warning.to_string(),
+            "'{{name}}' is sent as it is, since no feeder column fills it [unfilled-placeholder]"
+        );
+        let aborted = LoadTestConfig {
+            on_missing_value: MissingValue::Abort,
+            ..config.clone()
+        };
+        assert_eq!(
+            lint(&aborted, &client)[3].to_string(),
+            "'{{name}}' aborts the run, since no feeder column fills it [unfilled-placeholder]"
+        );
+    }
+}
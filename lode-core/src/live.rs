@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Mutex};
+
+use crate::metrics::TestMetrics;
+
+/// Default delay between two live metrics snapshots
+pub const DEFAULT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Upper bounds of the response time histogram buckets, in seconds
+pub const RESPONSE_TIME_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Point-in-time view of the metrics of a running load test
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MetricsSnapshot {
+    pub elapsed_seconds: f64,
+    pub total_requests: u64,
+    pub successful_requests: u64,
+    pub failed_requests: u64,
+    /// Requests per second since the previous snapshot
+    pub current_rps: f64,
+    pub p95_response_time_ms: f64,
+    /// Percentage of failed requests since the start of the run
+    pub error_rate: f64,
+    /// Set on the last snapshot of a run
+    pub finished: bool,
+    /// Distribution of the response times so far, for exporters
+    #[serde(skip)]
+    pub response_times: ResponseTimeHistogram,
+}
+
+/// Cumulative response time histogram with the bounds of [`RESPONSE_TIME_BUCKETS`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseTimeHistogram {
+    /// Upper bound in seconds and number of responses at or below it
+    pub buckets: Vec<(f64, u64)>,
+    pub sum_seconds: f64,
+    pub count: u64,
+}
+
+impl ResponseTimeHistogram {
+    fn from_metrics(metrics: &TestMetrics) -> Self {
+        let bounds = RESPONSE_TIME_BUCKETS.map(Duration::from_secs_f64);
+        Self {
+            buckets: RESPONSE_TIME_BUCKETS
+                .into_iter()
+                .zip(metrics.response_time_distribution(&bounds))
+                .collect(),
+            sum_seconds: metrics.response_time_sum().as_secs_f64(),
+            count: metrics.timed_requests(),
+        }
+    }
+}
+
+/// Sends periodic [`MetricsSnapshot`]s of a run to embedders, e.g. a live dashboard
+///
+/// Receivers always see the latest snapshot; slow receivers skip intermediate ones.
+#[derive(Debug, Clone)]
+pub struct LiveMetrics {
+    sender: watch::Sender<MetricsSnapshot>,
+    interval: Duration,
+}
+
+impl LiveMetrics {
+    /// Create a publisher sending a snapshot every `interval`, and a receiver for it
+    pub fn channel(interval: Duration) -> (Self, watch::Receiver<MetricsSnapshot>) {
+        let (sender, receiver) = watch::channel(MetricsSnapshot::default());
+        (Self { sender, interval }, receiver)
+    }
+
+    /// Subscribe another receiver to the snapshots
+    pub fn subscribe(&self) -> watch::Receiver<MetricsSnapshot> {
+        self.sender.subscribe()
+    }
+
+    /// Publish snapshots of `metrics` until the future is dropped
+    pub(crate) async fn publish(&self, metrics: &Mutex<TestMetrics>, started: Instant) {
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // The first tick completes immediately
+        ticker.tick().await;
+
+        let mut previous = (started, 0);
+        loop {
+            ticker.tick().await;
+            let now = Instant::now();
+            let metrics = metrics.lock().await;
+            let since = now.duration_since(previous.0).as_secs_f64();
+            let current_rps = if since > 0.0 {
+                (metrics.total_requests() - previous.1) as f64 / since
+            } else {
+                0.0
+            };
+            previous = (now, metrics.total_requests());
+            self.sender
+                .send_replace(snapshot(&metrics, started.elapsed(), current_rps, false));
+        }
+    }
+
+    /// Publish the final snapshot of a finished run
+    pub(crate) fn finish(&self, metrics: &TestMetrics) {
+        self.sender.send_replace(snapshot(
+            metrics,
+            metrics.total_duration(),
+            metrics.requests_per_second(),
+            true,
+        ));
+    }
+}
+
+fn snapshot(
+    metrics: &TestMetrics,
+    elapsed: Duration,
+    current_rps: f64,
+    finished: bool,
+) -> MetricsSnapshot {
+    let total = metrics.total_requests();
+    MetricsSnapshot {
+        elapsed_seconds: elapsed.as_secs_f64(),
+        total_requests: total,
+        successful_requests: metrics.successful_requests(),
+        failed_requests: metrics.failed_requests(),
+        current_rps,
+        p95_response_time_ms: metrics.p95_response_time().as_secs_f64() * 1000.0,
+        error_rate: if total > 0 {
+            metrics.failed_requests() as f64 / total as f64 * 100.0
+        } else {
+            0.0
+        },
+        finished,
+        response_times: ResponseTimeHistogram::from_metrics(metrics),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::RequestMetrics;
+    use reqwest::StatusCode;
+
+    #[tokio::test]
+    async fn test_publishes_snapshots() {
+        let metrics = Mutex::new(TestMetrics::new().unwrap());
+        for status in [
+            StatusCode::OK,
+            StatusCode::OK,
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ] {
+            metrics
+                .lock()
+                .await
+                .record_request(RequestMetrics::new().complete(status));
+        }
+
+        let (live, mut receiver) = LiveMetrics::channel(Duration::from_millis(20));
+        let publisher = live.publish(&metrics, Instant::now());
+        tokio::select! {
+            _ = publisher => unreachable!("publishing runs until dropped"),
+            changed = receiver.changed() => changed.unwrap(),
+        }
+
+        let snapshot = receiver.borrow_and_update().clone();
+        assert_eq!(snapshot.total_requests, 3);
+        assert_eq!(snapshot.failed_requests, 1);
+        assert!((snapshot.error_rate - 100.0 / 3.0).abs() < 1e-9);
+        assert!(snapshot.current_rps > 0.0);
+        assert!(!snapshot.finished);
+        assert_eq!(snapshot.response_times.count, 3);
+        assert_eq!(
+            snapshot.response_times.buckets.len(),
+            RESPONSE_TIME_BUCKETS.len()
+        );
+
+        live.finish(&*metrics.lock().await);
+        assert!(receiver.borrow().finished);
+    }
+}
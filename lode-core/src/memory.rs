@@ -0,0 +1,197 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::time::MissedTickBehavior;
+use tracing::warn;
+
+use crate::metrics::{RunEvent, TestMetrics};
+
+/// Time between two readings of the generator's resident memory
+pub const MEMORY_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Share of the memory ceiling from which the generator sheds what it can
+pub const PRESSURE_THRESHOLD: f64 = 0.8;
+
+/// Resident memory of the generator during a run kept under a ceiling
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryUsage {
+    /// Bytes of resident memory the generator should stay under
+    pub ceiling: u64,
+    /// Highest resident memory measured, in bytes
+    pub peak_resident: u64,
+    /// Times the resident memory went over [`PRESSURE_THRESHOLD`] of the ceiling
+    pub pressure_events: u64,
+    /// Recorded requests flushed to disk
+    pub spilled_requests: u64,
+    /// Sampled traces dropped to free memory or not kept afterwards
+    pub dropped_traces: u64,
+}
+
+/// Resident memory of this process in bytes, `None` where it cannot be measured
+pub fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Keeps the generator under a soft memory ceiling instead of being killed near the end of a
+/// giant run
+///
+/// Past [`PRESSURE_THRESHOLD`] of the ceiling, the requests recorded for the CSV or Parquet
+/// output are flushed to a file in the spill directory and half of the sampled traces are dropped,
+/// with no more traced afterwards. Requests keep being flushed for as long as the pressure lasts.
+#[derive(Debug)]
+pub(crate) struct MemoryGuard {
+    ceiling: u64,
+    spill_dir: PathBuf,
+}
+
+impl MemoryGuard {
+    /// Guard a ceiling of `ceiling` bytes, flushing to `spill_dir` or the system temp directory
+    pub fn new(ceiling: u64, spill_dir: Option<PathBuf>) -> Self {
+        Self {
+            ceiling,
+            spill_dir: spill_dir.unwrap_or_else(std::env::temp_dir),
+        }
+    }
+
+    pub fn ceiling(&self) -> u64 {
+        self.ceiling
+    }
+
+    /// Check the resident memory until the run ends, shedding memory when it nears the ceiling
+    pub async fn watch(&self, metrics: &Mutex<TestMetrics>, started: Instant) {
+        if resident_memory().is_none() {
+            warn!(
+                "Resident memory cannot be measured on this platform, the memory ceiling is ignored"
+            );
+            return futures::future::pending().await;
+        }
+        let threshold = (self.ceiling as f64 * PRESSURE_THRESHOLD) as u64;
+        let mut interval = tokio::time::interval(MEMORY_CHECK_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut pressed = false;
+        loop {
+            interval.tick().await;
+            let Some(resident) = resident_memory() else {
+                continue;
+            };
+            let spill = {
+                let mut metrics = metrics.lock().await;
+                metrics.record_resident_memory(resident);
+                if resident < threshold {
+                    pressed = false;
+                    continue;
+                }
+                let spilled = metrics.spill_requests(&self.spill_dir);
+                // Traces are shed once per rise over the threshold, requests every time
+                if !pressed {
+                    pressed = true;
+                    let dropped = metrics.shrink_traces();
+                    let detail = format!(
+                        "{} MB resident of a {} MB ceiling, {} requests flushed to disk, {} \
+                         traces dropped",
+                        resident / 1_000_000,
+                        self.ceiling / 1_000_000,
+                        spilled,
+                        dropped
+                    );
+                    warn!("Memory pressure: {}", detail);
+                    metrics.record_memory_pressure(RunEvent {
+                        offset: started.elapsed(),
+                        kind: "memory_pressure".to_string(),
+                        detail,
+                    });
+                }
+                metrics.spilled_requests().cloned()
+            };
+            // Written once the metrics are unlocked, so requests keep being recorded meanwhile;
+            // requests that cannot be written stay queued for the next attempt
+            if let Some(spill) = spill {
+                let path = spill.path().to_path_buf();
+                if let Ok(Err(e)) = tokio::task::spawn_blocking(move || spill.flush()).await {
+                    warn!(
+                        "Failed to flush the recorded requests to {}: {}",
+                        path.display(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::RequestMetrics;
+    use crate::trace::TraceEntry;
+    use reqwest::StatusCode;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_resident_memory() {
+        if cfg!(target_os = "linux") {
+            assert!(resident_memory().unwrap() > 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_pressure_flushes_requests_and_shrinks_traces() {
+        if resident_memory().is_none() {
+            return;
+        }
+        let mut metrics = TestMetrics::new().unwrap();
+        metrics.mark_started(None);
+        metrics.record_individual_requests();
+        metrics.watch_memory(1);
+        for _ in 0..10 {
+            let request = RequestMetrics::new()
+                .complete(StatusCode::OK)
+                .with_trace(TraceEntry::new("GET", "https://example.com"));
+            metrics.record_request(request);
+        }
+        let metrics = Arc::new(Mutex::new(metrics));
+
+        // Any process is over a ceiling of one byte
+        let guard = MemoryGuard::new(1, None);
+        tokio::select! {
+            _ = guard.watch(&metrics, Instant::now()) => unreachable!(),
+            _ = tokio::time::sleep(MEMORY_CHECK_INTERVAL / 2) => {}
+        }
+        {
+            let mut metrics = metrics.lock().await;
+            assert!(metrics.request_log().is_empty());
+            assert_eq!(metrics.spilled_requests().unwrap().len(), 10);
+            assert_eq!(metrics.traces().len(), 5);
+            let usage = metrics.memory_usage().unwrap();
+            assert_eq!(usage.pressure_events, 1);
+            assert_eq!(usage.spilled_requests, 10);
+            assert_eq!(usage.dropped_traces, 5);
+            assert!(usage.peak_resident > 0);
+            assert_eq!(metrics.timeline()[0].kind, "memory_pressure");
+
+            // No more traces are kept, while requests are flushed for as long as the pressure lasts
+            let request = RequestMetrics::new()
+                .complete(StatusCode::OK)
+                .with_trace(TraceEntry::new("GET", "https://example.com"));
+            metrics.record_request(request);
+            assert_eq!(metrics.traces().len(), 5);
+            assert_eq!(metrics.memory_usage().unwrap().dropped_traces, 6);
+        }
+        tokio::select! {
+            _ = guard.watch(&metrics, Instant::now()) => unreachable!(),
+            _ = tokio::time::sleep(MEMORY_CHECK_INTERVAL / 2) => {}
+        }
+        let metrics = metrics.lock().await;
+        assert_eq!(metrics.spilled_requests().unwrap().len(), 11);
+        assert_eq!(metrics.memory_usage().unwrap().pressure_events, 2);
+    }
+}
@@ -0,0 +1,5 @@
+//! Exporters publishing the metrics of a running test to monitoring systems
+
+#[cfg(feature = "otlp")]
+pub mod otlp; // OpenTelemetry instruments exported over OTLP
+pub mod prometheus; // Prometheus text exposition format
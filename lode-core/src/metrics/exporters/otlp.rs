@@ -0,0 +1,165 @@
+use opentelemetry::metrics::{AsyncInstrument, Meter};
+use opentelemetry::KeyValue;
+use std::sync::Arc;
+
+use crate::live::MetricsSnapshot;
+use crate::metrics::Labels;
+
+/// Name of the meter the instruments are created with
+pub const METER_NAME: &str = "lode";
+
+/// Snapshots of the runs to report, identified by their labels (e.g. `run_id`)
+pub type SnapshotSource = Arc<dyn Fn() -> Vec<(Labels, MetricsSnapshot)> + Send + Sync>;
+
+/// An instrument reading one value of a snapshot
+struct Reading<T> {
+    name: &'static str,
+    description: &'static str,
+    unit: &'static str,
+    value: fn(&MetricsSnapshot) -> T,
+}
+
+const COUNTERS: [Reading<u64>; 2] = [
+    Reading {
+        name: "lode.requests",
+        description: "Requests completed by the load test",
+        unit: "{request}",
+        value: |s| s.total_requests,
+    },
+    Reading {
+        name: "lode.requests.failed",
+        description: "Requests that failed",
+        unit: "{request}",
+        value: |s| s.failed_requests,
+    },
+];
+
+const GAUGES: [Reading<f64>; 4] = [
+    Reading {
+        name: "lode.requests.rate",
+        description: "Requests per second since the previous snapshot",
+        unit: "{request}/s",
+        value: |s| s.current_rps,
+    },
+    Reading {
+        name: "lode.error_ratio",
+        description: "Share of failed requests since the start of the run",
+        unit: "1",
+        value: |s| s.error_rate / 100.0,
+    },
+    Reading {
+        name: "lode.response_time.p95",
+        description: "95th percentile of the response times",
+        unit: "s",
+        value: |s| s.p95_response_time_ms / 1000.0,
+    },
+    Reading {
+        name: "lode.elapsed",
+        description: "Time since the start of the run",
+        unit: "s",
+        value: |s| s.elapsed_seconds,
+    },
+];
+
+/// Meter of the global provider, installed by [`crate::telemetry::get_otlp_subscriber`]
+pub fn global_meter() -> Meter {
+    opentelemetry::global::meter(METER_NAME)
+}
+
+/// Report the snapshots returned by `source` through observable instruments of `meter`
+///
+/// The source is read each time the meter provider collects, so the export interval is the
+/// one of the provider.
+pub fn observe(meter: &Meter, source: SnapshotSource) {
+    for reading in COUNTERS {
+        let source = source.clone();
+        meter
+            .u64_observable_counter(reading.name)
+            .with_description(reading.description)
+            .with_unit(reading.unit)
+            .with_callback(move |observer| report(observer, &source, reading.value))
+            .build();
+    }
+    for reading in GAUGES {
+        let source = source.clone();
+        meter
+            .f64_observable_gauge(reading.name)
+            .with_description(reading.description)
+            .with_unit(reading.unit)
+            .with_callback(move |observer| report(observer, &source, reading.value))
+            .build();
+    }
+}
+
+fn report<T>(
+    observer: &dyn AsyncInstrument<T>,
+    source: &SnapshotSource,
+    value: fn(&MetricsSnapshot) -> T,
+) {
+    for (labels, snapshot) in source() {
+        let attributes: Vec<KeyValue> = labels
+            .into_iter()
+            .map(|(key, value)| KeyValue::new(key, value))
+            .collect();
+        observer.observe(value(&snapshot), &attributes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData};
+    use opentelemetry_sdk::metrics::{InMemoryMetricExporter, PeriodicReader, SdkMeterProvider};
+
+    #[test]
+    fn test_observe_snapshots() {
+        let exporter = InMemoryMetricExporter::default();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter.clone()).build())
+            .build();
+
+        let snapshot = MetricsSnapshot {
+            total_requests: 10,
+            failed_requests: 1,
+            error_rate: 10.0,
+            ..Default::default()
+        };
+        let labels = Labels::from([("run_id".to_string(), "a".to_string())]);
+        observe(
+            &provider.meter(METER_NAME),
+            Arc::new(move || vec![(labels.clone(), snapshot.clone())]),
+        );
+        provider.force_flush().unwrap();
+
+        let metrics = exporter.get_finished_metrics().unwrap();
+        let metrics: Vec<_> = metrics
+            .iter()
+            .flat_map(|resource| resource.scope_metrics())
+            .flat_map(|scope| scope.metrics())
+            .collect();
+
+        let requests = metrics
+            .iter()
+            .find(|metric| metric.name() == "lode.requests")
+            .unwrap();
+        let AggregatedMetrics::U64(MetricData::Sum(sum)) = requests.data() else {
+            panic!("lode.requests is not a sum: {:?}", requests.data());
+        };
+        let point = sum.data_points().next().unwrap();
+        assert_eq!(point.value(), 10);
+        assert_eq!(
+            point.attributes().next(),
+            Some(&KeyValue::new("run_id", "a"))
+        );
+
+        let ratio = metrics
+            .iter()
+            .find(|metric| metric.name() == "lode.error_ratio")
+            .unwrap();
+        let AggregatedMetrics::F64(MetricData::Gauge(gauge)) = ratio.data() else {
+            panic!("lode.error_ratio is not a gauge: {:?}", ratio.data());
+        };
+        assert_eq!(gauge.data_points().next().unwrap().value(), 0.1);
+    }
+}
@@ -0,0 +1,293 @@
+use std::fmt::Write;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+
+use crate::error::Result;
+use crate::live::MetricsSnapshot;
+use crate::metrics::Labels;
+
+/// Content type of the Prometheus text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Path the metrics are served at
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Largest scrape request read before answering
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// A counter or gauge read from a snapshot
+struct Scalar {
+    name: &'static str,
+    kind: &'static str,
+    help: &'static str,
+    value: fn(&MetricsSnapshot) -> f64,
+}
+
+const SCALARS: [Scalar; 6] = [
+    Scalar {
+        name: "lode_requests_total",
+        kind: "counter",
+        help: "Requests completed by the load test",
+        value: |s| s.total_requests as f64,
+    },
+    Scalar {
+        name: "lode_requests_failed_total",
+        kind: "counter",
+        help: "Requests that failed",
+        value: |s| s.failed_requests as f64,
+    },
+    Scalar {
+        name: "lode_requests_per_second",
+        kind: "gauge",
+        help: "Requests per second since the previous snapshot",
+        value: |s| s.current_rps,
+    },
+    Scalar {
+        name: "lode_error_ratio",
+        kind: "gauge",
+        help: "Share of failed requests since the start of the run",
+        value: |s| s.error_rate / 100.0,
+    },
+    Scalar {
+        name: "lode_elapsed_seconds",
+        kind: "gauge",
+        help: "Time since the start of the run",
+        value: |s| s.elapsed_seconds,
+    },
+    Scalar {
+        name: "lode_run_finished",
+        kind: "gauge",
+        help: "1 once the run has finished",
+        value: |s| if s.finished { 1.0 } else { 0.0 },
+    },
+];
+
+/// Render snapshots of one or more runs in the Prometheus text exposition format
+///
+/// Each snapshot becomes one series per metric, identified by its labels (e.g. `run_id`).
+pub fn render<'a>(series: impl IntoIterator<Item = (&'a Labels, &'a MetricsSnapshot)>) -> String {
+    let series: Vec<_> = series.into_iter().collect();
+    let mut out = String::new();
+
+    for metric in &SCALARS {
+        header(&mut out, metric.name, metric.kind, metric.help);
+        for (labels, snapshot) in &series {
+            sample(
+                &mut out,
+                metric.name,
+                labels,
+                None,
+                (metric.value)(snapshot),
+            );
+        }
+    }
+
+    let name = "lode_response_time_seconds";
+    header(
+        &mut out,
+        name,
+        "histogram",
+        "Response times of the requests",
+    );
+    for (labels, snapshot) in &series {
+        let histogram = &snapshot.response_times;
+        let bucket = format!("{}_bucket", name);
+        for (bound, count) in &histogram.buckets {
+            let le = bound.to_string();
+            sample(&mut out, &bucket, labels, Some(&le), *count as f64);
+        }
+        sample(
+            &mut out,
+            &bucket,
+            labels,
+            Some("+Inf"),
+            histogram.count as f64,
+        );
+        sample(
+            &mut out,
+            &format!("{}_sum", name),
+            labels,
+            None,
+            histogram.sum_seconds,
+        );
+        sample(
+            &mut out,
+            &format!("{}_count", name),
+            labels,
+            None,
+            histogram.count as f64,
+        );
+    }
+
+    out
+}
+
+/// Serve the latest snapshot at [`METRICS_PATH`] until the future is dropped
+pub async fn serve(
+    listener: TcpListener,
+    snapshots: watch::Receiver<MetricsSnapshot>,
+) -> Result<()> {
+    info!(
+        "Serving Prometheus metrics on http://{}{}",
+        listener.local_addr()?,
+        METRICS_PATH
+    );
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let snapshots = snapshots.clone();
+        tokio::spawn(async move {
+            if let Err(e) = respond(stream, &snapshots).await {
+                warn!("Failed to answer metrics scrape from {}: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn respond(
+    mut stream: TcpStream,
+    snapshots: &watch::Receiver<MetricsSnapshot>,
+) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST_BYTES {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let (method, path) = (request_line.next(), request_line.next());
+    debug!("Metrics scrape: {:?} {:?}", method, path);
+
+    let (status, content_type, body) = match (method, path) {
+        (Some("GET"), Some(METRICS_PATH)) => {
+            let snapshot = snapshots.borrow().clone();
+            (
+                "200 OK",
+                CONTENT_TYPE,
+                render([(&Labels::new(), &snapshot)]),
+            )
+        }
+        _ => ("404 Not Found", "text/plain", "Not Found\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, labels: &Labels, le: Option<&str>, value: f64) {
+    let mut pairs: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, escape(value)))
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+
+    if pairs.is_empty() {
+        let _ = writeln!(out, "{} {}", name, value);
+    } else {
+        let _ = writeln!(out, "{}{{{}}} {}", name, pairs.join(","), value);
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::live::ResponseTimeHistogram;
+
+    fn snapshot() -> MetricsSnapshot {
+        MetricsSnapshot {
+            elapsed_seconds: 2.5,
+            total_requests: 10,
+            successful_requests: 9,
+            failed_requests: 1,
+            current_rps: 4.0,
+            p95_response_time_ms: 120.0,
+            error_rate: 10.0,
+            finished: false,
+            response_times: ResponseTimeHistogram {
+                buckets: vec![(0.1, 7), (0.25, 10)],
+                sum_seconds: 0.9,
+                count: 10,
+            },
+        }
+    }
+
+    #[test]
+    fn test_render_exposition_format() {
+        let snapshot = snapshot();
+        let text = render([(&Labels::new(), &snapshot)]);
+
+        assert!(text.contains("# TYPE lode_requests_total counter\nlode_requests_total 10\n"));
+        assert!(text.contains("lode_requests_failed_total 1\n"));
+        assert!(text.contains("lode_error_ratio 0.1\n"));
+        assert!(text.contains("lode_run_finished 0\n"));
+        assert!(text.contains("# TYPE lode_response_time_seconds histogram\n"));
+        assert!(text.contains("lode_response_time_seconds_bucket{le=\"0.1\"} 7\n"));
+        assert!(text.contains("lode_response_time_seconds_bucket{le=\"+Inf\"} 10\n"));
+        assert!(text.contains("lode_response_time_seconds_sum 0.9\n"));
+        assert!(text.contains("lode_response_time_seconds_count 10\n"));
+    }
+
+    #[test]
+    fn test_render_labelled_series() {
+        let snapshot = snapshot();
+        let a = Labels::from([("run_id".to_string(), "a".to_string())]);
+        let b = Labels::from([("run_id".to_string(), "b\"1".to_string())]);
+        let text = render([(&a, &snapshot), (&b, &snapshot)]);
+
+        assert_eq!(text.matches("# TYPE lode_requests_total").count(), 1);
+        assert!(text.contains("lode_requests_total{run_id=\"a\"} 10\n"));
+        assert!(text.contains("lode_requests_total{run_id=\"b\\\"1\"} 10\n"));
+        assert!(text.contains("lode_response_time_seconds_bucket{run_id=\"a\",le=\"0.25\"} 10\n"));
+    }
+
+    #[tokio::test]
+    async fn test_serve_metrics() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (_sender, receiver) = watch::channel(snapshot());
+        let server = tokio::spawn(serve(listener, receiver));
+
+        let response = reqwest::get(format!("http://{}{}", address, METRICS_PATH))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], CONTENT_TYPE);
+        assert!(response
+            .text()
+            .await
+            .unwrap()
+            .contains("lode_requests_total 10\n"));
+
+        let response = reqwest::get(format!("http://{}/other", address))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+        server.abort();
+    }
+}
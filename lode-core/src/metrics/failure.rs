@@ -0,0 +1,174 @@
+use reqwest::Error as ReqwestError;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::error::Error as _;
+use std::fmt;
+use std::str::FromStr;
+
+/// Why a request failed, keyed in reports by a stable name such as `http_503` or `timeout`
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(try_from = "String", into = "String")]
+#[schemars(with = "String")]
+pub enum ErrorKind {
+    /// Response with a status code not counted as success, named after it, e.g. `http_404`
+    Http(u16),
+    /// No response within the timeout
+    Timeout,
+    /// Host name resolution of a new connection failed
+    Dns,
+    /// TLS handshake of a new HTTPS connection failed
+    Tls,
+    /// TCP connect of a new connection failed
+    Connect,
+    /// Sending the request or reading the response body failed
+    Body,
+    /// The response did not hold a value the scenario expected, e.g. for an extractor
+    Assertion,
+    /// The response of a GraphQL operation held `errors`, whatever its status
+    Graphql,
+    /// Any other failure
+    Other,
+}
+
+impl ErrorKind {
+    /// Classify a failed request of reqwest
+    pub fn from_error(error: &ReqwestError) -> Self {
+        if error.is_timeout() {
+            ErrorKind::Timeout
+        } else if let Some(status) = error.status() {
+            ErrorKind::Http(status.as_u16())
+        } else if error.is_connect() {
+            Self::from_connect_error(error)
+        } else if error.is_request() || error.is_body() || error.is_decode() {
+            ErrorKind::Body
+        } else {
+            ErrorKind::Other
+        }
+    }
+
+    /// hyper-util names the step of a failed plain connection, the TLS connector does not
+    fn from_connect_error(error: &ReqwestError) -> Self {
+        let mut source = error.source();
+        while let Some(cause) = source {
+            let message = cause.to_string();
+            if message == "dns error" {
+                return ErrorKind::Dns;
+            }
+            if message.starts_with("tcp ") {
+                return ErrorKind::Connect;
+            }
+            source = cause.source();
+        }
+        match error.url() {
+            Some(url) if url.scheme() == "https" => ErrorKind::Tls,
+            _ => ErrorKind::Connect,
+        }
+    }
+}
+
+impl FromStr for ErrorKind {
+    type Err = String;
+
+    /// Parse a stable name, or the free-form names of reports before schema version 2
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let kind = match s {
+            "timeout" | "Timeout" => ErrorKind::Timeout,
+            "dns" => ErrorKind::Dns,
+            "tls" => ErrorKind::Tls,
+            "connect" => ErrorKind::Connect,
+            "body" | "Download Error" => ErrorKind::Body,
+            "assertion" | "Extraction Error" => ErrorKind::Assertion,
+            "graphql" => ErrorKind::Graphql,
+            "other" | "Unknown Error" => ErrorKind::Other,
+            _ => s
+                .strip_prefix("http_")
+                .or_else(|| s.strip_prefix("HTTP "))
+                .filter(|code| code.len() == 3)
+                .and_then(|code| code.parse().ok())
+                .map(ErrorKind::Http)
+                .ok_or_else(|| format!("Unknown error kind: {}", s))?,
+        };
+        Ok(kind)
+    }
+}
+
+impl TryFrom<String> for ErrorKind {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ErrorKind> for String {
+    fn from(kind: ErrorKind) -> Self {
+        kind.to_string()
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Http(code) => write!(f, "http_{}", code),
+            ErrorKind::Timeout => write!(f, "timeout"),
+            ErrorKind::Dns => write!(f, "dns"),
+            ErrorKind::Tls => write!(f, "tls"),
+            ErrorKind::Connect => write!(f, "connect"),
+            ErrorKind::Body => write!(f, "body"),
+            ErrorKind::Assertion => write!(f, "assertion"),
+            ErrorKind::Graphql => write!(f, "graphql"),
+            ErrorKind::Other => write!(f, "other"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kind_names_round_trip() {
+        for kind in [
+            ErrorKind::Http(404),
+            ErrorKind::Http(503),
+            ErrorKind::Timeout,
+            ErrorKind::Dns,
+            ErrorKind::Tls,
+            ErrorKind::Connect,
+            ErrorKind::Body,
+            ErrorKind::Assertion,
+            ErrorKind::Graphql,
+            ErrorKind::Other,
+        ] {
+            assert_eq!(kind.to_string().parse::<ErrorKind>(), Ok(kind));
+        }
+        assert!("http_5xx".parse::<ErrorKind>().is_err());
+        assert!("refused".parse::<ErrorKind>().is_err());
+    }
+
+    #[test]
+    fn test_error_kind_parses_legacy_names() {
+        assert_eq!("HTTP 429".parse(), Ok(ErrorKind::Http(429)));
+        assert_eq!("Timeout".parse(), Ok(ErrorKind::Timeout));
+        assert_eq!("Extraction Error".parse(), Ok(ErrorKind::Assertion));
+        assert_eq!("Download Error".parse(), Ok(ErrorKind::Body));
+        assert_eq!("Unknown Error".parse(), Ok(ErrorKind::Other));
+    }
+
+    #[tokio::test]
+    async fn test_error_kind_of_failed_connections() {
+        let client = reqwest::Client::new();
+
+        let error = client.get("http://127.0.0.1:1").send().await.unwrap_err();
+        assert_eq!(ErrorKind::from_error(&error), ErrorKind::Connect);
+
+        let error = client
+            .get("http://lode-test.invalid")
+            .send()
+            .await
+            .unwrap_err();
+        assert_eq!(ErrorKind::from_error(&error), ErrorKind::Dns);
+    }
+}
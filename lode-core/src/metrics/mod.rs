@@ -0,0 +1,1965 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use hdrhistogram::Histogram;
+use reqwest::{Error as ReqwestError, StatusCode, Version};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, instrument, warn};
+
+use crate::clock::ClockOffset;
+use crate::cooldown::CooldownMetrics;
+use crate::memory::MemoryUsage;
+use crate::scenario::ENDPOINT_LABEL;
+use crate::trace::TraceEntry;
+
+pub mod exporters;
+pub mod failure;
+pub mod spill;
+pub mod status;
+
+pub use failure::ErrorKind;
+pub use spill::RequestSpill;
+pub use status::SuccessCodes;
+
+/// Number of slowest raw response times kept exactly
+///
+/// The histogram rounds to 3 significant digits; the reservoir keeps the worst outliers as measured,
+/// which covers the top 0.1% of runs up to a million requests.
+pub const TAIL_SAMPLE_CAPACITY: usize = 1000;
+
+/// Labels attached to a request, e.g. step name, variant or region
+pub type Labels = BTreeMap<String, String>;
+
+/// Requests completed during one interval of a run
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeBucket {
+    /// Start of the interval since the start of the run
+    pub start: Duration,
+    pub total_requests: u64,
+    pub failed_requests: u64,
+    pub mean_response_time: Duration,
+    pub median_response_time: Duration,
+    pub p95_response_time: Duration,
+    pub p99_response_time: Duration,
+}
+
+impl TimeBucket {
+    fn from_metrics(start: Duration, metrics: &GroupMetrics) -> Self {
+        Self {
+            start,
+            total_requests: metrics.total_requests(),
+            failed_requests: metrics.failed_requests(),
+            mean_response_time: metrics.mean_response_time(),
+            median_response_time: metrics.percentile_response_time(50.0),
+            p95_response_time: metrics.percentile_response_time(95.0),
+            p99_response_time: metrics.percentile_response_time(99.0),
+        }
+    }
+}
+
+/// Something that happened to the run itself, noted on its timeline
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunEvent {
+    /// When it happened since the start of the run
+    pub offset: Duration,
+    /// What happened, e.g. `client_cert_rotated`
+    pub kind: String,
+    pub detail: String,
+}
+
+/// Requests completed during one stage of a staged run
+#[derive(Debug, Clone)]
+pub struct StageMetrics {
+    /// Stage in its normalized form, e.g. `ramp:60s:50`
+    pub name: String,
+    /// Start of the stage since the start of the run
+    pub start: Duration,
+    /// End of the stage since the start of the run, unset until the next stage or the end of the run
+    pub end: Option<Duration>,
+    pub metrics: GroupMetrics,
+}
+
+/// Label holding the region of the agent that sent a request
+pub const REGION_LABEL: &str = "region";
+
+/// Label holding the HTTP version a request was sent with, in breakdowns by protocol
+pub const PROTOCOL_LABEL: &str = "protocol";
+
+/// Name of an HTTP version in reports, e.g. `HTTP/2`
+pub fn protocol_name(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_09 => "HTTP/0.9",
+        Version::HTTP_10 => "HTTP/1.0",
+        Version::HTTP_11 => "HTTP/1.1",
+        Version::HTTP_2 => "HTTP/2",
+        Version::HTTP_3 => "HTTP/3",
+        _ => "unknown",
+    }
+}
+
+/// Phase of a request, timed on its own to tell where the response time goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Phase {
+    /// Wait in the generator past the time the request was due, e.g. its rate limiter slot, which
+    /// is part of the response time only in the open load models
+    Queue,
+    /// Host name resolution of a new connection
+    Dns,
+    /// TCP connect of a new plain HTTP connection
+    Connect,
+    /// TCP connect and TLS handshake of a new HTTPS connection, which reqwest performs in one step
+    Tls,
+    /// Wait for the response headers once connected
+    Ttfb,
+    /// Download of the response body
+    Download,
+}
+
+impl Phase {
+    /// Name of the phase in reports
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Queue => "queue",
+            Phase::Dns => "dns",
+            Phase::Connect => "connect",
+            Phase::Tls => "tls",
+            Phase::Ttfb => "ttfb",
+            Phase::Download => "download",
+        }
+    }
+}
+
+/// Outcome of one request, kept when individual requests are recorded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestRecord {
+    /// When the request was sent, on the reference clock when a clock offset is known
+    pub timestamp: DateTime<Utc>,
+    /// Response time, `None` when the request got no response
+    pub duration: Option<Duration>,
+    /// HTTP status code of the response
+    pub status: Option<u16>,
+    /// Why the request failed
+    pub error: Option<String>,
+}
+
+/// Metrics for a single request
+#[derive(Debug)]
+pub struct RequestMetrics {
+    start_time: Instant,
+    duration: Option<Duration>,
+    status: Option<StatusCode>,
+    error: Option<ReqwestError>,
+    /// Kind and message of a request that failed despite its response, e.g. a missing value
+    failure: Option<(ErrorKind, String)>,
+    labels: Labels,
+    /// Local address of the connection the request was sent on, when known
+    connection: Option<SocketAddr>,
+    /// Whether the TLS handshake of the connection the request opened resumed a session, when
+    /// the client tells
+    tls_resumed: Option<bool>,
+    /// Delay the server asked for in a `Retry-After` header of a 429, 502 or 503 response
+    retry_after: Option<Duration>,
+    /// Time spent in the phases of the request that were timed
+    phases: BTreeMap<Phase, Duration>,
+    /// HTTP version of the response
+    version: Option<Version>,
+    /// Attempts it took to send the request, more than one when it was retried
+    attempts: u32,
+    /// Whether the body was cut at the max body size instead of read to its end
+    truncated_body: bool,
+    /// Bytes of the request line, headers and body sent
+    bytes_sent: u64,
+    /// Bytes of the status line, headers and body received
+    bytes_received: u64,
+    /// When the rate had the request start, which may be before it was sent
+    due: Option<Instant>,
+    /// Status codes counted as success instead of any 2xx
+    success_codes: Option<Arc<SuccessCodes>>,
+    /// Trace of the request when it was sampled
+    trace: Option<Box<TraceEntry>>,
+}
+
+impl RequestMetrics {
+    /// Create new request metrics
+    #[instrument(skip_all)]
+    pub fn new() -> Self {
+        Self {
+            start_time: Instant::now(),
+            duration: None,
+            status: None,
+            error: None,
+            failure: None,
+            labels: Labels::new(),
+            connection: None,
+            tls_resumed: None,
+            retry_after: None,
+            phases: BTreeMap::new(),
+            version: None,
+            attempts: 1,
+            truncated_body: false,
+            bytes_sent: 0,
+            bytes_received: 0,
+            due: None,
+            success_codes: None,
+            trace: None,
+        }
+    }
+
+    /// Count responses with these status codes as successful instead of any 2xx
+    pub fn with_success_codes(mut self, success_codes: Arc<SuccessCodes>) -> Self {
+        self.success_codes = Some(success_codes);
+        self
+    }
+
+    /// Attach labels to the request
+    pub fn with_labels(mut self, labels: Labels) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Record the local address of the connection the request was sent on
+    pub fn with_connection(mut self, connection: Option<SocketAddr>) -> Self {
+        self.connection = connection;
+        self
+    }
+
+    /// Record whether the TLS handshake of the connection the request opened resumed a session
+    pub fn with_tls_resumption(mut self, resumed: Option<bool>) -> Self {
+        self.tls_resumed = resumed;
+        self
+    }
+
+    /// Attach the trace of a sampled request
+    pub fn with_trace(mut self, trace: TraceEntry) -> Self {
+        self.trace = Some(Box::new(trace));
+        self
+    }
+
+    /// Record the delay the server asked for before the next request
+    pub fn with_retry_after(mut self, retry_after: Option<Duration>) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Record the time spent in a phase of the request, when it went through it
+    pub fn with_phase(mut self, phase: Phase, duration: Option<Duration>) -> Self {
+        if let Some(duration) = duration {
+            self.phases.insert(phase, duration);
+        }
+        self
+    }
+
+    /// Record the HTTP version the response came in with
+    pub fn with_version(mut self, version: Version) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Record the attempts it took to send the request, when it was retried
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// Record that the body was cut at the max body size
+    pub fn with_truncated_body(mut self, truncated_body: bool) -> Self {
+        self.truncated_body = truncated_body;
+        self
+    }
+
+    /// Record the bytes of the request sent and of its response received
+    pub fn with_bytes(mut self, sent: u64, received: u64) -> Self {
+        self.bytes_sent = sent;
+        self.bytes_received = received;
+        self
+    }
+
+    /// Record when the request was due to start, leaving the wait of a late request out of its
+    /// response time
+    pub fn with_due(mut self, due: Instant) -> Self {
+        self.due = Some(due);
+        self
+    }
+
+    /// Count the response time from when the request was due to start rather than when it was
+    /// sent, so the wait of a late request is part of it
+    pub fn with_intended_start(mut self, due: Instant) -> Self {
+        let late = self.start_time.saturating_duration_since(due);
+        self.start_time -= late;
+        self.duration = self.duration.map(|duration| duration + late);
+        self.with_due(due)
+    }
+
+    /// Complete the request with a status code
+    #[instrument(skip(self))]
+    pub fn complete(mut self, status: StatusCode) -> Self {
+        if self.duration.is_none() {
+            self.duration = Some(self.start_time.elapsed());
+        }
+        self.status = Some(status);
+        self
+    }
+
+    /// Record a reqwest error
+    #[instrument(skip(self))]
+    pub fn record_error(mut self, error: ReqwestError) -> Self {
+        if self.duration.is_none() {
+            self.duration = Some(self.start_time.elapsed());
+        }
+        self.error = Some(error);
+        self
+    }
+
+    /// Fail a request that got no response within `timeout`
+    pub fn time_out(mut self, timeout: Duration) -> Self {
+        if self.duration.is_none() {
+            self.duration = Some(self.start_time.elapsed());
+        }
+        self.fail(
+            ErrorKind::Timeout,
+            format!("No response within {:?}", timeout),
+        )
+    }
+
+    /// Mark a completed request as failed, with the kind of failure and a message
+    pub fn fail(mut self, kind: ErrorKind, message: impl Into<String>) -> Self {
+        self.failure = Some((kind, message.into()));
+        self
+    }
+
+    /// Get the start time
+    pub fn start_time(&self) -> Instant {
+        self.start_time
+    }
+
+    /// Get the duration
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    /// Get the status code
+    pub fn status(&self) -> Option<StatusCode> {
+        self.status
+    }
+
+    /// Get the error
+    pub fn error(&self) -> Option<&ReqwestError> {
+        self.error.as_ref()
+    }
+
+    /// Get the kind of failure and message the request was failed with
+    pub fn failure(&self) -> Option<(ErrorKind, &str)> {
+        self.failure
+            .as_ref()
+            .map(|(kind, message)| (*kind, message.as_str()))
+    }
+
+    /// Get the labels
+    pub fn labels(&self) -> &Labels {
+        &self.labels
+    }
+
+    /// Get the local address of the connection the request was sent on
+    pub fn connection(&self) -> Option<SocketAddr> {
+        self.connection
+    }
+
+    /// Get the delay the server asked for before the next request
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after
+    }
+
+    /// Get the time spent in the phases of the request that were timed
+    pub fn phases(&self) -> &BTreeMap<Phase, Duration> {
+        &self.phases
+    }
+
+    /// Get the HTTP version the response came in with
+    pub fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    /// Get the attempts it took to send the request
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Whether the body was cut at the max body size
+    pub fn truncated_body(&self) -> bool {
+        self.truncated_body
+    }
+
+    /// Get the bytes of the request sent
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    /// Get the bytes of the response received
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// Get when the request was due to start, when recorded
+    pub fn due(&self) -> Option<Instant> {
+        self.due
+    }
+
+    /// Get the time from when the request was due to start until its response, the response time
+    /// a user waiting on the schedule would see
+    pub fn intended_duration(&self) -> Option<Duration> {
+        let late = self.due.map_or(Duration::ZERO, |due| {
+            self.start_time.saturating_duration_since(due)
+        });
+        self.duration.map(|duration| duration + late)
+    }
+
+    /// Get the kind of failure of the request, `None` when it succeeded
+    pub fn error_kind(&self) -> Option<ErrorKind> {
+        match (self.failure(), self.status, &self.error) {
+            (Some((kind, _)), _, _) => Some(kind),
+            (None, Some(status), None) if self.is_success_status(status) => None,
+            (None, Some(status), None) => Some(ErrorKind::Http(status.as_u16())),
+            (None, None, Some(error)) => Some(ErrorKind::from_error(error)),
+            _ => Some(ErrorKind::Other),
+        }
+    }
+
+    /// Whether the request completed with a successful status
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+            && self.failure.is_none()
+            && self
+                .status
+                .is_some_and(|status| self.is_success_status(status))
+    }
+
+    /// Whether a response with this status counts as successful
+    fn is_success_status(&self, status: StatusCode) -> bool {
+        match &self.success_codes {
+            Some(codes) => codes.contains(status.as_u16()),
+            None => status.is_success(),
+        }
+    }
+}
+
+/// Metrics aggregated over the requests sharing a set of labels
+#[derive(Debug, Clone)]
+pub struct GroupMetrics {
+    total_requests: u64,
+    successful_requests: u64,
+    failed_requests: u64,
+    response_times: Histogram<u64>,
+}
+
+impl GroupMetrics {
+    fn new() -> Self {
+        Self {
+            total_requests: 0,
+            successful_requests: 0,
+            failed_requests: 0,
+            response_times: Histogram::new_with_bounds(1, 60_000_000, 3)
+                .expect("histogram bounds are valid"),
+        }
+    }
+
+    fn record(&mut self, metrics: &RequestMetrics) {
+        self.total_requests += 1;
+        if metrics.is_success() {
+            self.successful_requests += 1;
+        } else {
+            self.failed_requests += 1;
+        }
+        if let Some(duration) = metrics.duration {
+            if duration.as_micros() >= 1 {
+                let _ = self.response_times.record(duration.as_micros() as u64);
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &GroupMetrics) {
+        self.total_requests += other.total_requests;
+        self.successful_requests += other.successful_requests;
+        self.failed_requests += other.failed_requests;
+        let _ = self.response_times.add(&other.response_times);
+    }
+
+    /// Get total number of requests
+    pub fn total_requests(&self) -> u64 {
+        self.total_requests
+    }
+
+    /// Get number of successful requests
+    pub fn successful_requests(&self) -> u64 {
+        self.successful_requests
+    }
+
+    /// Get number of failed requests
+    pub fn failed_requests(&self) -> u64 {
+        self.failed_requests
+    }
+
+    /// Get mean response time
+    pub fn mean_response_time(&self) -> Duration {
+        Duration::from_micros(self.response_times.mean() as u64)
+    }
+
+    /// Get the response time at the given percentile
+    pub fn percentile_response_time(&self, percentile: f64) -> Duration {
+        Duration::from_micros(self.response_times.value_at_percentile(percentile))
+    }
+}
+
+impl Default for RequestMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Metrics for a load test
+#[derive(Debug)]
+pub struct TestMetrics {
+    total_requests: u64,
+    successful_requests: u64,
+    failed_requests: u64,
+    total_duration: Duration,
+    /// Start of the run on the reference clock, or the local clock without an offset
+    started_at: Option<DateTime<Utc>>,
+    clock_offset: Option<ClockOffset>,
+    /// Set when the run was stopped before sending all its requests
+    cancelled: bool,
+    /// Set when the run was stopped by its overall deadline
+    deadline_exceeded: bool,
+    /// Abort rule that ended the run early
+    aborted: Option<String>,
+    /// Requests not sent because their data was missing, by reason
+    skipped: BTreeMap<String, u64>,
+    /// Set when the process panicked during the run
+    crashed: bool,
+    /// Local instant matching `started_at`
+    started_instant: Option<Instant>,
+    /// Every request in completion order, only when enabled
+    request_log: Option<Vec<RequestRecord>>,
+    /// Recorded requests flushed to disk under memory pressure, before those of the log
+    spilled_requests: Option<Arc<RequestSpill>>,
+    /// Traces of the sampled requests, in the order they were recorded
+    traces: Vec<TraceEntry>,
+    /// Traces kept at most once memory pressure shrank them
+    trace_limit: Option<usize>,
+    /// Resident memory of the generator, when kept under a ceiling
+    memory: Option<MemoryUsage>,
+    /// Extra percentiles to report
+    percentiles: Vec<f64>,
+    /// Region of the agent running the test
+    region: Option<String>,
+    /// Length of the time series buckets, only when enabled
+    time_series_interval: Option<Duration>,
+    /// Summaries of the completed time series buckets
+    time_buckets: Vec<TimeBucket>,
+    /// Index and metrics of the bucket being filled
+    current_bucket: Option<(u64, GroupMetrics)>,
+    /// Stages started so far, the current one last
+    stages: Vec<StageMetrics>,
+    /// Probes of the cooldown after the load, when one was observed
+    cooldown: Option<CooldownMetrics>,
+    requests_per_second: f64,
+    /// Iterations the virtual users completed, cut short by a failed request or not
+    iterations: u64,
+    iterations_per_second: f64,
+    /// Virtual users that ran the iterations
+    virtual_users: u64,
+    /// Configured share of the requests per endpoint of a weighted scenario, in percent
+    mix: BTreeMap<String, f64>,
+    /// Events of the run in the order they happened
+    timeline: Vec<RunEvent>,
+    response_times: Histogram<u64>,
+    error_counts: HashMap<ErrorKind, u64>,
+    error_messages: Vec<String>,
+    groups: HashMap<Labels, GroupMetrics>,
+    /// Slowest raw response times, smallest on top
+    tail_samples: BinaryHeap<Reverse<Duration>>,
+    /// Number of requests with a measured response time
+    timed_requests: u64,
+    /// Local addresses of the connections requests were sent on
+    connections: HashSet<SocketAddr>,
+    /// Requests sent on a connection an earlier request had opened
+    reused_connection_requests: u64,
+    /// TLS handshakes the client told about, and how many of them resumed a session
+    tls_handshakes: u64,
+    resumed_tls_handshakes: u64,
+    /// Times a virtual user backed off as a server asked in `Retry-After`
+    backoffs: u64,
+    /// Time spent backing off
+    backoff_time: Duration,
+    /// Requests sent more than once
+    retried_requests: u64,
+    /// Attempts sent again after a failed one, over all requests
+    retries: u64,
+    /// Requests that succeeded after at least one retry
+    retried_successes: u64,
+    /// Responses whose body was cut at the max body size
+    truncated_bodies: u64,
+    /// Responses per HTTP status code, whether they count as successes or failures
+    status_codes: BTreeMap<u16, u64>,
+    /// Bytes of all requests sent and of all responses received
+    bytes_sent: u64,
+    bytes_received: u64,
+    /// Status codes counted as success for the requests recorded without their own
+    success_codes: Option<Arc<SuccessCodes>>,
+    /// Time spent in each phase, in microseconds, by the requests that went through it
+    phase_times: BTreeMap<Phase, Histogram<u64>>,
+    /// Response times from the intended starts corrected for coordinated omission, only when
+    /// enabled, with the interval each virtual user was expected to send its requests at
+    corrected_response_times: Option<(Histogram<u64>, Option<Duration>)>,
+    /// Metrics per HTTP version of the responses
+    protocols: BTreeMap<&'static str, GroupMetrics>,
+    log_batch_size: u64,
+    last_batch_log: Instant,
+}
+
+impl TestMetrics {
+    /// Create new test metrics
+    #[instrument(skip_all)]
+    pub fn new() -> Result<Self, String> {
+        info!("Creating new test metrics");
+        Ok(Self {
+            total_requests: 0,
+            successful_requests: 0,
+            failed_requests: 0,
+            total_duration: Duration::from_secs(0),
+            started_at: None,
+            clock_offset: None,
+            cancelled: false,
+            deadline_exceeded: false,
+            aborted: None,
+            skipped: BTreeMap::new(),
+            crashed: false,
+            started_instant: None,
+            request_log: None,
+            spilled_requests: None,
+            traces: Vec::new(),
+            trace_limit: None,
+            memory: None,
+            percentiles: Vec::new(),
+            region: None,
+            time_series_interval: None,
+            time_buckets: Vec::new(),
+            current_bucket: None,
+            stages: Vec::new(),
+            cooldown: None,
+            requests_per_second: 0.0,
+            iterations: 0,
+            iterations_per_second: 0.0,
+            virtual_users: 0,
+            mix: BTreeMap::new(),
+            timeline: Vec::new(),
+            response_times: Histogram::new_with_bounds(1, 60_000_000, 3)
+                .map_err(|e| e.to_string())?,
+            error_counts: HashMap::new(),
+            error_messages: Vec::new(),
+            groups: HashMap::new(),
+            tail_samples: BinaryHeap::with_capacity(TAIL_SAMPLE_CAPACITY + 1),
+            timed_requests: 0,
+            connections: HashSet::new(),
+            reused_connection_requests: 0,
+            tls_handshakes: 0,
+            resumed_tls_handshakes: 0,
+            backoffs: 0,
+            backoff_time: Duration::ZERO,
+            retried_requests: 0,
+            retries: 0,
+            retried_successes: 0,
+            truncated_bodies: 0,
+            status_codes: BTreeMap::new(),
+            bytes_sent: 0,
+            bytes_received: 0,
+            success_codes: None,
+            phase_times: BTreeMap::new(),
+            corrected_response_times: None,
+            protocols: BTreeMap::new(),
+            log_batch_size: 100,
+            last_batch_log: Instant::now(),
+        })
+    }
+
+    /// Record a request
+    #[instrument(skip(self, metrics))]
+    pub fn record_request(&mut self, mut metrics: RequestMetrics) {
+        if let Some(trace) = metrics.trace.take() {
+            match (self.trace_limit, &mut self.memory) {
+                (Some(limit), Some(memory)) if self.traces.len() >= limit => {
+                    memory.dropped_traces += 1;
+                }
+                _ => self.traces.push(*trace),
+            }
+        }
+        let metrics = match &self.success_codes {
+            Some(codes) if metrics.success_codes.is_none() => {
+                metrics.with_success_codes(Arc::clone(codes))
+            }
+            _ => metrics,
+        };
+        self.total_requests += 1;
+
+        if self.request_log.is_some() {
+            let record = self.request_record(&metrics);
+            if let Some(log) = &mut self.request_log {
+                log.push(record);
+            }
+        }
+
+        if self.time_series_interval.is_some() {
+            self.record_time_bucket(&metrics);
+        }
+
+        if let Some(stage) = self.stages.last_mut() {
+            stage.metrics.record(&metrics);
+        }
+
+        if let Some(connection) = metrics.connection {
+            if !self.connections.insert(connection) {
+                self.reused_connection_requests += 1;
+            }
+        }
+
+        if let Some(resumed) = metrics.tls_resumed {
+            self.tls_handshakes += 1;
+            if resumed {
+                self.resumed_tls_handshakes += 1;
+            }
+        }
+
+        if metrics.attempts > 1 {
+            self.retried_requests += 1;
+            self.retries += u64::from(metrics.attempts - 1);
+            if metrics.is_success() {
+                self.retried_successes += 1;
+            }
+        }
+
+        if metrics.truncated_body {
+            self.truncated_bodies += 1;
+        }
+
+        if let Some(status) = metrics.status {
+            *self.status_codes.entry(status.as_u16()).or_insert(0) += 1;
+        }
+        self.bytes_sent += metrics.bytes_sent;
+        self.bytes_received += metrics.bytes_received;
+
+        for (phase, duration) in &metrics.phases {
+            let _ = self
+                .phase_times
+                .entry(*phase)
+                .or_insert_with(|| {
+                    Histogram::new_with_bounds(1, 60_000_000, 3)
+                        .expect("histogram bounds are valid")
+                })
+                .record((duration.as_micros() as u64).max(1));
+        }
+
+        if let Some(version) = metrics.version {
+            self.protocols
+                .entry(protocol_name(version))
+                .or_insert_with(GroupMetrics::new)
+                .record(&metrics);
+        }
+
+        if !metrics.labels.is_empty() {
+            self.groups
+                .entry(metrics.labels.clone())
+                .or_insert_with(GroupMetrics::new)
+                .record(&metrics);
+        }
+
+        if let Some(duration) = metrics.duration {
+            if duration.as_micros() >= 1 {
+                let duration_us = duration.as_micros() as u64;
+                let _ = self.response_times.record(duration_us);
+            }
+            self.record_tail_sample(duration);
+        }
+
+        if let (Some((histogram, interval)), Some(duration)) = (
+            &mut self.corrected_response_times,
+            metrics.intended_duration(),
+        ) {
+            let duration_us = (duration.as_micros() as u64).max(1);
+            let _ = match interval {
+                Some(interval) => {
+                    histogram.record_correct(duration_us, (interval.as_micros() as u64).max(1))
+                }
+                None => histogram.record(duration_us),
+            };
+        }
+
+        match (metrics.status(), metrics.error()) {
+            _ if metrics.failure().is_some() => {
+                let (kind, message) = metrics.failure().unwrap_or((ErrorKind::Other, ""));
+                self.failed_requests += 1;
+                *self.error_counts.entry(kind).or_insert(0) += 1;
+                self.error_messages.push(message.to_string());
+            }
+            (Some(status), None) => {
+                if metrics.is_success_status(status) {
+                    self.successful_requests += 1;
+                } else {
+                    self.failed_requests += 1;
+                    let kind = ErrorKind::Http(status.as_u16());
+                    *self.error_counts.entry(kind).or_insert(0) += 1;
+                    let error_message = status
+                        .canonical_reason()
+                        .unwrap_or(&status.to_string())
+                        .to_string();
+                    self.error_messages.push(error_message);
+                }
+            }
+            (None, Some(error)) => {
+                self.failed_requests += 1;
+                let kind = ErrorKind::from_error(error);
+                *self.error_counts.entry(kind).or_insert(0) += 1;
+                let error_message = error.to_string();
+                self.error_messages.push(error_message);
+            }
+            (None, None) => {
+                self.failed_requests += 1;
+                *self.error_counts.entry(ErrorKind::Other).or_insert(0) += 1;
+                self.error_messages.push("Unknown Error".to_string());
+            }
+            (Some(_), Some(_)) => {
+                self.failed_requests += 1;
+                *self.error_counts.entry(ErrorKind::Other).or_insert(0) += 1;
+                self.error_messages.push("Unknown Error".to_string());
+            }
+        }
+
+        if self.total_requests.is_multiple_of(self.log_batch_size) {
+            let elapsed = self.last_batch_log.elapsed();
+            let current_rps = self.log_batch_size as f64 / elapsed.as_secs_f64();
+            let success_rate =
+                (self.successful_requests as f64 / self.total_requests as f64) * 100.0;
+            info!(
+                "Progress: {} requests\n\
+                 Current RPS: {:.2}\n\
+                 Success Rate: {:.1}% ({}/{})\n\
+                 Error Rate: {:.1}% ({}/{})",
+                self.total_requests,
+                current_rps,
+                success_rate,
+                self.successful_requests,
+                self.total_requests,
+                100.0 - success_rate,
+                self.failed_requests,
+                self.total_requests
+            );
+            self.last_batch_log = Instant::now();
+        }
+    }
+
+    fn request_record(&self, metrics: &RequestMetrics) -> RequestRecord {
+        let timestamp = match (self.started_at, self.started_instant) {
+            (Some(started_at), Some(started_instant)) => {
+                let offset = metrics
+                    .start_time
+                    .saturating_duration_since(started_instant);
+                started_at + TimeDelta::from_std(offset).unwrap_or_default()
+            }
+            _ => Utc::now() - TimeDelta::from_std(metrics.start_time.elapsed()).unwrap_or_default(),
+        };
+        let error = if let Some((_, message)) = metrics.failure() {
+            Some(message.to_string())
+        } else if let Some(error) = metrics.error() {
+            Some(error.to_string())
+        } else {
+            match metrics.status() {
+                Some(status) if metrics.is_success_status(status) => None,
+                Some(status) => Some(format!("HTTP {}", status.as_u16())),
+                None => Some("Unknown Error".to_string()),
+            }
+        };
+        RequestRecord {
+            timestamp,
+            duration: metrics.duration(),
+            status: metrics.status().map(|status| status.as_u16()),
+            error,
+        }
+    }
+
+    /// Count a completed request in the bucket of the current time
+    ///
+    /// Requests are recorded as they complete, so buckets only move forward and the finished ones
+    /// are summarized right away instead of keeping a histogram per bucket.
+    fn record_time_bucket(&mut self, metrics: &RequestMetrics) {
+        let (Some(interval), Some(started)) = (self.time_series_interval, self.started_instant)
+        else {
+            return;
+        };
+        let index = (started.elapsed().as_nanos() / interval.as_nanos().max(1)) as u64;
+        match &mut self.current_bucket {
+            Some((current, bucket)) if *current == index => bucket.record(metrics),
+            _ => {
+                if let Some((current, bucket)) = self.current_bucket.take() {
+                    self.time_buckets
+                        .push(TimeBucket::from_metrics(interval * current as u32, &bucket));
+                }
+                // Intervals without completed requests, e.g. while the target stalls
+                while (self.time_buckets.len() as u64) < index {
+                    let start = interval * self.time_buckets.len() as u32;
+                    self.time_buckets
+                        .push(TimeBucket::from_metrics(start, &GroupMetrics::new()));
+                }
+                let mut bucket = GroupMetrics::new();
+                bucket.record(metrics);
+                self.current_bucket = Some((index, bucket));
+            }
+        }
+    }
+
+    fn record_tail_sample(&mut self, duration: Duration) {
+        self.timed_requests += 1;
+        if self.tail_samples.len() < TAIL_SAMPLE_CAPACITY {
+            self.tail_samples.push(Reverse(duration));
+        } else if self
+            .tail_samples
+            .peek()
+            .is_some_and(|Reverse(smallest)| duration > *smallest)
+        {
+            self.tail_samples.pop();
+            self.tail_samples.push(Reverse(duration));
+        }
+    }
+
+    /// Finalize the metrics with the total duration
+    #[instrument(skip(self))]
+    pub async fn finalize(&mut self, duration: Duration) -> Result<(), String> {
+        info!("Finalizing test metrics");
+        self.total_duration = duration;
+        if let Some(stage) = self.stages.last_mut() {
+            stage.end.get_or_insert(duration);
+        }
+        self.requests_per_second = if duration.as_secs_f64() > 0.0 {
+            self.total_requests as f64 / duration.as_secs_f64()
+        } else {
+            0.0
+        };
+        self.iterations_per_second = if duration.as_secs_f64() > 0.0 {
+            self.iterations as f64 / duration.as_secs_f64()
+        } else {
+            0.0
+        };
+
+        let success_rate = (self.successful_requests as f64 / self.total_requests as f64) * 100.0;
+        let error_rate = (self.failed_requests as f64 / self.total_requests as f64) * 100.0;
+
+        info!(
+            "Load Test Results:\n\
+             Duration: {:?}\n\
+             Total Requests: {}\n\
+             Average RPS: {:.2}\n\
+             Success Rate: {:.1}% ({}/{})\n\
+             Error Rate: {:.1}% ({}/{})\n\
+             Response Times:\n\
+             - Min: {:?}\n\
+             - Max: {:?}\n\
+             - Mean: {:?}\n\
+             - Median: {:?}\n\
+             - P95: {:?}\n\
+             - P99: {:?}\n\
+             - P99.9: {:?}",
+            duration,
+            self.total_requests,
+            self.requests_per_second,
+            success_rate,
+            self.successful_requests,
+            self.total_requests,
+            error_rate,
+            self.failed_requests,
+            self.total_requests,
+            self.min_response_time(),
+            self.max_response_time(),
+            self.mean_response_time(),
+            self.median_response_time(),
+            self.p95_response_time(),
+            self.p99_response_time(),
+            self.p999_response_time()
+        );
+
+        if !self.error_counts.is_empty() {
+            info!("Error Distribution:");
+            for (kind, count) in &self.error_counts {
+                info!("  {}: {} occurrences", kind, count);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get total number of requests
+    pub fn total_requests(&self) -> u64 {
+        self.total_requests
+    }
+
+    /// Get number of successful requests
+    pub fn successful_requests(&self) -> u64 {
+        self.successful_requests
+    }
+
+    /// Get number of failed requests
+    pub fn failed_requests(&self) -> u64 {
+        self.failed_requests
+    }
+
+    /// Record the start of the run, shifted onto the reference clock when an offset is known
+    pub fn mark_started(&mut self, clock_offset: Option<ClockOffset>) {
+        let now = Utc::now();
+        self.started_at = Some(clock_offset.map_or(now, |offset| offset.apply(now)));
+        self.started_instant = Some(Instant::now());
+        self.clock_offset = clock_offset;
+    }
+
+    /// Report the response times at these percentiles too
+    pub fn set_percentiles(&mut self, percentiles: Vec<f64>) {
+        self.percentiles = percentiles;
+    }
+
+    /// Get the extra percentiles to report
+    pub fn percentiles(&self) -> &[f64] {
+        &self.percentiles
+    }
+
+    /// Set the region of the agent running the test
+    pub fn set_region(&mut self, region: Option<String>) {
+        self.region = region;
+    }
+
+    /// Get the number of connections opened, as far as the client tells them
+    pub fn new_connections(&self) -> u64 {
+        self.connections.len() as u64
+    }
+
+    /// Get the number of requests sent on a connection opened by an earlier request
+    pub fn reused_connection_requests(&self) -> u64 {
+        self.reused_connection_requests
+    }
+
+    /// Get the number of TLS handshakes whose resumption the client told about
+    pub fn tls_handshakes(&self) -> u64 {
+        self.tls_handshakes
+    }
+
+    /// Get the number of TLS handshakes that resumed an earlier session
+    pub fn resumed_tls_handshakes(&self) -> u64 {
+        self.resumed_tls_handshakes
+    }
+
+    /// Record an iteration a virtual user completed
+    pub fn record_iteration(&mut self) {
+        self.iterations += 1;
+    }
+
+    /// Get the number of completed iterations
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    /// Get iterations per second
+    pub fn iterations_per_second(&self) -> f64 {
+        self.iterations_per_second
+    }
+
+    /// Set the number of virtual users that ran the iterations
+    pub fn set_virtual_users(&mut self, virtual_users: u64) {
+        self.virtual_users = virtual_users;
+    }
+
+    /// Get the number of virtual users that ran the iterations
+    pub fn virtual_users(&self) -> u64 {
+        self.virtual_users
+    }
+
+    /// Set the configured share of the requests per endpoint, in percent
+    pub fn set_mix(&mut self, mix: BTreeMap<String, f64>) {
+        self.mix = mix;
+    }
+
+    /// Get the configured share of the requests per endpoint, in percent
+    pub fn mix(&self) -> &BTreeMap<String, f64> {
+        &self.mix
+    }
+
+    /// Note an event of the run on its timeline
+    pub fn record_event(&mut self, event: RunEvent) {
+        let at = self
+            .timeline
+            .partition_point(|noted| noted.offset <= event.offset);
+        self.timeline.insert(at, event);
+    }
+
+    /// Get the events of the run in the order they happened
+    pub fn timeline(&self) -> &[RunEvent] {
+        &self.timeline
+    }
+
+    /// Record a virtual user backing off for `delay` before its next request
+    pub fn record_backoff(&mut self, delay: Duration) {
+        self.backoffs += 1;
+        self.backoff_time += delay;
+    }
+
+    /// Get the number of times a virtual user backed off
+    pub fn backoffs(&self) -> u64 {
+        self.backoffs
+    }
+
+    /// Get the time spent backing off, summed over the virtual users
+    pub fn backoff_time(&self) -> Duration {
+        self.backoff_time
+    }
+
+    /// Get the number of requests sent more than once
+    pub fn retried_requests(&self) -> u64 {
+        self.retried_requests
+    }
+
+    /// Get the number of attempts sent again after a failed one
+    pub fn retries(&self) -> u64 {
+        self.retries
+    }
+
+    /// Get the number of requests that succeeded after at least one retry
+    pub fn retried_successes(&self) -> u64 {
+        self.retried_successes
+    }
+
+    /// Get the number of responses whose body was cut at the max body size
+    pub fn truncated_bodies(&self) -> u64 {
+        self.truncated_bodies
+    }
+
+    /// Get the bytes of all requests sent
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    /// Get the bytes of all responses received
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// Count responses with these status codes as successful instead of any 2xx
+    ///
+    /// Requests that were given their own status codes with
+    /// [`RequestMetrics::with_success_codes`] keep them.
+    pub fn set_success_codes(&mut self, success_codes: SuccessCodes) {
+        self.success_codes = Some(Arc::new(success_codes));
+    }
+
+    /// Get the number of responses per HTTP status code
+    ///
+    /// Requests that got no response, e.g. timeouts, have no status code and are not counted.
+    pub fn status_codes(&self) -> &BTreeMap<u16, u64> {
+        &self.status_codes
+    }
+
+    /// Get the time spent in each phase, in microseconds, by the requests that went through it
+    ///
+    /// Only phases some request was timed in are present.
+    pub fn phase_times(&self) -> &BTreeMap<Phase, Histogram<u64>> {
+        &self.phase_times
+    }
+
+    /// Keep a second distribution of response times corrected for coordinated omission
+    ///
+    /// Response times are counted from when the requests were due to start. A request slower than
+    /// `expected_interval`, the interval each virtual user is expected to send at, also stands
+    /// for the requests its virtual user could not send meanwhile, as HdrHistogram's
+    /// `record_correct` fills them in. Without an interval the response times are only counted
+    /// from the intended starts, for open models where late requests are not held back.
+    pub fn correct_coordinated_omission(&mut self, expected_interval: Option<Duration>) {
+        let histogram =
+            Histogram::new_with_bounds(1, 60_000_000, 3).expect("histogram bounds are valid");
+        self.corrected_response_times = Some((histogram, expected_interval));
+    }
+
+    /// Get the response times corrected for coordinated omission, in microseconds, `None` unless
+    /// enabled
+    pub fn corrected_response_times(&self) -> Option<&Histogram<u64>> {
+        self.corrected_response_times
+            .as_ref()
+            .map(|(histogram, _)| histogram)
+    }
+
+    /// Get the region of the agent running the test
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    /// Keep a [`TimeBucket`] of the requests completed in every `interval` of the run
+    pub fn record_time_series(&mut self, interval: Duration) {
+        self.time_series_interval = Some(interval);
+    }
+
+    /// Get the length of the time series buckets, `None` unless enabled
+    pub fn time_series_interval(&self) -> Option<Duration> {
+        self.time_series_interval
+    }
+
+    /// Get the time series buckets so far, in order and without gaps
+    ///
+    /// Empty unless [`TestMetrics::record_time_series`] was called.
+    pub fn time_series(&self) -> Vec<TimeBucket> {
+        let mut buckets = self.time_buckets.clone();
+        if let (Some(interval), Some((index, bucket))) =
+            (self.time_series_interval, &self.current_bucket)
+        {
+            buckets.push(TimeBucket::from_metrics(interval * *index as u32, bucket));
+        }
+        buckets
+    }
+
+    /// Count the requests completed from now on in a new stage, ending the current one
+    pub fn start_stage(&mut self, name: impl Into<String>) {
+        let now = self
+            .started_instant
+            .map(|started| started.elapsed())
+            .unwrap_or_default();
+        if let Some(stage) = self.stages.last_mut() {
+            stage.end = Some(now);
+        }
+        self.stages.push(StageMetrics {
+            name: name.into(),
+            start: now,
+            end: None,
+            metrics: GroupMetrics::new(),
+        });
+    }
+
+    /// Get the stages started so far, in order
+    pub fn stages(&self) -> &[StageMetrics] {
+        &self.stages
+    }
+
+    /// Keep the probes of the cooldown observed after the load
+    pub fn set_cooldown(&mut self, cooldown: CooldownMetrics) {
+        self.cooldown = Some(cooldown);
+    }
+
+    /// Get the probes of the cooldown, if one was observed
+    pub fn cooldown(&self) -> Option<&CooldownMetrics> {
+        self.cooldown.as_ref()
+    }
+
+    /// Keep a [`RequestRecord`] of every request from now on
+    pub fn record_individual_requests(&mut self) {
+        self.request_log.get_or_insert_with(Vec::new);
+    }
+
+    /// Get the recorded requests, empty unless [`TestMetrics::record_individual_requests`] was called
+    pub fn request_log(&self) -> &[RequestRecord] {
+        self.request_log.as_deref().unwrap_or_default()
+    }
+
+    /// Get the recorded requests flushed to disk under memory pressure, which came before those
+    /// of [`TestMetrics::request_log`]
+    pub fn spilled_requests(&self) -> Option<&Arc<RequestSpill>> {
+        self.spilled_requests.as_ref()
+    }
+
+    /// Get the traces of the requests sampled by the run's trace sampling
+    pub fn traces(&self) -> &[TraceEntry] {
+        &self.traces
+    }
+
+    /// Keep track of the resident memory of the generator under a ceiling of `ceiling` bytes
+    pub fn watch_memory(&mut self, ceiling: u64) {
+        self.memory = Some(MemoryUsage {
+            ceiling,
+            ..Default::default()
+        });
+    }
+
+    /// Record a reading of the resident memory, in bytes
+    pub fn record_resident_memory(&mut self, resident: u64) {
+        if let Some(memory) = &mut self.memory {
+            memory.peak_resident = memory.peak_resident.max(resident);
+        }
+    }
+
+    /// Hand the recorded requests still in memory to a spill file in `dir`, returning how many
+    ///
+    /// No I/O happens here: the requests are queued on [`TestMetrics::spilled_requests`], to be
+    /// written by [`RequestSpill::flush`] once the metrics are unlocked.
+    pub fn spill_requests(&mut self, dir: &Path) -> u64 {
+        let Some(log) = self.request_log.as_mut().filter(|log| !log.is_empty()) else {
+            return 0;
+        };
+        let spilled = log.len() as u64;
+        self.spilled_requests
+            .get_or_insert_with(|| Arc::new(RequestSpill::new(dir)))
+            .queue(std::mem::take(log));
+        if let Some(memory) = &mut self.memory {
+            memory.spilled_requests += spilled;
+        }
+        spilled
+    }
+
+    /// Drop every other trace kept so far and no longer keep new ones, returning how many were
+    /// dropped
+    pub fn shrink_traces(&mut self) -> u64 {
+        let before = self.traces.len();
+        let mut index = 0;
+        self.traces.retain(|_| {
+            index += 1;
+            index % 2 == 1
+        });
+        self.traces.shrink_to_fit();
+        self.trace_limit = Some(self.traces.len());
+        let dropped = (before - self.traces.len()) as u64;
+        if let Some(memory) = &mut self.memory {
+            memory.dropped_traces += dropped;
+        }
+        dropped
+    }
+
+    /// Note on the timeline that the generator neared its memory ceiling
+    pub fn record_memory_pressure(&mut self, event: RunEvent) {
+        if let Some(memory) = &mut self.memory {
+            memory.pressure_events += 1;
+        }
+        self.record_event(event);
+    }
+
+    /// Get the resident memory of the generator, when it was kept under a ceiling
+    pub fn memory_usage(&self) -> Option<&MemoryUsage> {
+        self.memory.as_ref()
+    }
+
+    /// Get the start of the run
+    pub fn started_at(&self) -> Option<DateTime<Utc>> {
+        self.started_at
+    }
+
+    /// Get the clock offset the run was started with
+    pub fn clock_offset(&self) -> Option<ClockOffset> {
+        self.clock_offset
+    }
+
+    /// Record that the run hit its overall deadline before sending all its requests
+    pub fn mark_deadline_exceeded(&mut self) {
+        self.deadline_exceeded = true;
+    }
+
+    /// Whether the run was stopped by its overall deadline
+    pub fn is_deadline_exceeded(&self) -> bool {
+        self.deadline_exceeded
+    }
+
+    /// Record that the run was ended early by an abort rule or for missing data
+    pub fn mark_aborted(&mut self, rule: String) {
+        self.aborted = Some(rule);
+    }
+
+    /// Abort rule that ended the run early, if any
+    pub fn abort_reason(&self) -> Option<&str> {
+        self.aborted.as_deref()
+    }
+
+    /// Record requests not sent because their data was missing, e.g. a placeholder without a value
+    pub fn record_skipped(&mut self, reason: String, requests: u64) {
+        *self.skipped.entry(reason).or_default() += requests;
+    }
+
+    /// Requests not sent because their data was missing, by reason
+    pub fn skipped(&self) -> &BTreeMap<String, u64> {
+        &self.skipped
+    }
+
+    /// Record that the run was cancelled before sending all its requests
+    pub fn mark_cancelled(&mut self) {
+        self.cancelled = true;
+    }
+
+    /// Whether the run was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// Record that the process panicked before the run completed
+    pub fn mark_crashed(&mut self) {
+        self.crashed = true;
+    }
+
+    /// Whether the process panicked during the run
+    pub fn is_crashed(&self) -> bool {
+        self.crashed
+    }
+
+    /// Get total duration
+    pub fn total_duration(&self) -> Duration {
+        self.total_duration
+    }
+
+    /// Get requests per second
+    pub fn requests_per_second(&self) -> f64 {
+        self.requests_per_second
+    }
+
+    /// Get minimum response time
+    pub fn min_response_time(&self) -> Duration {
+        if self.response_times.is_empty() {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_micros(self.response_times.min())
+        }
+    }
+
+    /// Get maximum response time, exactly as measured
+    pub fn max_response_time(&self) -> Duration {
+        match self.tail_samples.iter().map(|Reverse(d)| *d).max() {
+            Some(max) => max,
+            None if self.response_times.is_empty() => Duration::from_secs(0),
+            None => Duration::from_micros(self.response_times.max()),
+        }
+    }
+
+    /// Get mean response time
+    pub fn mean_response_time(&self) -> Duration {
+        if self.response_times.is_empty() {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_micros(self.response_times.mean() as u64)
+        }
+    }
+
+    /// Get median response time
+    pub fn median_response_time(&self) -> Duration {
+        if self.response_times.is_empty() {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_micros(self.response_times.value_at_percentile(50.0))
+        }
+    }
+
+    /// Get 95th percentile response time
+    pub fn p95_response_time(&self) -> Duration {
+        if self.response_times.is_empty() {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_micros(self.response_times.value_at_percentile(95.0))
+        }
+    }
+
+    /// Get 99th percentile response time
+    pub fn p99_response_time(&self) -> Duration {
+        if self.response_times.is_empty() {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_micros(self.response_times.value_at_percentile(99.0))
+        }
+    }
+
+    /// Get the response time at the given percentile, between 0 and 100
+    pub fn percentile_response_time(&self, percentile: f64) -> Duration {
+        if self.response_times.is_empty() {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_micros(self.response_times.value_at_percentile(percentile))
+        }
+    }
+
+    /// Get the number of timed requests at or below each bound, cumulative like a Prometheus histogram
+    pub fn response_time_distribution(&self, bounds: &[Duration]) -> Vec<u64> {
+        bounds
+            .iter()
+            .map(|bound| {
+                self.response_times
+                    .count_between(0, bound.as_micros() as u64)
+            })
+            .collect()
+    }
+
+    /// Get the histogram of all recorded response times, in microseconds
+    pub fn response_time_histogram(&self) -> &Histogram<u64> {
+        &self.response_times
+    }
+
+    /// Get the number of requests with a recorded response time
+    pub fn timed_requests(&self) -> u64 {
+        self.timed_requests
+    }
+
+    /// Get the sum of all recorded response times
+    pub fn response_time_sum(&self) -> Duration {
+        Duration::from_secs_f64(
+            self.response_times.mean() * self.response_times.len() as f64 / 1_000_000.0,
+        )
+    }
+
+    /// Get metrics per distinct label set; unlabeled requests are not grouped
+    pub fn groups(&self) -> &HashMap<Labels, GroupMetrics> {
+        &self.groups
+    }
+
+    /// Get metrics per value of a single label, merging label sets that share the value
+    ///
+    /// Requests without the label are left out.
+    pub fn group_by(&self, label: &str) -> BTreeMap<String, GroupMetrics> {
+        let mut grouped: BTreeMap<String, GroupMetrics> = BTreeMap::new();
+        for (labels, metrics) in &self.groups {
+            if let Some(value) = labels.get(label) {
+                grouped
+                    .entry(value.clone())
+                    .or_insert_with(GroupMetrics::new)
+                    .merge(metrics);
+            }
+        }
+        grouped
+    }
+
+    /// Get metrics per HTTP version of the responses; requests without a response are left out
+    pub fn protocols(&self) -> &BTreeMap<&'static str, GroupMetrics> {
+        &self.protocols
+    }
+
+    /// Get metrics per scenario request
+    pub fn endpoints(&self) -> BTreeMap<String, GroupMetrics> {
+        self.group_by(ENDPOINT_LABEL)
+    }
+
+    /// Get 99.9th percentile response time
+    ///
+    /// Exact while the slowest 0.1% of requests fit in the tail reservoir, otherwise taken from the
+    /// histogram.
+    pub fn p999_response_time(&self) -> Duration {
+        let tail = self.tail_latencies();
+        match tail.last() {
+            Some(p999) if tail.len() == self.tail_len() => *p999,
+            _ if self.response_times.is_empty() => Duration::from_secs(0),
+            _ => Duration::from_micros(self.response_times.value_at_percentile(99.9)),
+        }
+    }
+
+    /// Get the raw response times of the slowest 0.1% of requests (at least one), slowest first
+    ///
+    /// Bounded by [`TAIL_SAMPLE_CAPACITY`].
+    pub fn tail_latencies(&self) -> Vec<Duration> {
+        let mut samples: Vec<Duration> = self.tail_samples.iter().map(|Reverse(d)| *d).collect();
+        samples.sort_unstable_by(|a, b| b.cmp(a));
+        samples.truncate(self.tail_len());
+        samples
+    }
+
+    /// Number of samples in the slowest 0.1% of recorded response times
+    fn tail_len(&self) -> usize {
+        (self.timed_requests as usize).div_ceil(1000).max(1)
+    }
+
+    /// Get error statistics
+    pub fn error_stats(&self) -> Option<(HashMap<ErrorKind, u64>, Vec<String>)> {
+        if self.error_counts.is_empty() {
+            None
+        } else {
+            Some((self.error_counts.clone(), self.error_messages.clone()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_request_metrics_lifecycle() {
+        let metrics = RequestMetrics::new();
+        assert!(metrics.duration().is_none());
+        assert!(metrics.status().is_none());
+        assert!(metrics.error().is_none());
+
+        let metrics = metrics.complete(StatusCode::OK);
+        assert!(metrics.duration().is_some());
+        assert_eq!(metrics.status(), Some(StatusCode::OK));
+        assert!(metrics.error().is_none());
+
+        // Test with explicit duration
+        let mut metrics = RequestMetrics::new();
+        let explicit_duration = Duration::from_millis(100);
+        metrics.duration = Some(explicit_duration);
+        let metrics = metrics.complete(StatusCode::OK);
+        assert!(metrics.duration().unwrap() >= explicit_duration);
+        assert_eq!(metrics.status(), Some(StatusCode::OK));
+        assert!(metrics.error().is_none());
+    }
+
+    #[test]
+    fn test_metrics_basic_stats() {
+        let mut metrics = TestMetrics::new().unwrap();
+
+        // Record successful requests
+        for _ in 0..5 {
+            metrics.record_request(RequestMetrics::new().complete(StatusCode::OK));
+        }
+
+        // Record failed requests
+        for _ in 0..3 {
+            metrics
+                .record_request(RequestMetrics::new().complete(StatusCode::INTERNAL_SERVER_ERROR));
+        }
+
+        assert_eq!(metrics.total_requests(), 8);
+        assert_eq!(metrics.successful_requests(), 5);
+        assert_eq!(metrics.failed_requests(), 3);
+    }
+
+    #[test]
+    fn test_metrics_response_times() {
+        let mut metrics = TestMetrics::new().unwrap();
+
+        // Record requests with different response times
+        let mut request = RequestMetrics::new();
+        request.duration = Some(Duration::from_millis(100));
+        metrics.record_request(request.complete(StatusCode::OK));
+
+        let mut request = RequestMetrics::new();
+        request.duration = Some(Duration::from_millis(200));
+        metrics.record_request(request.complete(StatusCode::OK));
+
+        let mut request = RequestMetrics::new();
+        request.duration = Some(Duration::from_millis(300));
+        metrics.record_request(request.complete(StatusCode::OK));
+
+        // Check that response times are recorded correctly
+        assert!(metrics.min_response_time() <= metrics.max_response_time());
+        assert!(metrics.mean_response_time() >= metrics.min_response_time());
+        assert!(metrics.mean_response_time() <= metrics.max_response_time());
+        assert!(metrics.p95_response_time() >= metrics.median_response_time());
+        assert!(metrics.p99_response_time() >= metrics.p95_response_time());
+    }
+
+    #[test]
+    fn test_metrics_with_invalid_histogram() {
+        let result = Histogram::<u64>::new(6); // 6 significant digits is invalid
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_metrics_error_handling() {
+        let result = TestMetrics::new();
+        assert!(result.is_ok());
+
+        let mut metrics = TestMetrics::new().unwrap();
+        let request_metrics = RequestMetrics::new();
+        metrics.record_request(request_metrics);
+        assert_eq!(metrics.total_requests(), 1);
+    }
+
+    #[test]
+    fn test_metrics_grouped_by_labels() {
+        let mut metrics = TestMetrics::new().unwrap();
+        let labels = |step: &str, region: &str| {
+            Labels::from([
+                ("step".to_string(), step.to_string()),
+                ("region".to_string(), region.to_string()),
+            ])
+        };
+
+        for (step, region, status) in [
+            ("login", "eu", StatusCode::OK),
+            ("login", "us", StatusCode::OK),
+            ("login", "us", StatusCode::UNAUTHORIZED),
+            ("checkout", "eu", StatusCode::OK),
+        ] {
+            metrics.record_request(
+                RequestMetrics::new()
+                    .with_labels(labels(step, region))
+                    .complete(status),
+            );
+        }
+        metrics.record_request(RequestMetrics::new().complete(StatusCode::OK));
+
+        assert_eq!(metrics.total_requests(), 5);
+        assert_eq!(metrics.groups().len(), 3);
+        let login_us = &metrics.groups()[&labels("login", "us")];
+        assert_eq!(login_us.total_requests(), 2);
+        assert_eq!(login_us.failed_requests(), 1);
+
+        let by_step = metrics.group_by("step");
+        assert_eq!(by_step.len(), 2);
+        assert_eq!(by_step["login"].total_requests(), 3);
+        assert_eq!(by_step["login"].successful_requests(), 2);
+        assert_eq!(by_step["checkout"].total_requests(), 1);
+
+        assert!(metrics.group_by("variant").is_empty());
+    }
+
+    #[test]
+    fn test_metrics_tail_latencies() {
+        let mut metrics = TestMetrics::new().unwrap();
+        for i in 1..=2500u64 {
+            let mut request = RequestMetrics::new();
+            request.duration = Some(Duration::from_nanos(1_000_000 + i * 1_001));
+            metrics.record_request(request.complete(StatusCode::OK));
+        }
+
+        // The slowest 0.1% of 2500 requests are the 3 slowest, kept to the nanosecond
+        let tail = metrics.tail_latencies();
+        assert_eq!(
+            tail,
+            vec![
+                Duration::from_nanos(1_000_000 + 2500 * 1_001),
+                Duration::from_nanos(1_000_000 + 2499 * 1_001),
+                Duration::from_nanos(1_000_000 + 2498 * 1_001),
+            ]
+        );
+        assert_eq!(metrics.max_response_time(), tail[0]);
+        assert_eq!(metrics.p999_response_time(), tail[2]);
+        assert!(metrics.p999_response_time() >= metrics.p99_response_time());
+    }
+
+    #[test]
+    fn test_metrics_tail_reservoir_is_bounded() {
+        let mut metrics = TestMetrics::new().unwrap();
+        for i in 0..(TAIL_SAMPLE_CAPACITY as u64 * 3) {
+            let mut request = RequestMetrics::new();
+            request.duration = Some(Duration::from_micros(10 + i));
+            metrics.record_request(request.complete(StatusCode::OK));
+        }
+
+        assert_eq!(metrics.tail_samples.len(), TAIL_SAMPLE_CAPACITY);
+        assert_eq!(
+            metrics.max_response_time(),
+            Duration::from_micros(10 + TAIL_SAMPLE_CAPACITY as u64 * 3 - 1)
+        );
+    }
+
+    #[test]
+    fn test_metrics_with_zero_requests() {
+        let metrics = TestMetrics::new().unwrap();
+        assert_eq!(metrics.total_requests(), 0);
+        assert_eq!(metrics.successful_requests(), 0);
+        assert_eq!(metrics.failed_requests(), 0);
+        assert_eq!(metrics.requests_per_second(), 0.0);
+    }
+
+    #[test]
+    fn test_metrics_no_error_stats() {
+        let mut metrics = TestMetrics::new().unwrap();
+
+        // Record only successful requests
+        metrics.record_request(RequestMetrics::new().complete(StatusCode::OK));
+        metrics.record_request(RequestMetrics::new().complete(StatusCode::OK));
+
+        assert_eq!(metrics.error_stats(), None);
+        assert_eq!(metrics.total_requests(), 2);
+        assert_eq!(metrics.successful_requests(), 2);
+        assert_eq!(metrics.failed_requests(), 0);
+    }
+
+    #[test]
+    fn test_metrics_unknown_errors() {
+        let mut metrics = TestMetrics::new().unwrap();
+
+        // Record a request with no status and no error
+        let request = RequestMetrics::new();
+        metrics.record_request(request);
+
+        let (error_counts, error_messages) = metrics.error_stats().unwrap();
+
+        assert_eq!(metrics.failed_requests(), 1);
+        assert_eq!(error_counts.get(&ErrorKind::Other).unwrap(), &1u64);
+        assert_eq!(error_messages.len(), 1);
+        assert_eq!(error_messages[0], "Unknown Error");
+    }
+
+    #[test]
+    fn test_metrics_failed_despite_response() {
+        let mut metrics = TestMetrics::new().unwrap();
+
+        let request = RequestMetrics::new()
+            .complete(StatusCode::OK)
+            .fail(ErrorKind::Assertion, "No value for 'token'");
+        assert!(!request.is_success());
+        metrics.record_request(request);
+
+        let (error_counts, error_messages) = metrics.error_stats().unwrap();
+
+        assert_eq!(metrics.successful_requests(), 0);
+        assert_eq!(metrics.failed_requests(), 1);
+        assert_eq!(error_counts.get(&ErrorKind::Assertion).unwrap(), &1u64);
+        assert_eq!(error_messages[0], "No value for 'token'");
+    }
+
+    #[test]
+    fn test_metrics_phase_times() {
+        let mut metrics = TestMetrics::new().unwrap();
+
+        // Only the first request on a connection sets it up
+        metrics.record_request(
+            RequestMetrics::new()
+                .with_phase(Phase::Dns, Some(Duration::from_millis(5)))
+                .with_phase(Phase::Connect, Some(Duration::from_millis(10)))
+                .with_phase(Phase::Ttfb, Some(Duration::from_millis(40)))
+                .complete(StatusCode::OK),
+        );
+        metrics.record_request(
+            RequestMetrics::new()
+                .with_phase(Phase::Connect, None)
+                .with_phase(Phase::Ttfb, Some(Duration::from_millis(20)))
+                .complete(StatusCode::OK),
+        );
+
+        let phases = metrics.phase_times();
+        assert_eq!(
+            phases.keys().collect::<Vec<_>>(),
+            [&Phase::Dns, &Phase::Connect, &Phase::Ttfb]
+        );
+        assert_eq!(phases[&Phase::Connect].len(), 1);
+        let ttfb = &phases[&Phase::Ttfb];
+        assert_eq!(ttfb.len(), 2);
+        assert!((ttfb.mean() - 30_000.0).abs() < 100.0);
+    }
+
+    #[test]
+    fn test_metrics_corrected_for_coordinated_omission() {
+        let mut metrics = TestMetrics::new().unwrap();
+        assert!(metrics.corrected_response_times().is_none());
+        metrics.correct_coordinated_omission(Some(Duration::from_millis(100)));
+
+        let timed = |duration: Duration, late: Duration| {
+            let mut request = RequestMetrics::new();
+            request.duration = Some(duration);
+            let due = request.start_time - late;
+            request.with_due(due).complete(StatusCode::OK)
+        };
+        for _ in 0..9 {
+            metrics.record_request(timed(Duration::from_millis(10), Duration::ZERO));
+        }
+        // A 1s stall held back the 9 requests its virtual user was due to send meanwhile
+        metrics.record_request(timed(Duration::from_millis(990), Duration::from_millis(10)));
+
+        assert_eq!(metrics.response_time_histogram().len(), 10);
+        let corrected = metrics.corrected_response_times().unwrap();
+        assert_eq!(corrected.len(), 19);
+        assert_eq!(corrected.max() / 1000, 1000);
+        assert!(corrected.value_at_percentile(50.0) >= 100_000);
+        assert!(metrics.median_response_time() < Duration::from_millis(11));
+    }
+
+    #[test]
+    fn test_metrics_sub_millisecond_response_times() {
+        let mut metrics = TestMetrics::new().unwrap();
+
+        // Record requests with sub-millisecond response times
+        let mut request = RequestMetrics::new();
+        thread::sleep(Duration::from_micros(100));
+        request.duration = Some(request.start_time.elapsed());
+        println!("Recording duration: {:?}", request.duration.unwrap());
+        metrics.record_request(request.complete(StatusCode::OK));
+
+        let mut request = RequestMetrics::new();
+        thread::sleep(Duration::from_micros(300));
+        request.duration = Some(request.start_time.elapsed());
+        println!("Recording duration: {:?}", request.duration.unwrap());
+        metrics.record_request(request.complete(StatusCode::OK));
+
+        let mut request = RequestMetrics::new();
+        thread::sleep(Duration::from_micros(500));
+        request.duration = Some(request.start_time.elapsed());
+        println!("Recording duration: {:?}", request.duration.unwrap());
+        metrics.record_request(request.complete(StatusCode::OK));
+
+        println!("Min value in histogram: {}", metrics.response_times.min());
+        println!("Max value in histogram: {}", metrics.response_times.max());
+        println!("Mean value in histogram: {}", metrics.response_times.mean());
+
+        // Check that sub-millisecond response times are recorded correctly
+        let min_time = metrics.min_response_time();
+        let max_time = metrics.max_response_time();
+        let median_time = metrics.median_response_time();
+        let mean_time = metrics.mean_response_time();
+        let p95_time = metrics.p95_response_time();
+        let p99_time = metrics.p99_response_time();
+
+        println!("Min time: {:?}", min_time);
+        println!("Max time: {:?}", max_time);
+        println!("Median time: {:?}", median_time);
+        println!("Mean time: {:?}", mean_time);
+        println!("P95 time: {:?}", p95_time);
+        println!("P99 time: {:?}", p99_time);
+
+        assert!(
+            min_time >= Duration::from_micros(100),
+            "Min time {:?} should be >= 100µs",
+            min_time
+        );
+        assert!(
+            max_time >= Duration::from_micros(500),
+            "Max time {:?} should be >= 500µs",
+            max_time
+        );
+        assert!(
+            median_time >= Duration::from_micros(300),
+            "Median time {:?} should be >= 300µs",
+            median_time
+        );
+        assert!(
+            mean_time >= Duration::from_micros(300),
+            "Mean time {:?} should be >= 300µs",
+            mean_time
+        );
+        assert!(
+            p95_time >= Duration::from_micros(500),
+            "P95 time {:?} should be >= 500µs",
+            p95_time
+        );
+        assert!(
+            p99_time >= Duration::from_micros(500),
+            "P99 time {:?} should be >= 500µs",
+            p99_time
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metrics_requests_per_second() {
+        let mut metrics = TestMetrics::new().unwrap();
+
+        // Record 20 requests
+        for _ in 0..20 {
+            metrics.record_request(RequestMetrics::new().complete(StatusCode::OK));
+        }
+
+        // Test with a very short duration (sub-second)
+        let short_duration = Duration::from_secs_f64(0.006573375);
+        metrics.finalize(short_duration).await.unwrap();
+        assert!(
+            (metrics.requests_per_second() - 3042.86).abs() < 1.0,
+            "Expected RPS ~3042.86, got {}",
+            metrics.requests_per_second()
+        );
+
+        // Reset metrics
+        let mut metrics = TestMetrics::new().unwrap();
+        for _ in 0..50 {
+            metrics.record_request(RequestMetrics::new().complete(StatusCode::OK));
+        }
+
+        // Test with a longer duration
+        let long_duration = Duration::from_secs_f64(0.075828625);
+        metrics.finalize(long_duration).await.unwrap();
+        assert!(
+            (metrics.requests_per_second() - 659.38).abs() < 1.0,
+            "Expected RPS ~659.38, got {}",
+            metrics.requests_per_second()
+        );
+
+        // Test with zero duration
+        let mut metrics = TestMetrics::new().unwrap();
+        metrics.record_request(RequestMetrics::new().complete(StatusCode::OK));
+        metrics.finalize(Duration::from_secs(0)).await.unwrap();
+        assert_eq!(metrics.requests_per_second(), 0.0);
+    }
+}
@@ -0,0 +1,131 @@
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use super::RequestRecord;
+
+/// Recorded requests flushed to a file to free memory, one JSON line per request in completion
+/// order
+///
+/// Requests are queued without any I/O, so they can be handed over under a lock, and written by
+/// [`RequestSpill::flush`]. The file is created with the first write and removed once the spill
+/// and every report holding it are dropped.
+#[derive(Debug)]
+pub struct RequestSpill {
+    path: PathBuf,
+    len: AtomicU64,
+    /// Requests queued and not written yet, locked while they are written
+    pending: Mutex<Vec<RequestRecord>>,
+}
+
+impl RequestSpill {
+    /// Spill to a new file in `dir`
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            path: dir.join(format!("lode-requests-{}.jsonl", uuid::Uuid::new_v4())),
+            len: AtomicU64::new(0),
+            pending: Mutex::default(),
+        }
+    }
+
+    /// Path of the spill file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Requests spilled so far, written or queued
+    pub fn len(&self) -> u64 {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Queue requests to be written after the ones spilled before
+    pub fn queue(&self, mut records: Vec<RequestRecord>) {
+        self.len.fetch_add(records.len() as u64, Ordering::Relaxed);
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .append(&mut records);
+    }
+
+    /// Write the queued requests to the file; they stay queued when they cannot be written
+    pub fn flush(&self) -> io::Result<()> {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let mut writer = BufWriter::new(file);
+        for record in pending.iter() {
+            serde_json::to_writer(&mut writer, record)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        pending.clear();
+        Ok(())
+    }
+
+    /// Write requests to the file after the ones spilled before
+    pub fn append(&self, records: &[RequestRecord]) -> io::Result<()> {
+        self.queue(records.to_vec());
+        self.flush()
+    }
+
+    /// Read the spilled requests back, in the order they were spilled
+    ///
+    /// Waits for a write in progress and writes the requests still queued first.
+    pub fn records(&self) -> io::Result<impl Iterator<Item = io::Result<RequestRecord>>> {
+        self.flush()?;
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&self.path)?;
+        Ok(BufReader::new(file)
+            .lines()
+            .map(|line| Ok(serde_json::from_str(&line?)?)))
+    }
+}
+
+impl Drop for RequestSpill {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_spilled_requests_are_read_back_in_order() {
+        let spill = RequestSpill::new(&std::env::temp_dir());
+        assert_eq!(spill.records().unwrap().count(), 0);
+        let record = |status| RequestRecord {
+            timestamp: Utc::now(),
+            duration: Some(Duration::from_micros(1500)),
+            status: Some(status),
+            error: (status >= 500).then(|| "HTTP 500, \"oops\"\n".to_string()),
+        };
+        let records = vec![record(200), record(500), record(201)];
+        spill.append(&records[..2]).unwrap();
+        spill.queue(records[2..].to_vec());
+        assert_eq!(spill.len(), 3);
+        let read: Vec<RequestRecord> = spill.records().unwrap().map(Result::unwrap).collect();
+        assert_eq!(read, records);
+
+        let path = spill.path().to_path_buf();
+        drop(spill);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::config::ConfigError;
+
+/// Status codes of the responses that count as successful, any 2xx by default
+///
+/// Written as a comma-separated list of codes, ranges and classes, e.g. `201`, `200-299,404` or
+/// `2xx,3xx`, so a cache-miss test can expect its 404s or a create endpoint can require a 201.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SuccessCodes {
+    ranges: Vec<RangeInclusive<u16>>,
+}
+
+impl SuccessCodes {
+    /// Whether a response with this status code counts as successful
+    pub fn contains(&self, status: u16) -> bool {
+        self.ranges.iter().any(|range| range.contains(&status))
+    }
+}
+
+impl Default for SuccessCodes {
+    fn default() -> Self {
+        Self {
+            ranges: vec![200..=299],
+        }
+    }
+}
+
+impl FromStr for SuccessCodes {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |part: &str| {
+            ConfigError::InvalidSuccessCodes(format!(
+                "{} in {} (expected status codes from 100 to 599, ranges such as 200-299 or \
+                 classes such as 2xx)",
+                part, s
+            ))
+        };
+        let code = |part: &str| {
+            part.trim()
+                .parse::<u16>()
+                .ok()
+                .filter(|code| (100..=599).contains(code))
+        };
+        let ranges = s
+            .split(',')
+            .map(|part| {
+                let part = part.trim();
+                let range = if let Some(class) = part.strip_suffix("xx") {
+                    code(&format!("{}00", class)).map(|start| start..=start + 99)
+                } else if let Some((start, end)) = part.split_once('-') {
+                    code(start)
+                        .zip(code(end))
+                        .filter(|(start, end)| start <= end)
+                        .map(|(start, end)| start..=end)
+                } else {
+                    code(part).map(|code| code..=code)
+                };
+                range.ok_or_else(|| invalid(part))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { ranges })
+    }
+}
+
+impl TryFrom<String> for SuccessCodes {
+    type Error = ConfigError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<SuccessCodes> for String {
+    fn from(codes: SuccessCodes) -> Self {
+        codes.to_string()
+    }
+}
+
+impl fmt::Display for SuccessCodes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self
+            .ranges
+            .iter()
+            .map(|range| match (range.start(), range.end()) {
+                (start, end) if start == end => start.to_string(),
+                (start, end) if start % 100 == 0 && *end == start + 99 => {
+                    format!("{}xx", start / 100)
+                }
+                (start, end) => format!("{}-{}", start, end),
+            })
+            .collect();
+        f.write_str(&parts.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_success_codes_from_str() {
+        let codes: SuccessCodes = "2xx, 404".parse().unwrap();
+        assert!(codes.contains(200) && codes.contains(299) && codes.contains(404));
+        assert!(!codes.contains(301) && !codes.contains(403));
+        assert_eq!(codes.to_string(), "2xx,404");
+
+        let codes: SuccessCodes = "201".parse().unwrap();
+        assert!(codes.contains(201) && !codes.contains(200));
+        assert_eq!(
+            "200-204".parse::<SuccessCodes>().unwrap().to_string(),
+            "200-204"
+        );
+        assert_eq!(SuccessCodes::default().to_string(), "2xx");
+
+        for invalid in ["", "ok", "99", "600", "299-200", "6xx", "200,"] {
+            assert!(
+                matches!(
+                    invalid.parse::<SuccessCodes>(),
+                    Err(ConfigError::InvalidSuccessCodes(_))
+                ),
+                "{} should be rejected",
+                invalid
+            );
+        }
+    }
+}
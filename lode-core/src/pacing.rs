@@ -0,0 +1,287 @@
+use reqwest::header::{HeaderMap, HeaderName};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::config::ConfigError;
+
+/// Header telling the requests left in the current rate limit window by default
+pub const DEFAULT_REMAINING_HEADER: &str = "X-RateLimit-Remaining";
+
+/// Header telling when the current rate limit window resets by default
+pub const DEFAULT_RESET_HEADER: &str = "X-RateLimit-Reset";
+
+/// Share of the published rate limit used by default, leaving a margin for other clients
+pub const DEFAULT_LIMIT_SHARE: f64 = 0.9;
+
+/// Reset values above this are Unix timestamps rather than seconds from now
+const EPOCH_THRESHOLD: f64 = 1e9;
+
+/// Longest time to a reset taken from a response, a day; later resets are read as this one
+const MAX_UNTIL_RESET: f64 = 24.0 * 60.0 * 60.0;
+
+/// Paces requests by the rate limit a server publishes in its response headers, so a run rides
+/// just under the limit of a partner API instead of running into 429s
+///
+/// Written as `<remaining header>,<reset header>[,<share>%]`, e.g.
+/// `X-RateLimit-Remaining,X-RateLimit-Reset,90%`. The reset is read as seconds from now, or as a
+/// Unix timestamp when it is that large, and at most a day away. The requests left are spread
+/// over the time to the reset, of which `share` is used; a configured rate still caps the pace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct HeaderPacing {
+    pub remaining_header: String,
+    pub reset_header: String,
+    /// Share of the published limit used, from 0 (excluded) to 1
+    pub share: f64,
+}
+
+impl HeaderPacing {
+    /// Check the header names and that the share is a fraction of the limit
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for name in [&self.remaining_header, &self.reset_header] {
+            if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(ConfigError::InvalidPacing(format!(
+                    "'{}' is not a valid header name",
+                    name
+                )));
+            }
+        }
+        if !(self.share > 0.0 && self.share <= 1.0) {
+            return Err(ConfigError::InvalidPacing(format!(
+                "Share of the limit must be above 0% and at most 100%, got {}%",
+                self.share * 100.0
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for HeaderPacing {
+    fn default() -> Self {
+        Self {
+            remaining_header: DEFAULT_REMAINING_HEADER.to_string(),
+            reset_header: DEFAULT_RESET_HEADER.to_string(),
+            share: DEFAULT_LIMIT_SHARE,
+        }
+    }
+}
+
+impl FromStr for HeaderPacing {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            ConfigError::InvalidPacing(format!(
+                "{} (expected <remaining header>,<reset header>[,<share>%])",
+                s
+            ))
+        };
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        let (remaining, reset, share) = match parts[..] {
+            [remaining, reset] => (remaining, reset, DEFAULT_LIMIT_SHARE),
+            [remaining, reset, share] => {
+                let share = share
+                    .strip_suffix('%')
+                    .and_then(|percent| percent.trim().parse::<f64>().ok())
+                    .ok_or_else(invalid)?;
+                (remaining, reset, share / 100.0)
+            }
+            _ => return Err(invalid()),
+        };
+        let pacing = Self {
+            remaining_header: remaining.to_string(),
+            reset_header: reset.to_string(),
+            share,
+        };
+        pacing.validate()?;
+        Ok(pacing)
+    }
+}
+
+impl TryFrom<String> for HeaderPacing {
+    type Error = ConfigError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<HeaderPacing> for String {
+    fn from(pacing: HeaderPacing) -> Self {
+        pacing.to_string()
+    }
+}
+
+impl fmt::Display for HeaderPacing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{}%",
+            self.remaining_header,
+            self.reset_header,
+            self.share * 100.0
+        )
+    }
+}
+
+/// Rate published in the latest response, until its window resets
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    rate: f64,
+    resets: Instant,
+}
+
+/// Follows the rate limit headers of the responses of a run
+#[derive(Debug)]
+pub(crate) struct Pacer {
+    pacing: HeaderPacing,
+    window: Mutex<Option<Window>>,
+}
+
+impl Pacer {
+    pub(crate) fn new(pacing: HeaderPacing) -> Self {
+        Self {
+            pacing,
+            window: Mutex::new(None),
+        }
+    }
+
+    /// Take the rate limit of a response into account; responses without both headers are ignored
+    pub(crate) fn observe(&self, headers: &HeaderMap) {
+        let value = |name: &str| {
+            headers
+                .get(name)?
+                .to_str()
+                .ok()?
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite() && *value >= 0.0)
+        };
+        let (Some(remaining), Some(reset)) = (
+            value(&self.pacing.remaining_header),
+            value(&self.pacing.reset_header),
+        ) else {
+            return;
+        };
+        let until_reset = if reset > EPOCH_THRESHOLD {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            reset - now
+        } else {
+            reset
+        };
+        if until_reset <= 0.0 {
+            return;
+        }
+        let until_reset = until_reset.min(MAX_UNTIL_RESET);
+        let Some(resets) = Instant::now().checked_add(Duration::from_secs_f64(until_reset)) else {
+            return;
+        };
+        // With nothing left, the next request waits for the reset
+        let rate = (remaining * self.pacing.share).max(1.0) / until_reset;
+        let window = Window { rate, resets };
+        let mut current = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        if current.is_none() {
+            info!(
+                "Pacing at {:.2} req/s by the {} header",
+                rate, self.pacing.remaining_header
+            );
+        }
+        *current = Some(window);
+    }
+
+    /// Rate the latest response allows, `None` when none published one or its window has reset
+    pub(crate) fn rate(&self) -> Option<f64> {
+        let window = (*self.window.lock().unwrap_or_else(|e| e.into_inner()))?;
+        (Instant::now() < window.resets).then_some(window.rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_header_pacing_from_str() {
+        let pacing: HeaderPacing = "RateLimit-Remaining, RateLimit-Reset, 80%".parse().unwrap();
+        assert_eq!(pacing.remaining_header, "RateLimit-Remaining");
+        assert_eq!(pacing.reset_header, "RateLimit-Reset");
+        assert_eq!(pacing.share, 0.8);
+        assert_eq!(
+            pacing.to_string(),
+            "RateLimit-Remaining,RateLimit-Reset,80%"
+        );
+        assert_eq!(
+            "X-RateLimit-Remaining,X-RateLimit-Reset"
+                .parse::<HeaderPacing>()
+                .unwrap(),
+            HeaderPacing::default()
+        );
+
+        for invalid in [
+            "",
+            "X-Remaining",
+            "a,b,c,d",
+            "a,b,0%",
+            "a,b,150%",
+            "a,b,90",
+            "a b,c",
+        ] {
+            assert!(
+                matches!(
+                    invalid.parse::<HeaderPacing>(),
+                    Err(ConfigError::InvalidPacing(_))
+                ),
+                "{} should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_pacer_follows_headers() {
+        let pacer = Pacer::new(HeaderPacing::default());
+        assert_eq!(pacer.rate(), None);
+
+        let headers = |remaining: &str, reset: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                DEFAULT_REMAINING_HEADER,
+                HeaderValue::from_str(remaining).unwrap(),
+            );
+            headers.insert(DEFAULT_RESET_HEADER, HeaderValue::from_str(reset).unwrap());
+            headers
+        };
+        pacer.observe(&headers("100", "10"));
+        assert_eq!(pacer.rate(), Some(9.0));
+
+        // Nothing left: one request once the window resets
+        pacer.observe(&headers("0", "5"));
+        assert_eq!(pacer.rate(), Some(0.2));
+
+        let epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        pacer.observe(&headers("50", &(epoch + 100).to_string()));
+        let rate = pacer.rate().unwrap();
+        assert!(rate > 0.44 && rate < 0.46, "{}", rate);
+
+        // Incomplete or unparsable headers leave the pace as it is
+        pacer.observe(&HeaderMap::new());
+        pacer.observe(&headers("many", "10"));
+        assert_eq!(pacer.rate(), Some(rate));
+
+        // Resets far ahead are read as a day from now
+        pacer.observe(&headers("0", "1e300"));
+        assert_eq!(pacer.rate(), Some(1.0 / MAX_UNTIL_RESET));
+    }
+}
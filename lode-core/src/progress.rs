@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Minimum window over which the current request rate is measured
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Point-in-time view of a running load test
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProgressSnapshot {
+    pub completed_requests: u64,
+    pub total_requests: u64,
+    pub percent_complete: f64,
+    pub elapsed_seconds: f64,
+    pub current_rps: f64,
+    pub eta_seconds: Option<f64>,
+}
+
+#[derive(Debug)]
+struct RateWindow {
+    started_at: Instant,
+    completed: u64,
+    rps: f64,
+}
+
+#[derive(Debug)]
+struct ProgressState {
+    started_at: Option<Instant>,
+    finished_after: Option<Duration>,
+    window: RateWindow,
+}
+
+#[derive(Debug)]
+struct ProgressInner {
+    total: AtomicU64,
+    completed: AtomicU64,
+    state: Mutex<ProgressState>,
+}
+
+/// Shared progress counters of a load test run
+///
+/// The handle is cheap to clone; the engine updates it while the run is in
+/// flight and any clone can take a [`ProgressSnapshot`] at any time.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    inner: Arc<ProgressInner>,
+}
+
+impl Progress {
+    /// Create a progress handle for a run that has not started yet
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(ProgressInner {
+                total: AtomicU64::new(0),
+                completed: AtomicU64::new(0),
+                state: Mutex::new(ProgressState {
+                    started_at: None,
+                    finished_after: None,
+                    window: RateWindow {
+                        started_at: Instant::now(),
+                        completed: 0,
+                        rps: 0.0,
+                    },
+                }),
+            }),
+        }
+    }
+
+    /// Progress of a run that finished after `elapsed`, e.g. one read back from saved history
+    pub fn finished(completed_requests: u64, total_requests: u64, elapsed: Duration) -> Self {
+        let progress = Self::new();
+        progress
+            .inner
+            .total
+            .store(total_requests, Ordering::Relaxed);
+        progress
+            .inner
+            .completed
+            .store(completed_requests, Ordering::Relaxed);
+        {
+            let mut state = progress.lock_state();
+            state.started_at = Some(Instant::now());
+            state.finished_after = Some(elapsed);
+        }
+        progress
+    }
+
+    /// Mark the run as started with the given number of requests
+    pub fn start(&self, total_requests: u64) {
+        let now = Instant::now();
+        self.inner.total.store(total_requests, Ordering::Relaxed);
+        self.inner.completed.store(0, Ordering::Relaxed);
+        let mut state = self.lock_state();
+        state.started_at = Some(now);
+        state.finished_after = None;
+        state.window = RateWindow {
+            started_at: now,
+            completed: 0,
+            rps: 0.0,
+        };
+    }
+
+    /// Record a completed request
+    pub fn record(&self) {
+        self.inner.completed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Remove requests that will not be sent after all from the total
+    pub fn skip(&self, requests: u64) {
+        self.inner.total.fetch_sub(requests, Ordering::Relaxed);
+    }
+
+    /// Mark the run as finished, freezing the elapsed time
+    pub fn finish(&self) {
+        let mut state = self.lock_state();
+        state.finished_after = state.started_at.map(|t| t.elapsed());
+    }
+
+    /// Number of completed requests
+    pub fn completed_requests(&self) -> u64 {
+        self.inner.completed.load(Ordering::Relaxed)
+    }
+
+    /// Total number of requests of the run
+    pub fn total_requests(&self) -> u64 {
+        self.inner.total.load(Ordering::Relaxed)
+    }
+
+    /// Take a snapshot of the current progress
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        let completed = self.completed_requests();
+        let total = self.total_requests();
+        let mut state = self.lock_state();
+
+        let elapsed = match (state.finished_after, state.started_at) {
+            (Some(finished_after), _) => finished_after,
+            (None, Some(started_at)) => started_at.elapsed(),
+            (None, None) => Duration::ZERO,
+        };
+        let finished = state.finished_after.is_some();
+
+        let window_elapsed = state.window.started_at.elapsed();
+        if !finished && window_elapsed >= RATE_WINDOW {
+            state.window.rps =
+                (completed - state.window.completed) as f64 / window_elapsed.as_secs_f64();
+            state.window.started_at = Instant::now();
+            state.window.completed = completed;
+        }
+
+        let average_rps = if elapsed.as_secs_f64() > 0.0 {
+            completed as f64 / elapsed.as_secs_f64()
+        } else {
+            0.0
+        };
+        let current_rps = if finished || state.window.rps == 0.0 {
+            average_rps
+        } else {
+            state.window.rps
+        };
+
+        let remaining = total.saturating_sub(completed);
+        let eta_seconds = if remaining == 0 && state.started_at.is_some() {
+            Some(0.0)
+        } else if average_rps > 0.0 {
+            Some(remaining as f64 / average_rps)
+        } else {
+            None
+        };
+
+        ProgressSnapshot {
+            completed_requests: completed,
+            total_requests: total,
+            percent_complete: if total > 0 {
+                completed as f64 / total as f64 * 100.0
+            } else {
+                0.0
+            },
+            elapsed_seconds: elapsed.as_secs_f64(),
+            current_rps,
+            eta_seconds,
+        }
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, ProgressState> {
+        self.inner
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_progress_before_start() {
+        let progress = Progress::new();
+        let snapshot = progress.snapshot();
+        assert_eq!(snapshot.completed_requests, 0);
+        assert_eq!(snapshot.total_requests, 0);
+        assert_eq!(snapshot.percent_complete, 0.0);
+        assert_eq!(snapshot.eta_seconds, None);
+    }
+
+    #[test]
+    fn test_progress_snapshot() {
+        let progress = Progress::new();
+        progress.start(10);
+        thread::sleep(Duration::from_millis(50));
+        for _ in 0..5 {
+            progress.record();
+        }
+
+        let snapshot = progress.clone().snapshot();
+        assert_eq!(snapshot.completed_requests, 5);
+        assert_eq!(snapshot.total_requests, 10);
+        assert_eq!(snapshot.percent_complete, 50.0);
+        assert!(snapshot.elapsed_seconds >= 0.05);
+        assert!(snapshot.current_rps > 0.0);
+
+        // Half of the requests are done, so the rest should take about as long again
+        let eta = snapshot.eta_seconds.unwrap();
+        assert!(eta > 0.0 && eta <= snapshot.elapsed_seconds * 1.5);
+    }
+
+    #[test]
+    fn test_progress_finish_freezes_elapsed() {
+        let progress = Progress::new();
+        progress.start(2);
+        progress.record();
+        progress.record();
+        progress.finish();
+
+        let first = progress.snapshot();
+        thread::sleep(Duration::from_millis(20));
+        let second = progress.snapshot();
+        assert_eq!(first.elapsed_seconds, second.elapsed_seconds);
+        assert_eq!(second.percent_complete, 100.0);
+        assert_eq!(second.eta_seconds, Some(0.0));
+    }
+}
@@ -0,0 +1,151 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+use crate::metrics::TestMetrics;
+use crate::report::Report;
+
+/// Writes the metrics collected so far to a recovery file when the process panics
+///
+/// A bug surfacing late in a long run would otherwise discard everything it measured. Once
+/// [`Recovery::install`]ed, a panic on any thread finalizes the metrics of the run being watched,
+/// marks its report `crashed` and writes it as JSON, after the usual panic message.
+#[derive(Debug, Clone)]
+pub struct Recovery {
+    path: PathBuf,
+    watched: Arc<StdMutex<Option<Watched>>>,
+    /// Set once the report was written, so panics on several threads write it once
+    dumped: Arc<AtomicBool>,
+}
+
+#[derive(Debug)]
+struct Watched {
+    metrics: Arc<Mutex<TestMetrics>>,
+    started: Instant,
+    /// Set once the engine finalized the metrics itself
+    finished: bool,
+}
+
+impl Recovery {
+    /// Create a recovery writing to `path`; nothing is written until it is installed
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            watched: Default::default(),
+            dumped: Default::default(),
+        }
+    }
+
+    /// Path of the recovery file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Chain a panic hook writing the recovery file after the current hook
+    pub fn install(&self) {
+        let recovery = self.clone();
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            previous(info);
+            match recovery.dump() {
+                Ok(true) => eprintln!(
+                    "Metrics collected before the panic written to {}",
+                    recovery.path.display()
+                ),
+                Ok(false) => {}
+                Err(e) => eprintln!(
+                    "Failed to write the metrics collected before the panic to {}: {}",
+                    recovery.path.display(),
+                    e
+                ),
+            }
+        }));
+    }
+
+    /// Watch the metrics of a run started at `started`
+    pub(crate) fn watch(&self, metrics: &Arc<Mutex<TestMetrics>>, started: Instant) {
+        *self.lock() = Some(Watched {
+            metrics: Arc::clone(metrics),
+            started,
+            finished: false,
+        });
+    }
+
+    /// Record that the watched run finalized its metrics, which are then written as they are
+    pub(crate) fn finish(&self) {
+        if let Some(watched) = self.lock().as_mut() {
+            watched.finished = true;
+        }
+    }
+
+    /// Write the report of the watched run, `false` when there is none or it was already written
+    pub fn dump(&self) -> Result<bool, String> {
+        let Some((metrics, started, finished)) = self.lock().as_ref().map(|watched| {
+            (
+                Arc::clone(&watched.metrics),
+                watched.started,
+                watched.finished,
+            )
+        }) else {
+            return Ok(false);
+        };
+        // The panicking thread may hold the lock, leaving the metrics half updated
+        let mut metrics = metrics
+            .try_lock()
+            .map_err(|_| "The metrics were being updated when the panic occurred".to_string())?;
+        if self.dumped.swap(true, Ordering::SeqCst) {
+            return Ok(false);
+        }
+        if !finished {
+            metrics.mark_crashed();
+            futures::executor::block_on(metrics.finalize(started.elapsed()))?;
+        }
+        let report = Report::from_test_metrics(&metrics).map_err(|e| e.to_string())?;
+        let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, json).map_err(|e| e.to_string())?;
+        Ok(true)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Watched>> {
+        self.watched.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    use crate::metrics::RequestMetrics;
+
+    #[tokio::test]
+    async fn test_dump_watched_metrics() {
+        let path =
+            std::env::temp_dir().join(format!("lode-recovery-{}.json", uuid::Uuid::new_v4()));
+        let recovery = Recovery::new(&path);
+        assert_eq!(recovery.dump(), Ok(false));
+
+        let metrics = Arc::new(Mutex::new(TestMetrics::new().unwrap()));
+        recovery.watch(&metrics, Instant::now());
+        for _ in 0..3 {
+            let request = RequestMetrics::new().complete(StatusCode::OK);
+            metrics.lock().await.record_request(request);
+        }
+
+        {
+            let _held = metrics.lock().await;
+            assert!(recovery.dump().is_err());
+        }
+        // Only the first panic writes the file
+        assert_eq!(recovery.dump(), Ok(true));
+        assert_eq!(recovery.dump(), Ok(false));
+
+        let report: Report =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(report.status, "crashed");
+        assert_eq!(report.total_requests, 3);
+    }
+}
@@ -0,0 +1,246 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::format::{self, TextOptions};
+use super::Report;
+use crate::error::{Error, Result};
+
+/// Confidence level of the intervals of an [`Aggregate`]
+pub const CONFIDENCE: f64 = 0.95;
+
+/// Two-sided 95% critical values of Student's t distribution for 1 to 30 degrees of freedom
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// Critical value of the normal distribution, close enough to t beyond 30 degrees of freedom
+const Z_95: f64 = 1.960;
+
+/// Repeated runs of the same test summed up as the mean of each figure with its confidence
+/// interval and run-to-run variation
+///
+/// A single run's percentiles are noisy; gating on the mean of a few runs, or on the bound of its
+/// interval, tells a regression from a bad run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Aggregate {
+    /// Runs aggregated
+    pub runs: usize,
+    /// Confidence level of the intervals, e.g. 0.95
+    pub confidence: f64,
+    /// Runs whose status was not `completed`, their figures cover part of the test
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partial_runs: Vec<String>,
+    pub metrics: Vec<MetricSpread>,
+}
+
+/// One figure across the aggregated runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct MetricSpread {
+    /// Name of the figure in the report, e.g. `p95_response_time_ms`
+    pub metric: String,
+    pub mean: f64,
+    /// Sample standard deviation between the runs
+    pub std_dev: f64,
+    /// Standard deviation relative to the mean, in percent
+    pub variation_percent: f64,
+    /// Bounds of the confidence interval of the mean
+    pub ci_low: f64,
+    pub ci_high: f64,
+    pub min: f64,
+    pub max: f64,
+    /// The figure of each run, in the order the runs were given
+    pub values: Vec<f64>,
+}
+
+impl MetricSpread {
+    fn new(metric: &str, values: Vec<f64>) -> Self {
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        let std_dev = variance.sqrt();
+        let critical = T_95.get(values.len() - 2).copied().unwrap_or(Z_95);
+        let margin = critical * std_dev / n.sqrt();
+        Self {
+            metric: metric.to_string(),
+            mean,
+            std_dev,
+            variation_percent: if mean == 0.0 {
+                0.0
+            } else {
+                std_dev / mean.abs() * 100.0
+            },
+            ci_low: mean - margin,
+            ci_high: mean + margin,
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            values,
+        }
+    }
+
+    /// Half the width of the confidence interval
+    pub fn margin(&self) -> f64 {
+        (self.ci_high - self.ci_low) / 2.0
+    }
+}
+
+impl Aggregate {
+    /// Aggregate the reports of at least two runs of the same test
+    pub fn from_reports(reports: &[Report]) -> Result<Self> {
+        if reports.len() < 2 {
+            return Err(Error::Report(
+                "At least two runs are needed to aggregate them".to_string(),
+            ));
+        }
+        let spread = |metric: &str, value: fn(&Report) -> f64| {
+            MetricSpread::new(metric, reports.iter().map(value).collect())
+        };
+        Ok(Self {
+            runs: reports.len(),
+            confidence: CONFIDENCE,
+            partial_runs: reports
+                .iter()
+                .filter(|report| report.status != "completed")
+                .map(|report| report.id.clone())
+                .collect(),
+            metrics: vec![
+                spread("requests_per_second", |r| r.requests_per_second),
+                spread("error_rate_percent", |r| {
+                    if r.total_requests == 0 {
+                        0.0
+                    } else {
+                        r.failed_requests as f64 / r.total_requests as f64 * 100.0
+                    }
+                }),
+                spread("mean_response_time_ms", |r| r.mean_response_time_ms),
+                spread("median_response_time_ms", |r| r.median_response_time_ms),
+                spread("p95_response_time_ms", |r| r.p95_response_time_ms),
+                spread("p99_response_time_ms", |r| r.p99_response_time_ms),
+                spread("p999_response_time_ms", |r| r.p999_response_time_ms),
+            ],
+        })
+    }
+
+    /// The spread of a figure by its name in the report
+    pub fn metric(&self, metric: &str) -> Option<&MetricSpread> {
+        self.metrics.iter().find(|spread| spread.metric == metric)
+    }
+
+    /// Format the aggregate as JSON
+    pub fn as_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Report(format!("Failed to serialize aggregate: {}", e)))
+    }
+
+    /// Format the aggregate as a human-readable string, a line per figure with its mean, the
+    /// confidence interval and the run-to-run variation
+    pub fn as_string_with(&self, options: &TextOptions) -> String {
+        let mut lines = vec![
+            format!(
+                "Aggregate of {} runs ({}% confidence intervals)",
+                self.runs,
+                self.confidence * 100.0
+            ),
+            "----------------".to_string(),
+        ];
+        if !self.partial_runs.is_empty() {
+            lines.push(format!(
+                "Partial runs included: {}",
+                self.partial_runs.join(", ")
+            ));
+        }
+        for spread in &self.metrics {
+            let value = |v: f64| match spread.metric.as_str() {
+                "requests_per_second" => format::decimal(v, 2, options.locale),
+                "error_rate_percent" => format!("{}%", format::decimal(v, 2, options.locale)),
+                _ => options.duration_ms(v),
+            };
+            lines.push(format!(
+                "{}: {} ± {} ({} to {}), run-to-run variation {}% (min {}, max {})",
+                label(&spread.metric),
+                value(spread.mean),
+                value(spread.margin()),
+                value(spread.ci_low),
+                value(spread.ci_high),
+                format::decimal(spread.variation_percent, 1, options.locale),
+                value(spread.min),
+                value(spread.max)
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Label of a figure in the text aggregate, as in the text report
+pub(super) fn label(metric: &str) -> &str {
+    match metric {
+        "requests_per_second" => "Requests/second",
+        "error_rate_percent" => "Error Rate",
+        "mean_response_time_ms" => "Mean Response Time",
+        "median_response_time_ms" => "Median Response Time",
+        "p95_response_time_ms" => "P95 Response Time",
+        "p99_response_time_ms" => "P99 Response Time",
+        "p999_response_time_ms" => "P99.9 Response Time",
+        metric => metric,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(rps: f64, p95: f64, failed: u64) -> Report {
+        Report {
+            id: format!("run-{}", rps),
+            status: "completed".to_string(),
+            total_requests: 100,
+            failed_requests: failed,
+            requests_per_second: rps,
+            p95_response_time_ms: p95,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_aggregate_runs() {
+        let reports = [
+            report(100.0, 120.0, 0),
+            report(110.0, 130.0, 2),
+            report(90.0, 110.0, 1),
+        ];
+        let aggregate = Aggregate::from_reports(&reports).unwrap();
+        assert_eq!(aggregate.runs, 3);
+        assert!(aggregate.partial_runs.is_empty());
+
+        let rps = aggregate.metric("requests_per_second").unwrap();
+        assert_eq!(rps.mean, 100.0);
+        assert_eq!(rps.std_dev, 10.0);
+        assert_eq!(rps.variation_percent, 10.0);
+        assert_eq!((rps.min, rps.max), (90.0, 110.0));
+        // t(2) = 4.303 over sqrt(3) runs
+        assert!((rps.margin() - 24.843).abs() < 0.01, "{}", rps.margin());
+
+        let errors = aggregate.metric("error_rate_percent").unwrap();
+        assert_eq!(errors.values, [0.0, 2.0, 1.0]);
+
+        let text = aggregate.as_string_with(&TextOptions::default());
+        assert!(text.contains("Aggregate of 3 runs (95% confidence intervals)"));
+        assert!(
+            text.contains("Requests/second: 100.00 ± 24.84 (75.16 to 124.84)"),
+            "{}",
+            text
+        );
+        assert!(text.contains("P95 Response Time: 120 ms"), "{}", text);
+    }
+
+    #[test]
+    fn test_aggregate_needs_two_runs() {
+        assert!(Aggregate::from_reports(&[report(100.0, 120.0, 0)]).is_err());
+
+        let mut partial = report(50.0, 300.0, 0);
+        partial.status = "cancelled".to_string();
+        let aggregate = Aggregate::from_reports(&[report(100.0, 120.0, 0), partial]).unwrap();
+        assert_eq!(aggregate.partial_runs, ["run-50"]);
+    }
+}
@@ -0,0 +1,291 @@
+use hdrhistogram::Histogram;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::path::{Path, PathBuf};
+
+use super::{Report, TimeBucketStats, TimeSeries};
+use crate::error::{Error, Result};
+
+const WIDTH: u32 = 960;
+const HEIGHT: u32 = 540;
+const FONT: &str = "sans-serif";
+
+/// Bars of the distribution chart
+const DISTRIBUTION_BARS: u64 = 50;
+
+/// Percentile drawn by the distribution chart as its highest response time, so a few outliers do
+/// not squeeze the bars of every other request into one
+const DISTRIBUTION_PERCENTILE: f64 = 99.9;
+
+type DrawResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+/// A chart drawn the same way on every image backend
+trait Chart {
+    /// File name of the chart, without extension
+    const NAME: &'static str;
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> DrawResult
+    where
+        DB::ErrorType: 'static;
+}
+
+/// Line of the latency chart: its name, color and the value it takes in a bucket
+type PercentileLine = (&'static str, RGBColor, fn(&TimeBucketStats) -> f64);
+
+/// Percentiles of the requests completed in every interval of the run
+struct LatencyChart<'a>(&'a TimeSeries);
+
+impl Chart for LatencyChart<'_> {
+    const NAME: &'static str = "latency";
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> DrawResult
+    where
+        DB::ErrorType: 'static,
+    {
+        let series = self.0;
+        let x_max = series
+            .buckets
+            .last()
+            .map_or(0.0, |bucket| bucket.start_seconds)
+            .max(series.interval_seconds);
+        let y_max = series
+            .buckets
+            .iter()
+            .map(|bucket| bucket.p99_response_time_ms)
+            .fold(1.0, f64::max)
+            * 1.1;
+
+        root.fill(&WHITE)?;
+        let mut chart = ChartBuilder::on(root)
+            .caption("Response time over time", (FONT, 24))
+            .margin(16)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            .build_cartesian_2d(0.0..x_max, 0.0..y_max)?;
+        chart
+            .configure_mesh()
+            .x_desc("Seconds since the start of the run")
+            .y_desc("Response time (ms)")
+            .draw()?;
+
+        let lines: [PercentileLine; 3] = [
+            ("p50", GREEN, |bucket| bucket.median_response_time_ms),
+            ("p95", BLUE, |bucket| bucket.p95_response_time_ms),
+            ("p99", RED, |bucket| bucket.p99_response_time_ms),
+        ];
+        for (name, color, value) in lines {
+            // Intervals without completed requests have no response times to draw
+            let points = series
+                .buckets
+                .iter()
+                .filter(|bucket| bucket.total_requests > 0)
+                .map(|bucket| (bucket.start_seconds, value(bucket)));
+            chart
+                .draw_series(LineSeries::new(points, color.stroke_width(2)))?
+                .label(name)
+                .legend(move |(x, y)| {
+                    PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2))
+                });
+        }
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+        root.present()?;
+        Ok(())
+    }
+}
+
+/// Requests per response time, up to [`DISTRIBUTION_PERCENTILE`]
+struct DistributionChart {
+    /// Lower and upper bound of every bar in milliseconds, with its requests
+    bars: Vec<(f64, f64, u64)>,
+}
+
+impl DistributionChart {
+    /// Bars of the response times of a histogram in microseconds
+    fn new(histogram: &Histogram<u64>) -> Self {
+        let top = histogram
+            .value_at_percentile(DISTRIBUTION_PERCENTILE)
+            .max(DISTRIBUTION_BARS);
+        let width = top.div_ceil(DISTRIBUTION_BARS);
+        let mut counts = vec![0; DISTRIBUTION_BARS as usize];
+        for value in histogram.iter_recorded() {
+            if let Some(count) = counts.get_mut((value.value_iterated_to() / width) as usize) {
+                *count += value.count_at_value();
+            }
+        }
+        let ms = |micros: u64| micros as f64 / 1000.0;
+        Self {
+            bars: counts
+                .into_iter()
+                .enumerate()
+                .map(|(index, count)| {
+                    let start = index as u64 * width;
+                    (ms(start), ms(start + width), count)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Chart for DistributionChart {
+    const NAME: &'static str = "distribution";
+
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> DrawResult
+    where
+        DB::ErrorType: 'static,
+    {
+        let x_max = self.bars.last().map_or(1.0, |(_, end, _)| *end);
+        let y_max = self
+            .bars
+            .iter()
+            .map(|(_, _, count)| *count)
+            .max()
+            .unwrap_or(0) as f64
+            * 1.1;
+
+        root.fill(&WHITE)?;
+        let mut chart = ChartBuilder::on(root)
+            .caption(
+                format!(
+                    "Response time distribution, up to p{}",
+                    DISTRIBUTION_PERCENTILE
+                ),
+                (FONT, 24),
+            )
+            .margin(16)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            .build_cartesian_2d(0.0..x_max, 0.0..y_max.max(1.0))?;
+        chart
+            .configure_mesh()
+            .x_desc("Response time (ms)")
+            .y_desc("Requests")
+            .draw()?;
+        chart.draw_series(self.bars.iter().map(|(start, end, count)| {
+            Rectangle::new([(*start, 0.0), (*end, *count as f64)], BLUE.filled())
+        }))?;
+        root.present()?;
+        Ok(())
+    }
+}
+
+/// Draw a chart to an SVG and a PNG image in `dir`, returning their paths
+fn render<C: Chart>(chart: &C, dir: &Path) -> Result<Vec<PathBuf>> {
+    let failed = |e: Box<dyn std::error::Error>| {
+        Error::Report(format!("Failed to render the {} chart: {}", C::NAME, e))
+    };
+    let svg = dir.join(format!("{}.svg", C::NAME));
+    chart
+        .draw(&SVGBackend::new(&svg, (WIDTH, HEIGHT)).into_drawing_area())
+        .map_err(failed)?;
+    let png = dir.join(format!("{}.png", C::NAME));
+    chart
+        .draw(&BitMapBackend::new(&png, (WIDTH, HEIGHT)).into_drawing_area())
+        .map_err(failed)?;
+    Ok(vec![svg, png])
+}
+
+impl Report {
+    /// Render static charts of the report to `dir` as SVG and PNG images, e.g. to embed in a wiki
+    /// page, returning the files written
+    ///
+    /// The distribution of response times is drawn from the histogram, and their percentiles over
+    /// time when a time series was recorded. Text is drawn with a sans-serif font of the system.
+    pub fn render_charts(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(dir)?;
+        let mut files = Vec::new();
+        if let Some(series) = self
+            .time_series
+            .as_ref()
+            .filter(|series| !series.buckets.is_empty())
+        {
+            files.extend(render(&LatencyChart(series), dir)?);
+        }
+        if let Some(histogram) = self.response_times()? {
+            files.extend(render(&DistributionChart::new(&histogram), dir)?);
+        }
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::hdr;
+
+    #[test]
+    fn test_distribution_bars() {
+        let mut histogram = Histogram::<u64>::new_with_bounds(1, 60_000_000, 3).unwrap();
+        histogram.record_n(1_000, 1_800).unwrap();
+        histogram.record_n(4_000, 199).unwrap();
+        histogram.record(1_000_000).unwrap();
+
+        // The outlier is past the last bar
+        let chart = DistributionChart::new(&histogram);
+        assert_eq!(chart.bars.len(), DISTRIBUTION_BARS as usize);
+        assert_eq!(
+            chart.bars.iter().map(|(_, _, count)| count).sum::<u64>(),
+            1_999
+        );
+        let bar = chart
+            .bars
+            .iter()
+            .find(|(start, end, _)| (*start..*end).contains(&1.0))
+            .unwrap();
+        assert_eq!(bar.2, 1_800);
+        assert!(chart.bars.last().unwrap().1 < 4.2);
+    }
+
+    #[test]
+    fn test_render_charts() {
+        let mut histogram = Histogram::<u64>::new_with_bounds(1, 60_000_000, 3).unwrap();
+        histogram.record_n(12_000, 10).unwrap();
+        let bucket = |start_seconds: f64| TimeBucketStats {
+            start_seconds,
+            total_requests: 10,
+            failed_requests: 0,
+            requests_per_second: 10.0,
+            mean_response_time_ms: 12.0,
+            median_response_time_ms: 12.0,
+            p95_response_time_ms: 15.0,
+            p99_response_time_ms: 20.0,
+        };
+        let report = Report {
+            response_time_histogram: Some(hdr::encode(&histogram).unwrap()),
+            time_series: Some(TimeSeries {
+                interval_seconds: 1.0,
+                buckets: vec![bucket(0.0), bucket(1.0)],
+            }),
+            ..Default::default()
+        };
+
+        let dir = std::env::temp_dir().join(format!("lode-charts-{}", uuid::Uuid::new_v4()));
+        let files = report.render_charts(&dir).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|file| file.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "latency.svg",
+                "latency.png",
+                "distribution.svg",
+                "distribution.png"
+            ]
+        );
+        let svg = std::fs::read_to_string(dir.join("latency.svg")).unwrap();
+        assert!(svg.contains("Response time over time"));
+        assert!(std::fs::read(dir.join("distribution.png"))
+            .unwrap()
+            .starts_with(b"\x89PNG"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(Report::default().render_charts(&dir).unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
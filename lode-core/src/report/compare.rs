@@ -0,0 +1,333 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::aggregate::label;
+use super::format::{self, Locale, TextOptions};
+use super::Report;
+use crate::error::{Error, Result};
+
+/// How much worse than the baseline a figure may get before it counts as a regression
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Tolerances {
+    /// Drop of the requests per second, in percent
+    pub throughput_percent: f64,
+    /// Rise of a response time percentile, in percent
+    pub latency_percent: f64,
+    /// Rise of the error rate, in percentage points
+    pub error_rate_points: f64,
+}
+
+impl Default for Tolerances {
+    fn default() -> Self {
+        Self {
+            throughput_percent: 5.0,
+            latency_percent: 10.0,
+            error_rate_points: 1.0,
+        }
+    }
+}
+
+impl Tolerances {
+    /// Check that no tolerance is negative, which would flag unchanged figures
+    pub fn validate(&self) -> Result<()> {
+        for (name, tolerance) in [
+            ("throughput", self.throughput_percent),
+            ("latency", self.latency_percent),
+            ("error rate", self.error_rate_points),
+        ] {
+            if tolerance.is_nan() || tolerance < 0.0 {
+                return Err(Error::Config(format!(
+                    "The {} tolerance must not be negative, got {}",
+                    name, tolerance
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A run compared with a baseline run of the same test, figure by figure
+///
+/// Lets CI fail a build whose load test got slower or less reliable than the last accepted one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Comparison {
+    /// Id of the baseline run
+    pub baseline: String,
+    /// Id of the compared run
+    pub current: String,
+    pub tolerances: Tolerances,
+    pub deltas: Vec<MetricDelta>,
+}
+
+/// One figure of the baseline and the compared run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct MetricDelta {
+    /// Name of the figure in the report, e.g. `p95_response_time_ms`
+    pub metric: String,
+    pub baseline: f64,
+    pub current: f64,
+    /// Difference of the compared run to the baseline, in the unit of the figure
+    pub change: f64,
+    /// Difference relative to the baseline in percent, missing when the baseline is 0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_percent: Option<f64>,
+    /// Whether the figure got worse by more than its tolerance
+    pub regressed: bool,
+}
+
+impl MetricDelta {
+    fn new(metric: &str, baseline: f64, current: f64) -> Self {
+        Self {
+            metric: metric.to_string(),
+            baseline,
+            current,
+            change: current - baseline,
+            change_percent: (baseline != 0.0).then(|| (current - baseline) / baseline * 100.0),
+            regressed: false,
+        }
+    }
+}
+
+impl Report {
+    /// Compare this run with a `baseline` run, flagging the figures that got worse by more than
+    /// the `tolerances`
+    ///
+    /// Throughput regresses when it drops, the error rate and the response time percentiles when
+    /// they rise. A figure that was 0 in the baseline only regresses for the error rate.
+    pub fn compare(&self, baseline: &Report, tolerances: &Tolerances) -> Comparison {
+        let mut deltas = Vec::new();
+
+        let mut throughput = MetricDelta::new(
+            "requests_per_second",
+            baseline.requests_per_second,
+            self.requests_per_second,
+        );
+        throughput.regressed = throughput
+            .change_percent
+            .is_some_and(|change| -change > tolerances.throughput_percent);
+        deltas.push(throughput);
+
+        let mut errors = MetricDelta::new(
+            "error_rate_percent",
+            error_rate_percent(baseline),
+            error_rate_percent(self),
+        );
+        errors.regressed = errors.change > tolerances.error_rate_points;
+        deltas.push(errors);
+
+        let latency = |metric: &str, value: fn(&Report) -> f64| {
+            let mut latency = MetricDelta::new(metric, value(baseline), value(self));
+            latency.regressed = latency
+                .change_percent
+                .is_some_and(|change| change > tolerances.latency_percent);
+            latency
+        };
+        deltas.extend([
+            latency("median_response_time_ms", |r| r.median_response_time_ms),
+            latency("p95_response_time_ms", |r| r.p95_response_time_ms),
+            latency("p99_response_time_ms", |r| r.p99_response_time_ms),
+            latency("p999_response_time_ms", |r| r.p999_response_time_ms),
+        ]);
+
+        Comparison {
+            baseline: baseline.id.clone(),
+            current: self.id.clone(),
+            tolerances: *tolerances,
+            deltas,
+        }
+    }
+}
+
+fn error_rate_percent(report: &Report) -> f64 {
+    if report.total_requests == 0 {
+        0.0
+    } else {
+        report.failed_requests as f64 / report.total_requests as f64 * 100.0
+    }
+}
+
+impl Comparison {
+    /// The figures that got worse by more than their tolerance
+    pub fn regressions(&self) -> impl Iterator<Item = &MetricDelta> {
+        self.deltas.iter().filter(|delta| delta.regressed)
+    }
+
+    pub fn regressed(&self) -> bool {
+        self.regressions().next().is_some()
+    }
+
+    /// The delta of a figure by its name in the report
+    pub fn metric(&self, metric: &str) -> Option<&MetricDelta> {
+        self.deltas.iter().find(|delta| delta.metric == metric)
+    }
+
+    /// Format the comparison as JSON
+    pub fn as_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Report(format!("Failed to serialize comparison: {}", e)))
+    }
+
+    /// Format the comparison as a human-readable string, a line per figure with both values, the
+    /// change and whether it is a regression
+    pub fn as_string_with(&self, options: &TextOptions) -> String {
+        let mut lines = vec![
+            format!(
+                "Run {} compared with baseline {}",
+                self.current, self.baseline
+            ),
+            "----------------".to_string(),
+        ];
+        let locale = options.locale;
+        for delta in &self.deltas {
+            let value = |v: f64| match delta.metric.as_str() {
+                "requests_per_second" => format::decimal(v, 2, locale),
+                "error_rate_percent" => format!("{}%", format::decimal(v, 2, locale)),
+                _ => options.duration_ms(v),
+            };
+            let (change, tolerance) = match delta.metric.as_str() {
+                "requests_per_second" => (
+                    percent(delta.change_percent, locale),
+                    format!(
+                        "-{}%",
+                        format::decimal(self.tolerances.throughput_percent, 1, locale)
+                    ),
+                ),
+                "error_rate_percent" => (
+                    format!(
+                        "{}{} points",
+                        sign(delta.change),
+                        format::decimal(delta.change, 2, locale)
+                    ),
+                    format!(
+                        "+{} points",
+                        format::decimal(self.tolerances.error_rate_points, 2, locale)
+                    ),
+                ),
+                _ => (
+                    percent(delta.change_percent, locale),
+                    format!(
+                        "+{}%",
+                        format::decimal(self.tolerances.latency_percent, 1, locale)
+                    ),
+                ),
+            };
+            lines.push(format!(
+                "{} {}: {} -> {} ({}, tolerance {})",
+                if delta.regressed { "FAIL" } else { "PASS" },
+                label(&delta.metric),
+                value(delta.baseline),
+                value(delta.current),
+                change,
+                tolerance
+            ));
+        }
+        let regressions = self.regressions().count();
+        lines.push(match regressions {
+            0 => "No regressions".to_string(),
+            1 => "1 regression".to_string(),
+            n => format!("{} regressions", n),
+        });
+        lines.join("\n")
+    }
+}
+
+/// A relative change with its sign, or `new` when the baseline was 0
+fn percent(change: Option<f64>, locale: Locale) -> String {
+    match change {
+        Some(change) => format!("{}{}%", sign(change), format::decimal(change, 1, locale)),
+        None => "new".to_string(),
+    }
+}
+
+/// Plus sign of a rise, a drop is signed by its number
+fn sign(change: f64) -> &'static str {
+    if change > 0.0 {
+        "+"
+    } else {
+        ""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(id: &str, rps: f64, p95: f64, failed: u64) -> Report {
+        Report {
+            id: id.to_string(),
+            total_requests: 1000,
+            failed_requests: failed,
+            requests_per_second: rps,
+            median_response_time_ms: 50.0,
+            p95_response_time_ms: p95,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_compare_flags_regressions_beyond_tolerance() {
+        let baseline = report("base", 200.0, 100.0, 5);
+        let current = report("new", 180.0, 108.0, 30);
+        let comparison = current.compare(&baseline, &Tolerances::default());
+
+        let rps = comparison.metric("requests_per_second").unwrap();
+        assert_eq!(rps.change, -20.0);
+        assert_eq!(rps.change_percent, Some(-10.0));
+        assert!(rps.regressed);
+        // 8% slower is within the default 10%
+        let p95 = comparison.metric("p95_response_time_ms").unwrap();
+        assert!(!p95.regressed);
+        let errors = comparison.metric("error_rate_percent").unwrap();
+        assert!((errors.change - 2.5).abs() < 1e-9);
+        assert!(errors.regressed);
+        // A percentile missing from both reports is unchanged
+        assert!(
+            !comparison
+                .metric("p999_response_time_ms")
+                .unwrap()
+                .regressed
+        );
+
+        assert_eq!(comparison.regressions().count(), 2);
+        let text = comparison.as_string_with(&TextOptions::default());
+        assert!(text.contains("Run new compared with baseline base"));
+        assert!(
+            text.contains("FAIL Requests/second: 200.00 -> 180.00 (-10.0%, tolerance -5.0%)"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("PASS P95 Response Time: 100 ms -> 108 ms (+8.0%, tolerance +10.0%)"),
+            "{}",
+            text
+        );
+        assert!(text.contains("Error Rate: 0.50% -> 3.00% (+2.50 points"));
+        assert!(text.ends_with("2 regressions"));
+    }
+
+    #[test]
+    fn test_compare_improvement_is_no_regression() {
+        let baseline = report("base", 200.0, 100.0, 5);
+        let current = report("new", 260.0, 60.0, 0);
+        let strict = Tolerances {
+            throughput_percent: 0.0,
+            latency_percent: 0.0,
+            error_rate_points: 0.0,
+        };
+        let comparison = current.compare(&baseline, &strict);
+        assert!(!comparison.regressed());
+        assert!(comparison
+            .as_string_with(&TextOptions::default())
+            .ends_with("No regressions"));
+    }
+
+    #[test]
+    fn test_tolerances_validation() {
+        assert!(Tolerances::default().validate().is_ok());
+        let negative = Tolerances {
+            latency_percent: -1.0,
+            ..Default::default()
+        };
+        assert!(negative.validate().is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use chrono::SecondsFormat;
+use std::io::Write;
+use tracing::warn;
+
+use super::Report;
+use crate::error::Result;
+
+/// Header row of [`Report::as_csv`]
+pub const HEADER: &str = "timestamp,duration_ms,status,error";
+
+impl Report {
+    /// Format the recorded requests as CSV, one row per request in completion order
+    ///
+    /// Requests are only recorded when the run had `record_requests` set; without them only the
+    /// header is written. Missing durations, statuses and errors are left empty. Requests flushed
+    /// to disk under memory pressure are read back; the rows from the first one that cannot be
+    /// are left out with a warning, [`Report::write_csv`] reports the error instead.
+    pub fn as_csv(&self) -> String {
+        let mut csv = Vec::with_capacity((self.requests.len() + 1) * 64);
+        if let Err(e) = self.write_csv(&mut csv) {
+            warn!("Failed to read back the requests flushed to disk: {}", e);
+        }
+        String::from_utf8_lossy(&csv).into_owned()
+    }
+
+    /// Write the rows of [`Report::as_csv`] to `writer` as they are read, without holding the
+    /// CSV in memory, failing when the requests flushed to disk cannot be read back
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(HEADER.as_bytes())?;
+        writer.write_all(b"\r\n")?;
+        for request in self.recorded_requests()? {
+            let request = request?;
+            write!(
+                writer,
+                "{},{},{},{}\r\n",
+                request
+                    .timestamp
+                    .to_rfc3339_opts(SecondsFormat::Micros, true),
+                request
+                    .duration
+                    .map(|d| format!("{:.3}", d.as_secs_f64() * 1000.0))
+                    .unwrap_or_default(),
+                request.status.map(|s| s.to_string()).unwrap_or_default(),
+                request.error.as_deref().map(escape).unwrap_or_default(),
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Quote a field holding a separator, quote or line break, as in RFC 4180
+///
+/// A field a spreadsheet would read as a formula, starting with `=`, `+`, `-`, `@`, a tab or a
+/// carriage return, is prefixed with `'` so that an error echoing a response cannot run one.
+fn escape(field: &str) -> String {
+    let field = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", field)
+    } else {
+        field.to_string()
+    };
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{RequestRecord, RequestSpill};
+    use chrono::{TimeZone, Utc};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_csv_rows() {
+        let timestamp = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        let report = Report {
+            requests: vec![
+                RequestRecord {
+                    timestamp,
+                    duration: Some(Duration::from_micros(12_345)),
+                    status: Some(200),
+                    error: None,
+                },
+                RequestRecord {
+                    timestamp,
+                    duration: Some(Duration::from_millis(3)),
+                    status: Some(500),
+                    error: Some("HTTP 500".to_string()),
+                },
+                RequestRecord {
+                    timestamp,
+                    duration: None,
+                    status: None,
+                    error: Some("error sending request, \"connection refused\"".to_string()),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let csv = report.as_csv();
+        let rows: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(rows[0], HEADER);
+        assert_eq!(rows[1], "2026-01-02T03:04:05.000000Z,12.345,200,");
+        assert_eq!(rows[2], "2026-01-02T03:04:05.000000Z,3.000,500,HTTP 500");
+        assert_eq!(
+            rows[3],
+            "2026-01-02T03:04:05.000000Z,,,\"error sending request, \"\"connection refused\"\"\""
+        );
+        assert_eq!(rows[4], "");
+    }
+
+    #[test]
+    fn test_csv_with_requests_flushed_to_disk() {
+        let record = |status| RequestRecord {
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+            duration: Some(Duration::from_millis(2)),
+            status: Some(status),
+            error: None,
+        };
+        let spill = RequestSpill::new(&std::env::temp_dir());
+        spill.append(&[record(200), record(201)]).unwrap();
+        let report = Report {
+            requests: vec![record(202)],
+            spilled_requests: Some(Arc::new(spill)),
+            ..Default::default()
+        };
+        assert_eq!(report.recorded_request_count(), 3);
+        let csv = report.as_csv();
+        let statuses: Vec<&str> = csv
+            .lines()
+            .skip(1)
+            .map(|row| row.split(',').nth(2).unwrap())
+            .collect();
+        assert_eq!(statuses, ["200", "201", "202"]);
+
+        let mut written = Vec::new();
+        report.write_csv(&mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), csv);
+    }
+
+    #[test]
+    fn test_csv_fields_are_not_formulas() {
+        assert_eq!(
+            escape("=HYPERLINK(\"http://x\")"),
+            "\"'=HYPERLINK(\"\"http://x\"\")\""
+        );
+        assert_eq!(escape("+1"), "'+1");
+        assert_eq!(escape("-2+3"), "'-2+3");
+        assert_eq!(escape("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(escape("\tcmd"), "'\tcmd");
+        assert_eq!(escape("HTTP 500 - upstream"), "HTTP 500 - upstream");
+    }
+
+    #[test]
+    fn test_csv_without_recorded_requests() {
+        assert_eq!(Report::default().as_csv(), format!("{}\r\n", HEADER));
+    }
+}
@@ -0,0 +1,313 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Number conventions used in human-readable reports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    /// `1,234.5`
+    #[default]
+    En,
+    /// `1.234,5`
+    De,
+    /// `1 234,5`, grouped with a narrow no-break space
+    Fr,
+}
+
+impl Locale {
+    fn separators(&self) -> (&'static str, &'static str) {
+        match self {
+            Locale::En => (",", "."),
+            Locale::De => (".", ","),
+            Locale::Fr => ("\u{202f}", ","),
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    /// Parse a language tag such as `de`, `fr-FR` or a POSIX locale such as `en_US.UTF-8`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let language = s
+            .split(['-', '_', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Ok(Locale::En),
+            "de" => Ok(Locale::De),
+            "fr" => Ok(Locale::Fr),
+            _ => Err(format!("Unsupported locale: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Locale::En => "en",
+            Locale::De => "de",
+            Locale::Fr => "fr",
+        })
+    }
+}
+
+/// Unit response times are shown in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LatencyUnit {
+    /// µs, ms or s depending on the size of each value
+    #[default]
+    Auto,
+    Micros,
+    Millis,
+    Seconds,
+}
+
+impl LatencyUnit {
+    /// Most readable unit for a response time in milliseconds
+    fn for_ms(ms: f64) -> Self {
+        if ms == 0.0 {
+            LatencyUnit::Millis
+        } else if ms.abs() < 1.0 {
+            LatencyUnit::Micros
+        } else if ms.abs() < 1000.0 {
+            LatencyUnit::Millis
+        } else {
+            LatencyUnit::Seconds
+        }
+    }
+
+    fn per_ms(&self) -> f64 {
+        match self {
+            LatencyUnit::Micros => 1000.0,
+            LatencyUnit::Auto | LatencyUnit::Millis => 1.0,
+            LatencyUnit::Seconds => 0.001,
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            LatencyUnit::Micros => "µs",
+            LatencyUnit::Auto | LatencyUnit::Millis => "ms",
+            LatencyUnit::Seconds => "s",
+        }
+    }
+}
+
+impl FromStr for LatencyUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Ok(LatencyUnit::Auto),
+            "us" | "µs" => Ok(LatencyUnit::Micros),
+            "ms" => Ok(LatencyUnit::Millis),
+            "s" => Ok(LatencyUnit::Seconds),
+            _ => Err(format!(
+                "Unsupported latency unit: {} (expected auto, us, ms or s)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LatencyUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LatencyUnit::Auto => "auto",
+            LatencyUnit::Micros => "us",
+            LatencyUnit::Millis => "ms",
+            LatencyUnit::Seconds => "s",
+        })
+    }
+}
+
+/// How numbers are written in the text report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextOptions {
+    pub locale: Locale,
+    pub latency_unit: LatencyUnit,
+    /// Decimals of response times; three significant digits when `None`
+    pub precision: Option<usize>,
+}
+
+impl TextOptions {
+    /// Format a response time in milliseconds with these options
+    pub fn duration_ms(&self, ms: f64) -> String {
+        duration(ms, self.latency_unit, self.precision, self.locale)
+    }
+}
+
+/// Format a count with thousands separators, e.g. `1,234,567`
+pub fn count(value: u64, locale: Locale) -> String {
+    group(&value.to_string(), locale.separators().0)
+}
+
+/// Format a count scaled to thousands or millions above 1000, e.g. `12.3K` or `4.5M`
+pub fn compact_count(value: u64, locale: Locale) -> String {
+    const UNITS: [(f64, &str); 3] = [(1e9, "B"), (1e6, "M"), (1e3, "K")];
+    UNITS
+        .iter()
+        .find(|(scale, _)| value as f64 >= *scale)
+        .map_or_else(
+            || value.to_string(),
+            |(scale, unit)| {
+                let scaled = value as f64 / scale;
+                let decimals = if scaled < 100.0 { 1 } else { 0 };
+                format!("{}{}", decimal(scaled, decimals, locale), unit)
+            },
+        )
+}
+
+/// Format a number with a fixed number of decimals and thousands separators
+pub fn decimal(value: f64, decimals: usize, locale: Locale) -> String {
+    let (thousands, point) = locale.separators();
+    let formatted = format!("{:.*}", decimals, value.abs());
+    let (integer, fraction) = match formatted.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (formatted.as_str(), None),
+    };
+
+    // Rounding can turn a tiny negative value into zero
+    let sign = if value < 0.0 && formatted.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+        "-"
+    } else {
+        ""
+    };
+    match fraction {
+        Some(fraction) => format!("{}{}{}{}", sign, group(integer, thousands), point, fraction),
+        None => format!("{}{}", sign, group(integer, thousands)),
+    }
+}
+
+/// Format a response time in milliseconds in the most readable unit: µs, ms or s
+///
+/// Values keep three significant digits, e.g. `123 µs`, `12.3 ms` or `2.07 s`.
+pub fn duration_ms(ms: f64, locale: Locale) -> String {
+    duration(ms, LatencyUnit::Auto, None, locale)
+}
+
+/// Format a response time in milliseconds in `unit` with `precision` decimals
+///
+/// Without a precision, values keep three significant digits like [`duration_ms`].
+pub fn duration(ms: f64, unit: LatencyUnit, precision: Option<usize>, locale: Locale) -> String {
+    let unit = match unit {
+        LatencyUnit::Auto => LatencyUnit::for_ms(ms),
+        unit => unit,
+    };
+    let value = ms * unit.per_ms();
+    let decimals = precision.unwrap_or(
+        if unit == LatencyUnit::Micros || value == value.trunc() || value.abs() >= 100.0 {
+            0
+        } else if value.abs() >= 10.0 {
+            1
+        } else {
+            2
+        },
+    );
+    format!("{} {}", decimal(value, decimals, locale), unit.symbol())
+}
+
+fn group(digits: &str, separator: &str) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 * separator.len());
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_grouping() {
+        assert_eq!(count(0, Locale::En), "0");
+        assert_eq!(count(999, Locale::En), "999");
+        assert_eq!(count(1_000, Locale::En), "1,000");
+        assert_eq!(count(1_234_567, Locale::En), "1,234,567");
+        assert_eq!(count(1_234_567, Locale::De), "1.234.567");
+        assert_eq!(count(1_234_567, Locale::Fr), "1\u{202f}234\u{202f}567");
+    }
+
+    #[test]
+    fn test_compact_count() {
+        assert_eq!(compact_count(999, Locale::En), "999");
+        assert_eq!(compact_count(1_000, Locale::En), "1.0K");
+        assert_eq!(compact_count(12_345, Locale::En), "12.3K");
+        assert_eq!(compact_count(123_456, Locale::En), "123K");
+        assert_eq!(compact_count(4_500_000, Locale::De), "4,5M");
+    }
+
+    #[test]
+    fn test_decimal() {
+        assert_eq!(decimal(1234.5678, 2, Locale::En), "1,234.57");
+        assert_eq!(decimal(1234.5678, 2, Locale::De), "1.234,57");
+        assert_eq!(decimal(-1234.5, 1, Locale::En), "-1,234.5");
+        assert_eq!(decimal(-0.001, 2, Locale::En), "0.00");
+        assert_eq!(decimal(10.0, 0, Locale::En), "10");
+    }
+
+    #[test]
+    fn test_duration_scaling() {
+        assert_eq!(duration_ms(0.0, Locale::En), "0 ms");
+        assert_eq!(duration_ms(0.123456789, Locale::En), "123 µs");
+        assert_eq!(duration_ms(1.23456, Locale::En), "1.23 ms");
+        assert_eq!(duration_ms(12.3456, Locale::En), "12.3 ms");
+        assert_eq!(duration_ms(100.0, Locale::En), "100 ms");
+        assert_eq!(duration_ms(551.423, Locale::En), "551 ms");
+        assert_eq!(duration_ms(2072.575291, Locale::En), "2.07 s");
+        assert_eq!(duration_ms(1_553_407.0, Locale::En), "1,553 s");
+        assert_eq!(duration_ms(1.5, Locale::De), "1,50 ms");
+    }
+
+    #[test]
+    fn test_duration_in_fixed_unit() {
+        assert_eq!(
+            duration(0.4, LatencyUnit::Millis, None, Locale::En),
+            "0.40 ms"
+        );
+        assert_eq!(
+            duration(0.4, LatencyUnit::Millis, Some(3), Locale::En),
+            "0.400 ms"
+        );
+        assert_eq!(
+            duration(0.4, LatencyUnit::Micros, Some(1), Locale::En),
+            "400.0 µs"
+        );
+        assert_eq!(
+            duration(2072.575291, LatencyUnit::Millis, Some(0), Locale::En),
+            "2,073 ms"
+        );
+        assert_eq!(
+            duration(250.0, LatencyUnit::Seconds, None, Locale::De),
+            "0,25 s"
+        );
+        assert_eq!(
+            duration(12.3456, LatencyUnit::Auto, Some(3), Locale::En),
+            "12.346 ms"
+        );
+    }
+
+    #[test]
+    fn test_latency_unit_parsing() {
+        assert_eq!("auto".parse(), Ok(LatencyUnit::Auto));
+        assert_eq!("us".parse(), Ok(LatencyUnit::Micros));
+        assert_eq!("µs".parse(), Ok(LatencyUnit::Micros));
+        assert_eq!("MS".parse(), Ok(LatencyUnit::Millis));
+        assert_eq!("s".parse(), Ok(LatencyUnit::Seconds));
+        assert!("min".parse::<LatencyUnit>().is_err());
+    }
+
+    #[test]
+    fn test_locale_parsing() {
+        assert_eq!("en".parse(), Ok(Locale::En));
+        assert_eq!("de_DE.UTF-8".parse(), Ok(Locale::De));
+        assert_eq!("fr-FR".parse(), Ok(Locale::Fr));
+        assert_eq!("C".parse(), Ok(Locale::En));
+        assert!("xx".parse::<Locale>().is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::SecondsFormat;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::Report;
+use crate::error::{Error, Result};
+use crate::trace::{TraceEntry, TracedBody};
+
+/// Version of the HAR format written by [`Report::as_har`]
+pub const HAR_VERSION: &str = "1.2";
+
+impl Report {
+    /// Format the traced requests as a HAR file, to open in browser devtools or share with the
+    /// owner of the target
+    ///
+    /// Requests are only traced when the run sampled them with `trace`; without them the log has
+    /// no entries. Headers are those the run set, not those the HTTP client adds on its own such
+    /// as `Host`. Bodies cut at the max body size of the sampling say so in a `comment`, bodies
+    /// that are not UTF-8 are base64 encoded and requests without a response carry their error
+    /// in `_error`, as browsers write it. Credential headers are redacted when traced, unless the
+    /// sampling keeps them.
+    pub fn as_har(&self) -> Result<String> {
+        let har = json!({
+            "log": {
+                "version": HAR_VERSION,
+                "creator": {"name": "lode", "version": env!("CARGO_PKG_VERSION")},
+                "pages": [],
+                "entries": self.traces.iter().map(entry).collect::<Vec<_>>(),
+            }
+        });
+        serde_json::to_string_pretty(&har)
+            .map_err(|e| Error::Report(format!("Failed to serialize HAR: {}", e)))
+    }
+}
+
+fn entry(trace: &TraceEntry) -> Value {
+    let http_version = trace
+        .response
+        .as_ref()
+        .map_or("HTTP/1.1", |response| response.http_version.as_str());
+    let mut request = json!({
+        "method": trace.method,
+        "url": trace.url,
+        "httpVersion": http_version,
+        "cookies": [],
+        "headers": headers(&trace.headers),
+        "queryString": query_string(&trace.url),
+        "headersSize": -1,
+        "bodySize": trace.body.as_ref().map_or(0, |body| body.size),
+    });
+    if let Some(body) = &trace.body {
+        request["postData"] = content(body, &trace.headers);
+    }
+
+    let response = match &trace.response {
+        Some(response) => json!({
+            "status": response.status,
+            "statusText": reqwest::StatusCode::from_u16(response.status)
+                .ok()
+                .and_then(|status| status.canonical_reason())
+                .unwrap_or_default(),
+            "httpVersion": response.http_version,
+            "cookies": [],
+            "headers": headers(&response.headers),
+            "content": match (&response.body, &response.body_error) {
+                (Some(body), _) => content(body, &response.headers),
+                (None, Some(error)) => json!({
+                    "size": -1,
+                    "mimeType": mime_type(&response.headers),
+                    "comment": format!("Failed to read: {}", error),
+                }),
+                (None, None) => json!({
+                    "size": -1,
+                    "mimeType": mime_type(&response.headers),
+                    "comment": "Discarded unread",
+                }),
+            },
+            "redirectURL": header(&response.headers, "location").unwrap_or_default(),
+            "headersSize": -1,
+            "bodySize": response.body.as_ref().map_or(-1, |body| body.size as i64),
+        }),
+        None => json!({
+            "status": 0,
+            "statusText": "",
+            "httpVersion": http_version,
+            "cookies": [],
+            "headers": [],
+            "content": {"size": 0, "mimeType": "x-unknown"},
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": -1,
+        }),
+    };
+
+    let mut entry = json!({
+        "startedDateTime": trace.started_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+        "time": millis(Some(trace.time)),
+        "request": request,
+        "response": response,
+        "cache": {},
+        "timings": {
+            "blocked": -1,
+            "dns": optional_millis(trace.dns),
+            "connect": optional_millis(trace.connect),
+            "ssl": optional_millis(trace.tls),
+            "send": 0,
+            "wait": millis(trace.wait),
+            "receive": millis(trace.receive),
+        },
+    });
+    if let Some(error) = &trace.error {
+        entry["_error"] = Value::from(error.as_str());
+    }
+    entry
+}
+
+fn headers(headers: &[(String, String)]) -> Vec<Value> {
+    headers
+        .iter()
+        .map(|(name, value)| json!({"name": name, "value": value}))
+        .collect()
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn mime_type(headers: &[(String, String)]) -> &str {
+    header(headers, "content-type").unwrap_or("x-unknown")
+}
+
+fn query_string(url: &str) -> Vec<Value> {
+    url::Url::parse(url)
+        .map(|url| {
+            url.query_pairs()
+                .map(|(name, value)| json!({"name": name, "value": value}))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The `content` of a response or `postData` of a request, text or base64 encoded bytes
+fn content(body: &TracedBody, headers: &[(String, String)]) -> Value {
+    let mut content = json!({
+        "size": body.size,
+        "mimeType": mime_type(headers),
+    });
+    match std::str::from_utf8(&body.bytes) {
+        Ok(text) => content["text"] = Value::from(text),
+        Err(_) => {
+            content["text"] = Value::from(BASE64.encode(&body.bytes));
+            content["encoding"] = Value::from("base64");
+        }
+    }
+    if body.truncated() {
+        content["comment"] = Value::from(format!(
+            "Truncated at {} of {} bytes",
+            body.bytes.len(),
+            body.size
+        ));
+    }
+    content
+}
+
+fn millis(duration: Option<Duration>) -> f64 {
+    duration.map_or(0.0, |d| d.as_secs_f64() * 1000.0)
+}
+
+/// Milliseconds of an optional timing, -1 when it does not apply
+fn optional_millis(duration: Option<Duration>) -> f64 {
+    duration.map_or(-1.0, |d| d.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::TracedResponse;
+
+    #[test]
+    fn test_har_of_traced_requests() {
+        let mut sent = TraceEntry::new("POST", "https://example.com/orders?page=2");
+        sent.time = Duration::from_millis(120);
+        sent.headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+        sent.body = Some(TracedBody::new(br#"{"item":1}"#, 10, 4096));
+        sent.tls = Some(Duration::from_millis(30));
+        sent.connect = sent.tls;
+        sent.wait = Some(Duration::from_millis(80));
+        sent.receive = Some(Duration::from_millis(10));
+        sent.response = Some(TracedResponse {
+            status: 201,
+            http_version: "HTTP/2.0".to_string(),
+            headers: vec![("content-type".to_string(), "text/plain".to_string())],
+            body: Some(TracedBody::new(b"created order 1", 15, 7)),
+            body_error: None,
+        });
+        let mut failed = TraceEntry::new("GET", "http://example.com/");
+        failed.error = Some("connection refused".to_string());
+        let report = Report {
+            traces: vec![sent, failed],
+            ..Default::default()
+        };
+
+        let har: Value = serde_json::from_str(&report.as_har().unwrap()).unwrap();
+        assert_eq!(har["log"]["version"], "1.2");
+        assert_eq!(har["log"]["creator"]["name"], "lode");
+        let entries = har["log"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let sent = &entries[0];
+        assert_eq!(sent["time"], 120.0);
+        assert_eq!(sent["request"]["method"], "POST");
+        assert_eq!(sent["request"]["httpVersion"], "HTTP/2.0");
+        assert_eq!(
+            sent["request"]["queryString"],
+            json!([{"name": "page", "value": "2"}])
+        );
+        assert_eq!(sent["request"]["postData"]["text"], r#"{"item":1}"#);
+        assert_eq!(sent["request"]["postData"]["mimeType"], "application/json");
+        assert_eq!(sent["response"]["status"], 201);
+        assert_eq!(sent["response"]["statusText"], "Created");
+        let content = &sent["response"]["content"];
+        assert_eq!(content["text"], "created");
+        assert_eq!(content["size"], 15);
+        assert_eq!(content["comment"], "Truncated at 7 of 15 bytes");
+        assert_eq!(sent["timings"]["dns"], -1.0);
+        assert_eq!(sent["timings"]["ssl"], 30.0);
+        assert_eq!(sent["timings"]["wait"], 80.0);
+
+        let failed = &entries[1];
+        assert_eq!(failed["response"]["status"], 0);
+        assert_eq!(failed["_error"], "connection refused");
+        assert!(failed["request"].get("postData").is_none());
+    }
+
+    #[test]
+    fn test_har_encodes_binary_bodies() {
+        let mut upload = TraceEntry::new("PUT", "http://example.com/blob");
+        upload.body = Some(TracedBody::new(&[0xff, 0x00, 0x10], 3, 4096));
+        let report = Report {
+            traces: vec![upload],
+            ..Default::default()
+        };
+        let har: Value = serde_json::from_str(&report.as_har().unwrap()).unwrap();
+        let post_data = &har["log"]["entries"][0]["request"]["postData"];
+        assert_eq!(post_data["encoding"], "base64");
+        assert_eq!(post_data["text"], "/wAQ");
+    }
+
+    #[test]
+    fn test_har_tells_unread_from_failed_bodies() {
+        let response = |body_error: Option<&str>| {
+            let mut entry = TraceEntry::new("GET", "http://example.com/");
+            entry.response = Some(TracedResponse {
+                status: 200,
+                http_version: "HTTP/1.1".to_string(),
+                headers: vec![],
+                body: None,
+                body_error: body_error.map(str::to_string),
+            });
+            entry
+        };
+        let report = Report {
+            traces: vec![response(None), response(Some("connection reset"))],
+            ..Default::default()
+        };
+        let har: Value = serde_json::from_str(&report.as_har().unwrap()).unwrap();
+        let entries = &har["log"]["entries"];
+        assert_eq!(
+            entries[0]["response"]["content"]["comment"],
+            "Discarded unread"
+        );
+        assert_eq!(
+            entries[1]["response"]["content"]["comment"],
+            "Failed to read: connection reset"
+        );
+    }
+
+    #[test]
+    fn test_har_without_traces() {
+        let har: Value = serde_json::from_str(&Report::default().as_har().unwrap()).unwrap();
+        assert_eq!(har["log"]["entries"], json!([]));
+    }
+}
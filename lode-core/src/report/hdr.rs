@@ -0,0 +1,55 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hdrhistogram::serialization::{Deserializer, Serializer, V2DeflateSerializer};
+use hdrhistogram::Histogram;
+
+use crate::error::{Error, Result};
+
+/// Encode a histogram in the compressed HdrHistogram V2 format, as base64
+///
+/// This is the encoding of HdrHistogram interval logs, so the HdrHistogram libraries of other
+/// languages can decode it, e.g. `HdrHistogram.decode` of the Python `hdrh` package.
+pub fn encode(histogram: &Histogram<u64>) -> Result<String> {
+    let mut bytes = Vec::new();
+    V2DeflateSerializer::new()
+        .serialize(histogram, &mut bytes)
+        .map_err(|e| Error::Report(format!("Failed to encode histogram: {:?}", e)))?;
+    Ok(STANDARD.encode(bytes))
+}
+
+/// Decode a histogram written by [`encode`]
+pub fn decode(encoded: &str) -> Result<Histogram<u64>> {
+    let bytes = STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| Error::Report(format!("Invalid histogram encoding: {}", e)))?;
+    Deserializer::new()
+        .deserialize(&mut bytes.as_slice())
+        .map_err(|e| Error::Report(format!("Failed to decode histogram: {:?}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_round_trip() {
+        let mut histogram = Histogram::<u64>::new_with_bounds(1, 60_000_000, 3).unwrap();
+        for value in [120, 450, 450, 9_800, 2_072_575] {
+            histogram.record(value).unwrap();
+        }
+
+        let decoded = decode(&encode(&histogram).unwrap()).unwrap();
+        assert_eq!(decoded.len(), 5);
+        assert_eq!(decoded, histogram);
+        assert_eq!(
+            decoded.value_at_percentile(50.0),
+            histogram.value_at_percentile(50.0)
+        );
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert!(matches!(decode("not base64!"), Err(Error::Report(_))));
+        assert!(matches!(decode("AAAA"), Err(Error::Report(_))));
+    }
+}
@@ -0,0 +1,463 @@
+use std::fmt::Write;
+
+use super::format::{self, Locale};
+use super::Report;
+use crate::scenario::ENDPOINT_LABEL;
+
+/// Relative change after which a figure counts as a regression (or an improvement)
+pub const REGRESSION_THRESHOLD: f64 = 0.05;
+
+const CURRENT_COLOR: &str = "#2563eb";
+const BASELINE_COLOR: &str = "#9ca3af";
+
+const CHART_WIDTH: f64 = 640.0;
+const CHART_HEIGHT: f64 = 300.0;
+const MARGIN_LEFT: f64 = 70.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 20.0;
+const MARGIN_BOTTOM: f64 = 50.0;
+const GRID_LINES: usize = 4;
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2rem auto;max-width:720px;color:#111827}\
+h1{font-size:1.5rem}h2{font-size:1.15rem;margin-top:2rem}\
+table{border-collapse:collapse;width:100%}th,td{padding:.35rem .6rem;text-align:right;border-bottom:1px solid #e5e7eb}\
+th:first-child,td:first-child{text-align:left}\
+.worse{color:#b91c1c;font-weight:600}.better{color:#15803d;font-weight:600}\
+.legend span{display:inline-block;margin-right:1rem}.legend i{display:inline-block;width:.8rem;height:.8rem;margin-right:.3rem;vertical-align:middle}\
+svg text{font-size:11px;fill:#374151}";
+
+/// One run drawn on a chart
+struct Series<'a> {
+    name: &'a str,
+    color: &'a str,
+    values: Vec<Option<f64>>,
+}
+
+/// A row of the summary table
+struct Figure {
+    name: &'static str,
+    current: f64,
+    baseline: Option<f64>,
+    lower_is_better: bool,
+}
+
+impl Figure {
+    /// Relative change from the baseline, positive when the figure got worse
+    fn regression(&self) -> Option<f64> {
+        let baseline = self.baseline.filter(|b| *b != 0.0)?;
+        let change = (self.current - baseline) / baseline;
+        Some(if self.lower_is_better {
+            change
+        } else {
+            -change
+        })
+    }
+}
+
+impl Report {
+    /// Format the report as a standalone HTML page with inline SVG charts
+    ///
+    /// With a baseline, both runs are drawn on the same charts and the summary shows how every
+    /// figure changed, highlighting changes beyond [`REGRESSION_THRESHOLD`].
+    pub fn as_html(&self, baseline: Option<&Report>) -> String {
+        let mut runs = vec![Series {
+            name: "Current",
+            color: CURRENT_COLOR,
+            values: Vec::new(),
+        }];
+        if baseline.is_some() {
+            runs.push(Series {
+                name: "Baseline",
+                color: BASELINE_COLOR,
+                values: Vec::new(),
+            });
+        }
+
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Load Test Report {id}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+             <h1>Load Test Report</h1>\n<p>Run <code>{id}</code>",
+            id = escape(&self.id),
+        );
+        if let Some(baseline) = baseline {
+            let _ = write!(
+                html,
+                " compared with baseline <code>{}</code>",
+                escape(&baseline.id)
+            );
+        }
+        html.push_str("</p>\n");
+        html.push_str(&legend(&runs));
+
+        html.push_str("<h2>Summary</h2>\n");
+        html.push_str(&summary_table(&self.figures(baseline), baseline.is_some()));
+
+        let percentiles = ["Min", "P50", "P95", "P99", "P99.9", "Max"];
+        let distribution = |report: &Report| {
+            [
+                report.min_response_time_ms,
+                report.median_response_time_ms,
+                report.p95_response_time_ms,
+                report.p99_response_time_ms,
+                report.p999_response_time_ms,
+                report.max_response_time_ms,
+            ]
+            .into_iter()
+            .map(Some)
+            .collect()
+        };
+        runs[0].values = distribution(self);
+        if let Some(baseline) = baseline {
+            runs[1].values = distribution(baseline);
+        }
+        html.push_str("<h2>Latency Distribution</h2>\n");
+        html.push_str(&line_chart(&percentiles, &runs, "ms"));
+
+        runs[0].values = vec![Some(self.requests_per_second)];
+        if let Some(baseline) = baseline {
+            runs[1].values = vec![Some(baseline.requests_per_second)];
+        }
+        html.push_str("<h2>Throughput</h2>\n");
+        html.push_str(&bar_chart(&["Requests/second"], &runs, "req/s"));
+
+        if !self.endpoints.is_empty() {
+            let names: Vec<&str> = self
+                .endpoints
+                .iter()
+                .map(|endpoint| endpoint.labels[ENDPOINT_LABEL].as_str())
+                .collect();
+            runs[0].values = self
+                .endpoints
+                .iter()
+                .map(|endpoint| Some(endpoint.p95_response_time_ms))
+                .collect();
+            if let Some(baseline) = baseline {
+                runs[1].values = names
+                    .iter()
+                    .map(|name| {
+                        baseline
+                            .endpoints
+                            .iter()
+                            .find(|endpoint| endpoint.labels[ENDPOINT_LABEL] == *name)
+                            .map(|endpoint| endpoint.p95_response_time_ms)
+                    })
+                    .collect();
+            }
+            html.push_str("<h2>P95 by Endpoint</h2>\n");
+            html.push_str(&bar_chart(&names, &runs, "ms"));
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn figures(&self, baseline: Option<&Report>) -> Vec<Figure> {
+        let error_rate = |report: &Report| {
+            if report.total_requests > 0 {
+                report.failed_requests as f64 / report.total_requests as f64 * 100.0
+            } else {
+                0.0
+            }
+        };
+        let figure = |name, value: fn(&Report) -> f64, lower_is_better| Figure {
+            name,
+            current: value(self),
+            baseline: baseline.map(value),
+            lower_is_better,
+        };
+
+        vec![
+            figure("Total requests", |r| r.total_requests as f64, false),
+            Figure {
+                name: "Error rate (%)",
+                current: error_rate(self),
+                baseline: baseline.map(error_rate),
+                lower_is_better: true,
+            },
+            figure("Requests/second", |r| r.requests_per_second, false),
+            figure("Mean (ms)", |r| r.mean_response_time_ms, true),
+            figure("Median (ms)", |r| r.median_response_time_ms, true),
+            figure("P95 (ms)", |r| r.p95_response_time_ms, true),
+            figure("P99 (ms)", |r| r.p99_response_time_ms, true),
+            figure("P99.9 (ms)", |r| r.p999_response_time_ms, true),
+            figure("Max (ms)", |r| r.max_response_time_ms, true),
+        ]
+    }
+}
+
+fn legend(runs: &[Series]) -> String {
+    let mut html = String::from("<p class=\"legend\">");
+    for run in runs {
+        let _ = write!(
+            html,
+            "<span><i style=\"background:{}\"></i>{}</span>",
+            run.color, run.name
+        );
+    }
+    html.push_str("</p>\n");
+    html
+}
+
+fn summary_table(figures: &[Figure], compare: bool) -> String {
+    let mut html = String::from("<table>\n<tr><th></th><th>Current</th>");
+    if compare {
+        html.push_str("<th>Baseline</th><th>Change</th>");
+    }
+    html.push_str("</tr>\n");
+
+    for figure in figures {
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td>{}</td>",
+            figure.name,
+            format::decimal(figure.current, 2, Locale::En)
+        );
+        if compare {
+            let baseline = figure
+                .baseline
+                .map_or("-".to_string(), |b| format::decimal(b, 2, Locale::En));
+            let change = match (figure.baseline, figure.regression()) {
+                (Some(baseline), Some(regression)) => {
+                    let class = if regression > REGRESSION_THRESHOLD {
+                        " class=\"worse\""
+                    } else if regression < -REGRESSION_THRESHOLD {
+                        " class=\"better\""
+                    } else {
+                        ""
+                    };
+                    format!(
+                        "<td{}>{:+.1}%</td>",
+                        class,
+                        (figure.current - baseline) / baseline * 100.0
+                    )
+                }
+                _ => "<td>-</td>".to_string(),
+            };
+            let _ = write!(html, "<td>{}</td>{}", baseline, change);
+        }
+        html.push_str("</tr>\n");
+    }
+
+    html.push_str("</table>\n");
+    html
+}
+
+/// Upper bound of the value axis, rounded up to a readable step
+fn axis_max(runs: &[Series]) -> f64 {
+    let max = runs
+        .iter()
+        .flat_map(|run| run.values.iter().flatten())
+        .fold(0.0_f64, |max, value| max.max(*value));
+    if max <= 0.0 {
+        return 1.0;
+    }
+    let magnitude = 10_f64.powf(max.log10().floor());
+    let step = [1.0, 2.0, 2.5, 5.0, 10.0]
+        .into_iter()
+        .map(|step| step * magnitude / GRID_LINES as f64)
+        .find(|step| step * GRID_LINES as f64 >= max)
+        .unwrap_or(magnitude);
+    step * GRID_LINES as f64
+}
+
+/// Opening tag, grid and value axis shared by all charts
+fn chart_frame(max: f64, unit: &str) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {CHART_WIDTH} {CHART_HEIGHT}\" \
+         width=\"100%\" role=\"img\">\n"
+    );
+    let plot_height = CHART_HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    for line in 0..=GRID_LINES {
+        let value = max * line as f64 / GRID_LINES as f64;
+        let y = MARGIN_TOP + plot_height * (1.0 - line as f64 / GRID_LINES as f64);
+        let _ = writeln!(
+            svg,
+            "<line x1=\"{MARGIN_LEFT}\" y1=\"{y:.1}\" x2=\"{:.1}\" y2=\"{y:.1}\" stroke=\"#e5e7eb\"/>\
+             <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{} {}</text>",
+            CHART_WIDTH - MARGIN_RIGHT,
+            MARGIN_LEFT - 6.0,
+            y + 4.0,
+            format_value(value),
+            unit
+        );
+    }
+    svg
+}
+
+fn category_label(svg: &mut String, x: f64, label: &str) {
+    let _ = writeln!(
+        svg,
+        "<text x=\"{x:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+        CHART_HEIGHT - MARGIN_BOTTOM + 18.0,
+        escape(label)
+    );
+}
+
+fn y_of(value: f64, max: f64) -> f64 {
+    let plot_height = CHART_HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    MARGIN_TOP + plot_height * (1.0 - value / max)
+}
+
+/// Runs drawn as lines over the categories, e.g. a latency distribution over percentiles
+fn line_chart(categories: &[&str], runs: &[Series], unit: &str) -> String {
+    let max = axis_max(runs);
+    let mut svg = chart_frame(max, unit);
+    let step = (CHART_WIDTH - MARGIN_LEFT - MARGIN_RIGHT) / categories.len() as f64;
+    let x_of = |index: usize| MARGIN_LEFT + step * (index as f64 + 0.5);
+
+    for (index, category) in categories.iter().enumerate() {
+        category_label(&mut svg, x_of(index), category);
+    }
+
+    // Draw the baseline first so the current run stays on top
+    for run in runs.iter().rev() {
+        let points: Vec<(f64, f64)> = run
+            .values
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| value.map(|v| (x_of(index), y_of(v, max))))
+            .collect();
+        let path = points
+            .iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>",
+            path, run.color
+        );
+        for ((x, y), value) in points.iter().zip(run.values.iter().flatten()) {
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"3.5\" fill=\"{}\">\
+                 <title>{}: {} {}</title></circle>",
+                run.color,
+                run.name,
+                format_value(*value),
+                unit
+            );
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Runs drawn as bars side by side for each category
+fn bar_chart(categories: &[&str], runs: &[Series], unit: &str) -> String {
+    let max = axis_max(runs);
+    let mut svg = chart_frame(max, unit);
+    let step = (CHART_WIDTH - MARGIN_LEFT - MARGIN_RIGHT) / categories.len() as f64;
+    let bar_width = (step * 0.7 / runs.len() as f64).min(60.0);
+
+    for (index, category) in categories.iter().enumerate() {
+        let center = MARGIN_LEFT + step * (index as f64 + 0.5);
+        category_label(&mut svg, center, category);
+
+        let group_start = center - bar_width * runs.len() as f64 / 2.0;
+        for (position, run) in runs.iter().enumerate() {
+            let Some(value) = run.values.get(index).copied().flatten() else {
+                continue;
+            };
+            let y = y_of(value, max);
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{:.1}\" y=\"{y:.1}\" width=\"{bar_width:.1}\" height=\"{:.1}\" fill=\"{}\">\
+                 <title>{}: {} {}</title></rect>",
+                group_start + bar_width * position as f64,
+                CHART_HEIGHT - MARGIN_BOTTOM - y,
+                run.color,
+                run.name,
+                format_value(value),
+                unit
+            );
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn format_value(value: f64) -> String {
+    if value >= 100.0 || value == value.trunc() {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(id: &str, p95: f64, rps: f64) -> Report {
+        Report {
+            id: id.to_string(),
+            total_requests: 100,
+            successful_requests: 100,
+            requests_per_second: rps,
+            min_response_time_ms: 10.0,
+            median_response_time_ms: 40.0,
+            p95_response_time_ms: p95,
+            p99_response_time_ms: p95 * 1.5,
+            p999_response_time_ms: p95 * 2.0,
+            max_response_time_ms: p95 * 2.5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_html_report_without_baseline() {
+        let html = report("run-<1>", 80.0, 250.0).as_html(None);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("run-&lt;1&gt;"));
+        assert!(html.contains("Latency Distribution"));
+        assert!(html.contains("<polyline"));
+        assert!(!html.contains("Baseline"));
+        assert!(!html.contains("P95 by Endpoint"));
+    }
+
+    #[test]
+    fn test_html_report_overlays_baseline() {
+        let current = report("current", 120.0, 200.0);
+        let baseline = report("baseline", 80.0, 201.0);
+        let html = current.as_html(Some(&baseline));
+
+        // Both runs on the latency chart
+        assert_eq!(html.matches("<polyline").count(), 2);
+        assert!(html.contains(&format!("stroke=\"{}\"", BASELINE_COLOR)));
+        assert!(html.contains("compared with baseline <code>baseline</code>"));
+
+        // P95 went from 80 to 120ms, throughput barely moved
+        assert!(html.contains(
+            "<td>P95 (ms)</td><td>120.00</td><td>80.00</td><td class=\"worse\">+50.0%</td>"
+        ));
+        assert!(
+            html.contains("<td>Requests/second</td><td>200.00</td><td>201.00</td><td>-0.5%</td>")
+        );
+    }
+
+    #[test]
+    fn test_axis_max_is_rounded_up() {
+        let series = |values: &[f64]| Series {
+            name: "run",
+            color: CURRENT_COLOR,
+            values: values.iter().copied().map(Some).collect(),
+        };
+
+        assert_eq!(axis_max(&[series(&[73.0])]), 100.0);
+        assert_eq!(axis_max(&[series(&[3.0]), series(&[180.0])]), 200.0);
+        assert_eq!(axis_max(&[series(&[0.0])]), 1.0);
+    }
+}
@@ -0,0 +1,174 @@
+use chrono::SecondsFormat;
+use std::fmt::Write;
+
+use super::format::TextOptions;
+use super::Report;
+
+impl Report {
+    /// Format the report as a JUnit XML test suite, so CI servers show the outcome of a load test
+    /// like that of their other tests
+    ///
+    /// The run itself is a test case failing when it did not complete, followed by a test case per
+    /// objective checked with [`Report::check_slos`] and per threshold checked with
+    /// [`Report::check_thresholds`]. The headline figures are suite properties.
+    pub fn as_junit_xml(&self) -> String {
+        let options = TextOptions::default();
+        let mut cases = Vec::with_capacity(1 + self.slos.len() + self.thresholds.len());
+        let run_failure = (self.status != "completed").then(|| match &self.abort_reason {
+            Some(reason) => format!("Run {}: {}", self.status, reason),
+            None => format!("Run {}, the figures cover part of the test", self.status),
+        });
+        cases.push(("run", "completed".to_string(), run_failure));
+        for result in &self.slos {
+            cases.push((
+                "slo",
+                result.slo.to_string(),
+                (!result.passed).then(|| result.as_string_with(&options)),
+            ));
+        }
+        for result in &self.thresholds {
+            cases.push((
+                "threshold",
+                result.threshold.to_string(),
+                (!result.passed).then(|| result.as_string_with(&options)),
+            ));
+        }
+
+        let failures = cases
+            .iter()
+            .filter(|(_, _, failure)| failure.is_some())
+            .count();
+        let time = format!("{:.3}", self.total_duration_seconds);
+        let name = escape(&format!("lode {}", self.id));
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"lode\" tests=\"{}\" failures=\"{}\" time=\"{}\">",
+            cases.len(),
+            failures,
+            time
+        );
+        let _ = write!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"0\" time=\"{}\"",
+            name,
+            cases.len(),
+            failures,
+            time
+        );
+        if let Some(started_at) = self.started_at {
+            let _ = write!(
+                xml,
+                " timestamp=\"{}\"",
+                started_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+            );
+        }
+        xml.push_str(">\n    <properties>\n");
+        for (property, value) in [
+            ("total_requests", self.total_requests.to_string()),
+            ("failed_requests", self.failed_requests.to_string()),
+            (
+                "requests_per_second",
+                format!("{:.2}", self.requests_per_second),
+            ),
+            (
+                "p95_response_time_ms",
+                format!("{:.3}", self.p95_response_time_ms),
+            ),
+            (
+                "p99_response_time_ms",
+                format!("{:.3}", self.p99_response_time_ms),
+            ),
+        ] {
+            let _ = writeln!(
+                xml,
+                "      <property name=\"{}\" value=\"{}\"/>",
+                property, value
+            );
+        }
+        xml.push_str("    </properties>\n");
+        for (kind, case, failure) in &cases {
+            let _ = write!(
+                xml,
+                "    <testcase classname=\"lode.{}\" name=\"{}\" time=\"0\"",
+                kind,
+                escape(case)
+            );
+            match failure {
+                Some(message) => {
+                    let _ = writeln!(
+                        xml,
+                        ">\n      <failure type=\"{}\" message=\"{}\"/>\n    </testcase>",
+                        kind,
+                        escape(message)
+                    );
+                }
+                None => xml.push_str("/>\n"),
+            }
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+/// Escape text for an XML attribute
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_junit_test_case_per_objective() {
+        let mut report = Report {
+            id: "run".to_string(),
+            status: "completed".to_string(),
+            total_requests: 100,
+            failed_requests: 2,
+            p95_response_time_ms: 300.0,
+            total_duration_seconds: 12.5,
+            ..Default::default()
+        };
+        report.check_slos(&["p95 < 250ms".parse().unwrap()]);
+        report.check_thresholds(&["error_rate < 5% && rps >= 0".parse().unwrap()]);
+
+        let xml = report.as_junit_xml();
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+        assert!(
+            xml.contains("<testsuites name=\"lode\" tests=\"3\" failures=\"1\" time=\"12.500\">")
+        );
+        assert!(xml.contains("<testcase classname=\"lode.run\" name=\"completed\" time=\"0\"/>"));
+        assert!(
+            xml.contains(
+                "<testcase classname=\"lode.slo\" name=\"p95 &lt; 250ms\" time=\"0\">\n      \
+                 <failure type=\"slo\" message=\"FAIL p95 &lt; 250 ms (measured 300 ms, headroom -20.0%)\"/>"
+            ),
+            "{}",
+            xml
+        );
+        assert!(xml.contains(
+            "<testcase classname=\"lode.threshold\" name=\"error_rate &lt; 5% &amp;&amp; rps &gt;= 0\" time=\"0\"/>"
+        ));
+        assert!(xml.contains("<property name=\"total_requests\" value=\"100\"/>"));
+        assert!(xml.ends_with("  </testsuite>\n</testsuites>\n"));
+    }
+
+    #[test]
+    fn test_junit_fails_partial_run() {
+        let report = Report {
+            id: "run".to_string(),
+            status: "aborted".to_string(),
+            abort_reason: Some("error_rate > 50% for 10s".to_string()),
+            ..Default::default()
+        };
+        let xml = report.as_junit_xml();
+        assert!(xml.contains("tests=\"1\" failures=\"1\""));
+        assert!(xml.contains("message=\"Run aborted: error_rate &gt; 50% for 10s\""));
+    }
+}
@@ -0,0 +1,857 @@
+use chrono::{DateTime, Utc};
+use hdrhistogram::Histogram;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::schema::SCHEMA_VERSION;
+use super::{
+    hdr, BackoffStats, ConnectionStats, CorrectedResponseTimes, ErrorStats, GroupStats,
+    MemoryStats, MixStats, PercentileStat, PhaseStats, RegionStats, Report, RetryStats,
+    SkippedStats, StageStats, TimeBucketStats, TimeSeries, TimelineEvent, TlsResumptionStats,
+    TransferStats,
+};
+use crate::clock::ClockOffset;
+use crate::error::{Error, Result};
+use crate::metrics::{Labels, RequestRecord, RequestSpill};
+
+/// Merged requests written to disk at once when the agents flushed theirs to disk
+const SPILL_BATCH: usize = 10_000;
+
+impl Report {
+    /// Merge the reports of the agents of a distributed run into one report
+    ///
+    /// Response time percentiles, overall and per region, come from the sum of the agents'
+    /// histograms, so they are as precise as those of a single run. Agents with a region are
+    /// broken down in `regions`. Endpoint, label, protocol, phase and stage breakdowns only carry
+    /// summary figures, their merged percentiles are the highest of the agents. Stages are matched by
+    /// position, their rates add up. Time series and timelines are lined up by the agents' starts,
+    /// which are on the coordinator's clock once their clock offsets are known, so the requests of
+    /// the same second land in the same interval; the merged clock offset keeps the widest error
+    /// bound of the agents'. Cooldowns are not merged.
+    pub fn merge(reports: &[Report]) -> Result<Report> {
+        let Some(first) = reports.first() else {
+            return Err(Error::Report("No reports to merge".to_string()));
+        };
+
+        let mut histogram = empty_histogram();
+        let mut regions: BTreeMap<&str, (Vec<&Report>, Histogram<u64>)> = BTreeMap::new();
+        for report in reports {
+            let response_times = response_times(report)?;
+            add(&mut histogram, &response_times)?;
+            if let Some(region) = &report.region {
+                let (agents, histogram) = regions
+                    .entry(region.as_str())
+                    .or_insert_with(|| (Vec::new(), empty_histogram()));
+                agents.push(report);
+                add(histogram, &response_times)?;
+            }
+        }
+
+        let mut percentiles: Vec<f64> = Vec::new();
+        for stat in reports.iter().flat_map(|report| &report.percentiles) {
+            if !percentiles.contains(&stat.percentile) {
+                percentiles.push(stat.percentile);
+            }
+        }
+
+        let timed: Vec<&Report> = reports
+            .iter()
+            .filter(|report| report.response_time_histogram.is_some())
+            .collect();
+        let total_requests = reports.iter().map(|r| r.total_requests).sum();
+        let iterations = reports.iter().map(|r| r.iterations).sum::<u64>();
+        let endpoints = merge_groups(reports.iter().map(|r| &r.endpoints));
+        // The agents ran the same scenario, with the same weights
+        let configured_mix = first
+            .mix
+            .iter()
+            .map(|stats| (stats.endpoint.clone(), stats.configured_percent))
+            .collect();
+        let total_duration_seconds = total_duration(reports);
+
+        let mut tail_latencies_ms: Vec<f64> = reports
+            .iter()
+            .flat_map(|report| report.tail_latencies_ms.iter().copied())
+            .collect();
+        tail_latencies_ms.sort_unstable_by(|a, b| b.total_cmp(a));
+        tail_latencies_ms.truncate((histogram.len() as usize).div_ceil(1000));
+
+        let (requests, spilled_requests) = merge_requests(reports)?;
+        let mut traces: Vec<_> = reports
+            .iter()
+            .flat_map(|report| report.traces.iter().cloned())
+            .collect();
+        traces.sort_by_key(|trace| trace.started_at);
+
+        Ok(Report {
+            schema_version: SCHEMA_VERSION,
+            id: Uuid::new_v4().to_string(),
+            status: reports
+                .iter()
+                .map(|report| report.status.as_str())
+                .find(|status| *status != "completed")
+                .unwrap_or("completed")
+                .to_string(),
+            abort_reason: reports
+                .iter()
+                .find_map(|report| report.abort_reason.clone()),
+            total_requests,
+            successful_requests: reports.iter().map(|r| r.successful_requests).sum(),
+            failed_requests: reports.iter().map(|r| r.failed_requests).sum(),
+            requests_per_second: if total_duration_seconds > 0.0 {
+                total_requests as f64 / total_duration_seconds
+            } else {
+                0.0
+            },
+            iterations,
+            iterations_per_second: if total_duration_seconds > 0.0 {
+                iterations as f64 / total_duration_seconds
+            } else {
+                0.0
+            },
+            virtual_users: reports.iter().map(|r| r.virtual_users).sum(),
+            min_response_time_ms: timed
+                .iter()
+                .map(|r| r.min_response_time_ms)
+                .reduce(f64::min)
+                .unwrap_or(0.0),
+            max_response_time_ms: timed
+                .iter()
+                .map(|r| r.max_response_time_ms)
+                .reduce(f64::max)
+                .unwrap_or(0.0),
+            mean_response_time_ms: mean_ms(&histogram),
+            median_response_time_ms: percentile_ms(&histogram, 50.0),
+            p95_response_time_ms: percentile_ms(&histogram, 95.0),
+            p99_response_time_ms: percentile_ms(&histogram, 99.0),
+            p999_response_time_ms: percentile_ms(&histogram, 99.9),
+            percentiles: percentile_stats(&histogram, &percentiles),
+            tail_latencies_ms,
+            total_duration_seconds,
+            response_time_histogram: match histogram.is_empty() {
+                true => None,
+                false => Some(hdr::encode(&histogram)?),
+            },
+            corrected_response_times: merge_corrected(reports)?,
+            stages: merge_stages(reports),
+            cooldown: None,
+            time_series: merge_time_series(reports),
+            timeline: merge_timelines(reports),
+            slos: Vec::new(),
+            thresholds: Vec::new(),
+            started_at: reports.iter().filter_map(|r| r.started_at).min(),
+            clock_offset: reports
+                .iter()
+                .filter_map(|r| r.clock_offset)
+                .map(|offset| offset.round_trip_ms)
+                .reduce(f64::max)
+                .map(|round_trip_ms| ClockOffset {
+                    offset_ms: 0.0,
+                    round_trip_ms,
+                }),
+            error_stats: merge_error_stats(reports),
+            connections: ConnectionStats::new(
+                reports
+                    .iter()
+                    .filter_map(|r| r.connections)
+                    .map(|c| c.new_connections)
+                    .sum(),
+                reports
+                    .iter()
+                    .filter_map(|r| r.connections)
+                    .map(|c| c.reused_connection_requests)
+                    .sum(),
+            ),
+            tls_resumption: TlsResumptionStats::new(
+                reports
+                    .iter()
+                    .filter_map(|r| r.tls_resumption)
+                    .map(|t| t.handshakes)
+                    .sum(),
+                reports
+                    .iter()
+                    .filter_map(|r| r.tls_resumption)
+                    .map(|t| t.resumed_handshakes)
+                    .sum(),
+            ),
+            skipped: SkippedStats::new(reports.iter().filter_map(|r| r.skipped.as_ref()).fold(
+                BTreeMap::new(),
+                |mut reasons, skipped| {
+                    for (reason, requests) in &skipped.reasons {
+                        *reasons.entry(reason.clone()).or_default() += requests;
+                    }
+                    reasons
+                },
+            )),
+            transfer: merge_transfer(reports, total_duration_seconds),
+            backoff: BackoffStats::new(
+                reports
+                    .iter()
+                    .filter_map(|r| r.backoff)
+                    .map(|b| b.backoffs)
+                    .sum(),
+                reports
+                    .iter()
+                    .filter_map(|r| r.backoff)
+                    .map(|b| b.total_backoff_seconds)
+                    .sum(),
+            ),
+            retries: merge_retries(reports),
+            truncated_bodies: reports
+                .iter()
+                .filter_map(|r| r.truncated_bodies)
+                .reduce(|merged, count| merged + count),
+            memory: merge_memory(reports),
+            status_codes: reports.iter().flat_map(|r| &r.status_codes).fold(
+                BTreeMap::new(),
+                |mut merged, (code, responses)| {
+                    *merged.entry(*code).or_insert(0) += responses;
+                    merged
+                },
+            ),
+            phases: merge_phases(reports),
+            mix: MixStats::from_endpoints(&configured_mix, &endpoints),
+            endpoints,
+            groups: merge_groups(reports.iter().map(|r| &r.groups)),
+            protocols: merge_groups(reports.iter().map(|r| &r.protocols)),
+            region: first
+                .region
+                .clone()
+                .filter(|region| reports.iter().all(|r| r.region.as_ref() == Some(region))),
+            regions: regions
+                .into_iter()
+                .map(|(region, (agents, histogram))| RegionStats {
+                    region: region.to_string(),
+                    agents: agents.len(),
+                    total_requests: agents.iter().map(|r| r.total_requests).sum(),
+                    successful_requests: agents.iter().map(|r| r.successful_requests).sum(),
+                    failed_requests: agents.iter().map(|r| r.failed_requests).sum(),
+                    mean_response_time_ms: mean_ms(&histogram),
+                    median_response_time_ms: percentile_ms(&histogram, 50.0),
+                    p95_response_time_ms: percentile_ms(&histogram, 95.0),
+                    p99_response_time_ms: percentile_ms(&histogram, 99.0),
+                    p999_response_time_ms: percentile_ms(&histogram, 99.9),
+                    percentiles: percentile_stats(&histogram, &percentiles),
+                })
+                .collect(),
+            // Each agent signed its own report, the merged one is signed anew if at all
+            signature: None,
+            requests,
+            spilled_requests,
+            traces,
+        })
+    }
+}
+
+fn empty_histogram() -> Histogram<u64> {
+    Histogram::new(3).expect("histogram precision is valid")
+}
+
+/// Decoded response times of a report; only reports without timed requests may lack them
+fn response_times(report: &Report) -> Result<Histogram<u64>> {
+    match report.response_times()? {
+        Some(histogram) => Ok(histogram),
+        None if report.total_requests == 0 || report.max_response_time_ms == 0.0 => {
+            Ok(empty_histogram())
+        }
+        None => Err(Error::Report(format!(
+            "Report {} has no response time histogram to merge",
+            report.id
+        ))),
+    }
+}
+
+fn add(histogram: &mut Histogram<u64>, other: &Histogram<u64>) -> Result<()> {
+    histogram
+        .add(other)
+        .map_err(|e| Error::Report(format!("Failed to merge histograms: {:?}", e)))
+}
+
+fn mean_ms(histogram: &Histogram<u64>) -> f64 {
+    if histogram.is_empty() {
+        0.0
+    } else {
+        histogram.mean() / 1000.0
+    }
+}
+
+fn percentile_ms(histogram: &Histogram<u64>, percentile: f64) -> f64 {
+    if histogram.is_empty() {
+        0.0
+    } else {
+        histogram.value_at_percentile(percentile) as f64 / 1000.0
+    }
+}
+
+fn percentile_stats(histogram: &Histogram<u64>, percentiles: &[f64]) -> Vec<PercentileStat> {
+    percentiles
+        .iter()
+        .map(|&percentile| PercentileStat {
+            percentile,
+            response_time_ms: percentile_ms(histogram, percentile),
+        })
+        .collect()
+}
+
+/// Span from the first start to the last end when all starts are known, the longest run otherwise
+fn total_duration(reports: &[Report]) -> f64 {
+    let longest = reports
+        .iter()
+        .map(|r| r.total_duration_seconds)
+        .fold(0.0, f64::max);
+    let spans: Option<Vec<(f64, f64)>> = reports
+        .iter()
+        .map(|report| {
+            let start = report.started_at?.timestamp_micros() as f64 / 1_000_000.0;
+            Some((start, start + report.total_duration_seconds))
+        })
+        .collect();
+    match spans {
+        Some(spans) if !spans.is_empty() => {
+            let start = spans
+                .iter()
+                .map(|(start, _)| *start)
+                .fold(f64::MAX, f64::min);
+            let end = spans.iter().map(|(_, end)| *end).fold(f64::MIN, f64::max);
+            (end - start).max(longest)
+        }
+        _ => longest,
+    }
+}
+
+fn merge_error_stats(reports: &[Report]) -> Option<ErrorStats> {
+    let mut merged: Option<ErrorStats> = None;
+    for stats in reports.iter().filter_map(|r| r.error_stats.as_ref()) {
+        let merged = merged.get_or_insert_with(|| ErrorStats {
+            error_counts: HashMap::new(),
+            error_messages: Vec::new(),
+        });
+        for (error, count) in &stats.error_counts {
+            *merged.error_counts.entry(*error).or_default() += count;
+        }
+        merged
+            .error_messages
+            .extend(stats.error_messages.iter().cloned());
+    }
+    merged
+}
+
+/// Sum of the corrected response times of the agents that corrected them
+fn merge_corrected(reports: &[Report]) -> Result<Option<CorrectedResponseTimes>> {
+    let mut merged = None;
+    for corrected in reports
+        .iter()
+        .filter_map(|r| r.corrected_response_times.as_ref())
+    {
+        add(
+            merged.get_or_insert_with(empty_histogram),
+            &corrected.histogram()?,
+        )?;
+    }
+    merged
+        .as_ref()
+        .map(CorrectedResponseTimes::from_histogram)
+        .transpose()
+}
+
+fn merge_retries(reports: &[Report]) -> Option<RetryStats> {
+    reports
+        .iter()
+        .filter_map(|r| r.retries)
+        .reduce(|merged, stats| RetryStats {
+            retried_requests: merged.retried_requests + stats.retried_requests,
+            retries: merged.retries + stats.retries,
+            first_attempt_successes: merged.first_attempt_successes + stats.first_attempt_successes,
+            retried_successes: merged.retried_successes + stats.retried_successes,
+        })
+}
+
+/// Interleave the recorded requests of the agents by the time they were sent, each agent's in
+/// the order it recorded them
+///
+/// When an agent flushed requests to disk, the merged ones are written to a new file next to
+/// its as they are read, rather than held in memory.
+fn merge_requests(reports: &[Report]) -> Result<(Vec<RequestRecord>, Option<Arc<RequestSpill>>)> {
+    let mut agents = reports
+        .iter()
+        .map(|report| Ok(report.recorded_requests()?.peekable()))
+        .collect::<Result<Vec<_>>>()?;
+    let spill = reports
+        .iter()
+        .find_map(|report| report.spilled_requests.as_ref())
+        .map(|spilled| {
+            let dir = spilled.path().parent().map(ToOwned::to_owned);
+            RequestSpill::new(&dir.unwrap_or_else(std::env::temp_dir))
+        });
+    let mut requests = Vec::new();
+    loop {
+        let mut earliest = None;
+        for (index, agent) in agents.iter_mut().enumerate() {
+            match agent.peek() {
+                Some(Ok(request)) if earliest.is_none_or(|(_, sent)| request.timestamp < sent) => {
+                    earliest = Some((index, request.timestamp));
+                }
+                Some(Err(_)) => {
+                    agent.next().transpose()?;
+                }
+                _ => {}
+            }
+        }
+        let Some((index, _)) = earliest else {
+            break;
+        };
+        requests.extend(agents[index].next().transpose()?);
+        if let Some(spill) = spill.as_ref().filter(|_| requests.len() >= SPILL_BATCH) {
+            spill.append(&std::mem::take(&mut requests))?;
+        }
+    }
+    Ok(match spill {
+        Some(spill) => {
+            spill.append(&std::mem::take(&mut requests))?;
+            (requests, Some(Arc::new(spill)))
+        }
+        None => (requests, None),
+    })
+}
+
+/// Keep the highest ceiling and peak of the generators, each with memory of its own, and sum what
+/// they shed
+fn merge_memory(reports: &[Report]) -> Option<MemoryStats> {
+    reports
+        .iter()
+        .filter_map(|r| r.memory)
+        .reduce(|merged, stats| MemoryStats {
+            ceiling_mb: merged.ceiling_mb.max(stats.ceiling_mb),
+            peak_resident_mb: merged.peak_resident_mb.max(stats.peak_resident_mb),
+            pressure_events: merged.pressure_events + stats.pressure_events,
+            spilled_requests: merged.spilled_requests + stats.spilled_requests,
+            dropped_traces: merged.dropped_traces + stats.dropped_traces,
+        })
+}
+
+/// Sum the bytes transferred in each region and work the rates out over the merged run
+fn merge_transfer(reports: &[Report], total_duration_seconds: f64) -> Option<TransferStats> {
+    let transfers = reports.iter().filter_map(|r| r.transfer);
+    let responses = reports
+        .iter()
+        .filter(|r| r.transfer.is_some())
+        .flat_map(|r| r.status_codes.values())
+        .sum();
+    TransferStats::new(
+        transfers.clone().map(|t| t.bytes_sent).sum(),
+        transfers.map(|t| t.bytes_received).sum(),
+        responses,
+        total_duration_seconds,
+    )
+}
+
+/// Combine breakdowns by labels, weighting means by request count
+fn merge_groups<'a>(breakdowns: impl Iterator<Item = &'a Vec<GroupStats>>) -> Vec<GroupStats> {
+    let mut merged: BTreeMap<Labels, GroupStats> = BTreeMap::new();
+    for stats in breakdowns.flatten() {
+        match merged.get_mut(&stats.labels) {
+            None => {
+                merged.insert(stats.labels.clone(), stats.clone());
+            }
+            Some(group) => {
+                let total = group.total_requests + stats.total_requests;
+                if total > 0 {
+                    group.mean_response_time_ms = (group.mean_response_time_ms
+                        * group.total_requests as f64
+                        + stats.mean_response_time_ms * stats.total_requests as f64)
+                        / total as f64;
+                }
+                group.total_requests = total;
+                group.successful_requests += stats.successful_requests;
+                group.failed_requests += stats.failed_requests;
+                group.p95_response_time_ms =
+                    group.p95_response_time_ms.max(stats.p95_response_time_ms);
+                group.p99_response_time_ms =
+                    group.p99_response_time_ms.max(stats.p99_response_time_ms);
+            }
+        }
+    }
+    merged.into_values().collect()
+}
+
+/// Seconds from the first start of the agents to the start of this one, when both are known
+fn start_shift(report: &Report, first_start: Option<DateTime<Utc>>) -> f64 {
+    match (first_start, report.started_at) {
+        (Some(first), Some(start)) => (start - first).num_microseconds().unwrap_or(0) as f64 / 1e6,
+        _ => 0.0,
+    }
+}
+
+/// Add up the intervals of the agents' time series that coincide once lined up by their starts
+///
+/// Rates add up and means are weighted by request count; percentiles are the highest of the
+/// agents. An agent sampling at another interval than the first is left out.
+fn merge_time_series(reports: &[Report]) -> Option<TimeSeries> {
+    let interval = reports
+        .iter()
+        .find_map(|r| r.time_series.as_ref())?
+        .interval_seconds;
+    let first_start = reports.iter().filter_map(|r| r.started_at).min();
+    let mut buckets: Vec<TimeBucketStats> = Vec::new();
+    for report in reports {
+        let Some(series) = report
+            .time_series
+            .as_ref()
+            .filter(|series| series.interval_seconds == interval)
+        else {
+            continue;
+        };
+        let shift = start_shift(report, first_start);
+        for stats in &series.buckets {
+            let index = ((shift + stats.start_seconds) / interval).round().max(0.0) as usize;
+            while buckets.len() <= index {
+                buckets.push(TimeBucketStats {
+                    start_seconds: buckets.len() as f64 * interval,
+                    total_requests: 0,
+                    failed_requests: 0,
+                    requests_per_second: 0.0,
+                    mean_response_time_ms: 0.0,
+                    median_response_time_ms: 0.0,
+                    p95_response_time_ms: 0.0,
+                    p99_response_time_ms: 0.0,
+                });
+            }
+            let bucket = &mut buckets[index];
+            let total = bucket.total_requests + stats.total_requests;
+            if total > 0 {
+                bucket.mean_response_time_ms = (bucket.mean_response_time_ms
+                    * bucket.total_requests as f64
+                    + stats.mean_response_time_ms * stats.total_requests as f64)
+                    / total as f64;
+            }
+            bucket.total_requests = total;
+            bucket.failed_requests += stats.failed_requests;
+            bucket.requests_per_second += stats.requests_per_second;
+            bucket.median_response_time_ms = bucket
+                .median_response_time_ms
+                .max(stats.median_response_time_ms);
+            bucket.p95_response_time_ms =
+                bucket.p95_response_time_ms.max(stats.p95_response_time_ms);
+            bucket.p99_response_time_ms =
+                bucket.p99_response_time_ms.max(stats.p99_response_time_ms);
+        }
+    }
+    Some(TimeSeries {
+        interval_seconds: interval,
+        buckets,
+    })
+}
+
+/// Events of all agents in the order they happened, each noted with the region of its agent
+fn merge_timelines(reports: &[Report]) -> Vec<TimelineEvent> {
+    let first_start = reports.iter().filter_map(|r| r.started_at).min();
+    let mut timeline: Vec<TimelineEvent> = reports
+        .iter()
+        .flat_map(|report| {
+            let shift = start_shift(report, first_start);
+            report.timeline.iter().map(move |event| TimelineEvent {
+                offset_seconds: shift + event.offset_seconds,
+                event: event.event.clone(),
+                detail: match &report.region {
+                    Some(region) => format!("{}: {}", region, event.detail),
+                    None => event.detail.clone(),
+                },
+            })
+        })
+        .collect();
+    timeline.sort_by(|a, b| a.offset_seconds.total_cmp(&b.offset_seconds));
+    timeline
+}
+
+/// Combine the stage breakdowns of agents running the same stages, weighting means by request count
+fn merge_stages(reports: &[Report]) -> Vec<StageStats> {
+    let mut merged: Vec<StageStats> = Vec::new();
+    for report in reports {
+        for (index, stats) in report.stages.iter().enumerate() {
+            match merged.get_mut(index) {
+                None => merged.push(stats.clone()),
+                Some(stage) => {
+                    let total = stage.total_requests + stats.total_requests;
+                    if total > 0 {
+                        stage.mean_response_time_ms = (stage.mean_response_time_ms
+                            * stage.total_requests as f64
+                            + stats.mean_response_time_ms * stats.total_requests as f64)
+                            / total as f64;
+                    }
+                    stage.total_requests = total;
+                    stage.successful_requests += stats.successful_requests;
+                    stage.failed_requests += stats.failed_requests;
+                    stage.requests_per_second += stats.requests_per_second;
+                    stage.start_seconds = stage.start_seconds.min(stats.start_seconds);
+                    stage.duration_seconds = stage.duration_seconds.max(stats.duration_seconds);
+                    stage.median_response_time_ms = stage
+                        .median_response_time_ms
+                        .max(stats.median_response_time_ms);
+                    stage.p95_response_time_ms =
+                        stage.p95_response_time_ms.max(stats.p95_response_time_ms);
+                    stage.p99_response_time_ms =
+                        stage.p99_response_time_ms.max(stats.p99_response_time_ms);
+                }
+            }
+        }
+    }
+    merged
+}
+
+/// Combine the phase breakdowns, weighting means by request count
+fn merge_phases(reports: &[Report]) -> Vec<PhaseStats> {
+    let mut merged: Vec<PhaseStats> = Vec::new();
+    for stats in reports.iter().flat_map(|r| &r.phases) {
+        match merged.iter_mut().find(|phase| phase.phase == stats.phase) {
+            None => merged.push(stats.clone()),
+            Some(phase) => {
+                let total = phase.requests + stats.requests;
+                if total > 0 {
+                    phase.mean_ms = (phase.mean_ms * phase.requests as f64
+                        + stats.mean_ms * stats.requests as f64)
+                        / total as f64;
+                }
+                phase.requests = total;
+                phase.median_ms = phase.median_ms.max(stats.median_ms);
+                phase.p95_ms = phase.p95_ms.max(stats.p95_ms);
+                phase.p99_ms = phase.p99_ms.max(stats.p99_ms);
+            }
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn agent(region: &str, response_times_ms: &[u64], started_second: u32) -> Report {
+        let mut histogram = Histogram::<u64>::new_with_bounds(1, 60_000_000, 3).unwrap();
+        for ms in response_times_ms {
+            histogram.record(ms * 1000).unwrap();
+        }
+        Report {
+            id: format!("{}-{}", region, started_second),
+            status: "completed".to_string(),
+            total_requests: response_times_ms.len() as u64,
+            successful_requests: response_times_ms.len() as u64,
+            min_response_time_ms: *response_times_ms.iter().min().unwrap() as f64,
+            max_response_time_ms: *response_times_ms.iter().max().unwrap() as f64,
+            total_duration_seconds: 10.0,
+            started_at: Some(
+                Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, started_second)
+                    .unwrap(),
+            ),
+            response_time_histogram: Some(hdr::encode(&histogram).unwrap()),
+            region: Some(region.to_string()),
+            percentiles: vec![PercentileStat {
+                percentile: 75.0,
+                response_time_ms: 0.0,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merge_breaks_down_regions() {
+        let reports = [
+            agent("eu-west-1", &[10, 20, 30, 40], 0),
+            agent("eu-west-1", &[10, 20, 30, 40], 5),
+            agent("ap-south-1", &[200, 300], 0),
+        ];
+        let merged = Report::merge(&reports).unwrap();
+
+        assert_eq!(merged.total_requests, 10);
+        assert_eq!(merged.successful_requests, 10);
+        // Agents started 5 seconds apart
+        assert_eq!(merged.total_duration_seconds, 15.0);
+        assert_eq!(merged.min_response_time_ms, 10.0);
+        assert_eq!(merged.max_response_time_ms, 300.0);
+        assert_eq!(merged.region, None);
+        assert_eq!(merged.response_times().unwrap().unwrap().len(), 10);
+
+        let regions: Vec<(&str, usize, u64)> = merged
+            .regions
+            .iter()
+            .map(|r| (r.region.as_str(), r.agents, r.total_requests))
+            .collect();
+        assert_eq!(regions, vec![("ap-south-1", 1, 2), ("eu-west-1", 2, 8)]);
+
+        let eu = &merged.regions[1];
+        assert!((eu.p99_response_time_ms - 40.0).abs() < 0.1);
+        assert!((eu.percentiles[0].response_time_ms - 30.0).abs() < 0.1);
+        let ap = &merged.regions[0];
+        assert!((ap.median_response_time_ms - 200.0).abs() < 0.2);
+        assert!(ap.p95_response_time_ms > eu.p99_response_time_ms);
+
+        let string = merged.as_string();
+        assert!(string.contains("By Region"));
+        assert!(string.contains("eu-west-1: 8 requests (0 failed) from 2 agents"));
+        assert!(string.contains("ap-south-1: 2 requests (0 failed) from 1 agent,"));
+    }
+
+    #[test]
+    fn test_merge_single_region_and_status() {
+        let mut cancelled = agent("us-east-1", &[50], 0);
+        cancelled.status = "cancelled".to_string();
+        let merged = Report::merge(&[agent("us-east-1", &[20], 0), cancelled]).unwrap();
+        assert_eq!(merged.status, "cancelled");
+        assert_eq!(merged.region.as_deref(), Some("us-east-1"));
+        assert_eq!(merged.regions.len(), 1);
+    }
+
+    #[test]
+    fn test_merge_timelines() {
+        let rotated = |offset_seconds| TimelineEvent {
+            offset_seconds,
+            event: "client_cert_rotated".to_string(),
+            detail: "client.pem".to_string(),
+        };
+        let mut eu = agent("eu-west-1", &[20], 0);
+        eu.timeline = vec![rotated(30.0)];
+        let mut us = agent("us-east-1", &[20], 0);
+        us.timeline = vec![rotated(12.5)];
+
+        let merged = Report::merge(&[eu.clone(), us]).unwrap();
+        assert_eq!(merged.timeline.len(), 2);
+        assert_eq!(merged.timeline[0].detail, "us-east-1: client.pem");
+        assert!(merged
+            .as_string()
+            .contains("12.5s: client_cert_rotated (us-east-1: client.pem)"));
+
+        // Offsets are from the start of the first agent
+        let mut late = agent("us-east-1", &[20], 20);
+        late.timeline = vec![rotated(12.5)];
+        let merged = Report::merge(&[late, eu]).unwrap();
+        assert_eq!(merged.timeline[0].offset_seconds, 30.0);
+        assert_eq!(merged.timeline[1].offset_seconds, 32.5);
+    }
+
+    #[test]
+    fn test_merge_time_series() {
+        let series = |buckets: &[(u64, f64)]| TimeSeries {
+            interval_seconds: 1.0,
+            buckets: buckets
+                .iter()
+                .enumerate()
+                .map(|(second, &(requests, p95))| TimeBucketStats {
+                    start_seconds: second as f64,
+                    total_requests: requests,
+                    failed_requests: 0,
+                    requests_per_second: requests as f64,
+                    mean_response_time_ms: p95 / 2.0,
+                    median_response_time_ms: p95 / 2.0,
+                    p95_response_time_ms: p95,
+                    p99_response_time_ms: p95,
+                })
+                .collect(),
+        };
+        let offset = |round_trip_ms| ClockOffset {
+            offset_ms: 250.0,
+            round_trip_ms,
+        };
+        // The second agent started two seconds after the first, on the coordinator's clock
+        let mut eu = agent("eu-west-1", &[20], 0);
+        eu.time_series = Some(series(&[(10, 20.0), (10, 20.0), (10, 20.0)]));
+        eu.clock_offset = Some(offset(4.0));
+        let mut us = agent("us-east-1", &[20], 2);
+        us.time_series = Some(series(&[(30, 60.0), (30, 60.0)]));
+        us.clock_offset = Some(offset(10.0));
+
+        let merged = Report::merge(&[us, eu.clone()]).unwrap();
+        let series = merged.time_series.unwrap();
+        let requests: Vec<u64> = series.buckets.iter().map(|b| b.total_requests).collect();
+        assert_eq!(requests, [10, 10, 40, 30]);
+        let starts: Vec<f64> = series.buckets.iter().map(|b| b.start_seconds).collect();
+        assert_eq!(starts, [0.0, 1.0, 2.0, 3.0]);
+        let third = &series.buckets[2];
+        assert_eq!(third.requests_per_second, 40.0);
+        assert_eq!(third.mean_response_time_ms, 25.0);
+        assert_eq!(third.p95_response_time_ms, 60.0);
+        assert_eq!(
+            merged.clock_offset,
+            Some(ClockOffset {
+                offset_ms: 0.0,
+                round_trip_ms: 10.0
+            })
+        );
+
+        let merged = Report::merge(&[agent("us-east-1", &[20], 0)]).unwrap();
+        assert!(merged.time_series.is_none());
+        assert!(merged.clock_offset.is_none());
+    }
+
+    #[test]
+    fn test_merge_corrected_response_times() {
+        let mut corrected = agent("us-east-1", &[20, 900], 0);
+        let mut histogram = Histogram::<u64>::new_with_bounds(1, 60_000_000, 3).unwrap();
+        histogram.record_correct(900_000, 100_000).unwrap();
+        corrected.corrected_response_times =
+            Some(CorrectedResponseTimes::from_histogram(&histogram).unwrap());
+
+        let merged = Report::merge(&[corrected.clone(), corrected]).unwrap();
+        let merged = merged.corrected_response_times.unwrap();
+        assert_eq!(merged.requests, 18);
+        assert_eq!(merged.histogram().unwrap().len(), 18);
+
+        let merged = Report::merge(&[agent("us-east-1", &[20], 0)]).unwrap();
+        assert!(merged.corrected_response_times.is_none());
+    }
+
+    #[test]
+    fn test_merge_requires_histograms() {
+        assert!(matches!(Report::merge(&[]), Err(Error::Report(_))));
+
+        let mut old = agent("us-east-1", &[20], 0);
+        old.response_time_histogram = None;
+        assert!(matches!(Report::merge(&[old]), Err(Error::Report(_))));
+
+        // A run without responses has nothing to merge
+        let empty = Report {
+            id: "empty".to_string(),
+            status: "completed".to_string(),
+            ..Default::default()
+        };
+        let merged = Report::merge(&[empty, agent("us-east-1", &[20], 0)]).unwrap();
+        assert_eq!(merged.total_requests, 1);
+        assert_eq!(merged.min_response_time_ms, 20.0);
+    }
+
+    #[test]
+    fn test_merge_interleaves_recorded_requests() {
+        let request = |second| RequestRecord {
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, second).unwrap(),
+            duration: None,
+            status: Some(200),
+            error: None,
+        };
+        let seconds = |requests: &[RequestRecord]| -> Vec<u32> {
+            requests
+                .iter()
+                .map(|request| request.timestamp.timestamp() as u32 % 60)
+                .collect()
+        };
+        let mut eu = agent("eu-west-1", &[10], 0);
+        eu.requests = vec![request(1), request(4)];
+        let mut us = agent("us-east-1", &[20], 0);
+        us.requests = vec![request(2), request(3)];
+        let merged = Report::merge(&[eu.clone(), us.clone()]).unwrap();
+        assert_eq!(seconds(&merged.requests), [1, 2, 3, 4]);
+        assert!(merged.spilled_requests.is_none());
+
+        // Requests an agent flushed to disk are merged on disk too
+        let spill = RequestSpill::new(&std::env::temp_dir());
+        spill.append(&[request(0)]).unwrap();
+        eu.spilled_requests = Some(Arc::new(spill));
+        let merged = Report::merge(&[eu, us]).unwrap();
+        assert!(merged.requests.is_empty());
+        let spilled: Vec<RequestRecord> = merged
+            .recorded_requests()
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(seconds(&spilled), [0, 1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,1703 @@
+use crate::clock::ClockOffset;
+use crate::cooldown::{CooldownMetrics, RECOVERY_FACTOR};
+use crate::error::Result;
+use crate::memory::MemoryUsage;
+use crate::metrics::{
+    ErrorKind, GroupMetrics, Labels, Phase, RequestRecord, RequestSpill, RunEvent, StageMetrics,
+    TestMetrics, TimeBucket, PROTOCOL_LABEL,
+};
+use crate::scenario::ENDPOINT_LABEL;
+use crate::slo::{SloResult, SloTarget};
+use crate::trace::TraceEntry;
+
+use chrono::{DateTime, Utc};
+use hdrhistogram::Histogram;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+pub mod aggregate;
+#[cfg(feature = "charts")]
+pub mod charts;
+pub mod compare;
+pub mod csv;
+pub mod format;
+pub mod har;
+pub mod hdr;
+pub mod html;
+pub mod junit;
+pub mod merge;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod schema;
+pub mod signing;
+pub mod sla;
+pub mod stream;
+pub mod thresholds;
+
+use format::{Locale, TextOptions};
+use signing::ReportSignature;
+use thresholds::ThresholdResult;
+
+/// A formatted test report
+///
+/// Its JSON form is versioned by `schema_version` and described by a published JSON Schema.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Report {
+    /// Version of the report format, 0 for reports written before it was versioned
+    #[serde(default)]
+    pub schema_version: u32,
+    pub id: String,
+    pub status: String,
+    /// Abort rule that ended an `aborted` run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abort_reason: Option<String>,
+    pub total_requests: u64,
+    pub successful_requests: u64,
+    pub failed_requests: u64,
+    pub requests_per_second: f64,
+    /// Iterations the virtual users completed, each one request of a mix or every request of a
+    /// flow
+    #[serde(default)]
+    pub iterations: u64,
+    #[serde(default)]
+    pub iterations_per_second: f64,
+    /// Virtual users that ran the iterations
+    #[serde(default)]
+    pub virtual_users: u64,
+    pub min_response_time_ms: f64,
+    pub max_response_time_ms: f64,
+    pub mean_response_time_ms: f64,
+    pub median_response_time_ms: f64,
+    pub p95_response_time_ms: f64,
+    pub p99_response_time_ms: f64,
+    #[serde(default)]
+    pub p999_response_time_ms: f64,
+    /// Response times at the percentiles configured for the run, in the configured order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub percentiles: Vec<PercentileStat>,
+    /// Raw response times of the slowest 0.1% of requests, slowest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tail_latencies_ms: Vec<f64>,
+    pub total_duration_seconds: f64,
+    /// All response times in microseconds, as a base64 compressed HdrHistogram V2 histogram
+    ///
+    /// Decoded histograms of several runs can be added up to compare or merge distributions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_time_histogram: Option<String>,
+    /// Response times corrected for coordinated omission, when the run was asked to correct them;
+    /// the figures above are the raw ones as measured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corrected_response_times: Option<CorrectedResponseTimes>,
+    /// Start of the run, on the coordinator's clock when a clock offset was captured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    /// Offset of the generator's clock from the coordinator's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock_offset: Option<ClockOffset>,
+    pub error_stats: Option<ErrorStats>,
+    /// Connections opened and reused, when the HTTP client tells them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connections: Option<ConnectionStats>,
+    /// TLS handshakes that resumed an earlier session, when the TLS settings set session
+    /// resumption
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_resumption: Option<TlsResumptionStats>,
+    /// Requests not sent because their data was missing, when any were skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<SkippedStats>,
+    /// Bytes sent and received with headers and bodies, when any were
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transfer: Option<TransferStats>,
+    /// Back-offs of virtual users as servers asked in `Retry-After`, when any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backoff: Option<BackoffStats>,
+    /// Requests sent again by the retry policy, when any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<RetryStats>,
+    /// Responses whose body was cut at the max body size instead of read to its end, when any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated_bodies: Option<u64>,
+    /// Resident memory of the generator and what it shed nearing the ceiling, when the run had a
+    /// memory ceiling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryStats>,
+    /// Responses per HTTP status code, successful or not; requests without a response have none
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub status_codes: BTreeMap<u16, u64>,
+    /// Time spent in each phase of the requests, for the phases that were timed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phases: Vec<PhaseStats>,
+    /// Breakdown per scenario request, labelled with its name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub endpoints: Vec<GroupStats>,
+    /// Configured and achieved share of the requests of a weighted scenario, per request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mix: Vec<MixStats>,
+    /// Breakdown per distinct set of request labels
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<GroupStats>,
+    /// Breakdown per HTTP version of the responses, labelled with it as `protocol`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protocols: Vec<GroupStats>,
+    /// Breakdown per stage of a staged run, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stages: Vec<StageStats>,
+    /// Recovery of the target after the load stopped, when a cooldown was observed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown: Option<CooldownStats>,
+    /// Requests and response times per interval of the run, when a time series was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_series: Option<TimeSeries>,
+    /// Events of the run itself in the order they happened, e.g. client certificate rotations
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub timeline: Vec<TimelineEvent>,
+    /// Service level objectives checked with [`Report::check_slos`] and their outcome
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub slos: Vec<SloResult>,
+    /// Threshold expressions evaluated with [`Report::check_thresholds`] and their outcome
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thresholds: Vec<ThresholdResult>,
+    /// Region of the agent that ran the test, unset in merged reports of several regions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Breakdown per agent region, in reports merged with [`Report::merge`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<RegionStats>,
+    /// Signature of the generator that wrote the report, set with [`Report::sign`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ReportSignature>,
+    /// Every request of the run when it was recorded, exported through [`Report::as_csv`] and
+    /// [`Report::write_parquet`]
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub requests: Vec<RequestRecord>,
+    /// Recorded requests the run flushed to disk under memory pressure, which came before
+    /// `requests`; read back by [`Report::recorded_requests`]
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub spilled_requests: Option<Arc<RequestSpill>>,
+    /// Requests traced with their headers and bodies when the run sampled them, not part of the
+    /// JSON report; written as HAR by [`Report::as_har`]
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub traces: Vec<TraceEntry>,
+}
+
+/// How many connections the requests of a run opened and how often they reused one
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ConnectionStats {
+    /// Connections opened during the run
+    pub new_connections: u64,
+    /// Requests sent on a connection opened by an earlier request
+    pub reused_connection_requests: u64,
+    /// Share of the requests with a known connection that reused one, in percent
+    pub reuse_percent: f64,
+}
+
+impl ConnectionStats {
+    fn new(new_connections: u64, reused_connection_requests: u64) -> Option<Self> {
+        let requests = new_connections + reused_connection_requests;
+        (requests > 0).then(|| Self {
+            new_connections,
+            reused_connection_requests,
+            reuse_percent: reused_connection_requests as f64 / requests as f64 * 100.0,
+        })
+    }
+}
+
+/// How many of the TLS handshakes of a run resumed an earlier session instead of a full one
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct TlsResumptionStats {
+    /// TLS handshakes of the connections opened during the run
+    pub handshakes: u64,
+    /// Handshakes that resumed a session, skipping the server certificate
+    pub resumed_handshakes: u64,
+    /// Share of the handshakes that resumed a session, in percent
+    pub resumption_percent: f64,
+}
+
+impl TlsResumptionStats {
+    fn new(handshakes: u64, resumed_handshakes: u64) -> Option<Self> {
+        (handshakes > 0).then(|| Self {
+            handshakes,
+            resumed_handshakes,
+            resumption_percent: resumed_handshakes as f64 / handshakes as f64 * 100.0,
+        })
+    }
+}
+
+/// Requests of a run skipped without being sent because their data was missing, e.g. a
+/// placeholder without a value in the row of the feeder
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct SkippedStats {
+    pub requests: u64,
+    /// Skipped requests by reason, e.g. `No value for {{user}}`
+    pub reasons: BTreeMap<String, u64>,
+}
+
+impl SkippedStats {
+    fn new(reasons: BTreeMap<String, u64>) -> Option<Self> {
+        (!reasons.is_empty()).then(|| Self {
+            requests: reasons.values().sum(),
+            reasons,
+        })
+    }
+}
+
+/// How many bytes the requests of a run sent and received, headers and bodies included
+///
+/// Megabytes are 10^6 bytes. Bodies cut at the max body size count up to the cut and discarded
+/// bodies not at all.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct TransferStats {
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Bytes received per response
+    pub mean_response_bytes: f64,
+    pub sent_mb_per_second: f64,
+    pub received_mb_per_second: f64,
+}
+
+impl TransferStats {
+    pub(crate) fn new(
+        bytes_sent: u64,
+        bytes_received: u64,
+        responses: u64,
+        duration_seconds: f64,
+    ) -> Option<Self> {
+        let per_second = |bytes: u64| match duration_seconds {
+            seconds if seconds > 0.0 => bytes as f64 / 1e6 / seconds,
+            _ => 0.0,
+        };
+        (bytes_sent + bytes_received > 0).then(|| Self {
+            bytes_sent,
+            bytes_received,
+            mean_response_bytes: match responses {
+                0 => 0.0,
+                responses => bytes_received as f64 / responses as f64,
+            },
+            sent_mb_per_second: per_second(bytes_sent),
+            received_mb_per_second: per_second(bytes_received),
+        })
+    }
+}
+
+/// Resident memory of the generator during a run with a memory ceiling, and what it shed to stay
+/// under it
+///
+/// Megabytes are 10^6 bytes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct MemoryStats {
+    pub ceiling_mb: f64,
+    /// Highest resident memory measured
+    pub peak_resident_mb: f64,
+    /// Times the resident memory neared the ceiling
+    pub pressure_events: u64,
+    /// Recorded requests flushed to disk
+    pub spilled_requests: u64,
+    /// Sampled traces dropped to free memory or not kept afterwards
+    pub dropped_traces: u64,
+}
+
+impl MemoryStats {
+    /// Stats of a run whose resident memory was measured
+    fn new(usage: &MemoryUsage) -> Option<Self> {
+        (usage.peak_resident > 0).then(|| Self {
+            ceiling_mb: usage.ceiling as f64 / 1e6,
+            peak_resident_mb: usage.peak_resident as f64 / 1e6,
+            pressure_events: usage.pressure_events,
+            spilled_requests: usage.spilled_requests,
+            dropped_traces: usage.dropped_traces,
+        })
+    }
+}
+
+/// How often and how long virtual users backed off as servers asked in `Retry-After` headers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct BackoffStats {
+    pub backoffs: u64,
+    /// Time spent backing off, summed over the virtual users
+    pub total_backoff_seconds: f64,
+}
+
+impl BackoffStats {
+    fn new(backoffs: u64, total_backoff_seconds: f64) -> Option<Self> {
+        (backoffs > 0).then_some(Self {
+            backoffs,
+            total_backoff_seconds,
+        })
+    }
+}
+
+/// How many requests the retry policy sent again and how many of them it saved
+///
+/// A retried request counts once in the totals of the report, with its last attempt.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct RetryStats {
+    /// Requests sent more than once
+    pub retried_requests: u64,
+    /// Attempts sent again after a failed one, over all requests
+    pub retries: u64,
+    /// Requests that succeeded at their first attempt
+    pub first_attempt_successes: u64,
+    /// Requests that succeeded after at least one retry
+    pub retried_successes: u64,
+}
+
+impl RetryStats {
+    fn new(
+        retried_requests: u64,
+        retries: u64,
+        successful_requests: u64,
+        retried_successes: u64,
+    ) -> Option<Self> {
+        (retried_requests > 0).then(|| Self {
+            retried_requests,
+            retries,
+            first_attempt_successes: successful_requests.saturating_sub(retried_successes),
+            retried_successes,
+        })
+    }
+}
+
+/// Time the requests spent in one of their phases, e.g. resolving a host name or downloading the body
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct PhaseStats {
+    /// `queue`, `dns`, `connect`, `tls`, `ttfb` or `download`; `queue` is spent in the generator
+    /// before the request is sent and is part of the response time only in the open load models
+    pub phase: String,
+    /// Requests that went through the phase; only the first request on a connection sets it up
+    pub requests: u64,
+    pub mean_ms: f64,
+    pub median_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+}
+
+impl PhaseStats {
+    fn from_histogram(phase: Phase, histogram: &Histogram<u64>) -> Self {
+        let ms = |micros: u64| micros as f64 / 1000.0;
+        Self {
+            phase: phase.name().to_string(),
+            requests: histogram.len(),
+            mean_ms: histogram.mean() / 1000.0,
+            median_ms: ms(histogram.value_at_percentile(50.0)),
+            p95_ms: ms(histogram.value_at_percentile(95.0)),
+            p99_ms: ms(histogram.value_at_percentile(99.0)),
+        }
+    }
+}
+
+/// Response times a user waiting on the schedule of the rate would see, corrected for coordinated
+/// omission
+///
+/// They are counted from when the requests were due to start, and a request slower than the
+/// interval its virtual user was due to send at also stands for the requests held back meanwhile.
+/// A stalled target then weighs on the percentiles as long as it stalled, rather than as the one
+/// slow request the generator got to send.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct CorrectedResponseTimes {
+    /// Requests in the distribution, those sent and those filled in for the ones held back
+    pub requests: u64,
+    pub mean_ms: f64,
+    pub median_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub p999_ms: f64,
+    pub max_ms: f64,
+    /// All corrected response times in microseconds, encoded as `response_time_histogram`
+    pub histogram: String,
+}
+
+impl CorrectedResponseTimes {
+    pub(crate) fn from_histogram(histogram: &Histogram<u64>) -> Result<Self> {
+        let ms = |micros: u64| micros as f64 / 1000.0;
+        Ok(Self {
+            requests: histogram.len(),
+            mean_ms: match histogram.is_empty() {
+                true => 0.0,
+                false => histogram.mean() / 1000.0,
+            },
+            median_ms: ms(histogram.value_at_percentile(50.0)),
+            p95_ms: ms(histogram.value_at_percentile(95.0)),
+            p99_ms: ms(histogram.value_at_percentile(99.0)),
+            p999_ms: ms(histogram.value_at_percentile(99.9)),
+            max_ms: ms(histogram.max()),
+            histogram: hdr::encode(histogram)?,
+        })
+    }
+
+    /// Decode the corrected response time histogram, in microseconds
+    pub fn histogram(&self) -> Result<Histogram<u64>> {
+        hdr::decode(&self.histogram)
+    }
+}
+
+/// Response time at a configured percentile
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct PercentileStat {
+    /// Percentile between 0 and 100, e.g. 99.99
+    pub percentile: f64,
+    pub response_time_ms: f64,
+}
+
+/// Requests completed in every interval of a run, to follow throughput and latency over time
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct TimeSeries {
+    pub interval_seconds: f64,
+    /// One bucket per interval from the start of the run, including empty ones
+    pub buckets: Vec<TimeBucketStats>,
+}
+
+/// Something that happened to a run, e.g. `client_cert_rotated`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct TimelineEvent {
+    /// When it happened, in seconds since the start of the run
+    pub offset_seconds: f64,
+    pub event: String,
+    /// What it happened to, e.g. the certificate file
+    pub detail: String,
+}
+
+impl From<&RunEvent> for TimelineEvent {
+    fn from(event: &RunEvent) -> Self {
+        Self {
+            offset_seconds: event.offset.as_secs_f64(),
+            event: event.kind.clone(),
+            detail: event.detail.clone(),
+        }
+    }
+}
+
+/// Statistics for the requests completed in one interval
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct TimeBucketStats {
+    /// Start of the interval, in seconds since the start of the run
+    pub start_seconds: f64,
+    pub total_requests: u64,
+    pub failed_requests: u64,
+    pub requests_per_second: f64,
+    pub mean_response_time_ms: f64,
+    pub median_response_time_ms: f64,
+    pub p95_response_time_ms: f64,
+    pub p99_response_time_ms: f64,
+}
+
+impl TimeSeries {
+    /// Summarize the buckets of a run lasting `total_duration`
+    ///
+    /// The last interval is usually cut short by the end of the run, its rate covers only the
+    /// part that elapsed.
+    fn from_buckets(interval: Duration, buckets: &[TimeBucket], total_duration: Duration) -> Self {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        Self {
+            interval_seconds: interval.as_secs_f64(),
+            buckets: buckets
+                .iter()
+                .map(|bucket| {
+                    let elapsed = total_duration.saturating_sub(bucket.start).min(interval);
+                    let seconds = if elapsed.is_zero() { interval } else { elapsed };
+                    TimeBucketStats {
+                        start_seconds: bucket.start.as_secs_f64(),
+                        total_requests: bucket.total_requests,
+                        failed_requests: bucket.failed_requests,
+                        requests_per_second: bucket.total_requests as f64 / seconds.as_secs_f64(),
+                        mean_response_time_ms: ms(bucket.mean_response_time),
+                        median_response_time_ms: ms(bucket.median_response_time),
+                        p95_response_time_ms: ms(bucket.p95_response_time),
+                        p99_response_time_ms: ms(bucket.p99_response_time),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Statistics for the requests completed during one stage of a staged run
+///
+/// Totals over a ramp mix the load levels it passes through; each stage has its own figures.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct StageStats {
+    /// Stage in its normalized form, e.g. `ramp:60s:50`
+    pub stage: String,
+    /// Start of the stage, in seconds since the start of the run
+    pub start_seconds: f64,
+    pub duration_seconds: f64,
+    pub total_requests: u64,
+    pub successful_requests: u64,
+    pub failed_requests: u64,
+    pub requests_per_second: f64,
+    pub mean_response_time_ms: f64,
+    pub median_response_time_ms: f64,
+    pub p95_response_time_ms: f64,
+    pub p99_response_time_ms: f64,
+}
+
+impl StageStats {
+    /// Summarize a stage of a run lasting `total_duration`
+    fn from_metrics(stage: &StageMetrics, total_duration: Duration) -> Self {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let metrics = &stage.metrics;
+        let duration = stage
+            .end
+            .unwrap_or(total_duration)
+            .saturating_sub(stage.start);
+        Self {
+            stage: stage.name.clone(),
+            start_seconds: stage.start.as_secs_f64(),
+            duration_seconds: duration.as_secs_f64(),
+            total_requests: metrics.total_requests(),
+            successful_requests: metrics.successful_requests(),
+            failed_requests: metrics.failed_requests(),
+            requests_per_second: match duration.is_zero() {
+                true => 0.0,
+                false => metrics.total_requests() as f64 / duration.as_secs_f64(),
+            },
+            mean_response_time_ms: ms(metrics.mean_response_time()),
+            median_response_time_ms: ms(metrics.percentile_response_time(50.0)),
+            p95_response_time_ms: ms(metrics.percentile_response_time(95.0)),
+            p99_response_time_ms: ms(metrics.percentile_response_time(99.0)),
+        }
+    }
+}
+
+/// How the target recovered once the load stopped, from the probes of a cooldown
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct CooldownStats {
+    /// Median response time of the probes sent before the load, none when they all failed
+    pub baseline_ms: Option<f64>,
+    /// Response time under which a successful probe is back at the baseline
+    pub threshold_ms: Option<f64>,
+    /// Time after the load stopped from which every probe was back at the baseline, none when the
+    /// target had not recovered by the end of the cooldown
+    pub recovery_seconds: Option<f64>,
+    pub probes: Vec<ProbeStats>,
+}
+
+/// One probe of a cooldown
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ProbeStats {
+    /// Time the probe was sent, in seconds since the load stopped
+    pub offset_seconds: f64,
+    pub response_time_ms: f64,
+    pub success: bool,
+}
+
+impl CooldownStats {
+    fn from_metrics(cooldown: &CooldownMetrics) -> Self {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        Self {
+            baseline_ms: cooldown.baseline.map(ms),
+            threshold_ms: cooldown
+                .baseline
+                .map(|baseline| ms(baseline) * RECOVERY_FACTOR),
+            recovery_seconds: cooldown.recovery_time().map(|time| time.as_secs_f64()),
+            probes: cooldown
+                .probes
+                .iter()
+                .map(|probe| ProbeStats {
+                    offset_seconds: probe.offset.as_secs_f64(),
+                    response_time_ms: ms(probe.response_time),
+                    success: probe.success,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Statistics for the requests sent by the agents of one region
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct RegionStats {
+    pub region: String,
+    /// Number of merged reports from the region
+    pub agents: usize,
+    pub total_requests: u64,
+    pub successful_requests: u64,
+    pub failed_requests: u64,
+    pub mean_response_time_ms: f64,
+    pub median_response_time_ms: f64,
+    pub p95_response_time_ms: f64,
+    pub p99_response_time_ms: f64,
+    pub p999_response_time_ms: f64,
+    /// Response times at the percentiles configured for the runs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub percentiles: Vec<PercentileStat>,
+}
+
+/// Statistics for the requests sharing a set of labels
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct GroupStats {
+    pub labels: Labels,
+    pub total_requests: u64,
+    pub successful_requests: u64,
+    pub failed_requests: u64,
+    pub mean_response_time_ms: f64,
+    pub p95_response_time_ms: f64,
+    pub p99_response_time_ms: f64,
+}
+
+impl GroupStats {
+    fn from_metrics(labels: Labels, metrics: &GroupMetrics) -> Self {
+        Self {
+            labels,
+            total_requests: metrics.total_requests(),
+            successful_requests: metrics.successful_requests(),
+            failed_requests: metrics.failed_requests(),
+            mean_response_time_ms: metrics.mean_response_time().as_secs_f64() * 1000.0,
+            p95_response_time_ms: metrics.percentile_response_time(95.0).as_secs_f64() * 1000.0,
+            p99_response_time_ms: metrics.percentile_response_time(99.0).as_secs_f64() * 1000.0,
+        }
+    }
+
+    /// Labels formatted as `key=value` pairs
+    pub fn label_string(&self) -> String {
+        self.labels
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Share of the requests of a weighted scenario sent to one of its requests
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct MixStats {
+    /// Name of the scenario request
+    pub endpoint: String,
+    /// Share set by the weights, in percent
+    pub configured_percent: f64,
+    /// Share of the recorded requests of the mix, in percent
+    pub achieved_percent: f64,
+    pub requests: u64,
+}
+
+impl MixStats {
+    /// Shares of the requests of a mix, from the configured percentages and the endpoint breakdown
+    fn from_endpoints(configured: &BTreeMap<String, f64>, endpoints: &[GroupStats]) -> Vec<Self> {
+        let requests = |name: &str| {
+            endpoints
+                .iter()
+                .find(|endpoint| {
+                    endpoint.labels.get(ENDPOINT_LABEL).map(String::as_str) == Some(name)
+                })
+                .map_or(0, |endpoint| endpoint.total_requests)
+        };
+        let total: u64 = configured.keys().map(|name| requests(name)).sum();
+        configured
+            .iter()
+            .map(|(name, percent)| Self {
+                endpoint: name.clone(),
+                configured_percent: *percent,
+                achieved_percent: match total {
+                    0 => 0.0,
+                    total => requests(name) as f64 / total as f64 * 100.0,
+                },
+                requests: requests(name),
+            })
+            .collect()
+    }
+}
+
+/// Error statistics for a test report
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ErrorStats {
+    /// Failed requests per kind of failure, e.g. `http_503`, `timeout`, `dns`, `tls`, `connect`,
+    /// `body`, `assertion`, `graphql` or `other`
+    #[schemars(extend("propertyNames" = {
+        "pattern": "^(http_[0-9]{3}|timeout|dns|tls|connect|body|assertion|graphql|other)$"
+    }))]
+    pub error_counts: HashMap<ErrorKind, u64>,
+    pub error_messages: Vec<String>,
+}
+
+impl Report {
+    /// Create a new report from test metrics
+    pub async fn from_metrics(metrics: Arc<Mutex<TestMetrics>>) -> Result<Self> {
+        Self::from_test_metrics(&*metrics.lock().await)
+    }
+
+    /// Create a new report from test metrics that are not shared
+    pub fn from_test_metrics(metrics: &TestMetrics) -> Result<Self> {
+        let mut groups: Vec<GroupStats> = metrics
+            .groups()
+            .iter()
+            .map(|(labels, group)| GroupStats::from_metrics(labels.clone(), group))
+            .collect();
+        groups.sort_by(|a, b| a.labels.cmp(&b.labels));
+
+        let endpoints: Vec<GroupStats> = metrics
+            .endpoints()
+            .iter()
+            .map(|(name, endpoint)| {
+                let labels = Labels::from([(ENDPOINT_LABEL.to_string(), name.clone())]);
+                GroupStats::from_metrics(labels, endpoint)
+            })
+            .collect();
+        let mix = MixStats::from_endpoints(metrics.mix(), &endpoints);
+
+        Ok(Self {
+            schema_version: schema::SCHEMA_VERSION,
+            id: Uuid::new_v4().to_string(),
+            status: if metrics.is_crashed() {
+                "crashed"
+            } else if metrics.is_cancelled() {
+                "cancelled"
+            } else if metrics.is_deadline_exceeded() {
+                "deadline_exceeded"
+            } else if metrics.abort_reason().is_some() {
+                "aborted"
+            } else {
+                "completed"
+            }
+            .to_string(),
+            abort_reason: metrics.abort_reason().map(str::to_string),
+            total_requests: metrics.total_requests(),
+            successful_requests: metrics.successful_requests(),
+            failed_requests: metrics.failed_requests(),
+            requests_per_second: metrics.requests_per_second(),
+            iterations: metrics.iterations(),
+            iterations_per_second: metrics.iterations_per_second(),
+            virtual_users: metrics.virtual_users(),
+            min_response_time_ms: metrics.min_response_time().as_secs_f64() * 1000.0,
+            max_response_time_ms: metrics.max_response_time().as_secs_f64() * 1000.0,
+            mean_response_time_ms: metrics.mean_response_time().as_secs_f64() * 1000.0,
+            median_response_time_ms: metrics.median_response_time().as_secs_f64() * 1000.0,
+            p95_response_time_ms: metrics.p95_response_time().as_secs_f64() * 1000.0,
+            p99_response_time_ms: metrics.p99_response_time().as_secs_f64() * 1000.0,
+            p999_response_time_ms: metrics.p999_response_time().as_secs_f64() * 1000.0,
+            percentiles: metrics
+                .percentiles()
+                .iter()
+                .map(|&percentile| PercentileStat {
+                    percentile,
+                    response_time_ms: metrics.percentile_response_time(percentile).as_secs_f64()
+                        * 1000.0,
+                })
+                .collect(),
+            tail_latencies_ms: metrics
+                .tail_latencies()
+                .iter()
+                .map(|d| d.as_secs_f64() * 1000.0)
+                .collect(),
+            total_duration_seconds: metrics.total_duration().as_secs_f64(),
+            response_time_histogram: match metrics.response_time_histogram() {
+                histogram if histogram.is_empty() => None,
+                histogram => Some(hdr::encode(histogram)?),
+            },
+            corrected_response_times: metrics
+                .corrected_response_times()
+                .map(CorrectedResponseTimes::from_histogram)
+                .transpose()?,
+            stages: metrics
+                .stages()
+                .iter()
+                .map(|stage| StageStats::from_metrics(stage, metrics.total_duration()))
+                .collect(),
+            cooldown: metrics.cooldown().map(CooldownStats::from_metrics),
+            time_series: metrics.time_series_interval().map(|interval| {
+                TimeSeries::from_buckets(interval, &metrics.time_series(), metrics.total_duration())
+            }),
+            timeline: metrics.timeline().iter().map(TimelineEvent::from).collect(),
+            started_at: metrics.started_at(),
+            clock_offset: metrics.clock_offset(),
+            error_stats: metrics.error_stats().map(|(counts, messages)| ErrorStats {
+                error_counts: counts,
+                error_messages: messages,
+            }),
+            connections: ConnectionStats::new(
+                metrics.new_connections(),
+                metrics.reused_connection_requests(),
+            ),
+            tls_resumption: TlsResumptionStats::new(
+                metrics.tls_handshakes(),
+                metrics.resumed_tls_handshakes(),
+            ),
+            skipped: SkippedStats::new(metrics.skipped().clone()),
+            transfer: TransferStats::new(
+                metrics.bytes_sent(),
+                metrics.bytes_received(),
+                metrics.status_codes().values().sum(),
+                metrics.total_duration().as_secs_f64(),
+            ),
+            backoff: BackoffStats::new(metrics.backoffs(), metrics.backoff_time().as_secs_f64()),
+            retries: RetryStats::new(
+                metrics.retried_requests(),
+                metrics.retries(),
+                metrics.successful_requests(),
+                metrics.retried_successes(),
+            ),
+            truncated_bodies: Some(metrics.truncated_bodies()).filter(|count| *count > 0),
+            memory: metrics.memory_usage().and_then(MemoryStats::new),
+            status_codes: metrics.status_codes().clone(),
+            phases: metrics
+                .phase_times()
+                .iter()
+                .map(|(phase, histogram)| PhaseStats::from_histogram(*phase, histogram))
+                .collect(),
+            endpoints,
+            mix,
+            groups,
+            protocols: metrics
+                .protocols()
+                .iter()
+                .map(|(protocol, group)| {
+                    let labels = Labels::from([(PROTOCOL_LABEL.to_string(), protocol.to_string())]);
+                    GroupStats::from_metrics(labels, group)
+                })
+                .collect(),
+            slos: Vec::new(),
+            thresholds: Vec::new(),
+            region: metrics.region().map(str::to_string),
+            regions: Vec::new(),
+            signature: None,
+            requests: metrics.request_log().to_vec(),
+            spilled_requests: metrics.spilled_requests().cloned(),
+            traces: metrics.traces().to_vec(),
+        })
+    }
+
+    /// Read the recorded requests in completion order, those flushed to disk first
+    pub fn recorded_requests(&self) -> Result<impl Iterator<Item = Result<RequestRecord>> + '_> {
+        let spilled = self
+            .spilled_requests
+            .as_ref()
+            .map(|spill| spill.records())
+            .transpose()?;
+        Ok(spilled
+            .into_iter()
+            .flatten()
+            .map(|record| record.map_err(Into::into))
+            .chain(self.requests.iter().cloned().map(Ok)))
+    }
+
+    /// Number of recorded requests, those flushed to disk included
+    pub fn recorded_request_count(&self) -> u64 {
+        self.spilled_requests
+            .as_ref()
+            .map_or(0, |spill| spill.len())
+            + self.requests.len() as u64
+    }
+
+    /// Decode the response time histogram, in microseconds
+    pub fn response_times(&self) -> Result<Option<Histogram<u64>>> {
+        self.response_time_histogram
+            .as_deref()
+            .map(hdr::decode)
+            .transpose()
+    }
+
+    /// Response time at a percentile in milliseconds, from the reported figures or the histogram
+    pub fn response_time_at(&self, percentile: f64) -> Option<f64> {
+        let reported = match percentile {
+            50.0 => Some(self.median_response_time_ms),
+            95.0 => Some(self.p95_response_time_ms),
+            99.0 => Some(self.p99_response_time_ms),
+            99.9 => Some(self.p999_response_time_ms),
+            _ => self
+                .percentiles
+                .iter()
+                .find(|stat| stat.percentile == percentile)
+                .map(|stat| stat.response_time_ms),
+        };
+        reported.or_else(|| {
+            let histogram = self.response_times().ok()??;
+            Some(histogram.value_at_percentile(percentile) as f64 / 1000.0)
+        })
+    }
+
+    /// Measure the objectives in the report and keep their outcome in `slos`
+    pub fn check_slos(&mut self, slos: &[SloTarget]) {
+        self.slos = slos.iter().map(|slo| slo.check(self)).collect();
+    }
+
+    /// Whether every checked objective was met and every threshold passed, true when none were
+    /// checked
+    pub fn slos_passed(&self) -> bool {
+        self.slos.iter().all(|result| result.passed)
+            && self.thresholds.iter().all(|result| result.passed)
+    }
+
+    /// Format the report as JSON
+    pub fn as_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| crate::error::Error::Report(format!("Failed to serialize report: {}", e)))
+    }
+
+    /// Format the report as a human-readable string
+    pub fn as_string(&self) -> String {
+        self.as_string_in(Locale::default())
+    }
+
+    /// Format the report as a human-readable string with the number conventions of `locale`
+    ///
+    /// Counts get thousands separators and response times are scaled to µs, ms or s.
+    pub fn as_string_in(&self, locale: Locale) -> String {
+        self.as_string_with(&TextOptions {
+            locale,
+            ..Default::default()
+        })
+    }
+
+    /// Format the report as a human-readable string, with response times in the unit and
+    /// precision of `options`
+    pub fn as_string_with(&self, options: &TextOptions) -> String {
+        let locale = options.locale;
+        let count = |value| format::count(value, locale);
+        let time = |ms| options.duration_ms(ms);
+        let percentiles: String = self
+            .percentiles
+            .iter()
+            .map(|stat| {
+                format!(
+                    "\n            P{}: {}",
+                    stat.percentile,
+                    time(stat.response_time_ms)
+                )
+            })
+            .collect();
+        // Reports written before virtual users were counted have no iterations
+        let iterations = match self.iterations {
+            0 => String::new(),
+            iterations => format!(
+                "\n            Iterations: {} by {} virtual users\n            Iterations/second: {}",
+                count(iterations),
+                count(self.virtual_users),
+                format::decimal(self.iterations_per_second, 2, locale)
+            ),
+        };
+        let mut report = format!(
+            r#"Load Test Report
+            ----------------
+            Total Requests: {}
+            Successful Requests: {}
+            Failed Requests: {}
+            Requests/second: {}{}
+
+            Response Time
+            ----------------
+            Min: {}
+            Max: {}
+            Mean: {}
+            Median: {}
+            P95: {}
+            P99: {}
+            P99.9: {}{}
+
+            Total Duration: {} seconds"#,
+            count(self.total_requests),
+            count(self.successful_requests),
+            count(self.failed_requests),
+            format::decimal(self.requests_per_second, 2, locale),
+            iterations,
+            time(self.min_response_time_ms),
+            time(self.max_response_time_ms),
+            time(self.mean_response_time_ms),
+            time(self.median_response_time_ms),
+            time(self.p95_response_time_ms),
+            time(self.p99_response_time_ms),
+            time(self.p999_response_time_ms),
+            percentiles,
+            format::decimal(self.total_duration_seconds, 2, locale),
+        );
+
+        match self.status.as_str() {
+            "crashed" => report.push_str("\n            Status: crashed, partial results"),
+            "cancelled" => report.push_str("\n            Status: cancelled, partial results"),
+            "deadline_exceeded" => {
+                report.push_str("\n            Status: deadline exceeded, partial results")
+            }
+            "aborted" => report.push_str(&format!(
+                "\n            Status: aborted, partial results ({})",
+                self.abort_reason.as_deref().unwrap_or("abort rule met")
+            )),
+            _ => {}
+        }
+
+        if let Some(offset) = &self.clock_offset {
+            report.push_str(&format!(
+                "\n            Clock Offset: {}{} ms (±{} ms)",
+                if offset.offset_ms >= 0.0 { "+" } else { "" },
+                format::decimal(offset.offset_ms, 1, locale),
+                format::decimal(offset.round_trip_ms / 2.0, 1, locale)
+            ));
+        }
+
+        if !self.status_codes.is_empty() {
+            let codes: Vec<String> = self
+                .status_codes
+                .iter()
+                .map(|(code, responses)| format!("{} ({})", code, count(*responses)))
+                .collect();
+            report.push_str(&format!("\n            Status Codes: {}", codes.join(", ")));
+        }
+
+        if let Some(connections) = &self.connections {
+            report.push_str(&format!(
+                "\n            Connections: {} opened, {} requests reused one ({}%)",
+                count(connections.new_connections),
+                count(connections.reused_connection_requests),
+                format::decimal(connections.reuse_percent, 1, locale)
+            ));
+        }
+
+        if let Some(resumption) = &self.tls_resumption {
+            report.push_str(&format!(
+                "\n            TLS Resumption: {} of {} handshakes resumed a session ({}%)",
+                count(resumption.resumed_handshakes),
+                count(resumption.handshakes),
+                format::decimal(resumption.resumption_percent, 1, locale)
+            ));
+        }
+
+        if let Some(skipped) = &self.skipped {
+            let reasons: Vec<String> = skipped
+                .reasons
+                .iter()
+                .map(|(reason, requests)| format!("{}: {}", reason, count(*requests)))
+                .collect();
+            report.push_str(&format!(
+                "\n            Skipped: {} requests not sent ({})",
+                count(skipped.requests),
+                reasons.join(", ")
+            ));
+        }
+
+        if let Some(transfer) = &self.transfer {
+            report.push_str(&format!(
+                "\n            Transfer: {} MB received ({} MB/s, {} bytes per response), {} MB sent ({} MB/s)",
+                format::decimal(transfer.bytes_received as f64 / 1e6, 2, locale),
+                format::decimal(transfer.received_mb_per_second, 2, locale),
+                format::decimal(transfer.mean_response_bytes, 0, locale),
+                format::decimal(transfer.bytes_sent as f64 / 1e6, 2, locale),
+                format::decimal(transfer.sent_mb_per_second, 2, locale)
+            ));
+        }
+
+        if let Some(backoff) = &self.backoff {
+            report.push_str(&format!(
+                "\n            Backoff: {} {}, {} seconds in total",
+                count(backoff.backoffs),
+                if backoff.backoffs == 1 {
+                    "time"
+                } else {
+                    "times"
+                },
+                format::decimal(backoff.total_backoff_seconds, 2, locale)
+            ));
+        }
+
+        if let Some(retries) = &self.retries {
+            report.push_str(&format!(
+                "\n            Retries: {} requests retried {} times, {} succeeded at the first attempt, {} after retrying",
+                count(retries.retried_requests),
+                count(retries.retries),
+                count(retries.first_attempt_successes),
+                count(retries.retried_successes)
+            ));
+        }
+
+        if let Some(truncated) = self.truncated_bodies {
+            report.push_str(&format!(
+                "\n            Truncated bodies: {} read up to the max body size",
+                count(truncated)
+            ));
+        }
+
+        if let Some(memory) = &self.memory {
+            report.push_str(&format!(
+                "\n            Memory: peak {} MB resident of a {} MB ceiling",
+                format::decimal(memory.peak_resident_mb, 1, locale),
+                format::decimal(memory.ceiling_mb, 1, locale)
+            ));
+            if memory.pressure_events > 0 {
+                report.push_str(&format!(
+                    ", neared {} {}: {} requests flushed to disk, {} traces dropped",
+                    count(memory.pressure_events),
+                    if memory.pressure_events == 1 {
+                        "time"
+                    } else {
+                        "times"
+                    },
+                    count(memory.spilled_requests),
+                    count(memory.dropped_traces)
+                ));
+            }
+        }
+
+        if let Some(corrected) = &self.corrected_response_times {
+            report.push_str(&format!(
+                "\n            Corrected for coordinated omission: median {}, p95 {}, p99 {}, p99.9 {}, max {} over {} requests",
+                time(corrected.median_ms),
+                time(corrected.p95_ms),
+                time(corrected.p99_ms),
+                time(corrected.p999_ms),
+                time(corrected.max_ms),
+                count(corrected.requests)
+            ));
+        }
+
+        if !self.tail_latencies_ms.is_empty() {
+            const SHOWN: usize = 10;
+            let slowest = self
+                .tail_latencies_ms
+                .iter()
+                .take(SHOWN)
+                .map(|ms| time(*ms))
+                .collect::<Vec<_>>()
+                .join(", ");
+            report.push_str(&format!("\n            Slowest: {}", slowest));
+            if self.tail_latencies_ms.len() > SHOWN {
+                report.push_str(&format!(
+                    " (+{} more)",
+                    count((self.tail_latencies_ms.len() - SHOWN) as u64)
+                ));
+            }
+        }
+
+        if !self.phases.is_empty() {
+            report.push_str("\n\n            By Phase\n            ----------------");
+            for phase in &self.phases {
+                report.push_str(&format!(
+                    "\n            {}: {} requests, mean {}, median {}, p95 {}, p99 {}",
+                    phase.phase,
+                    format::compact_count(phase.requests, locale),
+                    time(phase.mean_ms),
+                    time(phase.median_ms),
+                    time(phase.p95_ms),
+                    time(phase.p99_ms),
+                ));
+            }
+        }
+
+        let breakdown = |report: &mut String, name: &str, stats: &GroupStats| {
+            report.push_str(&format!(
+                "\n            {}: {} requests ({} failed), mean {}, p95 {}, p99 {}",
+                name,
+                format::compact_count(stats.total_requests, locale),
+                format::compact_count(stats.failed_requests, locale),
+                time(stats.mean_response_time_ms),
+                time(stats.p95_response_time_ms),
+                time(stats.p99_response_time_ms),
+            ));
+        };
+
+        if !self.stages.is_empty() {
+            report.push_str("\n\n            By Stage\n            ----------------");
+            for stage in &self.stages {
+                report.push_str(&format!(
+                    "\n            {} ({}s from {}s): {} requests ({} failed), {} req/s, mean {}, median {}, p95 {}, p99 {}",
+                    stage.stage,
+                    format::decimal(stage.duration_seconds, 1, locale),
+                    format::decimal(stage.start_seconds, 1, locale),
+                    format::compact_count(stage.total_requests, locale),
+                    format::compact_count(stage.failed_requests, locale),
+                    format::decimal(stage.requests_per_second, 2, locale),
+                    time(stage.mean_response_time_ms),
+                    time(stage.median_response_time_ms),
+                    time(stage.p95_response_time_ms),
+                    time(stage.p99_response_time_ms),
+                ));
+            }
+        }
+
+        if let Some(cooldown) = &self.cooldown {
+            report.push_str("\n\n            Cooldown\n            ----------------");
+            let failed = cooldown
+                .probes
+                .iter()
+                .filter(|probe| !probe.success)
+                .count();
+            report.push_str(&format!(
+                "\n            Probes: {} ({} failed)",
+                format::compact_count(cooldown.probes.len() as u64, locale),
+                format::compact_count(failed as u64, locale)
+            ));
+            match (cooldown.baseline_ms, cooldown.threshold_ms) {
+                (Some(baseline), Some(threshold)) => report.push_str(&format!(
+                    "\n            Baseline: {} (recovered under {})",
+                    time(baseline),
+                    time(threshold)
+                )),
+                _ => report.push_str("\n            Baseline: none, every baseline probe failed"),
+            }
+            match cooldown.recovery_seconds {
+                Some(seconds) => report.push_str(&format!(
+                    "\n            Recovered after: {}s",
+                    format::decimal(seconds, 1, locale)
+                )),
+                None => report.push_str("\n            Recovered after: not recovered"),
+            }
+        }
+
+        if !self.timeline.is_empty() {
+            report.push_str("\n\n            Timeline\n            ----------------");
+            for event in &self.timeline {
+                report.push_str(&format!(
+                    "\n            {}s: {} ({})",
+                    format::decimal(event.offset_seconds, 1, locale),
+                    event.event,
+                    event.detail
+                ));
+            }
+        }
+
+        if !self.endpoints.is_empty() {
+            report.push_str("\n\n            By Endpoint\n            ----------------");
+            for endpoint in &self.endpoints {
+                breakdown(&mut report, &endpoint.labels[ENDPOINT_LABEL], endpoint);
+            }
+        }
+
+        if !self.mix.is_empty() {
+            report.push_str("\n\n            Mix\n            ----------------");
+            for stats in &self.mix {
+                report.push_str(&format!(
+                    "\n            {}: {}% configured, {}% achieved ({} requests)",
+                    stats.endpoint,
+                    format::decimal(stats.configured_percent, 1, locale),
+                    format::decimal(stats.achieved_percent, 1, locale),
+                    format::compact_count(stats.requests, locale),
+                ));
+            }
+        }
+
+        if !self.groups.is_empty() {
+            report.push_str("\n\n            By Labels\n            ----------------");
+            for group in &self.groups {
+                breakdown(&mut report, &group.label_string(), group);
+            }
+        }
+
+        if !self.protocols.is_empty() {
+            report.push_str("\n\n            By Protocol\n            ----------------");
+            for protocol in &self.protocols {
+                breakdown(&mut report, &protocol.labels[PROTOCOL_LABEL], protocol);
+            }
+        }
+
+        if !self.regions.is_empty() {
+            report.push_str("\n\n            By Region\n            ----------------");
+            for region in &self.regions {
+                report.push_str(&format!(
+                    "\n            {}: {} requests ({} failed) from {} {}, median {}, p95 {}, p99 {}, p99.9 {}",
+                    region.region,
+                    format::compact_count(region.total_requests, locale),
+                    format::compact_count(region.failed_requests, locale),
+                    region.agents,
+                    if region.agents == 1 { "agent" } else { "agents" },
+                    time(region.median_response_time_ms),
+                    time(region.p95_response_time_ms),
+                    time(region.p99_response_time_ms),
+                    time(region.p999_response_time_ms),
+                ));
+                for stat in &region.percentiles {
+                    report.push_str(&format!(
+                        ", p{} {}",
+                        stat.percentile,
+                        time(stat.response_time_ms)
+                    ));
+                }
+            }
+        }
+
+        report
+    }
+
+    // Getters
+    pub fn total_requests(&self) -> u64 {
+        self.total_requests
+    }
+
+    pub fn successful_requests(&self) -> u64 {
+        self.successful_requests
+    }
+
+    pub fn failed_requests(&self) -> u64 {
+        self.failed_requests
+    }
+
+    pub fn requests_per_second(&self) -> f64 {
+        self.requests_per_second
+    }
+
+    pub fn min_response_time_ms(&self) -> f64 {
+        self.min_response_time_ms
+    }
+
+    pub fn max_response_time_ms(&self) -> f64 {
+        self.max_response_time_ms
+    }
+
+    pub fn mean_response_time_ms(&self) -> f64 {
+        self.mean_response_time_ms
+    }
+
+    pub fn median_response_time_ms(&self) -> f64 {
+        self.median_response_time_ms
+    }
+
+    pub fn p95_response_time_ms(&self) -> f64 {
+        self.p95_response_time_ms
+    }
+
+    pub fn p99_response_time_ms(&self) -> f64 {
+        self.p99_response_time_ms
+    }
+
+    pub fn p999_response_time_ms(&self) -> f64 {
+        self.p999_response_time_ms
+    }
+
+    pub fn total_duration_seconds(&self) -> f64 {
+        self.total_duration_seconds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{RequestMetrics, TestMetrics};
+    use format::LatencyUnit;
+    use reqwest::{Client, StatusCode};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_report_from_metrics() {
+        let metrics = Arc::new(Mutex::new(TestMetrics::new().unwrap()));
+        {
+            let mut metrics = metrics.lock().await;
+
+            // Record successful requests with different response times
+            let request_metrics = RequestMetrics::new();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let request_metrics = request_metrics.complete(StatusCode::OK);
+            metrics.record_request(request_metrics);
+
+            let request_metrics = RequestMetrics::new();
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let request_metrics = request_metrics.complete(StatusCode::OK);
+            metrics.record_request(request_metrics);
+
+            let request_metrics = RequestMetrics::new();
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            let request_metrics = request_metrics.complete(StatusCode::OK);
+            metrics.record_request(request_metrics);
+
+            // Record error requests
+            let request_metrics = RequestMetrics::new();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let client = Client::new();
+            let error = client.get("invalid://url").send().await.unwrap_err();
+            let request_metrics = request_metrics.record_error(error);
+            metrics.record_request(request_metrics);
+
+            let request_metrics = RequestMetrics::new();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let client = Client::new();
+            let error = client
+                .get("http://localhost:1")
+                .timeout(Duration::from_nanos(1))
+                .send()
+                .await
+                .unwrap_err();
+            let request_metrics = request_metrics.record_error(error);
+            metrics.record_request(request_metrics);
+
+            metrics.finalize(Duration::from_secs(1)).await.unwrap();
+        }
+
+        let report = Report::from_metrics(metrics).await.unwrap();
+
+        assert_eq!(report.total_requests(), 5);
+        assert_eq!(report.successful_requests(), 3);
+        assert_eq!(report.failed_requests(), 2);
+        assert_eq!(report.requests_per_second(), 5.0);
+        assert!(report.min_response_time_ms() >= 100.0);
+        assert!(report.max_response_time_ms() >= 300.0);
+        assert!(report.mean_response_time_ms() >= 160.0);
+        assert!(report.median_response_time_ms() >= 100.0);
+        assert!(report.p95_response_time_ms() >= 300.0);
+        assert!(report.p99_response_time_ms() >= 300.0);
+        assert!(report.p999_response_time_ms() >= report.p99_response_time_ms() - 1.0);
+        assert_eq!(report.tail_latencies_ms.len(), 1);
+        assert_eq!(report.tail_latencies_ms[0], report.max_response_time_ms());
+        assert_eq!(report.total_duration_seconds(), 1.0);
+
+        let error_stats = report.error_stats.unwrap();
+        assert_eq!(error_stats.error_messages.len(), 2);
+    }
+
+    #[test]
+    fn test_report_json_serialization() {
+        let report = Report {
+            id: "test-id".to_string(),
+            status: "completed".to_string(),
+            total_requests: 100,
+            successful_requests: 95,
+            failed_requests: 5,
+            requests_per_second: 10.0,
+            min_response_time_ms: 100.0,
+            max_response_time_ms: 500.0,
+            mean_response_time_ms: 200.0,
+            median_response_time_ms: 180.0,
+            p95_response_time_ms: 400.0,
+            p99_response_time_ms: 450.0,
+            total_duration_seconds: 10.0,
+            error_stats: Some(ErrorStats {
+                error_counts: HashMap::from([(ErrorKind::Timeout, 3), (ErrorKind::Connect, 2)]),
+                error_messages: vec![
+                    "Request timed out".to_string(),
+                    "Connection refused".to_string(),
+                ],
+            }),
+            ..Default::default()
+        };
+
+        let json = report.as_json().unwrap();
+        let deserialized: Report = serde_json::from_str(&json).unwrap();
+        assert_eq!(report.id, deserialized.id);
+        assert_eq!(report.total_requests, deserialized.total_requests);
+        assert_eq!(
+            report.error_stats.unwrap().error_counts,
+            deserialized.error_stats.unwrap().error_counts
+        );
+    }
+
+    #[test]
+    fn test_report_string_format() {
+        let report = Report {
+            id: "test-id".to_string(),
+            status: "completed".to_string(),
+            total_requests: 100,
+            successful_requests: 95,
+            failed_requests: 5,
+            requests_per_second: 10.0,
+            min_response_time_ms: 100.0,
+            max_response_time_ms: 500.0,
+            mean_response_time_ms: 200.0,
+            median_response_time_ms: 180.0,
+            p95_response_time_ms: 400.0,
+            p99_response_time_ms: 450.0,
+            total_duration_seconds: 10.0,
+            error_stats: None,
+            ..Default::default()
+        };
+
+        let string = report.as_string();
+        assert!(string.contains("Total Requests: 100"));
+        assert!(string.contains("Successful Requests: 95"));
+        assert!(string.contains("Failed Requests: 5"));
+        assert!(string.contains("Requests/second: 10.00"));
+        assert!(!string.contains("Iterations"));
+        assert!(string.contains("Min: 100"));
+        assert!(string.contains("Max: 500"));
+        assert!(string.contains("Mean: 200"));
+        assert!(string.contains("Median: 180"));
+        assert!(string.contains("P95: 400"));
+        assert!(string.contains("P99: 450"));
+        assert!(string.contains("P99.9: 0"));
+        assert!(string.contains("Total Duration: 10.00 seconds"));
+        assert!(!string.contains("Clock Offset"));
+        assert!(!string.contains("P75"));
+
+        let iterations = Report {
+            iterations: 25,
+            iterations_per_second: 2.5,
+            virtual_users: 4,
+            ..report.clone()
+        }
+        .as_string();
+        assert!(iterations.contains("Requests/second: 10.00\n            Iterations: 25 by 4 virtual users\n            Iterations/second: 2.50\n"));
+
+        let extra = Report {
+            percentiles: vec![
+                PercentileStat {
+                    percentile: 75.0,
+                    response_time_ms: 250.0,
+                },
+                PercentileStat {
+                    percentile: 99.99,
+                    response_time_ms: 490.0,
+                },
+            ],
+            ..report.clone()
+        };
+        let string = extra.as_string();
+        assert!(string.contains("P75: 250"));
+        assert!(string.find("P99.9:") < string.find("P75:"));
+        assert!(string.find("P75:") < string.find("Total Duration"));
+        assert!(string.contains("P99.99: 490"));
+
+        assert!(!string.contains("Connections"));
+
+        let pooled = Report {
+            connections: ConnectionStats::new(4, 96),
+            ..report.clone()
+        };
+        assert!(pooled
+            .as_string()
+            .contains("Connections: 4 opened, 96 requests reused one (96.0%)"));
+        assert!(!string.contains("TLS Resumption"));
+        let resumed = Report {
+            tls_resumption: TlsResumptionStats::new(4, 3),
+            ..report.clone()
+        };
+        assert!(resumed
+            .as_string()
+            .contains("TLS Resumption: 3 of 4 handshakes resumed a session (75.0%)"));
+        assert!(!string.contains("Skipped"));
+        let skipped = Report {
+            skipped: SkippedStats::new(BTreeMap::from([("No value for {{user}}".to_string(), 3)])),
+            ..report.clone()
+        };
+        assert!(skipped
+            .as_string()
+            .contains("Skipped: 3 requests not sent (No value for {{user}}: 3)"));
+        assert!(!string.contains("Memory"));
+        let shed = Report {
+            memory: MemoryStats::new(&MemoryUsage {
+                ceiling: 2_000_000_000,
+                peak_resident: 1_650_000_000,
+                pressure_events: 1,
+                spilled_requests: 120_000,
+                dropped_traces: 500,
+            }),
+            ..report.clone()
+        };
+        assert!(shed.as_string().contains(
+            "Memory: peak 1,650.0 MB resident of a 2,000.0 MB ceiling, neared 1 time: 120,000 requests flushed to disk, 500 traces dropped"
+        ));
+        assert!(!string.contains("Backoff"));
+
+        let cooperative = Report {
+            backoff: BackoffStats::new(3, 4.5),
+            ..report.clone()
+        };
+        assert!(cooperative
+            .as_string()
+            .contains("Backoff: 3 times, 4.50 seconds in total"));
+        assert!(!string.contains("Retries"));
+
+        let retried = Report {
+            retries: RetryStats::new(5, 7, 98, 4),
+            ..report.clone()
+        };
+        assert!(retried.as_string().contains(
+            "Retries: 5 requests retried 7 times, 94 succeeded at the first attempt, 4 after retrying"
+        ));
+        assert!(!string.contains("By Phase"));
+
+        let phased = Report {
+            phases: vec![PhaseStats {
+                phase: "ttfb".to_string(),
+                requests: 100,
+                mean_ms: 150.0,
+                median_ms: 140.0,
+                p95_ms: 300.0,
+                p99_ms: 420.0,
+            }],
+            ..report.clone()
+        };
+        assert!(phased.as_string().contains(
+            "By Phase\n            ----------------\n            ttfb: 100 requests, mean 150 ms, median 140 ms, p95 300 ms, p99 420 ms"
+        ));
+
+        let synced = Report {
+            clock_offset: Some(ClockOffset {
+                offset_ms: -12.5,
+                round_trip_ms: 3.0,
+            }),
+            ..report
+        };
+        assert!(synced
+            .as_string()
+            .contains("Clock Offset: -12.5 ms (±1.5 ms)"));
+    }
+
+    #[test]
+    fn test_report_string_in_locale() {
+        let report = Report {
+            total_requests: 1_234_567,
+            requests_per_second: 12_345.678,
+            min_response_time_ms: 0.123456789,
+            max_response_time_ms: 2072.575291,
+            p95_response_time_ms: 12.3456,
+            total_duration_seconds: 100.0,
+            ..Default::default()
+        };
+
+        let string = report.as_string();
+        assert!(string.contains("Total Requests: 1,234,567"));
+        assert!(string.contains("Requests/second: 12,345.68"));
+        assert!(string.contains("Min: 123 µs"));
+        assert!(string.contains("Max: 2.07 s"));
+        assert!(string.contains("P95: 12.3 ms"));
+
+        let string = report.as_string_in(Locale::De);
+        assert!(string.contains("Total Requests: 1.234.567"));
+        assert!(string.contains("Requests/second: 12.345,68"));
+        assert!(string.contains("Max: 2,07 s"));
+        assert!(string.contains("Total Duration: 100,00 seconds"));
+    }
+
+    #[test]
+    fn test_report_string_with_latency_unit() {
+        let report = Report {
+            min_response_time_ms: 0.4,
+            max_response_time_ms: 2072.575291,
+            ..Default::default()
+        };
+
+        let string = report.as_string_with(&TextOptions {
+            latency_unit: LatencyUnit::Millis,
+            precision: Some(3),
+            ..Default::default()
+        });
+        assert!(string.contains("Min: 0.400 ms"));
+        assert!(string.contains("Max: 2,072.575 ms"));
+
+        let string = report.as_string_with(&TextOptions {
+            latency_unit: LatencyUnit::Micros,
+            ..Default::default()
+        });
+        assert!(string.contains("Min: 400 µs"));
+    }
+
+    #[test]
+    fn test_report_getters() {
+        let report = Report {
+            id: "test-id".to_string(),
+            status: "completed".to_string(),
+            total_requests: 100,
+            successful_requests: 95,
+            failed_requests: 5,
+            requests_per_second: 10.0,
+            min_response_time_ms: 100.0,
+            max_response_time_ms: 500.0,
+            mean_response_time_ms: 200.0,
+            median_response_time_ms: 180.0,
+            p95_response_time_ms: 400.0,
+            p99_response_time_ms: 450.0,
+            total_duration_seconds: 10.0,
+            error_stats: None,
+            ..Default::default()
+        };
+
+        assert_eq!(report.total_requests(), 100);
+        assert_eq!(report.successful_requests(), 95);
+        assert_eq!(report.failed_requests(), 5);
+        assert_eq!(report.requests_per_second(), 10.0);
+        assert_eq!(report.min_response_time_ms(), 100.0);
+        assert_eq!(report.max_response_time_ms(), 500.0);
+        assert_eq!(report.mean_response_time_ms(), 200.0);
+        assert_eq!(report.median_response_time_ms(), 180.0);
+        assert_eq!(report.p95_response_time_ms(), 400.0);
+        assert_eq!(report.p99_response_time_ms(), 450.0);
+        assert_eq!(report.total_duration_seconds(), 10.0);
+    }
+}
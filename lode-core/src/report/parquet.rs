@@ -0,0 +1,185 @@
+use parquet::basic::Compression;
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int32Type, Int64Type};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+use parquet::schema::parser::parse_message_type;
+use std::io::Write;
+use std::sync::Arc;
+
+use super::Report;
+use crate::error::{Error, Result};
+
+/// Columns of [`Report::write_parquet`], those of [`Report::as_csv`] with their types
+const SCHEMA: &str = "
+message request {
+    REQUIRED INT64 timestamp (TIMESTAMP(MICROS,true));
+    OPTIONAL DOUBLE duration_ms;
+    OPTIONAL INT32 status;
+    OPTIONAL BYTE_ARRAY error (UTF8);
+}
+";
+
+/// Requests written per row group, so readers can skip through large runs
+const ROW_GROUP_SIZE: usize = 1_000_000;
+
+impl Report {
+    /// Write the recorded requests as a Snappy-compressed Parquet file, one row per request in
+    /// completion order
+    ///
+    /// The rows hold the columns of [`Report::as_csv`]: the timestamp in microseconds since the
+    /// epoch, then the duration in milliseconds, the status and the error, null when missing.
+    /// Without recorded requests the file has the columns and no rows. Requests flushed to disk
+    /// under memory pressure are read back one row group at a time.
+    pub fn write_parquet<W: Write + Send>(&self, writer: W) -> Result<()> {
+        write_parquet(self, writer).map_err(|e| Error::Report(format!("Parquet: {}", e)))
+    }
+}
+
+fn write_parquet<W: Write + Send>(report: &Report, writer: W) -> parquet::errors::Result<()> {
+    let schema = Arc::new(parse_message_type(SCHEMA)?);
+    let properties = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+    let mut writer = SerializedFileWriter::new(writer, schema, properties)?;
+    // Requests flushed to disk are read back one row group at a time
+    let unreadable = |e: Error| ParquetError::General(format!("Failed to read requests: {}", e));
+    let mut records = report.recorded_requests().map_err(unreadable)?;
+    loop {
+        let requests = records
+            .by_ref()
+            .take(ROW_GROUP_SIZE)
+            .collect::<Result<Vec<_>>>()
+            .map_err(unreadable)?;
+        if requests.is_empty() {
+            break;
+        }
+        let mut row_group = writer.next_row_group()?;
+
+        let timestamps: Vec<i64> = requests
+            .iter()
+            .map(|request| request.timestamp.timestamp_micros())
+            .collect();
+        let mut column = next_column(row_group.next_column()?)?;
+        column
+            .typed::<Int64Type>()
+            .write_batch(&timestamps, None, None)?;
+        column.close()?;
+
+        let (durations, levels) = optional(requests.iter().map(|request| {
+            request
+                .duration
+                .map(|duration| duration.as_secs_f64() * 1000.0)
+        }));
+        let mut column = next_column(row_group.next_column()?)?;
+        column
+            .typed::<DoubleType>()
+            .write_batch(&durations, Some(&levels), None)?;
+        column.close()?;
+
+        let (statuses, levels) =
+            optional(requests.iter().map(|request| request.status.map(i32::from)));
+        let mut column = next_column(row_group.next_column()?)?;
+        column
+            .typed::<Int32Type>()
+            .write_batch(&statuses, Some(&levels), None)?;
+        column.close()?;
+
+        let (errors, levels) = optional(
+            requests
+                .iter()
+                .map(|request| request.error.as_deref().map(ByteArray::from)),
+        );
+        let mut column = next_column(row_group.next_column()?)?;
+        column
+            .typed::<ByteArrayType>()
+            .write_batch(&errors, Some(&levels), None)?;
+        column.close()?;
+
+        row_group.close()?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+fn next_column<'a>(
+    column: Option<SerializedColumnWriter<'a>>,
+) -> parquet::errors::Result<SerializedColumnWriter<'a>> {
+    column.ok_or_else(|| ParquetError::General("Fewer columns than in the schema".to_string()))
+}
+
+/// Values of an optional column with their definition levels, 0 for the nulls
+fn optional<T>(values: impl Iterator<Item = Option<T>>) -> (Vec<T>, Vec<i16>) {
+    let mut present = Vec::new();
+    let mut levels = Vec::new();
+    for value in values {
+        levels.push(i16::from(value.is_some()));
+        present.extend(value);
+    }
+    (present, levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::RequestRecord;
+    use chrono::{TimeZone, Utc};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parquet_rows() {
+        let timestamp = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        let report = Report {
+            requests: vec![
+                RequestRecord {
+                    timestamp,
+                    duration: Some(Duration::from_micros(12_345)),
+                    status: Some(200),
+                    error: None,
+                },
+                RequestRecord {
+                    timestamp,
+                    duration: None,
+                    status: None,
+                    error: Some("error sending request".to_string()),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let path = std::env::temp_dir().join(format!("lode-{}.parquet", uuid::Uuid::new_v4()));
+        report
+            .write_parquet(std::fs::File::create(&path).unwrap())
+            .unwrap();
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        let rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(
+            rows[0].get_timestamp_micros(0).unwrap(),
+            timestamp.timestamp_micros()
+        );
+        assert_eq!(rows[0].get_double(1).unwrap(), 12.345);
+        assert_eq!(rows[0].get_int(2).unwrap(), 200);
+        assert!(rows[0].get_string(3).is_err());
+        assert!(rows[1].get_double(1).is_err());
+        assert!(rows[1].get_int(2).is_err());
+        assert_eq!(rows[1].get_string(3).unwrap(), "error sending request");
+    }
+
+    #[test]
+    fn test_parquet_without_recorded_requests() {
+        let mut file = Vec::new();
+        Report::default().write_parquet(&mut file).unwrap();
+        assert_eq!(&file[..4], b"PAR1");
+    }
+}
@@ -0,0 +1,51 @@
+use serde_json::Value;
+
+use super::Report;
+
+/// Version of the report JSON format, embedded in every report as `schema_version`
+///
+/// Bump it whenever a field of [`Report`] is renamed, removed or changes type, and publish the
+/// regenerated schema next to the previous ones.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// URI identifying the schema of the current report format
+pub fn schema_id() -> String {
+    format!(
+        "https://raw.githubusercontent.com/ihbobul/lode/master/lode-core/schemas/report.v{}.json",
+        SCHEMA_VERSION
+    )
+}
+
+/// JSON Schema of the report format, generated from the serde model
+pub fn report_schema() -> Value {
+    let mut schema = schemars::schema_for!(Report).to_value();
+    if let Value::Object(object) = &mut schema {
+        object.insert("$id".to_string(), Value::from(schema_id()));
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_describes_report_fields() {
+        let schema = report_schema();
+        assert_eq!(schema["$id"], schema_id());
+        assert_eq!(schema["title"], "Report");
+
+        let properties = schema["properties"].as_object().unwrap();
+        for field in [
+            "schema_version",
+            "total_requests",
+            "p95_response_time_ms",
+            "groups",
+        ] {
+            assert!(properties.contains_key(field), "missing {}", field);
+        }
+        let required = schema["required"].as_array().unwrap();
+        assert!(required.contains(&Value::from("total_requests")));
+        assert!(!required.contains(&Value::from("schema_version")));
+    }
+}
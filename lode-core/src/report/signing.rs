@@ -0,0 +1,217 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use thiserror::Error;
+
+use super::Report;
+use crate::error::{Error as CoreError, Result};
+
+/// Field of a signed document holding its signature
+pub const SIGNATURE_FIELD: &str = "signature";
+
+/// Only signature algorithm written and accepted
+pub const ALGORITHM: &str = "ed25519";
+
+/// Signature of a report by the generator that wrote it, so an archived report can be shown to be
+/// unchanged since
+///
+/// It signs the compact JSON of the report, keys sorted and without this field, so it holds
+/// however the report is indented.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ReportSignature {
+    /// Always `ed25519`
+    pub algorithm: String,
+    /// Public key of the signer, its 32 bytes in base64
+    pub public_key: String,
+    /// Signature of the report in base64
+    pub signature: String,
+}
+
+/// Why a signed document failed verification
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum SignatureError {
+    #[error("The report is not signed")]
+    Unsigned,
+    #[error("Unsupported signature algorithm '{0}', expected ed25519")]
+    Algorithm(String),
+    #[error("Malformed signature: {0}")]
+    Malformed(String),
+    #[error("The report was signed by another key than the trusted one")]
+    UntrustedKey,
+    #[error("The signature does not match the report, it changed after it was signed")]
+    Mismatch,
+}
+
+/// Signs reports with an ed25519 private key
+#[derive(Debug, Clone)]
+pub struct ReportSigner {
+    key: SigningKey,
+}
+
+impl ReportSigner {
+    pub fn new(key: SigningKey) -> Self {
+        Self { key }
+    }
+
+    /// Read a PKCS#8 PEM private key, as written by `openssl genpkey -algorithm ed25519`
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        SigningKey::from_pkcs8_pem(pem)
+            .map(Self::new)
+            .map_err(|e| CoreError::Config(format!("Invalid ed25519 private key: {}", e)))
+    }
+
+    /// Read the PEM private key in the file at `path`
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Self::from_pem(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.key.verifying_key()
+    }
+
+    /// Sign a JSON document, leaving out its `signature` field
+    pub fn sign(&self, document: &Value) -> Result<ReportSignature> {
+        let signature = self.key.sign(&signed_bytes(document)?);
+        Ok(ReportSignature {
+            algorithm: ALGORITHM.to_string(),
+            public_key: encode_public_key(&self.key.verifying_key()),
+            signature: STANDARD.encode(signature.to_bytes()),
+        })
+    }
+}
+
+/// Read a SPKI PEM public key, as written by `openssl pkey -pubout`
+pub fn public_key_from_pem(pem: &str) -> Result<VerifyingKey> {
+    VerifyingKey::from_public_key_pem(pem)
+        .map_err(|e| CoreError::Config(format!("Invalid ed25519 public key: {}", e)))
+}
+
+/// A public key as written in signatures, its 32 bytes in base64
+pub fn encode_public_key(key: &VerifyingKey) -> String {
+    STANDARD.encode(key.as_bytes())
+}
+
+/// Check the `signature` field of a JSON document against the rest of it
+///
+/// Without a trusted key the signature is checked against the public key it carries, which shows
+/// the document is intact but not who signed it. Returns the key that signed it.
+pub fn verify(
+    document: &Value,
+    trusted: Option<&VerifyingKey>,
+) -> std::result::Result<VerifyingKey, SignatureError> {
+    let signature = document
+        .get(SIGNATURE_FIELD)
+        .filter(|signature| !signature.is_null())
+        .ok_or(SignatureError::Unsigned)?;
+    let signature: ReportSignature = serde_json::from_value(signature.clone())
+        .map_err(|e| SignatureError::Malformed(e.to_string()))?;
+    if signature.algorithm != ALGORITHM {
+        return Err(SignatureError::Algorithm(signature.algorithm));
+    }
+
+    let public_key: [u8; 32] = decode(&signature.public_key, "public key")?;
+    let key = VerifyingKey::from_bytes(&public_key)
+        .map_err(|e| SignatureError::Malformed(format!("public key: {}", e)))?;
+    if trusted.is_some_and(|trusted| *trusted != key) {
+        return Err(SignatureError::UntrustedKey);
+    }
+    let signature: [u8; 64] = decode(&signature.signature, "signature")?;
+    let signed = signed_bytes(document).map_err(|e| SignatureError::Malformed(e.to_string()))?;
+    key.verify(&signed, &Signature::from_bytes(&signature))
+        .map_err(|_| SignatureError::Mismatch)?;
+    Ok(key)
+}
+
+/// Decode a base64 field of a known length
+fn decode<const N: usize>(
+    encoded: &str,
+    field: &str,
+) -> std::result::Result<[u8; N], SignatureError> {
+    STANDARD
+        .decode(encoded)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| SignatureError::Malformed(format!("{} is not {} bytes of base64", field, N)))
+}
+
+/// Compact JSON of the document without its signature; object keys are sorted by `serde_json`
+fn signed_bytes(document: &Value) -> Result<Vec<u8>> {
+    let mut document = document.clone();
+    if let Some(fields) = document.as_object_mut() {
+        fields.remove(SIGNATURE_FIELD);
+    }
+    Ok(serde_json::to_vec(&document)?)
+}
+
+impl Report {
+    /// Sign the report with `signer`, replacing any earlier signature
+    ///
+    /// Anything changed in the report afterwards breaks the signature, so sign it last.
+    pub fn sign(&mut self, signer: &ReportSigner) -> Result<()> {
+        self.signature = None;
+        self.signature = Some(signer.sign(&serde_json::to_value(&*self)?)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::pkcs8::{spki::der::pem::LineEnding, EncodePrivateKey, EncodePublicKey};
+
+    fn signer(seed: u8) -> ReportSigner {
+        ReportSigner::new(SigningKey::from_bytes(&[seed; 32]))
+    }
+
+    #[test]
+    fn test_signed_report_verifies_until_changed() {
+        let signer = signer(7);
+        let mut report = Report {
+            id: "run".to_string(),
+            total_requests: 10,
+            p95_response_time_ms: 12.345678901234567,
+            ..Default::default()
+        };
+        report.sign(&signer).unwrap();
+
+        // As read back from the pretty printed file
+        let document: Value = serde_json::from_str(&report.as_json().unwrap()).unwrap();
+        assert_eq!(verify(&document, None), Ok(signer.verifying_key()));
+        assert_eq!(
+            verify(&document, Some(&signer.verifying_key())),
+            Ok(signer.verifying_key())
+        );
+        assert_eq!(
+            verify(&document, Some(&self::signer(8).verifying_key())),
+            Err(SignatureError::UntrustedKey)
+        );
+
+        let mut tampered = document.clone();
+        tampered["total_requests"] = 9.into();
+        assert_eq!(verify(&tampered, None), Err(SignatureError::Mismatch));
+
+        let mut unsigned = document;
+        unsigned.as_object_mut().unwrap().remove(SIGNATURE_FIELD);
+        assert_eq!(verify(&unsigned, None), Err(SignatureError::Unsigned));
+    }
+
+    #[test]
+    fn test_keys_from_pem() {
+        let key = SigningKey::from_bytes(&[3; 32]);
+        let private = key.to_pkcs8_pem(LineEnding::LF).unwrap();
+        let public = key
+            .verifying_key()
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap();
+
+        let signer = ReportSigner::from_pem(&private).unwrap();
+        assert_eq!(signer.verifying_key(), key.verifying_key());
+        assert_eq!(public_key_from_pem(&public).unwrap(), key.verifying_key());
+        assert!(ReportSigner::from_pem(&public).is_err());
+        assert!(public_key_from_pem("not a key").is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use super::format::{self, TextOptions};
+use super::Report;
+use crate::slo::{SloMetric, SloResult};
+
+impl Report {
+    /// Format the checked objectives as a condensed summary for stakeholders
+    ///
+    /// Only the verdict and, per objective, its target, the measured value, pass or fail and the
+    /// headroom are shown, followed by the outcome of every threshold. Objectives are checked with
+    /// [`Report::check_slos`] and thresholds with [`Report::check_thresholds`].
+    pub fn as_sla_summary(&self, options: &TextOptions) -> String {
+        let mut lines = vec!["SLA Summary".to_string(), "----------------".to_string()];
+
+        if self.slos.is_empty() && self.thresholds.is_empty() {
+            lines.push("No service level objectives configured".to_string());
+            return lines.join("\n");
+        }
+
+        let total = self.slos.len() + self.thresholds.len();
+        let met = self.slos.iter().filter(|result| result.passed).count()
+            + self
+                .thresholds
+                .iter()
+                .filter(|result| result.passed)
+                .count();
+        lines.push(format!(
+            "Verdict: {}, {} of {} objectives met",
+            if met == total { "PASS" } else { "FAIL" },
+            met,
+            total
+        ));
+        match self.status.as_str() {
+            "crashed" => lines.push("Status: crashed, partial results".to_string()),
+            "cancelled" => lines.push("Status: cancelled, partial results".to_string()),
+            "deadline_exceeded" => {
+                lines.push("Status: deadline exceeded, partial results".to_string())
+            }
+            "aborted" => lines.push(format!(
+                "Status: aborted, partial results ({})",
+                self.abort_reason.as_deref().unwrap_or("abort rule met")
+            )),
+            _ => {}
+        }
+        if !self.slos.is_empty() {
+            lines.push(String::new());
+            self.push_slo_table(&mut lines, options);
+        }
+        if !self.thresholds.is_empty() {
+            lines.push(String::new());
+            lines.push("Thresholds".to_string());
+            lines.extend(
+                self.thresholds
+                    .iter()
+                    .map(|result| result.as_string_with(options)),
+            );
+        }
+
+        lines.join("\n")
+    }
+
+    /// One row per objective with its target, measured value, outcome and headroom
+    fn push_slo_table(&self, lines: &mut Vec<String>, options: &TextOptions) {
+        let rows: Vec<[String; 4]> = self
+            .slos
+            .iter()
+            .map(|result| {
+                [
+                    objective(result, options),
+                    result
+                        .measured
+                        .map(|value| figure(result.slo.metric, value, options))
+                        .unwrap_or_else(|| "n/a".to_string()),
+                    if result.passed { "PASS" } else { "FAIL" }.to_string(),
+                    result
+                        .headroom_percent
+                        .map(|headroom| {
+                            format!("{}%", format::decimal(headroom, 1, options.locale))
+                        })
+                        .unwrap_or_else(|| "n/a".to_string()),
+                ]
+            })
+            .collect();
+        let header = [
+            "Objective".to_string(),
+            "Measured".to_string(),
+            "Result".to_string(),
+            "Headroom".to_string(),
+        ];
+        let widths: Vec<usize> = (0..3)
+            .map(|column| {
+                rows.iter()
+                    .chain([&header])
+                    .map(|row| row[column].chars().count())
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect();
+        for row in [&header].into_iter().chain(&rows) {
+            lines.push(format!(
+                "{:<w0$}  {:<w1$}  {:<w2$}  {}",
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+            ));
+        }
+    }
+}
+
+impl SloResult {
+    /// One line telling whether the objective passed, e.g.
+    /// `PASS p95 < 250 ms (measured 200 ms, headroom 20.0%)`
+    pub fn as_string_with(&self, options: &TextOptions) -> String {
+        let measured = match (self.measured, self.headroom_percent) {
+            (Some(measured), Some(headroom)) => format!(
+                "measured {}, headroom {}%",
+                figure(self.slo.metric, measured, options),
+                format::decimal(headroom, 1, options.locale)
+            ),
+            (Some(measured), None) => {
+                format!("measured {}", figure(self.slo.metric, measured, options))
+            }
+            (None, _) => "not measured".to_string(),
+        };
+        format!(
+            "{} {} ({})",
+            if self.passed { "PASS" } else { "FAIL" },
+            objective(self, options),
+            measured
+        )
+    }
+}
+
+/// The objective as written, with the target response time scaled like the measured ones
+fn objective(result: &SloResult, options: &TextOptions) -> String {
+    let target = match result.slo.metric {
+        SloMetric::ErrorRate => format!("{}%", result.slo.target),
+        SloMetric::RequestsPerSecond => format!("{} req/s", result.slo.target),
+        _ => options.duration_ms(result.slo.target),
+    };
+    format!("{} {} {}", result.slo.metric, result.slo.comparison, target)
+}
+
+/// A value in the unit of the metric
+pub(super) fn figure(metric: SloMetric, value: f64, options: &TextOptions) -> String {
+    match metric {
+        SloMetric::ErrorRate => format!("{}%", format::decimal(value, 2, options.locale)),
+        SloMetric::RequestsPerSecond => {
+            format!("{} req/s", format::decimal(value, 2, options.locale))
+        }
+        _ => options.duration_ms(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slo::SloTarget;
+
+    #[test]
+    fn test_sla_summary() {
+        let mut report = Report {
+            status: "completed".to_string(),
+            total_requests: 1000,
+            failed_requests: 20,
+            requests_per_second: 120.0,
+            p95_response_time_ms: 200.0,
+            p99_response_time_ms: 400.0,
+            ..Default::default()
+        };
+        let slos: Vec<SloTarget> = ["p95 < 250ms", "error_rate < 1%", "rps >= 100"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        report.check_slos(&slos);
+        assert!(!report.slos_passed());
+
+        let summary = report.as_sla_summary(&TextOptions::default());
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines[0], "SLA Summary");
+        assert_eq!(lines[2], "Verdict: FAIL, 2 of 3 objectives met");
+        assert_eq!(lines[4], "Objective         Measured      Result  Headroom");
+        assert_eq!(lines[5], "p95 < 250 ms      200 ms        PASS    20.0%");
+        assert_eq!(lines[6], "error_rate < 1%   2.00%         FAIL    -100.0%");
+        assert_eq!(lines[7], "rps >= 100 req/s  120.00 req/s  PASS    20.0%");
+        assert!(!summary.contains("Total Requests"));
+
+        let options = TextOptions::default();
+        assert_eq!(
+            report.slos[0].as_string_with(&options),
+            "PASS p95 < 250 ms (measured 200 ms, headroom 20.0%)"
+        );
+        assert_eq!(
+            report.slos[1].as_string_with(&options),
+            "FAIL error_rate < 1% (measured 2.00%, headroom -100.0%)"
+        );
+    }
+
+    #[test]
+    fn test_sla_summary_with_thresholds() {
+        let mut report = Report {
+            total_requests: 1000,
+            failed_requests: 20,
+            p95_response_time_ms: 200.0,
+            ..Default::default()
+        };
+        report.check_slos(&["p95 < 250ms".parse().unwrap()]);
+        report.check_thresholds(&["p95 < 300ms && error_rate < 1%".parse().unwrap()]);
+        assert!(!report.slos_passed());
+
+        let summary = report.as_sla_summary(&TextOptions::default());
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines[2], "Verdict: FAIL, 1 of 2 objectives met");
+        assert_eq!(lines[5], "p95 < 250 ms  200 ms    PASS    20.0%");
+        assert_eq!(lines[7], "Thresholds");
+        assert_eq!(
+            lines[8],
+            "FAIL p95 < 300ms && error_rate < 1% (p95 200 ms, error_rate 2.00%)"
+        );
+    }
+
+    #[test]
+    fn test_sla_summary_without_objectives() {
+        let summary = Report::default().as_sla_summary(&TextOptions::default());
+        assert!(summary.ends_with("No service level objectives configured"));
+    }
+}
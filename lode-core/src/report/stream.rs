@@ -0,0 +1,48 @@
+use serde::Serialize;
+
+use super::Report;
+use crate::error::{Error, Result};
+use crate::live::MetricsSnapshot;
+
+/// One line of the NDJSON stream of a run: interval snapshots, then a final summary
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamRecord<'a> {
+    Snapshot(&'a MetricsSnapshot),
+    Summary(&'a Report),
+}
+
+impl StreamRecord<'_> {
+    /// Format the record as a single JSON line, without the trailing newline
+    pub fn to_line(&self) -> Result<String> {
+        serde_json::to_string(self)
+            .map_err(|e| Error::Report(format!("Failed to serialize stream record: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_records_are_tagged_lines() {
+        let snapshot = MetricsSnapshot {
+            total_requests: 42,
+            ..Default::default()
+        };
+        let line = StreamRecord::Snapshot(&snapshot).to_line().unwrap();
+        assert!(!line.contains('\n'));
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(json["type"], "snapshot");
+        assert_eq!(json["total_requests"], 42);
+
+        let report = Report {
+            total_requests: 42,
+            ..Default::default()
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&StreamRecord::Summary(&report).to_line().unwrap()).unwrap();
+        assert_eq!(json["type"], "summary");
+        assert_eq!(json["total_requests"], 42);
+    }
+}
@@ -0,0 +1,522 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use super::format::TextOptions;
+use super::sla::figure;
+use super::{GroupStats, Report};
+use crate::config::ConfigError;
+use crate::metrics::Labels;
+use crate::slo::{SloMetric, SloResult, SloTarget};
+
+/// Whether a series holds a metric
+type HoldsMetric = fn(&SloMetric) -> bool;
+
+/// Series a comparison may name before its labels, as in `http_req_duration{step:checkout}`
+const SERIES: [(&str, HoldsMetric); 3] = [
+    ("http_req_duration", SloMetric::is_response_time),
+    ("http_req_failed", |metric| *metric == SloMetric::ErrorRate),
+    ("http_reqs", |metric| {
+        *metric == SloMetric::RequestsPerSecond
+    }),
+];
+
+/// A pass or fail condition on a report, written as comparisons of its figures joined by `&&`
+/// and `||`, e.g. `p95 < 300ms && error_rate < 1%`
+///
+/// Every comparison takes the form of a [`SloTarget`], optionally preceded by labels that narrow
+/// it to the requests carrying them: `http_req_duration{step:checkout} p99 < 800ms`. `&&` binds
+/// tighter than `||` and parentheses group comparisons.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+#[schemars(with = "String")]
+pub struct Threshold {
+    /// The expression as written
+    source: String,
+    expression: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    Check(Check),
+    /// Holds when all of its operands hold
+    All(Vec<Expression>),
+    /// Holds when any of its operands holds
+    Any(Vec<Expression>),
+}
+
+/// A comparison of a threshold
+#[derive(Debug, Clone, PartialEq)]
+struct Check {
+    /// Labels of the requests the figure is measured on, empty for all requests
+    labels: Labels,
+    slo: SloTarget,
+}
+
+impl Threshold {
+    /// Measure every comparison of the threshold in a report
+    pub fn evaluate(&self, report: &Report) -> ThresholdResult {
+        let mut checks = Vec::new();
+        let passed = evaluate(&self.expression, report, &mut checks);
+        ThresholdResult {
+            threshold: self.clone(),
+            passed,
+            checks,
+        }
+    }
+}
+
+/// Whether an expression holds, keeping the outcome of its comparisons in `checks`
+///
+/// Every comparison is measured, even once the outcome of the expression is known.
+fn evaluate(expression: &Expression, report: &Report, checks: &mut Vec<CheckResult>) -> bool {
+    match expression {
+        Expression::Check(check) => {
+            let result = check.slo.check_measured(measure(check, report));
+            checks.push(CheckResult {
+                labels: check.labels.clone(),
+                result,
+            });
+            result.passed
+        }
+        Expression::All(operands) => operands
+            .iter()
+            .map(|operand| evaluate(operand, report, checks))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .all(|passed| passed),
+        Expression::Any(operands) => operands
+            .iter()
+            .map(|operand| evaluate(operand, report, checks))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .any(|passed| passed),
+    }
+}
+
+/// Figure of a comparison in a report, from the breakdown of the requests with its labels
+///
+/// Labelled figures come from the label groups, else the endpoints or protocols. When several
+/// groups carry the labels their requests are added up and the highest percentile is taken.
+/// Only the mean, p95 and p99 response times, the error rate and the throughput are broken down.
+fn measure(check: &Check, report: &Report) -> Option<f64> {
+    if check.labels.is_empty() {
+        return check.slo.metric.measure(report);
+    }
+    let groups: Vec<&GroupStats> = [&report.groups, &report.endpoints, &report.protocols]
+        .into_iter()
+        .map(|breakdown| {
+            breakdown
+                .iter()
+                .filter(|group| {
+                    check
+                        .labels
+                        .iter()
+                        .all(|(key, value)| group.labels.get(key) == Some(value))
+                })
+                .collect::<Vec<_>>()
+        })
+        .find(|groups| !groups.is_empty())?;
+
+    let total: u64 = groups.iter().map(|group| group.total_requests).sum();
+    let highest = |percentile: fn(&GroupStats) -> f64| {
+        groups
+            .iter()
+            .map(|group| percentile(group))
+            .reduce(f64::max)
+    };
+    match check.slo.metric {
+        SloMetric::MeanResponseTime if total == 0 => Some(0.0),
+        SloMetric::MeanResponseTime => Some(
+            groups
+                .iter()
+                .map(|group| group.mean_response_time_ms * group.total_requests as f64)
+                .sum::<f64>()
+                / total as f64,
+        ),
+        SloMetric::Percentile(95.0) => highest(|group| group.p95_response_time_ms),
+        SloMetric::Percentile(99.0) => highest(|group| group.p99_response_time_ms),
+        SloMetric::ErrorRate if total == 0 => Some(0.0),
+        SloMetric::ErrorRate => {
+            let failed: u64 = groups.iter().map(|group| group.failed_requests).sum();
+            Some(failed as f64 / total as f64 * 100.0)
+        }
+        SloMetric::RequestsPerSecond if report.total_duration_seconds > 0.0 => {
+            Some(total as f64 / report.total_duration_seconds)
+        }
+        _ => None,
+    }
+}
+
+/// Recursive descent parser of threshold expressions
+struct Parser<'a> {
+    source: &'a str,
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn invalid(&self, reason: &str) -> ConfigError {
+        ConfigError::InvalidThreshold(format!("{}: {}", self.source.trim(), reason))
+    }
+
+    /// Skip `token` if the rest starts with it
+    fn eat(&mut self, token: &str) -> bool {
+        self.rest = self.rest.trim_start();
+        match self.rest.strip_prefix(token) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// `all ('||' all)*`
+    fn any(&mut self) -> Result<Expression, ConfigError> {
+        let mut operands = vec![self.all()?];
+        while self.eat("||") {
+            operands.push(self.all()?);
+        }
+        Ok(match operands.len() {
+            1 => operands.remove(0),
+            _ => Expression::Any(operands),
+        })
+    }
+
+    /// `operand ('&&' operand)*`
+    fn all(&mut self) -> Result<Expression, ConfigError> {
+        let mut operands = vec![self.operand()?];
+        while self.eat("&&") {
+            operands.push(self.operand()?);
+        }
+        Ok(match operands.len() {
+            1 => operands.remove(0),
+            _ => Expression::All(operands),
+        })
+    }
+
+    /// `'(' any ')' | check`
+    fn operand(&mut self) -> Result<Expression, ConfigError> {
+        if !self.eat("(") {
+            return self.check().map(Expression::Check);
+        }
+        let expression = self.any()?;
+        if !self.eat(")") {
+            return Err(self.invalid("missing closing parenthesis"));
+        }
+        Ok(expression)
+    }
+
+    /// `[series] ['{' key ':' value (',' key ':' value)* '}'] objective`
+    fn check(&mut self) -> Result<Check, ConfigError> {
+        self.rest = self.rest.trim_start();
+        let name_end = self
+            .rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(self.rest.len());
+        let series = SERIES
+            .into_iter()
+            .find(|(name, _)| *name == &self.rest[..name_end]);
+        if series.is_some() {
+            self.rest = &self.rest[name_end..];
+        }
+        let labels = if self.eat("{") {
+            self.labels()?
+        } else {
+            Labels::new()
+        };
+
+        let end = ["&&", "||", ")"]
+            .into_iter()
+            .filter_map(|token| self.rest.find(token))
+            .min()
+            .unwrap_or(self.rest.len());
+        let (objective, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        let slo: SloTarget = objective.trim().parse().map_err(|e| match e {
+            ConfigError::InvalidSlo(reason) => self.invalid(&reason),
+            e => e,
+        })?;
+        match series {
+            Some((name, holds)) if !holds(&slo.metric) => {
+                Err(self.invalid(&format!("{} has no {}", name, slo.metric)))
+            }
+            _ => Ok(Check { labels, slo }),
+        }
+    }
+
+    /// `key ':' value (',' key ':' value)* '}'`, after the opening brace
+    fn labels(&mut self) -> Result<Labels, ConfigError> {
+        let (selector, rest) = self
+            .rest
+            .split_once('}')
+            .ok_or_else(|| self.invalid("missing closing brace"))?;
+        self.rest = rest;
+        selector
+            .split(',')
+            .map(|label| match label.split_once(':') {
+                Some((key, value)) if !key.trim().is_empty() && !value.trim().is_empty() => {
+                    Ok((key.trim().to_string(), value.trim().to_string()))
+                }
+                _ => Err(self.invalid(&format!(
+                    "label '{}' should be written key:value",
+                    label.trim()
+                ))),
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Threshold {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { source: s, rest: s };
+        let expression = parser.any()?;
+        if !parser.rest.trim().is_empty() {
+            return Err(parser.invalid(&format!("unexpected '{}'", parser.rest.trim())));
+        }
+        Ok(Self {
+            source: s.trim().to_string(),
+            expression,
+        })
+    }
+}
+
+impl TryFrom<String> for Threshold {
+    type Error = ConfigError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Threshold> for String {
+    fn from(threshold: Threshold) -> Self {
+        threshold.source
+    }
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Outcome of a [`Threshold`] evaluated on a report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ThresholdResult {
+    pub threshold: Threshold,
+    pub passed: bool,
+    /// Outcome of every comparison, in the order they are written
+    pub checks: Vec<CheckResult>,
+}
+
+/// Outcome of one comparison of a threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CheckResult {
+    /// Labels of the requests the figure was measured on, empty for all requests
+    #[serde(default, skip_serializing_if = "Labels::is_empty")]
+    pub labels: Labels,
+    #[serde(flatten)]
+    pub result: SloResult,
+}
+
+impl ThresholdResult {
+    /// One line telling whether the threshold passed, e.g.
+    /// `FAIL p95 < 300ms && error_rate < 1% (p95 200 ms, error_rate 2.00%)`
+    pub fn as_string_with(&self, options: &TextOptions) -> String {
+        format!(
+            "{} {} ({})",
+            if self.passed { "PASS" } else { "FAIL" },
+            self.threshold,
+            self.measurements(options)
+        )
+    }
+
+    /// The figure measured for every comparison, e.g. `{step:checkout} p99 650 ms`
+    pub fn measurements(&self, options: &TextOptions) -> String {
+        self.checks
+            .iter()
+            .map(|check| {
+                let labels = match check.labels.is_empty() {
+                    true => String::new(),
+                    false => format!(
+                        "{{{}}} ",
+                        check
+                            .labels
+                            .iter()
+                            .map(|(key, value)| format!("{}:{}", key, value))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    ),
+                };
+                let metric = check.result.slo.metric;
+                match check.result.measured {
+                    Some(measured) => {
+                        format!("{}{} {}", labels, metric, figure(metric, measured, options))
+                    }
+                    None => format!("{}{} not measured", labels, metric),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Report {
+    /// Evaluate the thresholds on the report and keep their outcome in `thresholds`
+    pub fn check_thresholds(&mut self, thresholds: &[Threshold]) {
+        self.thresholds = thresholds
+            .iter()
+            .map(|threshold| threshold.evaluate(self))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(step: &str, total: u64, failed: u64, p99: f64) -> GroupStats {
+        GroupStats {
+            labels: Labels::from([
+                ("endpoint".to_string(), format!("{}-page", step)),
+                ("step".to_string(), step.to_string()),
+            ]),
+            total_requests: total,
+            successful_requests: total - failed,
+            failed_requests: failed,
+            mean_response_time_ms: p99 / 2.0,
+            p95_response_time_ms: p99 * 0.8,
+            p99_response_time_ms: p99,
+        }
+    }
+
+    fn report() -> Report {
+        Report {
+            total_requests: 1000,
+            failed_requests: 20,
+            total_duration_seconds: 10.0,
+            p95_response_time_ms: 200.0,
+            groups: vec![
+                group("browse", 800, 0, 300.0),
+                group("checkout", 200, 20, 900.0),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_thresholds() {
+        let threshold: Threshold = " p95 < 300ms && error_rate < 1% ".parse().unwrap();
+        assert_eq!(threshold.to_string(), "p95 < 300ms && error_rate < 1%");
+        assert!(matches!(&threshold.expression, Expression::All(checks) if checks.len() == 2));
+
+        let threshold: Threshold = "p95 < 1s || rps > 10 && error_rate < 5%".parse().unwrap();
+        let Expression::Any(operands) = &threshold.expression else {
+            panic!("|| should bind looser than &&");
+        };
+        assert!(matches!(operands[1], Expression::All(_)));
+
+        let threshold: Threshold = "http_req_duration{step:checkout, region : eu} p99 < 800ms"
+            .parse()
+            .unwrap();
+        let Expression::Check(check) = &threshold.expression else {
+            panic!("a single comparison");
+        };
+        assert_eq!(check.labels["step"], "checkout");
+        assert_eq!(check.labels["region"], "eu");
+        assert_eq!(check.slo, "p99 < 800ms".parse().unwrap());
+
+        for invalid in [
+            "",
+            "p95 < 1s &&",
+            "(p95 < 1s || rps > 1",
+            "p95 < 1s)",
+            "{step} p95 < 1s",
+            "{step:checkout p95 < 1s",
+            "http_req_failed{step:checkout} p95 < 1s",
+            "latency < 1s",
+        ] {
+            assert!(
+                matches!(
+                    invalid.parse::<Threshold>(),
+                    Err(ConfigError::InvalidThreshold(_))
+                ),
+                "{} should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_evaluate_thresholds() {
+        let report = report();
+
+        let result = "p95 < 300ms && error_rate < 1%"
+            .parse::<Threshold>()
+            .unwrap()
+            .evaluate(&report);
+        assert!(!result.passed);
+        assert_eq!(result.checks.len(), 2);
+        assert!(result.checks[0].result.passed);
+        assert_eq!(result.checks[1].result.measured, Some(2.0));
+        assert_eq!(
+            result.as_string_with(&TextOptions::default()),
+            "FAIL p95 < 300ms && error_rate < 1% (p95 200 ms, error_rate 2.00%)"
+        );
+
+        let result = "error_rate < 1% || p95 < 300ms"
+            .parse::<Threshold>()
+            .unwrap()
+            .evaluate(&report);
+        assert!(result.passed);
+
+        let result = "http_req_duration{step:checkout} p99 < 800ms"
+            .parse::<Threshold>()
+            .unwrap()
+            .evaluate(&report);
+        assert!(!result.passed);
+        assert_eq!(result.checks[0].result.measured, Some(900.0));
+        assert_eq!(
+            result.measurements(&TextOptions::default()),
+            "{step:checkout} p99 900 ms"
+        );
+
+        // Groups with the labels are added up
+        let check = |threshold: &str| {
+            threshold
+                .parse::<Threshold>()
+                .unwrap()
+                .evaluate(&report)
+                .checks[0]
+                .result
+                .measured
+        };
+        assert_eq!(
+            check("{endpoint:checkout-page} error_rate < 1%"),
+            Some(10.0)
+        );
+        assert_eq!(check("{step:browse} rps > 1"), Some(80.0));
+        assert_eq!(check("{step:browse} p99 < 1s"), Some(300.0));
+        // Neither broken down nor labelled like any request
+        assert_eq!(check("{step:browse} p75 < 1s"), None);
+        assert_eq!(check("{step:login} p99 < 1s"), None);
+    }
+
+    #[test]
+    fn test_threshold_serialization() {
+        let mut report = report();
+        let threshold: Threshold = "{step:checkout} p99 < 1s".parse().unwrap();
+        report.check_thresholds(std::slice::from_ref(&threshold));
+        let json = serde_json::to_value(&report.thresholds[0]).unwrap();
+        assert_eq!(json["threshold"], "{step:checkout} p99 < 1s");
+        assert_eq!(json["passed"], true);
+        assert_eq!(json["checks"][0]["labels"]["step"], "checkout");
+        assert_eq!(json["checks"][0]["measured"], 900.0);
+        let deserialized: ThresholdResult = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.threshold, threshold);
+    }
+}
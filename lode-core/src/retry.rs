@@ -0,0 +1,241 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::config::ConfigError;
+use crate::metrics::{ErrorKind, RequestMetrics};
+
+/// Attempts per request at most, so a policy cannot multiply the load of a run without bound
+pub const MAX_ATTEMPTS: u32 = 10;
+
+/// What makes a failed attempt worth retrying
+///
+/// Written as `connect` for failures to connect (DNS, TCP or TLS) or as a status code, e.g. `503`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum RetryCondition {
+    /// No connection could be established, so the target never saw the request
+    Connect,
+    /// The response came with this status code
+    Status(u16),
+}
+
+impl RetryCondition {
+    /// Whether a failure of this kind meets the condition
+    fn matches(&self, kind: ErrorKind) -> bool {
+        match self {
+            RetryCondition::Connect => {
+                matches!(kind, ErrorKind::Dns | ErrorKind::Connect | ErrorKind::Tls)
+            }
+            RetryCondition::Status(code) => kind == ErrorKind::Http(*code),
+        }
+    }
+}
+
+impl FromStr for RetryCondition {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("connect") {
+            return Ok(RetryCondition::Connect);
+        }
+        s.parse()
+            .ok()
+            .filter(|code| (100..=599).contains(code))
+            .map(RetryCondition::Status)
+            .ok_or_else(|| {
+                ConfigError::InvalidRetry(format!(
+                    "Unknown retry condition '{}', expected connect or a status code",
+                    s
+                ))
+            })
+    }
+}
+
+impl TryFrom<String> for RetryCondition {
+    type Error = ConfigError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<RetryCondition> for String {
+    fn from(condition: RetryCondition) -> Self {
+        condition.to_string()
+    }
+}
+
+impl fmt::Display for RetryCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetryCondition::Connect => write!(f, "connect"),
+            RetryCondition::Status(code) => write!(f, "{}", code),
+        }
+    }
+}
+
+/// How a failed request is sent again before it counts as failed
+///
+/// Attempts are spaced by an exponential backoff with full jitter: the n-th retry waits a random
+/// delay of up to `base_delay * 2^(n-1)`, capped at `max_delay`, so retrying virtual users do not
+/// hit a struggling target in lockstep.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Attempts per request at most, the first one included, up to [`MAX_ATTEMPTS`]
+    pub max_attempts: u32,
+
+    /// Failures retried; other failures are final
+    #[serde(default = "default_retry_on")]
+    pub retry_on: Vec<RetryCondition>,
+
+    /// Upper bound of the delay before the first retry, doubled for every further one
+    #[serde(default = "default_base_delay")]
+    pub base_delay: Duration,
+
+    /// Upper bound of the delay before any retry
+    #[serde(default = "default_max_delay")]
+    pub max_delay: Duration,
+}
+
+fn default_retry_on() -> Vec<RetryCondition> {
+    vec![RetryCondition::Connect]
+}
+
+fn default_base_delay() -> Duration {
+    Duration::from_millis(100)
+}
+
+fn default_max_delay() -> Duration {
+    Duration::from_secs(5)
+}
+
+impl RetryPolicy {
+    /// Retry failures to connect up to `max_attempts` attempts, with the default backoff
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            retry_on: default_retry_on(),
+            base_delay: default_base_delay(),
+            max_delay: default_max_delay(),
+        }
+    }
+
+    /// Retry on these failures instead of failures to connect
+    pub fn with_retry_on(mut self, retry_on: Vec<RetryCondition>) -> Self {
+        self.retry_on = retry_on;
+        self
+    }
+
+    /// Space attempts by this backoff
+    pub fn with_backoff(mut self, base_delay: Duration, max_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Check the policy invariants
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_attempts == 0 {
+            return Err(ConfigError::InvalidRetry(
+                "Attempts must be greater than 0".to_string(),
+            ));
+        }
+        if self.max_attempts > MAX_ATTEMPTS {
+            return Err(ConfigError::InvalidRetry(format!(
+                "Attempts cannot be more than {}",
+                MAX_ATTEMPTS
+            )));
+        }
+        if self.base_delay > self.max_delay {
+            return Err(ConfigError::InvalidRetry(
+                "Base delay cannot be greater than the maximum delay".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Delay before sending a request again after its `attempt`-th attempt ended as `metrics`
+    ///
+    /// `None` when the attempt succeeded, failed in a way that is not retried or was the last one.
+    pub fn retry_delay(&self, attempt: u32, metrics: &RequestMetrics) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let kind = metrics.error_kind()?;
+        if !self
+            .retry_on
+            .iter()
+            .any(|condition| condition.matches(kind))
+        {
+            return None;
+        }
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        Some(ceiling.mul_f64(random_fraction()))
+    }
+}
+
+/// Random fraction in [0, 1), from the random keys the standard library seeds hash maps with
+pub(crate) fn random_fraction() -> f64 {
+    let bits = RandomState::new().hash_one(0u8) >> 11;
+    bits as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_retry_condition_from_str() {
+        assert_eq!(
+            "connect".parse::<RetryCondition>().unwrap(),
+            RetryCondition::Connect
+        );
+        assert_eq!(
+            " 503 ".parse::<RetryCondition>().unwrap(),
+            RetryCondition::Status(503)
+        );
+        assert!("600".parse::<RetryCondition>().is_err());
+        assert!("timeout".parse::<RetryCondition>().is_err());
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let policy = RetryPolicy::new(3)
+            .with_retry_on(vec![RetryCondition::Status(503)])
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(150));
+        let unavailable = RequestMetrics::new().complete(StatusCode::SERVICE_UNAVAILABLE);
+
+        let first = policy.retry_delay(1, &unavailable).unwrap();
+        assert!(first < Duration::from_millis(100));
+        let second = policy.retry_delay(2, &unavailable).unwrap();
+        assert!(second < Duration::from_millis(150));
+        assert_eq!(policy.retry_delay(3, &unavailable), None);
+
+        let ok = RequestMetrics::new().complete(StatusCode::OK);
+        assert_eq!(policy.retry_delay(1, &ok), None);
+        let not_found = RequestMetrics::new().complete(StatusCode::NOT_FOUND);
+        assert_eq!(policy.retry_delay(1, &not_found), None);
+    }
+
+    #[test]
+    fn test_retry_policy_validate() {
+        assert!(RetryPolicy::new(1).validate().is_ok());
+        assert!(RetryPolicy::new(0).validate().is_err());
+        assert!(RetryPolicy::new(MAX_ATTEMPTS).validate().is_ok());
+        assert!(RetryPolicy::new(MAX_ATTEMPTS + 1).validate().is_err());
+        assert!(RetryPolicy::new(u32::MAX).validate().is_err());
+        assert!(RetryPolicy::new(3)
+            .with_backoff(Duration::from_secs(2), Duration::from_secs(1))
+            .validate()
+            .is_err());
+    }
+}
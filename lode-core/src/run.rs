@@ -0,0 +1,365 @@
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::config::LoadTestConfig;
+use crate::engine::{LoadTestEngine, RunOptions};
+use crate::error::{Error, Result};
+use crate::http::HttpClient;
+use crate::report::Report;
+
+/// Lifecycle state of a load test run
+///
+/// A run is pending until it starts, then ends completed, aborted, cancelled or failed. Cancelling
+/// is the only way to end a run that never started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunState {
+    /// Waiting to start, e.g. for a free worker of the API; shown as `queued`
+    Pending,
+    Running,
+    /// Sent all its requests or ran for its whole duration
+    Completed,
+    /// Ended early by an abort rule, a deadline or a crash, see the report for why
+    Aborted,
+    /// Stopped on request; a run cancelled while running keeps its partial report
+    Cancelled,
+    /// Ended without a report, see the run's error
+    Failed,
+}
+
+impl RunState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunState::Pending => "queued",
+            RunState::Running => "running",
+            RunState::Completed => "completed",
+            RunState::Aborted => "aborted",
+            RunState::Cancelled => "cancelled",
+            RunState::Failed => "failed",
+        }
+    }
+
+    /// Whether the run has reached a final state
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, RunState::Pending | RunState::Running)
+    }
+
+    /// Whether a run in this state may move to `next`
+    pub fn can_move_to(&self, next: RunState) -> bool {
+        match self {
+            RunState::Pending => matches!(next, RunState::Running | RunState::Cancelled),
+            RunState::Running => next.is_finished(),
+            _ => false,
+        }
+    }
+
+    /// Final state of a run that produced `report`
+    pub fn of_report(report: &Report) -> Self {
+        Self::of_report_status(&report.status)
+    }
+
+    /// Final state of a run whose report has the status `status`
+    ///
+    /// Only a run that sent all its requests or ran for its whole duration is completed: one
+    /// aborted by a rule, past its deadline or crashed is aborted, as its report covers part of
+    /// the load it was meant to send.
+    pub fn of_report_status(status: &str) -> Self {
+        match status {
+            "completed" => RunState::Completed,
+            "cancelled" => RunState::Cancelled,
+            _ => RunState::Aborted,
+        }
+    }
+}
+
+impl fmt::Display for RunState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RunState {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "queued" | "pending" => Ok(RunState::Pending),
+            "running" => Ok(RunState::Running),
+            "completed" => Ok(RunState::Completed),
+            "aborted" => Ok(RunState::Aborted),
+            "cancelled" => Ok(RunState::Cancelled),
+            "failed" => Ok(RunState::Failed),
+            other => Err(format!(
+                "'{}' is not a run status. Use `queued`, `running`, `completed`, `aborted`, \
+                 `cancelled` or `failed`.",
+                other
+            )),
+        }
+    }
+}
+
+/// A load test run: its configuration, where it is in its lifecycle and its report once it ends
+///
+/// The CLI and the API both drive their runs through it, so a run moves through the same
+/// [`RunState`]s whichever front end started it. Moves the state machine does not allow are
+/// refused with [`Error::Run`].
+#[derive(Debug, Clone)]
+pub struct TestRun {
+    pub id: String,
+    pub config: LoadTestConfig,
+    state: RunState,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Report of the run, kept for completed, aborted and cancelled runs that started
+    pub report: Option<Report>,
+    /// Why the run failed
+    pub error: Option<String>,
+}
+
+impl TestRun {
+    /// A pending run of `config` with a new id
+    pub fn new(config: LoadTestConfig) -> Self {
+        Self::with_id(Uuid::new_v4().to_string(), config)
+    }
+
+    /// A pending run of `config` known as `id`
+    pub fn with_id(id: String, config: LoadTestConfig) -> Self {
+        Self {
+            id,
+            config,
+            state: RunState::Pending,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            report: None,
+            error: None,
+        }
+    }
+
+    /// A finished run known as `id`, as a front end saved it without its report
+    ///
+    /// Refuses a state that is not final with [`Error::Run`].
+    pub fn restored(
+        id: String,
+        config: LoadTestConfig,
+        state: RunState,
+        started_at: DateTime<Utc>,
+        finished_at: Option<DateTime<Utc>>,
+        error: Option<String>,
+    ) -> Result<Self> {
+        if !state.is_finished() {
+            return Err(Error::Run(format!(
+                "Run {} is {}, only finished runs are restored",
+                id, state
+            )));
+        }
+        Ok(Self {
+            state,
+            created_at: started_at,
+            started_at: Some(started_at),
+            finished_at,
+            error,
+            ..Self::with_id(id, config)
+        })
+    }
+
+    pub fn state(&self) -> RunState {
+        self.state
+    }
+
+    fn move_to(&mut self, next: RunState) -> Result<()> {
+        if !self.state.can_move_to(next) {
+            return Err(Error::Run(format!(
+                "Run {} cannot go from {} to {}",
+                self.id, self.state, next
+            )));
+        }
+        self.state = next;
+        let now = Utc::now();
+        if next == RunState::Running {
+            self.started_at = Some(now);
+        } else {
+            self.finished_at = Some(now);
+        }
+        Ok(())
+    }
+
+    /// Start the pending run
+    pub fn start(&mut self) -> Result<()> {
+        self.move_to(RunState::Running)
+    }
+
+    /// End the running run with its report, in the state the report tells
+    pub fn finish(&mut self, report: Report) -> Result<()> {
+        self.move_to(RunState::of_report(&report))?;
+        self.report = Some(report);
+        Ok(())
+    }
+
+    /// The report of the run, once it has one
+    pub fn into_report(self) -> Option<Report> {
+        self.report
+    }
+
+    /// End the running run without a report
+    pub fn fail(&mut self, error: impl Into<String>) -> Result<()> {
+        self.move_to(RunState::Failed)?;
+        self.error = Some(error.into());
+        Ok(())
+    }
+
+    /// Cancel the run before it starts
+    ///
+    /// A running run is cancelled through the token of its [`RunOptions`] and finishes with the
+    /// partial report of a cancelled run.
+    pub fn cancel(&mut self) -> Result<()> {
+        if self.state == RunState::Running {
+            return Err(Error::Run(format!(
+                "Run {} is running, cancel it through its run options",
+                self.id
+            )));
+        }
+        self.move_to(RunState::Cancelled)
+    }
+
+    /// Run the run on `engine`, starting it when pending, and finish it with its report, checked
+    /// against the SLOs and thresholds of the configuration
+    ///
+    /// A run the engine or the report fails on is marked as failed and the error returned.
+    pub async fn execute<C: HttpClient>(
+        &mut self,
+        engine: &LoadTestEngine<C>,
+        options: RunOptions,
+    ) -> Result<&mut Report> {
+        if self.state != RunState::Running {
+            self.start()?;
+        }
+        let report = match engine.execute(&self.config, options).await {
+            Ok(metrics) => Report::from_metrics(metrics).await,
+            Err(e) => Err(e),
+        };
+        match report {
+            Ok(mut report) => {
+                report.check_slos(&self.config.slos);
+                report.check_thresholds(&self.config.thresholds);
+                self.finish(report)?;
+                Ok(self
+                    .report
+                    .as_mut()
+                    .expect("finished runs keep their report"))
+            }
+            Err(e) => {
+                self.fail(e.to_string())?;
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HttpMethod;
+    use crate::metrics::TestMetrics;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+
+    fn run() -> TestRun {
+        let config = LoadTestConfig::new(
+            "https://example.com".to_string(),
+            HttpMethod::GET,
+            1,
+            1,
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        TestRun::new(config)
+    }
+
+    #[test]
+    fn test_run_state_machine() {
+        let mut run = run();
+        assert_eq!(run.state(), RunState::Pending);
+        assert!(run.fail("too early").is_err());
+        run.start().unwrap();
+        assert!(run.started_at.is_some());
+        assert!(run.start().is_err());
+        assert!(run.cancel().is_err());
+        run.fail("connection refused").unwrap();
+        assert_eq!(run.state(), RunState::Failed);
+        assert_eq!(run.error.as_deref(), Some("connection refused"));
+        assert!(run.finished_at.is_some());
+        assert!(run.start().is_err());
+
+        let mut run = self::run();
+        run.cancel().unwrap();
+        assert_eq!(run.state(), RunState::Cancelled);
+        assert!(run.started_at.is_none());
+        assert!(run.start().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_finished_run_keeps_its_report() {
+        let metrics = Arc::new(Mutex::new(TestMetrics::new().unwrap()));
+        let report = Report::from_metrics(metrics).await.unwrap();
+        let mut run = run();
+        assert!(run.finish(report.clone()).is_err());
+        run.start().unwrap();
+        run.finish(report.clone()).unwrap();
+        assert_eq!(run.state(), RunState::Completed);
+        assert!(run.report.is_some());
+
+        let mut run = self::run();
+        run.start().unwrap();
+        let mut late = report;
+        late.status = "deadline_exceeded".to_string();
+        run.finish(late).unwrap();
+        assert_eq!(run.state(), RunState::Aborted);
+    }
+
+    #[test]
+    fn test_restored_runs_are_finished() {
+        let config = run().config;
+        let started_at = Utc::now();
+        let restored = TestRun::restored(
+            "a".to_string(),
+            config.clone(),
+            RunState::Failed,
+            started_at,
+            Some(started_at),
+            Some("connection refused".to_string()),
+        )
+        .unwrap();
+        assert_eq!(restored.state(), RunState::Failed);
+        assert_eq!(restored.started_at, Some(started_at));
+        assert!(restored.report.is_none());
+        assert!(TestRun::restored(
+            "b".to_string(),
+            config,
+            RunState::Running,
+            started_at,
+            None,
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_run_state_names() {
+        for state in [
+            RunState::Pending,
+            RunState::Running,
+            RunState::Completed,
+            RunState::Aborted,
+            RunState::Cancelled,
+            RunState::Failed,
+        ] {
+            assert_eq!(state.as_str().parse::<RunState>().unwrap(), state);
+        }
+        assert_eq!("pending".parse::<RunState>().unwrap(), RunState::Pending);
+        assert!("done".parse::<RunState>().is_err());
+    }
+}
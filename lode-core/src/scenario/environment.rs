@@ -0,0 +1,182 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::extract::{render, Variables};
+use super::Scenario;
+use crate::config::ConfigError;
+
+/// Where a scenario is sent in one environment, e.g. `dev`, `staging` or `prod`
+///
+/// Selecting an environment resolves the relative URLs of the requests (those starting with `/`)
+/// against its `url`, fills its `variables` into the `{{name}}` placeholders of the requests and
+/// adds its headers, so one scenario runs against every environment without editing it:
+///
+/// ```json
+/// "environments": {
+///     "staging": {
+///         "url": "https://staging.example.com",
+///         "headers": {"X-Tenant": "load-test"},
+///         "auth": {"bearer": "staging-token"}
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Environment {
+    /// Base URL the relative request URLs are resolved against, may include a path
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// Headers sent with every request, unless the request sets them itself
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// Credentials sent in the `Authorization` header of every request
+    #[serde(default)]
+    pub auth: Option<Auth>,
+
+    /// Values of the `{{name}}` placeholders in the URLs, headers and bodies of the requests
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+/// Credentials of an environment
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Auth {
+    /// Token sent as `Bearer <token>`
+    Bearer(String),
+
+    /// User name and password sent as HTTP basic authentication
+    Basic { username: String, password: String },
+}
+
+impl Auth {
+    /// Value of the `Authorization` header
+    pub fn header_value(&self) -> String {
+        match self {
+            Auth::Bearer(token) => format!("Bearer {}", token),
+            Auth::Basic { username, password } => {
+                format!(
+                    "Basic {}",
+                    STANDARD.encode(format!("{}:{}", username, password))
+                )
+            }
+        }
+    }
+}
+
+impl Scenario {
+    /// Point the requests at the environment named `name`, out of those the scenario defines
+    ///
+    /// Headers of the environment and its `Authorization` header are only added to requests that
+    /// do not set them. Placeholders the environment has no variable for are left for the feeder
+    /// and extracted values.
+    pub fn apply_environment(&mut self, name: &str) -> Result<(), ConfigError> {
+        let Some(environment) = self.environments.get(name).cloned() else {
+            let known: Vec<&str> = self.environments.keys().map(String::as_str).collect();
+            return Err(ConfigError::InvalidScenario(match known.is_empty() {
+                true => format!("Unknown environment '{}', the scenario defines none", name),
+                false => format!(
+                    "Unknown environment '{}', expected one of: {}",
+                    name,
+                    known.join(", ")
+                ),
+            }));
+        };
+
+        let variables: Variables = environment.variables.into_iter().collect();
+        let mut headers = environment.headers;
+        if let Some(auth) = &environment.auth {
+            headers.insert("Authorization".to_string(), auth.header_value());
+        }
+        for request in &mut self.requests {
+            if let (Some(base), true) = (&environment.url, request.url.starts_with('/')) {
+                request.url = format!("{}{}", base.trim_end_matches('/'), request.url);
+            }
+            request.url = render(&request.url, &variables);
+            for value in request.headers.values_mut() {
+                *value = render(value, &variables);
+            }
+            for (name, value) in &headers {
+                if !request
+                    .headers
+                    .keys()
+                    .any(|header| header.eq_ignore_ascii_case(name))
+                {
+                    request
+                        .headers
+                        .insert(name.clone(), render(value, &variables));
+                }
+            }
+            request.body = request.body.as_deref().map(|body| render(body, &variables));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::RequestSpec;
+
+    fn scenario() -> Scenario {
+        serde_json::from_str(
+            r#"{
+                "environments": {
+                    "dev": {"url": "http://localhost:8080/"},
+                    "staging": {
+                        "url": "https://staging.example.com/api",
+                        "headers": {"X-Tenant": "{{tenant}}", "Accept": "text/plain"},
+                        "auth": {"basic": {"username": "lode", "password": "secret"}},
+                        "variables": {"tenant": "load-test"}
+                    }
+                },
+                "requests": [
+                    {"name": "home", "url": "/", "headers": {"Accept": "application/json"}},
+                    {"name": "tenant", "url": "/tenants/{{tenant}}/users/{{id}}"},
+                    {"name": "status", "url": "https://status.example.com"}
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_apply_environment() {
+        let mut staging = scenario();
+        staging.apply_environment("staging").unwrap();
+        let urls: Vec<&str> = staging.requests.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://staging.example.com/api/",
+                "https://staging.example.com/api/tenants/load-test/users/{{id}}",
+                "https://status.example.com",
+            ]
+        );
+        let home = &staging.requests[0].headers;
+        assert_eq!(home["Accept"], "application/json");
+        assert_eq!(home["X-Tenant"], "load-test");
+        assert_eq!(home["Authorization"], "Basic bG9kZTpzZWNyZXQ=");
+        assert_eq!(staging.requests[1].headers["Accept"], "text/plain");
+
+        let mut dev = scenario();
+        dev.apply_environment("dev").unwrap();
+        assert_eq!(dev.requests[0].url, "http://localhost:8080/");
+        assert!(!dev.requests[1].headers.contains_key("Authorization"));
+    }
+
+    #[test]
+    fn test_unknown_environment() {
+        let error = scenario().apply_environment("prod").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid scenario: Unknown environment 'prod', expected one of: dev, staging"
+        );
+        let mut plain = Scenario::new(vec![RequestSpec::new("home", "/")]);
+        assert!(plain.apply_environment("dev").is_err());
+        assert!(plain.validate().is_err());
+    }
+}
@@ -0,0 +1,272 @@
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::config::ConfigError;
+
+/// Values extracted so far in one iteration of a sequential scenario, by name
+pub type Variables = HashMap<String, String>;
+
+/// Where an extracted value is read from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "from", rename_all = "snake_case")]
+pub enum Source {
+    /// Dot-separated path into a JSON body, e.g. `data.items.0.id`
+    Json { path: String },
+
+    /// Value of a response header
+    Header { header: String },
+
+    /// First capture group of a regular expression matched against the body, or the whole match
+    /// when the pattern has no groups
+    Regex { pattern: String },
+}
+
+/// A named value taken from a response and injected into later requests as `{{name}}`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Extractor {
+    pub name: String,
+    #[serde(flatten)]
+    pub source: Source,
+}
+
+impl Extractor {
+    /// Extract the value at a dot-separated JSON path
+    pub fn json(name: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            source: Source::Json { path: path.into() },
+        }
+    }
+
+    /// Extract the value of a response header
+    pub fn header(name: impl Into<String>, header: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            source: Source::Header {
+                header: header.into(),
+            },
+        }
+    }
+
+    /// Extract the first capture group of a regular expression
+    pub fn regex(name: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            source: Source::Regex {
+                pattern: pattern.into(),
+            },
+        }
+    }
+
+    /// Check the extractor and prepare it for use on responses
+    pub(crate) fn compile(&self) -> Result<CompiledExtractor, ConfigError> {
+        if !is_variable_name(&self.name) {
+            return Err(ConfigError::InvalidScenario(format!(
+                "'{}' is not a valid variable name, use letters, digits and '_'",
+                self.name
+            )));
+        }
+        let matcher = match &self.source {
+            Source::Json { path } if path.is_empty() => {
+                return Err(ConfigError::InvalidScenario(format!(
+                    "JSON path of '{}' cannot be empty",
+                    self.name
+                )))
+            }
+            Source::Json { path } => Matcher::Json(path.clone()),
+            Source::Header { header } => Matcher::Header(header.clone()),
+            Source::Regex { pattern } => Matcher::Regex(Regex::new(pattern).map_err(|e| {
+                ConfigError::InvalidScenario(format!("Pattern of '{}': {}", self.name, e))
+            })?),
+        };
+        Ok(CompiledExtractor {
+            name: self.name.clone(),
+            matcher,
+        })
+    }
+}
+
+#[derive(Debug)]
+enum Matcher {
+    Json(String),
+    Header(String),
+    Regex(Regex),
+}
+
+/// An [`Extractor`] ready to run against responses
+#[derive(Debug)]
+pub(crate) struct CompiledExtractor {
+    pub(crate) name: String,
+    matcher: Matcher,
+}
+
+impl CompiledExtractor {
+    /// Read the value from a response, `None` when the response does not contain it
+    pub(crate) fn extract(&self, headers: &HeaderMap, body: &str) -> Option<String> {
+        match &self.matcher {
+            Matcher::Json(path) => {
+                let json: Value = serde_json::from_str(body).ok()?;
+                match json_path(&json, path)? {
+                    Value::String(s) => Some(s.clone()),
+                    Value::Null => None,
+                    other => Some(other.to_string()),
+                }
+            }
+            Matcher::Header(name) => headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            Matcher::Regex(regex) => {
+                let captures = regex.captures(body)?;
+                captures
+                    .get(1)
+                    .or_else(|| captures.get(0))
+                    .map(|m| m.as_str().to_string())
+            }
+        }
+    }
+
+    /// Describe the extraction for error messages
+    pub(crate) fn describe(&self) -> String {
+        match &self.matcher {
+            Matcher::Json(path) => format!("'{}' (JSON path '{}')", self.name, path),
+            Matcher::Header(name) => format!("'{}' (header '{}')", self.name, name),
+            Matcher::Regex(regex) => format!("'{}' (pattern '{}')", self.name, regex),
+        }
+    }
+}
+
+/// Walk a dot-separated path through JSON objects and arrays
+pub(crate) fn json_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(value, |value, segment| match value {
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            Value::Object(map) => map.get(segment),
+            _ => None,
+        })
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Names of the `{{name}}` placeholders in a template, in order of appearance
+pub fn placeholders(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        names.push(rest[start + 2..start + 2 + end].trim());
+        rest = &rest[start + 2 + end + 2..];
+    }
+    names
+}
+
+/// Replace the `{{name}}` placeholders of a template with their values
+///
+/// Placeholders without a value are left as they are; scenarios are validated so every
+/// placeholder refers to a value extracted by an earlier request.
+pub fn render(template: &str, variables: &Variables) -> String {
+    if !template.contains("{{") {
+        return template.to_string();
+    }
+
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let placeholder = &rest[start..start + 2 + end + 2];
+        rendered.push_str(&rest[..start]);
+        match variables.get(rest[start + 2..start + 2 + end].trim()) {
+            Some(value) => rendered.push_str(value),
+            None => rendered.push_str(placeholder),
+        }
+        rest = &rest[start + placeholder.len()..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_render_template() {
+        let variables = Variables::from([
+            ("token".to_string(), "abc".to_string()),
+            ("id".to_string(), "42".to_string()),
+        ]);
+
+        assert_eq!(
+            render("Bearer {{token}}", &variables),
+            "Bearer abc".to_string()
+        );
+        assert_eq!(
+            render("/users/{{ id }}/orders/{{id}}", &variables),
+            "/users/42/orders/42".to_string()
+        );
+        assert_eq!(render("{{missing}} {{", &variables), "{{missing}} {{");
+        assert_eq!(placeholders("{{a}}-{{ b }}-{{c"), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_extract_values() {
+        let body = r#"{"data": {"token": "abc", "items": [{"id": 7}]}, "next": null}"#;
+        let mut headers = HeaderMap::new();
+        headers.insert("location", HeaderValue::from_static("/orders/9"));
+
+        let extract = |extractor: Extractor| extractor.compile().unwrap().extract(&headers, body);
+
+        assert_eq!(
+            extract(Extractor::json("token", "data.token")),
+            Some("abc".to_string())
+        );
+        assert_eq!(
+            extract(Extractor::json("id", "data.items.0.id")),
+            Some("7".to_string())
+        );
+        assert_eq!(extract(Extractor::json("next", "next")), None);
+        assert_eq!(extract(Extractor::json("missing", "data.nope")), None);
+        assert_eq!(
+            extract(Extractor::header("location", "Location")),
+            Some("/orders/9".to_string())
+        );
+        assert_eq!(
+            extract(Extractor::regex("token", r#""token": "(\w+)""#)),
+            Some("abc".to_string())
+        );
+        assert_eq!(
+            extract(Extractor::regex("data", r"data")),
+            Some("data".to_string())
+        );
+    }
+
+    #[test]
+    fn test_compile_rejects_invalid_extractors() {
+        assert!(Extractor::json("bad name", "a").compile().is_err());
+        assert!(Extractor::json("a", "").compile().is_err());
+        assert!(Extractor::regex("a", "(unclosed").compile().is_err());
+    }
+
+    #[test]
+    fn test_extractor_deserialization() {
+        let extractor: Extractor =
+            serde_json::from_str(r#"{"name": "token", "from": "json", "path": "data.token"}"#)
+                .unwrap();
+        assert_eq!(extractor, Extractor::json("token", "data.token"));
+
+        let extractor: Extractor =
+            serde_json::from_str(r#"{"name": "loc", "from": "header", "header": "Location"}"#)
+                .unwrap();
+        assert_eq!(extractor, Extractor::header("loc", "Location"));
+    }
+}
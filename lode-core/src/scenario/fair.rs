@@ -0,0 +1,218 @@
+use std::cmp::Ordering;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Slot time a request is charged at least, so requests about as fast split the picks by weight
+/// rather than by the jitter of their latencies
+const MIN_SLOT_TIME: Duration = Duration::from_millis(20);
+
+/// Weighted fair queue picking the requests of a weighted scenario
+///
+/// The virtual users share their slots between the requests by weight: each request gets its
+/// weighted share of the time slots are held, rather than of the picks. The next request is the
+/// one that would finish earliest on its share, its virtual finish time being the slot time it
+/// used, requests in flight included, plus what one more is expected to use, over its weight. Slow
+/// requests so cannot take over the slots and starve fast ones; they are sent less often than
+/// their weights tell instead, which the mix of the report shows. Requests are charged at least
+/// [`MIN_SLOT_TIME`], and those whose slot time is not known yet are expected to take as long as
+/// the others, so with equal times every window of `sum(weights)` picks matches the weights.
+#[derive(Debug)]
+pub struct FairQueue {
+    epoch: Instant,
+    flows: Mutex<Vec<Flow>>,
+}
+
+/// Slot time used by the requests of one kind, in microseconds
+#[derive(Debug)]
+struct Flow {
+    weight: u64,
+    /// Slot time of the finished requests
+    used: u64,
+    finished: u64,
+    in_flight: u64,
+    /// Sum of the times the requests in flight were picked at, since the epoch
+    picked: u64,
+}
+
+impl Flow {
+    /// Slot time used at `now`, requests in flight included
+    fn used_at(&self, now: u64) -> u64 {
+        let in_flight = (self.in_flight * now).saturating_sub(self.picked);
+        self.used + in_flight.max(self.in_flight * min_slot_time())
+    }
+
+    /// Slot time of a request, `None` before one has finished
+    fn mean(&self) -> Option<u64> {
+        (self.finished > 0).then(|| self.used / self.finished)
+    }
+}
+
+fn min_slot_time() -> u64 {
+    MIN_SLOT_TIME.as_micros() as u64
+}
+
+/// A slot held by the request [`FairQueue::acquire`] picked, charged to it once dropped
+#[derive(Debug)]
+pub struct FairSlot<'a> {
+    queue: &'a FairQueue,
+    index: usize,
+    /// Microseconds since the epoch of the queue
+    picked: u64,
+}
+
+impl FairSlot<'_> {
+    /// Index of the request picked
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl Drop for FairSlot<'_> {
+    fn drop(&mut self) {
+        let now = self.queue.now();
+        let mut flows = self.queue.flows.lock().unwrap_or_else(|e| e.into_inner());
+        let flow = &mut flows[self.index];
+        flow.in_flight -= 1;
+        flow.picked -= self.picked;
+        flow.used += now.saturating_sub(self.picked).max(min_slot_time());
+        flow.finished += 1;
+    }
+}
+
+impl FairQueue {
+    /// Create the queue of requests with the given weights, none of which is zero
+    pub fn new(weights: impl IntoIterator<Item = u32>) -> Self {
+        let flows = weights
+            .into_iter()
+            .map(|weight| Flow {
+                weight: u64::from(weight),
+                used: 0,
+                finished: 0,
+                in_flight: 0,
+                picked: 0,
+            })
+            .collect();
+        Self {
+            epoch: Instant::now(),
+            flows: Mutex::new(flows),
+        }
+    }
+
+    fn now(&self) -> u64 {
+        self.epoch.elapsed().as_micros() as u64
+    }
+
+    /// Pick the next request for a slot, charged the time it holds the slot until released
+    pub fn acquire(&self) -> FairSlot<'_> {
+        let now = self.now();
+        let mut flows = self.flows.lock().unwrap_or_else(|e| e.into_inner());
+        let index = pick(&flows, now);
+        flows[index].in_flight += 1;
+        flows[index].picked += now;
+        FairSlot {
+            queue: self,
+            index,
+            picked: now,
+        }
+    }
+
+    /// Index of the next request, charged as taking as long as its requests did so far
+    pub fn next(&self) -> usize {
+        let now = self.now();
+        let mut flows = self.flows.lock().unwrap_or_else(|e| e.into_inner());
+        let index = pick(&flows, now);
+        let expected = expected_times(&flows)[index];
+        let flow = &mut flows[index];
+        flow.used += expected;
+        flow.finished += 1;
+        index
+    }
+}
+
+/// Slot time expected of a request of each kind: what its requests took, or the mean of the
+/// others while none has finished
+fn expected_times(flows: &[Flow]) -> Vec<u64> {
+    let known: Vec<u64> = flows.iter().filter_map(Flow::mean).collect();
+    let fallback = match known.len() {
+        0 => min_slot_time(),
+        n => known.iter().sum::<u64>() / n as u64,
+    };
+    flows
+        .iter()
+        .map(|flow| flow.mean().unwrap_or(fallback))
+        .collect()
+}
+
+/// Index of the request with the earliest virtual finish time at `now`; on ties the one that used
+/// the least of its share, so requests interleave, then the first one
+fn pick(flows: &[Flow], now: u64) -> usize {
+    let expected = expected_times(flows);
+    let tags: Vec<(u64, u64, u64)> = flows
+        .iter()
+        .zip(expected)
+        .map(|(flow, expected)| {
+            let used = flow.used_at(now);
+            (used + expected, used, flow.weight)
+        })
+        .collect();
+    // Tags over weights, compared by cross-multiplying to stay exact
+    let share = |a: u64, a_weight: u64, b: u64, b_weight: u64| -> Ordering {
+        (u128::from(a) * u128::from(b_weight)).cmp(&(u128::from(b) * u128::from(a_weight)))
+    };
+    tags.iter()
+        .enumerate()
+        .min_by(|(a_index, a), (b_index, b)| {
+            share(a.0, a.2, b.0, b.2)
+                .then(share(a.1, a.2, b.1, b.2))
+                .then(a_index.cmp(b_index))
+        })
+        .map(|(index, _)| index)
+        .expect("queue has at least one request")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fair_queue_interleaves_by_weight() {
+        let queue = FairQueue::new([3, 1]);
+        let picks: Vec<usize> = (0..8).map(|_| queue.next()).collect();
+        assert_eq!(picks, [0, 0, 1, 0, 0, 0, 1, 0]);
+
+        // Shares hold in every window, also across threads
+        let queue = std::sync::Arc::new(FairQueue::new([5, 3, 2]));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let queue = queue.clone();
+                std::thread::spawn(move || (0..250).map(|_| queue.next()).collect::<Vec<_>>())
+            })
+            .collect();
+        let picks: Vec<usize> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        for (index, weight) in [5, 3, 2].into_iter().enumerate() {
+            assert_eq!(
+                picks.iter().filter(|pick| **pick == index).count(),
+                weight * 100
+            );
+        }
+    }
+
+    #[test]
+    fn test_slow_requests_get_their_share_of_slot_time() {
+        let queue = FairQueue::new([1, 1]);
+        // A slow request holds its slot for 100ms, then fast ones take turns
+        let slow = queue.acquire();
+        assert_eq!(slow.index(), 0);
+        std::thread::sleep(Duration::from_millis(100));
+        drop(slow);
+        for _ in 0..4 {
+            let fast = queue.acquire();
+            assert_eq!(fast.index(), 1);
+        }
+        // Four fast requests still used less slot time than the slow one, so more are picked
+        assert_eq!(queue.acquire().index(), 1);
+    }
+}
@@ -0,0 +1,241 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::Scenario;
+use crate::config::ConfigError;
+
+/// Weights are kept to a hundredth of a percent
+const RESOLUTION: f64 = 100.0;
+
+/// Share of the traffic per endpoint, as percentages keyed by scenario request name
+///
+/// Typically exported from API gateway analytics, either as a JSON object
+/// (`{"browse": 62.5, "checkout": 7.5}`) or as `name,percentage` lines:
+///
+/// ```text
+/// endpoint,percentage
+/// browse,62.5%
+/// checkout,7.5%
+/// ```
+///
+/// The percentages do not need to add up to 100, they are relative to their sum.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(transparent)]
+pub struct WorkloadMix {
+    pub weights: BTreeMap<String, f64>,
+}
+
+impl WorkloadMix {
+    /// Parse a weights file, either a JSON object or `name,percentage` lines
+    ///
+    /// Empty lines and lines starting with `#` are skipped, as is a header line.
+    pub fn parse(content: &str) -> Result<Self, ConfigError> {
+        let invalid = |reason: String| ConfigError::InvalidScenario(reason);
+
+        if content.trim_start().starts_with('{') {
+            return serde_json::from_str(content)
+                .map_err(|e| invalid(format!("Invalid weights file: {}", e)));
+        }
+
+        let mut weights = BTreeMap::new();
+        let lines = content
+            .lines()
+            .map(str::trim)
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        for (position, (index, line)) in lines.enumerate() {
+            let (name, percentage) = line.rsplit_once(',').ok_or_else(|| {
+                invalid(format!(
+                    "Line {} of the weights file is not 'name,percentage': {}",
+                    index + 1,
+                    line
+                ))
+            })?;
+            let (name, percentage) = (unquote(name), unquote(percentage));
+            let percentage = match percentage.trim_end_matches('%').trim().parse::<f64>() {
+                Ok(percentage) => percentage,
+                Err(_) if position == 0 => continue,
+                Err(_) => {
+                    return Err(invalid(format!(
+                        "Invalid percentage on line {} of the weights file: {}",
+                        index + 1,
+                        percentage
+                    )))
+                }
+            };
+            if weights.insert(name.to_string(), percentage).is_some() {
+                return Err(invalid(format!(
+                    "Endpoint '{}' appears twice in the weights file",
+                    name
+                )));
+            }
+        }
+
+        Ok(Self { weights })
+    }
+
+    /// Check that the mix has at least one endpoint and only positive percentages
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |reason: String| Err(ConfigError::InvalidScenario(reason));
+
+        if self.weights.is_empty() {
+            return invalid("A weights file needs at least one endpoint".to_string());
+        }
+        for (name, percentage) in &self.weights {
+            if !(percentage.is_finite() && *percentage > 0.0) {
+                return invalid(format!(
+                    "Percentage of endpoint '{}' must be greater than 0, got {}",
+                    name, percentage
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Scenario {
+    /// Replace the weights of the requests with the percentages of `mix`
+    ///
+    /// Requests missing from the mix are removed, so endpoints without production traffic are
+    /// not sent. Every endpoint of the mix must name a request of the scenario.
+    pub fn apply_mix(&mut self, mix: &WorkloadMix) -> Result<(), ConfigError> {
+        let invalid = |reason: String| Err(ConfigError::InvalidScenario(reason));
+
+        mix.validate()?;
+        if self.is_sequential() {
+            return invalid("A weights file only applies to weighted scenarios".to_string());
+        }
+        if let Some(name) = mix
+            .weights
+            .keys()
+            .find(|name| !self.requests.iter().any(|r| &r.name == *name))
+        {
+            return invalid(format!(
+                "Weights file names '{}', which is not a request of the scenario",
+                name
+            ));
+        }
+
+        let total: f64 = mix.weights.values().sum();
+        self.requests.retain(|r| mix.weights.contains_key(&r.name));
+        for request in &mut self.requests {
+            let share = mix.weights[&request.name] / total * 100.0;
+            request.weight = ((share * RESOLUTION).round() as u32).max(1);
+        }
+
+        // Keep the schedule cycle short, e.g. 50% and 25% become 2:1
+        let divisor = self.requests.iter().map(|r| r.weight).fold(0, gcd);
+        for request in &mut self.requests {
+            request.weight /= divisor;
+        }
+        Ok(())
+    }
+}
+
+fn unquote(field: &str) -> &str {
+    let field = field.trim();
+    field
+        .strip_prefix('"')
+        .and_then(|f| f.strip_suffix('"'))
+        .unwrap_or(field)
+        .trim()
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::{RequestSpec, ScenarioMode};
+
+    fn scenario(names: &[&str]) -> Scenario {
+        Scenario::new(
+            names
+                .iter()
+                .map(|name| RequestSpec::new(*name, format!("http://example.com/{}", name)))
+                .collect(),
+        )
+    }
+
+    fn weights(scenario: &Scenario) -> Vec<(&str, u32)> {
+        scenario
+            .requests
+            .iter()
+            .map(|r| (r.name.as_str(), r.weight))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        let mix = WorkloadMix::parse(
+            "# exported 2026-10-01\nendpoint,percentage\nbrowse,62.5%\n\n\"search\", 30\ncheckout,7.5\n",
+        )
+        .unwrap();
+        assert_eq!(
+            mix.weights,
+            BTreeMap::from([
+                ("browse".to_string(), 62.5),
+                ("checkout".to_string(), 7.5),
+                ("search".to_string(), 30.0),
+            ])
+        );
+
+        assert!(WorkloadMix::parse("browse,62.5\nsearch,lots").is_err());
+        assert!(WorkloadMix::parse("browse 62.5").is_err());
+        assert!(WorkloadMix::parse("browse,50\nbrowse,50").is_err());
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let mix = WorkloadMix::parse(r#"{"browse": 75, "checkout": 25}"#).unwrap();
+        assert_eq!(mix.weights["browse"], 75.0);
+        assert_eq!(mix.weights["checkout"], 25.0);
+        assert!(WorkloadMix::parse(r#"{"browse": "a lot"}"#).is_err());
+    }
+
+    #[test]
+    fn test_apply_mix() {
+        let mut mixed = scenario(&["browse", "search", "checkout", "admin"]);
+        let mix = WorkloadMix::parse("browse,50\nsearch,37.5\ncheckout,12.5").unwrap();
+        mixed.apply_mix(&mix).unwrap();
+        assert_eq!(
+            weights(&mixed),
+            vec![("browse", 4), ("search", 3), ("checkout", 1)]
+        );
+
+        // Percentages are relative to their sum
+        let mut mixed = scenario(&["browse", "checkout"]);
+        let mix = WorkloadMix::parse("browse,30\ncheckout,10").unwrap();
+        mixed.apply_mix(&mix).unwrap();
+        assert_eq!(weights(&mixed), vec![("browse", 3), ("checkout", 1)]);
+
+        // Tiny shares still get some traffic
+        let mut mixed = scenario(&["browse", "export"]);
+        let mix = WorkloadMix::parse("browse,99.999\nexport,0.001").unwrap();
+        mixed.apply_mix(&mix).unwrap();
+        assert_eq!(weights(&mixed), vec![("browse", 10000), ("export", 1)]);
+    }
+
+    #[test]
+    fn test_apply_invalid_mix() {
+        let mix = WorkloadMix::parse("browse,50\nunknown,50").unwrap();
+        assert!(scenario(&["browse"]).apply_mix(&mix).is_err());
+
+        let mix = WorkloadMix::parse("browse,0").unwrap();
+        assert!(scenario(&["browse"]).apply_mix(&mix).is_err());
+        assert!(scenario(&["browse"])
+            .apply_mix(&WorkloadMix::default())
+            .is_err());
+
+        let mut sequential = scenario(&["browse"]);
+        sequential.mode = ScenarioMode::Sequential;
+        let mix = WorkloadMix::parse("browse,100").unwrap();
+        assert!(sequential.apply_mix(&mix).is_err());
+    }
+}
@@ -0,0 +1,439 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use url::Url;
+
+use crate::config::{ConfigError, HttpMethod};
+use crate::headers::validate_header;
+use crate::metrics::{Labels, SuccessCodes};
+
+pub mod environment;
+pub mod extract;
+pub mod fair;
+pub mod mix;
+pub mod query;
+
+use environment::Environment;
+use extract::{placeholders, render, Extractor};
+use fair::FairQueue;
+
+/// Label holding the name of the scenario request a metric belongs to
+pub const ENDPOINT_LABEL: &str = "endpoint";
+
+/// A single request of a scenario
+///
+/// The URL, header values and body may contain `{{name}}` placeholders, filled with the columns
+/// of the run's feeder or, in a sequential scenario, the values extracted from the responses of
+/// earlier requests.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RequestSpec {
+    /// Name used for the per-endpoint breakdown
+    pub name: String,
+
+    /// Target URL
+    pub url: String,
+
+    /// HTTP method
+    #[serde(default = "default_method")]
+    pub method: HttpMethod,
+
+    /// Headers sent in addition to the run-level headers
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// Optional request body
+    #[serde(default)]
+    pub body: Option<String>,
+
+    /// Relative share of the traffic sent to this request
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+
+    /// Labels added to the run-level labels for this request
+    #[serde(default)]
+    pub labels: Labels,
+
+    /// Values taken from the response for the following requests of a sequential scenario
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extract: Vec<Extractor>,
+
+    /// Status codes counted as successful for this request, e.g. `201`, instead of the run's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub success_codes: Option<SuccessCodes>,
+}
+
+fn default_method() -> HttpMethod {
+    HttpMethod::GET
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+impl RequestSpec {
+    /// GET request with weight 1
+    pub fn new(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            url: url.into(),
+            method: HttpMethod::GET,
+            headers: BTreeMap::new(),
+            body: None,
+            weight: 1,
+            labels: Labels::new(),
+            extract: Vec::new(),
+            success_codes: None,
+        }
+    }
+
+    /// Set the HTTP method
+    pub fn with_method(mut self, method: HttpMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Set the request body
+    pub fn with_body(mut self, body: impl Into<String>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Count responses with these status codes as successful for this request
+    pub fn with_success_codes(mut self, success_codes: SuccessCodes) -> Self {
+        self.success_codes = Some(success_codes);
+        self
+    }
+
+    /// Set the relative share of the traffic
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Add a header sent in addition to the run-level headers
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Extract a value from the response for the following requests
+    pub fn with_extract(mut self, extractor: Extractor) -> Self {
+        self.extract.push(extractor);
+        self
+    }
+
+    /// Templates of the request that may reference extracted values
+    fn templates(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.url.as_str())
+            .chain(self.headers.values().map(String::as_str))
+            .chain(self.body.as_deref())
+    }
+}
+
+/// How the requests of a scenario are executed
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScenarioMode {
+    /// Every request of the run picks one of the requests, in proportion to their weights
+    #[default]
+    Weighted,
+
+    /// Every request of the run picks one of the requests at random, in proportion to their weights
+    Random,
+
+    /// Every iteration of the run sends all requests in order, like a user flow
+    /// (login, fetch a token, call the API)
+    ///
+    /// Values extracted from a response can be used by the following requests. An iteration stops
+    /// at the first failed request or missing value, since the rest of the flow depends on it.
+    Sequential,
+}
+
+/// A set of requests executed as a weighted mix or as a sequential flow
+///
+/// In a weighted mix requests are interleaved with smooth weighted round-robin, so weights `3:1`
+/// send `a, a, b, a` rather than `a, a, a, b`, and every window of `sum(weights)` requests matches
+/// the weights exactly. See [`ScenarioMode::Sequential`] for flows.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Scenario {
+    #[serde(default)]
+    pub mode: ScenarioMode,
+
+    pub requests: Vec<RequestSpec>,
+
+    /// Targets of the scenario by environment name, one of which a run may select
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub environments: BTreeMap<String, Environment>,
+}
+
+impl Scenario {
+    /// Create a weighted scenario from its requests
+    pub fn new(requests: Vec<RequestSpec>) -> Self {
+        Self {
+            mode: ScenarioMode::Weighted,
+            requests,
+            environments: BTreeMap::new(),
+        }
+    }
+
+    /// Create a sequential scenario sending the requests in order
+    pub fn sequential(requests: Vec<RequestSpec>) -> Self {
+        Self {
+            mode: ScenarioMode::Sequential,
+            requests,
+            environments: BTreeMap::new(),
+        }
+    }
+
+    /// Create a weighted scenario sending the same request to each URL in turn
+    ///
+    /// Requests are named after their URL, so reports break results down per URL.
+    pub fn from_urls(urls: impl IntoIterator<Item = String>, method: HttpMethod) -> Self {
+        Self::new(
+            urls.into_iter()
+                .map(|url| RequestSpec::new(url.clone(), url).with_method(method.clone()))
+                .collect(),
+        )
+    }
+
+    /// Send the requests in a random order instead of interleaving them
+    pub fn with_random_order(mut self) -> Self {
+        self.mode = ScenarioMode::Random;
+        self
+    }
+
+    /// Whether the requests are sent in order as a flow
+    pub fn is_sequential(&self) -> bool {
+        self.mode == ScenarioMode::Sequential
+    }
+
+    /// Check that the scenario can be executed
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.validate_with(&BTreeSet::new())
+    }
+
+    /// Check that the scenario can be executed with values for the given placeholders, e.g. the
+    /// columns of a feeder, on top of the values extracted by its requests
+    pub fn validate_with(&self, provided: &BTreeSet<&str>) -> Result<(), ConfigError> {
+        let invalid = |reason: String| Err(ConfigError::InvalidScenario(reason));
+
+        if self.requests.is_empty() {
+            return invalid("A scenario needs at least one request".to_string());
+        }
+
+        let mut names = HashSet::new();
+        let mut variables = HashSet::new();
+        for request in &self.requests {
+            if request.name.trim().is_empty() {
+                return invalid("Request names cannot be empty".to_string());
+            }
+            if !names.insert(request.name.as_str()) {
+                return invalid(format!("Duplicate request name '{}'", request.name));
+            }
+            if request.weight == 0 {
+                return invalid(format!(
+                    "Weight of request '{}' must be greater than 0",
+                    request.name
+                ));
+            }
+            for name in request.templates().flat_map(placeholders) {
+                if !variables.contains(name) && !provided.contains(name) {
+                    return invalid(format!(
+                        "Request '{}' uses '{{{{{}}}}}', which no earlier request extracts and no feeder provides",
+                        request.name, name
+                    ));
+                }
+            }
+            if !request.extract.is_empty() && !self.is_sequential() {
+                return invalid(format!(
+                    "Request '{}' extracts values, which requires a sequential scenario",
+                    request.name
+                ));
+            }
+            for extractor in &request.extract {
+                extractor.compile()?;
+                variables.insert(extractor.name.as_str());
+            }
+            if request.url.starts_with('/') {
+                return invalid(format!(
+                    "Request '{}' has a relative URL, which needs an environment with a base URL",
+                    request.name
+                ));
+            }
+            // Placeholders are checked above, validate the URL around them
+            let stand_ins = placeholders(&request.url)
+                .into_iter()
+                .map(|name| (name.to_string(), "x".to_string()))
+                .collect();
+            match Url::parse(&render(&request.url, &stand_ins)) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+                Ok(url) => {
+                    return invalid(format!(
+                        "Request '{}' uses unsupported scheme '{}'",
+                        request.name,
+                        url.scheme()
+                    ))
+                }
+                Err(e) => return invalid(format!("Request '{}': {}", request.name, e)),
+            }
+            for (name, value) in &request.headers {
+                validate_header(name, value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Order in which requests are picked, one cycle of `sum(weights)` entries
+    pub fn schedule(&self) -> Vec<usize> {
+        let queue = self.fair_queue();
+        let total: u32 = self.requests.iter().map(|r| r.weight).sum();
+        (0..total).map(|_| queue.next()).collect()
+    }
+
+    /// Queue picking the requests of a weighted mix one at a time, in the order of
+    /// [`Scenario::schedule`]
+    pub fn fair_queue(&self) -> FairQueue {
+        FairQueue::new(self.requests.iter().map(|r| r.weight))
+    }
+
+    /// Share of the requests of a weighted or random mix each request should get, in percent, keyed
+    /// by request name; empty for a sequential scenario
+    pub fn configured_mix(&self) -> BTreeMap<String, f64> {
+        if self.is_sequential() {
+            return BTreeMap::new();
+        }
+        let total: f64 = self.requests.iter().map(|r| r.weight as f64).sum();
+        self.requests
+            .iter()
+            .map(|r| (r.name.clone(), r.weight as f64 / total * 100.0))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenario(weights: &[u32]) -> Scenario {
+        Scenario::new(
+            weights
+                .iter()
+                .enumerate()
+                .map(|(i, weight)| {
+                    RequestSpec::new(format!("r{}", i), "http://example.com").with_weight(*weight)
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_schedule_is_proportional_and_interleaved() {
+        assert_eq!(scenario(&[3, 1]).schedule(), vec![0, 0, 1, 0]);
+        assert_eq!(scenario(&[1, 1, 1]).schedule(), vec![0, 1, 2]);
+
+        let schedule = scenario(&[5, 3, 2]).schedule();
+        assert_eq!(schedule.len(), 10);
+        for (index, weight) in [5, 3, 2].into_iter().enumerate() {
+            assert_eq!(schedule.iter().filter(|i| **i == index).count(), weight);
+        }
+    }
+
+    #[test]
+    fn test_validate_scenario() {
+        assert!(scenario(&[1, 2]).validate().is_ok());
+        assert!(Scenario::new(vec![]).validate().is_err());
+        assert!(scenario(&[1, 0]).validate().is_err());
+
+        let mut duplicate = scenario(&[1, 1]);
+        duplicate.requests[1].name = "r0".to_string();
+        assert!(duplicate.validate().is_err());
+
+        let mut bad_url = scenario(&[1]);
+        bad_url.requests[0].url = "ftp://example.com".to_string();
+        assert!(bad_url.validate().is_err());
+
+        let mut bad_header = scenario(&[1]);
+        bad_header.requests[0]
+            .headers
+            .insert("Bad Header".to_string(), "value".to_string());
+        assert!(matches!(
+            bad_header.validate().unwrap_err(),
+            ConfigError::InvalidHeader(_)
+        ));
+    }
+
+    #[test]
+    fn test_validate_sequential_scenario() {
+        let login = RequestSpec::new("login", "http://example.com/login")
+            .with_method(HttpMethod::POST)
+            .with_extract(Extractor::json("token", "token"))
+            .with_extract(Extractor::json("user", "user.id"));
+        let profile = RequestSpec::new("profile", "http://example.com/users/{{ user }}")
+            .with_header("Authorization", "Bearer {{token}}");
+
+        assert!(Scenario::sequential(vec![login.clone(), profile.clone()])
+            .validate()
+            .is_ok());
+
+        // Values can only be used after they are extracted
+        assert!(Scenario::sequential(vec![profile.clone(), login.clone()])
+            .validate()
+            .is_err());
+        assert!(Scenario::sequential(vec![profile.clone()])
+            .validate()
+            .is_err());
+
+        // ...or when a feeder provides them
+        assert!(Scenario::sequential(vec![profile])
+            .validate_with(&BTreeSet::from(["user", "token"]))
+            .is_ok());
+
+        // Extraction needs an order between requests
+        assert!(Scenario::new(vec![login.clone()]).validate().is_err());
+
+        let bad_pattern = login.with_extract(Extractor::regex("id", "(unclosed"));
+        assert!(Scenario::sequential(vec![bad_pattern]).validate().is_err());
+    }
+
+    #[test]
+    fn test_scenario_deserialization_defaults() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{"requests": [
+                {"name": "home", "url": "http://example.com/"},
+                {"name": "order", "url": "http://example.com/orders", "method": "POST",
+                 "body": "{}", "weight": 3, "headers": {"Content-Type": "application/json"}}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(scenario.mode, ScenarioMode::Weighted);
+        assert_eq!(scenario.requests[0].method, HttpMethod::GET);
+        assert_eq!(scenario.requests[0].weight, 1);
+        assert_eq!(scenario.requests[1].method, HttpMethod::POST);
+        assert_eq!(scenario.requests[1].weight, 3);
+        assert_eq!(
+            scenario.requests[1].headers["Content-Type"],
+            "application/json"
+        );
+    }
+
+    #[test]
+    fn test_sequential_scenario_deserialization() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{"mode": "sequential", "requests": [
+                {"name": "login", "url": "http://example.com/login", "method": "POST",
+                 "extract": [{"name": "token", "from": "json", "path": "token"}]},
+                {"name": "orders", "url": "http://example.com/orders",
+                 "headers": {"Authorization": "Bearer {{token}}"}}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert!(scenario.is_sequential());
+        assert_eq!(
+            scenario.requests[0].extract,
+            vec![Extractor::json("token", "token")]
+        );
+        assert!(scenario.validate().is_ok());
+    }
+}
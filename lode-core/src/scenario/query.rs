@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use url::form_urlencoded;
+
+use crate::config::ConfigError;
+use crate::retry::random_fraction;
+
+/// Values a randomized query parameter takes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamValues {
+    /// An integer between both bounds, inclusive
+    Range(i64, i64),
+    /// One of the listed values
+    Choice(Vec<String>),
+}
+
+/// A query parameter appended to every request with a random value, e.g. to defeat caches or
+/// spread the load over keys
+///
+/// Written as `name=min..max` for an integer range or `name=a|b|c` for a choice of values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RandomParam {
+    pub name: String,
+    pub values: ParamValues,
+}
+
+impl RandomParam {
+    /// Pick a value for one request
+    pub fn sample(&self) -> String {
+        match &self.values {
+            ParamValues::Range(min, max) => {
+                let span = max.abs_diff(*min);
+                let offset = ((random_fraction() * (span as f64 + 1.0)) as u64).min(span);
+                (*min as i128 + offset as i128).to_string()
+            }
+            ParamValues::Choice(values) => {
+                let index = (random_fraction() * values.len() as f64) as usize;
+                values[index.min(values.len() - 1)].clone()
+            }
+        }
+    }
+}
+
+/// Append the parameters to a URL with freshly picked values
+pub fn append_random_params(url: &str, params: &[RandomParam]) -> String {
+    if params.is_empty() {
+        return url.to_string();
+    }
+    let (url, fragment) = match url.split_once('#') {
+        Some((url, fragment)) => (url, Some(fragment)),
+        None => (url, None),
+    };
+    let mut query = form_urlencoded::Serializer::new(String::new());
+    for param in params {
+        query.append_pair(&param.name, &param.sample());
+    }
+    let separator = match url.contains('?') {
+        true if url.ends_with(['?', '&']) => "",
+        true => "&",
+        false => "?",
+    };
+    let mut appended = format!("{}{}{}", url, separator, query.finish());
+    if let Some(fragment) = fragment {
+        appended.push('#');
+        appended.push_str(fragment);
+    }
+    appended
+}
+
+impl FromStr for RandomParam {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| {
+            Err(ConfigError::InvalidQueryParam(format!(
+                "{}: {}, expected name=min..max or name=a|b|c",
+                s, reason
+            )))
+        };
+        let Some((name, values)) = s.split_once('=') else {
+            return invalid("missing '='");
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return invalid("the name is empty");
+        }
+
+        let values = match values.split_once("..") {
+            Some((min, max)) => {
+                let (Ok(min), Ok(max)) = (min.trim().parse(), max.trim().parse()) else {
+                    return invalid("the bounds must be integers");
+                };
+                if min > max {
+                    return invalid("the lower bound is above the upper bound");
+                }
+                ParamValues::Range(min, max)
+            }
+            None => {
+                let values: Vec<String> = values.split('|').map(str::to_string).collect();
+                if values.iter().any(String::is_empty) {
+                    return invalid("a value is empty");
+                }
+                ParamValues::Choice(values)
+            }
+        };
+        Ok(Self {
+            name: name.to_string(),
+            values,
+        })
+    }
+}
+
+impl TryFrom<String> for RandomParam {
+    type Error = ConfigError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<RandomParam> for String {
+    fn from(param: RandomParam) -> Self {
+        param.to_string()
+    }
+}
+
+impl fmt::Display for RandomParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.values {
+            ParamValues::Range(min, max) => write!(f, "{}={}..{}", self.name, min, max),
+            ParamValues::Choice(values) => write!(f, "{}={}", self.name, values.join("|")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_param_from_str() {
+        let id: RandomParam = "id=1..1000".parse().unwrap();
+        assert_eq!(id.values, ParamValues::Range(1, 1000));
+        assert_eq!(id.to_string(), "id=1..1000");
+        let sort: RandomParam = "sort=asc|desc".parse().unwrap();
+        assert_eq!(
+            sort.values,
+            ParamValues::Choice(vec!["asc".to_string(), "desc".to_string()])
+        );
+
+        assert!("id".parse::<RandomParam>().is_err());
+        assert!("=1..2".parse::<RandomParam>().is_err());
+        assert!("id=5..1".parse::<RandomParam>().is_err());
+        assert!("id=a..b".parse::<RandomParam>().is_err());
+        assert!("sort=asc||desc".parse::<RandomParam>().is_err());
+    }
+
+    #[test]
+    fn test_append_random_params() {
+        let params = vec!["page=1..3".parse().unwrap(), "q=a b|a b".parse().unwrap()];
+        for _ in 0..50 {
+            let url = append_random_params("http://example.com/search", &params);
+            let page: i64 = url["http://example.com/search?page=".len()..][..1]
+                .parse()
+                .unwrap();
+            assert!((1..=3).contains(&page));
+            assert!(url.ends_with("&q=a+b"));
+        }
+
+        assert_eq!(
+            append_random_params("http://example.com/?lang=en#top", &params[1..]),
+            "http://example.com/?lang=en&q=a+b#top"
+        );
+        assert_eq!(
+            append_random_params("http://example.com/", &[]),
+            "http://example.com/"
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::{parse_duration, ConfigError};
+use crate::report::Report;
+
+/// A figure of a report that an objective is set on
+///
+/// Response times are in milliseconds, the error rate in percent of all requests and the
+/// throughput in requests per second.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+#[schemars(with = "String")]
+pub enum SloMetric {
+    MinResponseTime,
+    MaxResponseTime,
+    MeanResponseTime,
+    /// Response time at a percentile between 0 and 100, e.g. `p95` or `p99.9`
+    Percentile(f64),
+    ErrorRate,
+    RequestsPerSecond,
+}
+
+impl SloMetric {
+    /// Whether the metric is a response time, whose targets are durations
+    pub fn is_response_time(&self) -> bool {
+        !matches!(self, SloMetric::ErrorRate | SloMetric::RequestsPerSecond)
+    }
+
+    /// Value of the metric in a report, `None` for a percentile the report cannot tell
+    pub fn measure(&self, report: &Report) -> Option<f64> {
+        match *self {
+            SloMetric::MinResponseTime => Some(report.min_response_time_ms),
+            SloMetric::MaxResponseTime => Some(report.max_response_time_ms),
+            SloMetric::MeanResponseTime => Some(report.mean_response_time_ms),
+            SloMetric::Percentile(percentile) => report.response_time_at(percentile),
+            SloMetric::ErrorRate if report.total_requests == 0 => Some(0.0),
+            SloMetric::ErrorRate => {
+                Some(report.failed_requests as f64 / report.total_requests as f64 * 100.0)
+            }
+            SloMetric::RequestsPerSecond => Some(report.requests_per_second),
+        }
+    }
+}
+
+impl FromStr for SloMetric {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        let metric = match name.as_str() {
+            "min" => SloMetric::MinResponseTime,
+            "max" => SloMetric::MaxResponseTime,
+            "mean" | "avg" => SloMetric::MeanResponseTime,
+            "median" => SloMetric::Percentile(50.0),
+            "error_rate" | "errors" => SloMetric::ErrorRate,
+            "rps" | "throughput" => SloMetric::RequestsPerSecond,
+            _ => {
+                let percentile = name
+                    .strip_prefix('p')
+                    .and_then(|p| p.parse::<f64>().ok())
+                    .filter(|p| p.is_finite() && *p > 0.0 && *p <= 100.0)
+                    .ok_or_else(|| {
+                        ConfigError::InvalidSlo(format!(
+                            "Unknown metric '{}', expected min, max, mean, median, p<percentile>, \
+                             error_rate or rps",
+                            s.trim()
+                        ))
+                    })?;
+                SloMetric::Percentile(percentile)
+            }
+        };
+        Ok(metric)
+    }
+}
+
+impl TryFrom<String> for SloMetric {
+    type Error = ConfigError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<SloMetric> for String {
+    fn from(metric: SloMetric) -> Self {
+        metric.to_string()
+    }
+}
+
+impl fmt::Display for SloMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SloMetric::MinResponseTime => write!(f, "min"),
+            SloMetric::MaxResponseTime => write!(f, "max"),
+            SloMetric::MeanResponseTime => write!(f, "mean"),
+            SloMetric::Percentile(percentile) => write!(f, "p{}", percentile),
+            SloMetric::ErrorRate => write!(f, "error_rate"),
+            SloMetric::RequestsPerSecond => write!(f, "rps"),
+        }
+    }
+}
+
+/// Direction in which a metric must stay of its target
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub enum Comparison {
+    #[serde(rename = "<")]
+    Below,
+    #[serde(rename = "<=")]
+    AtMost,
+    #[serde(rename = ">")]
+    Above,
+    #[serde(rename = ">=")]
+    AtLeast,
+}
+
+impl Comparison {
+    fn holds(&self, measured: f64, target: f64) -> bool {
+        match self {
+            Comparison::Below => measured < target,
+            Comparison::AtMost => measured <= target,
+            Comparison::Above => measured > target,
+            Comparison::AtLeast => measured >= target,
+        }
+    }
+
+    fn is_upper_bound(&self) -> bool {
+        matches!(self, Comparison::Below | Comparison::AtMost)
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Comparison::Below => "<",
+            Comparison::AtMost => "<=",
+            Comparison::Above => ">",
+            Comparison::AtLeast => ">=",
+        })
+    }
+}
+
+/// A service level objective, such as `p95 < 250ms`, `error_rate < 1%` or `rps >= 100`
+///
+/// Response time targets take a duration (`250ms`, `1.5s`, a bare number is milliseconds), error
+/// rate targets a percentage and throughput targets requests per second.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct SloTarget {
+    pub metric: SloMetric,
+    pub comparison: Comparison,
+    /// Target in the unit of the metric
+    pub target: f64,
+}
+
+impl SloTarget {
+    /// Measure the objective in a report
+    pub fn check(&self, report: &Report) -> SloResult {
+        self.check_measured(self.metric.measure(report))
+    }
+
+    /// Compare a figure measured elsewhere, e.g. on some of the requests, with the target
+    pub(crate) fn check_measured(&self, measured: Option<f64>) -> SloResult {
+        let passed = measured.is_some_and(|measured| self.comparison.holds(measured, self.target));
+        let headroom_percent = match measured {
+            Some(measured) if self.target != 0.0 => {
+                let margin = if self.comparison.is_upper_bound() {
+                    self.target - measured
+                } else {
+                    measured - self.target
+                };
+                Some(margin / self.target * 100.0)
+            }
+            _ => None,
+        };
+        SloResult {
+            slo: *self,
+            measured,
+            passed,
+            headroom_percent,
+        }
+    }
+}
+
+impl FromStr for SloTarget {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| ConfigError::InvalidSlo(format!("{}: {}", s, reason));
+
+        let position = s
+            .find(['<', '>'])
+            .ok_or_else(|| invalid("expected <metric> <comparison> <target>, e.g. p95 < 250ms"))?;
+        let (metric, rest) = s.split_at(position);
+        let (comparison, target) = match rest {
+            _ if rest.starts_with("<=") => (Comparison::AtMost, &rest[2..]),
+            _ if rest.starts_with(">=") => (Comparison::AtLeast, &rest[2..]),
+            _ if rest.starts_with('<') => (Comparison::Below, &rest[1..]),
+            _ => (Comparison::Above, &rest[1..]),
+        };
+        let metric: SloMetric = metric.parse()?;
+
+        let target = target.trim();
+        let target = if metric.is_response_time() {
+            if target.chars().all(|c| c.is_ascii_digit() || c == '.') {
+                target.parse::<f64>().ok()
+            } else {
+                parse_duration(target)
+                    .ok()
+                    .map(|duration| duration.as_secs_f64() * 1000.0)
+            }
+        } else {
+            target.trim_end_matches('%').trim().parse::<f64>().ok()
+        };
+        let target = target
+            .filter(|target| target.is_finite() && *target >= 0.0)
+            .ok_or_else(|| invalid("target must be a non-negative number or duration"))?;
+
+        Ok(Self {
+            metric,
+            comparison,
+            target,
+        })
+    }
+}
+
+impl fmt::Display for SloTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.metric, self.comparison, self.target)?;
+        match self.metric {
+            SloMetric::ErrorRate => write!(f, "%"),
+            SloMetric::RequestsPerSecond => Ok(()),
+            _ => write!(f, "ms"),
+        }
+    }
+}
+
+/// Outcome of a [`SloTarget`] measured in a report
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct SloResult {
+    pub slo: SloTarget,
+    /// Value of the metric, `None` when the report cannot tell it
+    pub measured: Option<f64>,
+    pub passed: bool,
+    /// Distance to the target in percent of the target, negative when the objective is missed
+    pub headroom_percent: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> Report {
+        Report {
+            total_requests: 1000,
+            failed_requests: 5,
+            requests_per_second: 120.0,
+            mean_response_time_ms: 90.0,
+            median_response_time_ms: 80.0,
+            p95_response_time_ms: 200.0,
+            p99_response_time_ms: 400.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_slo_targets() {
+        let slo: SloTarget = "p95 < 250ms".parse().unwrap();
+        assert_eq!(slo.metric, SloMetric::Percentile(95.0));
+        assert_eq!(slo.comparison, Comparison::Below);
+        assert_eq!(slo.target, 250.0);
+
+        let slo: SloTarget = "p99.9<=1.5s".parse().unwrap();
+        assert_eq!(slo.metric, SloMetric::Percentile(99.9));
+        assert_eq!(slo.comparison, Comparison::AtMost);
+        assert_eq!(slo.target, 1500.0);
+
+        let slo: SloTarget = "error_rate < 1%".parse().unwrap();
+        assert_eq!(slo.metric, SloMetric::ErrorRate);
+        assert_eq!(slo.target, 1.0);
+
+        let slo: SloTarget = "rps >= 100".parse().unwrap();
+        assert_eq!(slo.comparison, Comparison::AtLeast);
+        assert_eq!(slo.to_string(), "rps >= 100");
+        assert_eq!("median < 80".parse::<SloTarget>().unwrap().target, 80.0);
+
+        for invalid in [
+            "p95",
+            "p95 = 250ms",
+            "latency < 1s",
+            "p101 < 1s",
+            "p95 < soon",
+        ] {
+            assert!(
+                matches!(
+                    invalid.parse::<SloTarget>(),
+                    Err(ConfigError::InvalidSlo(_))
+                ),
+                "{} should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_slo_targets() {
+        let report = report();
+
+        let result = "p95 < 250ms".parse::<SloTarget>().unwrap().check(&report);
+        assert!(result.passed);
+        assert_eq!(result.measured, Some(200.0));
+        assert_eq!(result.headroom_percent, Some(20.0));
+
+        let result = "p99 < 250ms".parse::<SloTarget>().unwrap().check(&report);
+        assert!(!result.passed);
+        assert_eq!(result.headroom_percent, Some(-60.0));
+
+        let result = "error_rate < 1%"
+            .parse::<SloTarget>()
+            .unwrap()
+            .check(&report);
+        assert!(result.passed);
+        assert_eq!(result.measured, Some(0.5));
+
+        let result = "rps > 100".parse::<SloTarget>().unwrap().check(&report);
+        assert!(result.passed);
+        assert_eq!(result.headroom_percent, Some(20.0));
+
+        // Neither a configured percentile nor a histogram to read it from
+        let result = "p75 < 1s".parse::<SloTarget>().unwrap().check(&report);
+        assert!(!result.passed);
+        assert_eq!(result.measured, None);
+    }
+
+    #[test]
+    fn test_slo_serialization() {
+        let slo: SloTarget = "p99.9 < 1s".parse().unwrap();
+        let json = serde_json::to_value(slo).unwrap();
+        assert_eq!(json["metric"], "p99.9");
+        assert_eq!(json["comparison"], "<");
+        assert_eq!(json["target"], 1000.0);
+        let deserialized: SloTarget = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, slo);
+    }
+}
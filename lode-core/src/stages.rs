@@ -0,0 +1,613 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tracing::{info, instrument, warn};
+
+use crate::config::{parse_duration, ConfigError};
+use crate::scenario::extract::json_path;
+
+/// Default number of identical consecutive readings for a condition to count as stable
+pub const DEFAULT_STABLE_POLLS: u32 = 3;
+
+/// Default delay between two polls of a wait condition
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default time after which a wait stage gives up and moves on
+pub const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// A stage of a staged load profile, similar to k6 stages
+///
+/// Stages are written as `ramp:<duration>:<target>[:<rate>]`, `hold:<duration>:<concurrency>[:<rate>]`,
+/// `burst:<on>:<off>:<cycles>:<concurrency>[:<rate>]`, `wait(<options>):<url>` or `signal`, see
+/// [`WaitCondition`] for the wait options. For example
+/// `ramp:1m:50`, `hold:5m:50`, `ramp:30s:0` ramps up to 50 concurrent requests over a minute,
+/// holds for five minutes and ramps back down.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Stage {
+    /// Linearly move from the current concurrency (and rate) to the target over a duration
+    Ramp {
+        duration: Duration,
+        target: usize,
+        /// Requests per second reached at the end of the ramp
+        #[serde(default)]
+        rate: Option<f64>,
+    },
+
+    /// Run at a fixed concurrency for a duration
+    Hold {
+        duration: Duration,
+        concurrency: usize,
+        /// Requests per second for the stage, overriding the run-level rate
+        #[serde(default)]
+        rate: Option<f64>,
+    },
+
+    /// Alternate between bursts at a fixed concurrency and idle periods, a square wave
+    ///
+    /// Useful to see how fast an autoscaler reacts and how queues drain. Requests in flight when a
+    /// burst ends still complete; no new request starts until the next burst.
+    Burst {
+        /// Length of each burst
+        on: Duration,
+        /// Length of the idle period after each burst
+        off: Duration,
+        /// Number of bursts, each followed by an idle period
+        cycles: u32,
+        concurrency: usize,
+        /// Requests per second during the bursts, overriding the run-level rate
+        #[serde(default)]
+        rate: Option<f64>,
+    },
+
+    /// Keep the current load until an external condition is met
+    Wait(WaitCondition),
+
+    /// Keep the current load until a [`StageTrigger`](crate::trigger::StageTrigger) moves the run
+    /// on, e.g. a touched file or a call to a control endpoint
+    Signal,
+}
+
+impl Stage {
+    /// Highest concurrency the stage can request
+    pub fn max_concurrency(&self) -> usize {
+        match self {
+            Stage::Ramp { target, .. } => *target,
+            Stage::Hold { concurrency, .. } | Stage::Burst { concurrency, .. } => *concurrency,
+            Stage::Wait(_) | Stage::Signal => 0,
+        }
+    }
+
+    /// Rate the stage sets, if any
+    pub fn rate(&self) -> Option<f64> {
+        match self {
+            Stage::Ramp { rate, .. } | Stage::Hold { rate, .. } | Stage::Burst { rate, .. } => {
+                *rate
+            }
+            Stage::Wait(_) | Stage::Signal => None,
+        }
+    }
+}
+
+/// Parse `<duration>:<concurrency>[:<rate>]`
+fn parse_load_stage(spec: &str) -> Result<(Duration, usize, Option<f64>), String> {
+    let mut parts = spec.split(':');
+    let (Some(duration), Some(concurrency)) = (parts.next(), parts.next()) else {
+        return Err("expected <duration>:<concurrency>[:<rate>]".to_string());
+    };
+    let duration = parse_duration(duration).map_err(|e| e.to_string())?;
+    let concurrency = concurrency
+        .trim()
+        .parse()
+        .map_err(|_| "concurrency must be a number".to_string())?;
+    let rate = match parts.next() {
+        Some(rate) => Some(
+            rate.trim()
+                .trim_end_matches("/s")
+                .parse::<f64>()
+                .ok()
+                .filter(|rate| rate.is_finite() && *rate > 0.0)
+                .ok_or_else(|| "rate must be a positive number".to_string())?,
+        ),
+        None => None,
+    };
+    if parts.next().is_some() {
+        return Err("expected <duration>:<concurrency>[:<rate>]".to_string());
+    }
+    Ok((duration, concurrency, rate))
+}
+
+/// External condition polled by a wait stage
+///
+/// The condition polls `url` and reads a number from the response: the value at `value_path`
+/// (dot-separated, e.g. `data.replicas`), otherwise the sum of a Prometheus query result,
+/// otherwise the whole body. The condition is met once `stable_polls` consecutive readings are
+/// identical, e.g. once the target's replica count stops changing.
+///
+/// Options are written as `wait(stable=3,interval=5s,timeout=10m,path=data.replicas):<url>`;
+/// all of them are optional.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WaitCondition {
+    /// URL polled for the value, e.g. a Prometheus `/api/v1/query` URL
+    pub url: String,
+
+    /// Optional dot-separated path of the value in a JSON response
+    pub value_path: Option<String>,
+
+    /// Number of identical consecutive readings required
+    pub stable_polls: u32,
+
+    /// Delay between two polls
+    pub poll_interval: Duration,
+
+    /// Time after which the stage gives up and moves on
+    pub timeout: Duration,
+}
+
+/// How a wait stage ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum WaitOutcome {
+    /// The polled value stabilized
+    Met { value: f64, waited: Duration },
+    /// The timeout elapsed before the value stabilized
+    TimedOut { waited: Duration },
+}
+
+impl WaitCondition {
+    /// Wait condition on the given URL with default options
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            value_path: None,
+            stable_polls: DEFAULT_STABLE_POLLS,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            timeout: DEFAULT_WAIT_TIMEOUT,
+        }
+    }
+
+    /// Poll until the condition is met or the timeout elapses
+    #[instrument(skip(self, client), fields(url = %self.url))]
+    pub async fn wait(&self, client: &Client) -> WaitOutcome {
+        let started = Instant::now();
+        let mut last: Option<f64> = None;
+        let mut identical = 0;
+
+        loop {
+            match self.poll(client).await {
+                Ok(value) => {
+                    if last == Some(value) {
+                        identical += 1;
+                    } else {
+                        identical = 1;
+                        last = Some(value);
+                    }
+                    info!(
+                        "Wait condition reading: {} ({}/{} stable)",
+                        value, identical, self.stable_polls
+                    );
+                    if identical >= self.stable_polls {
+                        return WaitOutcome::Met {
+                            value,
+                            waited: started.elapsed(),
+                        };
+                    }
+                }
+                Err(e) => {
+                    warn!("Wait condition poll failed: {}", e);
+                    identical = 0;
+                    last = None;
+                }
+            }
+
+            if started.elapsed() + self.poll_interval > self.timeout {
+                warn!(
+                    "Wait condition not met after {:?}, moving on",
+                    started.elapsed()
+                );
+                return WaitOutcome::TimedOut {
+                    waited: started.elapsed(),
+                };
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    async fn poll(&self, client: &Client) -> Result<f64, String> {
+        let response = client
+            .get(&self.url)
+            .timeout(self.poll_interval.max(Duration::from_secs(1)))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status().as_u16()));
+        }
+        let body = response.text().await.map_err(|e| e.to_string())?;
+        extract_value(&body, self.value_path.as_deref())
+    }
+}
+
+/// Read a number from a response body
+fn extract_value(body: &str, path: Option<&str>) -> Result<f64, String> {
+    let json: Value = match serde_json::from_str(body) {
+        Ok(json) => json,
+        Err(_) => return as_number(&Value::from(body.trim())),
+    };
+
+    if let Some(path) = path {
+        let value = json_path(&json, path).ok_or_else(|| format!("No value at path '{}'", path))?;
+        return as_number(value);
+    }
+
+    // Prometheus instant query: sum the vector, or read the scalar
+    if let Some(data) = json.get("data") {
+        match (
+            data.get("resultType").and_then(Value::as_str),
+            data.get("result"),
+        ) {
+            (Some("vector"), Some(Value::Array(series))) => {
+                return series
+                    .iter()
+                    .map(|s| {
+                        as_number(
+                            s.get("value")
+                                .and_then(|v| v.get(1))
+                                .unwrap_or(&Value::Null),
+                        )
+                    })
+                    .sum();
+            }
+            (Some("scalar"), Some(result)) => {
+                return as_number(result.get(1).unwrap_or(&Value::Null));
+            }
+            _ => {}
+        }
+    }
+
+    as_number(&json)
+}
+
+fn as_number(value: &Value) -> Result<f64, String> {
+    match value {
+        Value::Number(n) => n.as_f64().ok_or_else(|| format!("{} is not a number", n)),
+        Value::String(s) => s
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{}' is not a number", s)),
+        other => Err(format!("{} is not a number", other)),
+    }
+}
+
+impl FromStr for Stage {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| ConfigError::InvalidStage(format!("{}: {}", s, reason));
+        let s = s.trim();
+
+        if let Some(rest) = s.strip_prefix("ramp:") {
+            let (duration, target, rate) = parse_load_stage(rest).map_err(|e| invalid(&e))?;
+            return Ok(Stage::Ramp {
+                duration,
+                target,
+                rate,
+            });
+        }
+
+        if let Some(rest) = s.strip_prefix("hold:") {
+            let (duration, concurrency, rate) = parse_load_stage(rest).map_err(|e| invalid(&e))?;
+            if concurrency == 0 {
+                return Err(invalid("concurrency must be greater than 0"));
+            }
+            return Ok(Stage::Hold {
+                duration,
+                concurrency,
+                rate,
+            });
+        }
+
+        if let Some(rest) = s.strip_prefix("burst:") {
+            let mut parts = rest.splitn(4, ':');
+            let (Some(on), Some(off), Some(cycles), Some(load)) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                return Err(invalid(
+                    "expected burst:<on>:<off>:<cycles>:<concurrency>[:<rate>]",
+                ));
+            };
+            let on = parse_duration(on)?;
+            let off = parse_duration(off)?;
+            let cycles = cycles
+                .trim()
+                .parse()
+                .ok()
+                .filter(|cycles| *cycles > 0)
+                .ok_or_else(|| invalid("cycles must be a positive number"))?;
+            // The burst length stands in for the duration of a load stage
+            let (_, concurrency, rate) =
+                parse_load_stage(&format!("0s:{}", load)).map_err(|e| invalid(&e))?;
+            if concurrency == 0 {
+                return Err(invalid("concurrency must be greater than 0"));
+            }
+            return Ok(Stage::Burst {
+                on,
+                off,
+                cycles,
+                concurrency,
+                rate,
+            });
+        }
+
+        if s == "signal" {
+            return Ok(Stage::Signal);
+        }
+
+        if let Some(rest) = s.strip_prefix("wait") {
+            let (options, url) = match rest.strip_prefix('(') {
+                Some(rest) => rest
+                    .split_once("):")
+                    .ok_or_else(|| invalid("expected wait(<options>):<url>"))?,
+                None => (
+                    "",
+                    rest.strip_prefix(':')
+                        .ok_or_else(|| invalid("expected wait:<url>"))?,
+                ),
+            };
+
+            if let Err(e) = url::Url::parse(url) {
+                return Err(invalid(&format!("invalid URL: {}", e)));
+            }
+
+            let mut condition = WaitCondition::new(url.to_string());
+            for option in options.split(',').filter(|o| !o.trim().is_empty()) {
+                let (key, value) = option
+                    .split_once('=')
+                    .ok_or_else(|| invalid("wait options must be key=value"))?;
+                match key.trim() {
+                    "stable" => {
+                        condition.stable_polls = value
+                            .trim()
+                            .parse()
+                            .ok()
+                            .filter(|polls| *polls > 0)
+                            .ok_or_else(|| invalid("stable must be a positive number"))?
+                    }
+                    "interval" => condition.poll_interval = parse_duration(value)?,
+                    "timeout" => condition.timeout = parse_duration(value)?,
+                    "path" => condition.value_path = Some(value.trim().to_string()),
+                    other => return Err(invalid(&format!("unknown wait option '{}'", other))),
+                }
+            }
+            return Ok(Stage::Wait(condition));
+        }
+
+        Err(invalid(
+            "stage must start with `ramp:`, `hold:`, `burst:` or `wait`, or be `signal`",
+        ))
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Ramp {
+                duration,
+                target,
+                rate,
+            } => {
+                write!(f, "ramp:{}s:{}", duration.as_secs_f64(), target)?;
+                if let Some(rate) = rate {
+                    write!(f, ":{}", rate)?;
+                }
+                Ok(())
+            }
+            Stage::Hold {
+                duration,
+                concurrency,
+                rate,
+            } => {
+                write!(f, "hold:{}s:{}", duration.as_secs_f64(), concurrency)?;
+                if let Some(rate) = rate {
+                    write!(f, ":{}", rate)?;
+                }
+                Ok(())
+            }
+            Stage::Burst {
+                on,
+                off,
+                cycles,
+                concurrency,
+                rate,
+            } => {
+                write!(
+                    f,
+                    "burst:{}s:{}s:{}:{}",
+                    on.as_secs_f64(),
+                    off.as_secs_f64(),
+                    cycles,
+                    concurrency
+                )?;
+                if let Some(rate) = rate {
+                    write!(f, ":{}", rate)?;
+                }
+                Ok(())
+            }
+            Stage::Wait(condition) => {
+                write!(
+                    f,
+                    "wait(stable={},interval={}s,timeout={}s",
+                    condition.stable_polls,
+                    condition.poll_interval.as_secs_f64(),
+                    condition.timeout.as_secs_f64()
+                )?;
+                if let Some(path) = &condition.value_path {
+                    write!(f, ",path={}", path)?;
+                }
+                write!(f, "):{}", condition.url)
+            }
+            Stage::Signal => write!(f, "signal"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hold_stage() {
+        assert_eq!(
+            "hold:60s:50".parse::<Stage>().unwrap(),
+            Stage::Hold {
+                duration: Duration::from_secs(60),
+                concurrency: 50,
+                rate: None,
+            }
+        );
+        assert_eq!(
+            "hold:60s:50:200/s".parse::<Stage>().unwrap(),
+            Stage::Hold {
+                duration: Duration::from_secs(60),
+                concurrency: 50,
+                rate: Some(200.0),
+            }
+        );
+        assert!("hold:60s".parse::<Stage>().is_err());
+        assert!("hold:60s:5:0".parse::<Stage>().is_err());
+        assert!("hold:60s:5:10:extra".parse::<Stage>().is_err());
+        assert!("hold:60s:0".parse::<Stage>().is_err());
+        assert!("hold:later:5".parse::<Stage>().is_err());
+    }
+
+    #[test]
+    fn test_parse_ramp_stage() {
+        assert_eq!(
+            "ramp:1m:50".parse::<Stage>().unwrap(),
+            Stage::Ramp {
+                duration: Duration::from_secs(60),
+                target: 50,
+                rate: None,
+            }
+        );
+        assert_eq!(
+            "ramp:30s:0:10".parse::<Stage>().unwrap(),
+            Stage::Ramp {
+                duration: Duration::from_secs(30),
+                target: 0,
+                rate: Some(10.0),
+            }
+        );
+        assert!("ramp:soon:5".parse::<Stage>().is_err());
+        assert!("ramp:1m:many".parse::<Stage>().is_err());
+    }
+
+    #[test]
+    fn test_parse_burst_stage() {
+        let stage: Stage = "burst:30s:1m:5:50:200/s".parse().unwrap();
+        assert_eq!(
+            stage,
+            Stage::Burst {
+                on: Duration::from_secs(30),
+                off: Duration::from_secs(60),
+                cycles: 5,
+                concurrency: 50,
+                rate: Some(200.0),
+            }
+        );
+        assert_eq!(stage.to_string(), "burst:30s:60s:5:50:200");
+        assert_eq!(stage.to_string().parse::<Stage>().unwrap(), stage);
+        assert!("burst:30s:1m:5".parse::<Stage>().is_err());
+        assert!("burst:30s:1m:0:50".parse::<Stage>().is_err());
+        assert!("burst:30s:1m:5:0".parse::<Stage>().is_err());
+        assert!("burst:30s:1m:5:50:0".parse::<Stage>().is_err());
+    }
+
+    #[test]
+    fn test_parse_signal_stage() {
+        assert_eq!(" signal ".parse::<Stage>().unwrap(), Stage::Signal);
+        assert_eq!(Stage::Signal.to_string(), "signal");
+        assert!("signal:5m".parse::<Stage>().is_err());
+    }
+
+    #[test]
+    fn test_parse_wait_stage() {
+        let stage: Stage = "wait:http://prometheus:9090/api/v1/query?query=up"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            stage,
+            Stage::Wait(WaitCondition::new(
+                "http://prometheus:9090/api/v1/query?query=up".to_string()
+            ))
+        );
+
+        let stage: Stage =
+            "wait(stable=5,interval=1s,timeout=2m,path=status.replicas):http://k8s/deployments/api"
+                .parse()
+                .unwrap();
+        match stage {
+            Stage::Wait(condition) => {
+                assert_eq!(condition.url, "http://k8s/deployments/api");
+                assert_eq!(condition.stable_polls, 5);
+                assert_eq!(condition.poll_interval, Duration::from_secs(1));
+                assert_eq!(condition.timeout, Duration::from_secs(120));
+                assert_eq!(condition.value_path.as_deref(), Some("status.replicas"));
+            }
+            other => panic!("unexpected stage: {:?}", other),
+        }
+
+        assert!("wait:not-a-url".parse::<Stage>().is_err());
+        assert!("wait(stable=0):http://k8s".parse::<Stage>().is_err());
+        assert!("wait(color=red):http://k8s".parse::<Stage>().is_err());
+        assert!("sleep:5s".parse::<Stage>().is_err());
+    }
+
+    #[test]
+    fn test_stage_display_round_trip() {
+        for spec in [
+            "hold:1.5s:10",
+            "hold:60s:10:25",
+            "ramp:30s:0",
+            "ramp:120s:100:500.5",
+            "wait(stable=2,interval=1s,timeout=30s,path=data.0):http://example.com/replicas",
+        ] {
+            let stage: Stage = spec.parse().unwrap();
+            assert_eq!(stage.to_string(), spec);
+        }
+    }
+
+    #[test]
+    fn test_extract_value() {
+        assert_eq!(extract_value("3", None).unwrap(), 3.0);
+        assert_eq!(extract_value(" 4.5\n", None).unwrap(), 4.5);
+        assert_eq!(
+            extract_value(r#"{"status": {"replicas": 6}}"#, Some("status.replicas")).unwrap(),
+            6.0
+        );
+        assert_eq!(
+            extract_value(r#"{"items": [{"count": "2"}]}"#, Some("items.0.count")).unwrap(),
+            2.0
+        );
+
+        let prometheus = r#"{
+            "status": "success",
+            "data": {
+                "resultType": "vector",
+                "result": [
+                    {"metric": {"pod": "a"}, "value": [1712000000.1, "2"]},
+                    {"metric": {"pod": "b"}, "value": [1712000000.1, "3"]}
+                ]
+            }
+        }"#;
+        assert_eq!(extract_value(prometheus, None).unwrap(), 5.0);
+
+        let scalar = r#"{"status": "success", "data": {"resultType": "scalar", "result": [1712000000.1, "7"]}}"#;
+        assert_eq!(extract_value(scalar, None).unwrap(), 7.0);
+
+        assert!(extract_value("not a number", None).is_err());
+        assert!(extract_value(r#"{"a": 1}"#, Some("b")).is_err());
+    }
+}
@@ -1,11 +1,307 @@
+#[cfg(feature = "otlp")]
+use opentelemetry::trace::TracerProvider;
+#[cfg(feature = "otlp")]
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig, WithHttpConfig};
+#[cfg(feature = "otlp")]
+use opentelemetry_sdk::{metrics::SdkMeterProvider, trace::SdkTracerProvider, Resource};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tracing::subscriber::set_global_default;
-use tracing::Subscriber;
+use tracing::{warn, Subscriber};
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
 use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::{EnvFilter, Registry};
 
+use crate::error::{Error, Result};
+
+/// Standard variable holding the collector endpoint
+pub const OTLP_ENDPOINT_VAR: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// Standard variable holding the transport, `grpc` or `http/protobuf`
+pub const OTLP_PROTOCOL_VAR: &str = "OTEL_EXPORTER_OTLP_PROTOCOL";
+
+/// Transport used to reach an OpenTelemetry collector
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum OtlpProtocol {
+    /// OTLP over gRPC, usually on port 4317
+    #[default]
+    #[serde(rename = "grpc")]
+    Grpc,
+    /// OTLP over HTTP with protobuf payloads, usually on port 4318
+    #[serde(rename = "http/protobuf")]
+    HttpProtobuf,
+}
+
+impl FromStr for OtlpProtocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "grpc" => Ok(OtlpProtocol::Grpc),
+            "http/protobuf" | "http" => Ok(OtlpProtocol::HttpProtobuf),
+            _ => Err(format!(
+                "Unsupported OTLP protocol: {} (expected grpc or http/protobuf)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OtlpProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OtlpProtocol::Grpc => "grpc",
+            OtlpProtocol::HttpProtobuf => "http/protobuf",
+        })
+    }
+}
+
+/// Where to export traces and metrics over OTLP
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct OtlpSettings {
+    /// Base URL of the collector, e.g. `http://localhost:4317`
+    pub endpoint: String,
+    #[serde(default)]
+    pub protocol: OtlpProtocol,
+}
+
+impl OtlpSettings {
+    /// Read [`OTLP_ENDPOINT_VAR`] and [`OTLP_PROTOCOL_VAR`]; `None` when no endpoint is set
+    pub fn from_env() -> Result<Option<Self>> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Option<Self>> {
+        let endpoint = match var(OTLP_ENDPOINT_VAR) {
+            Some(endpoint) if !endpoint.trim().is_empty() => endpoint.trim().to_string(),
+            _ => return Ok(None),
+        };
+        let protocol = match var(OTLP_PROTOCOL_VAR) {
+            Some(protocol) => protocol.parse().map_err(Error::Config)?,
+            None => OtlpProtocol::default(),
+        };
+        Ok(Some(Self { endpoint, protocol }))
+    }
+}
+
+#[cfg(feature = "otlp")]
+impl OtlpSettings {
+    /// Endpoint of one signal; OTLP over HTTP expects a path per signal
+    fn signal_endpoint(&self, signal: &str) -> String {
+        match self.protocol {
+            OtlpProtocol::Grpc => self.endpoint.clone(),
+            OtlpProtocol::HttpProtobuf => {
+                format!("{}/v1/{}", self.endpoint.trim_end_matches('/'), signal)
+            }
+        }
+    }
+
+    fn span_exporter(&self) -> Result<SpanExporter> {
+        let endpoint = self.signal_endpoint("traces");
+        let exporter = match self.protocol {
+            OtlpProtocol::Grpc => SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .build(),
+            OtlpProtocol::HttpProtobuf => SpanExporter::builder()
+                .with_http()
+                .with_endpoint(endpoint)
+                .with_http_client(reqwest::Client::new())
+                .build(),
+        };
+        exporter
+            .map_err(|e| Error::Config(format!("Failed to create the OTLP span exporter: {}", e)))
+    }
+
+    fn metric_exporter(&self) -> Result<MetricExporter> {
+        let endpoint = self.signal_endpoint("metrics");
+        let exporter = match self.protocol {
+            OtlpProtocol::Grpc => MetricExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .build(),
+            OtlpProtocol::HttpProtobuf => MetricExporter::builder()
+                .with_http()
+                .with_endpoint(endpoint)
+                .with_http_client(reqwest::Client::new())
+                .build(),
+        };
+        exporter
+            .map_err(|e| Error::Config(format!("Failed to create the OTLP metric exporter: {}", e)))
+    }
+}
+
+/// Flushes and stops the OTLP exporters when dropped
+///
+/// Dropping blocks until pending data is exported, so drop it outside of async code,
+/// e.g. through `tokio::task::spawn_blocking`.
+#[cfg(feature = "otlp")]
+pub struct OtlpGuard {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+#[cfg(feature = "otlp")]
+impl Drop for OtlpGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            eprintln!("Failed to flush OTLP traces: {}", e);
+        }
+        if let Err(e) = self.meter_provider.shutdown() {
+            eprintln!("Failed to flush OTLP metrics: {}", e);
+        }
+    }
+}
+
+/// Window over which repeats of an error line are counted instead of logged one by one
+pub const ERROR_LOG_WINDOW: Duration = Duration::from_secs(5);
+
+/// Most distinct lines an error log counts the repeats of at once
+pub const ERROR_LOG_MAX_LINES: usize = 1024;
+
+/// Logs an error line once per window and counts its repeats in between
+///
+/// At high error rates every failed request would log the same line, e.g. a refused connection.
+/// The first one is logged right away and the repeats as one line once the window is over, e.g.
+/// `Request failed: connection refused ×1832 in last 5s`. Metrics keep the full counts either
+/// way. Repeats still pending are logged when the log is dropped.
+///
+/// Lines whose window is over are forgotten once per window, their repeats logged, so a log
+/// kept for the whole process only holds the lines of the last windows. Beyond
+/// [`ERROR_LOG_MAX_LINES`] lines within them, new lines are logged without counting repeats.
+#[derive(Debug)]
+pub struct ErrorLog {
+    window: Duration,
+    lines: Mutex<Lines>,
+}
+
+/// Lines counted by an error log
+#[derive(Debug)]
+struct Lines {
+    repeats: HashMap<String, Repeats>,
+    /// When lines whose window is over were last forgotten
+    swept: Instant,
+}
+
+/// Repeats of a line since it was last logged
+#[derive(Debug)]
+struct Repeats {
+    since: Instant,
+    count: u64,
+}
+
+impl ErrorLog {
+    /// Create a log that counts the repeats of a line for `window` before logging them
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            lines: Mutex::new(Lines {
+                repeats: HashMap::new(),
+                swept: Instant::now(),
+            }),
+        }
+    }
+
+    /// Log a warning, or count it when the same line was logged within the window
+    pub fn warn(&self, line: impl Into<String>) {
+        for line in self.record(line.into(), Instant::now()) {
+            warn!("{}", line);
+        }
+    }
+
+    /// Log the repeats counted so far
+    pub fn flush(&self) {
+        for line in self.drain(Instant::now()) {
+            warn!("{}", line);
+        }
+    }
+
+    /// Lines to log for an occurrence of `line` at `now`: the repeats of the other lines
+    /// forgotten, if their window is over, then the line or the summary of its repeats, if any
+    fn record(&self, line: String, now: Instant) -> Vec<String> {
+        let mut lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        let mut logged = Vec::new();
+        if now.saturating_duration_since(lines.swept) >= self.window {
+            lines.swept = now;
+            lines.repeats.retain(|other, repeats| {
+                let elapsed = now.saturating_duration_since(repeats.since);
+                if *other == line || elapsed < self.window {
+                    return true;
+                }
+                if repeats.count > 0 {
+                    logged.push(summary(other, repeats.count, elapsed));
+                }
+                false
+            });
+            logged.sort();
+        }
+
+        let full = lines.repeats.len() >= ERROR_LOG_MAX_LINES;
+        match lines.repeats.get_mut(&line) {
+            Some(repeats) => {
+                let elapsed = now.saturating_duration_since(repeats.since);
+                if elapsed < self.window {
+                    repeats.count += 1;
+                } else {
+                    let count = std::mem::take(&mut repeats.count);
+                    repeats.since = now;
+                    logged.push(match count {
+                        0 => line,
+                        count => summary(&line, count + 1, elapsed),
+                    });
+                }
+            }
+            None if full => logged.push(line),
+            None => {
+                lines.repeats.insert(
+                    line.clone(),
+                    Repeats {
+                        since: now,
+                        count: 0,
+                    },
+                );
+                logged.push(line);
+            }
+        }
+        logged
+    }
+
+    /// Lines summarizing the repeats not logged yet, forgetting every line
+    fn drain(&self, now: Instant) -> Vec<String> {
+        let mut lines = self.lines.lock().unwrap_or_else(|e| e.into_inner());
+        let mut summaries: Vec<String> = lines
+            .repeats
+            .drain()
+            .filter(|(_, repeats)| repeats.count > 0)
+            .map(|(line, repeats)| {
+                summary(
+                    &line,
+                    repeats.count,
+                    now.saturating_duration_since(repeats.since),
+                )
+            })
+            .collect();
+        summaries.sort();
+        summaries
+    }
+}
+
+impl Drop for ErrorLog {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+fn summary(line: &str, count: u64, elapsed: Duration) -> String {
+    format!("{} ×{} in last {:.0?}", line, count, elapsed)
+}
+
 pub fn get_subscriber<Sink>(
     name: String,
     env_filter: String,
@@ -14,13 +310,57 @@ pub fn get_subscriber<Sink>(
 where
     Sink: for<'a> MakeWriter<'a> + Send + Sync + 'static,
 {
-    let env_filter =
-        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(env_filter));
     let formatting_layer = BunyanFormattingLayer::new(name, sink);
     Registry::default()
-        .with(env_filter)
+        .with(default_env_filter(env_filter))
+        .with(JsonStorageLayer)
+        .with(formatting_layer)
+}
+
+/// Like [`get_subscriber`], and also export spans to an OpenTelemetry collector
+///
+/// Also installs the global meter provider, which exports the instruments created through
+/// [`crate::metrics::exporters::otlp::global_meter`]. Must be called within a Tokio runtime.
+#[cfg(feature = "otlp")]
+pub fn get_otlp_subscriber<Sink>(
+    name: String,
+    env_filter: String,
+    sink: Sink,
+    otlp: &OtlpSettings,
+) -> Result<(impl Subscriber + Send + Sync, OtlpGuard)>
+where
+    Sink: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let resource = Resource::builder().with_service_name(name.clone()).build();
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_batch_exporter(otlp.span_exporter()?)
+        .with_resource(resource.clone())
+        .build();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(otlp.metric_exporter()?)
+        .with_resource(resource)
+        .build();
+    opentelemetry::global::set_meter_provider(meter_provider.clone());
+
+    let tracer = tracer_provider.tracer(name.clone());
+    let formatting_layer = BunyanFormattingLayer::new(name, sink);
+    let subscriber = Registry::default()
+        .with(default_env_filter(env_filter))
         .with(JsonStorageLayer)
         .with(formatting_layer)
+        .with(tracing_opentelemetry::layer().with_tracer(tracer));
+    Ok((
+        subscriber,
+        OtlpGuard {
+            tracer_provider,
+            meter_provider,
+        },
+    ))
+}
+
+/// `RUST_LOG` when set, the given directives otherwise
+fn default_env_filter(env_filter: String) -> EnvFilter {
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(env_filter))
 }
 
 pub fn init_subscriber(subscriber: impl Subscriber + Send + Sync) {
@@ -35,3 +375,131 @@ pub fn get_stdout_subscriber(name: String, env_filter: String) -> impl Subscribe
 pub fn get_stderr_subscriber(name: String, env_filter: String) -> impl Subscriber + Send + Sync {
     get_subscriber(name, env_filter, std::io::stderr)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(vars: &[(&str, &str)]) -> Result<Option<OtlpSettings>> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        OtlpSettings::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_otlp_settings_from_vars() {
+        assert_eq!(settings(&[]).unwrap(), None);
+        assert_eq!(settings(&[(OTLP_ENDPOINT_VAR, " ")]).unwrap(), None);
+
+        let grpc = settings(&[(OTLP_ENDPOINT_VAR, "http://collector:4317")]).unwrap();
+        assert_eq!(
+            grpc,
+            Some(OtlpSettings {
+                endpoint: "http://collector:4317".into(),
+                protocol: OtlpProtocol::Grpc,
+            })
+        );
+
+        let http = settings(&[
+            (OTLP_ENDPOINT_VAR, "http://collector:4318"),
+            (OTLP_PROTOCOL_VAR, "http/protobuf"),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(http.protocol, OtlpProtocol::HttpProtobuf);
+
+        let invalid = settings(&[
+            (OTLP_ENDPOINT_VAR, "http://collector:4318"),
+            (OTLP_PROTOCOL_VAR, "http/json"),
+        ]);
+        assert!(matches!(invalid, Err(Error::Config(_))));
+    }
+
+    #[cfg(feature = "otlp")]
+    #[test]
+    fn test_signal_endpoints() {
+        let mut otlp = OtlpSettings {
+            endpoint: "http://collector:4318/".into(),
+            protocol: OtlpProtocol::HttpProtobuf,
+        };
+        assert_eq!(
+            otlp.signal_endpoint("traces"),
+            "http://collector:4318/v1/traces"
+        );
+
+        otlp.protocol = OtlpProtocol::Grpc;
+        assert_eq!(otlp.signal_endpoint("metrics"), "http://collector:4318/");
+    }
+
+    #[test]
+    fn test_error_log_counts_repeats() {
+        let log = ErrorLog::new(Duration::from_secs(5));
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+        let refused = || "Request failed: connection refused".to_string();
+
+        assert_eq!(log.record(refused(), at(0)), [refused()]);
+        assert!(log.record(refused(), at(1)).is_empty());
+        assert!(log.record(refused(), at(3)).is_empty());
+        assert_eq!(
+            log.record("Timeout: No response within 1s".to_string(), at(3)),
+            ["Timeout: No response within 1s"]
+        );
+        assert_eq!(
+            log.record(refused(), at(5)),
+            ["Request failed: connection refused ×3 in last 5s"]
+        );
+
+        // A line without repeats in the last window is logged as is
+        assert_eq!(log.record(refused(), at(11)), [refused()]);
+        assert!(log.record(refused(), at(12)).is_empty());
+        assert_eq!(
+            log.drain(at(14)),
+            vec!["Request failed: connection refused ×1 in last 3s".to_string()]
+        );
+        assert!(log.drain(at(15)).is_empty());
+    }
+
+    #[test]
+    fn test_error_log_forgets_lines_of_past_windows() {
+        let log = ErrorLog::new(Duration::from_secs(5));
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+        let line = |n: usize| format!("Request failed: error {}", n);
+
+        for n in 0..ERROR_LOG_MAX_LINES {
+            assert_eq!(log.record(line(n), at(0)), [line(n)]);
+        }
+        assert!(log.record(line(0), at(1)).is_empty());
+        // Beyond the most lines, a new line is logged each time
+        let extra = line(ERROR_LOG_MAX_LINES);
+        assert_eq!(log.record(extra.clone(), at(1)), [extra.as_str()]);
+        assert_eq!(log.record(extra.clone(), at(2)), [extra.as_str()]);
+
+        // Once their window is over, the lines are forgotten and their repeats logged
+        assert_eq!(
+            log.record(extra.clone(), at(6)),
+            ["Request failed: error 0 ×1 in last 6s".to_string(), extra]
+        );
+        assert_eq!(log.lines.lock().unwrap().repeats.len(), 1);
+    }
+
+    #[cfg(feature = "otlp")]
+    #[tokio::test]
+    async fn test_otlp_subscriber_builds_without_collector() {
+        for protocol in [OtlpProtocol::Grpc, OtlpProtocol::HttpProtobuf] {
+            let otlp = OtlpSettings {
+                endpoint: "http://127.0.0.1:1".into(),
+                protocol,
+            };
+            let (_subscriber, guard) =
+                get_otlp_subscriber("lode-test".into(), "info".into(), std::io::sink, &otlp)
+                    .unwrap();
+            tokio::task::spawn_blocking(move || drop(guard))
+                .await
+                .unwrap();
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::config::{parse_duration, ConfigError};
+use crate::retry::random_fraction;
+
+/// Pause of a virtual user between its requests, as a person reading a page would take
+///
+/// Written as a fixed duration (`2s`), a uniform range (`1s..3s`) or the mean of an exponential
+/// distribution (`exp:2s`), whose occasional long pauses resemble people better than a fixed
+/// delay does.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ThinkTime {
+    /// Always the same pause
+    Fixed(Duration),
+    /// Any pause between `min` and `max`, all equally likely
+    Uniform { min: Duration, max: Duration },
+    /// Exponentially distributed pauses averaging `mean`
+    Exponential { mean: Duration },
+}
+
+impl ThinkTime {
+    /// Pause before the next request of a virtual user
+    pub fn sample(&self) -> Duration {
+        match *self {
+            ThinkTime::Fixed(pause) => pause,
+            ThinkTime::Uniform { min, max } => min + (max - min).mul_f64(random_fraction()),
+            ThinkTime::Exponential { mean } => mean.mul_f64(-(1.0 - random_fraction()).ln()),
+        }
+    }
+
+    /// Check that a range does not end before it starts
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        match self {
+            ThinkTime::Uniform { min, max } if min > max => Err(ConfigError::InvalidThinkTime(
+                format!("Range {} ends before it starts", self),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for ThinkTime {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let duration = |part: &str| {
+            parse_duration(part).map_err(|_| {
+                ConfigError::InvalidThinkTime(format!("{} (expected e.g. 2s, 1s..3s or exp:2s)", s))
+            })
+        };
+        let think_time = if let Some(mean) = s.strip_prefix("exp:") {
+            ThinkTime::Exponential {
+                mean: duration(mean)?,
+            }
+        } else if let Some((min, max)) = s.split_once("..") {
+            ThinkTime::Uniform {
+                min: duration(min)?,
+                max: duration(max)?,
+            }
+        } else {
+            ThinkTime::Fixed(duration(s)?)
+        };
+        think_time.validate()?;
+        Ok(think_time)
+    }
+}
+
+impl TryFrom<String> for ThinkTime {
+    type Error = ConfigError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ThinkTime> for String {
+    fn from(think_time: ThinkTime) -> Self {
+        think_time.to_string()
+    }
+}
+
+impl fmt::Display for ThinkTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| format!("{}ms", duration.as_secs_f64() * 1000.0);
+        match *self {
+            ThinkTime::Fixed(pause) => write!(f, "{}", ms(pause)),
+            ThinkTime::Uniform { min, max } => write!(f, "{}..{}", ms(min), ms(max)),
+            ThinkTime::Exponential { mean } => write!(f, "exp:{}", ms(mean)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_think_time_from_str() {
+        assert_eq!(
+            "2s".parse::<ThinkTime>().unwrap(),
+            ThinkTime::Fixed(Duration::from_secs(2))
+        );
+        assert_eq!(
+            " 500ms..1.5s ".parse::<ThinkTime>().unwrap(),
+            ThinkTime::Uniform {
+                min: Duration::from_millis(500),
+                max: Duration::from_millis(1500)
+            }
+        );
+        assert_eq!(
+            "exp:2s".parse::<ThinkTime>().unwrap(),
+            ThinkTime::Exponential {
+                mean: Duration::from_secs(2)
+            }
+        );
+        assert!("3s..1s".parse::<ThinkTime>().is_err());
+        assert!("exp:".parse::<ThinkTime>().is_err());
+        assert!("soon".parse::<ThinkTime>().is_err());
+
+        let think_time: ThinkTime = serde_json::from_str("\"1s..2s\"").unwrap();
+        assert_eq!(
+            serde_json::to_string(&think_time).unwrap(),
+            "\"1000ms..2000ms\""
+        );
+    }
+
+    #[test]
+    fn test_think_time_samples() {
+        let range = ThinkTime::Uniform {
+            min: Duration::from_millis(100),
+            max: Duration::from_millis(200),
+        };
+        let exponential = ThinkTime::Exponential {
+            mean: Duration::from_millis(100),
+        };
+        let mut total = Duration::ZERO;
+        for _ in 0..10_000 {
+            let pause = range.sample();
+            assert!((Duration::from_millis(100)..=Duration::from_millis(200)).contains(&pause));
+            total += exponential.sample();
+        }
+        // The mean of 10,000 samples is within a few percent of the distribution's
+        let mean = total / 10_000;
+        assert!(
+            (Duration::from_millis(90)..Duration::from_millis(110)).contains(&mean),
+            "mean {:?}",
+            mean
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::config::ConfigError;
+use crate::retry::random_fraction;
+
+/// Requests traced at most by default
+pub const DEFAULT_TRACE_ENTRIES: usize = 1000;
+
+/// Bytes of a request or response body kept in a trace by default
+pub const DEFAULT_TRACE_BODY_SIZE: usize = 4096;
+
+/// Sampling of the requests of a run traced with their headers and bodies, e.g. for a HAR file
+/// to inspect in browser devtools or to share with the owner of the target
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceSampling {
+    /// Share of the requests traced, in percent
+    pub percent: f64,
+    /// Requests traced at most, the first ones sampled
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+    /// Bytes of each request and response body kept, the rest is cut
+    #[serde(default = "default_max_body_size")]
+    pub max_body_size: usize,
+    /// Keep the values of headers carrying credentials, such as `Authorization` and `Cookie`,
+    /// instead of redacting them
+    #[serde(default)]
+    pub keep_secrets: bool,
+}
+
+fn default_max_entries() -> usize {
+    DEFAULT_TRACE_ENTRIES
+}
+
+fn default_max_body_size() -> usize {
+    DEFAULT_TRACE_BODY_SIZE
+}
+
+impl TraceSampling {
+    /// Trace `percent` of the requests, up to the default number of entries and body size
+    pub fn new(percent: f64) -> Self {
+        Self {
+            percent,
+            max_entries: DEFAULT_TRACE_ENTRIES,
+            max_body_size: DEFAULT_TRACE_BODY_SIZE,
+            keep_secrets: false,
+        }
+    }
+
+    /// Check that the share is a percentage above 0 and that some entries are kept
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(self.percent > 0.0 && self.percent <= 100.0) {
+            return Err(ConfigError::InvalidTrace(format!(
+                "Share of the requests traced must be above 0% and at most 100%, got {}%",
+                self.percent
+            )));
+        }
+        if self.max_entries == 0 {
+            return Err(ConfigError::InvalidTrace(
+                "At least one request must be traced".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Picks the requests of a run to trace, shared by its virtual users
+#[derive(Debug)]
+pub struct TraceSampler {
+    sampling: TraceSampling,
+    taken: AtomicUsize,
+}
+
+impl TraceSampler {
+    pub fn new(sampling: TraceSampling) -> Self {
+        Self {
+            sampling,
+            taken: AtomicUsize::new(0),
+        }
+    }
+
+    /// Whether to trace the next request, counting it towards the max entries when it is
+    pub fn sample(&self) -> bool {
+        random_fraction() * 100.0 < self.sampling.percent
+            && self
+                .taken
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |taken| {
+                    (taken < self.sampling.max_entries).then_some(taken + 1)
+                })
+                .is_ok()
+    }
+
+    /// Bytes of each body kept in an entry
+    pub fn max_body_size(&self) -> usize {
+        self.sampling.max_body_size
+    }
+
+    /// Whether the values of headers carrying credentials are kept in the entries
+    pub fn keep_secrets(&self) -> bool {
+        self.sampling.keep_secrets
+    }
+}
+
+/// Value written in place of a header carrying credentials
+pub const REDACTED: &str = "[redacted]";
+
+/// Headers whose values are credentials, compared without case
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-auth-token",
+    "x-access-token",
+];
+
+/// Whether a header carries credentials: a known one or an API key such as `X-Api-Key`
+pub fn is_secret_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SECRET_HEADERS.contains(&name.as_str())
+        || ["api-key", "apikey", "api_key"]
+            .iter()
+            .any(|key| name.contains(key))
+}
+
+/// HTTP version as written in HAR
+pub fn http_version_name(version: reqwest::Version) -> &'static str {
+    match version {
+        reqwest::Version::HTTP_09 => "HTTP/0.9",
+        reqwest::Version::HTTP_10 => "HTTP/1.0",
+        reqwest::Version::HTTP_2 => "HTTP/2.0",
+        reqwest::Version::HTTP_3 => "HTTP/3.0",
+        _ => "HTTP/1.1",
+    }
+}
+
+/// A request traced with its response, as actually sent and received
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    pub started_at: DateTime<Utc>,
+    /// Time from sending the request to the end of its response
+    pub time: Duration,
+    pub method: String,
+    pub url: String,
+    /// Headers set by the run, without those the HTTP client adds on its own such as `Host`
+    pub headers: Vec<(String, String)>,
+    pub body: Option<TracedBody>,
+    /// Response, missing when the request got none
+    pub response: Option<TracedResponse>,
+    /// Why the request failed, with or without a response
+    pub error: Option<String>,
+    pub dns: Option<Duration>,
+    /// TCP connect, including the TLS handshake of an HTTPS connection
+    pub connect: Option<Duration>,
+    /// TCP connect and TLS handshake of an HTTPS connection
+    pub tls: Option<Duration>,
+    /// Time to the first byte of the response after the connection was set up
+    pub wait: Option<Duration>,
+    /// Download of the response body
+    pub receive: Option<Duration>,
+}
+
+impl TraceEntry {
+    /// Start tracing a request
+    pub fn new(method: &str, url: &str) -> Self {
+        Self {
+            started_at: Utc::now(),
+            time: Duration::ZERO,
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: None,
+            response: None,
+            error: None,
+            dns: None,
+            connect: None,
+            tls: None,
+            wait: None,
+            receive: None,
+        }
+    }
+
+    /// Replace the values of the request and response headers carrying credentials
+    pub fn redact(&mut self) {
+        let response = self.response.iter_mut().flat_map(|r| r.headers.iter_mut());
+        for (name, value) in self.headers.iter_mut().chain(response) {
+            if is_secret_header(name) {
+                *value = REDACTED.to_string();
+            }
+        }
+    }
+}
+
+/// Status, headers and body of a traced response
+#[derive(Debug, Clone, PartialEq)]
+pub struct TracedResponse {
+    pub status: u16,
+    /// HTTP version as written in HAR, e.g. `HTTP/1.1`
+    pub http_version: String,
+    pub headers: Vec<(String, String)>,
+    /// Body, missing when it was discarded unread or could not be read
+    pub body: Option<TracedBody>,
+    /// Why the body could not be read
+    pub body_error: Option<String>,
+}
+
+/// The start of a traced body
+#[derive(Debug, Clone, PartialEq)]
+pub struct TracedBody {
+    /// Bytes kept, at most the max body size of the sampling
+    pub bytes: Vec<u8>,
+    /// Bytes of the whole body
+    pub size: u64,
+}
+
+impl TracedBody {
+    /// Keep the start of a body of `size` bytes
+    pub fn new(bytes: &[u8], size: u64, max_body_size: usize) -> Self {
+        Self {
+            bytes: bytes[..bytes.len().min(max_body_size)].to_vec(),
+            size,
+        }
+    }
+
+    /// Whether the body was cut at the max body size
+    pub fn truncated(&self) -> bool {
+        (self.bytes.len() as u64) < self.size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampler_stops_at_max_entries() {
+        let sampler = TraceSampler::new(TraceSampling {
+            max_entries: 3,
+            ..TraceSampling::new(100.0)
+        });
+        let sampled = (0..10).filter(|_| sampler.sample()).count();
+        assert_eq!(sampled, 3);
+    }
+
+    #[test]
+    fn test_sampler_share() {
+        let sampler = TraceSampler::new(TraceSampling {
+            max_entries: usize::MAX,
+            ..TraceSampling::new(10.0)
+        });
+        let sampled = (0..10_000).filter(|_| sampler.sample()).count();
+        assert!((700..1300).contains(&sampled), "{}", sampled);
+    }
+
+    #[test]
+    fn test_sampling_validation() {
+        assert!(TraceSampling::new(1.0).validate().is_ok());
+        assert!(TraceSampling::new(100.0).validate().is_ok());
+        assert!(TraceSampling::new(0.0).validate().is_err());
+        assert!(TraceSampling::new(150.0).validate().is_err());
+        let none = TraceSampling {
+            max_entries: 0,
+            ..TraceSampling::new(5.0)
+        };
+        assert!(none.validate().is_err());
+    }
+
+    #[test]
+    fn test_secret_headers_are_redacted() {
+        let mut entry = TraceEntry::new("GET", "https://example.com/");
+        entry.headers = vec![
+            ("Authorization".to_string(), "Bearer abc".to_string()),
+            ("X-Api-Key".to_string(), "key".to_string()),
+            ("Accept".to_string(), "text/plain".to_string()),
+        ];
+        entry.response = Some(TracedResponse {
+            status: 200,
+            http_version: "HTTP/1.1".to_string(),
+            headers: vec![("Set-Cookie".to_string(), "session=1".to_string())],
+            body: None,
+            body_error: None,
+        });
+        entry.redact();
+        let values: Vec<&str> = entry.headers.iter().map(|(_, v)| v.as_str()).collect();
+        assert_eq!(values, [REDACTED, REDACTED, "text/plain"]);
+        assert_eq!(entry.response.unwrap().headers[0].1, REDACTED);
+    }
+
+    #[test]
+    fn test_traced_body_is_cut() {
+        let body = TracedBody::new(b"hello world", 11, 5);
+        assert_eq!(body.bytes, b"hello");
+        assert!(body.truncated());
+        assert!(!TracedBody::new(b"hi", 2, 5).truncated());
+    }
+}
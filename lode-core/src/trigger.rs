@@ -0,0 +1,166 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+
+use crate::error::Result;
+
+/// Path of the control endpoint moving the run to its next stage
+pub const ADVANCE_PATH: &str = "/advance";
+
+/// How often a watched file is checked for a touch
+pub const FILE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Largest control request read before answering
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// Moves a staged run to its next stage from outside the engine, e.g. once a live debugging
+/// session on the target is ready for more load
+///
+/// A trigger ends the current stage early and ends `signal` stages, which hold the load until one
+/// arrives. A trigger sent between two stages ends the next one as soon as it starts. Clones share
+/// the same trigger.
+#[derive(Debug, Clone, Default)]
+pub struct StageTrigger {
+    notify: Arc<Notify>,
+}
+
+impl StageTrigger {
+    /// Create a trigger nothing has fired yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the run to its next stage
+    pub fn advance(&self) {
+        self.notify.notify_one();
+    }
+
+    /// Wait for the next trigger
+    pub(crate) async fn triggered(&self) {
+        self.notify.notified().await;
+    }
+
+    /// Fire whenever the file at `path` is created or touched, until the future is dropped
+    pub async fn watch_file(self, path: PathBuf) {
+        info!("Touch {} to move to the next stage", path.display());
+        let modified = |path: &PathBuf| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        };
+        let mut last: Option<SystemTime> = modified(&path);
+        loop {
+            tokio::time::sleep(FILE_POLL_INTERVAL).await;
+            let current = modified(&path);
+            if current.is_some() && current != last {
+                info!("{} touched, moving to the next stage", path.display());
+                self.advance();
+            }
+            last = current;
+        }
+    }
+
+    /// Fire on every `POST` to [`ADVANCE_PATH`], until the future is dropped
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        info!(
+            "POST to http://{}{} to move to the next stage",
+            listener.local_addr()?,
+            ADVANCE_PATH
+        );
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let trigger = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = trigger.respond(stream).await {
+                    warn!("Failed to answer control request from {}: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    async fn respond(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST_BYTES {
+            let read = stream.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buffer[..read]);
+        }
+
+        let request = String::from_utf8_lossy(&request);
+        let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+        let (method, path) = (request_line.next(), request_line.next());
+        debug!("Control request: {:?} {:?}", method, path);
+
+        let (status, body) = match (method, path) {
+            (Some("POST"), Some(ADVANCE_PATH)) => {
+                info!("Control request received, moving to the next stage");
+                self.advance();
+                ("202 Accepted", "Moving to the next stage\n")
+            }
+            (_, Some(ADVANCE_PATH)) => ("405 Method Not Allowed", "Method Not Allowed\n"),
+            _ => ("404 Not Found", "Not Found\n"),
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_control_endpoint_advances() {
+        let trigger = StageTrigger::new();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(trigger.clone().serve(listener));
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}{}", address, ADVANCE_PATH))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 405);
+        let response = client
+            .post(format!("http://{}{}", address, ADVANCE_PATH))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 202);
+
+        tokio::time::timeout(Duration::from_secs(1), trigger.triggered())
+            .await
+            .unwrap();
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_file_touch_advances() {
+        let path = std::env::temp_dir().join(format!("lode-advance-{}", uuid::Uuid::new_v4()));
+        let trigger = StageTrigger::new();
+        let watcher = tokio::spawn(trigger.clone().watch_file(path.clone()));
+
+        tokio::time::sleep(FILE_POLL_INTERVAL).await;
+        std::fs::write(&path, "").unwrap();
+        tokio::time::timeout(FILE_POLL_INTERVAL * 3, trigger.triggered())
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        watcher.abort();
+    }
+}
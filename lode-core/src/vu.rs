@@ -0,0 +1,253 @@
+use reqwest::header::{HeaderMap, SET_COOKIE};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use url::Url;
+
+use crate::scenario::extract::Variables;
+
+/// A simulated user running the iterations of a load test one after another
+///
+/// Each virtual user keeps its own cookies and the values extracted from its responses from one
+/// iteration to the next, like a person staying logged in between page views. The row of test
+/// data of an iteration replaces the values of the same name when the iteration starts; the
+/// columns of the previous row do not carry over to a row without them, while values extracted
+/// since under the name of a column stay.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualUser {
+    id: usize,
+    iterations: u64,
+    variables: Variables,
+    /// Values the row of the current iteration filled in
+    row: Variables,
+    cookies: CookieJar,
+}
+
+impl VirtualUser {
+    /// Create the virtual user with the given number, without state
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            ..Default::default()
+        }
+    }
+
+    /// Number of the virtual user in the run, from 0
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Number of iterations the virtual user started
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    /// Start an iteration, filling in the columns of its row of test data
+    pub fn begin_iteration(&mut self, row: Option<&Variables>) {
+        self.iterations += 1;
+        for (name, value) in std::mem::take(&mut self.row) {
+            if self.variables.get(&name) == Some(&value) {
+                self.variables.remove(&name);
+            }
+        }
+        if let Some(row) = row {
+            self.row = row.clone();
+            self.variables.extend(
+                row.iter()
+                    .map(|(name, value)| (name.clone(), value.clone())),
+            );
+        }
+    }
+
+    /// Values filling the placeholders of the requests of the virtual user
+    pub fn variables(&self) -> &Variables {
+        &self.variables
+    }
+
+    /// Values extracted from the responses are added here
+    pub fn variables_mut(&mut self) -> &mut Variables {
+        &mut self.variables
+    }
+
+    /// Cookies the virtual user received
+    pub fn cookies(&self) -> &CookieJar {
+        &self.cookies
+    }
+
+    /// Cookies of the responses are stored here
+    pub fn cookies_mut(&mut self) -> &mut CookieJar {
+        &mut self.cookies
+    }
+}
+
+/// Cookies of a virtual user, sent back to the host that set them
+///
+/// Only the name and value of a cookie are kept; its path, expiry and flags are ignored, except
+/// that an empty value or `Max-Age=0` removes it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CookieJar {
+    /// Values by host and cookie name
+    cookies: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl CookieJar {
+    /// Store the `Set-Cookie` headers of a response to a request to `url`
+    pub fn store(&mut self, url: &str, headers: &HeaderMap) {
+        let Some(host) = host(url) else {
+            return;
+        };
+        for header in headers.get_all(SET_COOKIE) {
+            let Some((cookie, attributes)) = header
+                .to_str()
+                .ok()
+                .map(|header| header.split_once(';').unwrap_or((header, "")))
+            else {
+                continue;
+            };
+            let Some((name, value)) = cookie.split_once('=') else {
+                continue;
+            };
+            let (name, value) = (name.trim(), value.trim());
+            let expired = attributes.split(';').any(|attribute| {
+                attribute.split_once('=').is_some_and(|(key, value)| {
+                    key.trim().eq_ignore_ascii_case("max-age") && value.trim() == "0"
+                })
+            });
+            let cookies = self.cookies.entry(host.clone()).or_default();
+            if value.is_empty() || expired {
+                cookies.remove(name);
+            } else if !name.is_empty() {
+                cookies.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+
+    /// Value of the `Cookie` header of a request to `url`, none without cookies for its host
+    pub fn header(&self, url: &str) -> Option<String> {
+        let cookies = self.cookies.get(&host(url)?)?;
+        (!cookies.is_empty()).then(|| {
+            cookies
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
+    }
+
+    /// Whether no cookie was stored
+    pub fn is_empty(&self) -> bool {
+        self.cookies.values().all(BTreeMap::is_empty)
+    }
+}
+
+fn host(url: &str) -> Option<String> {
+    Url::parse(url).ok()?.host_str().map(str::to_string)
+}
+
+/// Virtual users waiting for their next iteration
+///
+/// An iteration takes an idle virtual user, or a new one when all are busy, and gives it back
+/// once done, so a run has as many virtual users as iterations it ran at once.
+#[derive(Debug, Default)]
+pub(crate) struct VirtualUsers {
+    idle: Mutex<Vec<VirtualUser>>,
+    created: AtomicUsize,
+}
+
+impl VirtualUsers {
+    /// Take the virtual user that ran the latest iteration, or a new one
+    pub(crate) fn take(&self) -> VirtualUser {
+        if let Some(vu) = self.idle.lock().unwrap().pop() {
+            return vu;
+        }
+        VirtualUser::new(self.created.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Give a virtual user back once its iteration is done
+    pub(crate) fn give_back(&self, vu: VirtualUser) {
+        self.idle.lock().unwrap().push(vu);
+    }
+
+    /// Number of virtual users created so far
+    pub(crate) fn created(&self) -> usize {
+        self.created.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn set_cookies(cookies: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for cookie in cookies {
+            headers.append(SET_COOKIE, HeaderValue::from_str(cookie).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_cookie_jar() {
+        let mut jar = CookieJar::default();
+        assert!(jar.is_empty());
+        jar.store(
+            "http://shop.test/login",
+            &set_cookies(&["session=abc; Path=/; HttpOnly", "theme=dark"]),
+        );
+        assert_eq!(
+            jar.header("http://shop.test/cart").as_deref(),
+            Some("session=abc; theme=dark")
+        );
+        assert_eq!(jar.header("http://other.test/"), None);
+
+        jar.store(
+            "http://shop.test/logout",
+            &set_cookies(&["session=; Max-Age=0", "theme=light; Max-Age=0"]),
+        );
+        assert_eq!(jar.header("http://shop.test/"), None);
+        assert!(jar.is_empty());
+    }
+
+    #[test]
+    fn test_virtual_user_keeps_state_between_iterations() {
+        let mut vu = VirtualUser::new(3);
+        vu.begin_iteration(Some(&Variables::from([(
+            "user".to_string(),
+            "alice".to_string(),
+        )])));
+        vu.variables_mut()
+            .insert("token".to_string(), "secret".to_string());
+        vu.begin_iteration(Some(&Variables::from([(
+            "user".to_string(),
+            "bob".to_string(),
+        )])));
+        assert_eq!(vu.id(), 3);
+        assert_eq!(vu.iterations(), 2);
+        assert_eq!(vu.variables()["user"], "bob");
+        assert_eq!(vu.variables()["token"], "secret");
+
+        // A row without the column leaves it without a value, unless a response filled it since
+        vu.variables_mut()
+            .insert("user".to_string(), "bob@example.com".to_string());
+        vu.begin_iteration(Some(&Variables::from([(
+            "id".to_string(),
+            "2".to_string(),
+        )])));
+        assert_eq!(vu.variables()["user"], "bob@example.com");
+        vu.begin_iteration(Some(&Variables::new()));
+        assert!(!vu.variables().contains_key("id"));
+        assert_eq!(vu.variables()["token"], "secret");
+    }
+
+    #[test]
+    fn test_virtual_users_are_reused() {
+        let vus = VirtualUsers::default();
+        let first = vus.take();
+        let second = vus.take();
+        assert_eq!((first.id(), second.id()), (0, 1));
+        vus.give_back(second);
+        assert_eq!(vus.take().id(), 1);
+        assert_eq!(vus.created(), 2);
+    }
+}
@@ -1,5 +1,8 @@
+pub mod test_config;
+pub mod test_distributed;
 pub mod test_engine;
 pub mod test_error_handling;
 pub mod test_http_client;
 pub mod test_metrics;
 pub mod test_report;
+pub mod test_schema;
@@ -0,0 +1,185 @@
+use lode_core::config::{
+    AdvancedConfig, ConfigError, HttpMethod, HttpVersion, LoadTestConfig, MissingValue, Stage,
+    TlsConfig,
+};
+use lode_core::scenario::{RequestSpec, Scenario};
+use proptest::prelude::*;
+use std::time::Duration;
+
+fn method() -> impl Strategy<Value = HttpMethod> {
+    prop_oneof![
+        Just(HttpMethod::GET),
+        Just(HttpMethod::POST),
+        Just(HttpMethod::PUT),
+        Just(HttpMethod::DELETE),
+        Just(HttpMethod::PATCH),
+    ]
+}
+
+fn config(requests: usize, concurrency: usize, timeout_ms: u64) -> LoadTestConfig {
+    LoadTestConfig {
+        url: "http://example.com/api".to_string(),
+        method: HttpMethod::GET,
+        requests,
+        concurrency,
+        timeout: Duration::from_millis(timeout_ms),
+        headers: Vec::new(),
+        body: None,
+        form: None,
+        graphql: None,
+        scenario: None,
+        feeder: None,
+        on_missing_value: MissingValue::Send,
+        random_params: Vec::new(),
+        labels: Default::default(),
+        faults: Vec::new(),
+        rate: None,
+        rate_burst: None,
+        max_concurrency: None,
+        max_rate: None,
+        load_model: Default::default(),
+        correct_coordinated_omission: false,
+        stages: Vec::new(),
+        record_requests: false,
+        trace: None,
+        deadline: None,
+        cooldown: None,
+        percentiles: Vec::new(),
+        region: None,
+        time_series_interval: None,
+        slos: Vec::new(),
+        thresholds: Vec::new(),
+        abort_on: Vec::new(),
+        honor_retry_after: false,
+        header_pacing: None,
+        retry: None,
+        think_time: None,
+        success_codes: None,
+        response_body: Default::default(),
+        http_version: HttpVersion::Auto,
+        tls: TlsConfig::default(),
+        dns: None,
+        advanced: AdvancedConfig::default(),
+    }
+}
+
+proptest! {
+    #[test]
+    fn test_new_and_validate_agree(
+        method in method(),
+        requests in 0usize..10_000,
+        concurrency in 0usize..10_000,
+        timeout_ms in 0u64..60_000,
+    ) {
+        let built = LoadTestConfig::new(
+            "http://example.com/api".to_string(),
+            method.clone(),
+            requests,
+            concurrency,
+            Duration::from_millis(timeout_ms),
+        );
+        let mut assembled = config(requests, concurrency, timeout_ms);
+        assembled.method = method;
+        prop_assert_eq!(built.is_ok(), assembled.validate().is_ok());
+    }
+
+    #[test]
+    fn test_valid_bounds_are_accepted(
+        requests in 1usize..10_000,
+        concurrency_fraction in 0.0f64..1.0,
+        timeout_ms in 1u64..60_000,
+    ) {
+        let concurrency = ((requests as f64 * concurrency_fraction) as usize).max(1);
+        prop_assert!(config(requests, concurrency, timeout_ms).validate().is_ok());
+    }
+
+    #[test]
+    fn test_concurrency_above_requests_is_rejected(requests in 1usize..10_000, extra in 1usize..100) {
+        let result = config(requests, requests + extra, 1000).validate();
+        prop_assert!(matches!(result, Err(ConfigError::InvalidConcurrency(_))));
+    }
+
+    #[test]
+    fn test_header_names_must_be_tokens(name in "[A-Za-z0-9_-]{0,8}[@ :/(){}]{1,3}[A-Za-z0-9_-]{0,8}") {
+        let result = config(10, 1, 1000)
+            .with_header(name, "value".to_string())
+            .validate();
+        prop_assert!(matches!(result, Err(ConfigError::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn test_token_header_names_are_accepted(name in "[A-Za-z0-9_-]{1,32}", value in "[ -~]{0,64}") {
+        prop_assert!(config(10, 1, 1000).with_header(name, value).validate().is_ok());
+    }
+
+    #[test]
+    fn test_tuning_knobs_must_be_positive(
+        worker_threads in 0usize..64,
+        stream_buffer in 0usize..1024,
+        max_body_size in 0usize..1024,
+        memory_ceiling in 0u64..1024,
+    ) {
+        let result = config(10, 1, 1000)
+            .with_advanced(AdvancedConfig {
+                worker_threads: Some(worker_threads),
+                stream_buffer: Some(stream_buffer),
+                max_body_size: Some(max_body_size),
+                memory_ceiling: Some(memory_ceiling),
+                spill_dir: None,
+            })
+            .validate();
+        prop_assert_eq!(
+            result.is_ok(),
+            worker_threads > 0 && stream_buffer > 0 && max_body_size > 0 && memory_ceiling > 0
+        );
+        if let Err(e) = result {
+            prop_assert!(matches!(e, ConfigError::InvalidTuning(_)));
+        }
+    }
+
+    #[test]
+    fn test_hold_stages_need_concurrency(seconds in 0u64..3600, concurrency in 0usize..100) {
+        let result = config(10, 1, 1000)
+            .with_stage(Stage::Hold {
+                duration: Duration::from_secs(seconds),
+                concurrency,
+                rate: None,
+            })
+            .validate();
+        prop_assert_eq!(result.is_ok(), concurrency > 0);
+    }
+
+    #[test]
+    fn test_total_requests_count_every_request_of_a_flow(
+        requests in 1usize..10_000,
+        steps in 1usize..5,
+    ) {
+        let specs: Vec<_> = (0..steps)
+            .map(|step| RequestSpec::new(format!("step{}", step), "https://example.com"))
+            .collect();
+        let flow = config(requests, 1, 1000).with_scenario(Scenario::sequential(specs.clone()));
+        prop_assert_eq!(flow.total_requests(), (requests * steps) as u64);
+        let mix = config(requests, 1, 1000).with_scenario(Scenario::new(specs));
+        prop_assert_eq!(mix.total_requests(), requests as u64);
+    }
+
+    #[test]
+    fn test_fingerprints_tell_configs_apart(
+        requests in 1usize..10_000,
+        concurrency in 1usize..100,
+        label in "[a-z]{1,8}",
+    ) {
+        let a = config(requests, concurrency.min(requests), 1000)
+            .with_label("step".to_string(), label.clone())
+            .with_label("variant".to_string(), "a".to_string());
+        // Labels are a map, their order does not matter
+        let b = config(requests, concurrency.min(requests), 1000)
+            .with_label("variant".to_string(), "a".to_string())
+            .with_label("step".to_string(), label);
+        prop_assert_eq!(a.fingerprint(), b.fingerprint());
+        prop_assert_eq!(a.fingerprint().len(), 64);
+        let mut c = b.clone();
+        c.timeout = Duration::from_millis(1001);
+        prop_assert_ne!(a.fingerprint(), c.fingerprint());
+    }
+}
@@ -0,0 +1,193 @@
+use lode_core::{
+    clock::ClockReading,
+    config::{HttpMethod, LoadTestConfig},
+    distributed::Controller,
+    metrics::{RequestMetrics, TestMetrics},
+    report::Report,
+};
+use reqwest::StatusCode;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Report of an agent in `region` having sent `requests` requests
+async fn agent_report(region: &str, requests: usize) -> Report {
+    let mut metrics = TestMetrics::new().unwrap();
+    metrics.mark_started(None);
+    for _ in 0..requests {
+        let request = RequestMetrics::new();
+        std::thread::sleep(Duration::from_millis(1));
+        metrics.record_request(request.complete(StatusCode::OK));
+    }
+    metrics.finalize(Duration::from_secs(1)).await.unwrap();
+    let mut report = Report::from_metrics(Arc::new(Mutex::new(metrics)))
+        .await
+        .unwrap();
+    report.region = Some(region.to_string());
+    report
+}
+
+fn run_status(status: &str, completed: u64, total: u64) -> serde_json::Value {
+    json!({
+        "id": "run",
+        "status": status,
+        "progress": {
+            "completed_requests": completed,
+            "total_requests": total,
+            "percent_complete": 100.0 * completed as f64 / total as f64,
+            "elapsed_seconds": 1.0,
+            "current_rps": 0.0,
+            "eta_seconds": null
+        }
+    })
+}
+
+/// An agent accepting a share of `requests` requests and reporting them as done
+async fn agent(region: &str, requests: usize) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/agent/load-tests"))
+        .and(body_partial_json(json!({ "requests": requests })))
+        .respond_with(ResponseTemplate::new(202).set_body_json(run_status(
+            "queued",
+            0,
+            requests as u64,
+        )))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/load-tests/run"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(run_status(
+            "completed",
+            requests as u64,
+            requests as u64,
+        )))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/load-tests/run/report"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(agent_report(region, requests).await),
+        )
+        .mount(&server)
+        .await;
+    server
+}
+
+fn config(requests: usize) -> LoadTestConfig {
+    LoadTestConfig::new(
+        "https://example.com".to_string(),
+        HttpMethod::GET,
+        requests,
+        4,
+        Duration::from_secs(5),
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_controller_merges_the_reports_of_its_agents() {
+    let (east, west) = (agent("us-east", 4).await, agent("eu-west", 3).await);
+    let controller = Controller::new(&[east.uri(), west.uri()])
+        .unwrap()
+        .with_poll_interval(Duration::from_millis(10));
+
+    let report = controller.run(&config(7), None, None).await.unwrap();
+    assert_eq!(report.total_requests, 7);
+    assert_eq!(report.successful_requests, 7);
+    let mut regions: Vec<(String, u64)> = report
+        .regions
+        .iter()
+        .map(|region| (region.region.clone(), region.total_requests))
+        .collect();
+    regions.sort();
+    assert_eq!(
+        regions,
+        [("eu-west".to_string(), 3), ("us-east".to_string(), 4)]
+    );
+}
+
+#[tokio::test]
+async fn test_controller_moves_reports_onto_its_clock() {
+    let east = agent("us-east", 4).await;
+    // The agent's clock is a minute ahead of the controller's
+    Mock::given(method("GET"))
+        .and(path("/time"))
+        .respond_with(|_: &wiremock::Request| {
+            ResponseTemplate::new(200).set_body_json(ClockReading {
+                unix_time_ms: ClockReading::now().unix_time_ms + 60_000,
+            })
+        })
+        .mount(&east)
+        .await;
+    let controller = Controller::new(&[east.uri()])
+        .unwrap()
+        .with_poll_interval(Duration::from_millis(10));
+
+    let report = controller.run(&config(4), None, None).await.unwrap();
+    let behind = chrono::Utc::now() - report.started_at.unwrap();
+    assert!((59..61).contains(&behind.num_seconds()), "{}", behind);
+    let offset = report.clock_offset.unwrap();
+    assert_eq!(offset.offset_ms, 0.0);
+    assert!(offset.round_trip_ms < 1000.0);
+}
+
+#[tokio::test]
+async fn test_controller_cancels_submitted_shares_when_an_agent_refuses() {
+    let accepting = agent("us-east", 4).await;
+    Mock::given(method("DELETE"))
+        .and(path("/load-tests/run"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&accepting)
+        .await;
+    let refusing = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(403).set_body_string("Target not allowed"))
+        .mount(&refusing)
+        .await;
+
+    let controller = Controller::new(&[accepting.uri(), refusing.uri()]).unwrap();
+    let error = controller.run(&config(7), None, None).await.unwrap_err();
+    assert!(error.to_string().contains("refused its share"));
+}
+
+#[tokio::test]
+async fn test_controller_cancels_running_shares_when_a_poll_fails() {
+    let running = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/agent/load-tests"))
+        .respond_with(ResponseTemplate::new(202).set_body_json(run_status("queued", 0, 4)))
+        .mount(&running)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/load-tests/run"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(run_status("running", 1, 4)))
+        .mount(&running)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/load-tests/run"))
+        .respond_with(ResponseTemplate::new(202))
+        .expect(1)
+        .mount(&running)
+        .await;
+    let failing = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/agent/load-tests"))
+        .respond_with(ResponseTemplate::new(202).set_body_json(run_status("queued", 0, 3)))
+        .mount(&failing)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&failing)
+        .await;
+
+    let controller = Controller::new(&[running.uri(), failing.uri()])
+        .unwrap()
+        .with_poll_interval(Duration::from_millis(10));
+    assert!(controller.run(&config(7), None, None).await.is_err());
+}
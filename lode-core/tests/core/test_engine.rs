@@ -1,10 +1,29 @@
 use crate::common::error_simulation::setup_error_mock_server;
 use crate::common::mock_server::setup_mock_server;
 
-use lode_core::engine::LoadTestEngine;
-use lode_core::http::DefaultHttpClient;
+use lode_core::config::{
+    AdvancedConfig, Cooldown, Feeder, FeederExhaustion, FeederStrategy, FormBody, FormField,
+    GeneratorFault, GraphqlRequest, HeaderPacing, HttpMethod, HttpVersion, LoadModel,
+    LoadTestConfig, MissingValue, ResponseBody, RetryCondition, RetryPolicy, Stage, ThinkTime,
+    TraceSampling, WaitCondition,
+};
+use lode_core::control::{LoadControl, OverrideLimits, Overrides};
+use lode_core::cooldown::BASELINE_PROBES;
+use lode_core::engine::{LoadTestEngine, RunOptions};
+use lode_core::http::{DefaultHttpClient, HttpClient, HttpClientOptions};
+use lode_core::live::LiveMetrics;
+use lode_core::metrics::ErrorKind;
+use lode_core::progress::Progress;
+use lode_core::report::Report;
+use lode_core::scenario::extract::Extractor;
+use lode_core::scenario::{RequestSpec, Scenario};
+use lode_core::trigger::StageTrigger;
 use reqwest::Method;
-use std::time::Duration;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use wiremock::matchers::{body_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
 #[tokio::test]
 async fn test_load_test_success() {
@@ -58,8 +77,2208 @@ async fn test_load_test_with_errors() {
     assert_eq!(metrics.failed_requests(), 5);
 
     let (error_counts, error_messages) = metrics.error_stats().unwrap();
-    assert_eq!(error_counts.get("HTTP 429").unwrap(), &5u64);
+    assert_eq!(error_counts.get(&ErrorKind::Http(429)).unwrap(), &5u64);
     assert!(error_messages
         .iter()
         .all(|msg| msg.contains("Too Many Requests")));
 }
+
+#[tokio::test]
+async fn test_execute_reports_progress() {
+    let mock_server = setup_mock_server(200, "/test", None).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        8,
+        2,
+        Duration::from_secs(1),
+    )
+    .unwrap();
+    let progress = Progress::new();
+
+    let metrics = engine
+        .execute(
+            &config,
+            RunOptions {
+                progress: Some(progress.clone()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(metrics.lock().await.total_requests(), 8);
+
+    let snapshot = progress.snapshot();
+    assert_eq!(snapshot.completed_requests, 8);
+    assert_eq!(snapshot.total_requests, 8);
+    assert_eq!(snapshot.percent_complete, 100.0);
+    assert_eq!(snapshot.eta_seconds, Some(0.0));
+}
+
+#[tokio::test]
+async fn test_pause_fault_delays_traffic() {
+    let mock_server = setup_mock_server(200, "/test", None).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        4,
+        1,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_fault(GeneratorFault::pause(
+        Duration::ZERO,
+        Duration::from_millis(300),
+    ));
+
+    let started = Instant::now();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    let metrics = metrics.lock().await;
+    assert_eq!(metrics.total_requests(), 4);
+    assert_eq!(metrics.successful_requests(), 4);
+}
+
+#[tokio::test]
+async fn test_throttle_fault_limits_concurrency() {
+    let delay = Duration::from_millis(100);
+    let mock_server = setup_mock_server(200, "/test", Some(delay)).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    // 4 requests at full concurrency take one round trip; throttled to 25% they run one at a time
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        4,
+        4,
+        Duration::from_secs(5),
+    )
+    .unwrap()
+    .with_fault(GeneratorFault::throttle(
+        Duration::ZERO,
+        Duration::from_secs(10),
+        25,
+    ));
+
+    let started = Instant::now();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+
+    assert!(started.elapsed() >= delay * 4);
+    assert_eq!(metrics.lock().await.successful_requests(), 4);
+}
+
+#[tokio::test]
+async fn test_stream_buffer_caps_requests_in_flight() {
+    let delay = Duration::from_millis(100);
+    let mock_server = setup_mock_server(200, "/test", Some(delay)).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        4,
+        4,
+        Duration::from_secs(5),
+    )
+    .unwrap()
+    .with_advanced(AdvancedConfig {
+        stream_buffer: Some(2),
+        ..Default::default()
+    });
+
+    let started = Instant::now();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+
+    assert!(started.elapsed() >= delay * 2);
+    assert_eq!(metrics.lock().await.successful_requests(), 4);
+}
+
+#[tokio::test]
+async fn test_connection_reuse_in_report() {
+    let mock_server = setup_mock_server(200, "/test", None).await;
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        10,
+        1,
+        Duration::from_secs(5),
+    )
+    .unwrap();
+
+    // One request at a time keeps reusing the first connection
+    let engine = LoadTestEngine::new(DefaultHttpClient::new().unwrap()).unwrap();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let connections = Report::from_metrics(metrics).await.unwrap().connections;
+    let connections = connections.expect("reqwest tells the connections");
+    assert_eq!(connections.new_connections, 1);
+    assert_eq!(connections.reused_connection_requests, 9);
+    assert_eq!(connections.reuse_percent, 90.0);
+
+    let client = DefaultHttpClient::with_options(HttpClientOptions {
+        keep_alive: false,
+        ..Default::default()
+    })
+    .unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let connections = Report::from_metrics(metrics).await.unwrap().connections;
+    let connections = connections.expect("reqwest tells the connections");
+    assert_eq!(connections.new_connections, 10);
+    assert_eq!(connections.reused_connection_requests, 0);
+}
+
+#[tokio::test]
+async fn test_response_body_policies() {
+    let mock_server = MockServer::start().await;
+    // Too large to arrive with the headers
+    Mock::given(path("/large"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![b'x'; 4 << 20]))
+        .mount(&mock_server)
+        .await;
+    let config = LoadTestConfig::new(
+        format!("{}/large", mock_server.uri()),
+        HttpMethod::GET,
+        5,
+        1,
+        Duration::from_secs(5),
+    )
+    .unwrap();
+    let engine = LoadTestEngine::new(DefaultHttpClient::new().unwrap()).unwrap();
+
+    // Drained bodies hand their connection back to the pool
+    for policy in [ResponseBody::Drain, ResponseBody::Read] {
+        let metrics = engine
+            .execute(
+                &config.clone().with_response_body(policy),
+                RunOptions::default(),
+            )
+            .await
+            .unwrap();
+        let report = Report::from_metrics(metrics).await.unwrap();
+        assert_eq!(report.successful_requests, 5);
+        assert_eq!(report.connections.unwrap().new_connections, 1);
+        assert!(report.phases.iter().any(|phase| phase.phase == "download"));
+    }
+
+    // Discarded bodies take their connection with them
+    let metrics = engine
+        .execute(
+            &config.with_response_body(ResponseBody::Discard),
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.successful_requests, 5);
+    assert_eq!(report.connections.unwrap().new_connections, 5);
+    assert!(report.phases.iter().all(|phase| phase.phase != "download"));
+}
+
+#[tokio::test]
+async fn test_forced_http_version_in_protocol_breakdown() {
+    let mock_server = setup_mock_server(200, "/test", None).await;
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        4,
+        2,
+        Duration::from_secs(5),
+    )
+    .unwrap();
+
+    for (http_version, protocol) in [
+        (HttpVersion::Auto, "HTTP/1.1"),
+        (HttpVersion::Http1, "HTTP/1.1"),
+        (HttpVersion::Http2, "HTTP/2"),
+    ] {
+        let client = DefaultHttpClient::with_options(HttpClientOptions {
+            http_version,
+            ..Default::default()
+        })
+        .unwrap();
+        let engine = LoadTestEngine::new(client).unwrap();
+        let metrics = engine
+            .execute(&config, RunOptions::default())
+            .await
+            .unwrap();
+        let report = Report::from_metrics(metrics).await.unwrap();
+        assert_eq!(report.successful_requests, 4);
+        assert_eq!(report.protocols.len(), 1);
+        assert_eq!(report.protocols[0].labels["protocol"], protocol);
+        assert_eq!(report.protocols[0].total_requests, 4);
+    }
+}
+
+#[tokio::test]
+async fn test_connect_rate_spaces_out_new_connections() {
+    let delay = Duration::from_millis(500);
+    let mock_server = setup_mock_server(200, "/test", Some(delay)).await;
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        5,
+        5,
+        Duration::from_secs(5),
+    )
+    .unwrap();
+
+    // The 5 requests start at once, but their connections open 100ms apart
+    let client = DefaultHttpClient::with_options(HttpClientOptions {
+        max_connect_rate: Some(10.0),
+        ..Default::default()
+    })
+    .unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+    let started = Instant::now();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+
+    assert!(started.elapsed() >= Duration::from_millis(400) + delay);
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.successful_requests, 5);
+    assert_eq!(report.connections.unwrap().new_connections, 5);
+}
+
+#[tokio::test]
+async fn test_request_phases_are_timed() {
+    let delay = Duration::from_millis(50);
+    let mock_server = setup_mock_server(200, "/test", Some(delay)).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    // A host name to resolve, on the port of the mock server
+    let url = format!("{}/test", mock_server.uri()).replace("127.0.0.1", "localhost");
+    let config = LoadTestConfig::new(url, HttpMethod::GET, 4, 1, Duration::from_secs(5)).unwrap();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.successful_requests, 4);
+
+    let phases: Vec<(&str, u64)> = report
+        .phases
+        .iter()
+        .map(|phase| (phase.phase.as_str(), phase.requests))
+        .collect();
+    // One connection serves all requests
+    assert_eq!(
+        phases,
+        vec![
+            ("queue", 4),
+            ("dns", 1),
+            ("connect", 1),
+            ("ttfb", 4),
+            ("download", 4)
+        ]
+    );
+    let ttfb = &report.phases[3];
+    assert!(ttfb.median_ms >= delay.as_secs_f64() * 1000.0);
+    assert!(ttfb.median_ms <= report.median_response_time_ms);
+}
+
+#[tokio::test]
+async fn test_retry_after_backs_off_virtual_user() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/busy"))
+        .respond_with(ResponseTemplate::new(503).insert_header("Retry-After", "1"))
+        .mount(&mock_server)
+        .await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = LoadTestConfig::new(
+        format!("{}/busy", mock_server.uri()),
+        HttpMethod::GET,
+        2,
+        1,
+        Duration::from_secs(5),
+    )
+    .unwrap();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(Report::from_metrics(metrics).await.unwrap().backoff, None);
+
+    let started = Instant::now();
+    let metrics = engine
+        .execute(&config.with_retry_after(), RunOptions::default())
+        .await
+        .unwrap();
+
+    // The second request waits for the back-off of the first
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.failed_requests, 2);
+    let backoff = report.backoff.unwrap();
+    assert_eq!(backoff.backoffs, 2);
+    assert_eq!(backoff.total_backoff_seconds, 2.0);
+}
+
+#[tokio::test]
+async fn test_retry_policy_retries_failed_attempts() {
+    let mock_server = MockServer::start().await;
+    // The first two attempts are refused with 503, every later one succeeds
+    Mock::given(path("/flaky"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .mount(&mock_server)
+        .await;
+    Mock::given(path("/flaky"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = LoadTestConfig::new(
+        format!("{}/flaky", mock_server.uri()),
+        HttpMethod::GET,
+        3,
+        1,
+        Duration::from_secs(5),
+    )
+    .unwrap()
+    .with_retry(
+        RetryPolicy::new(3)
+            .with_retry_on(vec![RetryCondition::Status(503)])
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(5)),
+    );
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.total_requests, 3);
+    assert_eq!(report.successful_requests, 3);
+    assert!(report.error_stats.is_none());
+    let retries = report.retries.unwrap();
+    assert_eq!(retries.retried_requests, 1);
+    assert_eq!(retries.retries, 2);
+    assert_eq!(retries.first_attempt_successes, 2);
+    assert_eq!(retries.retried_successes, 1);
+}
+
+#[tokio::test]
+async fn test_retry_policy_gives_up_after_max_attempts() {
+    let mock_server = setup_error_mock_server("/error").await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = LoadTestConfig::new(
+        format!("{}/error", mock_server.uri()),
+        HttpMethod::GET,
+        2,
+        1,
+        Duration::from_secs(5),
+    )
+    .unwrap()
+    .with_header("X-Error-Type".to_string(), "500".to_string())
+    .with_retry(
+        RetryPolicy::new(2)
+            .with_retry_on(vec![RetryCondition::Connect, RetryCondition::Status(500)])
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(1)),
+    );
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+
+    // Each request counts once, with the error of its last attempt
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.failed_requests, 2);
+    assert_eq!(
+        report.error_stats.unwrap().error_counts[&ErrorKind::Http(500)],
+        2
+    );
+    let retries = report.retries.unwrap();
+    assert_eq!(retries.retried_requests, 2);
+    assert_eq!(retries.retries, 2);
+    assert_eq!(retries.retried_successes, 0);
+    assert_eq!(mock_server.received_requests().await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_feeder_fills_request_templates() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/login"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let feeder = Feeder::from_csv("user,tenant\nalice,a\nbob,b\ncarol,c\n")
+        .unwrap()
+        .with_strategy(FeederStrategy::Unique);
+    let config = LoadTestConfig::new(
+        format!("{}/login", mock_server.uri()),
+        HttpMethod::POST,
+        10,
+        1,
+        Duration::from_secs(5),
+    )
+    .unwrap()
+    .with_header("X-Tenant".to_string(), "{{tenant}}".to_string())
+    .with_body(r#"{"user": "{{user}}"}"#.to_string())
+    .with_feeder(feeder);
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+
+    // Every row is sent once, then the run ends
+    assert_eq!(metrics.lock().await.total_requests(), 3);
+    let requests = mock_server.received_requests().await.unwrap();
+    let sent: Vec<_> = requests
+        .iter()
+        .map(|request| {
+            (
+                request.headers["x-tenant"].to_str().unwrap().to_string(),
+                String::from_utf8_lossy(&request.body).to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        sent,
+        vec![
+            ("a".to_string(), r#"{"user": "alice"}"#.to_string()),
+            ("b".to_string(), r#"{"user": "bob"}"#.to_string()),
+            ("c".to_string(), r#"{"user": "carol"}"#.to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_missing_values_and_exhausted_feeders() {
+    let mock_server = setup_mock_server(200, "/users", None).await;
+    let engine = LoadTestEngine::new(DefaultHttpClient::new().unwrap()).unwrap();
+    // The second row has no user
+    let feeder = Feeder::from_jsonl("{\"user\": \"alice\"}\n{\"id\": 2}\n{\"user\": \"carol\"}\n")
+        .unwrap()
+        .with_strategy(FeederStrategy::Unique);
+    let config = |feeder: Feeder, on_missing_value: MissingValue| {
+        LoadTestConfig::new(
+            format!("{}/users?name={{{{user}}}}", mock_server.uri()),
+            HttpMethod::GET,
+            6,
+            1,
+            Duration::from_secs(5),
+        )
+        .unwrap()
+        .with_feeder(feeder)
+        .with_on_missing_value(on_missing_value)
+    };
+    let run = |config: LoadTestConfig| {
+        let engine = &engine;
+        async move {
+            let metrics = engine
+                .execute(&config, RunOptions::default())
+                .await
+                .unwrap();
+            Report::from_metrics(metrics).await.unwrap()
+        }
+    };
+
+    // The row without a value is skipped and counted, the run ends with the unique rows
+    let report = run(config(feeder.clone(), MissingValue::Skip)).await;
+    assert_eq!(report.total_requests, 2);
+    assert_eq!(report.status, "completed");
+    let skipped = report.skipped.unwrap();
+    assert_eq!(skipped.requests, 1);
+    assert_eq!(skipped.reasons["No value for {{user}}"], 1);
+
+    // Sent as it is
+    let report = run(config(feeder.clone(), MissingValue::Send)).await;
+    assert_eq!(report.total_requests, 3);
+    assert!(report.skipped.is_none());
+
+    // The run is aborted naming the placeholder
+    let report = run(config(feeder.clone(), MissingValue::Abort)).await;
+    assert_eq!(report.status, "aborted");
+    assert_eq!(
+        report.abort_reason.as_deref(),
+        Some("No value for {{user}}")
+    );
+    assert!(report.total_requests <= 1);
+
+    // Rows of a unique feeder expected to last abort the run when they run out
+    let complete = Feeder::from_jsonl("{\"user\": \"alice\"}\n{\"user\": \"bob\"}\n")
+        .unwrap()
+        .with_strategy(FeederStrategy::Unique);
+    let report = run(config(
+        complete.clone().with_on_exhausted(FeederExhaustion::Fail),
+        MissingValue::Abort,
+    ))
+    .await;
+    assert_eq!(report.total_requests, 2);
+    assert_eq!(report.status, "aborted");
+    assert_eq!(
+        report.abort_reason.as_deref(),
+        Some("Feeder ran out of unique rows")
+    );
+
+    // Or start over
+    let report = run(config(
+        complete.with_on_exhausted(FeederExhaustion::Recycle),
+        MissingValue::Abort,
+    ))
+    .await;
+    assert_eq!(report.total_requests, 6);
+    assert_eq!(report.status, "completed");
+}
+
+#[tokio::test]
+async fn test_form_bodies() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+    let upload = std::env::temp_dir().join(format!("lode-upload-{}.bin", uuid::Uuid::new_v4()));
+    // Not valid UTF-8, so it must be sent as bytes
+    let content = [0xffu8, 0x00, 0xfe, 0x42];
+    std::fs::write(&upload, content).unwrap();
+
+    let config = |route: &str, form: FormBody| {
+        LoadTestConfig::new(
+            format!("{}/{}", mock_server.uri(), route),
+            HttpMethod::POST,
+            1,
+            1,
+            Duration::from_secs(5),
+        )
+        .unwrap()
+        .with_form(form)
+    };
+    let urlencoded = config(
+        "login",
+        FormBody::urlencoded(vec![
+            FormField::text("user", "alice"),
+            FormField::text("password", "a&b"),
+        ]),
+    );
+    let multipart = config(
+        "upload",
+        FormBody::multipart(vec![
+            FormField::text("title", "avatar"),
+            FormField::file("file", &upload).with_content_type("image/png"),
+        ]),
+    );
+    for config in [&urlencoded, &multipart] {
+        let metrics = engine.execute(config, RunOptions::default()).await.unwrap();
+        assert_eq!(metrics.lock().await.successful_requests(), 1);
+    }
+    std::fs::remove_file(&upload).unwrap();
+
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(
+        requests[0].headers["content-type"],
+        "application/x-www-form-urlencoded"
+    );
+    assert_eq!(requests[0].body, b"user=alice&password=a%26b");
+
+    let content_type = requests[1].headers["content-type"].to_str().unwrap();
+    let boundary = content_type
+        .strip_prefix("multipart/form-data; boundary=")
+        .unwrap();
+    let body = &requests[1].body;
+    assert!(body.starts_with(format!("--{}\r\n", boundary).as_bytes()));
+    assert!(body
+        .windows(content.len())
+        .any(|window| window == content.as_slice()));
+    assert!(body.ends_with(format!("--{}--\r\n", boundary).as_bytes()));
+}
+
+#[tokio::test]
+async fn test_staged_run_ends_after_last_stage() {
+    let delay = Duration::from_millis(20);
+    let mock_server = setup_mock_server(200, "/test", Some(delay)).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        1_000_000,
+        1,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_stage(Stage::Hold {
+        duration: Duration::from_millis(200),
+        concurrency: 2,
+        rate: None,
+    });
+
+    let started = Instant::now();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+
+    assert!(started.elapsed() >= Duration::from_millis(200));
+    assert!(started.elapsed() < Duration::from_secs(2));
+    let metrics = metrics.lock().await;
+    assert!(metrics.total_requests() > 0);
+    assert!(metrics.total_requests() < 1_000_000);
+    assert_eq!(metrics.failed_requests(), 0);
+}
+
+#[tokio::test]
+async fn test_report_breaks_down_stages() {
+    let delay = Duration::from_millis(20);
+    let mock_server = setup_mock_server(200, "/test", Some(delay)).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        1_000_000,
+        1,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_stage("hold:200ms:1".parse().unwrap())
+    .with_stage("hold:200ms:4".parse().unwrap());
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+
+    assert_eq!(report.stages.len(), 2);
+    let (first, second) = (&report.stages[0], &report.stages[1]);
+    assert_eq!(first.stage, "hold:0.2s:1");
+    assert_eq!(second.stage, "hold:0.2s:4");
+    assert!((first.duration_seconds - 0.2).abs() < 0.05);
+    // Each stage starts where the previous one ended
+    assert!((second.start_seconds - (first.start_seconds + first.duration_seconds)).abs() < 1e-9);
+    assert_eq!(
+        first.total_requests + second.total_requests,
+        report.total_requests
+    );
+    // Four virtual users get through more requests than one
+    assert!(second.requests_per_second > first.requests_per_second * 2.0);
+    assert!(first.p95_response_time_ms >= 20.0);
+}
+
+#[tokio::test]
+async fn test_wait_stage_waits_for_stable_value() {
+    let mock_server = setup_mock_server(200, "/test", None).await;
+    Mock::given(method("GET"))
+        .and(path("/replicas"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("3"))
+        .expect(3)
+        .mount(&mock_server)
+        .await;
+
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let mut condition = WaitCondition::new(format!("{}/replicas", mock_server.uri()));
+    condition.stable_polls = 3;
+    condition.poll_interval = Duration::from_millis(100);
+
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        1_000_000,
+        1,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_stage(Stage::Wait(condition));
+
+    let started = Instant::now();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+
+    // Three identical readings, 100ms apart
+    assert!(started.elapsed() >= Duration::from_millis(200));
+    assert!(metrics.lock().await.total_requests() > 0);
+    mock_server.verify().await;
+}
+
+#[tokio::test]
+async fn test_rate_limits_throughput() {
+    let mock_server = setup_mock_server(200, "/test", None).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    // 10 requests at 20 req/s start 50ms apart, regardless of concurrency
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        10,
+        10,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_rate(20.0);
+
+    let started = Instant::now();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+
+    assert!(started.elapsed() >= Duration::from_millis(450));
+    assert_eq!(metrics.lock().await.successful_requests(), 10);
+}
+
+#[tokio::test]
+async fn test_burst_starts_requests_at_once() {
+    let mock_server = setup_mock_server(200, "/test", None).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    // The full bucket lets 5 of the 10 requests start at once, the others follow 50ms apart
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        10,
+        10,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_rate(20.0)
+    .with_rate_burst(5);
+
+    let started = Instant::now();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(200));
+    assert!(elapsed < Duration::from_millis(400), "{:?}", elapsed);
+    assert_eq!(metrics.lock().await.successful_requests(), 10);
+
+    let config = config.with_load_model(LoadModel::Open);
+    assert!(config.validate().is_err());
+}
+
+#[tokio::test]
+async fn test_think_time_paces_virtual_users() {
+    let mock_server = setup_mock_server(200, "/test", None).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    // 2 virtual users think 100ms after each of their 3 requests
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        6,
+        2,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_think_time(ThinkTime::Fixed(Duration::from_millis(100)));
+
+    let started = Instant::now();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    let metrics = metrics.lock().await;
+    assert_eq!(metrics.successful_requests(), 6);
+    // Pauses are not part of the response times
+    assert!(metrics.max_response_time() < Duration::from_millis(100));
+}
+
+#[tokio::test]
+async fn test_open_model_counts_time_behind_the_cap() {
+    let mock_server = setup_mock_server(200, "/test", Some(Duration::from_millis(100))).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = |load_model: LoadModel| {
+        LoadTestConfig::new(
+            format!("{}/test", mock_server.uri()),
+            HttpMethod::GET,
+            6,
+            1,
+            Duration::from_secs(5),
+        )
+        .unwrap()
+        .with_rate(50.0)
+        .with_load_model(load_model)
+    };
+
+    // The virtual user waits for each response, so every request takes as long as the server
+    let metrics = engine
+        .execute(&config(LoadModel::Closed), RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.successful_requests, 6);
+    assert!(report.max_response_time_ms < 250.0);
+
+    // Requests arrive every 20ms but one at a time gets through, the last one 400ms late
+    let metrics = engine
+        .execute(&config(LoadModel::Open), RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.successful_requests, 6);
+    assert!(
+        report.max_response_time_ms > 350.0,
+        "max response time {}ms",
+        report.max_response_time_ms
+    );
+    let queue = report.phases.iter().find(|p| p.phase == "queue").unwrap();
+    assert!(queue.p99_ms > 300.0);
+}
+
+#[tokio::test]
+async fn test_coordinated_omission_correction() {
+    let mock_server = setup_mock_server(200, "/test", Some(Duration::from_millis(100))).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        6,
+        1,
+        Duration::from_secs(5),
+    )
+    .unwrap()
+    .with_rate(50.0)
+    .with_coordinated_omission_correction(true);
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+
+    // Each 100ms response held back the 4 requests due every 20ms behind it
+    let corrected = report.corrected_response_times.as_ref().unwrap();
+    assert_eq!(report.response_times().unwrap().unwrap().len(), 6);
+    assert!(corrected.requests >= 30, "{} requests", corrected.requests);
+    assert!(corrected.median_ms < report.median_response_time_ms);
+    assert!(corrected.max_ms >= report.max_response_time_ms - 1.0);
+}
+
+#[tokio::test]
+async fn test_ramp_up_and_down() {
+    let delay = Duration::from_millis(20);
+    let mock_server = setup_mock_server(200, "/test", Some(delay)).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        1_000_000,
+        1,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_stage("ramp:300ms:4".parse().unwrap())
+    .with_stage("ramp:200ms:0".parse().unwrap());
+
+    let started = Instant::now();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+
+    assert!(started.elapsed() >= Duration::from_millis(500));
+    let metrics = metrics.lock().await;
+    // At most 4 requests of 20ms in flight for 500ms
+    assert!(metrics.total_requests() > 0);
+    assert!(metrics.total_requests() <= 4 * 500 / 20);
+    assert_eq!(metrics.failed_requests(), 0);
+}
+
+#[tokio::test]
+async fn test_stage_rate_overrides_run_rate() {
+    let mock_server = setup_mock_server(200, "/test", None).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        1_000_000,
+        5,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_rate(1000.0)
+    .with_stage(Stage::Hold {
+        duration: Duration::from_millis(500),
+        concurrency: 5,
+        rate: Some(10.0),
+    });
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+
+    // 10 req/s for half a second
+    let total = metrics.lock().await.total_requests();
+    assert!((4..=7).contains(&total), "sent {} requests", total);
+}
+
+#[tokio::test]
+async fn test_scenario_splits_traffic_by_weight() {
+    let mock_server = MockServer::start().await;
+    for (route, status) in [("/browse", 200), ("/checkout", 500)] {
+        Mock::given(method("GET"))
+            .and(path(route))
+            .respond_with(ResponseTemplate::new(status))
+            .mount(&mock_server)
+            .await;
+    }
+
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let scenario = Scenario::new(vec![
+        RequestSpec::new("browse", format!("{}/browse", mock_server.uri())).with_weight(3),
+        RequestSpec::new("checkout", format!("{}/checkout", mock_server.uri())),
+    ]);
+    let config = LoadTestConfig::new(
+        mock_server.uri(),
+        HttpMethod::GET,
+        20,
+        4,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_scenario(scenario);
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+
+    assert_eq!(report.total_requests, 20);
+    assert_eq!(report.endpoints.len(), 2);
+    assert_eq!(report.endpoints[0].labels["endpoint"], "browse");
+    assert_eq!(report.endpoints[0].total_requests, 15);
+    assert_eq!(report.endpoints[0].failed_requests, 0);
+    assert_eq!(report.endpoints[1].labels["endpoint"], "checkout");
+    assert_eq!(report.endpoints[1].total_requests, 5);
+    assert_eq!(report.endpoints[1].failed_requests, 5);
+}
+
+#[tokio::test]
+async fn test_success_codes_per_run_and_request() {
+    let mock_server = MockServer::start().await;
+    for (route, status) in [("/cache", 404), ("/create", 200)] {
+        Mock::given(method("GET"))
+            .and(path(route))
+            .respond_with(ResponseTemplate::new(status))
+            .mount(&mock_server)
+            .await;
+    }
+
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+    // Misses are expected of the cache, the create endpoint must answer 201
+    let scenario = Scenario::new(vec![
+        RequestSpec::new("cache", format!("{}/cache", mock_server.uri())),
+        RequestSpec::new("create", format!("{}/create", mock_server.uri()))
+            .with_success_codes("201".parse().unwrap()),
+    ]);
+    let config = LoadTestConfig::new(
+        mock_server.uri(),
+        HttpMethod::GET,
+        10,
+        2,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_scenario(scenario)
+    .with_success_codes("2xx,404".parse().unwrap());
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+
+    assert_eq!(report.endpoints[0].labels["endpoint"], "cache");
+    assert_eq!(report.endpoints[0].failed_requests, 0);
+    assert_eq!(report.endpoints[1].labels["endpoint"], "create");
+    assert_eq!(report.endpoints[1].failed_requests, 5);
+    assert_eq!(
+        report.error_stats.unwrap().error_counts[&ErrorKind::Http(200)],
+        5
+    );
+    assert_eq!(report.status_codes[&404], 5);
+}
+
+#[tokio::test]
+async fn test_url_list_with_random_params() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/a"))
+        .and(query_param("sort", "asc"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/b"))
+        .and(query_param("sort", "asc"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let urls = ["a", "b"].map(|route| format!("{}/{}", mock_server.uri(), route));
+    let config = LoadTestConfig::new(
+        urls[0].clone(),
+        HttpMethod::GET,
+        10,
+        2,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_scenario(Scenario::from_urls(urls.clone(), HttpMethod::GET))
+    .with_random_param("sort=asc".parse().unwrap())
+    .with_random_param("page=1..9".parse().unwrap());
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+
+    assert_eq!(report.failed_requests, 0);
+    assert_eq!(report.endpoints.len(), 2);
+    for (endpoint, url) in report.endpoints.iter().zip(&urls) {
+        assert_eq!(&endpoint.labels["endpoint"], url);
+        assert_eq!(endpoint.total_requests, 5);
+    }
+    let pages: HashSet<String> = mock_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter_map(|request| {
+            request
+                .url
+                .query_pairs()
+                .find(|(name, _)| name == "page")
+                .map(|(_, page)| page.into_owned())
+        })
+        .collect();
+    assert!(pages.len() > 1, "pages were not randomized: {:?}", pages);
+}
+
+#[tokio::test]
+async fn test_sequential_scenario_passes_extracted_values() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/login"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("X-User", "7")
+                .set_body_string(r#"{"data": {"token": "secret"}}"#),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/users/7/orders"))
+        .and(header("Authorization", "Bearer secret"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"id": 42}]"#))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/orders/42"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let scenario = Scenario::sequential(vec![
+        RequestSpec::new("login", format!("{}/login", mock_server.uri()))
+            .with_method(HttpMethod::POST)
+            .with_extract(Extractor::json("token", "data.token"))
+            .with_extract(Extractor::header("user", "X-User")),
+        RequestSpec::new(
+            "orders",
+            format!("{}/users/{{{{user}}}}/orders", mock_server.uri()),
+        )
+        .with_header("Authorization", "Bearer {{token}}")
+        .with_extract(Extractor::regex("order", r#""id":\s*(\d+)"#)),
+        RequestSpec::new(
+            "order",
+            format!("{}/orders/{{{{order}}}}", mock_server.uri()),
+        ),
+    ]);
+    let config = LoadTestConfig::new(
+        mock_server.uri(),
+        HttpMethod::GET,
+        5,
+        2,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_scenario(scenario);
+    config.validate().unwrap();
+
+    let progress = Progress::new();
+    let metrics = engine
+        .execute(
+            &config,
+            RunOptions {
+                progress: Some(progress.clone()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+
+    assert_eq!(report.total_requests, 15);
+    assert_eq!(report.failed_requests, 0);
+    assert_eq!(report.endpoints.len(), 3);
+    assert!(report.endpoints.iter().all(|e| e.total_requests == 5));
+    assert_eq!(progress.snapshot().total_requests, 15);
+}
+
+#[tokio::test]
+async fn test_slow_requests_do_not_starve_fast_ones() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/browse"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/report"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(200)))
+        .mount(&mock_server)
+        .await;
+
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let scenario = Scenario::new(vec![
+        RequestSpec::new("browse", format!("{}/browse", mock_server.uri())),
+        RequestSpec::new("report", format!("{}/report", mock_server.uri())),
+    ]);
+    let config = LoadTestConfig::new(
+        mock_server.uri(),
+        HttpMethod::GET,
+        40,
+        4,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_scenario(scenario);
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+
+    // Picking by count would send 20 of each, the slow ones holding most of the slots; shared by
+    // slot time, the fast requests keep half of the slots and run far more often
+    assert_eq!(report.mix.len(), 2);
+    assert_eq!(report.mix[0].endpoint, "browse");
+    assert_eq!(report.mix[0].configured_percent, 50.0);
+    assert!(report.mix[0].requests >= 30, "{:?}", report.mix);
+    assert!(report.mix[1].requests >= 1, "{:?}", report.mix);
+    assert_eq!(report.mix[0].requests + report.mix[1].requests, 40);
+    assert!(report.as_string().contains("browse: 50.0% configured"));
+}
+
+#[tokio::test]
+async fn test_virtual_users_keep_cookies_between_iterations() {
+    let mock_server = MockServer::start().await;
+    // Only the first iteration of every virtual user logs in
+    Mock::given(method("GET"))
+        .and(path("/login"))
+        .respond_with(ResponseTemplate::new(200).insert_header("Set-Cookie", "session=abc; Path=/"))
+        .expect(2)
+        .up_to_n_times(2)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/login"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/cart"))
+        .and(header("Cookie", "session=abc"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let scenario = Scenario::sequential(vec![
+        RequestSpec::new("login", format!("{}/login", mock_server.uri())),
+        RequestSpec::new("cart", format!("{}/cart", mock_server.uri())),
+    ]);
+    let config = LoadTestConfig::new(
+        mock_server.uri(),
+        HttpMethod::GET,
+        6,
+        2,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_scenario(scenario);
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+
+    assert_eq!(report.total_requests, 12);
+    assert_eq!(report.failed_requests, 0);
+    assert_eq!(report.iterations, 6);
+    assert_eq!(report.virtual_users, 2);
+    assert!(report.iterations_per_second > 0.0);
+    assert_eq!(
+        report.iterations_per_second * 2.0,
+        report.requests_per_second
+    );
+}
+
+#[tokio::test]
+async fn test_sequential_scenario_stops_iteration_on_missing_value() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/login"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/profile"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let scenario = Scenario::sequential(vec![
+        RequestSpec::new("login", format!("{}/login", mock_server.uri()))
+            .with_extract(Extractor::json("token", "token")),
+        RequestSpec::new("profile", format!("{}/profile", mock_server.uri()))
+            .with_header("Authorization", "Bearer {{token}}"),
+    ]);
+    let config = LoadTestConfig::new(
+        mock_server.uri(),
+        HttpMethod::GET,
+        3,
+        1,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_scenario(scenario);
+
+    let progress = Progress::new();
+    let metrics = engine
+        .execute(
+            &config,
+            RunOptions {
+                progress: Some(progress.clone()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+
+    assert_eq!(report.total_requests, 3);
+    assert_eq!(report.failed_requests, 3);
+    assert_eq!(
+        report.error_stats.unwrap().error_counts[&ErrorKind::Assertion],
+        3
+    );
+
+    let snapshot = progress.snapshot();
+    assert_eq!(snapshot.completed_requests, 3);
+    assert_eq!(snapshot.total_requests, 3);
+}
+
+#[tokio::test]
+async fn test_live_metrics_snapshots() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/live"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+    let config = LoadTestConfig::new(
+        format!("{}/live", mock_server.uri()),
+        HttpMethod::GET,
+        20,
+        1,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_rate(50.0);
+
+    let (live, mut receiver) = LiveMetrics::channel(Duration::from_millis(100));
+    let watcher = tokio::spawn(async move {
+        let mut snapshots = Vec::new();
+        while receiver.changed().await.is_ok() {
+            let snapshot = receiver.borrow_and_update().clone();
+            let finished = snapshot.finished;
+            snapshots.push(snapshot);
+            if finished {
+                break;
+            }
+        }
+        snapshots
+    });
+
+    engine
+        .execute(
+            &config,
+            RunOptions {
+                live: Some(live),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let snapshots = watcher.await.unwrap();
+
+    // About 400ms at 50 req/s: a few live snapshots, then the final one
+    assert!(snapshots.len() >= 3, "{} snapshots", snapshots.len());
+    let (last, live) = snapshots.split_last().unwrap();
+    assert!(last.finished);
+    assert_eq!(last.total_requests, 20);
+    assert!(live.iter().all(|s| !s.finished && s.total_requests < 20));
+    assert!(live
+        .windows(2)
+        .all(|w| w[0].total_requests <= w[1].total_requests));
+}
+
+#[tokio::test]
+async fn test_cancel_stops_run_with_partial_metrics() {
+    let mock_server = setup_mock_server(200, "/cancel", None).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+    let config = LoadTestConfig::new(
+        format!("{}/cancel", mock_server.uri()),
+        HttpMethod::GET,
+        1000,
+        1,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_rate(50.0);
+
+    let cancel = CancellationToken::new();
+    let canceller = cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        canceller.cancel();
+    });
+
+    let started = Instant::now();
+    let metrics = engine
+        .execute(
+            &config,
+            RunOptions {
+                cancel: Some(cancel),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.status, "cancelled");
+    assert!(report.total_requests > 0);
+    assert!(report.total_requests < 1000);
+    assert_eq!(report.failed_requests, 0);
+}
+
+#[tokio::test]
+async fn test_deadline_ends_run_on_unresponsive_target() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/hang"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(30)))
+        .mount(&mock_server)
+        .await;
+
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+    let config = LoadTestConfig::new(
+        format!("{}/hang", mock_server.uri()),
+        HttpMethod::GET,
+        100,
+        4,
+        Duration::from_secs(20),
+    )
+    .unwrap()
+    .with_deadline(Duration::from_millis(300));
+
+    let started = Instant::now();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.status, "deadline_exceeded");
+    assert_eq!(report.total_requests, 0);
+}
+
+#[tokio::test]
+async fn test_abort_rule_ends_run_on_broken_target() {
+    let mock_server = setup_mock_server(500, "/broken", None).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+    let config = LoadTestConfig::new(
+        format!("{}/broken", mock_server.uri()),
+        HttpMethod::GET,
+        200,
+        2,
+        Duration::from_secs(5),
+    )
+    .unwrap()
+    .with_abort_rule("5 consecutive failures".parse().unwrap());
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.status, "aborted");
+    assert_eq!(
+        report.abort_reason.as_deref(),
+        Some("5 consecutive failures")
+    );
+    assert!((5..200).contains(&report.total_requests));
+    assert!(report
+        .as_string()
+        .contains("Status: aborted, partial results (5 consecutive failures)"));
+}
+
+/// A client that never answers and ignores the timeout it is given
+struct HangingClient;
+
+#[async_trait::async_trait]
+impl HttpClient for HangingClient {
+    async fn send_request(
+        &self,
+        _method: Method,
+        _url: String,
+        _timeout: Duration,
+        _headers: Vec<(String, String)>,
+        _body: Option<String>,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        std::future::pending().await
+    }
+
+    async fn send_bytes(
+        &self,
+        _method: Method,
+        _url: String,
+        _timeout: Duration,
+        _headers: Vec<(String, String)>,
+        _body: Vec<u8>,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        std::future::pending().await
+    }
+}
+
+#[tokio::test]
+async fn test_request_timeout_enforced_by_engine() {
+    let engine = LoadTestEngine::new(HangingClient).unwrap();
+    let config = LoadTestConfig::new(
+        "http://example.com/hang".to_string(),
+        HttpMethod::GET,
+        3,
+        3,
+        Duration::from_millis(100),
+    )
+    .unwrap();
+
+    let started = Instant::now();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.status, "completed");
+    assert_eq!(report.failed_requests, 3);
+    assert_eq!(
+        report.error_stats.unwrap().error_counts[&ErrorKind::Timeout],
+        3
+    );
+}
+
+#[tokio::test]
+async fn test_configured_percentiles_in_report() {
+    let mock_server = setup_mock_server(200, "/percentiles", None).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+    let config = LoadTestConfig::new(
+        format!("{}/percentiles", mock_server.uri()),
+        HttpMethod::GET,
+        20,
+        2,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_percentile(75.0)
+    .with_percentile(99.99);
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+
+    let percentiles: Vec<f64> = report.percentiles.iter().map(|p| p.percentile).collect();
+    assert_eq!(percentiles, vec![75.0, 99.99]);
+    assert!(report.percentiles[0].response_time_ms > 0.0);
+    assert!(report.percentiles[0].response_time_ms <= report.percentiles[1].response_time_ms);
+    assert!(report.percentiles[1].response_time_ms <= report.max_response_time_ms + 1.0);
+}
+
+/// A client that holds up the thread of the runtime before each request, like a saturated generator
+struct BlockingClient(DefaultHttpClient);
+
+#[async_trait::async_trait]
+impl HttpClient for BlockingClient {
+    async fn send_request(
+        &self,
+        method: Method,
+        url: String,
+        timeout: Duration,
+        headers: Vec<(String, String)>,
+        body: Option<String>,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        std::thread::sleep(Duration::from_millis(100));
+        self.0
+            .send_request(method, url, timeout, headers, body)
+            .await
+    }
+
+    async fn send_bytes(
+        &self,
+        method: Method,
+        url: String,
+        timeout: Duration,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        std::thread::sleep(Duration::from_millis(100));
+        self.0.send_bytes(method, url, timeout, headers, body).await
+    }
+}
+
+#[tokio::test]
+async fn test_late_starts_are_reported_as_queue_time() {
+    let mock_server = setup_mock_server(200, "/test", None).await;
+    let engine = LoadTestEngine::new(BlockingClient(DefaultHttpClient::new().unwrap())).unwrap();
+    let mut config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        4,
+        2,
+        Duration::from_secs(5),
+    )
+    .unwrap();
+    // Slots 50ms apart, while every request holds the only runtime thread for 100ms
+    config.rate = Some(20.0);
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.successful_requests, 4);
+
+    let queue = &report.phases[0];
+    assert_eq!(queue.phase, "queue");
+    assert_eq!(queue.requests, 4);
+    assert!(queue.p99_ms >= 40.0);
+    assert!(report.min_response_time_ms >= 100.0);
+}
+
+#[tokio::test]
+async fn test_cooldown_probes_after_load() {
+    let mock_server = setup_mock_server(200, "/test", None).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        10,
+        2,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_cooldown(Cooldown::new(Duration::from_millis(1500)));
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+
+    // The cooldown is not part of the run's duration or totals
+    assert_eq!(report.total_requests, 10);
+    assert!(report.total_duration_seconds < 1.0);
+    let cooldown = report.cooldown.unwrap();
+    assert!(cooldown.baseline_ms.is_some());
+    // One probe when the load stops and one a second later
+    assert_eq!(cooldown.probes.len(), 2);
+    assert!(cooldown.probes.iter().all(|probe| probe.success));
+    assert!(cooldown.probes[0].offset_seconds < 0.1);
+    assert!((cooldown.probes[1].offset_seconds - 1.0).abs() < 0.1);
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 10 + BASELINE_PROBES + 2);
+}
+
+#[tokio::test]
+async fn test_burst_stage_idles_between_bursts() {
+    let delay = Duration::from_millis(10);
+    let mock_server = setup_mock_server(200, "/test", Some(delay)).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = LoadTestConfig {
+        record_requests: true,
+        ..LoadTestConfig::new(
+            format!("{}/test", mock_server.uri()),
+            HttpMethod::GET,
+            1_000_000,
+            1,
+            Duration::from_secs(1),
+        )
+        .unwrap()
+        .with_stage("burst:150ms:250ms:2:2".parse().unwrap())
+    };
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+
+    // Both bursts sent requests, with no request starting during the idle period between them
+    let timestamps: Vec<_> = report.requests.iter().map(|r| r.timestamp).collect();
+    let gaps: Vec<_> = timestamps
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).to_std().unwrap_or_default())
+        .collect();
+    let idle: Vec<_> = (0..gaps.len())
+        .filter(|&index| gaps[index] >= Duration::from_millis(200))
+        .collect();
+    assert_eq!(idle.len(), 1);
+    assert!(idle[0] > 0 && idle[0] < gaps.len() - 1);
+    assert!(report.total_duration_seconds >= 0.8);
+}
+
+#[tokio::test]
+async fn test_graphql_errors_fail_requests() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(header("content-type", "application/json"))
+        .and(body_json(serde_json::json!({
+            "query": "query User($id: ID!) { user(id: $id) { name } }",
+            "variables": {"id": "1"},
+        })))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({"data": {"user": {}}})),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "data": null,
+            "errors": [{"message": "User not found"}],
+        })))
+        .mount(&mock_server)
+        .await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let query = "query User($id: ID!) { user(id: $id) { name } }";
+    let config = |id: &str| {
+        LoadTestConfig::new(
+            format!("{}/graphql", mock_server.uri()),
+            HttpMethod::GET,
+            4,
+            2,
+            Duration::from_secs(5),
+        )
+        .unwrap()
+        .with_graphql(GraphqlRequest::new(query).with_variables(serde_json::json!({"id": id})))
+    };
+
+    let metrics = engine
+        .execute(&config("1"), RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.successful_requests, 4);
+
+    // The server answers 200, the errors in the body fail the requests
+    let metrics = engine
+        .execute(&config("2"), RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.failed_requests, 4);
+    let errors = report.error_stats.unwrap();
+    assert_eq!(errors.error_counts[&ErrorKind::Graphql], 4);
+    assert!(errors.error_messages[0].contains("User not found"));
+}
+
+#[tokio::test]
+async fn test_read_bodies_are_truncated_at_max_body_size() {
+    let mock_server = MockServer::start().await;
+    let body = format!("token=abc {} id=7", "x".repeat(64 * 1024));
+    Mock::given(method("GET"))
+        .and(path("/download"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(body))
+        .mount(&mock_server)
+        .await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = |extractor: Extractor| {
+        let scenario = Scenario::new(vec![RequestSpec::new(
+            "download",
+            format!("{}/download", mock_server.uri()),
+        )
+        .with_extract(extractor)]);
+        LoadTestConfig::new(
+            mock_server.uri(),
+            HttpMethod::GET,
+            4,
+            2,
+            Duration::from_secs(5),
+        )
+        .unwrap()
+        .with_scenario(scenario)
+        .with_advanced(AdvancedConfig {
+            max_body_size: Some(1024),
+            ..Default::default()
+        })
+    };
+
+    // The token is within the limit, so cutting the body does not fail the requests
+    let metrics = engine
+        .execute(
+            &config(Extractor::regex("token", r"token=(\w+)")),
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.successful_requests, 4);
+    assert_eq!(report.truncated_bodies, Some(4));
+
+    let metrics = engine
+        .execute(
+            &config(Extractor::regex("id", r"id=(\d+)")),
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.failed_requests, 4);
+    let errors = report.error_stats.unwrap();
+    assert!(errors.error_messages[0].ends_with("(body truncated at 1024 bytes)"));
+}
+
+#[tokio::test]
+async fn test_trigger_moves_to_next_stage() {
+    let mock_server = setup_mock_server(200, "/test", Some(Duration::from_millis(5))).await;
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+
+    let config = LoadTestConfig::new(
+        format!("{}/test", mock_server.uri()),
+        HttpMethod::GET,
+        1_000_000,
+        1,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_stage("hold:1h:1".parse().unwrap())
+    .with_stage(Stage::Signal)
+    .with_stage("hold:100ms:2".parse().unwrap());
+
+    // One trigger cuts the hour-long hold short, the next one ends the signal stage
+    let trigger = StageTrigger::new();
+    let triggers = trigger.clone();
+    tokio::spawn(async move {
+        for _ in 0..2 {
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            triggers.advance();
+        }
+    });
+
+    let metrics = engine
+        .execute(
+            &config,
+            RunOptions {
+                trigger: Some(trigger),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+
+    let stages: Vec<_> = report.stages.iter().map(|s| s.stage.as_str()).collect();
+    assert_eq!(stages, vec!["hold:3600s:1", "signal", "hold:0.1s:2"]);
+    assert!(report.stages.iter().all(|stage| stage.total_requests > 0));
+    assert!(report.total_duration_seconds < 2.0);
+}
+
+#[tokio::test]
+async fn test_transfer_counts_headers_and_bodies() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/upload"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("x".repeat(1000)))
+        .mount(&mock_server)
+        .await;
+
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+    let config = LoadTestConfig::new(
+        format!("{}/upload", mock_server.uri()),
+        HttpMethod::POST,
+        10,
+        2,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_body("y".repeat(100));
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+
+    let transfer = report.transfer.unwrap();
+    // Bodies plus at least the request line and the status line of every request
+    assert!(transfer.bytes_sent > 10 * (100 + "POST /upload HTTP/1.1\r\n".len() as u64));
+    assert!(transfer.bytes_received > 10 * (1000 + "HTTP/1.1 200 OK\r\n".len() as u64));
+    assert!(transfer.mean_response_bytes > 1000.0 && transfer.mean_response_bytes < 1500.0);
+    assert!(transfer.received_mb_per_second > 0.0);
+    assert!(report.as_string().contains("Transfer: 0.01 MB received"));
+}
+
+#[tokio::test]
+async fn test_override_raises_concurrency() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(100)))
+        .mount(&mock_server)
+        .await;
+
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+    let config = LoadTestConfig::new(
+        mock_server.uri(),
+        HttpMethod::GET,
+        16,
+        1,
+        Duration::from_secs(5),
+    )
+    .unwrap()
+    .with_max_concurrency(8);
+
+    let control = LoadControl::new(OverrideLimits::for_config(&config));
+    assert!(control
+        .apply(Overrides {
+            concurrency: Some(9),
+            rate: None,
+        })
+        .is_err());
+    control
+        .apply(Overrides {
+            concurrency: Some(8),
+            rate: None,
+        })
+        .unwrap();
+
+    // One at a time the requests would take 1.6s
+    let started = Instant::now();
+    let metrics = engine
+        .execute(
+            &config,
+            RunOptions {
+                control: Some(control),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.successful_requests, 16);
+    assert_eq!(report.timeline[0].event, "load_overridden");
+    assert_eq!(report.timeline[0].detail, "concurrency 8");
+}
+
+#[tokio::test]
+async fn test_override_applies_while_running() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+    let config = LoadTestConfig::new(
+        mock_server.uri(),
+        HttpMethod::GET,
+        20,
+        1,
+        Duration::from_secs(5),
+    )
+    .unwrap()
+    .with_rate(5.0)
+    .with_max_rate(100.0);
+
+    let control = LoadControl::new(OverrideLimits::for_config(&config));
+    let live = control.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        live.apply(Overrides {
+            concurrency: None,
+            rate: Some(100.0),
+        })
+        .unwrap();
+    });
+
+    // At 5 requests per second the run would take 4s
+    let started = Instant::now();
+    let metrics = engine
+        .execute(
+            &config,
+            RunOptions {
+                control: Some(control),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.successful_requests, 20);
+    assert_eq!(report.timeline[0].event, "load_overridden");
+    assert_eq!(report.timeline[0].detail, "rate 100/s");
+}
+
+#[tokio::test]
+async fn test_header_pacing_rides_under_published_limit() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("X-RateLimit-Remaining", "10")
+                .insert_header("X-RateLimit-Reset", "1"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+    let config = LoadTestConfig::new(
+        mock_server.uri(),
+        HttpMethod::GET,
+        10,
+        2,
+        Duration::from_secs(5),
+    )
+    .unwrap()
+    .with_header_pacing(HeaderPacing::default());
+
+    // 9 requests per second once the first responses tell the limit: the last 8 requests take
+    // 7 intervals of 111ms
+    let started = Instant::now();
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    assert!(started.elapsed() > Duration::from_millis(700));
+    assert_eq!(metrics.lock().await.successful_requests(), 10);
+}
+
+#[tokio::test]
+async fn test_trace_sampling_keeps_requests_for_har() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(201)
+                .insert_header("Content-Type", "text/plain")
+                .set_body_string("created a very long order"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let engine = LoadTestEngine::new(DefaultHttpClient::new().unwrap()).unwrap();
+    let config = LoadTestConfig::new(
+        format!("{}/orders?page=2", mock_server.uri()),
+        HttpMethod::POST,
+        10,
+        2,
+        Duration::from_secs(5),
+    )
+    .unwrap()
+    .with_header("X-Trace".to_string(), "yes".to_string())
+    .with_header("Authorization".to_string(), "Bearer secret".to_string())
+    .with_body(r#"{"item":1}"#.to_string())
+    .with_trace(TraceSampling {
+        max_entries: 3,
+        max_body_size: 7,
+        ..TraceSampling::new(100.0)
+    });
+
+    let metrics = engine
+        .execute(&config, RunOptions::default())
+        .await
+        .unwrap();
+    let report = Report::from_metrics(metrics).await.unwrap();
+    assert_eq!(report.successful_requests, 10);
+    assert_eq!(report.traces.len(), 3);
+
+    let trace = &report.traces[0];
+    assert_eq!(trace.method, "POST");
+    assert!(trace.url.ends_with("/orders?page=2"));
+    assert!(trace
+        .headers
+        .contains(&("X-Trace".to_string(), "yes".to_string())));
+    // Credentials are redacted unless the sampling keeps them
+    assert!(trace
+        .headers
+        .contains(&("Authorization".to_string(), "[redacted]".to_string())));
+    assert_eq!(trace.body.as_ref().unwrap().bytes, b"{\"item\"");
+    assert_eq!(trace.body.as_ref().unwrap().size, 10);
+    let response = trace.response.as_ref().unwrap();
+    assert_eq!(response.status, 201);
+    assert_eq!(response.http_version, "HTTP/1.1");
+    let body = response.body.as_ref().unwrap();
+    assert_eq!(body.bytes, b"created");
+    assert_eq!(body.size, 25);
+    assert!(trace.time > Duration::ZERO);
+
+    let har: serde_json::Value = serde_json::from_str(&report.as_har().unwrap()).unwrap();
+    assert_eq!(har["log"]["entries"].as_array().unwrap().len(), 3);
+
+    // Without sampling nothing is traced
+    let metrics = engine
+        .execute(
+            &LoadTestConfig::new(
+                mock_server.uri(),
+                HttpMethod::POST,
+                2,
+                1,
+                Duration::from_secs(5),
+            )
+            .unwrap(),
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert!(Report::from_metrics(metrics)
+        .await
+        .unwrap()
+        .traces
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_invalid_extractor_fails_the_run() {
+    let client = DefaultHttpClient::new().unwrap();
+    let engine = LoadTestEngine::new(client).unwrap();
+    // Built without validating the configuration, as library callers may
+    let scenario = Scenario::new(vec![RequestSpec::new("order", "http://localhost:1/order")
+        .with_extract(Extractor::regex("order", r#""id":\s*(\d+"#))]);
+    let config = LoadTestConfig::new(
+        "http://localhost:1".to_string(),
+        HttpMethod::GET,
+        1,
+        1,
+        Duration::from_secs(1),
+    )
+    .unwrap()
+    .with_scenario(scenario);
+
+    let result = engine.execute(&config, RunOptions::default()).await;
+    assert!(matches!(result, Err(lode_core::error::Error::Config(_))));
+}
@@ -1,4 +1,4 @@
-use lode_core::{engine::LoadTestEngine, http::DefaultHttpClient};
+use lode_core::{engine::LoadTestEngine, http::DefaultHttpClient, metrics::ErrorKind};
 use reqwest::Method;
 use std::time::Duration;
 use wiremock::matchers::{header, method, path};
@@ -42,7 +42,7 @@ async fn test_error_handling_integration() {
     assert_eq!(metrics.failed_requests(), 5);
 
     let (error_counts, error_messages) = metrics.error_stats().unwrap();
-    assert_eq!(error_counts.get("HTTP 429").unwrap(), &5u64);
+    assert_eq!(error_counts.get(&ErrorKind::Http(429)).unwrap(), &5u64);
     assert!(error_messages
         .iter()
         .all(|msg| msg.contains("Too Many Requests")));
@@ -1,9 +1,19 @@
 use crate::common::error_simulation::setup_error_mock_server;
 use crate::common::mock_server::setup_mock_server;
 
-use lode_core::http::{DefaultHttpClient, HttpClient};
+use lode_core::config::{DnsConfig, TlsConfig, TlsVersion};
+use lode_core::conformance::check_http_client;
+use lode_core::http::{
+    connection_setup, DefaultHttpClient, HttpClient, HttpClientOptions, CERT_CHECK_INTERVAL,
+};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
 use reqwest::Method;
-use std::time::Duration;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use wiremock::matchers::{header, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
 #[tokio::test]
 async fn test_successful_request() {
@@ -76,3 +86,582 @@ async fn test_timeout() {
     assert!(result.is_err());
     assert!(result.unwrap_err().is_timeout());
 }
+
+fn tls_fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/tls")
+        .join(name)
+}
+
+#[tokio::test]
+async fn test_client_with_tls_settings() {
+    // The server asks for a client certificate issued by the CA of the fixtures
+    let addr = tls_server(Some(&tls_fixture("ca.pem"))).await;
+    let pem = |name: &str| Some(std::fs::read_to_string(tls_fixture(name)).unwrap());
+    let from_files = TlsConfig {
+        ca_cert: Some(tls_fixture("server.pem")),
+        client_cert: Some(tls_fixture("client.pem")),
+        client_key: Some(tls_fixture("client.key")),
+        min_version: Some(TlsVersion::Tls1_2),
+        ..Default::default()
+    };
+    let inline = TlsConfig {
+        ca_cert_pem: pem("server.pem"),
+        client_cert_pem: pem("client.pem"),
+        client_key_pem: pem("client.key"),
+        min_version: Some(TlsVersion::Tls1_2),
+        ..Default::default()
+    };
+    let send = |tls: TlsConfig| async move {
+        let client = DefaultHttpClient::with_options(HttpClientOptions {
+            tls,
+            ..Default::default()
+        })
+        .unwrap();
+        client
+            .send_request(
+                Method::GET,
+                format!("https://localhost:{}/test", addr.port()),
+                Duration::from_secs(5),
+                vec![],
+                None,
+            )
+            .await
+    };
+
+    for session_resumption in [None, Some(true)] {
+        for tls in [from_files.clone(), inline.clone()] {
+            let response = send(TlsConfig {
+                session_resumption,
+                ..tls
+            })
+            .await
+            .unwrap();
+            assert!(connection_setup(&response).unwrap().tls.is_some());
+            assert_eq!(response.text().await.unwrap(), "ok");
+        }
+    }
+
+    // Without its certificate the client is turned away, and the server's is not trusted
+    // without the CA
+    let anonymous = TlsConfig {
+        ca_cert: Some(tls_fixture("server.pem")),
+        ..Default::default()
+    };
+    assert!(send(anonymous).await.is_err());
+    let untrusted = TlsConfig {
+        ca_cert: None,
+        ..from_files
+    };
+    assert!(send(untrusted).await.is_err());
+}
+
+/// Write a file as a renewal would, with a modification time that tells it apart
+fn rewrite(path: &Path, contents: &[u8], modified: SystemTime) {
+    std::fs::write(path, contents).unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_client_reloads_rotated_certificate() {
+    let mock_server = setup_mock_server(200, "/test", None).await;
+    let dir = std::env::temp_dir().join(format!("lode-certs-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir(&dir).unwrap();
+    let (cert, key) = (dir.join("client.pem"), dir.join("client.key"));
+    let (cert_pem, key_pem) = (
+        std::fs::read(tls_fixture("client.pem")).unwrap(),
+        std::fs::read(tls_fixture("client.key")).unwrap(),
+    );
+    let issued = SystemTime::now() - Duration::from_secs(3600);
+    rewrite(&cert, &cert_pem, issued);
+    rewrite(&key, &key_pem, issued);
+
+    let client = DefaultHttpClient::with_options(HttpClientOptions {
+        tls: TlsConfig {
+            client_cert: Some(cert.clone()),
+            client_key: Some(key.clone()),
+            reload_client_cert: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .unwrap();
+    let send = || {
+        client.send_request(
+            Method::GET,
+            format!("{}/test", mock_server.uri()),
+            Duration::from_secs(1),
+            vec![],
+            None,
+        )
+    };
+
+    // A key that does not load yet is tried again, the previous certificate keeps being used
+    rewrite(&key, b"not a key", issued + Duration::from_secs(60));
+    tokio::time::sleep(CERT_CHECK_INTERVAL).await;
+    assert_eq!(send().await.unwrap().status().as_u16(), 200);
+    assert!(client.cert_rotations().is_empty());
+
+    rewrite(&key, &key_pem, issued + Duration::from_secs(120));
+    tokio::time::sleep(CERT_CHECK_INTERVAL).await;
+    assert_eq!(send().await.unwrap().status().as_u16(), 200);
+    let rotations = client.cert_rotations();
+    assert_eq!(rotations.len(), 1);
+    assert_eq!(rotations[0].cert, cert);
+
+    // Unchanged files are not loaded again
+    tokio::time::sleep(CERT_CHECK_INTERVAL).await;
+    assert_eq!(send().await.unwrap().status().as_u16(), 200);
+    assert_eq!(client.cert_rotations().len(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Serve HTTPS on localhost with the server certificate of the fixtures, a connection per request
+///
+/// With a CA given, clients must present a certificate it issued.
+async fn tls_server(client_ca: Option<&Path>) -> SocketAddr {
+    tls_server_with_versions(client_ca, rustls::DEFAULT_VERSIONS).await
+}
+
+/// Like [`tls_server`], speaking only the given TLS versions
+async fn tls_server_with_versions(
+    client_ca: Option<&Path>,
+    versions: &[&'static rustls::SupportedProtocolVersion],
+) -> SocketAddr {
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+    use rustls::server::WebPkiClientVerifier;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let certs = CertificateDer::pem_file_iter(tls_fixture("server.pem"))
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let key = PrivateKeyDer::from_pem_file(tls_fixture("server.key")).unwrap();
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = rustls::ServerConfig::builder_with_provider(Arc::clone(&provider))
+        .with_protocol_versions(versions)
+        .unwrap();
+    let builder = match client_ca {
+        Some(ca) => {
+            let mut roots = rustls::RootCertStore::empty();
+            for cert in CertificateDer::pem_file_iter(ca).unwrap() {
+                roots.add(cert.unwrap()).unwrap();
+            }
+            builder.with_client_cert_verifier(
+                WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                    .build()
+                    .unwrap(),
+            )
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = builder.with_single_cert(certs, key).unwrap();
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                let mut stream = acceptor.accept(stream).await?;
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buf).await?;
+                    if read == 0 {
+                        return Ok(());
+                    }
+                    request.extend_from_slice(&buf[..read]);
+                }
+                stream
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                    )
+                    .await?;
+                stream.shutdown().await
+            });
+        }
+    });
+    addr
+}
+
+/// Whether the TLS handshakes of three requests on new connections resumed a session
+async fn resumed_handshakes(tls: TlsConfig) -> Vec<Option<bool>> {
+    let addr = tls_server(None).await;
+    let client = DefaultHttpClient::with_options(HttpClientOptions {
+        keep_alive: false,
+        tls,
+        ..Default::default()
+    })
+    .unwrap();
+    let mut resumed = Vec::new();
+    for _ in 0..3 {
+        let response = client
+            .send_request(
+                Method::GET,
+                format!("https://localhost:{}/", addr.port()),
+                Duration::from_secs(5),
+                vec![],
+                None,
+            )
+            .await
+            .unwrap();
+        let setup = connection_setup(&response).unwrap();
+        assert!(setup.tls.is_some());
+        resumed.push(setup.tls_resumed);
+        assert_eq!(response.text().await.unwrap(), "ok");
+    }
+    resumed
+}
+
+#[tokio::test]
+async fn test_client_reports_tls_session_resumption() {
+    let verified = TlsConfig {
+        ca_cert: Some(tls_fixture("server.pem")),
+        session_resumption: Some(true),
+        ..Default::default()
+    };
+    assert_eq!(
+        resumed_handshakes(verified).await,
+        [Some(false), Some(true), Some(true)]
+    );
+
+    let insecure = TlsConfig {
+        insecure: true,
+        session_resumption: Some(true),
+        ..Default::default()
+    };
+    assert_eq!(
+        resumed_handshakes(insecure).await,
+        [Some(false), Some(true), Some(true)]
+    );
+
+    let disabled = TlsConfig {
+        ca_cert: Some(tls_fixture("server.pem")),
+        session_resumption: Some(false),
+        ..Default::default()
+    };
+    assert_eq!(resumed_handshakes(disabled).await, [Some(false); 3]);
+
+    // The native TLS backend does not tell
+    let native = TlsConfig {
+        insecure: true,
+        ..Default::default()
+    };
+    assert_eq!(resumed_handshakes(native).await, [None; 3]);
+}
+
+#[tokio::test]
+async fn test_client_requires_min_tls_version() {
+    let addr = tls_server_with_versions(None, &[&rustls::version::TLS12]).await;
+    let send = |min_version| async move {
+        let client = DefaultHttpClient::with_options(HttpClientOptions {
+            tls: TlsConfig {
+                ca_cert: Some(tls_fixture("server.pem")),
+                min_version: Some(min_version),
+                session_resumption: Some(true),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        client
+            .send_request(
+                Method::GET,
+                format!("https://localhost:{}/", addr.port()),
+                Duration::from_secs(5),
+                vec![],
+                None,
+            )
+            .await
+    };
+    assert_eq!(
+        send(TlsVersion::Tls1_2)
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap(),
+        "ok"
+    );
+    assert!(send(TlsVersion::Tls1_3).await.is_err());
+}
+
+#[tokio::test]
+async fn test_reloaded_client_resumes_tls_sessions() {
+    let addr = tls_server(Some(&tls_fixture("ca.pem"))).await;
+    let dir = std::env::temp_dir().join(format!("lode-certs-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir(&dir).unwrap();
+    let (cert, key) = (dir.join("client.pem"), dir.join("client.key"));
+    let (cert_pem, key_pem) = (
+        std::fs::read(tls_fixture("client.pem")).unwrap(),
+        std::fs::read(tls_fixture("client.key")).unwrap(),
+    );
+    let issued = SystemTime::now() - Duration::from_secs(3600);
+    rewrite(&cert, &cert_pem, issued);
+    rewrite(&key, &key_pem, issued);
+
+    let client = DefaultHttpClient::with_options(HttpClientOptions {
+        keep_alive: false,
+        tls: TlsConfig {
+            ca_cert: Some(tls_fixture("server.pem")),
+            client_cert: Some(cert.clone()),
+            client_key: Some(key.clone()),
+            reload_client_cert: true,
+            session_resumption: Some(true),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .unwrap();
+    let resumed = || async {
+        let response = client
+            .send_request(
+                Method::GET,
+                format!("https://localhost:{}/", addr.port()),
+                Duration::from_secs(5),
+                vec![],
+                None,
+            )
+            .await
+            .unwrap();
+        connection_setup(&response).unwrap().tls_resumed
+    };
+    assert_eq!(resumed().await, Some(false));
+    assert_eq!(resumed().await, Some(true));
+
+    // The client rebuilt with the renewed certificate resumes the sessions of the previous one
+    rewrite(&cert, &cert_pem, issued + Duration::from_secs(60));
+    tokio::time::sleep(CERT_CHECK_INTERVAL).await;
+    assert_eq!(resumed().await, Some(true));
+    assert_eq!(client.cert_rotations().len(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_client_with_unreadable_tls_files() {
+    let missing = tls_fixture("missing.pem");
+    let error = DefaultHttpClient::with_options(HttpClientOptions {
+        tls: TlsConfig {
+            ca_cert: Some(missing.clone()),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .err()
+    .unwrap();
+    assert!(error.to_string().contains(&missing.display().to_string()));
+
+    for session_resumption in [None, Some(true)] {
+        let result = DefaultHttpClient::with_options(HttpClientOptions {
+            tls: TlsConfig {
+                client_key: Some(tls_fixture("client.key")),
+                session_resumption,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert!(result.is_err());
+    }
+
+    // rustls reads the files too when it takes over for session resumption
+    let error = DefaultHttpClient::with_options(HttpClientOptions {
+        tls: TlsConfig {
+            ca_cert: Some(missing.clone()),
+            session_resumption: Some(false),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .err()
+    .unwrap();
+    assert!(error.to_string().contains(&missing.display().to_string()));
+}
+
+#[tokio::test]
+async fn test_builder_passes_reqwest_options_through() {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/moved"))
+        .and(header("user-agent", "lode-test"))
+        .respond_with(ResponseTemplate::new(302).insert_header("location", "/elsewhere"))
+        .mount(&mock_server)
+        .await;
+    let client = DefaultHttpClient::builder()
+        .options(HttpClientOptions {
+            keep_alive: false,
+            ..Default::default()
+        })
+        .connect_timeout(Duration::from_secs(1))
+        .no_proxy()
+        .redirect(reqwest::redirect::Policy::none())
+        .user_agent("lode-test")
+        .build()
+        .unwrap();
+
+    let response = client
+        .send_request(
+            Method::GET,
+            format!("{}/moved", mock_server.uri()),
+            Duration::from_secs(1),
+            vec![],
+            None,
+        )
+        .await
+        .unwrap();
+
+    // Served only with the user agent, and not followed to the missing location
+    assert_eq!(response.status().as_u16(), 302);
+}
+
+/// Resolves every host name to the loopback address, after a delay
+struct LoopbackResolver;
+
+impl Resolve for LoopbackResolver {
+    fn resolve(&self, _name: Name) -> Resolving {
+        Box::pin(async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let addrs = vec![SocketAddr::from(([127, 0, 0, 1], 0))];
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_custom_dns_resolver_is_timed() {
+    let mock_server = setup_mock_server(200, "/test", None).await;
+    let port = mock_server.address().port();
+    let client = DefaultHttpClient::builder()
+        // Takes precedence over the DNS settings
+        .options(HttpClientOptions {
+            dns: Some(DnsConfig {
+                nameservers: vec!["127.0.0.1:9".parse().unwrap()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .dns_resolver(Arc::new(LoopbackResolver))
+        .build()
+        .unwrap();
+
+    let response = client
+        .send_request(
+            Method::GET,
+            format!("http://lode.test:{}/test", port),
+            Duration::from_secs(1),
+            vec![],
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let dns = connection_setup(&response).unwrap().dns.unwrap();
+    assert!(dns >= Duration::from_millis(20), "{:?}", dns);
+}
+
+#[tokio::test]
+async fn test_client_with_unreachable_nameserver() {
+    let client = DefaultHttpClient::with_options(HttpClientOptions {
+        dns: Some(DnsConfig {
+            nameservers: vec!["127.0.0.1:9".parse().unwrap()],
+            timeout: Some(Duration::from_millis(100)),
+            no_cache: true,
+        }),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let error = client
+        .send_request(
+            Method::GET,
+            "http://lode.invalid/".to_string(),
+            Duration::from_secs(2),
+            vec![],
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert!(error.is_connect(), "{:?}", error);
+}
+
+#[tokio::test]
+async fn test_client_from_prebuilt_reqwest_client() {
+    let mock_server = MockServer::start().await;
+    Mock::given(path("/test"))
+        .and(header("x-api-key", "secret"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("x-api-key", "secret".parse().unwrap());
+    let client = DefaultHttpClient::from_client(
+        reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap(),
+    );
+
+    let response = client
+        .send_request(
+            Method::GET,
+            format!("{}/test", mock_server.uri()),
+            Duration::from_secs(1),
+            vec![],
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn test_default_client_conforms() {
+    check_http_client(&DefaultHttpClient::new().unwrap()).await;
+}
+
+/// Turns unsuccessful statuses into errors, which the engine could not count by status
+struct StatusErrorClient(DefaultHttpClient);
+
+#[async_trait::async_trait]
+impl HttpClient for StatusErrorClient {
+    async fn send_request(
+        &self,
+        method: Method,
+        url: String,
+        timeout: Duration,
+        headers: Vec<(String, String)>,
+        body: Option<String>,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        self.0
+            .send_request(method, url, timeout, headers, body)
+            .await?
+            .error_for_status()
+    }
+
+    async fn send_bytes(
+        &self,
+        method: Method,
+        url: String,
+        timeout: Duration,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        self.0
+            .send_bytes(method, url, timeout, headers, body)
+            .await?
+            .error_for_status()
+    }
+}
+
+#[tokio::test]
+#[should_panic(expected = "an unsuccessful status must be returned as a response")]
+async fn test_nonconforming_client_fails_the_check() {
+    check_http_client(&StatusErrorClient(DefaultHttpClient::new().unwrap())).await;
+}
@@ -1,8 +1,10 @@
 use crate::common::error_simulation::setup_error_mock_server;
 
 use lode_core::error::Result;
-use lode_core::metrics::{RequestMetrics, TestMetrics};
+use lode_core::metrics::{ErrorKind, RequestMetrics, TestMetrics};
+use lode_core::report::Report;
 use reqwest::StatusCode;
+use std::collections::BTreeMap;
 use std::time::Duration;
 
 async fn create_reqwest_error(kind: &str) -> reqwest::Error {
@@ -61,8 +63,8 @@ async fn test_metrics_error_stats() {
     metrics.record_request(RequestMetrics::new().complete(StatusCode::INTERNAL_SERVER_ERROR));
 
     let (error_counts, error_messages) = metrics.error_stats().unwrap();
-    assert_eq!(error_counts.get("HTTP 404").unwrap(), &2u64);
-    assert_eq!(error_counts.get("HTTP 500").unwrap(), &1u64);
+    assert_eq!(error_counts.get(&ErrorKind::Http(404)).unwrap(), &2u64);
+    assert_eq!(error_counts.get(&ErrorKind::Http(500)).unwrap(), &1u64);
     assert_eq!(error_messages.len(), 3);
 }
 
@@ -107,21 +109,21 @@ async fn test_metrics_with_http_errors() -> Result<()> {
 
     let client = reqwest::Client::new();
     let response = client
-        .get(&format!("{}/error", mock_server.uri()))
+        .get(format!("{}/error", mock_server.uri()))
         .header("X-Error-Type", "404")
         .send()
         .await?;
     metrics.record_request(RequestMetrics::new().complete(response.status()));
 
     let response = client
-        .get(&format!("{}/error", mock_server.uri()))
+        .get(format!("{}/error", mock_server.uri()))
         .header("X-Error-Type", "404")
         .send()
         .await?;
     metrics.record_request(RequestMetrics::new().complete(response.status()));
 
     let response = client
-        .get(&format!("{}/error", mock_server.uri()))
+        .get(format!("{}/error", mock_server.uri()))
         .header("X-Error-Type", "500")
         .send()
         .await?;
@@ -130,8 +132,8 @@ async fn test_metrics_with_http_errors() -> Result<()> {
     let (error_counts, error_messages) = metrics.error_stats().expect("Should have error stats");
 
     assert_eq!(metrics.failed_requests(), 3);
-    assert_eq!(error_counts.get("HTTP 404").unwrap(), &2u64);
-    assert_eq!(error_counts.get("HTTP 500").unwrap(), &1u64);
+    assert_eq!(error_counts.get(&ErrorKind::Http(404)).unwrap(), &2u64);
+    assert_eq!(error_counts.get(&ErrorKind::Http(500)).unwrap(), &1u64);
     assert_eq!(error_messages.len(), 3);
     Ok(())
 }
@@ -164,7 +166,7 @@ async fn test_metrics_with_mixed_errors() -> Result<()> {
 
     let client = reqwest::Client::new();
     let response = client
-        .get(&format!("{}/error", mock_server.uri()))
+        .get(format!("{}/error", mock_server.uri()))
         .header("X-Error-Type", "404")
         .send()
         .await?;
@@ -175,7 +177,7 @@ async fn test_metrics_with_mixed_errors() -> Result<()> {
     metrics.record_request(RequestMetrics::new().record_error(timeout_error));
 
     let response = client
-        .get(&format!("{}/error", mock_server.uri()))
+        .get(format!("{}/error", mock_server.uri()))
         .header("X-Error-Type", "404")
         .send()
         .await?;
@@ -183,7 +185,7 @@ async fn test_metrics_with_mixed_errors() -> Result<()> {
 
     // Record a successful request
     let response = client
-        .get(&format!("{}/error", mock_server.uri()))
+        .get(format!("{}/error", mock_server.uri()))
         .header("X-Error-Type", "200")
         .send()
         .await?;
@@ -198,12 +200,58 @@ async fn test_metrics_with_mixed_errors() -> Result<()> {
     assert_eq!(metrics.total_requests(), 5);
     assert_eq!(metrics.successful_requests(), 1);
     assert_eq!(metrics.failed_requests(), 4);
-    assert_eq!(error_counts.get("HTTP 404").unwrap(), &2u64);
+    assert_eq!(error_counts.get(&ErrorKind::Http(404)).unwrap(), &2u64);
     assert!(error_counts.values().sum::<u64>() == 4);
     assert_eq!(error_messages.len(), 4);
     Ok(())
 }
 
+#[tokio::test]
+async fn test_metrics_status_codes() -> Result<()> {
+    let mut metrics = TestMetrics::new()?;
+    for status in [200, 201, 301, 503, 200, 503] {
+        metrics
+            .record_request(RequestMetrics::new().complete(StatusCode::from_u16(status).unwrap()));
+    }
+    let timeout_error = create_reqwest_error("timeout").await;
+    metrics.record_request(RequestMetrics::new().record_error(timeout_error));
+
+    let expected = BTreeMap::from([(200, 2), (201, 1), (301, 1), (503, 2)]);
+    assert_eq!(metrics.status_codes(), &expected);
+
+    metrics.finalize(Duration::from_secs(1)).await?;
+    let report = Report::from_test_metrics(&metrics)?;
+    assert_eq!(report.status_codes, expected);
+    assert!(report
+        .as_string()
+        .contains("Status Codes: 200 (2), 201 (1), 301 (1), 503 (2)"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_metrics_transfer() -> Result<()> {
+    let mut metrics = TestMetrics::new()?;
+    for received in [1500, 500] {
+        metrics.record_request(
+            RequestMetrics::new()
+                .with_bytes(250, received)
+                .complete(StatusCode::OK),
+        );
+    }
+    assert_eq!(metrics.bytes_sent(), 500);
+    assert_eq!(metrics.bytes_received(), 2000);
+
+    metrics.finalize(Duration::from_secs(2)).await?;
+    let transfer = Report::from_test_metrics(&metrics)?.transfer.unwrap();
+    assert_eq!(transfer.mean_response_bytes, 1000.0);
+    assert!((transfer.received_mb_per_second - 0.001).abs() < 1e-9);
+    assert!((transfer.sent_mb_per_second - 0.00025).abs() < 1e-9);
+
+    let metrics = TestMetrics::new()?;
+    assert!(Report::from_test_metrics(&metrics)?.transfer.is_none());
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_request_metrics_lifecycle() {
     let metrics = RequestMetrics::new();
@@ -1,5 +1,5 @@
 use lode_core::{
-    metrics::{RequestMetrics, TestMetrics},
+    metrics::{ErrorKind, Labels, RequestMetrics, TestMetrics},
     report::Report,
 };
 use reqwest::StatusCode;
@@ -50,8 +50,14 @@ async fn test_report_with_errors() {
 
     assert!(report.error_stats.is_some());
     let error_stats = report.error_stats.unwrap();
-    assert_eq!(error_stats.error_counts.get("HTTP 404").unwrap(), &2u64);
-    assert_eq!(error_stats.error_counts.get("HTTP 500").unwrap(), &1u64);
+    assert_eq!(
+        error_stats.error_counts.get(&ErrorKind::Http(404)).unwrap(),
+        &2u64
+    );
+    assert_eq!(
+        error_stats.error_counts.get(&ErrorKind::Http(500)).unwrap(),
+        &1u64
+    );
 }
 
 #[tokio::test]
@@ -76,3 +82,156 @@ async fn test_report_response_times() {
     assert!(report.max_response_time_ms >= 200.0);
     assert!(report.mean_response_time_ms >= 150.0);
 }
+
+#[tokio::test]
+async fn test_report_groups_by_labels() {
+    let mut metrics = TestMetrics::new().unwrap();
+    let labels = |variant: &str| Labels::from([("variant".to_string(), variant.to_string())]);
+
+    for _ in 0..3 {
+        metrics.record_request(
+            RequestMetrics::new()
+                .with_labels(labels("b"))
+                .complete(StatusCode::OK),
+        );
+    }
+    metrics.record_request(
+        RequestMetrics::new()
+            .with_labels(labels("a"))
+            .complete(StatusCode::INTERNAL_SERVER_ERROR),
+    );
+    metrics.record_request(RequestMetrics::new().complete(StatusCode::OK));
+
+    metrics.finalize(Duration::from_secs(1)).await.unwrap();
+    let report = Report::from_metrics(Arc::new(Mutex::new(metrics)))
+        .await
+        .unwrap();
+
+    assert_eq!(report.total_requests, 5);
+    assert_eq!(report.groups.len(), 2);
+    assert_eq!(report.groups[0].label_string(), "variant=a");
+    assert_eq!(report.groups[0].failed_requests, 1);
+    assert_eq!(report.groups[1].label_string(), "variant=b");
+    assert_eq!(report.groups[1].total_requests, 3);
+
+    assert!(report
+        .as_string()
+        .contains("variant=b: 3 requests (0 failed)"));
+    let json: serde_json::Value = serde_json::from_str(&report.as_json().unwrap()).unwrap();
+    assert_eq!(json["groups"][1]["labels"]["variant"], "b");
+}
+
+#[tokio::test]
+async fn test_report_individual_requests() {
+    let mut metrics = TestMetrics::new().unwrap();
+    metrics.record_request(RequestMetrics::new().complete(StatusCode::OK));
+    assert!(metrics.request_log().is_empty());
+
+    metrics.mark_started(None);
+    metrics.record_individual_requests();
+    metrics.record_request(RequestMetrics::new().complete(StatusCode::OK));
+    metrics.record_request(RequestMetrics::new().complete(StatusCode::SERVICE_UNAVAILABLE));
+    metrics.record_request(RequestMetrics::new().fail(ErrorKind::Assertion, "token not found"));
+
+    metrics.finalize(Duration::from_secs(1)).await.unwrap();
+    let report = Report::from_metrics(Arc::new(Mutex::new(metrics)))
+        .await
+        .unwrap();
+
+    assert_eq!(report.total_requests, 4);
+    assert_eq!(report.requests.len(), 3);
+    let started_at = report.started_at.unwrap();
+    assert!(report.requests.iter().all(|r| r.timestamp >= started_at));
+    assert_eq!(report.requests[0].status, Some(200));
+    assert_eq!(report.requests[0].error, None);
+    assert_eq!(report.requests[1].error.as_deref(), Some("HTTP 503"));
+    assert_eq!(report.requests[2].status, None);
+    assert_eq!(report.requests[2].error.as_deref(), Some("token not found"));
+
+    let csv = report.as_csv();
+    assert_eq!(csv.lines().count(), 4);
+    assert!(csv.lines().nth(2).unwrap().ends_with(",503,HTTP 503"));
+    assert!(!report.as_json().unwrap().contains("\"requests\""));
+}
+
+#[tokio::test]
+async fn test_report_response_time_histogram() {
+    let empty = Report::from_metrics(Arc::new(Mutex::new(TestMetrics::new().unwrap())))
+        .await
+        .unwrap();
+    assert_eq!(empty.response_time_histogram, None);
+    assert!(empty.response_times().unwrap().is_none());
+
+    let mut reports = Vec::new();
+    for _ in 0..2 {
+        let mut metrics = TestMetrics::new().unwrap();
+        for _ in 0..3 {
+            let request = RequestMetrics::new();
+            std::thread::sleep(Duration::from_millis(1));
+            metrics.record_request(request.complete(StatusCode::OK));
+        }
+        metrics.finalize(Duration::from_secs(1)).await.unwrap();
+        reports.push(
+            Report::from_metrics(Arc::new(Mutex::new(metrics)))
+                .await
+                .unwrap(),
+        );
+    }
+
+    let json: serde_json::Value = serde_json::from_str(&reports[0].as_json().unwrap()).unwrap();
+    assert!(json["response_time_histogram"].is_string());
+
+    // Distributions of several runs merge by adding their histograms
+    let mut merged = reports[0].response_times().unwrap().unwrap();
+    merged
+        .add(reports[1].response_times().unwrap().unwrap())
+        .unwrap();
+    assert_eq!(merged.len(), 6);
+    assert!(merged.min() >= 1_000);
+}
+
+#[tokio::test]
+async fn test_report_time_series() {
+    let mut metrics = TestMetrics::new().unwrap();
+    metrics.mark_started(None);
+    metrics.record_time_series(Duration::from_millis(200));
+    metrics.record_request(RequestMetrics::new().complete(StatusCode::OK));
+    metrics.record_request(RequestMetrics::new().complete(StatusCode::OK));
+    // Nothing completes in the second interval
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    metrics.record_request(RequestMetrics::new().complete(StatusCode::BAD_GATEWAY));
+
+    metrics.finalize(Duration::from_millis(500)).await.unwrap();
+    let report = Report::from_metrics(Arc::new(Mutex::new(metrics)))
+        .await
+        .unwrap();
+
+    let series = report.time_series.as_ref().unwrap();
+    assert_eq!(series.interval_seconds, 0.2);
+    let counts: Vec<(u64, u64)> = series
+        .buckets
+        .iter()
+        .map(|b| (b.total_requests, b.failed_requests))
+        .collect();
+    assert_eq!(counts, vec![(2, 0), (0, 0), (1, 1)]);
+    assert_eq!(series.buckets[1].start_seconds, 0.2);
+    assert_eq!(series.buckets[0].requests_per_second, 10.0);
+    // The last interval ends with the run, 100ms in
+    assert!((series.buckets[2].requests_per_second - 10.0).abs() < 1e-9);
+    assert_eq!(series.buckets[1].p95_response_time_ms, 0.0);
+
+    let json: serde_json::Value = serde_json::from_str(&report.as_json().unwrap()).unwrap();
+    assert_eq!(json["time_series"]["buckets"][2]["failed_requests"], 1);
+}
+
+#[tokio::test]
+async fn test_report_without_time_series() {
+    let mut metrics = TestMetrics::new().unwrap();
+    metrics.mark_started(None);
+    metrics.record_request(RequestMetrics::new().complete(StatusCode::OK));
+    let report = Report::from_metrics(Arc::new(Mutex::new(metrics)))
+        .await
+        .unwrap();
+    assert_eq!(report.time_series, None);
+    assert!(!report.as_json().unwrap().contains("time_series"));
+}
@@ -0,0 +1,79 @@
+use lode_core::metrics::ErrorKind;
+use lode_core::report::schema::{report_schema, SCHEMA_VERSION};
+use lode_core::report::Report;
+use serde_json::Value;
+use std::path::PathBuf;
+
+fn published_schema_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("schemas")
+        .join(format!("report.v{}.json", SCHEMA_VERSION))
+}
+
+/// The published schema must match the serde model; set `LODE_UPDATE_SCHEMA=1` to regenerate it
+/// after a compatible change, and bump `SCHEMA_VERSION` first for an incompatible one
+#[test]
+fn test_published_schema_matches_model() {
+    let generated = serde_json::to_string_pretty(&report_schema()).unwrap() + "\n";
+    let path = published_schema_path();
+
+    if std::env::var_os("LODE_UPDATE_SCHEMA").is_some() {
+        std::fs::write(&path, &generated).unwrap();
+    }
+
+    let published = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Failed to read {}: {}", path.display(), e));
+    assert!(
+        published == generated,
+        "The report model no longer matches {}",
+        path.display()
+    );
+}
+
+#[test]
+fn test_v1_report_still_parses() {
+    let report: Report = serde_json::from_str(include_str!("../fixtures/report_v1.json")).unwrap();
+    assert_eq!(report.schema_version, 1);
+    assert_eq!(report.total_requests, 1000);
+    assert_eq!(report.groups.len(), 1);
+    assert_eq!(
+        report.error_stats.unwrap().error_counts[&ErrorKind::Timeout],
+        2
+    );
+}
+
+#[test]
+fn test_unversioned_report_still_parses() {
+    let report: Report = serde_json::from_str(include_str!("../fixtures/report_v0.json")).unwrap();
+    assert_eq!(report.schema_version, 0);
+    assert_eq!(report.p999_response_time_ms, 0.0);
+    assert!(report.tail_latencies_ms.is_empty());
+}
+
+#[test]
+fn test_report_fields_are_in_schema() {
+    let schema = report_schema();
+    let properties = schema["properties"].as_object().unwrap();
+
+    let report = Report {
+        schema_version: SCHEMA_VERSION,
+        ..Default::default()
+    };
+    let Value::Object(json) = serde_json::to_value(&report).unwrap() else {
+        panic!("report is not an object");
+    };
+    for field in json.keys() {
+        assert!(
+            properties.contains_key(field),
+            "{} is not in the schema",
+            field
+        );
+    }
+    for field in schema["required"].as_array().unwrap() {
+        assert!(
+            json.contains_key(field.as_str().unwrap()),
+            "{} is missing",
+            field
+        );
+    }
+}